@@ -1,12 +1,20 @@
 use serde::{Deserialize, Serialize};
 
+use crate::comment::Comment;
+use crate::date::DateSystem;
+use crate::date_text::{format_date_time, is_date_time_format};
 use crate::error::CellError;
-use crate::format::CellFormat;
+use crate::format::Borders;
+use crate::hyperlink::Hyperlink;
+use crate::numeric::round_significant;
+use crate::rich_text::RichText;
+use crate::style::{FormatDiff, NamedStyle};
 
 /// Represents the raw value stored in a cell
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", content = "value")]
 pub enum CellValue {
+    #[default]
     Empty,
     Number(f64),
     Text(String),
@@ -14,12 +22,6 @@ pub enum CellValue {
     Error(CellError),
 }
 
-impl Default for CellValue {
-    fn default() -> Self {
-        CellValue::Empty
-    }
-}
-
 impl CellValue {
     /// Check if the value is empty
     pub fn is_empty(&self) -> bool {
@@ -41,8 +43,10 @@ impl CellValue {
         match self {
             CellValue::Empty => String::new(),
             CellValue::Number(n) => {
+                // Hide binary floating point noise beyond 15 significant digits
+                let n = round_significant(*n);
                 if n.fract() == 0.0 && n.abs() < 1e15 {
-                    format!("{}", *n as i64)
+                    format!("{}", n as i64)
                 } else {
                     format!("{}", n)
                 }
@@ -151,20 +155,23 @@ impl CellContent {
 }
 
 /// Complete cell data structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Cell {
     pub content: CellContent,
+    /// Named style and the cell's own changes to it; see
+    /// [`Workbook::cell_format`](crate::Workbook::cell_format) for the format
+    /// it resolves to
     #[serde(default)]
-    pub format: CellFormat,
-}
-
-impl Default for Cell {
-    fn default() -> Self {
-        Cell {
-            content: CellContent::default(),
-            format: CellFormat::default(),
-        }
-    }
+    pub format: FormatDiff,
+    /// Formatting runs of a text value with mixed formatting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rich_text: Option<RichText>,
+    /// Comment thread attached to the cell
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<Comment>,
+    /// Link the cell opens when followed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hyperlink: Option<Hyperlink>,
 }
 
 impl Cell {
@@ -172,7 +179,10 @@ impl Cell {
     pub fn new(content: CellContent) -> Self {
         Cell {
             content,
-            format: CellFormat::default(),
+            format: FormatDiff::default(),
+            rich_text: None,
+            comment: None,
+            hyperlink: None,
         }
     }
 
@@ -192,6 +202,13 @@ impl Cell {
         })
     }
 
+    /// Create a cell with rich text
+    pub fn rich(text: RichText) -> Self {
+        let mut cell = Cell::default();
+        cell.set_rich_text(text);
+        cell
+    }
+
     /// Create a cell with a boolean value
     pub fn boolean(value: bool) -> Self {
         Cell::new(CellContent::Value {
@@ -210,10 +227,85 @@ impl Cell {
         self.content.computed_value()
     }
 
-    /// Check if the cell is empty (empty value and default format)
+    /// Get the formatting runs of the cell's text
+    ///
+    /// Returns `None` if the cell has no runs, or its value has since been
+    /// changed to something other than the runs' text.
+    pub fn text_runs(&self) -> Option<&RichText> {
+        let rich_text = self.rich_text.as_ref()?;
+        match &self.content {
+            CellContent::Value {
+                value: CellValue::Text(text),
+                ..
+            } if *text == rich_text.plain_text() => Some(rich_text),
+            _ => None,
+        }
+    }
+
+    /// Set the cell to a text value with formatting runs
+    ///
+    /// Text whose runs add no formatting is stored as plain text.
+    pub fn set_rich_text(&mut self, text: RichText) {
+        let plain = text.plain_text();
+        self.content = if plain.is_empty() {
+            CellContent::default()
+        } else {
+            CellContent::Value {
+                value: CellValue::Text(plain),
+                original_input: None,
+            }
+        };
+        self.rich_text = Some(text).filter(|text| !text.is_plain());
+    }
+
+    /// Get the value as shown in the cell
+    ///
+    /// Numbers with a date or time number format, their own or their named
+    /// style's in `styles`, are shown through it; everything else is shown
+    /// as [`CellContent::display_value`].
+    pub fn display_value(&self, styles: &[NamedStyle], system: DateSystem) -> String {
+        if let (CellValue::Number(n), Some(code)) =
+            (self.computed_value(), self.format.number_format(styles))
+        {
+            if let Some(text) = format_date_time(*n, code, system) {
+                return text;
+            }
+        }
+        self.content.display_value()
+    }
+
+    /// Give the cell the number format of typed date or time input
+    ///
+    /// An existing date or time format, including one from the cell's named
+    /// style in `styles`, is kept, so retyping a date in a `d-mmm` cell
+    /// doesn't change how it shows.
+    pub fn assign_input_format(&mut self, number_format: Option<String>, styles: &[NamedStyle]) {
+        let Some(number_format) = number_format else {
+            return;
+        };
+        let has_date_format = self
+            .format
+            .number_format(styles)
+            .is_some_and(is_date_time_format);
+        if !has_date_format {
+            self.format.number_format = Some(Some(number_format));
+        }
+    }
+
+    /// Get the cell's own borders
+    ///
+    /// Named styles don't set borders, so these are the borders the cell has.
+    pub fn borders(&self) -> Borders {
+        self.format.borders.unwrap_or_default()
+    }
+
+    /// Check if the cell is empty (empty value, no style or format changes,
+    /// no comment or link)
     pub fn is_empty(&self) -> bool {
         matches!(self.content, CellContent::Value { value: CellValue::Empty, .. })
-            && self.format == CellFormat::default()
+            && self.format == FormatDiff::default()
+            && self.comment.is_none()
+            && self.hyperlink.is_none()
     }
 }
 
@@ -233,10 +325,36 @@ mod tests {
     fn test_cell_value_as_text() {
         assert_eq!(CellValue::Number(42.0).as_text(), "42");
         assert_eq!(CellValue::Number(42.5).as_text(), "42.5");
+        assert_eq!(CellValue::Number(0.1 + 0.2).as_text(), "0.3");
         assert_eq!(CellValue::Boolean(true).as_text(), "TRUE");
         assert_eq!(CellValue::Text("hello".to_string()).as_text(), "hello");
     }
 
+    #[test]
+    fn test_rich_text_cell() {
+        use crate::rich_text::RunFormat;
+
+        let text = RichText::new()
+            .with_run("Total:", RunFormat::new().with_bold(true))
+            .with_run(" 42", RunFormat::new());
+        let mut cell = Cell::rich(text.clone());
+        assert_eq!(cell.computed_value().as_text(), "Total: 42");
+        assert_eq!(cell.text_runs(), Some(&text));
+
+        let json = serde_json::to_string(&cell).unwrap();
+        let restored: Cell = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.text_runs(), Some(&text));
+
+        // Runs no longer apply once the value changes
+        cell.content = Cell::number(42.0).content;
+        assert_eq!(cell.text_runs(), None);
+
+        // Runs without formatting are dropped
+        cell.set_rich_text(RichText::new().with_run("plain", RunFormat::new()));
+        assert_eq!(cell.rich_text, None);
+        assert_eq!(cell.computed_value().as_text(), "plain");
+    }
+
     #[test]
     fn test_cell_creation() {
         let cell = Cell::number(42.0);
@@ -134,7 +134,7 @@ impl<T> Chunk<T> {
             self.count += 1;
         }
 
-        std::mem::replace(&mut self.cells[idx], Some(value))
+        self.cells[idx].replace(value)
     }
 
     /// Remove a cell at the given local coordinates.
@@ -209,6 +209,9 @@ impl<T> Default for Chunk<T> {
 /// storage for chunks that contain data. Provides O(1) random access while
 /// minimizing memory usage for sparse data. Uses Morton encoding within chunks
 /// for cache-friendly access patterns.
+///
+/// Reads have no interior mutability, so a grid of `Sync` values can be read
+/// from several threads at once.
 #[derive(Clone, Debug)]
 pub struct ChunkedGrid<T> {
     /// Map of chunk coordinates to chunks.
@@ -259,7 +262,7 @@ impl<T: Clone> ChunkedGrid<T> {
     pub fn insert(&mut self, row: usize, col: usize, value: T) -> Option<T> {
         let chunk_coord = ChunkCoord::from_cell(row, col);
         let (local_row, local_col) = to_local_coords(row, col);
-        let chunk = self.chunks.entry(chunk_coord).or_insert_with(Chunk::new);
+        let chunk = self.chunks.entry(chunk_coord).or_default();
         chunk.insert(local_row, local_col, value)
     }
 
@@ -329,7 +332,7 @@ impl<T: Clone> ChunkedGrid<T> {
     /// * `col` - Global column coordinate
     pub fn get_or_create_chunk(&mut self, row: usize, col: usize) -> &mut Chunk<T> {
         let chunk_coord = ChunkCoord::from_cell(row, col);
-        self.chunks.entry(chunk_coord).or_insert_with(Chunk::new)
+        self.chunks.entry(chunk_coord).or_default()
     }
 
     /// Iterate over all cells in the grid.
@@ -705,6 +708,14 @@ mod tests {
         assert_eq!(items[2], ((15, 15), &3));
     }
 
+    #[test]
+    fn test_chunked_grid_is_thread_safe() {
+        // Recalculation reads cells from several threads at once
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ChunkedGrid<crate::Cell>>();
+        assert_send_sync::<crate::Workbook>();
+    }
+
     #[test]
     fn test_chunked_grid_basic_operations() {
         let mut grid = ChunkedGrid::new();
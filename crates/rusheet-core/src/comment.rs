@@ -0,0 +1,160 @@
+//! Cell comments with threaded replies
+//!
+//! Comments live on the cell they annotate, so they move with it through
+//! row/column inserts and deletes, sorting and cut/paste.
+
+use serde::{Deserialize, Serialize};
+
+/// A reply in a comment thread
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommentReply {
+    pub id: String,
+    pub author: String,
+    /// Unix time in milliseconds
+    pub timestamp: i64,
+    pub body: String,
+    /// When the body was last edited (Unix milliseconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edited: Option<i64>,
+}
+
+impl CommentReply {
+    pub fn new(
+        id: impl Into<String>,
+        author: impl Into<String>,
+        timestamp: i64,
+        body: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            author: author.into(),
+            timestamp,
+            body: body.into(),
+            edited: None,
+        }
+    }
+}
+
+/// A comment thread attached to a cell
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comment {
+    pub id: String,
+    pub author: String,
+    /// Unix time in milliseconds
+    pub timestamp: i64,
+    pub body: String,
+    /// When the body was last edited (Unix milliseconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edited: Option<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replies: Vec<CommentReply>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub resolved: bool,
+}
+
+impl Comment {
+    pub fn new(
+        id: impl Into<String>,
+        author: impl Into<String>,
+        timestamp: i64,
+        body: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            author: author.into(),
+            timestamp,
+            body: body.into(),
+            edited: None,
+            replies: Vec::new(),
+            resolved: false,
+        }
+    }
+
+    pub fn with_reply(mut self, reply: CommentReply) -> Self {
+        self.replies.push(reply);
+        self
+    }
+
+    /// Find a reply by ID
+    pub fn reply(&self, id: &str) -> Option<&CommentReply> {
+        self.replies.iter().find(|r| r.id == id)
+    }
+
+    /// Find a reply by ID for editing
+    pub fn reply_mut(&mut self, id: &str) -> Option<&mut CommentReply> {
+        self.replies.iter_mut().find(|r| r.id == id)
+    }
+
+    /// Users mentioned anywhere in the thread, in order of first mention
+    pub fn mentions(&self) -> Vec<String> {
+        let mut mentions = extract_mentions(&self.body);
+        for reply in &self.replies {
+            for name in extract_mentions(&reply.body) {
+                if !mentions.contains(&name) {
+                    mentions.push(name);
+                }
+            }
+        }
+        mentions
+    }
+}
+
+/// Find `@user` mentions in text, in order and without duplicates
+///
+/// A mention starts at an `@` that doesn't follow a word character (so
+/// email addresses are skipped) and runs over letters, digits, `_`, `.`
+/// and `-`, minus any trailing `.` or `-`.
+///
+/// ```
+/// use rusheet_core::extract_mentions;
+///
+/// let mentions = extract_mentions("@ana can you check this? cc @bo.li, not a@b.com");
+/// assert_eq!(mentions, vec!["ana", "bo.li"]);
+/// ```
+pub fn extract_mentions(text: &str) -> Vec<String> {
+    let is_name_char = |c: char| c.is_alphanumeric() || matches!(c, '_' | '.' | '-');
+    let mut mentions: Vec<String> = Vec::new();
+    let mut prev: Option<char> = None;
+
+    for (i, c) in text.char_indices() {
+        let at_word_start = prev.is_none_or(|p| !(p.is_alphanumeric() || p == '_'));
+        prev = Some(c);
+        if c != '@' || !at_word_start {
+            continue;
+        }
+
+        let rest = &text[i + 1..];
+        let end = rest.find(|c: char| !is_name_char(c)).unwrap_or(rest.len());
+        let name = rest[..end].trim_end_matches(['.', '-']);
+        if !name.is_empty() && !mentions.iter().any(|m| m == name) {
+            mentions.push(name.to_string());
+        }
+    }
+    mentions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_mentions() {
+        assert_eq!(extract_mentions("@ana please review"), vec!["ana"]);
+        assert_eq!(extract_mentions("thanks @ana. @ana-"), vec!["ana"]);
+        assert_eq!(extract_mentions("(@j_doe) and @Zoë"), vec!["j_doe", "Zoë"]);
+        assert!(extract_mentions("mail me at x@example.com or @ alone").is_empty());
+    }
+
+    #[test]
+    fn test_thread_mentions_and_serde() {
+        let comment = Comment::new("c1", "ana", 1_700_000_000_000, "@bo is this right?")
+            .with_reply(CommentReply::new("r1", "bo", 1_700_000_100_000, "Yes, cc @cy and @bo"));
+        assert_eq!(comment.mentions(), vec!["bo", "cy"]);
+        assert_eq!(comment.reply("r1").unwrap().author, "bo");
+
+        let json = serde_json::to_string(&comment).unwrap();
+        assert!(!json.contains("resolved"));
+        let restored: Comment = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, comment);
+    }
+}
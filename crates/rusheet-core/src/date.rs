@@ -0,0 +1,322 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::CellError;
+
+// Excel date constants
+const EXCEL_EPOCH_YEAR: i32 = 1900;
+const EXCEL_LEAP_YEAR_BUG_DAY: i64 = 60; // Feb 29, 1900 (phantom day)
+const DATE_1904_OFFSET: i64 = 1462; // 1904-01-01 in the 1900 system
+const MAX_SERIAL_1900: f64 = 2958466.0; // 10000-01-01 in the 1900 system
+
+/// Which epoch date serial numbers count from
+///
+/// Workbooks created by Excel for Windows use the 1900 system; older Mac
+/// Excel workbooks use the 1904 system, where serial 0 is 1904-01-01.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DateSystem {
+    /// Serial 1 is 1900-01-01, including Excel's phantom Feb 29, 1900
+    #[default]
+    #[serde(rename = "1900")]
+    Excel1900,
+    /// Serial 0 is 1904-01-01
+    #[serde(rename = "1904")]
+    Excel1904,
+}
+
+impl DateSystem {
+    /// Days between this system's serial numbers and the 1900 system's
+    fn offset(self) -> i64 {
+        match self {
+            DateSystem::Excel1900 => 0,
+            DateSystem::Excel1904 => DATE_1904_OFFSET,
+        }
+    }
+
+    /// Serial number of the Unix epoch (1970-01-01) in this system
+    pub fn unix_epoch_serial(self) -> f64 {
+        25569.0 - self.offset() as f64
+    }
+}
+
+/// Convert a date serial from one date system to another
+pub fn convert_serial(serial: f64, from: DateSystem, to: DateSystem) -> f64 {
+    serial + (from.offset() - to.offset()) as f64
+}
+
+/// Convert a date to a serial number in the given date system
+///
+/// In the 1900 system Excel treats 1900 as a leap year, so serials from
+/// March 1, 1900 onwards are one higher than the true day count.
+pub fn date_to_serial(
+    year: i32,
+    month: u32,
+    day: u32,
+    system: DateSystem,
+) -> Result<f64, CellError> {
+    // Validate inputs
+    let min_year = match system {
+        DateSystem::Excel1900 => EXCEL_EPOCH_YEAR,
+        DateSystem::Excel1904 => 1904,
+    };
+    if !(min_year..=9999).contains(&year) {
+        return Err(CellError::NumError);
+    }
+    if !(1..=12).contains(&month) {
+        return Err(CellError::NumError);
+    }
+    if day < 1 || day > days_in_month(year, month) {
+        return Err(CellError::NumError);
+    }
+
+    // Calculate days from epoch (1900/1/1 = serial 1)
+    let mut days = 0i64;
+
+    // Add days for complete years
+    for y in EXCEL_EPOCH_YEAR..year {
+        days += if is_leap_year(y) { 366 } else { 365 };
+    }
+
+    // Add days for complete months in the current year
+    for m in 1..month {
+        days += days_in_month(year, m) as i64;
+    }
+
+    // Add the day
+    days += day as i64;
+
+    // Excel's leap year bug: Excel treats 1900 as a leap year (it's not)
+    // For dates on or after March 1, 1900, we add 1 to match Excel's serial numbers
+    if year > 1900 || (year == 1900 && month > 2) {
+        days += 1;
+    }
+
+    Ok((days - system.offset()) as f64)
+}
+
+/// Convert a serial number in the given date system to (year, month, day)
+pub fn serial_to_date(serial: f64, system: DateSystem) -> Result<(i32, u32, u32), CellError> {
+    let serial = convert_serial(serial, system, DateSystem::Excel1900);
+    let min_serial = match system {
+        DateSystem::Excel1900 => 1.0,
+        DateSystem::Excel1904 => DATE_1904_OFFSET as f64,
+    };
+    if !(min_serial..MAX_SERIAL_1900).contains(&serial) {
+        // Valid range: epoch to 9999-12-31
+        return Err(CellError::NumError);
+    }
+
+    let mut days = serial.floor() as i64;
+
+    // Account for Excel's leap year bug (serial 60 is the phantom Feb 29, 1900)
+    // For serials > 60, we need to subtract 1 to get the real date
+    if days > EXCEL_LEAP_YEAR_BUG_DAY {
+        days -= 1;
+    } else if days == EXCEL_LEAP_YEAR_BUG_DAY {
+        // This is the phantom date Feb 29, 1900
+        return Ok((1900, 2, 29));
+    }
+
+    // Find the year
+    let mut year = EXCEL_EPOCH_YEAR;
+    let mut remaining_days = days;
+
+    while remaining_days > 0 {
+        let days_in_year = if is_leap_year(year) { 366 } else { 365 };
+        if remaining_days <= days_in_year {
+            break;
+        }
+        remaining_days -= days_in_year;
+        year += 1;
+    }
+
+    // Find the month
+    let mut month = 1u32;
+    while month <= 12 {
+        let dim = days_in_month(year, month) as i64;
+        if remaining_days <= dim {
+            break;
+        }
+        remaining_days -= dim;
+        month += 1;
+    }
+
+    let day = remaining_days as u32;
+
+    if day < 1 || day > days_in_month(year, month) {
+        return Err(CellError::NumError);
+    }
+
+    Ok((year, month, day))
+}
+
+/// Check if a year is a leap year
+pub fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || (year % 400 == 0)
+}
+
+/// Get the number of days in a month
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 => {
+            if is_leap_year(year) {
+                29
+            } else {
+                28
+            }
+        }
+        _ => 0,
+    }
+}
+
+/// Check whether a number format code displays a calendar date
+///
+/// Looks for day or year tokens outside quoted literals, bracketed
+/// sections and escaped characters. Time-only formats such as `h:mm`
+/// are not date formats.
+pub fn is_date_format(code: &str) -> bool {
+    let mut chars = code.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                for q in chars.by_ref() {
+                    if q == '"' {
+                        break;
+                    }
+                }
+            }
+            '[' => {
+                for q in chars.by_ref() {
+                    if q == ']' {
+                        break;
+                    }
+                }
+            }
+            '\\' => {
+                chars.next();
+            }
+            'd' | 'D' | 'y' | 'Y' => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_to_serial() {
+        // Test January 1, 1900 (day 1)
+        assert_eq!(date_to_serial(1900, 1, 1, DateSystem::Excel1900).unwrap(), 1.0);
+
+        // Test January 2, 1900 (day 2)
+        assert_eq!(date_to_serial(1900, 1, 2, DateSystem::Excel1900).unwrap(), 2.0);
+
+        // Test Feb 28, 1900 (day 59, before leap year bug)
+        assert_eq!(date_to_serial(1900, 2, 28, DateSystem::Excel1900).unwrap(), 59.0);
+
+        // Test March 1, 1900 (day 61, after leap year bug adjustment)
+        assert_eq!(date_to_serial(1900, 3, 1, DateSystem::Excel1900).unwrap(), 61.0);
+
+        // Test a known date: January 1, 2000
+        assert_eq!(date_to_serial(2000, 1, 1, DateSystem::Excel1900).unwrap(), 36526.0);
+
+        // Test June 15, 2024
+        // Calculated: 45290 days (1900-2023) + 167 days (Jan-Jun 15 in 2024) + 1 (Excel bug) = 45458
+        assert_eq!(date_to_serial(2024, 6, 15, DateSystem::Excel1900).unwrap(), 45458.0);
+    }
+
+    #[test]
+    fn test_serial_to_date() {
+        // Test day 1
+        assert_eq!(serial_to_date(1.0, DateSystem::Excel1900).unwrap(), (1900, 1, 1));
+
+        // Test day 2
+        assert_eq!(serial_to_date(2.0, DateSystem::Excel1900).unwrap(), (1900, 1, 2));
+
+        // Test day 59 (Feb 28, 1900)
+        assert_eq!(serial_to_date(59.0, DateSystem::Excel1900).unwrap(), (1900, 2, 28));
+
+        // Test day 60 (Feb 29, 1900 - phantom date)
+        assert_eq!(serial_to_date(60.0, DateSystem::Excel1900).unwrap(), (1900, 2, 29));
+
+        // Test day 61 (March 1, 1900, after leap year bug)
+        assert_eq!(serial_to_date(61.0, DateSystem::Excel1900).unwrap(), (1900, 3, 1));
+
+        // Test January 1, 2000
+        assert_eq!(serial_to_date(36526.0, DateSystem::Excel1900).unwrap(), (2000, 1, 1));
+
+        // Test June 15, 2024 (serial 45458)
+        assert_eq!(serial_to_date(45458.0, DateSystem::Excel1900).unwrap(), (2024, 6, 15));
+    }
+
+    #[test]
+    fn test_1904_system() {
+        assert_eq!(date_to_serial(1904, 1, 1, DateSystem::Excel1904).unwrap(), 0.0);
+        assert_eq!(date_to_serial(2024, 1, 1, DateSystem::Excel1904).unwrap(), 43830.0);
+        assert!(date_to_serial(1903, 12, 31, DateSystem::Excel1904).is_err());
+
+        assert_eq!(serial_to_date(0.0, DateSystem::Excel1904).unwrap(), (1904, 1, 1));
+        assert_eq!(serial_to_date(43830.0, DateSystem::Excel1904).unwrap(), (2024, 1, 1));
+        assert_eq!(serial_to_date(2957003.0, DateSystem::Excel1904).unwrap(), (9999, 12, 31));
+        assert!(serial_to_date(-1.0, DateSystem::Excel1904).is_err());
+        assert!(serial_to_date(2957004.0, DateSystem::Excel1904).is_err());
+    }
+
+    #[test]
+    fn test_convert_serial() {
+        let s1900 = date_to_serial(2024, 6, 15, DateSystem::Excel1900).unwrap();
+        let s1904 = date_to_serial(2024, 6, 15, DateSystem::Excel1904).unwrap();
+        assert_eq!(convert_serial(s1900, DateSystem::Excel1900, DateSystem::Excel1904), s1904);
+        assert_eq!(convert_serial(s1904, DateSystem::Excel1904, DateSystem::Excel1900), s1900);
+        assert_eq!(convert_serial(s1900, DateSystem::Excel1900, DateSystem::Excel1900), s1900);
+
+        assert_eq!(DateSystem::Excel1900.unix_epoch_serial(), 25569.0);
+        assert_eq!(DateSystem::Excel1904.unix_epoch_serial(), 24107.0);
+    }
+
+    #[test]
+    fn test_leap_year() {
+        // 2000 is a leap year (divisible by 400)
+        assert!(is_leap_year(2000));
+
+        // 2024 is a leap year (divisible by 4, not by 100)
+        assert!(is_leap_year(2024));
+
+        // 1900 is NOT a leap year (divisible by 100 but not 400)
+        assert!(!is_leap_year(1900));
+
+        // 2023 is not a leap year
+        assert!(!is_leap_year(2023));
+    }
+
+    #[test]
+    fn test_days_in_month() {
+        // January has 31 days
+        assert_eq!(days_in_month(2024, 1), 31);
+
+        // February in leap year has 29 days
+        assert_eq!(days_in_month(2024, 2), 29);
+
+        // February in non-leap year has 28 days
+        assert_eq!(days_in_month(2023, 2), 28);
+
+        // April has 30 days
+        assert_eq!(days_in_month(2024, 4), 30);
+    }
+
+    #[test]
+    fn test_is_date_format() {
+        assert!(is_date_format("yyyy-mm-dd"));
+        assert!(is_date_format("m/d/yy h:mm"));
+        assert!(is_date_format("[$-404]e/m/d"));
+        assert!(!is_date_format("h:mm:ss"));
+        assert!(!is_date_format("0.00"));
+        assert!(!is_date_format("General"));
+        assert!(!is_date_format("0 \"days\""));
+        assert!(!is_date_format("[Red]0.00"));
+    }
+}
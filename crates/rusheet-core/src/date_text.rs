@@ -0,0 +1,643 @@
+//! Reading dates and times from text and writing them back
+//!
+//! Typed input such as `2026-10-16`, `9:30 AM` or `36:15` is read into a
+//! serial number together with a number format that displays it the way it
+//! was typed. [`format_date_time`] renders such serials through a number
+//! format for display.
+
+use serde::{Deserialize, Serialize};
+
+use crate::date::{convert_serial, date_to_serial, serial_to_date, DateSystem};
+
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const DAY_NAMES: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+/// Order of day, month and year in all-numeric dates such as `3/4/2024`
+///
+/// Dates that start with a four-digit year or spell out the month are read
+/// the same way in every order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DateOrder {
+    /// `3/4/2024` is March 4, as in the United States
+    #[default]
+    #[serde(rename = "mdy")]
+    MonthDayYear,
+    /// `3/4/2024` is April 3, as in most of Europe
+    #[serde(rename = "dmy")]
+    DayMonthYear,
+    /// `24/3/4` is March 4, as in East Asia
+    #[serde(rename = "ymd")]
+    YearMonthDay,
+}
+
+/// A date, time or duration read from typed input
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateTimeInput {
+    /// Serial number in the workbook's date system
+    pub serial: f64,
+    /// Number format that shows the serial the way it was typed
+    pub number_format: String,
+}
+
+/// Read typed input as a date, time of day, date and time, or duration
+///
+/// Durations are times past 23 hours such as `36:15`, formatted with
+/// elapsed hours (`[h]:mm`). Returns `None` if the text is none of these.
+///
+/// # Examples
+///
+/// ```
+/// use rusheet_core::{parse_date_time_input, DateOrder, DateSystem};
+///
+/// let input = parse_date_time_input("2026-10-16", DateOrder::default(), DateSystem::Excel1900);
+/// assert_eq!(input.unwrap().number_format, "yyyy-mm-dd");
+///
+/// let input = parse_date_time_input("9:30 AM", DateOrder::default(), DateSystem::Excel1900);
+/// assert_eq!(input.unwrap().serial, 0.3958333333333333);
+/// ```
+pub fn parse_date_time_input(
+    text: &str,
+    order: DateOrder,
+    system: DateSystem,
+) -> Option<DateTimeInput> {
+    let (date_part, time_part) = split_date_time(text);
+    let time = match time_part {
+        Some(t) => Some(parse_clock(t)?),
+        None => None,
+    };
+    let date = match date_part {
+        Some(d) => {
+            let ((year, month, day), format) = parse_date_parts(d, order)?;
+            Some((date_to_serial(year, month, day, system).ok()?, format))
+        }
+        None => None,
+    };
+
+    let (serial, number_format) = match (date, time) {
+        (Some((serial, format)), None) => (serial, format.to_string()),
+        (None, Some(time)) if time.is_duration() => (time.fraction(), time.duration_format()),
+        (None, Some(time)) => (time.fraction(), time.format()),
+        (Some((serial, format)), Some(time)) if !time.is_duration() => {
+            (serial + time.fraction(), format!("{} {}", format, time.format()))
+        }
+        _ => return None,
+    };
+    Some(DateTimeInput {
+        serial,
+        number_format,
+    })
+}
+
+/// Parse the date portion of a text value into (year, month, day)
+///
+/// Supports ISO (2024-06-15, 2024/06/15), all-numeric dates in the given
+/// order (6/15/2024, or 15.6.2024 in day-month-year order), and month-name
+/// forms (15-Jun-2024, 15 June 2024, June 15, 2024). Days and months are
+/// not range-checked.
+pub fn parse_date_text(text: &str, order: DateOrder) -> Option<(i32, u32, u32)> {
+    parse_date_parts(text, order).map(|(date, _)| date)
+}
+
+/// Parse a time of day (e.g. 18:30, 18:30:45, 6:30 PM) into a fraction of a day
+pub fn parse_time_text(text: &str) -> Option<f64> {
+    parse_clock(text)
+        .filter(|time| !time.is_duration())
+        .map(|time| time.fraction())
+}
+
+/// Split text into an optional date part and an optional time part
+pub fn split_date_time(text: &str) -> (Option<&str>, Option<&str>) {
+    let text = text.trim();
+    match text.find(':') {
+        None => (Some(text), None),
+        Some(colon) => {
+            // The time starts at the last space before the first colon
+            match text[..colon].rfind(' ') {
+                Some(space) => (Some(text[..space].trim()), Some(&text[space + 1..])),
+                None => (None, Some(text)),
+            }
+        }
+    }
+}
+
+/// Parse a month name or an abbreviation of at least three letters
+fn parse_month_name(s: &str) -> Option<u32> {
+    let lower = s.to_lowercase();
+    if lower.len() < 3 {
+        return None;
+    }
+    MONTH_NAMES
+        .iter()
+        .position(|name| name.to_lowercase().starts_with(&lower))
+        .map(|i| i as u32 + 1)
+}
+
+/// Parse a date along with the number format matching how it was written
+///
+/// The parts are split by one separator throughout: `-` or `/`, or `.` in
+/// day-month-year order. Dates naming the month may also use spaces and
+/// commas, as in `October 16, 2026`.
+fn parse_date_parts(text: &str, order: DateOrder) -> Option<((i32, u32, u32), &'static str)> {
+    let named = text
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .any(|word| parse_month_name(word).is_some());
+    let mut separators = text.chars().filter(|c| !c.is_ascii_alphanumeric());
+    let first = separators.next()?;
+    let consistent = match first {
+        ' ' | ',' if named => separators.all(|c| c == ' ' || c == ','),
+        '-' | '/' => separators.all(|c| c == first),
+        '.' if named || order == DateOrder::DayMonthYear => separators.all(|c| c == first),
+        _ => false,
+    };
+    if !consistent {
+        return None;
+    }
+
+    let parts: Vec<&str> = text
+        .split(['-', '/', '.', ' ', ','])
+        .filter(|p| !p.is_empty())
+        .collect();
+    if parts.len() != 3 {
+        return None;
+    }
+
+    let year = |s: &str| {
+        if !s.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let y: i32 = s.parse().ok()?;
+        // Two-digit years follow Excel's 1930 cutoff
+        Some(match (s.len(), y) {
+            (2, 0..=29) => 2000 + y,
+            (2, _) => 1900 + y,
+            _ => y,
+        })
+    };
+    let number = |s: &str| -> Option<u32> {
+        if s.len() > 2 {
+            return None;
+        }
+        s.parse().ok()
+    };
+
+    if parts[0].len() == 4 && parts[0].chars().all(|c| c.is_ascii_digit()) {
+        let date = (year(parts[0])?, number(parts[1])?, number(parts[2])?);
+        return Some((date, "yyyy-mm-dd"));
+    }
+    if let Some(m) = parse_month_name(parts[0]) {
+        return Some(((year(parts[2])?, m, number(parts[1])?), "d-mmm-yyyy"));
+    }
+    if let Some(m) = parse_month_name(parts[1]) {
+        return Some(((year(parts[2])?, m, number(parts[0])?), "d-mmm-yyyy"));
+    }
+    let (a, b) = (number(parts[0]), number(parts[1]));
+    match order {
+        DateOrder::MonthDayYear => Some(((year(parts[2])?, a?, b?), "m/d/yyyy")),
+        DateOrder::DayMonthYear => Some(((year(parts[2])?, b?, a?), "d/m/yyyy")),
+        DateOrder::YearMonthDay => Some(((year(parts[0])?, b?, number(parts[2])?), "yyyy/m/d")),
+    }
+}
+
+/// A clock reading such as `18:30`, `6:30:15 PM` or `36:00`
+struct ClockTime {
+    hours: u32,
+    minutes: u32,
+    seconds: f64,
+    has_seconds: bool,
+    twelve_hour: bool,
+}
+
+impl ClockTime {
+    /// Whether this is an elapsed time rather than a time of day
+    fn is_duration(&self) -> bool {
+        self.hours > 23
+    }
+
+    fn fraction(&self) -> f64 {
+        (self.hours as f64 * 3600.0 + self.minutes as f64 * 60.0 + self.seconds) / 86400.0
+    }
+
+    fn format(&self) -> String {
+        let clock = if self.has_seconds { "h:mm:ss" } else { "h:mm" };
+        if self.twelve_hour {
+            format!("{} AM/PM", clock)
+        } else {
+            clock.to_string()
+        }
+    }
+
+    fn duration_format(&self) -> String {
+        let clock = if self.has_seconds { "[h]:mm:ss" } else { "[h]:mm" };
+        clock.to_string()
+    }
+}
+
+fn parse_clock(text: &str) -> Option<ClockTime> {
+    let lower = text.trim().to_lowercase();
+    let (clock, meridiem) = if let Some(rest) = lower.strip_suffix("am") {
+        (rest.trim(), Some(false))
+    } else if let Some(rest) = lower.strip_suffix("pm") {
+        (rest.trim(), Some(true))
+    } else {
+        (lower.as_str(), None)
+    };
+
+    let parts: Vec<&str> = clock.split(':').collect();
+    if parts.len() < 2 || parts.len() > 3 {
+        return None;
+    }
+    let digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    if !digits(parts[0]) || !digits(parts[1]) {
+        return None;
+    }
+    let mut hours: u32 = parts[0].parse().ok()?;
+    let minutes: u32 = parts[1].parse().ok()?;
+    let seconds: f64 = match parts.get(2) {
+        Some(s) if s.starts_with(|c: char| c.is_ascii_digit()) => s.parse().ok()?,
+        Some(_) => return None,
+        None => 0.0,
+    };
+    if minutes > 59 || !(0.0..60.0).contains(&seconds) {
+        return None;
+    }
+    if let Some(pm) = meridiem {
+        if hours == 0 || hours > 12 {
+            return None;
+        }
+        hours = hours % 12 + if pm { 12 } else { 0 };
+    }
+    Some(ClockTime {
+        hours,
+        minutes,
+        seconds,
+        has_seconds: parts.len() == 3,
+        twelve_hour: meridiem.is_some(),
+    })
+}
+
+/// Check whether a number format code displays a date, a time or both
+///
+/// Like [`is_date_format`](crate::is_date_format), but also true for
+/// time-only codes such as `h:mm` and elapsed-time codes such as `[h]:mm`.
+pub fn is_date_time_format(code: &str) -> bool {
+    tokenize(code).iter().any(|token| !matches!(token, Token::Literal(_)))
+}
+
+/// Render a serial number through a date or time number format
+///
+/// Supports the year, month, day, weekday, hour, minute, second, elapsed
+/// time (`[h]`, `[m]`, `[s]`) and `AM/PM` tokens, quoted literals and
+/// backslash escapes. Returns `None` if the code has no date or time tokens,
+/// or a date token is asked of a serial outside the calendar.
+///
+/// # Examples
+///
+/// ```
+/// use rusheet_core::{format_date_time, DateSystem};
+///
+/// let text = format_date_time(46311.75, "yyyy-mm-dd h:mm AM/PM", DateSystem::Excel1900);
+/// assert_eq!(text.as_deref(), Some("2026-10-16 6:00 PM"));
+/// ```
+pub fn format_date_time(serial: f64, code: &str, system: DateSystem) -> Option<String> {
+    let tokens = tokenize(code);
+    if tokens.iter().all(|token| matches!(token, Token::Literal(_))) {
+        return None;
+    }
+
+    // Round to the second first, so 0.99999999 shows as the next day
+    let total_seconds = (serial * 86400.0).round();
+    let day = (total_seconds / 86400.0).floor();
+    let seconds_of_day = (total_seconds - day * 86400.0) as u32;
+    let (hour, minute, second) = (
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60,
+    );
+    let twelve_hour = tokens.iter().any(|t| matches!(t, Token::AmPm(_)));
+
+    let needs_date = tokens
+        .iter()
+        .any(|t| matches!(t, Token::Year(_) | Token::Month(_) | Token::Day(_)));
+    let (year, month, day_of_month) = if needs_date {
+        serial_to_date(day, system).ok()?
+    } else {
+        (0, 0, 0)
+    };
+
+    let mut out = String::new();
+    for token in &tokens {
+        match token {
+            Token::Literal(text) => out.push_str(text),
+            Token::Year(2) => out.push_str(&format!("{:02}", year % 100)),
+            Token::Year(_) => out.push_str(&format!("{:04}", year)),
+            Token::Month(1) => out.push_str(&month.to_string()),
+            Token::Month(2) => out.push_str(&format!("{:02}", month)),
+            Token::Month(3) => out.push_str(&MONTH_NAMES[month as usize - 1][..3]),
+            Token::Month(4) => out.push_str(MONTH_NAMES[month as usize - 1]),
+            Token::Month(_) => out.push_str(&MONTH_NAMES[month as usize - 1][..1]),
+            Token::Day(1) => out.push_str(&day_of_month.to_string()),
+            Token::Day(2) => out.push_str(&format!("{:02}", day_of_month)),
+            Token::Day(n) => {
+                // Serial 1 (1900-01-01) is a Sunday in Excel's calendar
+                let serial_1900 = convert_serial(day, system, DateSystem::Excel1900);
+                let name = DAY_NAMES[((serial_1900 as i64 + 6).rem_euclid(7)) as usize];
+                out.push_str(if *n == 3 { &name[..3] } else { name });
+            }
+            Token::Hour(n) => {
+                let hour = if twelve_hour {
+                    (hour + 11) % 12 + 1
+                } else {
+                    hour
+                };
+                out.push_str(&pad(hour as f64, *n));
+            }
+            Token::Minute(n) => out.push_str(&pad(minute as f64, *n)),
+            Token::Second(n) => out.push_str(&pad(second as f64, *n)),
+            Token::Elapsed(unit, n) => {
+                let elapsed = (total_seconds / *unit as f64).floor();
+                out.push_str(&pad(elapsed, *n));
+            }
+            Token::AmPm(style) => {
+                let (am, pm) = match style {
+                    AmPmStyle::Upper => ("AM", "PM"),
+                    AmPmStyle::Lower => ("am", "pm"),
+                    AmPmStyle::Letter => ("A", "P"),
+                };
+                out.push_str(if hour < 12 { am } else { pm });
+            }
+        }
+    }
+    Some(out)
+}
+
+fn pad(value: f64, width: usize) -> String {
+    format!("{:0width$}", value as i64, width = width)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(String),
+    Year(usize),
+    Month(usize),
+    Day(usize),
+    Hour(usize),
+    Minute(usize),
+    Second(usize),
+    /// Elapsed time in units of the given number of seconds, e.g. `[h]`
+    Elapsed(u32, usize),
+    AmPm(AmPmStyle),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AmPmStyle {
+    Upper,
+    Lower,
+    Letter,
+}
+
+/// Split a number format into date/time tokens and literal text
+///
+/// Only the first section of a multi-section format is used. An `m` is
+/// minutes when it follows an hour or precedes a second, otherwise months.
+fn tokenize(code: &str) -> Vec<Token> {
+    let chars: Vec<char> = code.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let run = |i: usize, c: char| {
+        chars[i..]
+            .iter()
+            .take_while(|x| x.eq_ignore_ascii_case(&c))
+            .count()
+    };
+    let literal = |tokens: &mut Vec<Token>, text: &str| match tokens.last_mut() {
+        Some(Token::Literal(last)) => last.push_str(text),
+        _ => tokens.push(Token::Literal(text.to_string())),
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let rest: String = chars[i..].iter().collect();
+        let rest_lower = rest.to_lowercase();
+        match c.to_ascii_lowercase() {
+            ';' => break,
+            '"' => {
+                let end = chars[i + 1..].iter().position(|&q| q == '"');
+                let end = end.map_or(chars.len(), |e| i + 1 + e);
+                literal(&mut tokens, &chars[i + 1..end].iter().collect::<String>());
+                i = end + 1;
+            }
+            '\\' => {
+                if let Some(next) = chars.get(i + 1) {
+                    literal(&mut tokens, &next.to_string());
+                }
+                i += 2;
+            }
+            '[' => {
+                let end = chars[i..].iter().position(|&q| q == ']');
+                let end = end.map_or(chars.len(), |e| i + e);
+                let inner: String = chars[i + 1..end].iter().collect::<String>().to_lowercase();
+                let unit = match inner.chars().next() {
+                    Some('h') => Some(3600),
+                    Some('m') => Some(60),
+                    Some('s') => Some(1),
+                    _ => None,
+                };
+                let repeated = inner.chars().all(|x| inner.starts_with(x));
+                // Colors, conditions and locales such as [Red] aren't shown
+                if let Some(unit) = unit.filter(|_| repeated) {
+                    tokens.push(Token::Elapsed(unit, inner.len()));
+                }
+                i = end + 1;
+            }
+            'y' => {
+                let n = run(i, c);
+                tokens.push(Token::Year(if n <= 2 { 2 } else { 4 }));
+                i += n;
+            }
+            'd' => {
+                let n = run(i, c);
+                tokens.push(Token::Day(n.min(4)));
+                i += n;
+            }
+            'h' => {
+                let n = run(i, c);
+                tokens.push(Token::Hour(n.min(2)));
+                i += n;
+            }
+            's' => {
+                let n = run(i, c);
+                tokens.push(Token::Second(n.min(2)));
+                i += n;
+            }
+            'm' => {
+                let n = run(i, c);
+                tokens.push(Token::Month(n.min(5)));
+                i += n;
+            }
+            'a' if rest_lower.starts_with("am/pm") => {
+                let style = if rest.starts_with('a') {
+                    AmPmStyle::Lower
+                } else {
+                    AmPmStyle::Upper
+                };
+                tokens.push(Token::AmPm(style));
+                i += 5;
+            }
+            'a' if rest_lower.starts_with("a/p") => {
+                tokens.push(Token::AmPm(AmPmStyle::Letter));
+                i += 3;
+            }
+            _ => {
+                literal(&mut tokens, &c.to_string());
+                i += 1;
+            }
+        }
+    }
+
+    resolve_minutes(&mut tokens);
+    tokens
+}
+
+/// Turn each `m`/`mm` after an hour or before a second into minutes
+fn resolve_minutes(tokens: &mut [Token]) {
+    let is_time = |t: &Token| matches!(t, Token::Hour(_) | Token::Second(_) | Token::Elapsed(..));
+    for i in 0..tokens.len() {
+        let Token::Month(n) = tokens[i] else {
+            continue;
+        };
+        if n > 2 {
+            continue;
+        }
+        let previous = tokens[..i]
+            .iter()
+            .rev()
+            .find(|t| !matches!(t, Token::Literal(_)));
+        let next = tokens[i + 1..]
+            .iter()
+            .find(|t| !matches!(t, Token::Literal(_)));
+        let after_hour = matches!(previous, Some(Token::Hour(_) | Token::Elapsed(3600, _)));
+        let before_second = matches!(next, Some(Token::Second(_)));
+        if after_hour || before_second || (previous.is_some_and(is_time) && next.is_none()) {
+            tokens[i] = Token::Minute(n);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str, order: DateOrder) -> Option<(f64, String)> {
+        parse_date_time_input(text, order, DateSystem::Excel1900)
+            .map(|input| (input.serial, input.number_format))
+    }
+
+    fn format(serial: f64, code: &str) -> String {
+        format_date_time(serial, code, DateSystem::Excel1900).unwrap()
+    }
+
+    #[test]
+    fn test_parse_dates() {
+        let order = DateOrder::MonthDayYear;
+        assert_eq!(parse("2026-10-16", order), Some((46311.0, "yyyy-mm-dd".into())));
+        assert_eq!(parse("10/16/2026", order), Some((46311.0, "m/d/yyyy".into())));
+        assert_eq!(parse("16-Oct-2026", order), Some((46311.0, "d-mmm-yyyy".into())));
+        assert_eq!(parse("October 16, 2026", order), Some((46311.0, "d-mmm-yyyy".into())));
+
+        // Invalid dates and things that merely look like dates stay text
+        assert_eq!(parse("2026-02-30", order), None);
+        assert_eq!(parse("16/10/2026", order), None);
+        assert_eq!(parse("555-1234", order), None);
+        assert_eq!(parse("1.2.3.4", order), None);
+        assert_eq!(parse("1.2.24", order), None);
+        assert_eq!(parse("1 2 24", order), None);
+        assert_eq!(parse("1/2-24", order), None);
+        assert_eq!(parse("1, 2, 24", order), None);
+        assert_eq!(parse("hello", order), None);
+    }
+
+    #[test]
+    fn test_date_order() {
+        let dmy = DateOrder::DayMonthYear;
+        assert_eq!(parse("16/10/2026", dmy), Some((46311.0, "d/m/yyyy".into())));
+        assert_eq!(parse("16.10.2026", dmy), Some((46311.0, "d/m/yyyy".into())));
+        assert_eq!(parse("10/16/2026", dmy), None);
+        assert_eq!(parse("16 10 2026", dmy), None);
+        assert_eq!(parse("16.10/2026", dmy), None);
+
+        let ymd = DateOrder::YearMonthDay;
+        assert_eq!(parse("26/10/16", ymd), Some((46311.0, "yyyy/m/d".into())));
+
+        // ISO dates read the same in every order
+        assert_eq!(parse("2026-10-16", dmy).unwrap().0, 46311.0);
+    }
+
+    #[test]
+    fn test_parse_times_and_durations() {
+        let order = DateOrder::default();
+        assert_eq!(parse("18:00", order), Some((0.75, "h:mm".into())));
+        assert_eq!(parse("6:00 PM", order), Some((0.75, "h:mm AM/PM".into())));
+        assert_eq!(parse("6:00:30 am", order).unwrap().1, "h:mm:ss AM/PM");
+        assert_eq!(
+            parse("2026-10-16 18:00", order),
+            Some((46311.75, "yyyy-mm-dd h:mm".into()))
+        );
+
+        assert_eq!(parse("36:00", order), Some((1.5, "[h]:mm".into())));
+        assert_eq!(parse("100:30:00", order).unwrap().1, "[h]:mm:ss");
+
+        assert_eq!(parse("13:00 PM", order), None);
+        assert_eq!(parse("9:75", order), None);
+        assert_eq!(parse("2026-10-16 36:00", order), None);
+        assert_eq!(parse("a:b", order), None);
+    }
+
+    #[test]
+    fn test_format_date_time() {
+        assert_eq!(format(46311.0, "yyyy-mm-dd"), "2026-10-16");
+        assert_eq!(format(46311.0, "m/d/yy"), "10/16/26");
+        assert_eq!(format(46311.0, "dddd, mmmm d"), "Friday, October 16");
+        assert_eq!(format(46311.0, "ddd d-mmm-yyyy"), "Fri 16-Oct-2026");
+        assert_eq!(format(0.75, "h:mm AM/PM"), "6:00 PM");
+        assert_eq!(format(0.5 + 1.0 / 86400.0, "hh:mm:ss"), "12:00:01");
+        assert_eq!(format(1.5, "[h]:mm"), "36:00");
+        assert_eq!(format(0.99999999, "yyyy-mm-dd h:mm"), "1900-01-01 0:00");
+        assert_eq!(format(46311.0, "\"Due\" d mmm"), "Due 16 Oct");
+
+        assert_eq!(format_date_time(1.0, "0.00", DateSystem::Excel1900), None);
+        assert_eq!(format_date_time(-5.0, "yyyy", DateSystem::Excel1900), None);
+    }
+
+    #[test]
+    fn test_is_date_time_format() {
+        assert!(is_date_time_format("yyyy-mm-dd"));
+        assert!(is_date_time_format("h:mm"));
+        assert!(is_date_time_format("[h]:mm:ss"));
+        assert!(!is_date_time_format("0.00"));
+        assert!(!is_date_time_format("0.00E+00"));
+        assert!(!is_date_time_format("[Red]0"));
+        assert!(!is_date_time_format("0 \"days\""));
+    }
+}
@@ -20,6 +20,8 @@ pub enum CellError {
     NotAvailable,
     /// Circular reference detected
     CircularReference,
+    /// #CALC! - Formula exceeded an evaluation limit
+    CalcError,
 }
 
 impl fmt::Display for CellError {
@@ -33,6 +35,7 @@ impl fmt::Display for CellError {
             CellError::NumError => write!(f, "#NUM!"),
             CellError::NotAvailable => write!(f, "#N/A"),
             CellError::CircularReference => write!(f, "#CIRCULAR!"),
+            CellError::CalcError => write!(f, "#CALC!"),
         }
     }
 }
@@ -56,6 +59,28 @@ pub enum RusheetError {
     MergeOverlap,
     /// Attempting to unmerge a cell that isn't merged
     UnmergeNotMerged,
+    /// Outline group partially overlaps (or duplicates) an existing group
+    GroupOverlap,
+    /// Outline groups would nest deeper than Excel allows
+    OutlineTooDeep,
+    /// Table name is empty, has invalid characters or looks like a cell reference
+    InvalidTableName(String),
+    /// Table name is already used in the workbook
+    TableNameExists(String),
+    /// No table with the given name
+    TableNotFound(String),
+    /// Table would overlap another table, a merged range or existing data
+    TableOverlap,
+    /// Pivot table definition is incomplete or refers outside its source
+    InvalidPivot(String),
+    /// Pivot table name is already used in the workbook
+    PivotNameExists(String),
+    /// No pivot table with the given name
+    PivotNotFound(String),
+    /// Pivot table output would overlap its source, a table or another pivot table
+    PivotOverlap,
+    /// Pivot table output would replace existing data
+    PivotTargetNotEmpty,
     /// Generic error with message
     Generic(String),
 }
@@ -71,6 +96,23 @@ impl fmt::Display for RusheetError {
             RusheetError::RangeOutOfBounds => write!(f, "Range out of bounds"),
             RusheetError::MergeOverlap => write!(f, "Merge range overlaps with existing merges"),
             RusheetError::UnmergeNotMerged => write!(f, "Cell is not merged"),
+            RusheetError::GroupOverlap => write!(f, "Group overlaps an existing group"),
+            RusheetError::OutlineTooDeep => write!(f, "Groups cannot nest more than 7 levels deep"),
+            RusheetError::InvalidTableName(name) => write!(f, "Invalid table name: '{}'", name),
+            RusheetError::TableNameExists(name) => write!(f, "Table name '{}' already exists", name),
+            RusheetError::TableNotFound(name) => write!(f, "Table '{}' not found", name),
+            RusheetError::TableOverlap => {
+                write!(f, "Table overlaps another table, merged cells or existing data")
+            }
+            RusheetError::InvalidPivot(msg) => write!(f, "Invalid pivot table: {}", msg),
+            RusheetError::PivotNameExists(name) => write!(f, "Pivot table name '{}' already exists", name),
+            RusheetError::PivotNotFound(name) => write!(f, "Pivot table '{}' not found", name),
+            RusheetError::PivotOverlap => {
+                write!(f, "Pivot table overlaps its source, a table or another pivot table")
+            }
+            RusheetError::PivotTargetNotEmpty => {
+                write!(f, "Pivot table output would replace existing data")
+            }
             RusheetError::Generic(msg) => write!(f, "Error: {}", msg),
         }
     }
@@ -89,6 +131,17 @@ impl RusheetError {
             RusheetError::RangeOutOfBounds => "RANGE_OUT_OF_BOUNDS",
             RusheetError::MergeOverlap => "MERGE_OVERLAP",
             RusheetError::UnmergeNotMerged => "UNMERGE_NOT_MERGED",
+            RusheetError::GroupOverlap => "GROUP_OVERLAP",
+            RusheetError::OutlineTooDeep => "OUTLINE_TOO_DEEP",
+            RusheetError::InvalidTableName(_) => "INVALID_TABLE_NAME",
+            RusheetError::TableNameExists(_) => "TABLE_NAME_EXISTS",
+            RusheetError::TableNotFound(_) => "TABLE_NOT_FOUND",
+            RusheetError::TableOverlap => "TABLE_OVERLAP",
+            RusheetError::InvalidPivot(_) => "INVALID_PIVOT",
+            RusheetError::PivotNameExists(_) => "PIVOT_NAME_EXISTS",
+            RusheetError::PivotNotFound(_) => "PIVOT_NOT_FOUND",
+            RusheetError::PivotOverlap => "PIVOT_OVERLAP",
+            RusheetError::PivotTargetNotEmpty => "PIVOT_TARGET_NOT_EMPTY",
             RusheetError::Generic(_) => "GENERIC_ERROR",
         }
     }
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::style::{Theme, ThemeColor, ThemeFont};
+
 /// RGBA color representation
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Color {
@@ -90,6 +92,108 @@ pub enum VerticalAlign {
     Bottom,
 }
 
+/// Line style of a cell border
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BorderStyle {
+    #[default]
+    Thin,
+    Medium,
+    Thick,
+    Dashed,
+    Dotted,
+    Double,
+}
+
+impl BorderStyle {
+    /// How heavy the line looks, used to pick between two borders on a shared edge
+    pub fn weight(&self) -> u8 {
+        match self {
+            BorderStyle::Dotted => 1,
+            BorderStyle::Dashed => 2,
+            BorderStyle::Thin => 3,
+            BorderStyle::Medium => 4,
+            BorderStyle::Double => 5,
+            BorderStyle::Thick => 6,
+        }
+    }
+}
+
+/// A border line on one edge of a cell
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BorderLine {
+    #[serde(default)]
+    pub style: BorderStyle,
+    #[serde(default)]
+    pub color: Color,
+}
+
+impl BorderLine {
+    pub const fn new(style: BorderStyle, color: Color) -> Self {
+        BorderLine { style, color }
+    }
+
+    /// Pick the border to draw where two cells share an edge
+    ///
+    /// The heavier line wins; on a tie, `other` does.
+    pub fn resolve(a: Option<BorderLine>, other: Option<BorderLine>) -> Option<BorderLine> {
+        match (a, other) {
+            (Some(a), Some(b)) if a.style.weight() > b.style.weight() => Some(a),
+            (a, b) => b.or(a),
+        }
+    }
+}
+
+/// Borders on the edges and diagonals of a cell
+///
+/// An edge shared with a neighbour can be set on either cell; see
+/// [`Sheet::resolved_borders`](crate::Sheet::resolved_borders) for the line
+/// that is drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Borders {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top: Option<BorderLine>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub right: Option<BorderLine>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bottom: Option<BorderLine>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub left: Option<BorderLine>,
+    /// Line from the top-left to the bottom-right corner
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagonal_down: Option<BorderLine>,
+    /// Line from the bottom-left to the top-right corner
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagonal_up: Option<BorderLine>,
+}
+
+impl Borders {
+    /// Check if no edge or diagonal has a border
+    pub fn is_empty(&self) -> bool {
+        *self == Borders::default()
+    }
+}
+
+/// Which borders of a range to set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BorderTarget {
+    /// Every edge of every cell
+    All,
+    /// The edges around the range
+    Outline,
+    /// The edges between cells inside the range
+    Inside,
+    InsideHorizontal,
+    InsideVertical,
+    Top,
+    Right,
+    Bottom,
+    Left,
+    DiagonalDown,
+    DiagonalUp,
+}
+
 /// Cell formatting properties
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct CellFormat {
@@ -117,6 +221,20 @@ pub struct CellFormat {
     pub number_format: Option<String>,
     #[serde(default, skip_serializing_if = "is_false")]
     pub wrap_text: bool,
+    #[serde(default, skip_serializing_if = "Borders::is_empty")]
+    pub borders: Borders,
+    /// Named style the format is based on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
+    /// Theme slot that `text_color` follows
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_theme_color: Option<ThemeColor>,
+    /// Theme slot that `background_color` follows
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background_theme_color: Option<ThemeColor>,
+    /// Theme font that `font_family` follows
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme_font: Option<ThemeFont>,
 }
 
 fn is_false(b: &bool) -> bool {
@@ -179,6 +297,37 @@ impl CellFormat {
         self
     }
 
+    /// Builder pattern: follow a theme color for text
+    pub fn with_theme_text_color(mut self, slot: ThemeColor) -> Self {
+        self.text_theme_color = Some(slot);
+        self
+    }
+
+    /// Builder pattern: follow a theme color for the background
+    pub fn with_theme_background_color(mut self, slot: ThemeColor) -> Self {
+        self.background_theme_color = Some(slot);
+        self
+    }
+
+    /// Builder pattern: follow a theme font
+    pub fn with_theme_font(mut self, slot: ThemeFont) -> Self {
+        self.theme_font = Some(slot);
+        self
+    }
+
+    /// Resolve the theme colors and font the format follows
+    pub fn apply_theme(&mut self, theme: &Theme) {
+        if let Some(slot) = self.text_theme_color {
+            self.text_color = Some(theme.color(slot));
+        }
+        if let Some(slot) = self.background_theme_color {
+            self.background_color = Some(theme.color(slot));
+        }
+        if let Some(slot) = self.theme_font {
+            self.font_family = Some(theme.font(slot).to_string());
+        }
+    }
+
     /// Get the effective font size (default is 11)
     pub fn effective_font_size(&self) -> u8 {
         self.font_size.unwrap_or(11)
@@ -208,12 +357,15 @@ impl CellFormat {
         }
         if other.font_family.is_some() {
             self.font_family = other.font_family.clone();
+            self.theme_font = other.theme_font;
         }
         if other.text_color.is_some() {
             self.text_color = other.text_color;
+            self.text_theme_color = other.text_theme_color;
         }
         if other.background_color.is_some() {
             self.background_color = other.background_color;
+            self.background_theme_color = other.background_theme_color;
         }
         if other.horizontal_align != HorizontalAlign::default() {
             self.horizontal_align = other.horizontal_align;
@@ -227,6 +379,20 @@ impl CellFormat {
         if other.wrap_text {
             self.wrap_text = true;
         }
+        let borders = &other.borders;
+        let edges = [
+            (&mut self.borders.top, borders.top),
+            (&mut self.borders.right, borders.right),
+            (&mut self.borders.bottom, borders.bottom),
+            (&mut self.borders.left, borders.left),
+            (&mut self.borders.diagonal_down, borders.diagonal_down),
+            (&mut self.borders.diagonal_up, borders.diagonal_up),
+        ];
+        for (edge, line) in edges {
+            if line.is_some() {
+                *edge = line;
+            }
+        }
     }
 }
 
@@ -243,6 +409,36 @@ mod tests {
         assert_eq!(parsed, color);
     }
 
+    #[test]
+    fn test_border_resolve() {
+        let thin = BorderLine::new(BorderStyle::Thin, Color::BLACK);
+        let thick = BorderLine::new(BorderStyle::Thick, Color::RED);
+        let dashed = BorderLine::new(BorderStyle::Dashed, Color::BLUE);
+
+        assert_eq!(BorderLine::resolve(Some(thick), Some(thin)), Some(thick));
+        assert_eq!(BorderLine::resolve(Some(thin), Some(thick)), Some(thick));
+        assert_eq!(BorderLine::resolve(Some(dashed), None), Some(dashed));
+        assert_eq!(BorderLine::resolve(None, None), None);
+
+        // Equal weight goes to the second line
+        let red_thin = BorderLine::new(BorderStyle::Thin, Color::RED);
+        assert_eq!(BorderLine::resolve(Some(thin), Some(red_thin)), Some(red_thin));
+    }
+
+    #[test]
+    fn test_border_serialization() {
+        let mut format = CellFormat::new();
+        assert_eq!(serde_json::to_string(&format).unwrap(), "{}");
+
+        format.borders.bottom = Some(BorderLine::new(BorderStyle::Double, Color::BLACK));
+        let json = serde_json::to_string(&format).unwrap();
+        assert_eq!(
+            json,
+            r#"{"borders":{"bottom":{"style":"double","color":{"r":0,"g":0,"b":0,"a":255}}}}"#
+        );
+        assert_eq!(serde_json::from_str::<CellFormat>(&json).unwrap(), format);
+    }
+
     #[test]
     fn test_format_builder() {
         let format = CellFormat::new()
@@ -0,0 +1,105 @@
+//! Cell hyperlinks
+//!
+//! A link either opens an external address or jumps to a location in the
+//! workbook. Locations are kept as reference text (`Sheet2!A1`, `B5:C9` or a
+//! defined name), as in XLSX, so sheet renames and row/column shifts can
+//! rewrite them the same way as formula references.
+
+use serde::{Deserialize, Serialize};
+
+/// Where a hyperlink goes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkTarget {
+    /// A URL, `mailto:` address or file path
+    Url(String),
+    /// A reference or defined name in the workbook
+    Location(String),
+}
+
+/// A hyperlink attached to a cell
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hyperlink {
+    #[serde(flatten)]
+    pub target: LinkTarget,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tooltip: Option<String>,
+}
+
+impl Hyperlink {
+    pub fn url(url: impl Into<String>) -> Self {
+        Self {
+            target: LinkTarget::Url(url.into()),
+            tooltip: None,
+        }
+    }
+
+    pub fn location(location: impl Into<String>) -> Self {
+        Self {
+            target: LinkTarget::Location(location.into()),
+            tooltip: None,
+        }
+    }
+
+    /// Parse a link address as written in `HYPERLINK()` and XLSX
+    ///
+    /// Addresses starting with `#` are locations in the workbook; anything
+    /// else is external.
+    ///
+    /// ```
+    /// use rusheet_core::{Hyperlink, LinkTarget};
+    ///
+    /// let link = Hyperlink::parse("#'Q1 Data'!A1");
+    /// assert_eq!(link.target, LinkTarget::Location("'Q1 Data'!A1".to_string()));
+    /// assert_eq!(link.address(), "#'Q1 Data'!A1");
+    /// ```
+    pub fn parse(address: &str) -> Self {
+        let address = address.trim();
+        match address.strip_prefix('#') {
+            Some(location) => Self::location(location),
+            None => Self::url(address),
+        }
+    }
+
+    pub fn with_tooltip(mut self, tooltip: impl Into<String>) -> Self {
+        self.tooltip = Some(tooltip.into());
+        self
+    }
+
+    /// The address in `HYPERLINK()`/XLSX form, with `#` before locations
+    pub fn address(&self) -> String {
+        match &self.target {
+            LinkTarget::Url(url) => url.clone(),
+            LinkTarget::Location(location) => format!("#{}", location),
+        }
+    }
+
+    /// The workbook location, if this is an internal link
+    pub fn internal_location(&self) -> Option<&str> {
+        match &self.target {
+            LinkTarget::Location(location) => Some(location),
+            LinkTarget::Url(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_serde() {
+        let link = Hyperlink::parse("https://example.com/a#b").with_tooltip("Docs");
+        assert_eq!(link.target, LinkTarget::Url("https://example.com/a#b".to_string()));
+        assert_eq!(link.internal_location(), None);
+
+        let json = serde_json::to_string(&link).unwrap();
+        assert_eq!(json, r#"{"url":"https://example.com/a#b","tooltip":"Docs"}"#);
+        let restored: Hyperlink = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, link);
+
+        let link = Hyperlink::parse("#Sheet2!B3");
+        assert_eq!(link.internal_location(), Some("Sheet2!B3"));
+        assert_eq!(serde_json::to_string(&link).unwrap(), r#"{"location":"Sheet2!B3"}"#);
+    }
+}
@@ -1,36 +1,67 @@
 pub mod cell;
 pub mod chunk;
+pub mod comment;
 pub mod conditional_format;
+pub mod date;
+pub mod date_text;
 pub mod error;
 pub mod format;
 pub mod gap_buffer;
+pub mod hyperlink;
+pub mod numeric;
+pub mod outline;
+pub mod pivot;
 pub mod range;
+pub mod rich_text;
 pub mod search;
 pub mod sheet;
 pub mod spatial;
 pub mod state;
+pub mod style;
+pub mod table;
 pub mod validation;
 pub mod workbook;
 
 pub use cell::{Cell, CellContent, CellValue};
 pub use chunk::{Chunk, ChunkCoord, ChunkedGrid};
+pub use comment::{extract_mentions, Comment, CommentReply};
 pub use conditional_format::{
     ComparisonOperator, ConditionalFormat, ConditionalFormattingRule, ConditionalRule,
     TextOperator,
 };
+pub use date::{
+    convert_serial, date_to_serial, days_in_month, is_date_format, is_leap_year, serial_to_date,
+    DateSystem,
+};
+pub use date_text::{
+    format_date_time, is_date_time_format, parse_date_text, parse_date_time_input,
+    parse_time_text, split_date_time, DateOrder, DateTimeInput,
+};
 pub use error::{CellError, RusheetError};
-pub use format::{CellFormat, Color, HorizontalAlign, VerticalAlign};
+pub use format::{
+    BorderLine, BorderStyle, BorderTarget, Borders, CellFormat, Color, HorizontalAlign,
+    VerticalAlign,
+};
 pub use gap_buffer::GapBuffer;
+pub use hyperlink::{Hyperlink, LinkTarget};
+pub use numeric::{numbers_equal, round_significant, CompensatedSum};
+pub use outline::{Axis, Outline, OutlineGroup, SummaryPosition, MAX_OUTLINE_LEVEL};
+pub use pivot::{
+    DateBucket, PivotAggregation, PivotField, PivotFilter, PivotGrouping, PivotTable, PivotValueField,
+};
 pub use range::{col_from_label, col_to_label, CellCoord, CellRange};
+pub use rich_text::{RichText, RunFormat, TextRun};
 pub use search::{ReplaceOptions, SearchEngine, SearchError, SearchOptions, SearchResult};
-pub use sheet::{parse_cell_input, Sheet};
+pub use sheet::{parse_cell_input, parse_cell_input_with, Sheet};
 pub use spatial::{morton_decode, morton_encode, FenwickTree, SpatialIndex};
 pub use state::{
-    CellPosition, ClipboardState, EditState, InputAction, Selection, SpreadsheetState,
-    ViewportState,
+    CellPosition, ClipboardState, EditState, InputAction, Pane, PaneKind, Selection,
+    SpreadsheetState, ViewportState,
 };
+pub use style::{FormatDiff, NamedStyle, Theme, ThemeColor, ThemeFont};
+pub use table::{Table, TableSort, TableStyle, TotalsFunction};
 pub use validation::{
     DataValidationRule, ValidationCriteria, ValidationOperator, ValidationResult,
     ListSource, AlertStyle, ValidationMessage, ValidationAlert,
 };
-pub use workbook::{Workbook, WorkbookMetadata};
+pub use workbook::{ReplacedCells, Workbook, WorkbookMetadata};
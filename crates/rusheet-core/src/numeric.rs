@@ -0,0 +1,155 @@
+/// Number of significant digits Excel keeps when comparing and displaying numbers
+pub const SIGNIFICANT_DIGITS: usize = 15;
+
+/// Round a number to 15 significant digits
+///
+/// Binary floating point cannot represent most decimal fractions exactly,
+/// so `0.1 + 0.2` is `0.30000000000000004`. Excel hides this by only
+/// keeping 15 significant digits when comparing and displaying values.
+pub fn round_significant(n: f64) -> f64 {
+    if n == 0.0 || !n.is_finite() {
+        return n;
+    }
+    // Scale so the digits to keep are the integer part
+    let digits = SIGNIFICANT_DIGITS as i32;
+    let mut shift = digits - 1 - n.abs().log10().floor() as i32;
+    let scaled = |shift: i32| match shift {
+        0.. => n * 10f64.powi(shift),
+        _ => n / 10f64.powi(-shift),
+    };
+    // log10 can land one off next to a power of ten
+    if scaled(shift).abs() >= 10f64.powi(digits) {
+        shift -= 1;
+    } else if scaled(shift).abs() < 10f64.powi(digits - 1) {
+        shift += 1;
+    }
+
+    // Powers of ten above 10^22 aren't exact, so extremes go through text
+    if shift.abs() > MAX_EXACT_POWER_OF_TEN {
+        return format!("{:.*e}", SIGNIFICANT_DIGITS - 1, n)
+            .parse()
+            .ok()
+            .filter(|r: &f64| r.is_finite())
+            .unwrap_or(n);
+    }
+    let rounded = round_scaled(n, shift);
+    match shift {
+        0.. => rounded / 10f64.powi(shift),
+        _ => rounded * 10f64.powi(-shift),
+    }
+}
+
+/// Round `n` scaled by `10^shift` to an integer, as if scaled exactly
+///
+/// Scaling itself rounds, which can land a value just off a half exactly
+/// on it; the scaling's exact error decides those ties.
+fn round_scaled(n: f64, shift: i32) -> f64 {
+    let power = 10f64.powi(shift.abs());
+    let (scaled, error) = if shift >= 0 {
+        let scaled = n * power;
+        (scaled, n.mul_add(power, -scaled))
+    } else {
+        let scaled = n / power;
+        (scaled, (-scaled).mul_add(power, n))
+    };
+    let rounded = scaled.round();
+    if (scaled - scaled.trunc()).abs() == 0.5 && error != 0.0 && (error < 0.0) != (n < 0.0) {
+        // The exact value is nearer zero than the half
+        scaled.trunc()
+    } else {
+        rounded
+    }
+}
+
+/// Largest power of ten an f64 holds exactly
+const MAX_EXACT_POWER_OF_TEN: i32 = 22;
+
+/// Compare two numbers the way Excel does, at 15 significant digits
+pub fn numbers_equal(a: f64, b: f64) -> bool {
+    round_significant(a) == round_significant(b)
+}
+
+/// Running sum with Neumaier compensation
+///
+/// Tracks the low-order bits lost by each addition so long aggregates
+/// (SUM over thousands of cells) don't drift.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompensatedSum {
+    sum: f64,
+    compensation: f64,
+}
+
+impl CompensatedSum {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a value to the running sum
+    pub fn add(&mut self, value: f64) {
+        let t = self.sum + value;
+        if self.sum.abs() >= value.abs() {
+            self.compensation += (self.sum - t) + value;
+        } else {
+            self.compensation += (value - t) + self.sum;
+        }
+        self.sum = t;
+    }
+
+    /// The compensated total
+    pub fn total(&self) -> f64 {
+        self.sum + self.compensation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_significant() {
+        assert_eq!(round_significant(0.1 + 0.2), 0.3);
+        assert_eq!(round_significant(1.0 / 3.0), 0.333333333333333);
+        assert_eq!(round_significant(123456789012345678.0), 123456789012346000.0);
+        assert_eq!(round_significant(-2.5), -2.5);
+        assert_eq!(round_significant(0.0), 0.0);
+        assert!(round_significant(f64::NAN).is_nan());
+
+        // Next to powers of ten and at the extremes
+        assert_eq!(round_significant(1000.0), 1000.0);
+        assert_eq!(round_significant(0.001), 0.001);
+        assert_eq!(round_significant(1000.0 - 1e-13), 1000.0);
+        assert_eq!(round_significant(1.0 / 3.0 * 1e-300), 3.33333333333333e-301);
+
+        // Halves are judged on the exact value and round away from zero
+        assert_eq!(round_significant(683.3085714285714), 683.308571428571);
+        assert_eq!(round_significant(-128710289995254.5), -128710289995255.0);
+        assert_eq!(round_significant(f64::MAX), f64::MAX);
+        assert_eq!(round_significant(f64::MIN_POSITIVE), 2.2250738585072e-308);
+    }
+
+    #[test]
+    fn test_numbers_equal() {
+        assert!(numbers_equal(0.1 + 0.2, 0.3));
+        assert!(numbers_equal(1.0 - 0.9, 0.1));
+        assert!(!numbers_equal(0.3, 0.300000000000001));
+    }
+
+    #[test]
+    fn test_compensated_sum() {
+        let mut naive = 0.0;
+        let mut sum = CompensatedSum::new();
+        for _ in 0..10 {
+            naive += 0.1;
+            sum.add(0.1);
+        }
+        assert_ne!(naive, 1.0);
+        assert_eq!(sum.total(), 1.0);
+
+        // Large and small magnitudes
+        let mut sum = CompensatedSum::new();
+        for v in [1e16, 1.0, -1e16] {
+            sum.add(v);
+        }
+        assert_eq!(sum.total(), 1.0);
+    }
+}
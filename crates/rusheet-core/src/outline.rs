@@ -0,0 +1,305 @@
+//! Row and column outlines (grouping)
+//!
+//! Groups nest: every pair of groups on an axis is either disjoint or one
+//! contains the other, so a group's outline level is simply the number of
+//! groups containing it (itself included).
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+
+use crate::error::RusheetError;
+
+/// Deepest outline level, as in Excel
+pub const MAX_OUTLINE_LEVEL: u8 = 7;
+
+/// Rows or columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Axis {
+    Rows,
+    Cols,
+}
+
+/// Where a group's summary row (or column) sits relative to its details
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SummaryPosition {
+    /// Below the group (rows) or to its right (columns)
+    #[default]
+    After,
+    /// Above the group (rows) or to its left (columns)
+    Before,
+}
+
+/// A contiguous run of grouped rows or columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutlineGroup {
+    pub start: u32,
+    /// Last index in the group (inclusive)
+    pub end: u32,
+    /// Collapsed groups hide their rows or columns
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub collapsed: bool,
+}
+
+impl OutlineGroup {
+    pub fn new(start: u32, end: u32) -> Self {
+        Self {
+            start: start.min(end),
+            end: start.max(end),
+            collapsed: false,
+        }
+    }
+
+    /// Check if an index lies in the group
+    pub fn contains(&self, index: u32) -> bool {
+        (self.start..=self.end).contains(&index)
+    }
+
+    fn encloses(&self, other: &OutlineGroup) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    fn overlaps(&self, other: &OutlineGroup) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    fn same_span(&self, other: &OutlineGroup) -> bool {
+        self.start == other.start && self.end == other.end
+    }
+}
+
+/// The outline groups along one axis of a sheet
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Outline {
+    /// Groups ordered by start, outer groups before the ones they contain
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    groups: Vec<OutlineGroup>,
+    #[serde(default)]
+    pub summary: SummaryPosition,
+}
+
+impl Outline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// True when there is nothing worth serializing
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty() && self.summary == SummaryPosition::default()
+    }
+
+    pub fn groups(&self) -> &[OutlineGroup] {
+        &self.groups
+    }
+
+    /// Find the group spanning exactly `start..=end`
+    pub fn find(&self, start: u32, end: u32) -> Option<&OutlineGroup> {
+        let span = OutlineGroup::new(start, end);
+        self.groups.iter().find(|g| g.same_span(&span))
+    }
+
+    /// Outline level of an index (0 when ungrouped)
+    pub fn level_of(&self, index: u32) -> u8 {
+        self.groups.iter().filter(|g| g.contains(index)).count() as u8
+    }
+
+    /// Outline level of a group (1 for outermost groups)
+    pub fn group_level(&self, group: &OutlineGroup) -> u8 {
+        self.groups.iter().filter(|g| g.encloses(group)).count() as u8
+    }
+
+    /// Deepest level in use
+    pub fn max_level(&self) -> u8 {
+        self.groups
+            .iter()
+            .map(|g| self.group_level(g))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Index of the summary row/column that carries a group's toggle, if any
+    pub fn summary_index(&self, group: &OutlineGroup) -> Option<u32> {
+        match self.summary {
+            SummaryPosition::After => group.end.checked_add(1),
+            SummaryPosition::Before => group.start.checked_sub(1),
+        }
+    }
+
+    /// Group `start..=end`, nesting it inside or around existing groups
+    pub fn group(&mut self, start: u32, end: u32) -> Result<(), RusheetError> {
+        let group = OutlineGroup::new(start, end);
+        let conflicts = self.groups.iter().any(|g| {
+            g.same_span(&group) || (g.overlaps(&group) && !g.encloses(&group) && !group.encloses(g))
+        });
+        if conflicts {
+            return Err(RusheetError::GroupOverlap);
+        }
+
+        let at = self.groups.partition_point(|g| {
+            (g.start, Reverse(g.end)) < (group.start, Reverse(group.end))
+        });
+        self.groups.insert(at, group);
+        if self.max_level() > MAX_OUTLINE_LEVEL {
+            self.groups.remove(at);
+            return Err(RusheetError::OutlineTooDeep);
+        }
+        Ok(())
+    }
+
+    /// Remove the group spanning exactly `start..=end`
+    pub fn ungroup(&mut self, start: u32, end: u32) -> Option<OutlineGroup> {
+        let span = OutlineGroup::new(start, end);
+        let at = self.groups.iter().position(|g| g.same_span(&span))?;
+        Some(self.groups.remove(at))
+    }
+
+    /// Collapse or expand the group spanning exactly `start..=end`
+    pub fn set_collapsed(&mut self, start: u32, end: u32, collapsed: bool) -> bool {
+        let span = OutlineGroup::new(start, end);
+        match self.groups.iter_mut().find(|g| g.same_span(&span)) {
+            Some(group) => {
+                group.collapsed = collapsed;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Show levels up to `level`: deeper groups collapse, the rest expand
+    ///
+    /// `show_level(0)` hides every grouped index.
+    pub fn show_level(&mut self, level: u8) {
+        let levels: Vec<u8> = self.groups.iter().map(|g| self.group_level(g)).collect();
+        for (group, group_level) in self.groups.iter_mut().zip(levels) {
+            group.collapsed = group_level > level;
+        }
+    }
+
+    /// Indices hidden by collapsed groups
+    pub fn collapsed_indices(&self) -> BTreeSet<u32> {
+        self.groups
+            .iter()
+            .filter(|g| g.collapsed)
+            .flat_map(|g| g.start..=g.end)
+            .collect()
+    }
+
+    /// Shift groups for `count` indices inserted at `at`
+    ///
+    /// Insertions inside a group widen it.
+    pub fn insert(&mut self, at: u32, count: u32) {
+        for group in &mut self.groups {
+            if group.start >= at {
+                group.start += count;
+                group.end += count;
+            } else if group.end >= at {
+                group.end += count;
+            }
+        }
+    }
+
+    /// Shift groups for `count` indices deleted from `at`
+    ///
+    /// Groups lying wholly in the deleted span are dropped.
+    pub fn delete(&mut self, at: u32, count: u32) {
+        let end = at.saturating_add(count);
+        self.groups.retain(|g| g.start < at || g.end >= end);
+        for group in &mut self.groups {
+            if group.start >= end {
+                group.start -= count;
+            } else if group.start >= at {
+                group.start = at;
+            }
+            if group.end >= end {
+                group.end -= count;
+            } else if group.end >= at {
+                group.end = at - 1;
+            }
+        }
+        // Deleting can make two groups share a span; keep the outer one
+        self.groups.dedup_by(|inner, outer| inner.same_span(outer));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_levels() {
+        let mut outline = Outline::new();
+        outline.group(2, 9).unwrap();
+        outline.group(4, 6).unwrap();
+
+        assert_eq!(outline.level_of(1), 0);
+        assert_eq!(outline.level_of(3), 1);
+        assert_eq!(outline.level_of(5), 2);
+        assert_eq!(outline.max_level(), 2);
+        assert_eq!(outline.groups()[0], OutlineGroup::new(2, 9));
+
+        // Partial overlaps and duplicates are rejected
+        assert_eq!(outline.group(5, 12), Err(RusheetError::GroupOverlap));
+        assert_eq!(outline.group(4, 6), Err(RusheetError::GroupOverlap));
+
+        // A group around both lifts them a level
+        outline.group(0, 10).unwrap();
+        assert_eq!(outline.level_of(5), 3);
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let mut outline = Outline::new();
+        for i in 0..MAX_OUTLINE_LEVEL as u32 {
+            outline.group(i, 20 - i).unwrap();
+        }
+        assert_eq!(outline.group(10, 10), Err(RusheetError::OutlineTooDeep));
+        assert_eq!(outline.group(0, 30), Err(RusheetError::OutlineTooDeep));
+    }
+
+    #[test]
+    fn test_collapse_and_show_level() {
+        let mut outline = Outline::new();
+        outline.group(1, 8).unwrap();
+        outline.group(3, 4).unwrap();
+
+        assert!(outline.set_collapsed(3, 4, true));
+        assert_eq!(outline.collapsed_indices(), BTreeSet::from([3, 4]));
+
+        outline.show_level(0);
+        assert_eq!(outline.collapsed_indices().len(), 8);
+
+        outline.show_level(1);
+        assert!(!outline.find(1, 8).unwrap().collapsed);
+        assert!(outline.find(3, 4).unwrap().collapsed);
+
+        assert!(!outline.set_collapsed(2, 4, true));
+    }
+
+    #[test]
+    fn test_summary_index() {
+        let mut outline = Outline::new();
+        let group = OutlineGroup::new(0, 3);
+        assert_eq!(outline.summary_index(&group), Some(4));
+        outline.summary = SummaryPosition::Before;
+        assert_eq!(outline.summary_index(&group), None);
+    }
+
+    #[test]
+    fn test_insert_and_delete_shift_groups() {
+        let mut outline = Outline::new();
+        outline.group(2, 5).unwrap();
+        outline.group(10, 12).unwrap();
+
+        outline.insert(4, 2);
+        assert_eq!(outline.groups()[0], OutlineGroup::new(2, 7));
+        assert_eq!(outline.groups()[1], OutlineGroup::new(12, 14));
+
+        outline.delete(6, 7);
+        assert_eq!(outline.groups(), &[OutlineGroup::new(2, 5), OutlineGroup::new(6, 7)]);
+
+        outline.delete(6, 2);
+        assert_eq!(outline.groups(), &[OutlineGroup::new(2, 5)]);
+    }
+}
@@ -0,0 +1,856 @@
+//! Pivot tables
+//!
+//! A pivot table summarizes a source range whose first row holds field
+//! names. The workbook keeps its definition; refreshing it regroups the
+//! source's current values and writes the summary to the target sheet as
+//! plain values, so it only changes when refreshed.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cell::{CellContent, CellValue};
+use crate::date::{serial_to_date, DateSystem};
+use crate::error::{CellError, RusheetError};
+use crate::outline::Axis;
+use crate::numeric::CompensatedSum;
+use crate::range::{CellCoord, CellRange};
+use crate::sheet::Sheet;
+
+/// How a value field combines the records in each cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PivotAggregation {
+    Sum,
+    /// Non-empty values
+    Count,
+    Average,
+    Min,
+    Max,
+    /// Different non-empty values
+    DistinctCount,
+}
+
+impl PivotAggregation {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PivotAggregation::Sum => "Sum",
+            PivotAggregation::Count => "Count",
+            PivotAggregation::Average => "Average",
+            PivotAggregation::Min => "Min",
+            PivotAggregation::Max => "Max",
+            PivotAggregation::DistinctCount => "Distinct Count",
+        }
+    }
+}
+
+/// Calendar period that dates are grouped into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateBucket {
+    Year,
+    Quarter,
+    Month,
+    Day,
+}
+
+/// How a field's values are turned into items
+///
+/// Values the grouping doesn't apply to, such as text in a date field,
+/// stay items of their own.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PivotGrouping {
+    /// Each distinct value is an item
+    #[default]
+    None,
+    /// Date serials grouped by calendar period
+    Date { bucket: DateBucket },
+    /// Numbers grouped into bins of `size` starting at `start`
+    Bins { start: f64, size: f64 },
+}
+
+impl PivotGrouping {
+    fn is_none(&self) -> bool {
+        matches!(self, PivotGrouping::None)
+    }
+}
+
+/// A source column used as row, column or filter field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PivotField {
+    /// Column offset from the source range's first column
+    pub column: u32,
+    #[serde(default, skip_serializing_if = "PivotGrouping::is_none")]
+    pub grouping: PivotGrouping,
+}
+
+impl PivotField {
+    pub fn new(column: u32) -> Self {
+        Self {
+            column,
+            grouping: PivotGrouping::None,
+        }
+    }
+
+    pub fn grouped(mut self, grouping: PivotGrouping) -> Self {
+        self.grouping = grouping;
+        self
+    }
+}
+
+/// A source column summarized in the body of the pivot table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PivotValueField {
+    /// Column offset from the source range's first column
+    pub column: u32,
+    pub aggregation: PivotAggregation,
+    /// Header label, `"Sum of Amount"` style when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl PivotValueField {
+    pub fn new(column: u32, aggregation: PivotAggregation) -> Self {
+        Self {
+            column,
+            aggregation,
+            name: None,
+        }
+    }
+}
+
+/// Keeps only the records whose item in `field` is one of `items`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PivotFilter {
+    pub field: PivotField,
+    /// Item labels as shown in the pivot table, e.g. `"(blank)"` or `"2024 Qtr1"`
+    pub items: Vec<String>,
+}
+
+/// A pivot table definition and where its last refresh wrote to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PivotTable {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub source_sheet: String,
+    /// Source records, with field names in the first row
+    pub source: CellRange,
+    #[serde(default)]
+    pub target_sheet: String,
+    /// Top-left cell of the output
+    pub target: CellCoord,
+    #[serde(default)]
+    pub rows: Vec<PivotField>,
+    #[serde(default)]
+    pub columns: Vec<PivotField>,
+    #[serde(default)]
+    pub values: Vec<PivotValueField>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<PivotFilter>,
+    /// Add a grand total row and column
+    #[serde(default = "default_true")]
+    pub grand_totals: bool,
+    /// Cells written by the last refresh
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<CellRange>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+const GRAND_TOTAL: &str = "Grand Total";
+const BLANK: &str = "(blank)";
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+impl PivotTable {
+    pub fn new(
+        name: impl Into<String>,
+        source_sheet: impl Into<String>,
+        source: CellRange,
+        target_sheet: impl Into<String>,
+        target: CellCoord,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            source_sheet: source_sheet.into(),
+            source,
+            target_sheet: target_sheet.into(),
+            target,
+            rows: Vec::new(),
+            columns: Vec::new(),
+            values: Vec::new(),
+            filters: Vec::new(),
+            grand_totals: true,
+            output: None,
+        }
+    }
+
+    pub fn with_rows(mut self, rows: Vec<PivotField>) -> Self {
+        self.rows = rows;
+        self
+    }
+
+    pub fn with_columns(mut self, columns: Vec<PivotField>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    pub fn with_values(mut self, values: Vec<PivotValueField>) -> Self {
+        self.values = values;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: PivotFilter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// Check the definition can be computed
+    pub fn validate(&self) -> Result<(), RusheetError> {
+        if self.name.trim().is_empty() {
+            return Err(RusheetError::InvalidPivot("Name cannot be empty".to_string()));
+        }
+        if self.values.is_empty() {
+            return Err(RusheetError::InvalidPivot("At least one value field is required".to_string()));
+        }
+        let width = self.source.col_count();
+        let columns = self
+            .rows
+            .iter()
+            .chain(&self.columns)
+            .chain(self.filters.iter().map(|f| &f.field))
+            .map(|f| (f.column, f.grouping))
+            .chain(self.values.iter().map(|v| (v.column, PivotGrouping::None)));
+        for (column, grouping) in columns {
+            if column >= width {
+                return Err(RusheetError::InvalidPivot(format!(
+                    "Field column {} is outside the source range",
+                    column
+                )));
+            }
+            if let PivotGrouping::Bins { start, size } = grouping {
+                if !(size > 0.0 && size.is_finite() && start.is_finite()) {
+                    return Err(RusheetError::InvalidPivot("Bin size must be positive".to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Field names from the source's first row, `Column1` style where blank
+    pub fn field_names(&self, source: &Sheet) -> Vec<String> {
+        let header = self.source.start.row;
+        (0..self.source.col_count())
+            .map(|offset| {
+                let value = source.get_cell_value(CellCoord::new(header, self.source.start.col + offset));
+                match value.as_text() {
+                    name if name.trim().is_empty() => format!("Column{}", offset + 1),
+                    name => name,
+                }
+            })
+            .collect()
+    }
+
+    /// Summarize the source into rows of output values
+    ///
+    /// The output starts with one header row per column field and a row of
+    /// field and value labels, followed by a row per combination of row
+    /// items. Cells no record falls into are empty.
+    pub fn compute(&self, source: &Sheet, date_system: DateSystem) -> Result<Vec<Vec<CellValue>>, RusheetError> {
+        self.validate()?;
+        let names = self.field_names(source);
+        let value_count = self.values.len();
+
+        let mut row_keys = BTreeSet::new();
+        let mut col_keys = BTreeSet::new();
+        let mut cells: BTreeMap<(Key, Key), Vec<Accumulator>> = BTreeMap::new();
+
+        for row in self.source.start.row + 1..=self.source.end.row {
+            let value = |column: u32| source.get_cell_value(CellCoord::new(row, self.source.start.col + column));
+            if (0..self.source.col_count()).all(|column| value(column).is_empty()) {
+                continue;
+            }
+            let keep = self.filters.iter().all(|filter| {
+                let label = Item::new(value(filter.field.column), filter.field.grouping, date_system).label();
+                filter.items.iter().any(|item| item.eq_ignore_ascii_case(&label))
+            });
+            if !keep {
+                continue;
+            }
+
+            let key = |fields: &[PivotField]| -> Vec<Item> {
+                fields
+                    .iter()
+                    .map(|f| Item::new(value(f.column), f.grouping, date_system))
+                    .collect()
+            };
+            let row_key = key(&self.rows);
+            let col_key = key(&self.columns);
+            row_keys.insert(row_key.clone());
+            col_keys.insert(col_key.clone());
+
+            // Each record also counts toward the totals it falls under
+            let mut row_targets = vec![Some(row_key)];
+            if !self.rows.is_empty() {
+                row_targets.push(None);
+            }
+            let mut col_targets = vec![Some(col_key)];
+            if !self.columns.is_empty() {
+                col_targets.push(None);
+            }
+            for r in &row_targets {
+                for c in &col_targets {
+                    let accumulators = cells
+                        .entry((r.clone(), c.clone()))
+                        .or_insert_with(|| vec![Accumulator::default(); value_count]);
+                    for (accumulator, field) in accumulators.iter_mut().zip(&self.values) {
+                        accumulator.add(value(field.column));
+                    }
+                }
+            }
+        }
+
+        // Without column fields there is a single group of value columns
+        let mut columns: Vec<Key> = if self.columns.is_empty() {
+            vec![Some(Vec::new())]
+        } else {
+            col_keys.into_iter().map(Some).collect()
+        };
+        if !self.columns.is_empty() && self.grand_totals {
+            columns.push(None);
+        }
+        let left = self.rows.len().max(1);
+        let width = left + columns.len() * value_count;
+        let blank_row = || vec![CellValue::Empty; width];
+        let mut grid = Vec::new();
+
+        for level in 0..self.columns.len() {
+            let mut row = blank_row();
+            row[0] = CellValue::Text(names[self.columns[level].column as usize].clone());
+            for (index, key) in columns.iter().enumerate() {
+                let cell = &mut row[left + index * value_count];
+                match key {
+                    Some(key) => {
+                        let previous = index.checked_sub(1).and_then(|i| columns[i].as_ref());
+                        if previous.is_none_or(|p| p[..=level] != key[..=level]) {
+                            *cell = key[level].value();
+                        }
+                    }
+                    None if level == 0 => *cell = CellValue::Text(GRAND_TOTAL.to_string()),
+                    None => {}
+                }
+            }
+            grid.push(row);
+        }
+
+        let mut labels = blank_row();
+        for (cell, field) in labels.iter_mut().zip(&self.rows) {
+            *cell = CellValue::Text(names[field.column as usize].clone());
+        }
+        for index in 0..columns.len() {
+            for (offset, field) in self.values.iter().enumerate() {
+                let label = field.name.clone().unwrap_or_else(|| {
+                    format!("{} of {}", field.aggregation.label(), names[field.column as usize])
+                });
+                labels[left + index * value_count + offset] = CellValue::Text(label);
+            }
+        }
+        grid.push(labels);
+
+        let mut body: Vec<Key> = if self.rows.is_empty() {
+            vec![Some(Vec::new())]
+        } else {
+            row_keys.into_iter().map(Some).collect()
+        };
+        if !self.rows.is_empty() && self.grand_totals {
+            body.push(None);
+        }
+        let mut previous: Option<&Vec<Item>> = None;
+        for key in &body {
+            let mut row = blank_row();
+            match key {
+                Some(_) if self.rows.is_empty() => row[0] = CellValue::Text(GRAND_TOTAL.to_string()),
+                Some(key) => {
+                    // Outer items are shown once, on the first row of their group
+                    let changed = previous
+                        .map(|p| p.iter().zip(key).position(|(a, b)| a != b).unwrap_or(key.len()))
+                        .unwrap_or(0);
+                    for level in changed..key.len() {
+                        row[level] = key[level].value();
+                    }
+                    previous = Some(key);
+                }
+                None => row[0] = CellValue::Text(GRAND_TOTAL.to_string()),
+            }
+            for (index, col) in columns.iter().enumerate() {
+                if let Some(accumulators) = cells.get(&(key.clone(), col.clone())) {
+                    for (offset, (accumulator, field)) in accumulators.iter().zip(&self.values).enumerate() {
+                        row[left + index * value_count + offset] = accumulator.result(field.aggregation);
+                    }
+                }
+            }
+            grid.push(row);
+        }
+
+        Ok(grid)
+    }
+
+    /// Write computed output to `sheet` at the target, replacing cell contents
+    ///
+    /// Cell formats are kept. The caller checks the output fits on the sheet.
+    pub fn write(&self, sheet: &mut Sheet, grid: &[Vec<CellValue>]) {
+        for (r, row) in grid.iter().enumerate() {
+            for (c, value) in row.iter().enumerate() {
+                let coord = CellCoord::new(self.target.row + r as u32, self.target.col + c as u32);
+                set_content(sheet, coord, value.clone());
+            }
+        }
+    }
+
+    /// Empty the cells written by the last refresh
+    pub fn clear(&self, sheet: &mut Sheet) {
+        if let Some(output) = self.output {
+            for coord in output.iter() {
+                set_content(sheet, coord, CellValue::Empty);
+            }
+        }
+    }
+
+    /// Follow rows or columns inserted into or deleted from the sheet `sheet`
+    ///
+    /// The source and output move, grow or shrink like a table's range, and
+    /// fields follow their source columns; fields on deleted columns are
+    /// dropped. Returns false if the source's header row or all its columns,
+    /// or the whole output, were deleted.
+    pub fn shift(&mut self, sheet: &str, axis: Axis, at: u32, count: u32, insert: bool) -> bool {
+        if self.source_sheet == sheet {
+            let old_start = self.source.start.col;
+            if axis == Axis::Rows && !insert && (at..at + count).contains(&self.source.start.row) {
+                return false;
+            }
+            let Some(source) = shift_range(self.source, axis, at, count, insert) else {
+                return false;
+            };
+            if axis == Axis::Cols {
+                let moved = |column: &mut u32| -> bool {
+                    let col = old_start + *column;
+                    let new = match (insert, col >= at) {
+                        (_, false) => col,
+                        (true, true) => col + count,
+                        (false, true) if col < at + count => return false,
+                        (false, true) => col - count,
+                    };
+                    *column = new - source.start.col;
+                    true
+                };
+                self.rows.retain_mut(|f| moved(&mut f.column));
+                self.columns.retain_mut(|f| moved(&mut f.column));
+                self.values.retain_mut(|f| moved(&mut f.column));
+                self.filters.retain_mut(|f| moved(&mut f.field.column));
+            }
+            self.source = source;
+        }
+        if self.target_sheet == sheet {
+            let output = self.output.unwrap_or(CellRange::new(self.target, self.target));
+            let Some(moved) = shift_range(output, axis, at, count, insert) else {
+                return false;
+            };
+            self.target = moved.start;
+            self.output = self.output.map(|_| moved);
+        }
+        true
+    }
+}
+
+/// Shift `range` along `axis` for an insert or delete at `at`
+///
+/// Returns `None` if the whole range was deleted.
+fn shift_range(range: CellRange, axis: Axis, at: u32, count: u32, insert: bool) -> Option<CellRange> {
+    let (start, end) = match axis {
+        Axis::Rows => (range.start.row, range.end.row),
+        Axis::Cols => (range.start.col, range.end.col),
+    };
+    let (new_start, new_end) = if insert {
+        match at {
+            _ if at <= start => (start + count, end + count),
+            _ if at <= end => (start, end + count),
+            _ => (start, end),
+        }
+    } else {
+        let deleted = at..at + count;
+        if deleted.contains(&start) && deleted.contains(&end) {
+            return None;
+        }
+        let shift = |i: u32| match i {
+            _ if i >= at + count => i - count,
+            _ if i >= at => at.saturating_sub(1),
+            _ => i,
+        };
+        let new_start = if deleted.contains(&start) { at } else { shift(start) };
+        (new_start, shift(end).max(new_start))
+    };
+    let mut range = range;
+    match axis {
+        Axis::Rows => (range.start.row, range.end.row) = (new_start, new_end),
+        Axis::Cols => (range.start.col, range.end.col) = (new_start, new_end),
+    }
+    Some(range)
+}
+
+fn set_content(sheet: &mut Sheet, coord: CellCoord, value: CellValue) {
+    if value.is_empty() && sheet.get_cell(coord).is_none() {
+        return;
+    }
+    let cell = sheet.get_cell_mut(coord);
+    cell.content = CellContent::Value {
+        value,
+        original_input: None,
+    };
+    cell.rich_text = None;
+    if cell.is_empty() {
+        sheet.remove_cell(coord);
+    }
+}
+
+/// The items of a row or column, `None` for the grand total
+type Key = Option<Vec<Item>>;
+
+/// A row or column label
+///
+/// Text compares ignoring case, so `east` and `East` are one item shown as
+/// whichever came first.
+#[derive(Debug, Clone)]
+enum Item {
+    Number(f64),
+    /// A date period or bin, ordered by its position
+    Group(i64, String),
+    Text(String),
+    Boolean(bool),
+    Error(CellError),
+    Blank,
+}
+
+impl Item {
+    fn new(value: &CellValue, grouping: PivotGrouping, date_system: DateSystem) -> Self {
+        match (value, grouping) {
+            (CellValue::Number(n), PivotGrouping::Date { bucket }) => match serial_to_date(*n, date_system) {
+                Ok((year, month, day)) => date_item(bucket, year, month, day),
+                Err(_) => Item::Number(*n),
+            },
+            (CellValue::Number(n), PivotGrouping::Bins { start, size }) if size > 0.0 => {
+                let index = ((n - start) / size).floor();
+                let low = start + index * size;
+                // Whole-number bins don't share their edges
+                let high = if start.fract() == 0.0 && size.fract() == 0.0 {
+                    low + size - 1.0
+                } else {
+                    low + size
+                };
+                let label = format!("{}-{}", CellValue::Number(low).as_text(), CellValue::Number(high).as_text());
+                Item::Group(index as i64, label)
+            }
+            (CellValue::Number(n), _) => Item::Number(*n),
+            (CellValue::Text(text), _) if text.is_empty() => Item::Blank,
+            (CellValue::Text(text), _) => Item::Text(text.clone()),
+            (CellValue::Boolean(b), _) => Item::Boolean(*b),
+            (CellValue::Error(e), _) => Item::Error(e.clone()),
+            (CellValue::Empty, _) => Item::Blank,
+        }
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Item::Blank => BLANK.to_string(),
+            item => item.value().as_text(),
+        }
+    }
+
+    fn value(&self) -> CellValue {
+        match self {
+            Item::Number(n) => CellValue::Number(*n),
+            Item::Group(_, label) | Item::Text(label) => CellValue::Text(label.clone()),
+            Item::Boolean(b) => CellValue::Boolean(*b),
+            Item::Error(e) => CellValue::Error(e.clone()),
+            Item::Blank => CellValue::Text(BLANK.to_string()),
+        }
+    }
+
+    /// Numbers and groups first, then text, booleans, errors and blanks
+    fn rank(&self) -> u8 {
+        match self {
+            Item::Number(_) | Item::Group(..) => 0,
+            Item::Text(_) => 1,
+            Item::Boolean(_) => 2,
+            Item::Error(_) => 3,
+            Item::Blank => 4,
+        }
+    }
+}
+
+fn date_item(bucket: DateBucket, year: i32, month: u32, day: u32) -> Item {
+    let year_order = year as i64;
+    match bucket {
+        DateBucket::Year => Item::Group(year_order, year.to_string()),
+        DateBucket::Quarter => {
+            let quarter = (month - 1) / 3 + 1;
+            Item::Group(year_order * 4 + quarter as i64, format!("{} Qtr{}", year, quarter))
+        }
+        DateBucket::Month => Item::Group(
+            year_order * 12 + month as i64,
+            format!("{} {}", year, MONTHS[month as usize - 1]),
+        ),
+        DateBucket::Day => Item::Group(
+            (year_order * 12 + month as i64) * 31 + day as i64,
+            format!("{:04}-{:02}-{:02}", year, month, day),
+        ),
+    }
+}
+
+impl Ord for Item {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Item::Number(a), Item::Number(b)) => a.total_cmp(b),
+            (Item::Group(a, _), Item::Group(b, _)) => a.cmp(b),
+            (Item::Number(_), Item::Group(..)) => Ordering::Less,
+            (Item::Group(..), Item::Number(_)) => Ordering::Greater,
+            (Item::Text(a), Item::Text(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+            (Item::Boolean(a), Item::Boolean(b)) => a.cmp(b),
+            (Item::Error(a), Item::Error(b)) => a.to_string().cmp(&b.to_string()),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for Item {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Item {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Item {}
+
+/// Running aggregates of one value field over the records in a cell
+#[derive(Debug, Clone, Default)]
+struct Accumulator {
+    sum: CompensatedSum,
+    numbers: usize,
+    count: usize,
+    min: Option<f64>,
+    max: Option<f64>,
+    distinct: BTreeSet<Item>,
+    error: Option<CellError>,
+}
+
+impl Accumulator {
+    fn add(&mut self, value: &CellValue) {
+        if value.is_empty() {
+            return;
+        }
+        self.count += 1;
+        if let CellValue::Number(n) = value {
+            self.sum.add(*n);
+            self.numbers += 1;
+            self.min = Some(self.min.map_or(*n, |m| m.min(*n)));
+            self.max = Some(self.max.map_or(*n, |m| m.max(*n)));
+        }
+        if let CellValue::Error(e) = value {
+            self.error.get_or_insert_with(|| e.clone());
+        }
+        self.distinct.insert(Item::new(value, PivotGrouping::None, DateSystem::default()));
+    }
+
+    fn result(&self, aggregation: PivotAggregation) -> CellValue {
+        let numeric = matches!(
+            aggregation,
+            PivotAggregation::Sum | PivotAggregation::Average | PivotAggregation::Min | PivotAggregation::Max
+        );
+        if let (true, Some(error)) = (numeric, &self.error) {
+            return CellValue::Error(error.clone());
+        }
+        match aggregation {
+            PivotAggregation::Sum => CellValue::Number(self.sum.total()),
+            PivotAggregation::Count => CellValue::Number(self.count as f64),
+            PivotAggregation::Average if self.numbers == 0 => CellValue::Error(CellError::DivisionByZero),
+            PivotAggregation::Average => CellValue::Number(self.sum.total() / self.numbers as f64),
+            PivotAggregation::Min => CellValue::Number(self.min.unwrap_or(0.0)),
+            PivotAggregation::Max => CellValue::Number(self.max.unwrap_or(0.0)),
+            PivotAggregation::DistinctCount => CellValue::Number(self.distinct.len() as f64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sales() -> Sheet {
+        let mut sheet = Sheet::new("Data");
+        let rows = [
+            ["Region", "Product", "Date", "Amount"],
+            ["East", "Pen", "2024-01-15", "10"],
+            ["West", "Pen", "2024-02-10", "20"],
+            ["east", "Ink", "2024-04-01", "5"],
+            ["", "Ink", "2025-03-03", "7"],
+            ["West", "Ink", "2025-05-20", "3"],
+        ];
+        for (r, row) in rows.iter().enumerate() {
+            for (c, value) in row.iter().enumerate() {
+                sheet.set_cell_value(CellCoord::new(r as u32, c as u32), value);
+            }
+        }
+        sheet
+    }
+
+    fn pivot() -> PivotTable {
+        PivotTable::new(
+            "PivotTable1",
+            "Data",
+            CellRange::from_a1("A1:D6").unwrap(),
+            "Data",
+            CellCoord::new(0, 6),
+        )
+    }
+
+    fn text(s: &str) -> CellValue {
+        CellValue::Text(s.to_string())
+    }
+
+    fn num(n: f64) -> CellValue {
+        CellValue::Number(n)
+    }
+
+    #[test]
+    fn test_rows_and_columns() {
+        let pivot = pivot()
+            .with_rows(vec![PivotField::new(0)])
+            .with_columns(vec![PivotField::new(1)])
+            .with_values(vec![PivotValueField::new(3, PivotAggregation::Sum)]);
+        let grid = pivot.compute(&sales(), DateSystem::default()).unwrap();
+        let empty = CellValue::Empty;
+        assert_eq!(
+            grid,
+            vec![
+                vec![text("Product"), text("Ink"), text("Pen"), text("Grand Total")],
+                vec![text("Region"), text("Sum of Amount"), text("Sum of Amount"), text("Sum of Amount")],
+                vec![text("East"), num(5.0), num(10.0), num(15.0)],
+                vec![text("West"), num(3.0), num(20.0), num(23.0)],
+                vec![text("(blank)"), num(7.0), empty, num(7.0)],
+                vec![text("Grand Total"), num(15.0), num(30.0), num(45.0)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_aggregations_and_nested_rows() {
+        let mut average = PivotValueField::new(3, PivotAggregation::Average);
+        average.name = Some("Mean".to_string());
+        let pivot = pivot()
+            .with_rows(vec![PivotField::new(1), PivotField::new(0)])
+            .with_values(vec![
+                PivotValueField::new(0, PivotAggregation::Count),
+                PivotValueField::new(0, PivotAggregation::DistinctCount),
+                average,
+                PivotValueField::new(3, PivotAggregation::Max),
+            ]);
+        let grid = pivot.compute(&sales(), DateSystem::default()).unwrap();
+        assert_eq!(grid[0][2..], [text("Count of Region"), text("Distinct Count of Region"), text("Mean"), text("Max of Amount")]);
+        assert_eq!(grid[1], vec![text("Ink"), text("east"), num(1.0), num(1.0), num(5.0), num(5.0)]);
+        assert_eq!(grid[2][..2], [CellValue::Empty, text("West")]);
+        assert_eq!(grid[3][..2], [CellValue::Empty, text("(blank)")]);
+        assert_eq!(grid[3][2], num(0.0));
+        assert_eq!(grid[4][..2], [text("Pen"), text("East")]);
+        assert_eq!(grid[6], vec![text("Grand Total"), CellValue::Empty, num(4.0), num(2.0), num(9.0), num(20.0)]);
+    }
+
+    #[test]
+    fn test_grouping_and_filters() {
+        let pivot = pivot()
+            .with_rows(vec![PivotField::new(2).grouped(PivotGrouping::Date { bucket: DateBucket::Quarter })])
+            .with_values(vec![PivotValueField::new(3, PivotAggregation::Sum)])
+            .with_filter(PivotFilter {
+                field: PivotField::new(1),
+                items: vec!["ink".to_string()],
+            });
+        let grid = pivot.compute(&sales(), DateSystem::default()).unwrap();
+        assert_eq!(
+            grid,
+            vec![
+                vec![text("Date"), text("Sum of Amount")],
+                vec![text("2024 Qtr2"), num(5.0)],
+                vec![text("2025 Qtr1"), num(7.0)],
+                vec![text("2025 Qtr2"), num(3.0)],
+                vec![text("Grand Total"), num(15.0)],
+            ]
+        );
+
+        let mut pivot = pivot
+            .with_rows(vec![PivotField::new(3).grouped(PivotGrouping::Bins { start: 0.0, size: 10.0 })])
+            .with_values(vec![PivotValueField::new(3, PivotAggregation::Count)]);
+        pivot.filters.clear();
+        pivot.grand_totals = false;
+        let grid = pivot.compute(&sales(), DateSystem::default()).unwrap();
+        assert_eq!(grid[1..], [vec![text("0-9"), num(3.0)], vec![text("10-19"), num(1.0)], vec![text("20-29"), num(1.0)]]);
+    }
+
+    #[test]
+    fn test_values_only_and_validation() {
+        let minimum = pivot().with_values(vec![PivotValueField::new(3, PivotAggregation::Min)]);
+        let grid = minimum.compute(&sales(), DateSystem::default()).unwrap();
+        assert_eq!(grid, vec![vec![CellValue::Empty, text("Min of Amount")], vec![text("Grand Total"), num(3.0)]]);
+
+        assert!(matches!(pivot().validate(), Err(RusheetError::InvalidPivot(_))));
+        let outside = pivot().with_values(vec![PivotValueField::new(4, PivotAggregation::Sum)]);
+        assert!(matches!(outside.validate(), Err(RusheetError::InvalidPivot(_))));
+    }
+
+    #[test]
+    fn test_shift() {
+        let mut shifted = pivot()
+            .with_rows(vec![PivotField::new(0)])
+            .with_columns(vec![PivotField::new(1)])
+            .with_values(vec![PivotValueField::new(3, PivotAggregation::Sum)]);
+        shifted.output = CellRange::from_a1("G1:I4");
+
+        // Other sheets are untouched
+        assert!(shifted.shift("Other", Axis::Rows, 0, 5, true));
+        assert_eq!(shifted.source, CellRange::from_a1("A1:D6").unwrap());
+
+        assert!(shifted.shift("Data", Axis::Rows, 0, 2, true));
+        assert_eq!(shifted.source, CellRange::from_a1("A3:D8").unwrap());
+        assert_eq!(shifted.target, CellCoord::new(2, 6));
+        assert_eq!(shifted.output, CellRange::from_a1("G3:I6"));
+
+        // Deleting a source column drops its field and moves the rest
+        assert!(shifted.shift("Data", Axis::Cols, 1, 1, false));
+        assert_eq!(shifted.source, CellRange::from_a1("A3:C8").unwrap());
+        assert!(shifted.columns.is_empty());
+        assert_eq!(shifted.values[0].column, 2);
+        assert_eq!(shifted.target, CellCoord::new(2, 5));
+
+        assert!(!shifted.shift("Data", Axis::Rows, 2, 1, false));
+    }
+
+    #[test]
+    fn test_definition_json() {
+        let pivot = pivot()
+            .with_rows(vec![PivotField::new(2).grouped(PivotGrouping::Date { bucket: DateBucket::Month })])
+            .with_values(vec![PivotValueField::new(3, PivotAggregation::DistinctCount)]);
+        let json = serde_json::to_value(&pivot).unwrap();
+        assert_eq!(json["rows"][0]["grouping"], serde_json::json!({"type": "date", "bucket": "month"}));
+        assert_eq!(json["values"][0]["aggregation"], "distinct_count");
+        let parsed: PivotTable = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, pivot);
+    }
+}
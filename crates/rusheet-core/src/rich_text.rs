@@ -0,0 +1,394 @@
+//! Text with formatting that varies within a single cell
+//!
+//! A [`RichText`] is a list of runs, each a stretch of text with its own
+//! [`RunFormat`]. It is stored on the [`Cell`](crate::Cell) alongside the
+//! plain text value, which formulas and search keep working with.
+
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::format::Color;
+
+/// Character formatting for one run of rich text
+///
+/// Unset fields fall back to the cell's [`CellFormat`](crate::CellFormat).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunFormat {
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub bold: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub italic: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub underline: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub strikethrough: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_size: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_family: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_color: Option<Color>,
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+impl RunFormat {
+    /// Create a run format that inherits everything from the cell
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builder pattern: set bold
+    pub fn with_bold(mut self, bold: bool) -> Self {
+        self.bold = bold;
+        self
+    }
+
+    /// Builder pattern: set italic
+    pub fn with_italic(mut self, italic: bool) -> Self {
+        self.italic = italic;
+        self
+    }
+
+    /// Builder pattern: set text color
+    pub fn with_text_color(mut self, color: Color) -> Self {
+        self.text_color = Some(color);
+        self
+    }
+
+    /// Builder pattern: set font size
+    pub fn with_font_size(mut self, size: u8) -> Self {
+        self.font_size = Some(size);
+        self
+    }
+
+    /// Check if the run adds no formatting of its own
+    pub fn is_plain(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A stretch of text sharing one format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextRun {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "RunFormat::is_plain")]
+    pub format: RunFormat,
+}
+
+/// Text made of differently formatted runs
+///
+/// Adjacent runs with the same format are merged and empty runs dropped,
+/// so two rich texts that look the same compare equal.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RichText {
+    runs: Vec<TextRun>,
+}
+
+impl RichText {
+    /// Create an empty rich text
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builder pattern: append a run
+    pub fn with_run(mut self, text: impl Into<String>, format: RunFormat) -> Self {
+        self.push(text, format);
+        self
+    }
+
+    /// Append a run, merging it into the last run if the formats match
+    pub fn push(&mut self, text: impl Into<String>, format: RunFormat) {
+        let text = text.into();
+        if text.is_empty() {
+            return;
+        }
+        match self.runs.last_mut() {
+            Some(last) if last.format == format => last.text.push_str(&text),
+            _ => self.runs.push(TextRun { text, format }),
+        }
+    }
+
+    /// The runs in order
+    pub fn runs(&self) -> &[TextRun] {
+        &self.runs
+    }
+
+    /// The text without formatting
+    pub fn plain_text(&self) -> String {
+        self.runs.iter().map(|run| run.text.as_str()).collect()
+    }
+
+    /// Check if no run adds formatting of its own
+    pub fn is_plain(&self) -> bool {
+        self.runs.iter().all(|run| run.format.is_plain())
+    }
+
+    /// Format of the character at a byte offset in the plain text
+    ///
+    /// Offsets at or past the end get the last run's format.
+    pub fn format_at(&self, offset: usize) -> RunFormat {
+        let mut end = 0;
+        for run in &self.runs {
+            end += run.text.len();
+            if offset < end {
+                return run.format.clone();
+            }
+        }
+        self.runs.last().map(|run| run.format.clone()).unwrap_or_default()
+    }
+
+    /// Replace a byte range of the plain text, keeping the surrounding runs
+    ///
+    /// The replacement takes the format of the first replaced character.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusheet_core::{RichText, RunFormat};
+    ///
+    /// let bold = RunFormat::new().with_bold(true);
+    /// let mut text = RichText::new()
+    ///     .with_run("Total:", bold.clone())
+    ///     .with_run(" 42", RunFormat::new());
+    /// text.splice(0..5, "Sum");
+    ///
+    /// assert_eq!(text.plain_text(), "Sum: 42");
+    /// assert_eq!(text.runs()[0].text, "Sum:");
+    /// assert_eq!(text.runs()[0].format, bold);
+    /// ```
+    pub fn splice(&mut self, range: Range<usize>, replacement: &str) {
+        let format = self.format_at(range.start);
+        let mut result = RichText::new();
+        let mut start = 0;
+        for run in &self.runs {
+            let end = start + run.text.len();
+            if start < range.start {
+                result.push(&run.text[..range.start.min(end) - start], run.format.clone());
+            }
+            start = end;
+        }
+        result.push(replacement, format);
+        let mut start = 0;
+        for run in &self.runs {
+            let end = start + run.text.len();
+            if end > range.end {
+                result.push(&run.text[range.end.max(start) - start..], run.format.clone());
+            }
+            start = end;
+        }
+        *self = result;
+    }
+
+    /// Read the runs of an XLSX shared string (`<r>` elements of `<si>`)
+    ///
+    /// Reads bold, italic, underline, strikethrough, size, font name and RGB
+    /// color. A bare `<t>` without runs is read as one plain run. Returns
+    /// `None` if there is no text element at all.
+    pub fn from_ooxml(xml: &str) -> Option<Self> {
+        let mut text = RichText::new();
+        let mut format = RunFormat::new();
+        let mut found = false;
+        let mut rest = xml;
+        while let Some(open) = rest.find('<') {
+            rest = &rest[open + 1..];
+            let close = rest.find('>')?;
+            let tag = &rest[..close];
+            rest = &rest[close + 1..];
+
+            let self_closing = tag.ends_with('/');
+            let tag = tag.trim_end_matches('/');
+            let (name, attrs) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+            let enabled = xml_attr(attrs, "val").is_none_or(|v| v != "0" && v != "false");
+            match name {
+                "r" => format = RunFormat::new(),
+                "b" => format.bold = enabled,
+                "i" => format.italic = enabled,
+                "u" => format.underline = xml_attr(attrs, "val") != Some("none"),
+                "strike" => format.strikethrough = enabled,
+                "sz" => format.font_size = xml_attr(attrs, "val").and_then(parse_font_size),
+                "rFont" => format.font_family = xml_attr(attrs, "val").map(xml_unescape),
+                "color" => format.text_color = xml_attr(attrs, "rgb").and_then(parse_argb),
+                "t" if !self_closing => {
+                    let end = rest.find("</t>")?;
+                    text.push(xml_unescape(&rest[..end]), format.clone());
+                    rest = &rest[end + 4..];
+                    found = true;
+                }
+                "t" => found = true,
+                _ => {}
+            }
+        }
+        found.then_some(text)
+    }
+
+    /// Write the runs as the body of an XLSX shared string (`<si>`)
+    pub fn to_ooxml(&self) -> String {
+        let mut out = String::new();
+        for run in &self.runs {
+            out.push_str("<r>");
+            let format = &run.format;
+            if !format.is_plain() {
+                out.push_str("<rPr>");
+                if format.bold {
+                    out.push_str("<b/>");
+                }
+                if format.italic {
+                    out.push_str("<i/>");
+                }
+                if format.strikethrough {
+                    out.push_str("<strike/>");
+                }
+                if format.underline {
+                    out.push_str("<u/>");
+                }
+                if let Some(size) = format.font_size {
+                    out.push_str(&format!("<sz val=\"{}\"/>", size));
+                }
+                if let Some(color) = format.text_color {
+                    out.push_str(&format!(
+                        "<color rgb=\"{:02X}{:02X}{:02X}{:02X}\"/>",
+                        color.a, color.r, color.g, color.b
+                    ));
+                }
+                if let Some(family) = &format.font_family {
+                    out.push_str(&format!("<rFont val=\"{}\"/>", xml_escape(family)));
+                }
+                out.push_str("</rPr>");
+            }
+            out.push_str("<t xml:space=\"preserve\">");
+            out.push_str(&xml_escape(&run.text));
+            out.push_str("</t></r>");
+        }
+        out
+    }
+}
+
+/// Get the value of an attribute from the attribute part of a tag
+fn xml_attr<'a>(attrs: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = attrs;
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim();
+        let value = rest[eq + 1..].trim_start();
+        let quote = value.chars().next()?;
+        let end = value[1..].find(quote)?;
+        if key == name {
+            return Some(&value[1..1 + end]);
+        }
+        rest = &value[end + 2..];
+    }
+    None
+}
+
+/// Parse a point size, which XLSX allows to be fractional
+fn parse_font_size(val: &str) -> Option<u8> {
+    val.parse::<f64>().ok().map(|size| size.round().clamp(1.0, 255.0) as u8)
+}
+
+/// Parse an XLSX `AARRGGBB` color
+fn parse_argb(rgb: &str) -> Option<Color> {
+    if rgb.len() != 8 {
+        return None;
+    }
+    let byte = |i: usize| u8::from_str_radix(rgb.get(i..i + 2)?, 16).ok();
+    Some(Color::rgba(byte(2)?, byte(4)?, byte(6)?, byte(0)?))
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn xml_unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bold() -> RunFormat {
+        RunFormat::new().with_bold(true)
+    }
+
+    #[test]
+    fn test_push_merges_runs() {
+        let text = RichText::new()
+            .with_run("Total", bold())
+            .with_run(":", bold())
+            .with_run("", RunFormat::new())
+            .with_run(" 42", RunFormat::new());
+        assert_eq!(text.runs().len(), 2);
+        assert_eq!(text.plain_text(), "Total: 42");
+        assert!(!text.is_plain());
+        assert_eq!(text.format_at(0), bold());
+        assert_eq!(text.format_at(6), RunFormat::new());
+        assert_eq!(text.format_at(99), RunFormat::new());
+    }
+
+    #[test]
+    fn test_splice() {
+        let base = RichText::new()
+            .with_run("Total:", bold())
+            .with_run(" 42", RunFormat::new());
+
+        // Across a run boundary the replacement takes the first format
+        let mut text = base.clone();
+        text.splice(5..8, "=4");
+        assert_eq!(text, RichText::new().with_run("Total=4", bold()).with_run("2", RunFormat::new()));
+
+        // Inserting at the end continues the last run
+        let mut text = base.clone();
+        text.splice(9..9, "!");
+        assert_eq!(text.runs()[1].text, " 42!");
+
+        // Replacing everything leaves a single run
+        let mut text = base;
+        text.splice(0..9, "");
+        assert_eq!(text, RichText::new());
+    }
+
+    #[test]
+    fn test_ooxml_round_trip() {
+        let xml = concat!(
+            "<r><rPr><b/><sz val=\"11.5\"/><color rgb=\"FFFF0000\"/><rFont val=\"Calibri\"/></rPr>",
+            "<t>Total:</t></r>",
+            "<r><rPr><b val=\"0\"/><i/></rPr><t xml:space=\"preserve\"> 4 &amp; 2</t></r>",
+        );
+        let text = RichText::from_ooxml(xml).unwrap();
+        assert_eq!(text.plain_text(), "Total: 4 & 2");
+        let first = &text.runs()[0].format;
+        assert!(first.bold);
+        assert_eq!(first.font_size, Some(12));
+        assert_eq!(first.text_color, Some(Color::RED));
+        assert_eq!(first.font_family.as_deref(), Some("Calibri"));
+        assert_eq!(text.runs()[1].format, RunFormat::new().with_italic(true));
+
+        assert_eq!(RichText::from_ooxml(&text.to_ooxml()), Some(text));
+        assert_eq!(
+            RichText::from_ooxml("<t>plain</t>"),
+            Some(RichText::new().with_run("plain", RunFormat::new()))
+        );
+        assert_eq!(RichText::from_ooxml("no runs"), None);
+    }
+
+    #[test]
+    fn test_serialization() {
+        let text = RichText::new()
+            .with_run("Total:", bold())
+            .with_run(" 42", RunFormat::new());
+        let json = serde_json::to_string(&text).unwrap();
+        assert_eq!(json, r#"[{"text":"Total:","format":{"bold":true}},{"text":" 42"}]"#);
+        assert_eq!(serde_json::from_str::<RichText>(&json).unwrap(), text);
+    }
+}
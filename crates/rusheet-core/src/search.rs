@@ -2,6 +2,7 @@ use crate::{CellContent, Sheet, Workbook};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::ops::Range;
 
 /// Options for searching cells in a workbook
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -80,7 +81,20 @@ impl std::error::Error for SearchError {}
 trait Matcher {
     fn is_match(&self, text: &str) -> bool;
     fn find_match(&self, text: &str) -> Option<String>;
-    fn replace_all(&self, text: &str, replacement: &str) -> String;
+    /// Byte ranges of `text` to replace, each with its replacement
+    fn replacements(&self, text: &str, replacement: &str) -> Vec<(Range<usize>, String)>;
+
+    fn replace_all(&self, text: &str, replacement: &str) -> String {
+        let mut result = String::new();
+        let mut last_end = 0;
+        for (range, with) in self.replacements(text, replacement) {
+            result.push_str(&text[last_end..range.start]);
+            result.push_str(&with);
+            last_end = range.end;
+        }
+        result.push_str(&text[last_end..]);
+        result
+    }
 }
 
 /// Literal string matcher
@@ -125,24 +139,19 @@ impl Matcher for LiteralMatcher {
         }
     }
 
-    fn replace_all(&self, text: &str, replacement: &str) -> String {
-        if self.match_case {
-            text.replace(&self.query, replacement)
+    fn replacements(&self, text: &str, replacement: &str) -> Vec<(Range<usize>, String)> {
+        let indices: Vec<usize> = if self.match_case {
+            text.match_indices(&self.query).map(|(idx, _)| idx).collect()
         } else {
             // Case-insensitive replacement
             let lower_text = text.to_lowercase();
             let lower_query = self.query.to_lowercase();
-            let mut result = String::new();
-            let mut last_end = 0;
-
-            for (idx, _) in lower_text.match_indices(&lower_query) {
-                result.push_str(&text[last_end..idx]);
-                result.push_str(replacement);
-                last_end = idx + self.query.len();
-            }
-            result.push_str(&text[last_end..]);
-            result
-        }
+            lower_text.match_indices(&lower_query).map(|(idx, _)| idx).collect()
+        };
+        indices
+            .into_iter()
+            .map(|idx| (idx..idx + self.query.len(), replacement.to_string()))
+            .collect()
     }
 }
 
@@ -184,8 +193,15 @@ impl Matcher for RegexMatcher {
         }
     }
 
-    fn replace_all(&self, text: &str, replacement: &str) -> String {
-        self.regex.replace_all(text, replacement).to_string()
+    fn replacements(&self, text: &str, replacement: &str) -> Vec<(Range<usize>, String)> {
+        self.regex
+            .captures_iter(text)
+            .map(|caps| {
+                let mut with = String::new();
+                caps.expand(replacement, &mut with);
+                (caps.get_match().range(), with)
+            })
+            .collect()
     }
 }
 
@@ -358,10 +374,21 @@ impl SearchEngine {
 
         // Now perform the replacements
         for (coord, matched_text, search_text) in to_modify {
-            let new_text = matcher.replace_all(&search_text, &options.replacement);
-
-            // Update the cell
-            sheet.set_cell_value(coord, &new_text);
+            // Rich text is edited run by run so the formatting around matches survives
+            let rich_text = sheet.get_cell(coord).and_then(|c| c.text_runs()).cloned();
+            match rich_text {
+                Some(mut rich_text) => {
+                    let edits = matcher.replacements(&search_text, &options.replacement);
+                    for (range, with) in edits.into_iter().rev() {
+                        rich_text.splice(range, &with);
+                    }
+                    sheet.get_cell_mut(coord).set_rich_text(rich_text);
+                }
+                None => {
+                    let new_text = matcher.replace_all(&search_text, &options.replacement);
+                    sheet.set_cell_value(coord, &new_text);
+                }
+            }
 
             // Record the result
             let cell_value = sheet.get_cell(coord)
@@ -566,6 +593,39 @@ mod tests {
         assert_eq!(cell.content.display_value(), "Hi");
     }
 
+    #[test]
+    fn test_replace_keeps_rich_text_runs() {
+        use crate::{RichText, RunFormat};
+
+        let bold = RunFormat::new().with_bold(true);
+        let mut workbook = Workbook::new("Test");
+        let text = RichText::new()
+            .with_run("Total:", bold.clone())
+            .with_run(" 42 of 42", RunFormat::new());
+        workbook
+            .active_sheet_mut()
+            .set_cell(CellCoord::new(0, 0), Cell::rich(text));
+
+        let options = ReplaceOptions {
+            search: SearchOptions {
+                query: r"(\d+)".to_string(),
+                match_case: false,
+                match_entire_cell: false,
+                use_regex: true,
+                search_formulas: false,
+                sheet_indices: None,
+            },
+            replacement: "#$1".to_string(),
+        };
+        SearchEngine::replace(&mut workbook, &options).unwrap();
+
+        let cell = workbook.sheets[0].get_cell(CellCoord::new(0, 0)).unwrap();
+        let expected = RichText::new()
+            .with_run("Total:", bold)
+            .with_run(" #42 of #42", RunFormat::new());
+        assert_eq!(cell.text_runs(), Some(&expected));
+    }
+
     #[test]
     fn test_replace_regex() {
         let mut workbook = create_test_workbook();
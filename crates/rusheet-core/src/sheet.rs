@@ -1,16 +1,24 @@
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use crate::cell::{Cell, CellContent, CellValue};
 use crate::chunk::ChunkedGrid;
+use crate::comment::Comment;
 use crate::conditional_format::ConditionalFormattingRule;
-use crate::format::CellFormat;
+use crate::date::DateSystem;
+use crate::date_text::{parse_date_time_input, DateOrder};
+use crate::error::RusheetError;
+use crate::format::{BorderLine, BorderTarget, Borders, CellFormat};
+use crate::hyperlink::Hyperlink;
+use crate::outline::{Axis, Outline, OutlineGroup};
 use crate::range::{CellCoord, CellRange};
 use crate::spatial::SpatialIndex;
+use crate::state::{Pane, PaneKind, VisibleRange};
+use crate::table::{Table, TableSort, TotalsFunction};
 use crate::validation::{DataValidationRule, ValidationResult};
 
 /// Represents a filter applied to a column
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FilterState {
     pub col: u32,
     pub visible_values: HashSet<String>,
@@ -22,45 +30,45 @@ pub const DEFAULT_ROW_HEIGHT: f64 = 24.0;
 pub const DEFAULT_COL_WIDTH: f64 = 100.0;
 
 /// A single spreadsheet sheet with sparse storage for cells
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone)]
 pub struct Sheet {
     /// Sheet name (displayed in tab)
     pub name: String,
     /// Sparse storage for cells using chunked grid - only non-empty cells are stored
-    #[serde(default, with = "chunked_grid_serde")]
     cells: ChunkedGrid<Cell>,
     /// Custom row heights (row index -> height in pixels) - kept for serialization
-    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub row_heights: HashMap<u32, f64>,
     /// Custom column widths (column index -> width in pixels) - kept for serialization
-    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub col_widths: HashMap<u32, f64>,
     /// Default height for rows without custom height
-    #[serde(default = "default_row_height")]
     pub default_row_height: f64,
     /// Default width for columns without custom width
-    #[serde(default = "default_col_width")]
     pub default_col_width: f64,
     /// Number of frozen rows (scroll lock)
-    #[serde(default)]
     pub frozen_rows: u32,
     /// Number of frozen columns (scroll lock)
-    #[serde(default)]
     pub frozen_cols: u32,
     /// Merged cell ranges - each range represents a merged area with top-left as master cell
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub merged_ranges: Vec<CellRange>,
     /// Active column filters
-    #[serde(default)]
     pub active_filters: Vec<FilterState>,
     /// Conditional formatting rules
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub conditional_formatting: Vec<ConditionalFormattingRule>,
     /// Data validation rules
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub data_validation: Vec<DataValidationRule>,
+    /// Rows hidden by the user (filters track their hidden rows separately)
+    hidden_rows: BTreeSet<u32>,
+    /// Columns hidden by the user
+    hidden_cols: BTreeSet<u32>,
+    /// Row groups
+    row_outline: Outline,
+    /// Column groups
+    col_outline: Outline,
+    /// Structured tables
+    tables: Vec<Table>,
+    /// Rows hidden by the active filters
+    filtered_rows: BTreeSet<u32>,
     /// Spatial index for O(log N) position lookups (rebuilt on deserialize)
-    #[serde(skip, default = "SpatialIndex::new")]
     spatial: SpatialIndex,
 }
 
@@ -105,34 +113,91 @@ fn compare_cell_values(a: &Option<CellValue>, b: &Option<CellValue>) -> std::cmp
     }
 }
 
-/// Custom serialization for ChunkedGrid to maintain backward compatibility
-mod chunked_grid_serde {
-    use super::*;
-    use serde::ser::SerializeMap;
-    use serde::{de, Deserializer, Serializer};
-    use std::fmt;
+/// A sheet as it's saved
+#[derive(Serialize)]
+struct SheetRef<'a> {
+    name: &'a str,
+    cells: SavedCells<'a>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    row_heights: &'a HashMap<u32, f64>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    col_widths: &'a HashMap<u32, f64>,
+    default_row_height: f64,
+    default_col_width: f64,
+    frozen_rows: u32,
+    frozen_cols: u32,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    merged_ranges: &'a [CellRange],
+    active_filters: &'a [FilterState],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    conditional_formatting: &'a [ConditionalFormattingRule],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    data_validation: &'a [DataValidationRule],
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    hidden_rows: &'a BTreeSet<u32>,
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    hidden_cols: &'a BTreeSet<u32>,
+    #[serde(skip_serializing_if = "Outline::is_empty")]
+    row_outline: &'a Outline,
+    #[serde(skip_serializing_if = "Outline::is_empty")]
+    col_outline: &'a Outline,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    tables: &'a [Table],
+}
 
-    pub fn serialize<S>(grid: &ChunkedGrid<Cell>, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        // Serialize as a map with stringified tuple keys "row,col" for JSON compatibility
-        let mut map = serializer.serialize_map(Some(grid.len()))?;
-        for ((row, col), cell) in grid.iter() {
+impl<'a> SheetRef<'a> {
+    fn new(sheet: &'a Sheet) -> Self {
+        Self {
+            name: &sheet.name,
+            cells: SavedCells(&sheet.cells),
+            row_heights: &sheet.row_heights,
+            col_widths: &sheet.col_widths,
+            default_row_height: sheet.default_row_height,
+            default_col_width: sheet.default_col_width,
+            frozen_rows: sheet.frozen_rows,
+            frozen_cols: sheet.frozen_cols,
+            merged_ranges: &sheet.merged_ranges,
+            active_filters: &sheet.active_filters,
+            conditional_formatting: &sheet.conditional_formatting,
+            data_validation: &sheet.data_validation,
+            hidden_rows: &sheet.hidden_rows,
+            hidden_cols: &sheet.hidden_cols,
+            row_outline: &sheet.row_outline,
+            col_outline: &sheet.col_outline,
+            tables: &sheet.tables,
+        }
+    }
+}
+
+/// Cells saved as a map with "row,col" keys, for JSON compatibility
+struct SavedCells<'a>(&'a ChunkedGrid<Cell>);
+
+impl Serialize for SavedCells<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for ((row, col), cell) in self.0.iter() {
             let key = format!("{},{}", row, col);
             map.serialize_entry(&key, cell)?;
         }
         map.end()
     }
+}
 
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<ChunkedGrid<Cell>, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        struct GridVisitor;
+/// Saved cells with their coordinates
+#[derive(Default)]
+struct StoredCells(Vec<(u32, u32, Cell)>);
+
+impl<'de> Deserialize<'de> for StoredCells {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de;
+        use std::fmt;
+
+        struct CellsVisitor;
 
-        impl<'de> de::Visitor<'de> for GridVisitor {
-            type Value = ChunkedGrid<Cell>;
+        impl<'de> de::Visitor<'de> for CellsVisitor {
+            type Value = StoredCells;
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
                 formatter.write_str("a map with coordinate keys")
@@ -142,8 +207,7 @@ mod chunked_grid_serde {
             where
                 M: de::MapAccess<'de>,
             {
-                let mut grid = ChunkedGrid::new();
-
+                let mut cells = Vec::with_capacity(map.size_hint().unwrap_or(0));
                 while let Some(key) = map.next_key::<String>()? {
                     let cell: Cell = map.next_value()?;
 
@@ -151,16 +215,93 @@ mod chunked_grid_serde {
                     let parts: Vec<&str> = key.split(',').collect();
                     if parts.len() == 2 {
                         if let (Ok(row), Ok(col)) = (parts[0].parse::<u32>(), parts[1].parse::<u32>()) {
-                            grid.insert(row as usize, col as usize, cell);
+                            cells.push((row, col, cell));
                         }
                     }
                 }
-
-                Ok(grid)
+                Ok(StoredCells(cells))
             }
         }
 
-        deserializer.deserialize_map(GridVisitor)
+        deserializer.deserialize_map(CellsVisitor)
+    }
+}
+
+/// A saved sheet, read before its spatial index is rebuilt
+#[derive(Deserialize)]
+struct SheetData {
+    name: String,
+    #[serde(default)]
+    cells: StoredCells,
+    #[serde(default)]
+    row_heights: HashMap<u32, f64>,
+    #[serde(default)]
+    col_widths: HashMap<u32, f64>,
+    #[serde(default = "default_row_height")]
+    default_row_height: f64,
+    #[serde(default = "default_col_width")]
+    default_col_width: f64,
+    #[serde(default)]
+    frozen_rows: u32,
+    #[serde(default)]
+    frozen_cols: u32,
+    #[serde(default)]
+    merged_ranges: Vec<CellRange>,
+    #[serde(default)]
+    active_filters: Vec<FilterState>,
+    #[serde(default)]
+    conditional_formatting: Vec<ConditionalFormattingRule>,
+    #[serde(default)]
+    data_validation: Vec<DataValidationRule>,
+    #[serde(default)]
+    hidden_rows: BTreeSet<u32>,
+    #[serde(default)]
+    hidden_cols: BTreeSet<u32>,
+    #[serde(default)]
+    row_outline: Outline,
+    #[serde(default)]
+    col_outline: Outline,
+    #[serde(default)]
+    tables: Vec<Table>,
+}
+
+impl SheetData {
+    fn into_sheet(self) -> Sheet {
+        let mut cells = ChunkedGrid::new();
+        for (row, col, cell) in self.cells.0 {
+            cells.insert(row as usize, col as usize, cell);
+        }
+        let mut sheet = Sheet {
+            name: self.name,
+            cells,
+            row_heights: self.row_heights,
+            col_widths: self.col_widths,
+            default_row_height: self.default_row_height,
+            default_col_width: self.default_col_width,
+            frozen_rows: self.frozen_rows,
+            frozen_cols: self.frozen_cols,
+            merged_ranges: self.merged_ranges,
+            active_filters: self.active_filters,
+            conditional_formatting: self.conditional_formatting,
+            data_validation: self.data_validation,
+            hidden_rows: self.hidden_rows,
+            hidden_cols: self.hidden_cols,
+            row_outline: self.row_outline,
+            col_outline: self.col_outline,
+            tables: self.tables,
+            filtered_rows: BTreeSet::new(),
+            spatial: SpatialIndex::new(),
+        };
+
+        // Rebuild the spatial index from the deserialized data
+        sheet.rebuild_spatial_index();
+        sheet
+    }
+}
+
+impl Serialize for Sheet {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SheetRef::new(self).serialize(serializer)
     }
 }
 
@@ -185,6 +326,12 @@ impl Sheet {
             active_filters: Vec::new(),
             conditional_formatting: Vec::new(),
             data_validation: Vec::new(),
+            hidden_rows: BTreeSet::new(),
+            hidden_cols: BTreeSet::new(),
+            row_outline: Outline::new(),
+            col_outline: Outline::new(),
+            tables: Vec::new(),
+            filtered_rows: BTreeSet::new(),
             spatial: SpatialIndex::new(),
         }
     }
@@ -222,10 +369,17 @@ impl Sheet {
     }
 
     /// Set the value of a cell (parses input to determine type)
+    ///
+    /// Dates and times are read month-first in the 1900 date system, and
+    /// give the cell a matching number format unless it has a date or time
+    /// format of its own (a sheet doesn't see the workbook's named styles).
     pub fn set_cell_value(&mut self, coord: CellCoord, value: &str) {
-        let content = parse_cell_input(value);
+        let (content, number_format) =
+            parse_cell_input_with(value, DateOrder::default(), DateSystem::default());
         let cell = self.get_cell_mut(coord);
         cell.content = content;
+        cell.rich_text = None;
+        cell.assign_input_format(number_format, &[]);
 
         // Clean up if cell became empty
         if cell.is_empty() {
@@ -377,6 +531,9 @@ impl Sheet {
         for (&col, &width) in &self.col_widths {
             self.spatial.set_col_width(col as usize, width);
         }
+
+        self.sync_hidden(Axis::Rows);
+        self.sync_hidden(Axis::Cols);
     }
 
     /// Helper to shift HashMap keys by a delta (positive or negative).
@@ -434,6 +591,9 @@ impl Sheet {
             self.spatial.set_row_height(row as usize, height);
         }
 
+        self.shift_visibility(Axis::Rows, at_row, count, true);
+        self.shift_tables(Axis::Rows, at_row, count, true);
+
         // Convert shifts to CellCoord
         shifts
             .into_iter()
@@ -482,6 +642,9 @@ impl Sheet {
             self.spatial.set_row_height(row as usize, height);
         }
 
+        self.shift_visibility(Axis::Rows, at_row, count, false);
+        self.shift_tables(Axis::Rows, at_row, count, false);
+
         // Convert deleted cells to CellCoord
         deleted_cells
             .into_iter()
@@ -514,6 +677,9 @@ impl Sheet {
             self.spatial.set_col_width(col as usize, width);
         }
 
+        self.shift_visibility(Axis::Cols, at_col, count, true);
+        self.shift_tables(Axis::Cols, at_col, count, true);
+
         // Convert shifts to CellCoord
         shifts
             .into_iter()
@@ -555,7 +721,8 @@ impl Sheet {
         let num_rows = (end_row - start_row + 1) as usize;
 
         // Collect row data: (original_row_index, sort_value, all cells in row)
-        let mut row_data: Vec<(u32, Option<CellValue>, Vec<(u32, Cell)>)> = Vec::with_capacity(num_rows);
+        type RowData = (u32, Option<CellValue>, Vec<(u32, Cell)>);
+        let mut row_data: Vec<RowData> = Vec::with_capacity(num_rows);
 
         for row in start_row..=end_row {
             // Get sort value for this row
@@ -708,6 +875,9 @@ impl Sheet {
             self.spatial.set_col_width(col as usize, width);
         }
 
+        self.shift_visibility(Axis::Cols, at_col, count, false);
+        self.shift_tables(Axis::Cols, at_col, count, false);
+
         // Convert deleted cells to CellCoord
         deleted_cells
             .into_iter()
@@ -802,32 +972,739 @@ impl Sheet {
         self.merged_ranges.iter().any(|r| r.intersects(range))
     }
 
+    // =========================================================================
+    // Comments
+    // =========================================================================
+
+    /// Get the comment on a cell
+    pub fn get_comment(&self, coord: CellCoord) -> Option<&Comment> {
+        self.get_cell(coord)?.comment.as_ref()
+    }
+
+    /// Attach, replace or (with `None`) remove a cell's comment
+    /// Returns the previous comment
+    pub fn set_comment(&mut self, coord: CellCoord, comment: Option<Comment>) -> Option<Comment> {
+        if comment.is_none() && self.get_cell(coord).is_none() {
+            return None;
+        }
+        let cell = self.get_cell_mut(coord);
+        let old = std::mem::replace(&mut cell.comment, comment);
+        if cell.is_empty() {
+            self.remove_cell(coord);
+        }
+        old
+    }
+
+    /// All comments on the sheet, in row-major order
+    pub fn comments(&self) -> Vec<(CellCoord, &Comment)> {
+        let mut comments: Vec<(CellCoord, &Comment)> = self
+            .cells
+            .iter()
+            .filter_map(|((row, col), cell)| {
+                let coord = CellCoord::new(row as u32, col as u32);
+                cell.comment.as_ref().map(|comment| (coord, comment))
+            })
+            .collect();
+        comments.sort_by_key(|(coord, _)| (coord.row, coord.col));
+        comments
+    }
+
+    // =========================================================================
+    // Hyperlinks
+    // =========================================================================
+
+    /// Get the hyperlink on a cell
+    pub fn get_hyperlink(&self, coord: CellCoord) -> Option<&Hyperlink> {
+        self.get_cell(coord)?.hyperlink.as_ref()
+    }
+
+    /// Attach, replace or (with `None`) remove a cell's hyperlink
+    /// Returns the previous hyperlink
+    pub fn set_hyperlink(&mut self, coord: CellCoord, link: Option<Hyperlink>) -> Option<Hyperlink> {
+        if link.is_none() && self.get_cell(coord).is_none() {
+            return None;
+        }
+        let cell = self.get_cell_mut(coord);
+        let old = std::mem::replace(&mut cell.hyperlink, link);
+        if cell.is_empty() {
+            self.remove_cell(coord);
+        }
+        old
+    }
+
+    /// All hyperlinks on the sheet, in row-major order
+    pub fn hyperlinks(&self) -> Vec<(CellCoord, &Hyperlink)> {
+        let mut links: Vec<(CellCoord, &Hyperlink)> = self
+            .cells
+            .iter()
+            .filter_map(|((row, col), cell)| {
+                let coord = CellCoord::new(row as u32, col as u32);
+                cell.hyperlink.as_ref().map(|link| (coord, link))
+            })
+            .collect();
+        links.sort_by_key(|(coord, _)| (coord.row, coord.col));
+        links
+    }
+
+    // =========================================================================
+    // Tables
+    // =========================================================================
+
+    /// Get the sheet's tables
+    pub fn tables(&self) -> &[Table] {
+        &self.tables
+    }
+
+    /// Find a table by name, ignoring case
+    pub fn table(&self, name: &str) -> Option<&Table> {
+        self.table_index(name).map(|i| &self.tables[i])
+    }
+
+    /// Find a table by name for editing its options
+    pub fn table_mut(&mut self, name: &str) -> Option<&mut Table> {
+        self.table_index(name).map(|i| &mut self.tables[i])
+    }
+
+    fn table_index(&self, name: &str) -> Option<usize> {
+        self.tables
+            .iter()
+            .position(|t| t.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Get the table containing a cell
+    pub fn table_at(&self, coord: CellCoord) -> Option<&Table> {
+        self.tables.iter().find(|t| t.range.contains(coord))
+    }
+
+    /// Replace the sheet's tables wholesale, for undo
+    pub fn restore_tables(&mut self, tables: Vec<Table>) {
+        self.tables = tables;
+    }
+
+    /// Check that `range` can hold a table without overlapping another
+    /// table (other than `except`) or a merged range
+    fn check_table_range(&self, range: &CellRange, except: Option<usize>) -> Result<(), RusheetError> {
+        if range.end.row >= Self::MAX_ROWS || range.end.col >= Self::MAX_COLS {
+            return Err(RusheetError::RangeOutOfBounds);
+        }
+        let overlaps_table = self
+            .tables
+            .iter()
+            .enumerate()
+            .any(|(i, t)| Some(i) != except && t.range.intersects(range));
+        if overlaps_table || self.would_overlap_merge(range) {
+            return Err(RusheetError::TableOverlap);
+        }
+        Ok(())
+    }
+
+    /// Add a table over its range
+    ///
+    /// The first row becomes the header row; blank headers are named
+    /// `Column1`, `Column2`, ... and duplicates get a number appended. A
+    /// table with no data rows gains an empty one. Names are only checked
+    /// against this sheet's tables: use
+    /// [`Workbook::add_table`](crate::Workbook::add_table) to check the whole workbook.
+    pub fn add_table(&mut self, mut table: Table) -> Result<(), RusheetError> {
+        if !Table::is_valid_name(&table.name) {
+            return Err(RusheetError::InvalidTableName(table.name));
+        }
+        if self.table(&table.name).is_some() {
+            return Err(RusheetError::TableNameExists(table.name));
+        }
+        if table.range.row_count() < 2 + u32::from(table.totals_row) {
+            table.range.end.row = table.range.start.row + 1 + u32::from(table.totals_row);
+        }
+        self.check_table_range(&table.range, None)?;
+
+        self.tables.push(table);
+        self.fill_table_headers(self.tables.len() - 1);
+        Ok(())
+    }
+
+    /// Remove a table, leaving its cells as a plain range
+    ///
+    /// Rows hidden by the table's filters are shown again.
+    pub fn remove_table(&mut self, name: &str) -> Option<Table> {
+        let table = self.tables.remove(self.table_index(name)?);
+        if !table.filters.is_empty() {
+            self.refresh_filters();
+        }
+        Some(table)
+    }
+
+    /// Move or resize a table
+    ///
+    /// The header row must stay on the same row and the new range must
+    /// overlap the old one. A totals row moves to the new last row, and
+    /// filters on columns that are no longer in the table are dropped.
+    pub fn resize_table(&mut self, name: &str, range: CellRange) -> Result<(), RusheetError> {
+        let index = self
+            .table_index(name)
+            .ok_or_else(|| RusheetError::TableNotFound(name.to_string()))?;
+        let old = self.tables[index].clone();
+        if range.start.row != old.header_row()
+            || !range.intersects(&old.range)
+            || range.row_count() < 1 + u32::from(old.totals_row)
+        {
+            return Err(RusheetError::RangeOutOfBounds);
+        }
+        self.check_table_range(&range, Some(index))?;
+
+        if old.totals_row && range.end.row != old.range.end.row {
+            let totals: Vec<(u32, Option<Cell>)> = (old.range.start.col..=old.range.end.col)
+                .map(|col| {
+                    let coord = CellCoord::new(old.range.end.row, col);
+                    let cell = self.get_cell(coord).cloned();
+                    self.remove_cell(coord);
+                    (col, cell)
+                })
+                .collect();
+            for (col, cell) in totals {
+                if range.start.col <= col && col <= range.end.col {
+                    if let Some(cell) = cell {
+                        self.set_cell(CellCoord::new(range.end.row, col), cell);
+                    }
+                }
+            }
+        }
+
+        let moved = |col: u32| -> Option<u32> {
+            let col = old.range.start.col + col;
+            (range.start.col <= col && col <= range.end.col).then(|| col - range.start.col)
+        };
+        let table = &mut self.tables[index];
+        table.range = range;
+        table.filters = std::mem::take(&mut table.filters)
+            .into_iter()
+            .filter_map(|f| Some(FilterState { col: moved(f.col)?, ..f }))
+            .collect();
+        table.sort = table
+            .sort
+            .and_then(|s| Some(TableSort { column: moved(s.column)?, ..s }));
+        self.fill_table_headers(index);
+        if old.filters != self.tables[index].filters {
+            self.refresh_filters();
+        }
+        Ok(())
+    }
+
+    /// Show or hide a table's totals row
+    ///
+    /// Showing it takes over the empty row below the table, labelled "Total"
+    /// in the first column with a sum (or count, for text) of the last.
+    /// Hiding it clears that row.
+    pub fn set_table_totals_row(&mut self, name: &str, show: bool) -> Result<(), RusheetError> {
+        let index = self
+            .table_index(name)
+            .ok_or_else(|| RusheetError::TableNotFound(name.to_string()))?;
+        let table = self.tables[index].clone();
+        if table.totals_row == show {
+            return Ok(());
+        }
+        let (first, last) = (table.range.start.col, table.range.end.col);
+
+        if !show {
+            for col in first..=last {
+                self.remove_cell(CellCoord::new(table.range.end.row, col));
+            }
+            let table = &mut self.tables[index];
+            table.totals_row = false;
+            table.range.end.row -= 1;
+            return Ok(());
+        }
+
+        let row = table.range.end.row + 1;
+        let below = CellRange::new(CellCoord::new(row, first), CellCoord::new(row, last));
+        self.check_table_range(&below, Some(index))?;
+        if below.iter().any(|coord| self.get_cell(coord).is_some_and(|c| !c.is_empty())) {
+            return Err(RusheetError::TableOverlap);
+        }
+
+        {
+            let table = &mut self.tables[index];
+            table.totals_row = true;
+            table.range.end.row = row;
+        }
+        self.set_cell_value(CellCoord::new(row, first), "Total");
+        if last > first {
+            let numeric = table.data_range().is_some_and(|data| {
+                (data.start.row..=data.end.row).any(|r| {
+                    self.get_cell_value(CellCoord::new(r, last)).as_number().is_some()
+                })
+            });
+            let function = if numeric {
+                TotalsFunction::Sum
+            } else {
+                TotalsFunction::Count
+            };
+            self.set_table_total(name, last - first, Some(function))?;
+        }
+        Ok(())
+    }
+
+    /// Set the totals row function of a table column, or clear its cell with `None`
+    pub fn set_table_total(
+        &mut self,
+        name: &str,
+        column: u32,
+        function: Option<TotalsFunction>,
+    ) -> Result<(), RusheetError> {
+        let table = self
+            .table(name)
+            .ok_or_else(|| RusheetError::TableNotFound(name.to_string()))?;
+        let row = table.totals_row_index().ok_or(RusheetError::RangeOutOfBounds)?;
+        if column >= table.col_count() {
+            return Err(RusheetError::RangeOutOfBounds);
+        }
+        let coord = CellCoord::new(row, table.range.start.col + column);
+        match function {
+            Some(function) => {
+                let header = table.column_names(self).swap_remove(column as usize);
+                let formula = function.formula(&table.name, &header);
+                self.get_cell_mut(coord).content = CellContent::Formula {
+                    expression: formula,
+                    cached_value: CellValue::Empty,
+                };
+            }
+            None => self.remove_cell(coord),
+        }
+        Ok(())
+    }
+
+    /// Sort a table's data rows by one of its columns
+    ///
+    /// Returns the row mapping from [`Sheet::sort_range`], for undo.
+    pub fn sort_table(
+        &mut self,
+        name: &str,
+        column: u32,
+        ascending: bool,
+    ) -> Result<Vec<(u32, u32)>, RusheetError> {
+        let index = self
+            .table_index(name)
+            .ok_or_else(|| RusheetError::TableNotFound(name.to_string()))?;
+        let table = &mut self.tables[index];
+        if column >= table.col_count() {
+            return Err(RusheetError::RangeOutOfBounds);
+        }
+        table.sort = Some(TableSort { column, ascending });
+        let Some(data) = table.data_range() else {
+            return Ok(Vec::new());
+        };
+        Ok(self.sort_range(
+            data.start.row,
+            data.end.row,
+            data.start.col,
+            data.end.col,
+            data.start.col + column,
+            ascending,
+        ))
+    }
+
+    /// Filter a table column to the given values, or clear its filter with `None`
+    ///
+    /// Returns the rows shown or hidden by the change.
+    pub fn filter_table(
+        &mut self,
+        name: &str,
+        column: u32,
+        visible_values: Option<HashSet<String>>,
+    ) -> Result<Vec<u32>, RusheetError> {
+        let index = self
+            .table_index(name)
+            .ok_or_else(|| RusheetError::TableNotFound(name.to_string()))?;
+        let table = &mut self.tables[index];
+        if column >= table.col_count() {
+            return Err(RusheetError::RangeOutOfBounds);
+        }
+        table.filters.retain(|f| f.col != column);
+        if let Some(visible_values) = visible_values {
+            table.filters.push(FilterState { col: column, visible_values });
+        }
+
+        let before = self.filtered_rows.clone();
+        self.refresh_filters();
+        Ok(before.symmetric_difference(&self.filtered_rows).copied().collect())
+    }
+
+    /// Hide the data rows that a table's filters exclude, returning them
+    fn apply_table_filters(&mut self, index: usize) -> Vec<u32> {
+        let table = &self.tables[index];
+        let Some(data) = table.data_range() else {
+            return Vec::new();
+        };
+        let mut rows: Vec<u32> = table
+            .filters
+            .iter()
+            .flat_map(|f| {
+                self.rows_outside_filter(
+                    data.start.col + f.col,
+                    &f.visible_values,
+                    data.start.row..data.end.row + 1,
+                )
+            })
+            .collect();
+        rows.sort_unstable();
+        rows.dedup();
+        self.hide_rows(&rows);
+        rows
+    }
+
+    /// Recompute the rows hidden by sheet and table filters
+    fn refresh_filters(&mut self) {
+        let hidden = self.get_filtered_rows();
+        self.show_rows(&hidden);
+        self.reapply_all_filters();
+    }
+
+    /// Name blank or duplicate headers of a table
+    fn fill_table_headers(&mut self, index: usize) {
+        let table = &self.tables[index];
+        let (row, first) = (table.header_row(), table.range.start.col);
+        let names = table.column_names(self);
+
+        let free = |candidate: &String, taken: &HashSet<String>| {
+            !taken.contains(&candidate.to_lowercase())
+                && !names.iter().any(|other| other.eq_ignore_ascii_case(candidate))
+        };
+        let mut taken: HashSet<String> = HashSet::new();
+        for (i, name) in names.iter().enumerate() {
+            let unique = if name.is_empty() {
+                (1..).map(|n| format!("Column{}", n)).find(|c| free(c, &taken))
+            } else if taken.contains(&name.to_lowercase()) {
+                (2..).map(|n| format!("{}{}", name, n)).find(|c| free(c, &taken))
+            } else {
+                Some(name.clone())
+            }
+            .expect("column numbers are unbounded");
+            taken.insert(unique.to_lowercase());
+            if unique != *name {
+                let cell = self.get_cell_mut(CellCoord::new(row, first + i as u32));
+                cell.content = CellContent::Value {
+                    value: CellValue::Text(unique),
+                    original_input: None,
+                };
+            }
+        }
+    }
+
+    /// Shift, grow or drop tables after inserting or deleting rows or columns
+    fn shift_tables(&mut self, axis: Axis, at: u32, count: u32, insert: bool) {
+        let had_filters = self.tables.iter().any(|t| !t.filters.is_empty());
+        self.tables.retain_mut(|table| table.shift(axis, at, count, insert));
+        if axis == Axis::Cols && insert {
+            for index in 0..self.tables.len() {
+                self.fill_table_headers(index);
+            }
+        }
+        if had_filters && !insert {
+            self.refresh_filters();
+        }
+    }
+
+    /// The table that typing a value at `coord` would grow, with its new range
+    ///
+    /// A table with auto-expansion grows by a row when typing directly below
+    /// it (unless it shows a totals row) and by a column when typing directly
+    /// to its right. Growth that would run into another table or a merged
+    /// range doesn't happen.
+    pub fn table_expansion(&self, coord: CellCoord) -> Option<(String, CellRange)> {
+        if self.table_at(coord).is_some() {
+            return None;
+        }
+        self.tables.iter().enumerate().find_map(|(index, table)| {
+            let range = table.range;
+            let mut grown = range;
+            if coord.row == range.end.row + 1
+                && !table.totals_row
+                && (range.start.col..=range.end.col).contains(&coord.col)
+            {
+                grown.end.row += 1;
+            } else if coord.col == range.end.col + 1
+                && (range.start.row..=range.end.row).contains(&coord.row)
+            {
+                grown.end.col += 1;
+            } else {
+                return None;
+            }
+            let fits = table.auto_expand && self.check_table_range(&grown, Some(index)).is_ok();
+            fits.then(|| (table.name.clone(), grown))
+        })
+    }
+
+    // =========================================================================
+    // Freeze Panes
+    // =========================================================================
+
+    /// Freeze the first `rows` rows and `cols` columns (0 unfreezes)
+    pub fn set_frozen_panes(&mut self, rows: u32, cols: u32) {
+        self.frozen_rows = rows.min(Self::MAX_ROWS);
+        self.frozen_cols = cols.min(Self::MAX_COLS);
+    }
+
+    /// Pixel size (width, height) of the frozen columns and rows
+    pub fn frozen_size(&self) -> (f64, f64) {
+        (self.col_x_position(self.frozen_cols), self.row_y_position(self.frozen_rows))
+    }
+
+    /// Split a `width` x `height` viewport scrolled by (`scroll_x`, `scroll_y`)
+    /// into its frozen and scrolling panes
+    ///
+    /// The scroll offsets apply to the scrolling pane only: at (0, 0) the
+    /// first unfrozen row and column sit right after the frozen ones.
+    pub fn panes(&self, scroll_x: f64, scroll_y: f64, width: f64, height: f64) -> Vec<Pane> {
+        let (frozen_w, frozen_h) = self.frozen_size();
+        // A row ending exactly at the pane's top edge is not on screen
+        let mut first_row = self.row_at_y(frozen_h + scroll_y).max(self.frozen_rows);
+        if self.row_y_position(first_row + 1) <= frozen_h + scroll_y {
+            first_row += 1;
+        }
+        let mut first_col = self.col_at_x(frozen_w + scroll_x).max(self.frozen_cols);
+        if self.col_x_position(first_col + 1) <= frozen_w + scroll_x {
+            first_col += 1;
+        }
+        let last_row = self.row_at_y(scroll_y + height).max(first_row);
+        let last_col = self.col_at_x(scroll_x + width).max(first_col);
+
+        let rows = [
+            (self.frozen_rows > 0).then(|| (0, self.frozen_rows - 1, 0.0, 0.0)),
+            Some((first_row, last_row, frozen_h, -scroll_y)),
+        ];
+        let cols = [
+            (self.frozen_cols > 0).then(|| (0, self.frozen_cols - 1, 0.0, 0.0)),
+            Some((first_col, last_col, frozen_w, -scroll_x)),
+        ];
+        let kinds = [[PaneKind::Corner, PaneKind::Top], [PaneKind::Left, PaneKind::Main]];
+
+        let mut panes = Vec::new();
+        for (i, row_span) in rows.iter().enumerate() {
+            for (j, col_span) in cols.iter().enumerate() {
+                if let (Some((r0, r1, y, offset_y)), Some((c0, c1, x, offset_x))) = (row_span, col_span) {
+                    panes.push(Pane {
+                        kind: kinds[i][j],
+                        range: VisibleRange::new(*r0 as usize, *r1 as usize, *c0 as usize, *c1 as usize),
+                        x: *x,
+                        y: *y,
+                        offset_x: *offset_x,
+                        offset_y: *offset_y,
+                    });
+                }
+            }
+        }
+        panes
+    }
+
+    /// Find the cell under a viewport point, given the scrolling pane's offsets
+    pub fn cell_at_point(&self, x: f64, y: f64, scroll_x: f64, scroll_y: f64) -> CellCoord {
+        let (frozen_w, frozen_h) = self.frozen_size();
+        let row = if y < frozen_h {
+            self.row_at_y(y)
+        } else {
+            self.row_at_y(y + scroll_y).max(self.frozen_rows)
+        };
+        let col = if x < frozen_w {
+            self.col_at_x(x)
+        } else {
+            self.col_at_x(x + scroll_x).max(self.frozen_cols)
+        };
+        CellCoord::new(row, col)
+    }
+
+    // =========================================================================
+    // Hiding and Outlines
+    // =========================================================================
+
+    /// Hide or unhide rows or columns `start..=end` for the user
+    /// Returns the indices whose user-hidden state changed
+    pub fn set_hidden(&mut self, axis: Axis, start: u32, end: u32, hidden: bool) -> Vec<u32> {
+        let set = match axis {
+            Axis::Rows => &mut self.hidden_rows,
+            Axis::Cols => &mut self.hidden_cols,
+        };
+        let changed: Vec<u32> = (start.min(end)..=start.max(end))
+            .filter(|&i| if hidden { set.insert(i) } else { set.remove(&i) })
+            .collect();
+        self.sync_hidden(axis);
+        changed
+    }
+
+    /// Rows or columns hidden by the user
+    pub fn user_hidden(&self, axis: Axis) -> &BTreeSet<u32> {
+        match axis {
+            Axis::Rows => &self.hidden_rows,
+            Axis::Cols => &self.hidden_cols,
+        }
+    }
+
+    /// Check if a column is hidden (by the user or a collapsed group)
+    pub fn is_col_hidden(&self, col: u32) -> bool {
+        self.spatial.is_col_hidden(col as usize)
+    }
+
+    /// Get all hidden columns, in order
+    pub fn get_hidden_cols(&self) -> Vec<u32> {
+        let mut cols: Vec<u32> = self.spatial.get_hidden_cols().iter().map(|&c| c as u32).collect();
+        cols.sort_unstable();
+        cols
+    }
+
+    /// Row or column groups
+    pub fn outline(&self, axis: Axis) -> &Outline {
+        match axis {
+            Axis::Rows => &self.row_outline,
+            Axis::Cols => &self.col_outline,
+        }
+    }
+
+    /// Replace the outline of an axis (collapsed groups hide their indices)
+    pub fn set_outline(&mut self, axis: Axis, outline: Outline) {
+        match axis {
+            Axis::Rows => self.row_outline = outline,
+            Axis::Cols => self.col_outline = outline,
+        }
+        self.sync_hidden(axis);
+    }
+
+    /// Group rows or columns `start..=end`
+    pub fn group(&mut self, axis: Axis, start: u32, end: u32) -> Result<(), RusheetError> {
+        let mut outline = self.outline(axis).clone();
+        outline.group(start, end)?;
+        self.set_outline(axis, outline);
+        Ok(())
+    }
+
+    /// Remove the group spanning exactly `start..=end`
+    pub fn ungroup(&mut self, axis: Axis, start: u32, end: u32) -> Option<OutlineGroup> {
+        let mut outline = self.outline(axis).clone();
+        let removed = outline.ungroup(start, end)?;
+        self.set_outline(axis, outline);
+        Some(removed)
+    }
+
+    /// Collapse or expand the group spanning exactly `start..=end`
+    pub fn set_group_collapsed(&mut self, axis: Axis, start: u32, end: u32, collapsed: bool) -> bool {
+        let mut outline = self.outline(axis).clone();
+        if !outline.set_collapsed(start, end, collapsed) {
+            return false;
+        }
+        self.set_outline(axis, outline);
+        true
+    }
+
+    /// Push the union of filter, user and outline hiding into the spatial index
+    fn sync_hidden(&mut self, axis: Axis) {
+        let (user, outline) = match axis {
+            Axis::Rows => (&self.hidden_rows, &self.row_outline),
+            Axis::Cols => (&self.hidden_cols, &self.col_outline),
+        };
+        let mut wanted = outline.collapsed_indices();
+        wanted.extend(user);
+        if axis == Axis::Rows {
+            wanted.extend(&self.filtered_rows);
+        }
+
+        let current = match axis {
+            Axis::Rows => self.spatial.get_hidden_rows(),
+            Axis::Cols => self.spatial.get_hidden_cols(),
+        };
+        for index in current {
+            if !wanted.contains(&(index as u32)) {
+                match axis {
+                    Axis::Rows => self.spatial.unhide_row(index),
+                    Axis::Cols => self.spatial.unhide_col(index),
+                }
+            }
+        }
+        for index in wanted {
+            match axis {
+                Axis::Rows => self.spatial.hide_row(index as usize),
+                Axis::Cols => self.spatial.hide_col(index as usize),
+            }
+        }
+    }
+
+    /// Shift hidden indices, groups and the freeze boundary after inserting
+    /// or deleting `count` at `at`
+    fn shift_visibility(&mut self, axis: Axis, at: u32, count: u32, insert: bool) {
+        let shift = |set: &BTreeSet<u32>| -> BTreeSet<u32> {
+            set.iter()
+                .filter_map(|&i| match (i >= at, insert) {
+                    (false, _) => Some(i),
+                    (true, true) => Some(i + count),
+                    (true, false) => i.checked_sub(count).filter(|_| i >= at + count),
+                })
+                .collect()
+        };
+        let frozen = match axis {
+            Axis::Rows => &mut self.frozen_rows,
+            Axis::Cols => &mut self.frozen_cols,
+        };
+        if at < *frozen {
+            *frozen = if insert {
+                *frozen + count
+            } else {
+                at + frozen.saturating_sub(at + count)
+            };
+        }
+
+        let outline = match axis {
+            Axis::Rows => {
+                self.hidden_rows = shift(&self.hidden_rows);
+                self.filtered_rows = shift(&self.filtered_rows);
+                &mut self.row_outline
+            }
+            Axis::Cols => {
+                self.hidden_cols = shift(&self.hidden_cols);
+                &mut self.col_outline
+            }
+        };
+        if insert {
+            outline.insert(at, count);
+        } else {
+            outline.delete(at, count);
+        }
+        self.sync_hidden(axis);
+    }
+
     // =========================================================================
     // Filtering
     // =========================================================================
 
-    /// Hide specific rows
+    /// Hide specific rows (as filtered out)
     pub fn hide_rows(&mut self, rows: &[u32]) {
-        for &row in rows {
-            self.spatial.hide_row(row as usize);
-        }
+        self.filtered_rows.extend(rows);
+        self.sync_hidden(Axis::Rows);
     }
 
-    /// Show (unhide) specific rows
+    /// Show (unhide) specific filtered rows
     pub fn show_rows(&mut self, rows: &[u32]) {
-        for &row in rows {
-            self.spatial.unhide_row(row as usize);
+        for row in rows {
+            self.filtered_rows.remove(row);
         }
+        self.sync_hidden(Axis::Rows);
     }
 
-    /// Check if a row is hidden
+    /// Check if a row is hidden (by a filter, the user or a collapsed group)
     pub fn is_row_hidden(&self, row: u32) -> bool {
         self.spatial.is_row_hidden(row as usize)
     }
 
-    /// Get all hidden rows
+    /// Get all hidden rows, in order
     pub fn get_hidden_rows(&self) -> Vec<u32> {
-        self.spatial.get_hidden_rows().iter().map(|&r| r as u32).collect()
+        let mut rows: Vec<u32> = self.spatial.get_hidden_rows().iter().map(|&r| r as u32).collect();
+        rows.sort_unstable();
+        rows
+    }
+
+    /// Check if a row is hidden by a filter
+    pub fn is_row_filtered(&self, row: u32) -> bool {
+        self.filtered_rows.contains(&row)
+    }
+
+    /// Get the rows hidden by filters
+    pub fn get_filtered_rows(&self) -> Vec<u32> {
+        self.filtered_rows.iter().copied().collect()
     }
 
     /// Get unique values in a column (for filter dropdown)
@@ -849,19 +1726,7 @@ impl Sheet {
     /// Apply a column filter - hide rows where cell value not in visible_values
     /// Returns the rows that were hidden
     pub fn apply_column_filter(&mut self, col: u32, visible_values: &HashSet<String>, max_rows: u32) -> Vec<u32> {
-        let mut rows_to_hide = Vec::new();
-
-        for row in 0..max_rows {
-            let value = self.get_cell(CellCoord::new(row, col))
-                .map(|c| c.computed_value().as_text())
-                .unwrap_or_default();
-
-            // If cell value is not in visible set, hide the row
-            // Empty cells are hidden unless empty string is in visible_values
-            if !visible_values.contains(&value) {
-                rows_to_hide.push(row);
-            }
-        }
+        let rows_to_hide = self.rows_outside_filter(col, visible_values, 0..max_rows);
 
         // Hide the rows
         self.hide_rows(&rows_to_hide);
@@ -876,10 +1741,29 @@ impl Sheet {
         rows_to_hide
     }
 
+    /// Rows in `rows` whose value in `col` is not one of `visible_values`
+    ///
+    /// Empty cells fail unless the empty string is a visible value.
+    fn rows_outside_filter(
+        &self,
+        col: u32,
+        visible_values: &HashSet<String>,
+        rows: std::ops::Range<u32>,
+    ) -> Vec<u32> {
+        rows.filter(|&row| {
+            let value = self
+                .get_cell(CellCoord::new(row, col))
+                .map(|c| c.computed_value().as_text())
+                .unwrap_or_default();
+            !visible_values.contains(&value)
+        })
+        .collect()
+    }
+
     /// Clear filter on a specific column
     /// Returns the rows that were unhidden
     pub fn clear_column_filter(&mut self, col: u32) -> Vec<u32> {
-        let hidden_before: HashSet<u32> = self.get_hidden_rows().into_iter().collect();
+        let hidden_before: HashSet<u32> = self.get_filtered_rows().into_iter().collect();
 
         // Remove the filter
         self.active_filters.retain(|f| f.col != col);
@@ -899,7 +1783,7 @@ impl Sheet {
 
     /// Clear all filters
     pub fn clear_all_filters(&mut self) -> Vec<u32> {
-        let hidden_rows = self.get_hidden_rows();
+        let hidden_rows = self.get_filtered_rows();
 
         // Show all rows
         self.show_rows(&hidden_rows);
@@ -920,6 +1804,9 @@ impl Sheet {
             let hidden = self.apply_column_filter(filter.col, &filter.visible_values, 10000);
             all_hidden.extend(hidden);
         }
+        for index in 0..self.tables.len() {
+            all_hidden.extend(self.apply_table_filters(index));
+        }
 
         all_hidden.into_iter().collect()
     }
@@ -953,7 +1840,8 @@ impl Sheet {
     }
 
     /// Get the effective format for a cell, applying conditional formatting rules
-    /// This combines the base format with any matching conditional formats
+    /// This combines the base format with any matching conditional formats,
+    /// over the styling of the table the cell is in
     pub fn get_effective_format(
         &self,
         row: u32,
@@ -961,8 +1849,14 @@ impl Sheet {
         base_format: &CellFormat,
         value: &CellValue,
     ) -> CellFormat {
-        let mut result = base_format.clone();
         let coord = CellCoord::new(row, col);
+        let mut result = match self.table_at(coord).and_then(|t| t.format_at(coord)) {
+            Some(mut table_format) => {
+                table_format.merge(base_format);
+                table_format
+            }
+            None => base_format.clone(),
+        };
 
         // Calculate min/max for color scales (could be optimized with caching)
         let (min_val, max_val) = self.calculate_range_min_max();
@@ -980,6 +1874,117 @@ impl Sheet {
         result
     }
 
+    /// Set or clear borders on a range
+    ///
+    /// A `line` of `None` removes the targeted borders. Where a targeted edge
+    /// is shared with a neighbouring cell, the neighbour's side of it is
+    /// cleared so the new border is the one drawn. Returns the previous
+    /// borders of every cell that changed, for undo.
+    pub fn set_borders(
+        &mut self,
+        range: CellRange,
+        target: BorderTarget,
+        line: Option<BorderLine>,
+    ) -> Vec<(CellCoord, Borders)> {
+        let mut previous: Vec<(CellCoord, Borders)> = Vec::new();
+        let mut set = |sheet: &mut Self, coord: CellCoord, edge: Edge, line: Option<BorderLine>| {
+            let old = sheet.get_cell(coord).map(Cell::borders).unwrap_or_default();
+            let mut borders = old;
+            *edge.slot(&mut borders) = line;
+            if borders != old {
+                if !previous.iter().any(|(c, _)| *c == coord) {
+                    previous.push((coord, old));
+                }
+                sheet.set_cell_borders(coord, borders);
+            }
+        };
+
+        for coord in range.iter() {
+            let (row, col) = (coord.row, coord.col);
+            let (top, bottom) = (row == range.start.row, row == range.end.row);
+            let (left, right) = (col == range.start.col, col == range.end.col);
+            let edges = match target {
+                BorderTarget::All => [true, true, true, true],
+                BorderTarget::Outline => [top, right, bottom, left],
+                BorderTarget::Inside => [!top, !right, !bottom, !left],
+                BorderTarget::InsideHorizontal => [!top, false, !bottom, false],
+                BorderTarget::InsideVertical => [false, !right, false, !left],
+                BorderTarget::Top => [top, false, false, false],
+                BorderTarget::Right => [false, right, false, false],
+                BorderTarget::Bottom => [false, false, bottom, false],
+                BorderTarget::Left => [false, false, false, left],
+                BorderTarget::DiagonalDown => {
+                    set(self, coord, Edge::DiagonalDown, line);
+                    continue;
+                }
+                BorderTarget::DiagonalUp => {
+                    set(self, coord, Edge::DiagonalUp, line);
+                    continue;
+                }
+            };
+
+            let neighbours = [
+                row.checked_sub(1).map(|r| CellCoord::new(r, col)),
+                Some(col + 1).filter(|&c| c < Self::MAX_COLS).map(|c| CellCoord::new(row, c)),
+                Some(row + 1).filter(|&r| r < Self::MAX_ROWS).map(|r| CellCoord::new(r, col)),
+                col.checked_sub(1).map(|c| CellCoord::new(row, c)),
+            ];
+            let sides = [Edge::Top, Edge::Right, Edge::Bottom, Edge::Left];
+            for ((edge, neighbour), targeted) in sides.into_iter().zip(neighbours).zip(edges) {
+                if !targeted {
+                    continue;
+                }
+                set(self, coord, edge, line);
+                if let Some(neighbour) = neighbour {
+                    set(self, neighbour, edge.opposite(), None);
+                }
+            }
+        }
+        previous
+    }
+
+    /// Replace the borders of a single cell
+    pub fn set_cell_borders(&mut self, coord: CellCoord, borders: Borders) {
+        if borders.is_empty() && self.get_cell(coord).is_none() {
+            return;
+        }
+        let cell = self.get_cell_mut(coord);
+        cell.format.borders = Some(borders).filter(|borders| !borders.is_empty());
+        if cell.is_empty() {
+            self.remove_cell(coord);
+        }
+    }
+
+    /// Get the borders drawn around a cell
+    ///
+    /// Each edge is resolved against the neighbour sharing it, so a border
+    /// set as the bottom of the cell above shows as this cell's top. The
+    /// heavier line wins when both cells set one.
+    pub fn resolved_borders(&self, coord: CellCoord) -> Borders {
+        let borders_at = |row: Option<u32>, col: Option<u32>| -> Borders {
+            match (row, col) {
+                (Some(row), Some(col)) => self
+                    .get_cell(CellCoord::new(row, col))
+                    .map(Cell::borders)
+                    .unwrap_or_default(),
+                _ => Borders::default(),
+            }
+        };
+        let (row, col) = (coord.row, coord.col);
+        let own = borders_at(Some(row), Some(col));
+        let above = borders_at(row.checked_sub(1), Some(col));
+        let right = borders_at(Some(row), col.checked_add(1));
+        let below = borders_at(row.checked_add(1), Some(col));
+        let left = borders_at(Some(row), col.checked_sub(1));
+        Borders {
+            top: BorderLine::resolve(above.bottom, own.top),
+            right: BorderLine::resolve(right.left, own.right),
+            bottom: BorderLine::resolve(below.top, own.bottom),
+            left: BorderLine::resolve(left.right, own.left),
+            ..own
+        }
+    }
+
     /// Calculate the min and max numeric values in the sheet
     /// Used for color scale calculations
     fn calculate_range_min_max(&self) -> (f64, f64) {
@@ -1060,78 +2065,101 @@ impl<'de> Deserialize<'de> for Sheet {
     where
         D: serde::Deserializer<'de>,
     {
-        // Helper struct with same fields for deserialization
-        #[derive(Deserialize)]
-        struct SheetHelper {
-            name: String,
-            #[serde(default, with = "chunked_grid_serde")]
-            cells: ChunkedGrid<Cell>,
-            #[serde(default)]
-            row_heights: HashMap<u32, f64>,
-            #[serde(default)]
-            col_widths: HashMap<u32, f64>,
-            #[serde(default = "default_row_height")]
-            default_row_height: f64,
-            #[serde(default = "default_col_width")]
-            default_col_width: f64,
-            #[serde(default)]
-            frozen_rows: u32,
-            #[serde(default)]
-            frozen_cols: u32,
-            #[serde(default)]
-            merged_ranges: Vec<CellRange>,
-            #[serde(default)]
-            active_filters: Vec<FilterState>,
-            #[serde(default)]
-            conditional_formatting: Vec<ConditionalFormattingRule>,
-            #[serde(default)]
-            data_validation: Vec<DataValidationRule>,
-        }
-
-        let helper = SheetHelper::deserialize(deserializer)?;
+        Ok(SheetData::deserialize(deserializer)?.into_sheet())
+    }
+}
 
-        let mut sheet = Sheet {
-            name: helper.name,
-            cells: helper.cells,
-            row_heights: helper.row_heights,
-            col_widths: helper.col_widths,
-            default_row_height: helper.default_row_height,
-            default_col_width: helper.default_col_width,
-            frozen_rows: helper.frozen_rows,
-            frozen_cols: helper.frozen_cols,
-            merged_ranges: helper.merged_ranges,
-            active_filters: helper.active_filters,
-            conditional_formatting: helper.conditional_formatting,
-            data_validation: helper.data_validation,
-            spatial: SpatialIndex::new(),
-        };
+/// One edge or diagonal of a cell's borders
+#[derive(Debug, Clone, Copy)]
+enum Edge {
+    Top,
+    Right,
+    Bottom,
+    Left,
+    DiagonalDown,
+    DiagonalUp,
+}
 
-        // Rebuild the spatial index from the deserialized data
-        sheet.rebuild_spatial_index();
+impl Edge {
+    fn slot(self, borders: &mut Borders) -> &mut Option<BorderLine> {
+        match self {
+            Edge::Top => &mut borders.top,
+            Edge::Right => &mut borders.right,
+            Edge::Bottom => &mut borders.bottom,
+            Edge::Left => &mut borders.left,
+            Edge::DiagonalDown => &mut borders.diagonal_down,
+            Edge::DiagonalUp => &mut borders.diagonal_up,
+        }
+    }
 
-        Ok(sheet)
+    /// The same edge seen from the neighbouring cell
+    fn opposite(self) -> Self {
+        match self {
+            Edge::Top => Edge::Bottom,
+            Edge::Right => Edge::Left,
+            Edge::Bottom => Edge::Top,
+            Edge::Left => Edge::Right,
+            diagonal => diagonal,
+        }
     }
 }
 
 /// Parse user input to determine cell content type
+///
+/// Dates and times are read month-first in the 1900 date system; use
+/// [`parse_cell_input_with`] to also get their number format.
 pub fn parse_cell_input(input: &str) -> CellContent {
+    parse_cell_input_with(input, DateOrder::default(), DateSystem::default()).0
+}
+
+/// Parse user input, reading dates and times in the given order and date system
+///
+/// Returns the cell content, plus the number format to display it with when
+/// the input was a date, time or duration.
+pub fn parse_cell_input_with(
+    input: &str,
+    order: DateOrder,
+    system: DateSystem,
+) -> (CellContent, Option<String>) {
     let trimmed = input.trim();
 
     if trimmed.is_empty() {
-        return CellContent::Value {
+        let content = CellContent::Value {
             value: CellValue::Empty,
             original_input: None,
         };
+        return (content, None);
     }
 
     // Formulas: store expression
     if trimmed.starts_with('=') {
-        return CellContent::Formula {
+        let content = CellContent::Formula {
             expression: trimmed.to_string(),
             cached_value: CellValue::Empty,
         };
+        return (content, None);
+    }
+
+    let content = parse_plain_input(trimmed);
+    if !matches!(content.computed_value(), CellValue::Text(_)) {
+        return (content, None);
+    }
+
+    // Date, time or duration
+    match parse_date_time_input(trimmed, order, system) {
+        Some(input) => {
+            let content = CellContent::Value {
+                value: CellValue::Number(input.serial),
+                original_input: Some(trimmed.to_string()),
+            };
+            (content, Some(input.number_format))
+        }
+        None => (content, None),
     }
+}
 
+/// Parse non-empty input that is not a formula into a boolean, number or text
+fn parse_plain_input(trimmed: &str) -> CellContent {
     // Store original input for all value types
     let original = trimmed.to_string();
 
@@ -1157,8 +2185,8 @@ pub fn parse_cell_input(input: &str) -> CellContent {
     }
 
     // Percentage
-    if trimmed.ends_with('%') {
-        if let Ok(num) = trimmed[..trimmed.len() - 1].parse::<f64>() {
+    if let Some(number) = trimmed.strip_suffix('%') {
+        if let Ok(num) = number.parse::<f64>() {
             return CellContent::Value {
                 value: CellValue::Number(num / 100.0),
                 original_input: Some(original),
@@ -1195,6 +2223,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_parse_cell_input() {
         // Number
         let content = parse_cell_input("42");
@@ -1221,6 +2250,105 @@ mod tests {
         assert!(matches!(content, CellContent::Value { value: CellValue::Number(n), .. } if (n - 0.5).abs() < 0.001));
     }
 
+    #[test]
+    fn test_parse_date_input() {
+        let (content, format) =
+            parse_cell_input_with("2026-10-16", DateOrder::default(), DateSystem::Excel1900);
+        assert_eq!(content.computed_value(), &CellValue::Number(46311.0));
+        assert_eq!(content.original_input(), "2026-10-16");
+        assert_eq!(format.as_deref(), Some("yyyy-mm-dd"));
+
+        let (content, format) =
+            parse_cell_input_with("16/10/2026", DateOrder::DayMonthYear, DateSystem::Excel1904);
+        assert_eq!(content.computed_value(), &CellValue::Number(44849.0));
+        assert_eq!(format.as_deref(), Some("d/m/yyyy"));
+
+        // Plain numbers are never dates
+        let (content, format) =
+            parse_cell_input_with("2026", DateOrder::default(), DateSystem::Excel1900);
+        assert_eq!(content.computed_value(), &CellValue::Number(2026.0));
+        assert_eq!(format, None);
+
+        // Sheet::set_cell_value formats the cell, keeping an existing date format
+        let mut sheet = Sheet::new("Test");
+        let coord = CellCoord::new(0, 0);
+        sheet.set_cell_value(coord, "9:30 AM");
+        let cell = sheet.get_cell(coord).unwrap();
+        assert_eq!(cell.format.number_format(&[]), Some("h:mm AM/PM"));
+        assert_eq!(cell.display_value(&[], DateSystem::Excel1900), "9:30 AM");
+
+        sheet.get_cell_mut(coord).format.number_format = Some(Some("d mmm".to_string()));
+        sheet.set_cell_value(coord, "2026-10-16");
+        let cell = sheet.get_cell(coord).unwrap();
+        assert_eq!(cell.display_value(&[], DateSystem::Excel1900), "16 Oct");
+    }
+
+    #[test]
+    fn test_set_borders_resolves_shared_edges() {
+        use crate::format::{BorderStyle, Color};
+
+        let thin = BorderLine::new(BorderStyle::Thin, Color::BLACK);
+        let thick = BorderLine::new(BorderStyle::Thick, Color::RED);
+        let mut sheet = Sheet::new("Test");
+        let range = CellRange::from_a1("B2:C3").unwrap();
+
+        // Every edge of a 2x2 block is drawn, but inner edges are stored once
+        let previous = sheet.set_borders(range, BorderTarget::All, Some(thin));
+        assert_eq!(previous.len(), 4);
+        for coord in range.iter() {
+            let borders = sheet.resolved_borders(coord);
+            assert_eq!([borders.top, borders.right, borders.bottom, borders.left], [Some(thin); 4]);
+        }
+        let b2 = sheet.get_cell(CellCoord::new(1, 1)).unwrap().borders();
+        assert_eq!(b2.right, None);
+        assert_eq!(b2.bottom, None);
+
+        // A thick outline around B2 replaces the thin lines it shares with C2 and B3
+        let b2_range = CellRange::from_a1("B2").unwrap();
+        sheet.set_borders(b2_range, BorderTarget::Outline, Some(thick));
+        assert_eq!(sheet.resolved_borders(CellCoord::new(1, 2)).left, Some(thick));
+        assert_eq!(sheet.resolved_borders(CellCoord::new(2, 1)).top, Some(thick));
+
+        // Cells outside the range see the edges they share with it
+        assert_eq!(sheet.resolved_borders(CellCoord::new(0, 1)).bottom, Some(thick));
+        assert_eq!(sheet.resolved_borders(CellCoord::new(0, 0)).bottom, None);
+
+        // Clearing removes both sides of an edge, and cells left bare
+        sheet.set_borders(range, BorderTarget::All, None);
+        assert!(sheet.resolved_borders(CellCoord::new(1, 1)).is_empty());
+        assert!(sheet.resolved_borders(CellCoord::new(0, 1)).is_empty());
+        assert!(sheet.get_cell(CellCoord::new(1, 1)).is_none());
+    }
+
+    #[test]
+    fn test_set_borders_targets() {
+        use crate::format::{BorderStyle, Color};
+
+        let line = Some(BorderLine::new(BorderStyle::Dashed, Color::BLUE));
+        let range = CellRange::from_a1("A1:C3").unwrap();
+        let edges_at = |sheet: &Sheet, a1: &str| {
+            let borders = sheet.resolved_borders(CellCoord::from_a1(a1).unwrap());
+            [borders.top, borders.right, borders.bottom, borders.left].map(|e| e.is_some())
+        };
+
+        let mut sheet = Sheet::new("Test");
+        sheet.set_borders(range, BorderTarget::Outline, line);
+        assert_eq!(edges_at(&sheet, "A1"), [true, false, false, true]);
+        assert_eq!(edges_at(&sheet, "B2"), [false; 4]);
+        assert_eq!(edges_at(&sheet, "C3"), [false, true, true, false]);
+
+        let mut sheet = Sheet::new("Test");
+        sheet.set_borders(range, BorderTarget::InsideHorizontal, line);
+        assert_eq!(edges_at(&sheet, "A1"), [false, false, true, false]);
+        assert_eq!(edges_at(&sheet, "B2"), [true, false, true, false]);
+        assert_eq!(edges_at(&sheet, "C3"), [true, false, false, false]);
+
+        let mut sheet = Sheet::new("Test");
+        sheet.set_borders(range, BorderTarget::DiagonalUp, line);
+        assert_eq!(sheet.resolved_borders(CellCoord::new(1, 1)).diagonal_up, line);
+        assert_eq!(edges_at(&sheet, "B2"), [false; 4]);
+    }
+
     #[test]
     fn test_row_col_dimensions() {
         let mut sheet = Sheet::new("Test");
@@ -1935,4 +3063,203 @@ mod tests {
         let rule = &sheet.data_validation[0];
         assert_eq!(rule.range.end, CellCoord::new(10, 10));
     }
+
+    #[test]
+    fn test_user_hidden_separate_from_filters() {
+        let mut sheet = Sheet::new("Test");
+        sheet.set_cell_value(CellCoord::new(0, 0), "a");
+        sheet.set_cell_value(CellCoord::new(1, 0), "b");
+
+        assert_eq!(sheet.set_hidden(Axis::Rows, 1, 2, true), vec![1, 2]);
+        let visible: HashSet<String> = ["a".to_string()].into_iter().collect();
+        sheet.apply_column_filter(0, &visible, 4);
+        assert!(sheet.is_row_hidden(3));
+
+        // Clearing filters leaves the user's rows hidden
+        assert_eq!(sheet.clear_all_filters(), vec![1, 2, 3]);
+        assert_eq!(sheet.get_hidden_rows(), vec![1, 2]);
+
+        sheet.set_hidden(Axis::Cols, 4, 4, true);
+        let offset = sheet.col_x_position(5);
+        assert!(sheet.is_col_hidden(4));
+        assert_eq!(offset, 4.0 * DEFAULT_COL_WIDTH);
+
+        // Hidden columns move with inserts
+        sheet.insert_cols(0, 1);
+        assert_eq!(sheet.get_hidden_cols(), vec![5]);
+    }
+
+    #[test]
+    fn test_outline_groups_hide_when_collapsed() {
+        let mut sheet = Sheet::new("Test");
+        sheet.group(Axis::Rows, 1, 5).unwrap();
+        sheet.group(Axis::Rows, 2, 3).unwrap();
+        assert_eq!(sheet.group(Axis::Rows, 4, 8), Err(RusheetError::GroupOverlap));
+
+        assert!(sheet.set_group_collapsed(Axis::Rows, 2, 3, true));
+        assert_eq!(sheet.get_hidden_rows(), vec![2, 3]);
+
+        sheet.set_hidden(Axis::Rows, 3, 3, true);
+        sheet.set_group_collapsed(Axis::Rows, 2, 3, false);
+        assert_eq!(sheet.get_hidden_rows(), vec![3]);
+
+        // Deleting rows shifts groups; serialization keeps them
+        sheet.set_group_collapsed(Axis::Rows, 1, 5, true);
+        sheet.delete_rows(0, 1);
+        assert_eq!(sheet.get_hidden_rows(), vec![0, 1, 2, 3, 4]);
+
+        let json = serde_json::to_string(&sheet).unwrap();
+        let restored: Sheet = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.outline(Axis::Rows).max_level(), 2);
+        assert_eq!(restored.get_hidden_rows(), vec![0, 1, 2, 3, 4]);
+        assert_eq!(restored.user_hidden(Axis::Rows), &BTreeSet::from([2]));
+    }
+
+
+    #[test]
+    fn test_frozen_panes_follow_inserts_and_deletes() {
+        let mut sheet = Sheet::new("Test");
+        sheet.set_frozen_panes(3, 2);
+
+        sheet.insert_rows(1, 2);
+        assert_eq!(sheet.frozen_rows, 5);
+        // Inserting at or below the boundary leaves it alone
+        sheet.insert_rows(5, 1);
+        assert_eq!(sheet.frozen_rows, 5);
+
+        sheet.delete_rows(4, 3);
+        assert_eq!(sheet.frozen_rows, 4);
+        sheet.delete_cols(0, 1);
+        assert_eq!(sheet.frozen_cols, 1);
+    }
+
+    #[test]
+    fn test_panes_and_hit_testing() {
+        let mut sheet = Sheet::new("Test");
+        sheet.set_frozen_panes(2, 1);
+        let (row_h, col_w) = (DEFAULT_ROW_HEIGHT, DEFAULT_COL_WIDTH);
+
+        let panes = sheet.panes(0.0, 5.0 * row_h, 4.0 * col_w, 10.0 * row_h);
+        let kinds: Vec<PaneKind> = panes.iter().map(|p| p.kind).collect();
+        assert_eq!(kinds, vec![PaneKind::Corner, PaneKind::Top, PaneKind::Left, PaneKind::Main]);
+
+        let main = panes[3];
+        assert_eq!((main.range.first_row, main.range.last_row), (7, 14));
+        assert_eq!((main.range.first_col, main.range.last_col), (1, 3));
+        assert_eq!((main.y, main.offset_y), (2.0 * row_h, -5.0 * row_h));
+        assert_eq!(panes[1].range.first_row, 0);
+
+        // Points in the frozen rows ignore vertical scrolling
+        assert_eq!(sheet.cell_at_point(10.0, 10.0, 0.0, 5.0 * row_h), CellCoord::new(0, 0));
+        assert_eq!(
+            sheet.cell_at_point(150.0, 2.0 * row_h + 1.0, 0.0, 5.0 * row_h),
+            CellCoord::new(7, 1)
+        );
+
+        // Without freezing there is a single pane
+        sheet.set_frozen_panes(0, 0);
+        assert_eq!(sheet.panes(0.0, 0.0, 100.0, 100.0).len(), 1);
+    }
+
+
+    #[test]
+    fn test_comments_move_with_cells() {
+        let mut sheet = Sheet::new("Test");
+        let note = Comment::new("c1", "ana", 0, "check this");
+        sheet.set_comment(CellCoord::new(2, 1), Some(note.clone()));
+        sheet.set_cell_value(CellCoord::new(4, 0), "x");
+        sheet.set_comment(CellCoord::new(4, 0), Some(Comment::new("c2", "bo", 0, "ok")));
+
+        sheet.insert_rows(0, 2);
+        sheet.delete_cols(0, 1);
+        let comments = sheet.comments();
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0], (CellCoord::new(4, 0), &note));
+
+        // A comment keeps an otherwise empty cell alive, until removed
+        assert_eq!(sheet.set_comment(CellCoord::new(4, 0), None), Some(note));
+        assert!(sheet.is_empty());
+
+        sheet.set_cell_value(CellCoord::new(0, 0), "b");
+        sheet.set_cell_value(CellCoord::new(1, 0), "a");
+        sheet.set_comment(CellCoord::new(0, 0), Some(Comment::new("c3", "cy", 0, "on b")));
+        sheet.sort_range(0, 1, 0, 0, 0, true);
+        assert_eq!(sheet.get_comment(CellCoord::new(1, 0)).unwrap().id, "c3");
+
+        let json = serde_json::to_string(&sheet).unwrap();
+        let restored: Sheet = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.get_comment(CellCoord::new(1, 0)).unwrap().body, "on b");
+    }
+
+    #[test]
+    fn test_tables() {
+        let mut sheet = Sheet::new("Test");
+        let rows = [["Region", "", "Amount"], ["East", "a", "30"], ["West", "b", "10"], ["East", "c", "20"]];
+        for (r, row) in rows.iter().enumerate() {
+            for (c, value) in row.iter().enumerate() {
+                sheet.set_cell_value(CellCoord::new(r as u32, c as u32), value);
+            }
+        }
+        let range = CellRange::from_a1("A1:C4").unwrap();
+        sheet.add_table(Table::new("Sales", range)).unwrap();
+        let table = sheet.table("sales").unwrap();
+        assert_eq!(table.column_names(&sheet), ["Region", "Column1", "Amount"]);
+        assert_eq!(table.column_index(&sheet, "AMOUNT"), Some(2));
+        assert_eq!(
+            sheet.add_table(Table::new("Other", CellRange::from_a1("C4:D5").unwrap())),
+            Err(RusheetError::TableOverlap)
+        );
+        assert_eq!(
+            sheet.add_table(Table::new("SALES", CellRange::from_a1("F1:G2").unwrap())),
+            Err(RusheetError::TableNameExists("SALES".to_string()))
+        );
+
+        // Typing below or to the right grows the table
+        assert_eq!(
+            sheet.table_expansion(CellCoord::new(4, 1)),
+            Some(("Sales".to_string(), CellRange::from_a1("A1:C5").unwrap()))
+        );
+        assert_eq!(
+            sheet.table_expansion(CellCoord::new(0, 3)),
+            Some(("Sales".to_string(), CellRange::from_a1("A1:D4").unwrap()))
+        );
+        assert_eq!(sheet.table_expansion(CellCoord::new(4, 3)), None);
+
+        // Sort and filter work on the data rows only
+        sheet.sort_table("Sales", 2, true).unwrap();
+        assert_eq!(sheet.get_cell_value(CellCoord::new(0, 0)).as_text(), "Region");
+        assert_eq!(sheet.get_cell_value(CellCoord::new(1, 1)).as_text(), "b");
+        let changed = sheet
+            .filter_table("Sales", 0, Some(HashSet::from(["East".to_string()])))
+            .unwrap();
+        assert_eq!(changed, vec![1]);
+        assert!(!sheet.is_row_hidden(0));
+        assert_eq!(sheet.filter_table("Sales", 0, None).unwrap(), vec![1]);
+
+        sheet.set_table_totals_row("Sales", true).unwrap();
+        let table = sheet.table("Sales").unwrap();
+        assert_eq!(table.range, CellRange::from_a1("A1:C5").unwrap());
+        assert_eq!(sheet.get_cell_value(CellCoord::new(4, 0)).as_text(), "Total");
+        assert!(matches!(
+            &sheet.get_cell(CellCoord::new(4, 2)).unwrap().content,
+            CellContent::Formula { expression, .. } if expression == "=SUBTOTAL(109,Sales[Amount])"
+        ));
+        assert_eq!(sheet.table_expansion(CellCoord::new(5, 0)), None);
+
+        // Inserting inside grows the table; new columns get a header
+        sheet.insert_rows(2, 1);
+        sheet.insert_cols(1, 1);
+        let table = sheet.table("Sales").unwrap();
+        assert_eq!(table.range, CellRange::from_a1("A1:D6").unwrap());
+        assert_eq!(table.column_names(&sheet), ["Region", "Column2", "Column1", "Amount"]);
+        let header = sheet.get_effective_format(0, 0, &CellFormat::new(), &CellValue::Empty);
+        assert!(header.bold);
+
+        let json = serde_json::to_string(&sheet).unwrap();
+        let restored: Sheet = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.tables(), sheet.tables());
+
+        sheet.delete_rows(0, 1);
+        assert!(sheet.tables().is_empty());
+    }
 }
@@ -419,6 +419,11 @@ impl SpatialIndex {
         self.hidden_rows.iter().copied().collect()
     }
 
+    /// Returns a vector of all hidden column indices.
+    pub fn get_hidden_cols(&self) -> Vec<usize> {
+        self.hidden_cols.iter().copied().collect()
+    }
+
     /// Rebuilds the row heights Fenwick tree from the given row index onwards.
     ///
     /// This is necessary after insert/delete operations since Fenwick trees
@@ -3,9 +3,10 @@ use serde::{Deserialize, Serialize};
 use super::selection::SelectionRange;
 
 /// Clipboard mode determines what operation was last performed
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ClipboardMode {
     /// Clipboard is empty
+    #[default]
     Empty,
     /// Content was copied
     Copy,
@@ -13,12 +14,6 @@ pub enum ClipboardMode {
     Cut,
 }
 
-impl Default for ClipboardMode {
-    fn default() -> Self {
-        Self::Empty
-    }
-}
-
 /// Represents clipboard data
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ClipboardData {
@@ -3,9 +3,10 @@ use serde::{Deserialize, Serialize};
 use super::selection::CellPosition;
 
 /// Edit mode determines what the user is currently editing
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EditMode {
     /// Not editing, just viewing the spreadsheet
+    #[default]
     Viewing,
     /// Editing a cell directly in the grid
     CellEditing {
@@ -19,12 +20,6 @@ pub enum EditMode {
     },
 }
 
-impl Default for EditMode {
-    fn default() -> Self {
-        Self::Viewing
-    }
-}
-
 impl EditMode {
     pub fn is_viewing(&self) -> bool {
         matches!(self, EditMode::Viewing)
@@ -11,7 +11,7 @@ pub use input::{
     key_to_action, start_edit_with_char, InputAction, Key, Modifiers,
 };
 pub use selection::{CellPosition, Selection, SelectionMode, SelectionRange};
-pub use viewport::{ViewportState, VisibleRange};
+pub use viewport::{Pane, PaneKind, ViewportState, VisibleRange};
 
 use serde::{Deserialize, Serialize};
 
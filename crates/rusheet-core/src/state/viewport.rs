@@ -37,16 +37,52 @@ impl VisibleRange {
     }
 }
 
+/// Which quadrant of a viewport split by frozen rows and columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PaneKind {
+    /// Frozen rows and frozen columns; never scrolls
+    Corner,
+    /// Frozen rows; scrolls horizontally
+    Top,
+    /// Frozen columns; scrolls vertically
+    Left,
+    /// Scrolls both ways
+    Main,
+}
+
+/// One quadrant of the viewport, in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pane {
+    pub kind: PaneKind,
+    pub range: VisibleRange,
+    /// Screen position of the pane's top-left corner
+    pub x: f64,
+    pub y: f64,
+    /// Added to a sheet position to get its screen position in this pane
+    pub offset_x: f64,
+    pub offset_y: f64,
+}
+
 /// Manages the viewport and scroll state
+///
+/// With frozen rows or columns, the scroll position is the first row and
+/// column of the scrolling pane and never falls inside the frozen region.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ViewportState {
-    /// Top-left corner of the viewport
+    /// Top-left corner of the scrolling pane
     scroll_row: usize,
     scroll_col: usize,
-    /// Number of visible rows
+    /// Number of visible rows (frozen rows included)
     visible_rows: usize,
-    /// Number of visible columns
+    /// Number of visible columns (frozen columns included)
     visible_cols: usize,
+    /// Rows and columns locked at the top and left
+    #[serde(default)]
+    frozen_rows: usize,
+    #[serde(default)]
+    frozen_cols: usize,
 }
 
 impl Default for ViewportState {
@@ -62,48 +98,107 @@ impl ViewportState {
             scroll_col: 0,
             visible_rows,
             visible_cols,
+            frozen_rows: 0,
+            frozen_cols: 0,
         }
     }
 
+    /// Freeze the first `rows` rows and `cols` columns
+    pub fn set_frozen(&mut self, rows: usize, cols: usize) {
+        self.frozen_rows = rows;
+        self.frozen_cols = cols;
+        self.clamp_scroll();
+    }
+
+    /// Get the frozen (rows, columns)
+    pub fn frozen(&self) -> (usize, usize) {
+        (self.frozen_rows, self.frozen_cols)
+    }
+
+    /// Rows and columns the scrolling pane shows (at least one each)
+    fn scroll_capacity(&self) -> (usize, usize) {
+        (
+            self.visible_rows.saturating_sub(self.frozen_rows).max(1),
+            self.visible_cols.saturating_sub(self.frozen_cols).max(1),
+        )
+    }
+
+    fn clamp_scroll(&mut self) {
+        self.scroll_row = self.scroll_row.max(self.frozen_rows);
+        self.scroll_col = self.scroll_col.max(self.frozen_cols);
+    }
+
     /// Scroll to a specific position (top-left corner)
     pub fn scroll_to(&mut self, row: usize, col: usize) {
         self.scroll_row = row;
         self.scroll_col = col;
+        self.clamp_scroll();
     }
 
     /// Scroll by a relative amount
     pub fn scroll_by(&mut self, delta_row: isize, delta_col: isize) {
         self.scroll_row = (self.scroll_row as isize + delta_row).max(0) as usize;
         self.scroll_col = (self.scroll_col as isize + delta_col).max(0) as usize;
+        self.clamp_scroll();
     }
 
     /// Ensure a cell is visible, scrolling if necessary
+    ///
+    /// Cells in frozen rows or columns are always visible along that axis.
     pub fn ensure_cell_visible(&mut self, pos: CellPosition) {
+        let (rows, cols) = self.scroll_capacity();
+
         // Scroll vertically if needed
-        if pos.row < self.scroll_row {
-            self.scroll_row = pos.row;
-        } else if pos.row >= self.scroll_row + self.visible_rows {
-            self.scroll_row = pos.row.saturating_sub(self.visible_rows - 1);
+        if pos.row >= self.frozen_rows {
+            if pos.row < self.scroll_row {
+                self.scroll_row = pos.row;
+            } else if pos.row >= self.scroll_row + rows {
+                self.scroll_row = pos.row.saturating_sub(rows - 1);
+            }
         }
 
         // Scroll horizontally if needed
-        if pos.col < self.scroll_col {
-            self.scroll_col = pos.col;
-        } else if pos.col >= self.scroll_col + self.visible_cols {
-            self.scroll_col = pos.col.saturating_sub(self.visible_cols - 1);
+        if pos.col >= self.frozen_cols {
+            if pos.col < self.scroll_col {
+                self.scroll_col = pos.col;
+            } else if pos.col >= self.scroll_col + cols {
+                self.scroll_col = pos.col.saturating_sub(cols - 1);
+            }
         }
+        self.clamp_scroll();
     }
 
-    /// Get the current visible range
+    /// Get the range shown by the scrolling pane
     pub fn get_visible_range(&self) -> VisibleRange {
+        let (rows, cols) = self.scroll_capacity();
         VisibleRange {
             first_row: self.scroll_row,
-            last_row: self.scroll_row + self.visible_rows.saturating_sub(1),
+            last_row: self.scroll_row + rows - 1,
             first_col: self.scroll_col,
-            last_col: self.scroll_col + self.visible_cols.saturating_sub(1),
+            last_col: self.scroll_col + cols - 1,
         }
     }
 
+    /// Get the ranges of each non-empty pane, frozen panes first
+    pub fn pane_ranges(&self) -> Vec<(PaneKind, VisibleRange)> {
+        let main = self.get_visible_range();
+        let frozen_rows = (self.frozen_rows > 0).then(|| (0, self.frozen_rows - 1));
+        let frozen_cols = (self.frozen_cols > 0).then(|| (0, self.frozen_cols - 1));
+
+        let mut panes = Vec::new();
+        if let (Some((r0, r1)), Some((c0, c1))) = (frozen_rows, frozen_cols) {
+            panes.push((PaneKind::Corner, VisibleRange::new(r0, r1, c0, c1)));
+        }
+        if let Some((r0, r1)) = frozen_rows {
+            panes.push((PaneKind::Top, VisibleRange::new(r0, r1, main.first_col, main.last_col)));
+        }
+        if let Some((c0, c1)) = frozen_cols {
+            panes.push((PaneKind::Left, VisibleRange::new(main.first_row, main.last_row, c0, c1)));
+        }
+        panes.push((PaneKind::Main, main));
+        panes
+    }
+
     /// Get the scroll position (top-left corner)
     pub fn scroll_position(&self) -> (usize, usize) {
         (self.scroll_row, self.scroll_col)
@@ -120,47 +215,51 @@ impl ViewportState {
         self.visible_cols = visible_cols;
     }
 
-    /// Check if a cell is currently visible
+    /// Check if a cell is currently visible (in any pane)
     pub fn is_visible(&self, pos: CellPosition) -> bool {
-        self.get_visible_range().contains(pos)
+        self.pane_ranges().iter().any(|(_, range)| range.contains(pos))
     }
 
     /// Scroll to show a specific cell at the top-left
     pub fn scroll_to_cell(&mut self, pos: CellPosition) {
-        self.scroll_row = pos.row;
-        self.scroll_col = pos.col;
+        self.scroll_to(pos.row, pos.col);
     }
 
     /// Center the viewport on a specific cell
     pub fn center_on_cell(&mut self, pos: CellPosition) {
-        self.scroll_row = pos.row.saturating_sub(self.visible_rows / 2);
-        self.scroll_col = pos.col.saturating_sub(self.visible_cols / 2);
+        let (rows, cols) = self.scroll_capacity();
+        self.scroll_row = pos.row.saturating_sub(rows / 2);
+        self.scroll_col = pos.col.saturating_sub(cols / 2);
+        self.clamp_scroll();
     }
 
     /// Scroll down by one page
     pub fn page_down(&mut self) {
-        self.scroll_row += self.visible_rows;
+        self.scroll_row += self.scroll_capacity().0;
     }
 
     /// Scroll up by one page
     pub fn page_up(&mut self) {
-        self.scroll_row = self.scroll_row.saturating_sub(self.visible_rows);
+        self.scroll_row = self.scroll_row.saturating_sub(self.scroll_capacity().0);
+        self.clamp_scroll();
     }
 
     /// Scroll right by one page
     pub fn page_right(&mut self) {
-        self.scroll_col += self.visible_cols;
+        self.scroll_col += self.scroll_capacity().1;
     }
 
     /// Scroll left by one page
     pub fn page_left(&mut self) {
-        self.scroll_col = self.scroll_col.saturating_sub(self.visible_cols);
+        self.scroll_col = self.scroll_col.saturating_sub(self.scroll_capacity().1);
+        self.clamp_scroll();
     }
 
     /// Reset scroll to origin
     pub fn reset(&mut self) {
         self.scroll_row = 0;
         self.scroll_col = 0;
+        self.clamp_scroll();
     }
 }
 
@@ -489,4 +588,26 @@ mod tests {
 
         assert_eq!(range, deserialized);
     }
+
+    #[test]
+    fn test_viewport_frozen_panes() {
+        let mut viewport = ViewportState::new(10, 5);
+        viewport.set_frozen(2, 1);
+        assert_eq!(viewport.scroll_position(), (2, 1));
+
+        // Scrolling never enters the frozen region
+        viewport.scroll_to(0, 0);
+        assert_eq!(viewport.scroll_position(), (2, 1));
+
+        // The scrolling pane holds the rows left over after the frozen ones
+        viewport.ensure_cell_visible(CellPosition::new(20, 0));
+        assert_eq!(viewport.get_visible_range(), VisibleRange::new(13, 20, 1, 4));
+        assert!(viewport.is_visible(CellPosition::new(0, 3)));
+        assert!(!viewport.is_visible(CellPosition::new(5, 3)));
+
+        let kinds: Vec<PaneKind> = viewport.pane_ranges().iter().map(|(kind, _)| *kind).collect();
+        assert_eq!(kinds, vec![PaneKind::Corner, PaneKind::Top, PaneKind::Left, PaneKind::Main]);
+        assert_eq!(viewport.pane_ranges()[1].1, VisibleRange::new(0, 1, 1, 4));
+    }
+
 }
@@ -0,0 +1,369 @@
+//! Named cell styles and the workbook theme
+//!
+//! A theme is a palette of twelve colors plus a heading and a body font.
+//! Formats can follow a theme slot instead of a fixed color or font, and can
+//! be based on a named style such as "Heading 1" or "Currency". A cell
+//! stores its style name plus a [`FormatDiff`] of its own changes, in memory
+//! and when saved, and is resolved against the workbook's styles and theme
+//! when its format is looked up, so redefining a style restyles every cell
+//! based on it.
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::format::{Borders, CellFormat, Color, HorizontalAlign, VerticalAlign};
+
+/// A color slot in the theme palette
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ThemeColor {
+    Dark1,
+    Light1,
+    Dark2,
+    Light2,
+    Accent1,
+    Accent2,
+    Accent3,
+    Accent4,
+    Accent5,
+    Accent6,
+    Hyperlink,
+    FollowedHyperlink,
+}
+
+impl ThemeColor {
+    /// All slots, in palette order
+    pub const ALL: [ThemeColor; 12] = [
+        ThemeColor::Dark1,
+        ThemeColor::Light1,
+        ThemeColor::Dark2,
+        ThemeColor::Light2,
+        ThemeColor::Accent1,
+        ThemeColor::Accent2,
+        ThemeColor::Accent3,
+        ThemeColor::Accent4,
+        ThemeColor::Accent5,
+        ThemeColor::Accent6,
+        ThemeColor::Hyperlink,
+        ThemeColor::FollowedHyperlink,
+    ];
+}
+
+/// A font slot in the theme
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeFont {
+    /// Font for headings
+    Major,
+    /// Font for body text
+    Minor,
+}
+
+/// Workbook theme: a color palette and heading/body fonts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    /// Colors in [`ThemeColor::ALL`] order
+    pub colors: [Color; 12],
+    pub major_font: String,
+    pub minor_font: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::office()
+    }
+}
+
+impl Theme {
+    /// The default Office theme
+    pub fn office() -> Self {
+        Self {
+            name: "Office".to_string(),
+            colors: [
+                Color::rgb(0x00, 0x00, 0x00),
+                Color::rgb(0xff, 0xff, 0xff),
+                Color::rgb(0x44, 0x54, 0x6a),
+                Color::rgb(0xe7, 0xe6, 0xe6),
+                Color::rgb(0x44, 0x72, 0xc4),
+                Color::rgb(0xed, 0x7d, 0x31),
+                Color::rgb(0xa5, 0xa5, 0xa5),
+                Color::rgb(0xff, 0xc0, 0x00),
+                Color::rgb(0x5b, 0x9b, 0xd5),
+                Color::rgb(0x70, 0xad, 0x47),
+                Color::rgb(0x05, 0x63, 0xc1),
+                Color::rgb(0x95, 0x4f, 0x72),
+            ],
+            major_font: "Calibri Light".to_string(),
+            minor_font: "Calibri".to_string(),
+        }
+    }
+
+    pub fn color(&self, slot: ThemeColor) -> Color {
+        self.colors[slot as usize]
+    }
+
+    pub fn set_color(&mut self, slot: ThemeColor, color: Color) {
+        self.colors[slot as usize] = color;
+    }
+
+    pub fn font(&self, slot: ThemeFont) -> &str {
+        match slot {
+            ThemeFont::Major => &self.major_font,
+            ThemeFont::Minor => &self.minor_font,
+        }
+    }
+}
+
+/// A named, workbook-level cell style
+///
+/// The name doubles as the style's ID in cell formats. Styles don't set
+/// borders, which stay with each cell.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamedStyle {
+    pub name: String,
+    pub format: CellFormat,
+}
+
+impl NamedStyle {
+    pub fn new(name: impl Into<String>, format: CellFormat) -> Self {
+        Self {
+            name: name.into(),
+            format,
+        }
+    }
+
+    /// The styles every new workbook starts with, resolved against `theme`
+    pub fn builtins(theme: &Theme) -> Vec<NamedStyle> {
+        let fill = |background: &str, text: &str| {
+            CellFormat::new()
+                .with_background_color(Color::from_hex(background).unwrap_or_default())
+                .with_text_color(Color::from_hex(text).unwrap_or_default())
+        };
+        let number = |code: &str| CellFormat {
+            number_format: Some(code.to_string()),
+            ..CellFormat::default()
+        };
+        let heading = |size: u8, bold: bool| {
+            CellFormat::new()
+                .with_bold(bold)
+                .with_font_size(size)
+                .with_theme_text_color(ThemeColor::Dark2)
+        };
+
+        let mut styles = vec![
+            NamedStyle::new("Title", heading(18, false).with_theme_font(ThemeFont::Major)),
+            NamedStyle::new("Heading 1", heading(15, true)),
+            NamedStyle::new("Heading 2", heading(13, true)),
+            NamedStyle::new("Heading 3", heading(11, true)),
+            NamedStyle::new("Good", fill("#c6efce", "#006100")),
+            NamedStyle::new("Bad", fill("#ffc7ce", "#9c0006")),
+            NamedStyle::new("Neutral", fill("#ffeb9c", "#9c5700")),
+            NamedStyle::new("Input", fill("#ffcc99", "#3f3f76")),
+            NamedStyle::new("Output", fill("#f2f2f2", "#3f3f3f").with_bold(true)),
+            NamedStyle::new("Note", fill("#ffffcc", "#000000")),
+            NamedStyle::new("Currency", number("$#,##0.00")),
+            NamedStyle::new("Comma", number("#,##0.00")),
+            NamedStyle::new("Percent", number("0%")),
+        ];
+        for style in &mut styles {
+            style.format.apply_theme(theme);
+        }
+        styles
+    }
+}
+
+/// How a cell format differs from the format it's based on
+///
+/// This is how cells store their format. Every field is `None` when it
+/// matches the base. Serialized, unchanged
+/// fields are left out, so a cell based on the default format serializes
+/// exactly like a plain [`CellFormat`], and a cell that only adds bold to
+/// "Currency" stores `{"style":"Currency","bold":true}`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FormatDiff {
+    /// Named style of the format (not diffed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "present")]
+    pub bold: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "present")]
+    pub italic: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "present")]
+    pub underline: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "present")]
+    pub strikethrough: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "present")]
+    pub font_size: Option<Option<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "present")]
+    pub font_family: Option<Option<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "present")]
+    pub text_color: Option<Option<Color>>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "present")]
+    pub background_color: Option<Option<Color>>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "present")]
+    pub horizontal_align: Option<HorizontalAlign>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "present")]
+    pub vertical_align: Option<VerticalAlign>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "present")]
+    pub number_format: Option<Option<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "present")]
+    pub wrap_text: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "present")]
+    pub borders: Option<Borders>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "present")]
+    pub text_theme_color: Option<Option<ThemeColor>>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "present")]
+    pub background_theme_color: Option<Option<ThemeColor>>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "present")]
+    pub theme_font: Option<Option<ThemeFont>>,
+}
+
+/// Deserialize a field that is present, so `null` becomes `Some(None)`
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+fn style_format<'a>(styles: &'a [NamedStyle], name: Option<&str>) -> Option<&'a CellFormat> {
+    let name = name?;
+    styles.iter().find(|s| s.name == name).map(|s| &s.format)
+}
+
+fn changed<T: PartialEq + Clone>(base: &T, value: &T) -> Option<T> {
+    (base != value).then(|| value.clone())
+}
+
+fn assign<T: Clone>(field: &mut T, value: &Option<T>) {
+    if let Some(value) = value {
+        *field = value.clone();
+    }
+}
+
+impl FormatDiff {
+    /// The changes that turn `base` into `format`
+    pub fn between(base: &CellFormat, format: &CellFormat) -> Self {
+        Self {
+            style: format.style.clone(),
+            bold: changed(&base.bold, &format.bold),
+            italic: changed(&base.italic, &format.italic),
+            underline: changed(&base.underline, &format.underline),
+            strikethrough: changed(&base.strikethrough, &format.strikethrough),
+            font_size: changed(&base.font_size, &format.font_size),
+            font_family: changed(&base.font_family, &format.font_family),
+            text_color: changed(&base.text_color, &format.text_color),
+            background_color: changed(&base.background_color, &format.background_color),
+            horizontal_align: changed(&base.horizontal_align, &format.horizontal_align),
+            vertical_align: changed(&base.vertical_align, &format.vertical_align),
+            number_format: changed(&base.number_format, &format.number_format),
+            wrap_text: changed(&base.wrap_text, &format.wrap_text),
+            borders: changed(&base.borders, &format.borders),
+            text_theme_color: changed(&base.text_theme_color, &format.text_theme_color),
+            background_theme_color: changed(
+                &base.background_theme_color,
+                &format.background_theme_color,
+            ),
+            theme_font: changed(&base.theme_font, &format.theme_font),
+        }
+    }
+
+    /// How `format` differs from its named style in `styles` (the default
+    /// format for unstyled formats or unknown styles)
+    pub fn against_style(styles: &[NamedStyle], format: &CellFormat) -> Self {
+        match style_format(styles, format.style.as_deref()) {
+            Some(base) => Self::between(base, format),
+            None => Self::between(&CellFormat::default(), format),
+        }
+    }
+
+    /// Apply the changes on top of their named style in `styles`
+    pub fn resolve(&self, styles: &[NamedStyle]) -> CellFormat {
+        match style_format(styles, self.style.as_deref()) {
+            Some(base) => self.apply(base),
+            None => self.apply(&CellFormat::default()),
+        }
+    }
+
+    /// The number format the changes resolve to against `styles`
+    pub fn number_format<'a>(&'a self, styles: &'a [NamedStyle]) -> Option<&'a str> {
+        match &self.number_format {
+            Some(number_format) => number_format.as_deref(),
+            None => style_format(styles, self.style.as_deref())?.number_format.as_deref(),
+        }
+    }
+
+    /// Apply the changes on top of `base`
+    pub fn apply(&self, base: &CellFormat) -> CellFormat {
+        let mut format = base.clone();
+        format.style = self.style.clone();
+        assign(&mut format.bold, &self.bold);
+        assign(&mut format.italic, &self.italic);
+        assign(&mut format.underline, &self.underline);
+        assign(&mut format.strikethrough, &self.strikethrough);
+        assign(&mut format.font_size, &self.font_size);
+        assign(&mut format.font_family, &self.font_family);
+        assign(&mut format.text_color, &self.text_color);
+        assign(&mut format.background_color, &self.background_color);
+        assign(&mut format.horizontal_align, &self.horizontal_align);
+        assign(&mut format.vertical_align, &self.vertical_align);
+        assign(&mut format.number_format, &self.number_format);
+        assign(&mut format.wrap_text, &self.wrap_text);
+        assign(&mut format.borders, &self.borders);
+        assign(&mut format.text_theme_color, &self.text_theme_color);
+        assign(&mut format.background_theme_color, &self.background_theme_color);
+        assign(&mut format.theme_font, &self.theme_font);
+        format
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtins_follow_theme() {
+        let mut theme = Theme::office();
+        let styles = NamedStyle::builtins(&theme);
+        let heading = styles.iter().find(|s| s.name == "Heading 1").unwrap();
+        assert_eq!(heading.format.text_color, Some(theme.color(ThemeColor::Dark2)));
+
+        let title = styles.iter().find(|s| s.name == "Title").unwrap();
+        assert_eq!(title.format.font_family.as_deref(), Some("Calibri Light"));
+
+        theme.set_color(ThemeColor::Dark2, Color::RED);
+        let mut format = heading.format.clone();
+        format.apply_theme(&theme);
+        assert_eq!(format.text_color, Some(Color::RED));
+    }
+
+    #[test]
+    fn test_format_diff_round_trip() {
+        let base = CellFormat::new()
+            .with_bold(true)
+            .with_font_size(15)
+            .with_text_color(Color::BLUE);
+        let mut format = base.clone().with_italic(true);
+        format.style = Some("Heading 1".to_string());
+        format.bold = false;
+        format.font_size = None;
+
+        let diff = FormatDiff::between(&base, &format);
+        let json = serde_json::to_string(&diff).unwrap();
+        assert_eq!(
+            json,
+            r#"{"style":"Heading 1","bold":false,"italic":true,"font_size":null}"#
+        );
+        let restored: FormatDiff = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, diff);
+        assert_eq!(restored.apply(&base), format);
+
+        // Against the default format, a diff is the plain format
+        let plain = CellFormat::new().with_bold(true).with_text_color(Color::RED);
+        assert_eq!(
+            serde_json::to_string(&FormatDiff::between(&CellFormat::default(), &plain)).unwrap(),
+            serde_json::to_string(&plain).unwrap()
+        );
+    }
+}
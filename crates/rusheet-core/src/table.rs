@@ -0,0 +1,375 @@
+//! Structured tables
+//!
+//! A table is a named range with a header row, an optional totals row,
+//! banded styling and its own sort and filter state. Column names are the
+//! text of the header cells, so editing a header renames its column; the
+//! table itself only records geometry and options. Totals are ordinary
+//! `SUBTOTAL` formulas in the totals row.
+
+use serde::{Deserialize, Serialize};
+
+use crate::format::{CellFormat, Color};
+use crate::outline::Axis;
+use crate::range::{CellCoord, CellRange};
+use crate::sheet::{FilterState, Sheet};
+
+/// Banded styling drawn beneath the cells' own formats
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableStyle {
+    pub header_color: Color,
+    pub header_text_color: Color,
+    pub band_color: Color,
+    #[serde(default = "default_true")]
+    pub banded_rows: bool,
+    #[serde(default)]
+    pub banded_columns: bool,
+    /// Bold the first column
+    #[serde(default)]
+    pub first_column: bool,
+    /// Bold the last column
+    #[serde(default)]
+    pub last_column: bool,
+}
+
+impl Default for TableStyle {
+    fn default() -> Self {
+        Self {
+            header_color: Color::rgb(0x44, 0x72, 0xc4),
+            header_text_color: Color::WHITE,
+            band_color: Color::rgb(0xd9, 0xe1, 0xf2),
+            banded_rows: true,
+            banded_columns: false,
+            first_column: false,
+            last_column: false,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// The column a table was last sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableSort {
+    /// Column offset from the table's first column
+    pub column: u32,
+    pub ascending: bool,
+}
+
+/// Aggregate shown in a totals row cell, as a `SUBTOTAL` function code
+///
+/// The codes ignore rows hidden by filters, so totals follow the filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TotalsFunction {
+    Sum,
+    Average,
+    Count,
+    CountNums,
+    Max,
+    Min,
+    StdDev,
+    Var,
+}
+
+impl TotalsFunction {
+    pub fn code(&self) -> u8 {
+        match self {
+            TotalsFunction::Average => 101,
+            TotalsFunction::CountNums => 102,
+            TotalsFunction::Count => 103,
+            TotalsFunction::Max => 104,
+            TotalsFunction::Min => 105,
+            TotalsFunction::StdDev => 107,
+            TotalsFunction::Sum => 109,
+            TotalsFunction::Var => 110,
+        }
+    }
+
+    /// The totals formula for a column
+    ///
+    /// ```
+    /// use rusheet_core::TotalsFunction;
+    ///
+    /// assert_eq!(
+    ///     TotalsFunction::Sum.formula("Sales", "Amount"),
+    ///     "=SUBTOTAL(109,Sales[Amount])"
+    /// );
+    /// ```
+    pub fn formula(&self, table: &str, column: &str) -> String {
+        format!("=SUBTOTAL({},{}[{}])", self.code(), table, escape_column(column))
+    }
+}
+
+/// Escape `[`, `]`, `#` and `'` in a column name for a structured reference
+pub fn escape_column(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for ch in name.chars() {
+        if matches!(ch, '[' | ']' | '#' | '\'') {
+            escaped.push('\'');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// A named table on a sheet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Table {
+    pub name: String,
+    /// The whole table, from the header row to the totals row
+    pub range: CellRange,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub totals_row: bool,
+    #[serde(default)]
+    pub style: TableStyle,
+    /// Grow the table when typing in the row below or the column to its right
+    #[serde(default = "default_true")]
+    pub auto_expand: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<TableSort>,
+    /// Column filters, with columns as offsets from the table's first column
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<FilterState>,
+}
+
+impl Table {
+    pub fn new(name: impl Into<String>, range: CellRange) -> Self {
+        Self {
+            name: name.into(),
+            range,
+            totals_row: false,
+            style: TableStyle::default(),
+            auto_expand: true,
+            sort: None,
+            filters: Vec::new(),
+        }
+    }
+
+    pub fn with_totals_row(mut self, totals_row: bool) -> Self {
+        self.totals_row = totals_row;
+        self
+    }
+
+    pub fn with_style(mut self, style: TableStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Check a table name: a letter or underscore followed by letters,
+    /// digits, underscores or periods, and not readable as a cell reference
+    pub fn is_valid_name(name: &str) -> bool {
+        let mut chars = name.chars();
+        let Some(first) = chars.next() else {
+            return false;
+        };
+        (first.is_alphabetic() || first == '_')
+            && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+            && name.len() <= 255
+            && !CellCoord::from_a1(name)
+                .is_some_and(|c| c.row < Sheet::MAX_ROWS && c.col < Sheet::MAX_COLS)
+            && !matches!(name.to_ascii_uppercase().as_str(), "R" | "C")
+    }
+
+    pub fn header_row(&self) -> u32 {
+        self.range.start.row
+    }
+
+    pub fn totals_row_index(&self) -> Option<u32> {
+        self.totals_row.then_some(self.range.end.row)
+    }
+
+    /// The rows between the header and totals rows, if there are any
+    pub fn data_range(&self) -> Option<CellRange> {
+        let start = self.range.start.row + 1;
+        let end = self.range.end.row - u32::from(self.totals_row);
+        (start <= end).then(|| {
+            CellRange::new(
+                CellCoord::new(start, self.range.start.col),
+                CellCoord::new(end, self.range.end.col),
+            )
+        })
+    }
+
+    pub fn col_count(&self) -> u32 {
+        self.range.col_count()
+    }
+
+    /// Column names, read from the header cells
+    pub fn column_names(&self, sheet: &Sheet) -> Vec<String> {
+        (self.range.start.col..=self.range.end.col)
+            .map(|col| {
+                sheet
+                    .get_cell_value(CellCoord::new(self.header_row(), col))
+                    .as_text()
+            })
+            .collect()
+    }
+
+    /// Offset of the column called `name`, ignoring case
+    pub fn column_index(&self, sheet: &Sheet, name: &str) -> Option<u32> {
+        let name = name.to_lowercase();
+        self.column_names(sheet)
+            .iter()
+            .position(|column| column.to_lowercase() == name)
+            .map(|i| i as u32)
+    }
+
+    /// The table's styling for a cell, or `None` outside the table
+    pub fn format_at(&self, coord: CellCoord) -> Option<CellFormat> {
+        if !self.range.contains(coord) {
+            return None;
+        }
+        let style = &self.style;
+        let col = coord.col - self.range.start.col;
+        let mut format = CellFormat::new();
+
+        if coord.row == self.header_row() {
+            return Some(
+                format
+                    .with_bold(true)
+                    .with_background_color(style.header_color)
+                    .with_text_color(style.header_text_color),
+            );
+        }
+        if Some(coord.row) == self.totals_row_index() {
+            return Some(format.with_bold(true));
+        }
+
+        let row = coord.row - self.header_row() - 1;
+        if (style.banded_rows && row.is_multiple_of(2)) || (style.banded_columns && col.is_multiple_of(2)) {
+            format.background_color = Some(style.band_color);
+        }
+        if (style.first_column && col == 0) || (style.last_column && col + 1 == self.col_count()) {
+            format.bold = true;
+        }
+        Some(format)
+    }
+
+    /// Adjust the table for `count` rows or columns inserted or deleted at `at`
+    ///
+    /// Inserting inside the table grows it. Returns `false` if the deletion
+    /// removed the header row or every column, which drops the table.
+    pub(crate) fn shift(&mut self, axis: Axis, at: u32, count: u32, insert: bool) -> bool {
+        let (start, end) = match axis {
+            Axis::Rows => (self.range.start.row, self.range.end.row),
+            Axis::Cols => (self.range.start.col, self.range.end.col),
+        };
+        let (new_start, new_end) = if insert {
+            match at {
+                _ if at <= start => (start + count, end + count),
+                _ if at <= end => (start, end + count),
+                _ => (start, end),
+            }
+        } else {
+            let deleted = at..at + count;
+            if (axis == Axis::Rows && deleted.contains(&start))
+                || (deleted.contains(&start) && deleted.contains(&end))
+            {
+                return false;
+            }
+            let shift = |i: u32| match i {
+                _ if i >= at + count => i - count,
+                _ if i >= at => at.saturating_sub(1),
+                _ => i,
+            };
+            let new_start = if deleted.contains(&start) { at } else { shift(start) };
+            (new_start, shift(end).max(new_start))
+        };
+
+        if axis == Axis::Rows && !insert && self.totals_row && (at..at + count).contains(&end) {
+            self.totals_row = false;
+        }
+        if axis == Axis::Cols {
+            let offset = |col: u32| start + col;
+            let moved = |col: u32| -> Option<u32> {
+                let col = offset(col);
+                let new = match (insert, col >= at) {
+                    (_, false) => col,
+                    (true, true) => col + count,
+                    (false, true) if col < at + count => return None,
+                    (false, true) => col - count,
+                };
+                Some(new - new_start)
+            };
+            self.filters = std::mem::take(&mut self.filters)
+                .into_iter()
+                .filter_map(|f| Some(FilterState { col: moved(f.col)?, ..f }))
+                .collect();
+            self.sort = self
+                .sort
+                .and_then(|s| Some(TableSort { column: moved(s.column)?, ..s }));
+        }
+
+        match axis {
+            Axis::Rows => {
+                self.range.start.row = new_start;
+                self.range.end.row = new_end;
+            }
+            Axis::Cols => {
+                self.range.start.col = new_start;
+                self.range.end.col = new_end;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(a1: &str) -> Table {
+        Table::new("Sales", CellRange::from_a1(a1).unwrap())
+    }
+
+    #[test]
+    fn test_names_and_regions() {
+        assert!(Table::is_valid_name("Sales_2024"));
+        assert!(Table::is_valid_name("_t.x"));
+        assert!(!Table::is_valid_name("A1"));
+        assert!(!Table::is_valid_name("xfd1048576"));
+        assert!(Table::is_valid_name("Table1"));
+        assert!(!Table::is_valid_name("2024Sales"));
+        assert!(!Table::is_valid_name("My Table"));
+        assert!(!Table::is_valid_name("r"));
+
+        let t = table("B2:D6").with_totals_row(true);
+        assert_eq!(t.header_row(), 1);
+        assert_eq!(t.totals_row_index(), Some(5));
+        assert_eq!(t.data_range(), CellRange::from_a1("B3:D5"));
+        assert_eq!(table("B2:D2").data_range(), None);
+        assert_eq!(escape_column("Q1 [est]#"), "Q1 '[est']'#");
+
+        assert_eq!(t.format_at(CellCoord::new(1, 1)).unwrap().background_color, Some(t.style.header_color));
+        assert_eq!(t.format_at(CellCoord::new(2, 1)).unwrap().background_color, Some(t.style.band_color));
+        assert_eq!(t.format_at(CellCoord::new(3, 1)).unwrap().background_color, None);
+        assert!(t.format_at(CellCoord::new(5, 2)).unwrap().bold);
+        assert_eq!(t.format_at(CellCoord::new(6, 1)), None);
+    }
+
+    #[test]
+    fn test_shift() {
+        let mut t = table("B2:D6");
+        assert!(t.shift(Axis::Rows, 0, 2, true));
+        assert_eq!(t.range, CellRange::from_a1("B4:D8").unwrap());
+        assert!(t.shift(Axis::Rows, 5, 1, true));
+        assert_eq!(t.range, CellRange::from_a1("B4:D9").unwrap());
+        assert!(t.shift(Axis::Rows, 9, 1, true));
+        assert_eq!(t.range, CellRange::from_a1("B4:D9").unwrap());
+        assert!(t.shift(Axis::Rows, 7, 5, false));
+        assert_eq!(t.range, CellRange::from_a1("B4:D7").unwrap());
+        assert!(!t.clone().shift(Axis::Rows, 3, 1, false));
+
+        t.filters.push(FilterState { col: 2, visible_values: Default::default() });
+        t.sort = Some(TableSort { column: 1, ascending: true });
+        assert!(t.shift(Axis::Cols, 2, 1, false));
+        assert_eq!(t.range, CellRange::from_a1("B4:C7").unwrap());
+        assert_eq!(t.filters[0].col, 1);
+        assert_eq!(t.sort, None);
+        assert!(t.shift(Axis::Cols, 0, 1, false));
+        assert_eq!(t.range, CellRange::from_a1("A4:B7").unwrap());
+        assert!(!t.shift(Axis::Cols, 0, 2, false));
+    }
+}
@@ -1,7 +1,18 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use crate::cell::{Cell, CellContent, CellValue};
+use crate::date::{convert_serial, is_date_format, DateSystem};
+use crate::date_text::DateOrder;
+use crate::format::{Borders, CellFormat};
 use crate::sheet::Sheet;
 use crate::error::RusheetError;
+use crate::hyperlink::LinkTarget;
+use crate::outline::Axis;
+use crate::pivot::PivotTable;
+use crate::range::{CellCoord, CellRange};
+use crate::style::{FormatDiff, NamedStyle, Theme};
+use crate::table::Table;
+use crate::validation::{ListSource, ValidationCriteria};
 
 /// Metadata about the workbook
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -21,18 +32,126 @@ pub struct WorkbookMetadata {
 }
 
 /// A workbook containing multiple sheets
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
 pub struct Workbook {
     /// Workbook name (usually the file name)
     pub name: String,
     /// List of sheets in the workbook
     pub sheets: Vec<Sheet>,
     /// Index of the currently active sheet
-    #[serde(default)]
     pub active_sheet_index: usize,
     /// Workbook metadata
-    #[serde(default)]
     pub metadata: WorkbookMetadata,
+    /// Epoch that date serial numbers count from
+    pub date_system: DateSystem,
+    /// Order of day, month and year when reading typed dates
+    pub date_order: DateOrder,
+    /// User's offset from UTC in minutes, used by TODAY/NOW
+    ///
+    /// The offset is fixed: it doesn't follow daylight saving changes, so it
+    /// has to be set again when the clocks change.
+    pub utc_offset_minutes: i32,
+    /// Color palette and fonts that formats can follow
+    pub theme: Theme,
+    /// Named cell styles, resolved against the theme
+    pub styles: Vec<NamedStyle>,
+    /// Pivot table definitions, refreshed on demand
+    pub pivots: Vec<PivotTable>,
+}
+
+#[derive(Serialize)]
+struct WorkbookRef<'a> {
+    name: &'a str,
+    theme: &'a Theme,
+    styles: &'a [NamedStyle],
+    sheets: &'a [Sheet],
+    active_sheet_index: usize,
+    metadata: &'a WorkbookMetadata,
+    date_system: DateSystem,
+    date_order: DateOrder,
+    utc_offset_minutes: i32,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pivots: &'a [PivotTable],
+}
+
+impl Serialize for Workbook {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        WorkbookRef {
+            name: &self.name,
+            theme: &self.theme,
+            styles: &self.styles,
+            sheets: &self.sheets,
+            active_sheet_index: self.active_sheet_index,
+            metadata: &self.metadata,
+            date_system: self.date_system,
+            date_order: self.date_order,
+            utc_offset_minutes: self.utc_offset_minutes,
+            pivots: &self.pivots,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Workbook {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct WorkbookHelper {
+            name: String,
+            sheets: Vec<Sheet>,
+            #[serde(default)]
+            active_sheet_index: usize,
+            #[serde(default)]
+            metadata: WorkbookMetadata,
+            #[serde(default)]
+            date_system: DateSystem,
+            #[serde(default)]
+            date_order: DateOrder,
+            #[serde(default)]
+            utc_offset_minutes: i32,
+            #[serde(default)]
+            theme: Theme,
+            #[serde(default)]
+            styles: Option<Vec<NamedStyle>>,
+            #[serde(default)]
+            pivots: Vec<PivotTable>,
+        }
+
+        let helper = WorkbookHelper::deserialize(deserializer)?;
+        let styles = helper
+            .styles
+            .unwrap_or_else(|| NamedStyle::builtins(&helper.theme));
+
+        Ok(Self {
+            name: helper.name,
+            sheets: helper.sheets,
+            active_sheet_index: helper.active_sheet_index,
+            metadata: helper.metadata,
+            date_system: helper.date_system,
+            date_order: helper.date_order,
+            utc_offset_minutes: helper.utc_offset_minutes,
+            theme: helper.theme,
+            styles,
+            pivots: helper.pivots,
+        })
+    }
+}
+
+/// Cells overwritten by a pivot table edit: sheet index, cell and the cell
+/// before the edit, in the order they were saved
+pub type ReplacedCells = Vec<(usize, CellCoord, Option<Cell>)>;
+
+fn resolve_format(cell: &Cell, styles: &[NamedStyle], theme: &Theme) -> CellFormat {
+    let mut format = cell.format.resolve(styles);
+    format.apply_theme(theme);
+    format
+}
+
+/// The first of `Table1`, `Table2`, ... not in `taken`
+fn unused_table_name(taken: &[String]) -> String {
+    (1..)
+        .map(|n| format!("Table{}", n))
+        .find(|name| !taken.iter().any(|t| t.eq_ignore_ascii_case(name)))
+        .expect("table numbers are unbounded")
 }
 
 impl Default for Workbook {
@@ -49,6 +168,12 @@ impl Workbook {
             sheets: vec![Sheet::new("Sheet1")],
             active_sheet_index: 0,
             metadata: WorkbookMetadata::default(),
+            date_system: DateSystem::default(),
+            date_order: DateOrder::default(),
+            utc_offset_minutes: 0,
+            theme: Theme::default(),
+            styles: NamedStyle::builtins(&Theme::default()),
+            pivots: Vec::new(),
         }
     }
 
@@ -143,6 +268,8 @@ impl Workbook {
         }
 
         let sheet = self.sheets.remove(index);
+        // Pivot tables go with the sheet they're written to
+        self.pivots.retain(|pivot| pivot.target_sheet != sheet.name);
 
         // Adjust active sheet index if needed
         if self.active_sheet_index >= self.sheets.len() {
@@ -170,7 +297,15 @@ impl Workbook {
         }
 
         if let Some(sheet) = self.sheets.get_mut(index) {
-            sheet.name = new_name;
+            let old_name = std::mem::replace(&mut sheet.name, new_name);
+            for pivot in &mut self.pivots {
+                if pivot.source_sheet == old_name {
+                    pivot.source_sheet = sheet.name.clone();
+                }
+                if pivot.target_sheet == old_name {
+                    pivot.target_sheet = sheet.name.clone();
+                }
+            }
             Ok(())
         } else {
             Err(RusheetError::SheetNotFound(index))
@@ -215,6 +350,14 @@ impl Workbook {
 
         let mut new_sheet = sheet;
         new_sheet.name = new_name;
+        // Table names are unique across the workbook, so the copies get new ones
+        let mut taken = self.table_names();
+        let mut tables = new_sheet.tables().to_vec();
+        for table in &mut tables {
+            table.name = unused_table_name(&taken);
+            taken.push(table.name.clone());
+        }
+        new_sheet.restore_tables(tables);
 
         let new_index = index + 1;
         self.sheets.insert(new_index, new_sheet);
@@ -231,6 +374,441 @@ impl Workbook {
         self.sheets.iter().map(|s| s.name.as_str()).collect()
     }
 
+    /// Find a table on any sheet by name, ignoring case
+    ///
+    /// Returns the index of the sheet it is on and the table.
+    pub fn table(&self, name: &str) -> Option<(usize, &Table)> {
+        self.sheets
+            .iter()
+            .enumerate()
+            .find_map(|(index, sheet)| sheet.table(name).map(|table| (index, table)))
+    }
+
+    fn table_names(&self) -> Vec<String> {
+        self.sheets
+            .iter()
+            .flat_map(|sheet| sheet.tables().iter().map(|t| t.name.clone()))
+            .collect()
+    }
+
+    /// The first of `Table1`, `Table2`, ... not used in the workbook
+    pub fn next_table_name(&self) -> String {
+        unused_table_name(&self.table_names())
+    }
+
+    /// Add a table to a sheet, checking its name is unused in the workbook
+    pub fn add_table(&mut self, sheet_index: usize, table: Table) -> Result<(), RusheetError> {
+        if self.table(&table.name).is_some() {
+            return Err(RusheetError::TableNameExists(table.name));
+        }
+        self.sheets
+            .get_mut(sheet_index)
+            .ok_or(RusheetError::SheetNotFound(sheet_index))?
+            .add_table(table)
+    }
+
+    /// Rename a table
+    ///
+    /// Formulas are not rewritten; `rusheet_formula::rename_table` does both.
+    pub fn rename_table(&mut self, name: &str, new_name: &str) -> Result<(), RusheetError> {
+        if !Table::is_valid_name(new_name) {
+            return Err(RusheetError::InvalidTableName(new_name.to_string()));
+        }
+        let (index, _) = self
+            .table(name)
+            .ok_or_else(|| RusheetError::TableNotFound(name.to_string()))?;
+        if self
+            .table(new_name)
+            .is_some_and(|(_, other)| !other.name.eq_ignore_ascii_case(name))
+        {
+            return Err(RusheetError::TableNameExists(new_name.to_string()));
+        }
+        if let Some(table) = self.sheets[index].table_mut(name) {
+            table.name = new_name.to_string();
+        }
+        Ok(())
+    }
+
+    /// Find a pivot table by name, ignoring case
+    pub fn pivot(&self, name: &str) -> Option<&PivotTable> {
+        self.pivots.iter().find(|p| p.name.eq_ignore_ascii_case(name))
+    }
+
+    fn pivot_index(&self, name: &str) -> Result<usize, RusheetError> {
+        self.pivots
+            .iter()
+            .position(|p| p.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| RusheetError::PivotNotFound(name.to_string()))
+    }
+
+    /// The first of `PivotTable1`, `PivotTable2`, ... not used in the workbook
+    pub fn next_pivot_name(&self) -> String {
+        (1..)
+            .map(|n| format!("PivotTable{}", n))
+            .find(|name| self.pivot(name).is_none())
+            .expect("pivot numbers are unbounded")
+    }
+
+    /// Add a pivot table and write its output
+    ///
+    /// Output that would land on existing data is refused unless `replace`
+    /// is set. Nothing changes if the first refresh fails. Returns the cells
+    /// the output replaced.
+    pub fn add_pivot(&mut self, pivot: PivotTable, replace: bool) -> Result<ReplacedCells, RusheetError> {
+        if self.pivot(&pivot.name).is_some() {
+            return Err(RusheetError::PivotNameExists(pivot.name));
+        }
+        pivot.validate()?;
+        self.pivots.push(PivotTable { output: None, ..pivot });
+        let index = self.pivots.len() - 1;
+        let mut replaced = Vec::new();
+        self.refresh_pivot_at(index, replace, &mut replaced)
+            .inspect_err(|_| {
+                self.pivots.pop();
+            })
+            .map(|_| replaced)
+    }
+
+    /// Replace a pivot table's definition, moving its output if needed
+    ///
+    /// The previous definition and output are kept if the new one fails.
+    pub fn update_pivot(
+        &mut self,
+        name: &str,
+        pivot: PivotTable,
+        replace: bool,
+    ) -> Result<ReplacedCells, RusheetError> {
+        let index = self.pivot_index(name)?;
+        if self
+            .pivot(&pivot.name)
+            .is_some_and(|other| !other.name.eq_ignore_ascii_case(name))
+        {
+            return Err(RusheetError::PivotNameExists(pivot.name));
+        }
+        pivot.validate()?;
+
+        let previous = std::mem::replace(&mut self.pivots[index], PivotTable { output: None, ..pivot });
+        let mut replaced = Vec::new();
+        self.clear_pivot_output(&previous, &mut replaced);
+        match self.refresh_pivot_at(index, replace, &mut replaced) {
+            Ok(_) => Ok(replaced),
+            Err(error) => {
+                self.restore_cells(&replaced);
+                self.pivots[index] = previous;
+                Err(error)
+            }
+        }
+    }
+
+    /// Remove a pivot table and clear its output
+    ///
+    /// Returns the removed definition and the cells that were cleared.
+    pub fn remove_pivot(&mut self, name: &str) -> Result<(PivotTable, ReplacedCells), RusheetError> {
+        let index = self.pivot_index(name)?;
+        let pivot = self.pivots.remove(index);
+        let mut replaced = Vec::new();
+        self.clear_pivot_output(&pivot, &mut replaced);
+        Ok((pivot, replaced))
+    }
+
+    /// Recompute a pivot table from its source and rewrite its output
+    ///
+    /// The previous output is cleared first, so a smaller result doesn't
+    /// leave stale cells behind. Returns the cells the refresh replaced.
+    pub fn refresh_pivot(&mut self, name: &str, replace: bool) -> Result<ReplacedCells, RusheetError> {
+        let index = self.pivot_index(name)?;
+        let mut replaced = Vec::new();
+        self.refresh_pivot_at(index, replace, &mut replaced)
+            .inspect_err(|_| self.restore_cells(&replaced))
+            .map(|_| replaced)
+    }
+
+    /// Refresh every pivot table
+    ///
+    /// Nothing changes if any of them fails.
+    pub fn refresh_pivots(&mut self, replace: bool) -> Result<ReplacedCells, RusheetError> {
+        let before = self.pivots.clone();
+        let mut replaced = Vec::new();
+        for index in 0..self.pivots.len() {
+            if let Err(error) = self.refresh_pivot_at(index, replace, &mut replaced) {
+                self.restore_cells(&replaced);
+                self.pivots = before;
+                return Err(error);
+            }
+        }
+        Ok(replaced)
+    }
+
+    /// Put back cells replaced by a pivot table edit, latest first
+    pub fn restore_cells(&mut self, replaced: &ReplacedCells) {
+        for (sheet, coord, cell) in replaced.iter().rev() {
+            let Some(sheet) = self.sheets.get_mut(*sheet) else {
+                continue;
+            };
+            match cell {
+                Some(cell) => sheet.set_cell(*coord, cell.clone()),
+                None => sheet.remove_cell(*coord),
+            }
+        }
+    }
+
+    /// Move pivot tables to follow rows or columns inserted into or deleted
+    /// from sheet `sheet_index`
+    ///
+    /// Pivot tables whose source header row or output was deleted are
+    /// dropped; their cells stay as plain values.
+    pub fn shift_pivots(&mut self, sheet_index: usize, axis: Axis, at: u32, count: u32, insert: bool) {
+        let Some(sheet) = self.sheets.get(sheet_index) else {
+            return;
+        };
+        let name = sheet.name.clone();
+        self.pivots.retain_mut(|pivot| pivot.shift(&name, axis, at, count, insert));
+    }
+
+    fn pivot_sheet(&self, name: &str) -> Result<usize, RusheetError> {
+        self.get_sheet_index(name)
+            .ok_or_else(|| RusheetError::InvalidPivot(format!("Sheet '{}' not found", name)))
+    }
+
+    /// Record the cells in `range` before they are overwritten
+    fn save_cells(&self, sheet: usize, range: CellRange, replaced: &mut ReplacedCells) {
+        replaced.extend(
+            range
+                .iter()
+                .map(|coord| (sheet, coord, self.sheets[sheet].get_cell(coord).cloned())),
+        );
+    }
+
+    fn clear_pivot_output(&mut self, pivot: &PivotTable, replaced: &mut ReplacedCells) {
+        let (Some(output), Some(sheet)) = (pivot.output, self.get_sheet_index(&pivot.target_sheet)) else {
+            return;
+        };
+        self.save_cells(sheet, output, replaced);
+        pivot.clear(&mut self.sheets[sheet]);
+    }
+
+    fn refresh_pivot_at(
+        &mut self,
+        index: usize,
+        replace: bool,
+        replaced: &mut ReplacedCells,
+    ) -> Result<CellRange, RusheetError> {
+        let pivot = &self.pivots[index];
+        let source = self.pivot_sheet(&pivot.source_sheet)?;
+        let target = self.pivot_sheet(&pivot.target_sheet)?;
+        let grid = pivot.compute(&self.sheets[source], self.date_system)?;
+
+        let height = grid.len() as u32;
+        let width = grid[0].len() as u32;
+        let (end_row, end_col) = (
+            pivot.target.row.checked_add(height - 1),
+            pivot.target.col.checked_add(width - 1),
+        );
+        let range = match (end_row, end_col) {
+            (Some(row), Some(col)) if row < Sheet::MAX_ROWS && col < Sheet::MAX_COLS => {
+                CellRange::new(pivot.target, CellCoord::new(row, col))
+            }
+            _ => return Err(RusheetError::RangeOutOfBounds),
+        };
+        let sheet = &self.sheets[target];
+        let overlaps = (source == target && range.intersects(&pivot.source))
+            || sheet.tables().iter().any(|table| table.range.intersects(&range))
+            || sheet.would_overlap_merge(&range)
+            || self.pivots.iter().enumerate().any(|(i, other)| {
+                i != index
+                    && other.target_sheet == pivot.target_sheet
+                    && other.output.is_some_and(|output| output.intersects(&range))
+            });
+        if overlaps {
+            return Err(RusheetError::PivotOverlap);
+        }
+        // The pivot table's own previous output doesn't count as data
+        let occupied = range.iter().any(|coord| {
+            !pivot.output.is_some_and(|output| output.contains(coord))
+                && sheet.get_cell(coord).is_some_and(|cell| !cell.content.is_empty())
+        });
+        if occupied && !replace {
+            return Err(RusheetError::PivotTargetNotEmpty);
+        }
+
+        let pivot = self.pivots[index].clone();
+        self.clear_pivot_output(&pivot, replaced);
+        self.save_cells(target, range, replaced);
+        pivot.write(&mut self.sheets[target], &grid);
+        self.pivots[index].output = Some(range);
+        Ok(range)
+    }
+
+    /// Switch the workbook's date system
+    ///
+    /// Numeric values in cells with a date number format are shifted so they
+    /// keep showing the same calendar date. Formulas are left untouched and
+    /// should be recalculated by the caller. Returns the number of converted cells.
+    pub fn set_date_system(&mut self, system: DateSystem) -> usize {
+        let from = self.date_system;
+        if from == system {
+            return 0;
+        }
+        self.date_system = system;
+
+        let mut converted = 0;
+        for sheet in &mut self.sheets {
+            let coords: Vec<_> = sheet.non_empty_coords().collect();
+            for coord in coords {
+                let cell = sheet.get_cell_mut(coord);
+                let is_date = cell
+                    .format
+                    .number_format(&self.styles)
+                    .is_some_and(is_date_format);
+                if !is_date {
+                    continue;
+                }
+                if let CellContent::Value {
+                    value: CellValue::Number(n),
+                    ..
+                } = &mut cell.content
+                {
+                    *n = convert_serial(*n, from, system);
+                    converted += 1;
+                }
+            }
+        }
+        converted
+    }
+
+    /// Get a named style
+    pub fn style(&self, name: &str) -> Option<&NamedStyle> {
+        self.styles.iter().find(|s| s.name == name)
+    }
+
+    /// The format that applies a named style to a cell
+    pub fn style_format(&self, name: &str) -> Option<CellFormat> {
+        let mut format = self.style(name)?.format.clone();
+        format.style = Some(name.to_string());
+        Some(format)
+    }
+
+    /// Resolve a cell's format against its named style and the theme
+    pub fn cell_format(&self, cell: &Cell) -> CellFormat {
+        resolve_format(cell, &self.styles, &self.theme)
+    }
+
+    /// Add or redefine a named style
+    ///
+    /// The style's theme colors and font are resolved and its borders are
+    /// dropped. Cells based on the style follow the new definition, keeping
+    /// their own overrides. Returns the number of cells whose format changed.
+    pub fn set_style(&mut self, mut style: NamedStyle) -> usize {
+        style.format.style = None;
+        style.format.borders = Borders::default();
+        style.format.apply_theme(&self.theme);
+
+        let previous = self.styles.clone();
+        match self.styles.iter_mut().find(|s| s.name == style.name) {
+            Some(existing) => *existing = style,
+            None => self.styles.push(style),
+        }
+        self.restyled(&previous, &self.theme)
+    }
+
+    /// Remove a named style
+    ///
+    /// Cells based on it keep their look but no longer follow the style.
+    pub fn remove_style(&mut self, name: &str) -> Option<NamedStyle> {
+        let index = self.styles.iter().position(|s| s.name == name)?;
+        let removed = self.styles.remove(index);
+        for sheet in &mut self.sheets {
+            let coords: Vec<_> = sheet.non_empty_coords().collect();
+            for coord in coords {
+                let cell = sheet.get_cell_mut(coord);
+                if cell.format.style.as_deref() == Some(name) {
+                    let mut format = cell.format.apply(&removed.format);
+                    format.style = None;
+                    cell.format = FormatDiff::between(&CellFormat::default(), &format);
+                }
+            }
+        }
+        Some(removed)
+    }
+
+    /// Switch the workbook theme
+    ///
+    /// Styles are re-resolved against it, and cells follow it through their
+    /// styles and their own theme colors and fonts. Returns the number of
+    /// cells whose format changed.
+    pub fn set_theme(&mut self, theme: Theme) -> usize {
+        let previous_theme = std::mem::replace(&mut self.theme, theme);
+        let previous = self.styles.clone();
+        for style in &mut self.styles {
+            style.format.apply_theme(&self.theme);
+        }
+        self.restyled(&previous, &previous_theme)
+    }
+
+    /// Count the cells whose format differs from what it resolved to with
+    /// the `previous` styles and theme
+    fn restyled(&self, previous: &[NamedStyle], previous_theme: &Theme) -> usize {
+        self.sheets
+            .iter()
+            .flat_map(|sheet| sheet.non_empty_coords().filter_map(|coord| sheet.get_cell(coord)))
+            .filter(|cell| {
+                resolve_format(cell, previous, previous_theme) != self.cell_format(cell)
+            })
+            .count()
+    }
+
+    /// Rewrite every formula in the workbook
+    ///
+    /// `rewrite` is called with each cell formula, custom validation formula,
+    /// validation list range and hyperlink location (as `=Sheet2!A1:A5`), and
+    /// returns the replacement or `None` to leave it unchanged. Cached formula values are
+    /// kept, so the caller should recalculate. Returns the number of rewritten
+    /// formulas.
+    pub fn rewrite_formulas(&mut self, mut rewrite: impl FnMut(&str) -> Option<String>) -> usize {
+        let mut rewritten = 0;
+        for sheet in &mut self.sheets {
+            let coords: Vec<_> = sheet.non_empty_coords().collect();
+            for coord in coords {
+                let cell = sheet.get_cell_mut(coord);
+                if let CellContent::Formula { expression, .. } = &mut cell.content {
+                    if let Some(new_expression) = rewrite(expression) {
+                        *expression = new_expression;
+                        rewritten += 1;
+                    }
+                }
+                let link = cell.hyperlink.as_mut().map(|link| &mut link.target);
+                if let Some(LinkTarget::Location(location)) = link {
+                    if let Some(new_location) = rewrite(&format!("={}", location)) {
+                        *location = new_location.trim_start_matches('=').to_string();
+                        rewritten += 1;
+                    }
+                }
+            }
+
+            for rule in &mut sheet.data_validation {
+                match &mut rule.criteria {
+                    ValidationCriteria::Custom { formula } => {
+                        if let Some(new_formula) = rewrite(formula) {
+                            *formula = new_formula;
+                            rewritten += 1;
+                        }
+                    }
+                    ValidationCriteria::List {
+                        source: ListSource::Range { reference },
+                        ..
+                    } => {
+                        if let Some(new_reference) = rewrite(&format!("={}", reference)) {
+                            *reference = new_reference.trim_start_matches('=').to_string();
+                            rewritten += 1;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        rewritten
+    }
+
     /// Serialize the workbook to JSON
     pub fn to_json(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string(self)
@@ -319,4 +897,249 @@ mod tests {
         assert_eq!(wb2.name, "Test");
         assert_eq!(wb2.sheet_count(), 2);
     }
+
+    #[test]
+    fn test_set_date_system_converts_dates() {
+        use crate::range::CellCoord;
+
+        let mut wb = Workbook::new("Test");
+        let sheet = wb.active_sheet_mut();
+
+        // 2024-01-01 formatted as a date
+        let date_cell = sheet.get_cell_mut(CellCoord::new(0, 0));
+        date_cell.content = CellContent::Value {
+            value: CellValue::Number(45292.0),
+            original_input: None,
+        };
+        date_cell.format.number_format = Some(Some("yyyy-mm-dd".to_string()));
+
+        // Plain number is left alone
+        let plain_cell = sheet.get_cell_mut(CellCoord::new(1, 0));
+        plain_cell.content = CellContent::Value {
+            value: CellValue::Number(45292.0),
+            original_input: None,
+        };
+
+        assert_eq!(wb.set_date_system(DateSystem::Excel1904), 1);
+        assert_eq!(wb.date_system, DateSystem::Excel1904);
+        let sheet = wb.active_sheet();
+        assert_eq!(
+            sheet.get_cell(CellCoord::new(0, 0)).unwrap().content.computed_value(),
+            &CellValue::Number(43830.0)
+        );
+        assert_eq!(
+            sheet.get_cell(CellCoord::new(1, 0)).unwrap().content.computed_value(),
+            &CellValue::Number(45292.0)
+        );
+
+        // Setting the same system again is a no-op
+        assert_eq!(wb.set_date_system(DateSystem::Excel1904), 0);
+        assert_eq!(wb.set_date_system(DateSystem::Excel1900), 1);
+        assert_eq!(
+            wb.active_sheet().get_cell(CellCoord::new(0, 0)).unwrap().content.computed_value(),
+            &CellValue::Number(45292.0)
+        );
+
+        // Date system survives serialization
+        wb.set_date_system(DateSystem::Excel1904);
+        let wb2 = Workbook::from_json(&wb.to_json().unwrap()).unwrap();
+        assert_eq!(wb2.date_system, DateSystem::Excel1904);
+    }
+
+    #[test]
+    fn test_rewrite_formulas() {
+        use crate::hyperlink::Hyperlink;
+        use crate::range::{CellCoord, CellRange};
+        use crate::validation::DataValidationRule;
+
+        let mut wb = Workbook::new("Test");
+        let sheet = wb.active_sheet_mut();
+        sheet.set_cell_value(CellCoord::new(0, 0), "=A2+1");
+        sheet.set_cell_value(CellCoord::new(1, 0), "5");
+        sheet.set_hyperlink(CellCoord::new(2, 0), Some(Hyperlink::location("A2")));
+        sheet.set_hyperlink(CellCoord::new(3, 0), Some(Hyperlink::url("https://a2.example")));
+        sheet.add_data_validation(DataValidationRule::new(
+            "list".to_string(),
+            CellRange::new(CellCoord::new(0, 1), CellCoord::new(5, 1)),
+            ValidationCriteria::List {
+                source: ListSource::Range {
+                    reference: "A2:A5".to_string(),
+                },
+                show_dropdown: true,
+            },
+        ));
+
+        let count = wb.rewrite_formulas(|formula| Some(formula.replace("A2", "A3")));
+        assert_eq!(count, 3);
+
+        let sheet = wb.active_sheet();
+        assert_eq!(
+            sheet.get_cell(CellCoord::new(0, 0)).unwrap().content.formula_expression(),
+            Some("=A3+1")
+        );
+        assert_eq!(
+            sheet.get_data_validation_rules()[0].criteria,
+            ValidationCriteria::List {
+                source: ListSource::Range {
+                    reference: "A3:A5".to_string(),
+                },
+                show_dropdown: true,
+            }
+        );
+        assert_eq!(
+            sheet.get_hyperlink(CellCoord::new(2, 0)),
+            Some(&Hyperlink::location("A3"))
+        );
+        assert_eq!(
+            sheet.get_hyperlink(CellCoord::new(3, 0)),
+            Some(&Hyperlink::url("https://a2.example"))
+        );
+    }
+
+    #[test]
+    fn test_named_styles_and_theme() {
+        use crate::format::Color;
+        use crate::range::CellCoord;
+        use crate::style::ThemeColor;
+
+        let mut wb = Workbook::new("Test");
+        let a1 = CellCoord::new(0, 0);
+        let b1 = CellCoord::new(0, 1);
+        let currency = FormatDiff::against_style(
+            &wb.styles,
+            &wb.style_format("Currency").unwrap().with_bold(true),
+        );
+        let heading = FormatDiff::against_style(&wb.styles, &wb.style_format("Heading 1").unwrap());
+        let sheet = wb.active_sheet_mut();
+        sheet.set_cell_value(a1, "12.5");
+        sheet.get_cell_mut(a1).format = currency;
+        sheet.get_cell_mut(b1).format = heading;
+
+        // Cells hold their style and overrides, which is also all that's saved
+        let format_of = |wb: &Workbook, coord| wb.cell_format(wb.active_sheet().get_cell(coord).unwrap());
+        let a1_format = &wb.active_sheet().get_cell(a1).unwrap().format;
+        assert_eq!(a1_format.style.as_deref(), Some("Currency"));
+        assert_eq!(a1_format.bold, Some(true));
+        assert_eq!(a1_format.number_format, None);
+        let json = wb.to_json().unwrap();
+        assert!(json.contains(r#""format":{"style":"Currency","bold":true}"#));
+        let restored = Workbook::from_json(&json).unwrap();
+        assert_eq!(format_of(&restored, a1), format_of(&wb, a1));
+
+        // Redefining a style restyles its cells and keeps their overrides
+        let mut euro = wb.style("Currency").unwrap().clone();
+        euro.format.number_format = Some("#,##0.00 €".to_string());
+        assert_eq!(wb.set_style(euro), 1);
+        let format = format_of(&wb, a1);
+        assert_eq!(format.number_format.as_deref(), Some("#,##0.00 €"));
+        assert!(format.bold);
+
+        // Cells resolve against the saved styles wherever they appear in the file
+        let value: serde_json::Value = serde_json::from_str(&wb.to_json().unwrap()).unwrap();
+        let json = value.to_string();
+        assert!(json.find(r#""sheets""#) < json.find(r#""styles""#));
+        let restored = Workbook::from_json(&json).unwrap();
+        let format = format_of(&restored, a1);
+        assert_eq!(format.number_format.as_deref(), Some("#,##0.00 €"));
+        assert!(format.bold);
+
+        // Heading colors follow the theme
+        let mut theme = wb.theme.clone();
+        theme.set_color(ThemeColor::Dark2, Color::RED);
+        assert_eq!(wb.set_theme(theme), 1);
+        assert_eq!(wb.style("Heading 1").unwrap().format.text_color, Some(Color::RED));
+        assert_eq!(format_of(&wb, b1).text_color, Some(Color::RED));
+
+        // Removing a style leaves the look in place
+        let before = format_of(&wb, b1);
+        assert!(wb.remove_style("Heading 1").is_some());
+        let after = format_of(&wb, b1);
+        assert_eq!(after.style, None);
+        assert_eq!(after.text_color, before.text_color);
+        assert!(after.bold);
+    }
+
+    #[test]
+    fn test_pivot_tables() {
+        use crate::pivot::{PivotAggregation, PivotField, PivotValueField};
+
+        let mut wb = Workbook::new("Test");
+        wb.add_sheet("Report").unwrap();
+        for (r, row) in [["Region", "Amount"], ["East", "10"], ["West", "20"], ["East", "5"]].iter().enumerate() {
+            for (c, value) in row.iter().enumerate() {
+                wb.sheets[0].set_cell_value(CellCoord::new(r as u32, c as u32), value);
+            }
+        }
+        let pivot = PivotTable::new(
+            wb.next_pivot_name(),
+            "Sheet1",
+            CellRange::from_a1("A1:B4").unwrap(),
+            "Report",
+            CellCoord::new(0, 0),
+        )
+        .with_rows(vec![PivotField::new(0)])
+        .with_values(vec![PivotValueField::new(1, PivotAggregation::Sum)]);
+        assert!(wb.add_pivot(pivot.clone(), false).is_ok());
+        assert_eq!(wb.pivots[0].name, "PivotTable1");
+        assert_eq!(wb.pivots[0].output, CellRange::from_a1("A1:B4"));
+        assert_eq!(
+            wb.add_pivot(pivot.clone(), false).unwrap_err(),
+            RusheetError::PivotNameExists("PivotTable1".to_string())
+        );
+        let value = |wb: &Workbook, a1: &str| wb.sheets[1].get_cell_value(CellCoord::from_a1(a1).unwrap()).clone();
+        assert_eq!(value(&wb, "B2"), CellValue::Number(15.0));
+
+        // Output doesn't change until refreshed
+        wb.sheets[0].set_cell_value(CellCoord::new(2, 0), "East");
+        assert_eq!(value(&wb, "B2"), CellValue::Number(15.0));
+        let before = wb.pivots.clone();
+        let replaced = wb.refresh_pivot("pivottable1", false).unwrap();
+        assert_eq!(wb.pivots[0].output, CellRange::from_a1("A1:B3"));
+        assert_eq!(value(&wb, "B2"), CellValue::Number(35.0));
+        assert_eq!(value(&wb, "A4"), CellValue::Empty);
+
+        // Restoring the replaced cells and definitions undoes the refresh
+        wb.restore_cells(&replaced);
+        wb.pivots = before;
+        assert_eq!(value(&wb, "B2"), CellValue::Number(15.0));
+        assert_eq!(value(&wb, "A4"), CellValue::Text("Grand Total".to_string()));
+        wb.refresh_pivot("PivotTable1", false).unwrap();
+        assert_eq!(value(&wb, "A4"), CellValue::Empty);
+
+        // Overlapping output is rejected and the old definition kept
+        let mut moved = pivot.clone();
+        moved.target_sheet = "Sheet1".to_string();
+        assert_eq!(wb.update_pivot("PivotTable1", moved, true).unwrap_err(), RusheetError::PivotOverlap);
+        assert_eq!(value(&wb, "B2"), CellValue::Number(35.0));
+
+        // Existing data is only overwritten when asked to
+        wb.sheets[1].set_cell_value(CellCoord::from_a1("A5").unwrap(), "Notes");
+        let mut longer = pivot.clone();
+        longer.target = CellCoord::from_a1("A3").unwrap();
+        assert_eq!(
+            wb.update_pivot("PivotTable1", longer.clone(), false).unwrap_err(),
+            RusheetError::PivotTargetNotEmpty
+        );
+        assert_eq!(value(&wb, "A5"), CellValue::Text("Notes".to_string()));
+        assert_eq!(value(&wb, "B2"), CellValue::Number(35.0));
+        let before = wb.pivots.clone();
+        let replaced = wb.update_pivot("PivotTable1", longer, true).unwrap();
+        assert_eq!(value(&wb, "A5"), CellValue::Text("Grand Total".to_string()));
+        wb.restore_cells(&replaced);
+        wb.pivots = before;
+        assert_eq!(value(&wb, "A5"), CellValue::Text("Notes".to_string()));
+        wb.sheets[1].remove_cell(CellCoord::from_a1("A5").unwrap());
+
+        // Definitions persist and follow sheet renames
+        wb.rename_sheet(0, "Data").unwrap();
+        assert_eq!(wb.pivots[0].source_sheet, "Data");
+        let restored = Workbook::from_json(&wb.to_json().unwrap()).unwrap();
+        assert_eq!(restored.pivots, wb.pivots);
+
+        let (removed, _) = wb.remove_pivot("PivotTable1").unwrap();
+        assert_eq!(value(&wb, "A1"), CellValue::Empty);
+        wb.add_pivot(removed, false).unwrap();
+        wb.remove_sheet(1).unwrap();
+        assert!(wb.pivots.is_empty());
+    }
 }
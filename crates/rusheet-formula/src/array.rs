@@ -0,0 +1,174 @@
+use rusheet_core::{CellError, CellValue};
+
+/// A two-dimensional block of values produced by evaluating an expression
+/// in array context
+///
+/// Values are stored row by row. Arrays read from a range remember the
+/// range's top-left cell, which implicit intersection uses to find the
+/// value in line with the formula's cell.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Array {
+    rows: usize,
+    cols: usize,
+    values: Vec<CellValue>,
+    origin: Option<(u32, u32)>,
+}
+
+impl Array {
+    /// Create an array from row-major values
+    ///
+    /// # Panics
+    /// Panics if `values.len()` is not `rows * cols`.
+    pub fn new(rows: usize, cols: usize, values: Vec<CellValue>) -> Self {
+        assert_eq!(values.len(), rows * cols, "array values must fill rows * cols");
+        Self {
+            rows,
+            cols,
+            values,
+            origin: None,
+        }
+    }
+
+    /// Wrap a single value as a 1x1 array
+    pub fn from_scalar(value: CellValue) -> Self {
+        Self::new(1, 1, vec![value])
+    }
+
+    /// Record the (row, col) of the top-left cell this array was read from
+    pub fn with_origin(mut self, row: u32, col: u32) -> Self {
+        self.origin = Some((row, col));
+        self
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Top-left cell of the range this array was read from, if any
+    pub fn origin(&self) -> Option<(u32, u32)> {
+        self.origin
+    }
+
+    /// Get the value at (row, col), or None if out of bounds
+    pub fn get(&self, row: usize, col: usize) -> Option<&CellValue> {
+        if row < self.rows && col < self.cols {
+            self.values.get(row * self.cols + col)
+        } else {
+            None
+        }
+    }
+
+    /// Consume the array, returning its values row by row
+    pub fn into_values(self) -> Vec<CellValue> {
+        self.values
+    }
+
+    /// Apply a function to every element
+    pub fn map(self, f: impl Fn(CellValue) -> CellValue) -> Self {
+        Self {
+            values: self.values.into_iter().map(f).collect(),
+            ..self
+        }
+    }
+
+    /// Combine two arrays element by element
+    ///
+    /// Follows Excel's broadcasting rules: a single row is repeated down
+    /// every row and a single column across every column, so a 1xN and an
+    /// Nx1 array combine into an NxN result. When the sizes otherwise
+    /// disagree the result takes the larger size in each direction and
+    /// positions missing from either operand are #N/A.
+    pub fn broadcast(
+        left: &Array,
+        right: &Array,
+        f: impl Fn(&CellValue, &CellValue) -> CellValue,
+    ) -> Array {
+        let rows = left.rows.max(right.rows);
+        let cols = left.cols.max(right.cols);
+
+        let mut values = Vec::with_capacity(rows * cols);
+        for row in 0..rows {
+            for col in 0..cols {
+                values.push(match (left.broadcast_get(row, col), right.broadcast_get(row, col)) {
+                    (Some(a), Some(b)) => f(a, b),
+                    _ => CellValue::Error(CellError::NotAvailable),
+                });
+            }
+        }
+
+        // Keep the position of whichever operand already has the result's shape
+        let origin = [left, right]
+            .into_iter()
+            .find(|a| a.rows == rows && a.cols == cols && a.origin.is_some())
+            .and_then(|a| a.origin);
+
+        Array {
+            rows,
+            cols,
+            values,
+            origin,
+        }
+    }
+
+    /// Element lookup that repeats single rows and columns
+    fn broadcast_get(&self, row: usize, col: usize) -> Option<&CellValue> {
+        let row = if self.rows == 1 { 0 } else { row };
+        let col = if self.cols == 1 { 0 } else { col };
+        self.get(row, col)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(rows: usize, cols: usize, values: &[f64]) -> Array {
+        Array::new(rows, cols, values.iter().map(|n| CellValue::Number(*n)).collect())
+    }
+
+    fn add(a: &CellValue, b: &CellValue) -> CellValue {
+        CellValue::Number(a.as_number().unwrap() + b.as_number().unwrap())
+    }
+
+    #[test]
+    fn test_broadcast_same_shape() {
+        let a = numbers(3, 1, &[1.0, 2.0, 3.0]);
+        let b = numbers(3, 1, &[10.0, 20.0, 30.0]);
+        assert_eq!(Array::broadcast(&a, &b, add), numbers(3, 1, &[11.0, 22.0, 33.0]));
+    }
+
+    #[test]
+    fn test_broadcast_scalar() {
+        let a = numbers(2, 2, &[1.0, 2.0, 3.0, 4.0]).with_origin(4, 1);
+        let b = Array::from_scalar(CellValue::Number(10.0));
+        let result = Array::broadcast(&a, &b, add);
+        assert_eq!(result.origin(), Some((4, 1)));
+        assert_eq!(result.into_values(), numbers(2, 2, &[11.0, 12.0, 13.0, 14.0]).into_values());
+    }
+
+    #[test]
+    fn test_broadcast_row_by_column() {
+        // 1x3 combined with 3x1 gives a 3x3 table
+        let row = numbers(1, 3, &[1.0, 2.0, 3.0]);
+        let col = numbers(3, 1, &[10.0, 20.0, 30.0]);
+        let result = Array::broadcast(&row, &col, add);
+        assert_eq!((result.rows(), result.cols()), (3, 3));
+        assert_eq!(result.get(0, 2), Some(&CellValue::Number(13.0)));
+        assert_eq!(result.get(2, 0), Some(&CellValue::Number(31.0)));
+        assert_eq!(result.origin(), None);
+    }
+
+    #[test]
+    fn test_broadcast_mismatched_sizes() {
+        let a = numbers(3, 1, &[1.0, 2.0, 3.0]);
+        let b = numbers(2, 1, &[10.0, 20.0]);
+        let result = Array::broadcast(&a, &b, add);
+        assert_eq!(result.rows(), 3);
+        assert_eq!(result.get(1, 0), Some(&CellValue::Number(22.0)));
+        assert_eq!(result.get(2, 0), Some(&CellValue::Error(CellError::NotAvailable)));
+    }
+}
@@ -44,6 +44,109 @@ pub enum Expr {
 
     // Parenthesized expression
     Grouped(Box<Expr>),
+
+    // Array constant (e.g., {1,2,3;4,5,6}), stored row by row
+    Array(Vec<Vec<Expr>>),
+
+    // Reference union (e.g., (A1:A5,C1:C5)), a multi-area reference
+    Union(Vec<Expr>),
+
+    // Reference intersection (e.g., A1:C5 B2:D4), the cells common to both
+    Intersection {
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+
+    // Structured table reference (e.g., Sales[Amount], Sales[#Totals], [@Amount])
+    Structured(StructuredRef),
+}
+
+/// Rows of a table that a structured reference covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableArea {
+    /// `[#Data]`, the default
+    Data,
+    /// `[#All]`: the header, data and totals rows
+    All,
+    Headers,
+    Totals,
+    /// `[#Headers],[#Data]`
+    HeadersAndData,
+    /// `[#Data],[#Totals]`
+    DataAndTotals,
+    /// `[@Column]`: the data row the formula is on
+    ThisRow,
+}
+
+impl TableArea {
+    /// The specifiers that select this area, as written in a reference
+    pub fn specifiers(&self) -> &'static [&'static str] {
+        match self {
+            TableArea::Data => &["#Data"],
+            TableArea::All => &["#All"],
+            TableArea::Headers => &["#Headers"],
+            TableArea::Totals => &["#Totals"],
+            TableArea::HeadersAndData => &["#Headers", "#Data"],
+            TableArea::DataAndTotals => &["#Data", "#Totals"],
+            TableArea::ThisRow => &["#This Row"],
+        }
+    }
+}
+
+/// A reference to part of a table by name, e.g. `Sales[[#Totals],[Amount]]`
+///
+/// Structured references are resolved to cell ranges against the table's
+/// current geometry when the formula is evaluated, so they follow the table
+/// as it grows or moves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructuredRef {
+    /// The table, or `None` for the table containing the formula (`[@Amount]`)
+    pub table: Option<String>,
+    pub area: TableArea,
+    /// First and last column names, or `None` for every column
+    pub columns: Option<(String, String)>,
+}
+
+impl std::fmt::Display for StructuredRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use rusheet_core::table::escape_column;
+
+        let bracketed = |name: &str| format!("[{}]", escape_column(name));
+        let simple = |name: &str| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+        };
+        let span = |(first, last): &(String, String)| {
+            if first == last {
+                bracketed(first)
+            } else {
+                format!("{}:{}", bracketed(first), bracketed(last))
+            }
+        };
+
+        write!(f, "{}[", self.table.as_deref().unwrap_or(""))?;
+        match (self.area, &self.columns) {
+            (TableArea::ThisRow, None) => write!(f, "@")?,
+            (TableArea::ThisRow, Some((first, last))) if first == last && simple(first) => {
+                write!(f, "@{}", escape_column(first))?
+            }
+            (TableArea::ThisRow, Some(columns)) => write!(f, "@{}", span(columns))?,
+            (TableArea::Data, Some((first, last))) if first == last && simple(first) => {
+                write!(f, "{}", escape_column(first))?
+            }
+            (TableArea::Data, Some(columns)) => write!(f, "{}", span(columns))?,
+            (area, None) if area.specifiers().len() == 1 => write!(f, "{}", area.specifiers()[0])?,
+            (area, columns) => {
+                let mut items: Vec<String> =
+                    area.specifiers().iter().map(|s| format!("[{}]", s)).collect();
+                items.extend(columns.as_ref().map(span));
+                write!(f, "{}", items.join(","))?
+            }
+        }
+        write!(f, "]")
+    }
 }
 
 /// Binary operators
@@ -92,6 +195,9 @@ pub enum UnaryOp {
     Neg,     // -
     Pos,     // +
     Percent, // %
+    /// Implicit intersection (`@A1:A10`): the single cell of a range that
+    /// lines up with the formula's own row or column
+    ImplicitIntersection, // @
 }
 
 impl Expr {
@@ -147,6 +253,183 @@ impl Expr {
             end: Box::new(end),
         }
     }
+
+    /// Create a reference intersection expression
+    pub fn intersection(left: Expr, right: Expr) -> Self {
+        Expr::Intersection {
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    /// The expression inside any enclosing parentheses
+    pub fn strip_groups(&self) -> &Expr {
+        match self {
+            Expr::Grouped(inner) => inner.strip_groups(),
+            other => other,
+        }
+    }
+
+    /// Canonical form of this expression
+    ///
+    /// Removes parentheses, which the tree's shape already makes redundant,
+    /// uppercases function names and folds negated numbers into negative
+    /// literals, so `-1` parsed and `-1` built as a number agree. Formulas
+    /// that differ only in those respects normalize to the same expression.
+    pub fn normalized(&self) -> Expr {
+        let boxed = |e: &Expr| Box::new(e.normalized());
+        match self {
+            Expr::Grouped(inner) => inner.normalized(),
+            Expr::Unary {
+                op: UnaryOp::Neg,
+                operand,
+            } => match operand.normalized() {
+                Expr::Number(n) => Expr::Number(-n),
+                operand => Expr::unary(UnaryOp::Neg, operand),
+            },
+            Expr::Range { start, end } => Expr::Range {
+                start: boxed(start),
+                end: boxed(end),
+            },
+            Expr::SheetRef {
+                sheet_name,
+                reference,
+            } => Expr::SheetRef {
+                sheet_name: sheet_name.clone(),
+                reference: boxed(reference),
+            },
+            Expr::Binary { .. } => {
+                let (first, links) = self.chain();
+                links.into_iter().fold(first.normalized(), |left, (op, right)| {
+                    Expr::binary(left, op, right.normalized())
+                })
+            }
+            Expr::Unary { op, operand } => Expr::Unary {
+                op: *op,
+                operand: boxed(operand),
+            },
+            Expr::FunctionCall { name, args } => Expr::FunctionCall {
+                name: name.to_uppercase(),
+                args: args.iter().map(Expr::normalized).collect(),
+            },
+            Expr::Array(rows) => Expr::Array(
+                rows.iter()
+                    .map(|row| row.iter().map(Expr::normalized).collect())
+                    .collect(),
+            ),
+            Expr::Union(areas) => Expr::Union(areas.iter().map(Expr::normalized).collect()),
+            Expr::Intersection { left, right } => Expr::Intersection {
+                left: boxed(left),
+                right: boxed(right),
+            },
+            leaf => leaf.clone(),
+        }
+    }
+
+    /// Number of levels in the expression tree; a literal or reference is 1
+    ///
+    /// An operator chain such as `A1+A2+A3` is one level, since chains are
+    /// worked through in a loop rather than by recursion; see [`Expr::chain`].
+    ///
+    /// Walks the tree without recursion, so it is safe on trees too deep to
+    /// evaluate.
+    pub fn depth(&self) -> usize {
+        let mut deepest = 0;
+        let mut pending = vec![(self, 1)];
+        while let Some((expr, depth)) = pending.pop() {
+            deepest = deepest.max(depth);
+            let children = match expr {
+                Expr::Binary { .. } => {
+                    let (first, links) = expr.chain();
+                    pending.push((first, depth + 1));
+                    links.into_iter().map(|(_, right)| right).collect()
+                }
+                _ => expr.children(),
+            };
+            pending.extend(children.into_iter().map(|child| (child, depth + 1)));
+        }
+        deepest
+    }
+
+    /// Check whether the expression calls the function `name`, given in
+    /// upper case as the parser stores function names
+    ///
+    /// Text that merely contains the name, such as `"SUBTOTAL("`, doesn't
+    /// count. Walks the tree without recursion.
+    pub fn calls_function(&self, name: &str) -> bool {
+        let mut pending = vec![self];
+        while let Some(expr) = pending.pop() {
+            if matches!(expr, Expr::FunctionCall { name: called, .. } if called == name) {
+                return true;
+            }
+            pending.extend(expr.children());
+        }
+        false
+    }
+
+    /// The sub-expressions directly below this one
+    fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Range { start, end } => vec![start, end],
+            Expr::SheetRef { reference, .. } => vec![reference],
+            Expr::Binary { left, right, .. } | Expr::Intersection { left, right } => {
+                vec![left, right]
+            }
+            Expr::Unary { operand, .. } => vec![operand],
+            Expr::Grouped(inner) => vec![inner],
+            Expr::FunctionCall { args, .. } | Expr::Union(args) => args.iter().collect(),
+            Expr::Array(rows) => rows.iter().flatten().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Split a chain of binary operations of one precedence, such as
+    /// `A1+A2-A3`, into its first operand and each operator with its right
+    /// operand, in formula order
+    ///
+    /// Chains nest through their left operands and can run to thousands of
+    /// links, so tree walkers go through them with this rather than
+    /// recursing. Anything but a binary operation is a chain of one operand.
+    pub fn chain(&self) -> (&Expr, Vec<(BinaryOp, &Expr)>) {
+        let mut links: Vec<(BinaryOp, &Expr)> = Vec::new();
+        let mut first = self;
+        while let Expr::Binary { left, op, right } = first {
+            match links.last() {
+                Some(&(last, _)) if last.precedence() != op.precedence() => break,
+                _ => {}
+            }
+            links.push((*op, right.as_ref()));
+            first = left;
+        }
+        links.reverse();
+        (first, links)
+    }
+
+    /// Check whether this expression evaluates to a reference rather than a value
+    pub fn is_reference(&self) -> bool {
+        match self {
+            Expr::CellRef { .. } | Expr::Range { .. } | Expr::SheetRef { .. } => true,
+            Expr::Structured(_) => true,
+            Expr::Union(_) | Expr::Intersection { .. } => true,
+            Expr::Grouped(inner) => inner.is_reference(),
+            _ => false,
+        }
+    }
+}
+
+/// A sheet name as written in a reference, quoted unless the parser accepts it bare
+pub(crate) fn quote_sheet_name(name: &str) -> std::borrow::Cow<'_, str> {
+    let bare = !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        // Would otherwise read as a number or boolean
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && !name.eq_ignore_ascii_case("TRUE")
+        && !name.eq_ignore_ascii_case("FALSE");
+    if bare {
+        name.into()
+    } else {
+        format!("'{}'", name.replace('\'', "''")).into()
+    }
 }
 
 impl std::fmt::Display for Expr {
@@ -162,7 +445,7 @@ impl std::fmt::Display for Expr {
             }
             Expr::String(s) => write!(f, "\"{}\"", s.replace('"', "\"\"")),
             Expr::Boolean(b) => write!(f, "{}", if *b { "TRUE" } else { "FALSE" }),
-            Expr::Error(e) => write!(f, "{:?}", e),
+            Expr::Error(e) => write!(f, "{}", e),
             Expr::CellRef {
                 col,
                 row,
@@ -186,20 +469,21 @@ impl std::fmt::Display for Expr {
                 sheet_name,
                 reference,
             } => {
-                // Quote sheet name if it contains spaces or special chars
-                if sheet_name.contains(' ') || sheet_name.contains('!') {
-                    write!(f, "'{}'!{}", sheet_name, reference)
-                } else {
-                    write!(f, "{}!{}", sheet_name, reference)
-                }
+                write!(f, "{}!{}", quote_sheet_name(sheet_name), reference)
             }
-            Expr::Binary { left, op, right } => {
-                write!(f, "{}{}{}", left, op, right)
+            Expr::Binary { .. } => {
+                let (first, links) = self.chain();
+                write!(f, "{}", first)?;
+                for (op, right) in links {
+                    write!(f, "{}{}", op, right)?;
+                }
+                Ok(())
             }
             Expr::Unary { op, operand } => match op {
                 UnaryOp::Neg => write!(f, "-{}", operand),
                 UnaryOp::Pos => write!(f, "+{}", operand),
                 UnaryOp::Percent => write!(f, "{}%", operand),
+                UnaryOp::ImplicitIntersection => write!(f, "@{}", operand),
             },
             Expr::FunctionCall { name, args } => {
                 write!(f, "{}(", name)?;
@@ -212,6 +496,32 @@ impl std::fmt::Display for Expr {
                 write!(f, ")")
             }
             Expr::Grouped(inner) => write!(f, "({})", inner),
+            Expr::Array(rows) => {
+                write!(f, "{{")?;
+                for (i, row) in rows.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ";")?;
+                    }
+                    for (j, item) in row.iter().enumerate() {
+                        if j > 0 {
+                            write!(f, ",")?;
+                        }
+                        write!(f, "{}", item)?;
+                    }
+                }
+                write!(f, "}}")
+            }
+            Expr::Union(areas) => {
+                for (i, area) in areas.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", area)?;
+                }
+                Ok(())
+            }
+            Expr::Intersection { left, right } => write!(f, "{} {}", left, right),
+            Expr::Structured(reference) => write!(f, "{}", reference),
         }
     }
 }
@@ -0,0 +1,996 @@
+//! Incremental recalculation engine
+//!
+//! [`CalcEngine`] owns a [`Workbook`] together with the dependency graph of
+//! every sheet's formulas, so an edit also reaches formulas on other sheets
+//! that read it. Edits mark cells dirty; [`CalcEngine::recalculate`] then
+//! re-evaluates the dirty cells and everything downstream of them in a
+//! single batch, and reports which cells' computed values actually changed.
+//!
+//! The wasm engine sits on top of it. The undo history only rewrites formula
+//! text and leaves recalculation to the caller, and the collaboration server
+//! stores workbook content as sent by clients and evaluates nothing, so
+//! neither uses the engine.
+//!
+//! The `parallel` feature is opt-in and nothing in this workspace turns it
+//! on. Native users who enable it get independent cells evaluated on
+//! rayon's thread pool; wasm builds must leave it off.
+
+use std::collections::HashSet;
+
+use rusheet_core::{
+    parse_cell_input_with, CellContent, CellCoord, CellError, CellRange, CellValue, Hyperlink,
+    Workbook,
+};
+
+use crate::ast::Expr;
+use crate::dependency::DependencyGraph;
+use crate::functions::datetime::DateContext;
+use crate::evaluator::{CrossSheetEvaluator, RowVisibility};
+use crate::collect_references_cross_sheet;
+use crate::limits::EvalLimits;
+use crate::parser_nom::{NomParser, ParseError, ParseErrorKind};
+use crate::structured::{has_structured_refs, resolve_structured_refs};
+use crate::trace::{with_source_text, TraceNode};
+
+type ValueLookup<'a> = Box<dyn Fn(Option<&str>, u32, u32) -> CellValue + 'a>;
+type ContentLookup<'a> = Box<dyn Fn(Option<&str>, u32, u32) -> Option<CellContent> + 'a>;
+type RowLookup<'a> = Box<dyn Fn(Option<&str>, u32) -> RowVisibility + 'a>;
+
+/// A cell by sheet index, row and column
+type SheetCell = (usize, u32, u32);
+
+/// A table's sheet, name, range, totals row flag and column names
+type TableLayout = (usize, String, CellRange, bool, Vec<String>);
+
+/// Smallest level worth splitting across threads
+#[cfg(feature = "parallel")]
+const PARALLEL_MIN_LEVEL_SIZE: usize = 64;
+
+/// Cells whose computed value changed during a recalculation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangeReport {
+    /// Changed cells with their sheet index, sorted by sheet, row then column
+    pub changed: Vec<(usize, CellCoord)>,
+}
+
+impl ChangeReport {
+    /// Check if no cell changed
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty()
+    }
+
+    /// Number of changed cells
+    pub fn len(&self) -> usize {
+        self.changed.len()
+    }
+
+    /// Check if the given cell on sheet `sheet_index` changed
+    pub fn contains(&self, sheet_index: usize, coord: CellCoord) -> bool {
+        self.changed
+            .binary_search_by_key(&(sheet_index, coord.row, coord.col), |&(s, c)| (s, c.row, c.col))
+            .is_ok()
+    }
+
+    /// Changed cells on sheet `sheet_index`, sorted by row then column
+    pub fn on_sheet(&self, sheet_index: usize) -> Vec<CellCoord> {
+        self.changed
+            .iter()
+            .filter(|&&(sheet, _)| sheet == sheet_index)
+            .map(|&(_, coord)| coord)
+            .collect()
+    }
+
+    fn from_cells(cells: HashSet<SheetCell>) -> Self {
+        Self {
+            changed: sorted_sheet_coords(cells),
+        }
+    }
+}
+
+/// Recalculation engine owning a workbook and its dependency graph
+#[derive(Debug)]
+pub struct CalcEngine {
+    workbook: Workbook,
+    dep_graph: DependencyGraph<SheetCell>,
+    /// Cells edited since the last recalculation
+    dirty: HashSet<SheetCell>,
+    /// Resolve ranges in scalar context by implicit intersection
+    implicit_intersection: bool,
+    /// Current time in milliseconds since the Unix epoch, or `None` for the system clock
+    clock: fn() -> Option<f64>,
+    /// Resource limits for parsing and evaluating each formula
+    limits: EvalLimits,
+    /// Formula cells with structured references
+    table_formulas: HashSet<SheetCell>,
+    /// Tables as they were when structured references were last resolved
+    table_layout: Vec<TableLayout>,
+    /// Formula cells that call SUBTOTAL
+    subtotal_formulas: HashSet<SheetCell>,
+}
+
+impl Default for CalcEngine {
+    fn default() -> Self {
+        Self::new(Workbook::default())
+    }
+}
+
+impl CalcEngine {
+    /// Create an engine for a workbook and build its dependency graph
+    ///
+    /// Cached formula values are trusted as loaded; call
+    /// [`recalculate_all`](Self::recalculate_all) to refresh them.
+    pub fn new(workbook: Workbook) -> Self {
+        let mut engine = Self {
+            workbook,
+            dep_graph: DependencyGraph::new(),
+            dirty: HashSet::new(),
+            implicit_intersection: false,
+            clock: || None,
+            limits: EvalLimits::default(),
+            table_formulas: HashSet::new(),
+            table_layout: Vec::new(),
+            subtotal_formulas: HashSet::new(),
+        };
+        engine.rebuild_dependencies();
+        engine
+    }
+
+    /// Get the workbook
+    pub fn workbook(&self) -> &Workbook {
+        &self.workbook
+    }
+
+    /// Get the workbook for editing
+    ///
+    /// Report edited cells with [`cell_changed`](Self::cell_changed), or call
+    /// [`rebuild_dependencies`](Self::rebuild_dependencies) after structural
+    /// changes, so the next recalculation picks them up.
+    pub fn workbook_mut(&mut self) -> &mut Workbook {
+        &mut self.workbook
+    }
+
+    /// Replace the workbook, rebuilding the dependency graph
+    pub fn set_workbook(&mut self, workbook: Workbook) {
+        self.workbook = workbook;
+        self.rebuild_dependencies();
+    }
+
+    /// Consume the engine, returning its workbook
+    pub fn into_workbook(self) -> Workbook {
+        self.workbook
+    }
+
+    /// Get the dependency graph, keyed by sheet index, row and column
+    pub fn dependency_graph(&self) -> &DependencyGraph<(usize, u32, u32)> {
+        &self.dep_graph
+    }
+
+    /// Switch the active sheet
+    pub fn set_active_sheet(&mut self, index: usize) -> bool {
+        self.workbook.set_active_sheet(index)
+    }
+
+    /// Set the user's offset from UTC in minutes, used by TODAY/NOW
+    ///
+    /// The offset is saved with the workbook. It is fixed, so daylight
+    /// saving changes aren't followed.
+    pub fn set_utc_offset(&mut self, minutes: i32) {
+        self.workbook.utc_offset_minutes = minutes;
+    }
+
+    /// Resolve ranges used where one value is expected by implicit intersection
+    pub fn set_implicit_intersection(&mut self, enabled: bool) {
+        self.implicit_intersection = enabled;
+    }
+
+    /// Check if implicit intersection is enabled
+    pub fn implicit_intersection(&self) -> bool {
+        self.implicit_intersection
+    }
+
+    /// Set the clock used by TODAY/NOW, for targets without a system clock
+    pub fn set_clock(&mut self, clock: fn() -> Option<f64>) {
+        self.clock = clock;
+    }
+
+    /// Set the resource limits for parsing and evaluating each formula
+    ///
+    /// Takes effect at the next recalculation.
+    pub fn set_limits(&mut self, limits: EvalLimits) {
+        self.limits = limits;
+    }
+
+    /// Get the resource limits for each formula
+    pub fn limits(&self) -> EvalLimits {
+        self.limits
+    }
+
+    /// Date system and clock for evaluating date/time functions
+    pub fn date_context(&self) -> DateContext {
+        DateContext {
+            date_system: self.workbook.date_system,
+            date_order: self.workbook.date_order,
+            utc_offset_minutes: self.workbook.utc_offset_minutes,
+            now_unix_ms: (self.clock)(),
+        }
+    }
+
+    /// Parse and store user input in a cell on the active sheet, then recalculate
+    ///
+    /// Dates and times are read with the workbook's date order and system,
+    /// and give the cell a matching number format.
+    pub fn set_cell_input(&mut self, coord: CellCoord, input: &str) -> ChangeReport {
+        let (content, number_format) =
+            parse_cell_input_with(input, self.workbook.date_order, self.workbook.date_system);
+        let workbook = &mut self.workbook;
+        let sheet = &mut workbook.sheets[workbook.active_sheet_index];
+        let cell = sheet.get_cell_mut(coord);
+        cell.content = content;
+        cell.rich_text = None;
+        cell.assign_input_format(number_format, &workbook.styles);
+        if sheet.get_cell(coord).is_some_and(|c| c.is_empty()) {
+            sheet.remove_cell(coord);
+        }
+
+        self.cell_changed(coord);
+        self.recalculate()
+    }
+
+    /// Mark a cell for recalculation without re-reading its formula
+    pub fn mark_dirty(&mut self, coord: CellCoord) {
+        self.dirty.insert(self.on_active_sheet(coord));
+    }
+
+    /// Record that a cell's content on the active sheet was edited
+    ///
+    /// Updates the cell's dependencies and marks it dirty.
+    pub fn cell_changed(&mut self, coord: CellCoord) {
+        self.sheet_cell_changed(self.on_active_sheet(coord));
+    }
+
+    /// Record that several cells were edited
+    pub fn cells_changed(&mut self, coords: impl IntoIterator<Item = CellCoord>) {
+        for coord in coords {
+            self.cell_changed(coord);
+        }
+    }
+
+    /// Record that rows were hidden, shown or filtered
+    ///
+    /// SUBTOTAL skips hidden rows, so its formulas are marked dirty.
+    pub fn rows_visibility_changed(&mut self) {
+        self.dirty.extend(self.subtotal_formulas.iter().copied());
+    }
+
+    /// Evaluate a formula cell on the active sheet step by step
+    ///
+    /// The trace's text and spans follow the formula as typed where they
+    /// can. Returns `None` if the cell holds no formula or it doesn't parse.
+    pub fn trace_cell(&self, coord: CellCoord) -> Option<TraceNode> {
+        let cell = self.on_active_sheet(coord);
+        let formula = self.formula_at(cell)?;
+        let ast = self.parser().parse(formula).ok()?;
+        let evaluator = self.evaluator(cell, self.date_context());
+        // Keep the steps as written, with table references resolved for evaluation
+        let trace = if has_structured_refs(&ast) {
+            evaluator.trace_resolved(&ast, &self.resolve_tables(&ast, cell))
+        } else {
+            evaluator.trace(&ast)
+        };
+        Some(with_source_text(trace, formula))
+    }
+
+    /// The link a cell on the active sheet opens
+    ///
+    /// That is the cell's own hyperlink, or else the target of a formula
+    /// that is a `HYPERLINK` call.
+    pub fn hyperlink_at(&self, coord: CellCoord) -> Option<Hyperlink> {
+        let cell = self.workbook.active_sheet().get_cell(coord)?;
+        if let Some(link) = &cell.hyperlink {
+            return Some(link.clone());
+        }
+
+        let at = self.on_active_sheet(coord);
+        let mut ast = self
+            .parse_formula(cell.content.formula_expression()?, at)
+            .ok()?;
+        while let Expr::Grouped(inner) = ast {
+            ast = *inner;
+        }
+        let Expr::FunctionCall { name, args } = &ast else {
+            return None;
+        };
+        if !name.eq_ignore_ascii_case("HYPERLINK") {
+            return None;
+        }
+        match self.evaluator(at, self.date_context()).evaluate(args.first()?) {
+            CellValue::Empty | CellValue::Error(_) => None,
+            address => Some(Hyperlink::parse(&address.as_text())),
+        }
+    }
+
+    /// Resolve a hyperlink location to a sheet index and range
+    ///
+    /// Unqualified references are on the active sheet. Returns `None` for
+    /// missing sheets and locations that aren't a cell or range reference.
+    pub fn resolve_location(&self, location: &str) -> Option<(usize, CellRange)> {
+        let (sheet_index, reference) = match self.parser().parse(location).ok()? {
+            Expr::SheetRef { sheet_name, reference } => {
+                (self.workbook.get_sheet_index(&sheet_name)?, *reference)
+            }
+            reference => (self.workbook.active_sheet_index, reference),
+        };
+        let corner = |expr: &Expr| match expr {
+            Expr::CellRef { row, col, .. } => Some(CellCoord::new(*row, *col)),
+            _ => None,
+        };
+        let range = match &reference {
+            Expr::Range { start, end } => CellRange::new(corner(start)?, corner(end)?),
+            cell => {
+                let coord = corner(cell)?;
+                CellRange::new(coord, coord)
+            }
+        };
+        Some((sheet_index, range))
+    }
+
+    /// Cells that a formula cell on the active sheet reads directly, on any
+    /// sheet, as (sheet index, cell)
+    pub fn precedents(&self, coord: CellCoord) -> Vec<(usize, CellCoord)> {
+        let cell = self.on_active_sheet(coord);
+        sorted_sheet_coords(self.dep_graph.get_direct_dependencies(cell).into_iter().flatten().copied())
+    }
+
+    /// Formula cells, on any sheet, that read a cell on the active sheet
+    /// directly, as (sheet index, cell)
+    pub fn dependents(&self, coord: CellCoord) -> Vec<(usize, CellCoord)> {
+        let cell = self.on_active_sheet(coord);
+        sorted_sheet_coords(self.dep_graph.get_direct_dependents(cell).into_iter().flatten().copied())
+    }
+
+    /// Rebuild the dependency graph from every sheet's formulas
+    ///
+    /// Call this after sheets are added, renamed, moved or deleted, since
+    /// references find their sheet by name.
+    pub fn rebuild_dependencies(&mut self) {
+        self.dep_graph.clear();
+        self.table_formulas.clear();
+        self.subtotal_formulas.clear();
+        self.table_layout = table_layout(&self.workbook);
+
+        for cell in formula_cells(&self.workbook) {
+            self.update_dependencies(cell);
+        }
+    }
+
+    /// Re-read a cell's dependencies and mark it dirty
+    fn sheet_cell_changed(&mut self, cell: SheetCell) {
+        self.update_dependencies(cell);
+        self.dirty.insert(cell);
+    }
+
+    /// Re-read the dependencies of a cell
+    ///
+    /// References to sheets that don't exist aren't tracked.
+    fn update_dependencies(&mut self, cell: SheetCell) {
+        let mut structured = false;
+        let mut subtotal = false;
+        let deps = match self.formula_at(cell).map(|f| self.parser().parse(f)) {
+            Some(Ok(ast)) => {
+                subtotal = ast.calls_function("SUBTOTAL");
+                structured = has_structured_refs(&ast);
+                let ast = if structured { self.resolve_tables(&ast, cell) } else { ast };
+                collect_references_cross_sheet(&ast, None)
+                    .into_iter()
+                    .filter_map(|(sheet, row, col)| {
+                        let index = match sheet {
+                            Some(name) => self.workbook.get_sheet_index(&name)?,
+                            None => cell.0,
+                        };
+                        Some((index, row, col))
+                    })
+                    .collect()
+            }
+            _ => HashSet::new(),
+        };
+        self.dep_graph.set_dependencies(cell, deps);
+        if subtotal {
+            self.subtotal_formulas.insert(cell);
+        } else {
+            self.subtotal_formulas.remove(&cell);
+        }
+        if structured {
+            self.table_formulas.insert(cell);
+        } else {
+            self.table_formulas.remove(&cell);
+        }
+    }
+
+    /// Re-resolve structured references if a table changed shape
+    ///
+    /// Tables grow, shrink and rename columns without their formulas being
+    /// edited, so formulas that name them are checked on every pass.
+    fn refresh_table_formulas(&mut self) {
+        let layout = table_layout(&self.workbook);
+        if layout == self.table_layout {
+            return;
+        }
+        self.table_layout = layout;
+        let formulas: Vec<_> = self.table_formulas.iter().copied().collect();
+        for cell in formulas {
+            self.sheet_cell_changed(cell);
+        }
+    }
+
+    /// Recalculate dirty cells and their dependents in one batch
+    ///
+    /// Dirty cells are always reported as changed, since the engine does not
+    /// see their value before the edit. Formula cells are reported only when
+    /// their computed value differs from the cached one. Formulas caught in a
+    /// circular reference evaluate to #CIRCULAR!.
+    pub fn recalculate(&mut self) -> ChangeReport {
+        self.refresh_table_formulas();
+        let starts: Vec<_> = self.dirty.drain().collect();
+        let mut changed: HashSet<SheetCell> = starts.iter().copied().collect();
+        self.recalculate_from(&starts, &mut changed);
+
+        ChangeReport::from_cells(changed)
+    }
+
+    /// Recalculate every formula in the workbook
+    pub fn recalculate_all(&mut self) -> ChangeReport {
+        self.refresh_table_formulas();
+        self.dirty.clear();
+        let mut changed = HashSet::new();
+
+        let starts = formula_cells(&self.workbook);
+        self.recalculate_from(&starts, &mut changed);
+
+        ChangeReport::from_cells(changed)
+    }
+
+    /// Evaluate `starts` and their dependents in dependency order
+    fn recalculate_from(&mut self, starts: &[SheetCell], changed: &mut HashSet<SheetCell>) {
+        match self.dep_graph.get_recalc_levels(starts.iter().copied()) {
+            Ok(levels) => self.evaluate_levels(&levels, changed),
+            Err(_) => {
+                // Keep cells outside the cycle calculating normally
+                for &start in starts {
+                    match self.dep_graph.get_recalc_levels([start]) {
+                        Ok(levels) => self.evaluate_levels(&levels, changed),
+                        Err(_) => {
+                            for cell in self.dep_graph.get_affected_cells([start]) {
+                                let circular = CellValue::Error(CellError::CircularReference);
+                                if self.store_result(cell, circular) {
+                                    changed.insert(cell);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Evaluate each level against the results of the levels before it
+    ///
+    /// Cells within a level don't read each other, so with the `parallel`
+    /// feature they are evaluated across threads. Results are written back in
+    /// the level's sorted order either way.
+    fn evaluate_levels(&mut self, levels: &[Vec<SheetCell>], changed: &mut HashSet<SheetCell>) {
+        let date_context = self.date_context();
+
+        for level in levels {
+            let evaluate = |&cell: &SheetCell| {
+                self.evaluate_cell(cell, date_context)
+                    .map(|result| (cell, result))
+            };
+
+            #[cfg(feature = "parallel")]
+            let results: Vec<_> = {
+                use rayon::prelude::*;
+                if level.len() >= PARALLEL_MIN_LEVEL_SIZE {
+                    level.par_iter().filter_map(evaluate).collect()
+                } else {
+                    level.iter().filter_map(evaluate).collect()
+                }
+            };
+            #[cfg(not(feature = "parallel"))]
+            let results: Vec<_> = level.iter().filter_map(evaluate).collect();
+
+            for (cell, result) in results {
+                if self.store_result(cell, result) {
+                    changed.insert(cell);
+                }
+            }
+        }
+    }
+
+    /// Evaluate one formula cell without storing the result
+    ///
+    /// Returns `None` if the cell holds no formula.
+    fn evaluate_cell(&self, cell: SheetCell, date_context: DateContext) -> Option<CellValue> {
+        let expression = self.formula_at(cell)?;
+        let result = match self.parse_formula(expression, cell) {
+            Ok(ast) => self.evaluator(cell, date_context).evaluate(&ast),
+            Err(e) if matches!(e.kind, ParseErrorKind::TooDeep | ParseErrorKind::TooLong) => {
+                CellValue::Error(CellError::CalcError)
+            }
+            Err(_) => CellValue::Error(CellError::InvalidValue),
+        };
+        Some(result)
+    }
+
+    fn parser(&self) -> NomParser {
+        NomParser::new().with_limits(&self.limits)
+    }
+
+    /// Parse the formula in `cell`, resolving its table references
+    fn parse_formula(&self, expression: &str, cell: SheetCell) -> Result<Expr, ParseError> {
+        let ast = self.parser().parse(expression)?;
+        if has_structured_refs(&ast) {
+            Ok(self.resolve_tables(&ast, cell))
+        } else {
+            Ok(ast)
+        }
+    }
+
+    fn resolve_tables(&self, expr: &Expr, (sheet, row, col): SheetCell) -> Expr {
+        resolve_structured_refs(expr, &self.workbook, sheet, CellCoord::new(row, col))
+    }
+
+    fn formula_at(&self, (sheet, row, col): SheetCell) -> Option<&str> {
+        self.workbook
+            .get_sheet(sheet)?
+            .get_cell(CellCoord::new(row, col))?
+            .content
+            .formula_expression()
+    }
+
+    fn on_active_sheet(&self, coord: CellCoord) -> SheetCell {
+        (self.workbook.active_sheet_index, coord.row, coord.col)
+    }
+
+    /// Evaluator for a formula in `cell`
+    fn evaluator(
+        &self,
+        (sheet_index, row, col): SheetCell,
+        date_context: DateContext,
+    ) -> CrossSheetEvaluator<ValueLookup<'_>, ContentLookup<'_>, RowLookup<'_>> {
+        let resolve_sheet = move |name: Option<&str>| match name {
+            Some(name) => self.workbook.get_sheet_by_name(name),
+            None => self.workbook.get_sheet(sheet_index),
+        };
+        let get_cell_value = move |sheet_name: Option<&str>, r, c| {
+            let sheet = match resolve_sheet(sheet_name) {
+                Some(s) => s,
+                None => return CellValue::Error(CellError::InvalidReference),
+            };
+            sheet
+                .get_cell(CellCoord::new(r, c))
+                .map(|c| c.computed_value().clone())
+                .unwrap_or(CellValue::Empty)
+        };
+        let get_cell_content = move |sheet_name: Option<&str>, r, c| {
+            resolve_sheet(sheet_name)?
+                .get_cell(CellCoord::new(r, c))
+                .map(|c| c.content.clone())
+        };
+
+        let get_row_visibility = move |sheet_name: Option<&str>, r| {
+            match resolve_sheet(sheet_name) {
+                Some(sheet) if sheet.is_row_filtered(r) => RowVisibility::Filtered,
+                Some(sheet) if sheet.is_row_hidden(r) => RowVisibility::Hidden,
+                _ => RowVisibility::Visible,
+            }
+        };
+
+        let get_cell_value: ValueLookup = Box::new(get_cell_value);
+        let get_cell_content: ContentLookup = Box::new(get_cell_content);
+        let get_row_visibility: RowLookup = Box::new(get_row_visibility);
+        let sheet_name = self.workbook.get_sheet(sheet_index).map_or("", |s| s.name.as_str());
+        CrossSheetEvaluator::with_sheet(get_cell_value, sheet_name)
+            .with_cell_content(get_cell_content)
+            .with_row_visibility(get_row_visibility)
+            .with_date_context(date_context)
+            .with_current_cell(row, col)
+            .with_implicit_intersection(self.implicit_intersection)
+            .with_limits(self.limits)
+    }
+
+    /// Write a formula's computed value, returning whether it changed
+    fn store_result(&mut self, (sheet, row, col): SheetCell, result: CellValue) -> bool {
+        let coord = CellCoord::new(row, col);
+        let Some(sheet) = self.workbook.get_sheet_mut(sheet) else {
+            return false;
+        };
+        let Some(cell) = sheet.get_cell(coord) else {
+            return false;
+        };
+        if !cell.content.is_formula() {
+            return false;
+        }
+
+        if let CellContent::Formula { cached_value, .. } = &mut sheet.get_cell_mut(coord).content {
+            if *cached_value != result {
+                *cached_value = result;
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Every formula cell in the workbook
+/// `cells` as (sheet index, cell), sorted by sheet, row and column
+fn sorted_sheet_coords(cells: impl IntoIterator<Item = SheetCell>) -> Vec<(usize, CellCoord)> {
+    let mut coords: Vec<_> = cells
+        .into_iter()
+        .map(|(sheet, row, col)| (sheet, CellCoord::new(row, col)))
+        .collect();
+    coords.sort_unstable_by_key(|&(sheet, c)| (sheet, c.row, c.col));
+    coords
+}
+
+fn formula_cells(workbook: &Workbook) -> Vec<SheetCell> {
+    let mut cells = Vec::new();
+    for (index, sheet) in workbook.sheets.iter().enumerate() {
+        let formulas = sheet
+            .non_empty_coords()
+            .filter(|&coord| sheet.get_cell(coord).is_some_and(|c| c.content.is_formula()))
+            .map(|coord| (index, coord.row, coord.col));
+        cells.extend(formulas);
+    }
+    cells
+}
+
+fn table_layout(workbook: &Workbook) -> Vec<TableLayout> {
+    let mut layout = Vec::new();
+    for (index, sheet) in workbook.sheets.iter().enumerate() {
+        for table in sheet.tables() {
+            let columns = table.column_names(sheet);
+            layout.push((index, table.name.clone(), table.range, table.totals_row, columns));
+        }
+    }
+    layout
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusheet_core::{Axis, DateOrder, DateSystem, Sheet};
+
+    fn value_at(engine: &CalcEngine, row: u32, col: u32) -> CellValue {
+        engine
+            .workbook()
+            .active_sheet()
+            .get_cell_value(CellCoord::new(row, col))
+            .clone()
+    }
+
+    #[test]
+    fn test_set_cell_input_recalculates_dependents() {
+        let mut engine = CalcEngine::default();
+        engine.set_cell_input(CellCoord::new(0, 0), "5");
+        engine.set_cell_input(CellCoord::new(0, 1), "=A1*2");
+        engine.set_cell_input(CellCoord::new(0, 2), "=B1+1");
+        assert_eq!(value_at(&engine, 0, 2), CellValue::Number(11.0));
+
+        let report = engine.set_cell_input(CellCoord::new(0, 0), "10");
+        assert_eq!(
+            report.on_sheet(0),
+            vec![CellCoord::new(0, 0), CellCoord::new(0, 1), CellCoord::new(0, 2)]
+        );
+        assert_eq!(value_at(&engine, 0, 2), CellValue::Number(21.0));
+    }
+
+    #[test]
+    fn test_report_skips_unchanged_values() {
+        let mut engine = CalcEngine::default();
+        engine.set_cell_input(CellCoord::new(0, 0), "5");
+        engine.set_cell_input(CellCoord::new(0, 1), "=A1>0");
+        engine.set_cell_input(CellCoord::new(0, 2), "=B1");
+
+        // B1 stays TRUE, so C1 is not reported
+        let report = engine.set_cell_input(CellCoord::new(0, 0), "7");
+        assert_eq!(report.changed, vec![(0, CellCoord::new(0, 0))]);
+        assert!(!report.contains(0, CellCoord::new(0, 2)));
+    }
+
+    #[test]
+    fn test_batched_dirty_cells() {
+        let mut engine = CalcEngine::default();
+        engine.set_cell_input(CellCoord::new(2, 0), "=SUM(A1:A2)");
+
+        let sheet = engine.workbook_mut().active_sheet_mut();
+        sheet.set_cell_value(CellCoord::new(0, 0), "1");
+        sheet.set_cell_value(CellCoord::new(1, 0), "2");
+        engine.cells_changed([CellCoord::new(0, 0), CellCoord::new(1, 0)]);
+
+        // Nothing is evaluated until the batch runs
+        assert_eq!(value_at(&engine, 2, 0), CellValue::Number(0.0));
+
+        let report = engine.recalculate();
+        assert_eq!(report.len(), 3);
+        assert_eq!(value_at(&engine, 2, 0), CellValue::Number(3.0));
+
+        // The dirty set is cleared by a recalculation
+        assert!(engine.recalculate().is_empty());
+    }
+
+    #[test]
+    fn test_circular_reference() {
+        let mut engine = CalcEngine::default();
+        engine.set_cell_input(CellCoord::new(0, 0), "=B1");
+        engine.set_cell_input(CellCoord::new(0, 1), "=A1");
+        engine.set_cell_input(CellCoord::new(5, 5), "=1+1");
+
+        let circular = CellValue::Error(CellError::CircularReference);
+        assert_eq!(value_at(&engine, 0, 0), circular);
+        assert_eq!(value_at(&engine, 0, 1), circular);
+        assert_eq!(value_at(&engine, 5, 5), CellValue::Number(2.0));
+
+        // Breaking the cycle recovers
+        engine.set_cell_input(CellCoord::new(0, 1), "3");
+        assert_eq!(value_at(&engine, 0, 0), CellValue::Number(3.0));
+    }
+
+    #[test]
+    fn test_recalculate_all_orders_by_dependency() {
+        let mut sheet = Sheet::new("Sheet1");
+        // C1 depends on B1 which depends on A1, stored in reverse order
+        sheet.set_cell_value(CellCoord::new(0, 0), "=B1+1");
+        sheet.set_cell_value(CellCoord::new(0, 1), "=C1+1");
+        sheet.set_cell_value(CellCoord::new(0, 2), "1");
+        let mut workbook = Workbook::new("Test");
+        workbook.sheets[0] = sheet;
+
+        let mut engine = CalcEngine::new(workbook);
+        let report = engine.recalculate_all();
+
+        assert_eq!(value_at(&engine, 0, 0), CellValue::Number(3.0));
+        assert_eq!(report.on_sheet(0), vec![CellCoord::new(0, 0), CellCoord::new(0, 1)]);
+    }
+
+    #[test]
+    fn test_cross_sheet_dependents() {
+        let mut engine = CalcEngine::default();
+        engine.workbook_mut().add_sheet("Sheet2").unwrap();
+        engine.rebuild_dependencies();
+        engine.set_cell_input(CellCoord::new(0, 1), "=Sheet2!A1+A2");
+        engine.set_cell_input(CellCoord::new(0, 2), "=B1*2");
+
+        let deps = engine.dependency_graph().get_direct_dependencies((0, 0, 1)).unwrap();
+        assert_eq!(deps, &[(1, 0, 0), (0, 1, 0)].into_iter().collect());
+        assert_eq!(
+            engine.precedents(CellCoord::new(0, 1)),
+            vec![(0, CellCoord::new(1, 0)), (1, CellCoord::new(0, 0))]
+        );
+
+        // Edits flow from Sheet1 to Sheet2 and back
+        engine.set_active_sheet(1);
+        engine.set_cell_input(CellCoord::new(0, 0), "=Sheet1!A2+1");
+        engine.set_active_sheet(0);
+        let report = engine.set_cell_input(CellCoord::new(1, 0), "5");
+        assert_eq!(value_at(&engine, 0, 1), CellValue::Number(11.0));
+        assert_eq!(value_at(&engine, 0, 2), CellValue::Number(22.0));
+        assert_eq!(
+            report.on_sheet(0),
+            vec![CellCoord::new(0, 1), CellCoord::new(0, 2), CellCoord::new(1, 0)]
+        );
+        assert_eq!(report.on_sheet(1), vec![CellCoord::new(0, 0)]);
+        assert!(report.contains(1, CellCoord::new(0, 0)));
+        assert_eq!(
+            engine.dependents(CellCoord::new(1, 0)),
+            vec![(0, CellCoord::new(0, 1)), (1, CellCoord::new(0, 0))]
+        );
+        let sheet2 = &engine.workbook().sheets[1];
+        assert_eq!(sheet2.get_cell_value(CellCoord::new(0, 0)), &CellValue::Number(6.0));
+    }
+
+    #[test]
+    fn test_wide_level_recalculates_every_cell() {
+        let mut engine = CalcEngine::default();
+        let sheet = engine.workbook_mut().active_sheet_mut();
+        sheet.set_cell_value(CellCoord::new(0, 0), "2");
+        for row in 1..=200 {
+            sheet.set_cell_value(CellCoord::new(row, 0), &format!("=A1*{}", row));
+            sheet.set_cell_value(CellCoord::new(row, 1), &format!("=A{}+1", row + 1));
+        }
+        engine.rebuild_dependencies();
+        engine.recalculate_all();
+        assert_eq!(value_at(&engine, 200, 1), CellValue::Number(401.0));
+
+        let report = engine.set_cell_input(CellCoord::new(0, 0), "3");
+        assert_eq!(report.len(), 401);
+        assert_eq!(report.changed[1], (0, CellCoord::new(1, 0)));
+        assert_eq!(value_at(&engine, 200, 1), CellValue::Number(601.0));
+    }
+
+    #[test]
+    fn test_trace_and_precedents() {
+        let mut engine = CalcEngine::default();
+        engine.set_cell_input(CellCoord::new(0, 0), "4");
+        engine.set_cell_input(CellCoord::new(1, 0), "=A1*2");
+        engine.set_cell_input(CellCoord::new(2, 0), "=A1+A2");
+
+        let trace = engine.trace_cell(CellCoord::new(2, 0)).unwrap();
+        assert_eq!(trace.text, "A1+A2");
+        assert_eq!(trace.value, Some(CellValue::Number(12.0)));
+        assert_eq!(trace.children[1].value, Some(CellValue::Number(8.0)));
+        assert!(engine.trace_cell(CellCoord::new(0, 0)).is_none());
+
+        assert_eq!(
+            engine.precedents(CellCoord::new(2, 0)),
+            vec![(0, CellCoord::new(0, 0)), (0, CellCoord::new(1, 0))]
+        );
+        assert_eq!(
+            engine.dependents(CellCoord::new(0, 0)),
+            vec![(0, CellCoord::new(1, 0)), (0, CellCoord::new(2, 0))]
+        );
+        assert!(engine.dependents(CellCoord::new(2, 0)).is_empty());
+    }
+
+    #[test]
+    fn test_hyperlinks() {
+        let mut engine = CalcEngine::default();
+        engine.workbook_mut().add_sheet("Q1 Data").unwrap();
+        engine.set_cell_input(CellCoord::new(0, 0), "https://example.com");
+        engine.set_cell_input(CellCoord::new(0, 1), "=HYPERLINK(A1, \"Docs\")");
+        engine.set_cell_input(CellCoord::new(0, 2), "=(HYPERLINK(\"#'Q1 Data'!B2:C3\"))");
+        engine.set_cell_input(CellCoord::new(0, 3), "=UPPER(HYPERLINK(A1))");
+
+        assert_eq!(value_at(&engine, 0, 1), CellValue::Text("Docs".to_string()));
+        assert_eq!(
+            engine.hyperlink_at(CellCoord::new(0, 1)),
+            Some(Hyperlink::url("https://example.com"))
+        );
+        assert_eq!(engine.hyperlink_at(CellCoord::new(0, 3)), None);
+
+        // A link attribute wins over the formula
+        let linked = CellCoord::new(0, 2);
+        let link = engine.hyperlink_at(linked).unwrap();
+        assert_eq!(link, Hyperlink::location("'Q1 Data'!B2:C3"));
+        let sheet = engine.workbook_mut().active_sheet_mut();
+        sheet.set_hyperlink(linked, Some(Hyperlink::location("A1")));
+        assert_eq!(engine.hyperlink_at(linked), Some(Hyperlink::location("A1")));
+
+        let range = CellRange::new(CellCoord::new(1, 1), CellCoord::new(2, 2));
+        assert_eq!(engine.resolve_location("'Q1 Data'!B2:C3"), Some((1, range)));
+        assert_eq!(
+            engine.resolve_location("A1"),
+            Some((0, CellRange::new(CellCoord::new(0, 0), CellCoord::new(0, 0))))
+        );
+        assert_eq!(engine.resolve_location("Missing!A1"), None);
+        assert_eq!(engine.resolve_location("Totals"), None);
+    }
+
+    #[test]
+    fn test_structured_refs_follow_table() {
+        let mut engine = CalcEngine::default();
+        for (input, at) in [("Item", "A1"), ("Amount", "B1"), ("a", "A2"), ("2", "B2"), ("b", "A3"), ("3", "B3")] {
+            engine.set_cell_input(CellCoord::from_a1(at).unwrap(), input);
+        }
+        let range = CellRange::from_a1("A1:B3").unwrap();
+        engine
+            .workbook_mut()
+            .add_table(0, rusheet_core::Table::new("Sales", range))
+            .unwrap();
+        engine.set_cell_input(CellCoord::new(0, 3), "=SUM(Sales[Amount])");
+        engine.set_cell_input(CellCoord::new(1, 2), "=Sales[@Amount]*10");
+        assert_eq!(value_at(&engine, 0, 3), CellValue::Number(5.0));
+        assert_eq!(value_at(&engine, 1, 2), CellValue::Number(20.0));
+
+        // Growing the table moves the dependencies with it
+        engine.set_cell_input(CellCoord::new(3, 1), "4");
+        assert_eq!(value_at(&engine, 0, 3), CellValue::Number(5.0));
+        let sheet = engine.workbook_mut().active_sheet_mut();
+        sheet.resize_table("Sales", CellRange::from_a1("A1:C4").unwrap()).unwrap();
+        engine.recalculate();
+        assert_eq!(value_at(&engine, 0, 3), CellValue::Number(9.0));
+        assert!(engine.precedents(CellCoord::new(0, 3)).contains(&(0, CellCoord::new(3, 1))));
+
+        let report = engine.set_cell_input(CellCoord::new(3, 1), "10");
+        assert!(report.contains(0, CellCoord::new(0, 3)));
+        assert_eq!(value_at(&engine, 0, 3), CellValue::Number(15.0));
+
+        let trace = engine.trace_cell(CellCoord::new(1, 2)).unwrap();
+        assert_eq!(trace.text, "Sales[@Amount]*10");
+        assert_eq!(trace.value, Some(CellValue::Number(20.0)));
+    }
+
+    #[test]
+    fn test_subtotal_skips_hidden_rows() {
+        let mut engine = CalcEngine::default();
+        for (row, input) in ["1", "2", "4", "=SUBTOTAL(9,A1:A3)"].iter().enumerate() {
+            engine.set_cell_input(CellCoord::new(row as u32, 0), input);
+        }
+        engine.set_cell_input(CellCoord::new(0, 1), "=SUBTOTAL(9,A1:A4)");
+        engine.set_cell_input(CellCoord::new(1, 1), "=SUBTOTAL(109,A1:A3)");
+        assert_eq!(value_at(&engine, 0, 1), CellValue::Number(7.0));
+
+        let sheet = engine.workbook_mut().active_sheet_mut();
+        sheet.set_hidden(Axis::Rows, 1, 1, true);
+        engine.rows_visibility_changed();
+        engine.recalculate();
+        assert_eq!(value_at(&engine, 0, 1), CellValue::Number(7.0));
+        assert_eq!(value_at(&engine, 1, 1), CellValue::Number(5.0));
+
+        let sheet = engine.workbook_mut().active_sheet_mut();
+        sheet.set_hidden(Axis::Rows, 1, 1, false);
+        sheet.hide_rows(&[2]);
+        engine.rows_visibility_changed();
+        engine.recalculate();
+        assert_eq!(value_at(&engine, 0, 1), CellValue::Number(3.0));
+        assert_eq!(value_at(&engine, 1, 1), CellValue::Number(3.0));
+
+        // Only an actual call counts, not the name inside text
+        engine.set_cell_input(CellCoord::new(0, 2), "=IF(A1=\"SUBTOTAL(\",1,2)+5");
+        engine.set_cell_input(CellCoord::new(1, 2), "=SUBTOTAL(9,C1)");
+        assert_eq!(value_at(&engine, 1, 2), CellValue::Number(7.0));
+        assert!(!engine.subtotal_formulas.contains(&(0, 0, 2)));
+        assert!(engine.subtotal_formulas.contains(&(0, 1, 2)));
+    }
+
+    #[test]
+    fn test_fixed_clock() {
+        let mut engine = CalcEngine::default();
+        // 2024-01-01T12:00:00Z
+        engine.set_clock(|| Some(1_704_110_400_000.0));
+        engine.set_cell_input(CellCoord::new(0, 0), "=NOW()");
+        assert_eq!(value_at(&engine, 0, 0), CellValue::Number(45292.5));
+    }
+
+    #[test]
+    fn test_typed_dates_are_numbers() {
+        let mut engine = CalcEngine::default();
+        engine.workbook_mut().date_order = DateOrder::DayMonthYear;
+        engine.set_cell_input(CellCoord::new(0, 0), "1/3/2026");
+        engine.set_cell_input(CellCoord::new(0, 1), "16 Oct 2026");
+        engine.set_cell_input(CellCoord::new(0, 2), "=DATEDIF(A1,B1,\"m\")");
+        assert_eq!(value_at(&engine, 0, 2), CellValue::Number(7.0));
+
+        let styles = &engine.workbook().styles;
+        let cell = engine.workbook().active_sheet().get_cell(CellCoord::new(0, 0)).unwrap();
+        assert_eq!(cell.format.number_format(styles), Some("d/m/yyyy"));
+        assert_eq!(cell.display_value(styles, DateSystem::Excel1900), "1/3/2026");
+    }
+
+    #[test]
+    fn test_limits_become_error_values() {
+        let mut engine = CalcEngine::default();
+        let nested = format!("={}1{}", "(".repeat(1000), ")".repeat(1000));
+        engine.set_cell_input(CellCoord::new(0, 0), &nested);
+        assert_eq!(value_at(&engine, 0, 0), CellValue::Error(CellError::CalcError));
+
+        engine.set_limits(EvalLimits {
+            max_string_length: 10,
+            ..Default::default()
+        });
+        engine.set_cell_input(CellCoord::new(1, 0), "hello");
+        engine.set_cell_input(CellCoord::new(1, 1), "=A2&\" \"&A2");
+        assert_eq!(value_at(&engine, 1, 1), CellValue::Error(CellError::InvalidValue));
+        engine.set_cell_input(CellCoord::new(1, 0), "hi");
+        assert_eq!(value_at(&engine, 1, 1), CellValue::Text("hi hi".to_string()));
+    }
+
+    #[test]
+    fn test_long_sums() {
+        let mut engine = CalcEngine::default();
+        for row in 0..1000 {
+            engine.set_cell_input(CellCoord::new(row, 0), "1");
+        }
+        let terms: Vec<String> = (1..=1000).map(|row| format!("A{row}")).collect();
+        engine.set_cell_input(CellCoord::new(0, 1), &format!("={}", terms.join("+")));
+        assert_eq!(value_at(&engine, 0, 1), CellValue::Number(1000.0));
+        engine.set_cell_input(CellCoord::new(999, 0), "2");
+        assert_eq!(value_at(&engine, 0, 1), CellValue::Number(1001.0));
+
+        // As long a chain as Excel's formula length allows
+        let longest = format!("=1{}", "+1".repeat(4_095));
+        engine.set_cell_input(CellCoord::new(0, 2), &longest);
+        assert_eq!(value_at(&engine, 0, 2), CellValue::Number(4096.0));
+        engine.set_cell_input(CellCoord::new(0, 2), &format!("{longest}+1"));
+        assert_eq!(value_at(&engine, 0, 2), CellValue::Error(CellError::CalcError));
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
 
 use rusheet_core::CellError;
 
@@ -6,24 +7,36 @@ use rusheet_core::CellError;
 pub type CellCoord = (u32, u32);
 
 /// Tracks dependencies between cells for efficient recalculation
-#[derive(Debug, Default)]
-pub struct DependencyGraph {
+///
+/// Cells are keyed by [`CellCoord`] unless another key is given, such as
+/// one that also names the sheet.
+#[derive(Debug)]
+pub struct DependencyGraph<K = CellCoord> {
     /// Maps a cell to the cells it depends on (formula inputs)
     /// e.g., if A1 = B1 + C1, then dependencies[A1] = {B1, C1}
-    dependencies: HashMap<CellCoord, HashSet<CellCoord>>,
+    dependencies: HashMap<K, HashSet<K>>,
 
     /// Maps a cell to the cells that depend on it (reverse lookup)
     /// e.g., if A1 = B1 + C1, then dependents[B1] contains A1
-    dependents: HashMap<CellCoord, HashSet<CellCoord>>,
+    dependents: HashMap<K, HashSet<K>>,
 }
 
-impl DependencyGraph {
+impl<K> Default for DependencyGraph<K> {
+    fn default() -> Self {
+        Self {
+            dependencies: HashMap::new(),
+            dependents: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Copy + Eq + Hash + Ord> DependencyGraph<K> {
     pub fn new() -> Self {
         Self::default()
     }
 
     /// Update dependencies for a cell after formula change
-    pub fn set_dependencies(&mut self, cell: CellCoord, deps: HashSet<CellCoord>) {
+    pub fn set_dependencies(&mut self, cell: K, deps: HashSet<K>) {
         // Remove old reverse dependencies
         if let Some(old_deps) = self.dependencies.get(&cell) {
             for dep in old_deps {
@@ -37,7 +50,7 @@ impl DependencyGraph {
         for dep in &deps {
             self.dependents
                 .entry(*dep)
-                .or_insert_with(HashSet::new)
+                .or_default()
                 .insert(cell);
         }
 
@@ -50,30 +63,97 @@ impl DependencyGraph {
     }
 
     /// Remove all dependencies for a cell (when cell is cleared)
-    pub fn remove_cell(&mut self, cell: CellCoord) {
+    pub fn remove_cell(&mut self, cell: K) {
         self.set_dependencies(cell, HashSet::new());
     }
 
     /// Get cells that directly depend on the given cell
-    pub fn get_direct_dependents(&self, cell: CellCoord) -> Option<&HashSet<CellCoord>> {
+    pub fn get_direct_dependents(&self, cell: K) -> Option<&HashSet<K>> {
         self.dependents.get(&cell)
     }
 
     /// Get cells that the given cell directly depends on
-    pub fn get_direct_dependencies(&self, cell: CellCoord) -> Option<&HashSet<CellCoord>> {
+    pub fn get_direct_dependencies(&self, cell: K) -> Option<&HashSet<K>> {
         self.dependencies.get(&cell)
     }
 
     /// Get all cells that need recalculation when a cell changes
     /// Returns cells in topological order (dependencies before dependents)
-    pub fn get_recalc_order(&self, changed: CellCoord) -> Result<Vec<CellCoord>, CellError> {
+    pub fn get_recalc_order(&self, changed: K) -> Result<Vec<K>, CellError> {
+        self.get_batch_recalc_order([changed])
+    }
+
+    /// Get all cells that need recalculation when several cells change at once
+    /// Returns cells in topological order (dependencies before dependents),
+    /// with each affected cell appearing once
+    pub fn get_batch_recalc_order(
+        &self,
+        changed: impl IntoIterator<Item = K>,
+    ) -> Result<Vec<K>, CellError> {
         let mut to_recalc = Vec::new();
         let mut visited = HashSet::new();
         let mut in_progress = HashSet::new();
 
+        let affected = self.get_affected_cells(changed);
+
+        // Sort the starting points so the order is deterministic
+        let mut starts: Vec<_> = affected.iter().copied().collect();
+        starts.sort_unstable();
+
+        // Topologically sort the affected cells
+        for cell in starts {
+            if !visited.contains(&cell) {
+                self.topological_sort(cell, &affected, &mut to_recalc, &mut visited, &mut in_progress)?;
+            }
+        }
+
+        Ok(to_recalc)
+    }
+
+    /// Split the cells needing recalculation into levels
+    ///
+    /// Every cell depends only on cells in earlier levels, so the cells within
+    /// one level can be evaluated independently. Each level is sorted.
+    pub fn get_recalc_levels(
+        &self,
+        changed: impl IntoIterator<Item = K>,
+    ) -> Result<Vec<Vec<K>>, CellError> {
+        let order = self.get_batch_recalc_order(changed)?;
+
+        let mut level_of: HashMap<K, usize> = HashMap::with_capacity(order.len());
+        let mut levels: Vec<Vec<K>> = Vec::new();
+        for cell in order {
+            // Dependencies come first in topological order, so theirs are known
+            let level = self
+                .dependencies
+                .get(&cell)
+                .into_iter()
+                .flatten()
+                .filter_map(|dep| level_of.get(dep))
+                .map(|level| level + 1)
+                .max()
+                .unwrap_or(0);
+            level_of.insert(cell, level);
+
+            if levels.len() <= level {
+                levels.resize_with(level + 1, Vec::new);
+            }
+            levels[level].push(cell);
+        }
+
+        for level in &mut levels {
+            level.sort_unstable();
+        }
+        Ok(levels)
+    }
+
+    /// Get the changed cells plus every cell that depends on them, directly or indirectly
+    pub fn get_affected_cells(
+        &self,
+        changed: impl IntoIterator<Item = K>,
+    ) -> HashSet<K> {
         // Find all cells affected by this change using BFS
-        let mut queue = VecDeque::new();
-        queue.push_back(changed);
+        let mut queue: VecDeque<K> = changed.into_iter().collect();
 
         let mut affected = HashSet::new();
         while let Some(cell) = queue.pop_front() {
@@ -89,24 +169,17 @@ impl DependencyGraph {
             }
         }
 
-        // Topologically sort the affected cells
-        for cell in &affected {
-            if !visited.contains(cell) {
-                self.topological_sort(*cell, &affected, &mut to_recalc, &mut visited, &mut in_progress)?;
-            }
-        }
-
-        Ok(to_recalc)
+        affected
     }
 
     /// Depth-first topological sort
     fn topological_sort(
         &self,
-        cell: CellCoord,
-        affected: &HashSet<CellCoord>,
-        result: &mut Vec<CellCoord>,
-        visited: &mut HashSet<CellCoord>,
-        in_progress: &mut HashSet<CellCoord>,
+        cell: K,
+        affected: &HashSet<K>,
+        result: &mut Vec<K>,
+        visited: &mut HashSet<K>,
+        in_progress: &mut HashSet<K>,
     ) -> Result<(), CellError> {
         if in_progress.contains(&cell) {
             return Err(CellError::CircularReference);
@@ -135,7 +208,7 @@ impl DependencyGraph {
     }
 
     /// Check if adding a dependency would create a circular reference
-    pub fn would_create_cycle(&self, cell: CellCoord, new_dep: CellCoord) -> bool {
+    pub fn would_create_cycle(&self, cell: K, new_dep: K) -> bool {
         // Check if new_dep (directly or indirectly) depends on cell
         let mut visited = HashSet::new();
         let mut queue = VecDeque::new();
@@ -162,7 +235,7 @@ impl DependencyGraph {
     }
 
     /// Get all cells that have formulas (have dependencies)
-    pub fn cells_with_formulas(&self) -> impl Iterator<Item = CellCoord> + '_ {
+    pub fn cells_with_formulas(&self) -> impl Iterator<Item = K> + '_ {
         self.dependencies.keys().copied()
     }
 
@@ -281,4 +354,51 @@ mod tests {
         // Would C1 = some other cell create a cycle? No
         assert!(!graph.would_create_cycle(c1, (0, 3)));
     }
+
+    #[test]
+    fn test_batch_recalc_order() {
+        let mut graph = DependencyGraph::new();
+
+        let a1 = (0, 0);
+        let a2 = (1, 0);
+        let b1 = (0, 1);
+        let c1 = (0, 2);
+
+        // B1 = A1 + A2, C1 = B1
+        graph.set_dependencies(b1, [a1, a2].into_iter().collect());
+        graph.set_dependencies(c1, [b1].into_iter().collect());
+
+        // Changing A1 and A2 together recalculates B1 and C1 once each
+        let order = graph.get_batch_recalc_order([a1, a2]).unwrap();
+        assert_eq!(order.len(), 4);
+        assert_eq!(order.iter().filter(|&&c| c == b1).count(), 1);
+        let b1_pos = order.iter().position(|&c| c == b1).unwrap();
+        let c1_pos = order.iter().position(|&c| c == c1).unwrap();
+        assert!(b1_pos < c1_pos);
+
+        let affected = graph.get_affected_cells([a2]);
+        assert_eq!(affected, [a2, b1, c1].into_iter().collect());
+    }
+
+    #[test]
+    fn test_recalc_levels() {
+        let mut graph = DependencyGraph::new();
+
+        let a1 = (0, 0);
+        let b1 = (0, 1);
+        let b2 = (1, 1);
+        let c1 = (0, 2);
+
+        // B1 = A1, B2 = A1 * 2, C1 = B1 + B2 + A1
+        graph.set_dependencies(b1, [a1].into_iter().collect());
+        graph.set_dependencies(b2, [a1].into_iter().collect());
+        graph.set_dependencies(c1, [a1, b1, b2].into_iter().collect());
+
+        let levels = graph.get_recalc_levels([a1]).unwrap();
+        assert_eq!(levels, vec![vec![a1], vec![b1, b2], vec![c1]]);
+
+        // Cycles are still reported
+        graph.set_dependencies(a1, [c1].into_iter().collect());
+        assert!(graph.get_recalc_levels([a1]).is_err());
+    }
 }
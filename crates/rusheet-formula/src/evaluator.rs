@@ -1,6 +1,30 @@
+use crate::array::Array;
 use crate::ast::{BinaryOp, Expr, UnaryOp};
 use crate::functions;
-use rusheet_core::{CellError, CellValue};
+use crate::functions::datetime::DateContext;
+use crate::limits::EvalLimits;
+use crate::parser_nom::NomParser;
+use crate::trace::{trace_resolved, TraceNode};
+use rusheet_core::{round_significant, CellContent, CellError, CellValue};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+/// Content lookup used by evaluators that have no access to cell contents
+pub type NoContentLookup = fn(Option<&str>, u32, u32) -> Option<CellContent>;
+
+/// Row lookup used by evaluators that have no access to row visibility
+pub type NoRowLookup = fn(Option<&str>, u32) -> RowVisibility;
+
+/// Whether a row is shown, as `SUBTOTAL` sees it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RowVisibility {
+    #[default]
+    Visible,
+    /// Hidden by the user or a collapsed group
+    Hidden,
+    /// Hidden by a filter
+    Filtered,
+}
 
 /// Evaluator for formula AST
 pub struct Evaluator<F>
@@ -8,6 +32,10 @@ where
     F: Fn(u32, u32) -> CellValue,
 {
     get_cell_value: F,
+    date_context: DateContext,
+    current_cell: Option<(u32, u32)>,
+    implicit_intersection: bool,
+    limits: EvalLimits,
 }
 
 impl<F> Evaluator<F>
@@ -15,425 +43,372 @@ where
     F: Fn(u32, u32) -> CellValue,
 {
     pub fn new(get_cell_value: F) -> Self {
-        Self { get_cell_value }
+        Self {
+            get_cell_value,
+            date_context: DateContext::default(),
+            current_cell: None,
+            implicit_intersection: false,
+            limits: EvalLimits::default(),
+        }
     }
 
-    /// Evaluate an expression AST to a value
-    pub fn evaluate(&self, expr: &Expr) -> CellValue {
-        match expr {
-            Expr::Number(n) => CellValue::Number(*n),
-            Expr::String(s) => CellValue::Text(s.clone()),
-            Expr::Boolean(b) => CellValue::Boolean(*b),
-            Expr::Error(e) => CellValue::Error(e.clone()),
-
-            Expr::CellRef { row, col, .. } => (self.get_cell_value)(*row, *col),
-
-            Expr::Range { .. } => {
-                // Ranges should be expanded by functions, not evaluated directly
-                CellValue::Error(CellError::InvalidValue)
-            }
-
-            Expr::SheetRef { .. } => {
-                // Cross-sheet references not implemented yet
-                CellValue::Error(CellError::InvalidReference)
-            }
-
-            Expr::Binary { left, op, right } => self.evaluate_binary(left, *op, right),
+    /// Set the date system and clock used by date/time functions
+    pub fn with_date_context(mut self, date_context: DateContext) -> Self {
+        self.date_context = date_context;
+        self
+    }
 
-            Expr::Unary { op, operand } => self.evaluate_unary(*op, operand),
+    /// Set the (row, col) of the cell holding the formula, used by the `@`
+    /// operator to pick the value in line with it
+    pub fn with_current_cell(mut self, row: u32, col: u32) -> Self {
+        self.current_cell = Some((row, col));
+        self
+    }
 
-            Expr::FunctionCall { name, args } => self.evaluate_function(name, args),
+    /// Resolve multi-cell results in scalar context by implicit
+    /// intersection, as legacy spreadsheets do, instead of returning #VALUE!
+    pub fn with_implicit_intersection(mut self, enabled: bool) -> Self {
+        self.implicit_intersection = enabled;
+        self
+    }
 
-            Expr::Grouped(inner) => self.evaluate(inner),
-        }
+    /// Set the resource limits for each evaluation
+    pub fn with_limits(mut self, limits: EvalLimits) -> Self {
+        self.limits = limits;
+        self
     }
 
-    fn evaluate_binary(&self, left: &Expr, op: BinaryOp, right: &Expr) -> CellValue {
-        let left_val = self.evaluate(left);
-        let right_val = self.evaluate(right);
+    /// Evaluate an expression AST to a value
+    pub fn evaluate(&self, expr: &Expr) -> CellValue {
+        self.with_core(|core| core.evaluate_root(expr))
+    }
 
-        // Propagate errors
-        if let CellValue::Error(e) = &left_val {
-            return CellValue::Error(e.clone());
-        }
-        if let CellValue::Error(e) = &right_val {
-            return CellValue::Error(e.clone());
-        }
+    /// Evaluate an expression, recording the value of every sub-expression
+    pub fn trace(&self, expr: &Expr) -> TraceNode {
+        self.with_core(|core| core.trace(expr, expr))
+    }
 
-        match op {
-            BinaryOp::Add => self.numeric_op(&left_val, &right_val, |a, b| a + b),
-            BinaryOp::Sub => self.numeric_op(&left_val, &right_val, |a, b| a - b),
-            BinaryOp::Mul => self.numeric_op(&left_val, &right_val, |a, b| a * b),
-            BinaryOp::Div => {
-                match (left_val.as_number(), right_val.as_number()) {
-                    (Some(a), Some(b)) => {
-                        if b == 0.0 {
-                            CellValue::Error(CellError::DivisionByZero)
-                        } else {
-                            CellValue::Number(a / b)
-                        }
-                    }
-                    _ => CellValue::Error(CellError::InvalidValue),
-                }
-            }
-            BinaryOp::Pow => self.numeric_op(&left_val, &right_val, |a, b| a.powf(b)),
+    fn with_core<R>(&self, f: impl FnOnce(&EvalCore) -> R) -> R {
+        // Cross-sheet references are not supported by the single-sheet evaluator
+        let lookup = |sheet: Option<&str>, row: u32, col: u32| match sheet {
+            Some(_) => CellValue::Error(CellError::InvalidReference),
+            None => (self.get_cell_value)(row, col),
+        };
+
+        f(&EvalCore {
+            get_cell_value: &lookup,
+            get_cell_content: None,
+            get_row_visibility: None,
+            current_sheet: None,
+            date_context: self.date_context,
+            current_cell: self.current_cell,
+            implicit_intersection: self.implicit_intersection,
+            limits: self.limits,
+            steps: Cell::new(0),
+            recorded: RefCell::new(None),
+        })
+    }
+}
 
-            BinaryOp::Concat => {
-                let left_str = left_val.as_text();
-                let right_str = right_val.as_text();
-                CellValue::Text(format!("{}{}", left_str, right_str))
-            }
+/// Evaluator with cross-sheet reference support
+///
+/// Cell values are read through `get_cell_value`. Functions that inspect the
+/// cell itself rather than its value (such as ISFORMULA) additionally need a
+/// content lookup, supplied with [`CrossSheetEvaluator::with_cell_content`],
+/// and SUBTOTAL skips hidden rows given a row lookup, supplied with
+/// [`CrossSheetEvaluator::with_row_visibility`].
+pub struct CrossSheetEvaluator<F, C = NoContentLookup, V = NoRowLookup>
+where
+    F: Fn(Option<&str>, u32, u32) -> CellValue,
+    C: Fn(Option<&str>, u32, u32) -> Option<CellContent>,
+    V: Fn(Option<&str>, u32) -> RowVisibility,
+{
+    get_cell_value: F,
+    get_cell_content: Option<C>,
+    get_row_visibility: Option<V>,
+    current_sheet: Option<String>,
+    date_context: DateContext,
+    current_cell: Option<(u32, u32)>,
+    implicit_intersection: bool,
+    limits: EvalLimits,
+}
 
-            BinaryOp::Eq => CellValue::Boolean(self.compare_values(&left_val, &right_val) == 0),
-            BinaryOp::Ne => CellValue::Boolean(self.compare_values(&left_val, &right_val) != 0),
-            BinaryOp::Lt => CellValue::Boolean(self.compare_values(&left_val, &right_val) < 0),
-            BinaryOp::Gt => CellValue::Boolean(self.compare_values(&left_val, &right_val) > 0),
-            BinaryOp::Le => CellValue::Boolean(self.compare_values(&left_val, &right_val) <= 0),
-            BinaryOp::Ge => CellValue::Boolean(self.compare_values(&left_val, &right_val) >= 0),
+impl<F> CrossSheetEvaluator<F>
+where
+    F: Fn(Option<&str>, u32, u32) -> CellValue,
+{
+    pub fn new(get_cell_value: F) -> Self {
+        Self {
+            get_cell_value,
+            get_cell_content: None,
+            get_row_visibility: None,
+            current_sheet: None,
+            date_context: DateContext::default(),
+            current_cell: None,
+            implicit_intersection: false,
+            limits: EvalLimits::default(),
         }
     }
 
-    fn numeric_op(
-        &self,
-        left: &CellValue,
-        right: &CellValue,
-        op: impl Fn(f64, f64) -> f64,
-    ) -> CellValue {
-        match (left.as_number(), right.as_number()) {
-            (Some(a), Some(b)) => {
-                let result = op(a, b);
-                if result.is_nan() || result.is_infinite() {
-                    CellValue::Error(CellError::NumError)
-                } else {
-                    CellValue::Number(result)
-                }
-            }
-            _ => CellValue::Error(CellError::InvalidValue),
+    /// Create evaluator with current sheet context
+    pub fn with_sheet(get_cell_value: F, current_sheet: &str) -> Self {
+        Self {
+            get_cell_value,
+            get_cell_content: None,
+            get_row_visibility: None,
+            current_sheet: Some(current_sheet.to_string()),
+            date_context: DateContext::default(),
+            current_cell: None,
+            implicit_intersection: false,
+            limits: EvalLimits::default(),
         }
     }
+}
 
-    fn compare_values(&self, left: &CellValue, right: &CellValue) -> i8 {
-        match (left, right) {
-            (CellValue::Number(a), CellValue::Number(b)) => {
-                if a < b {
-                    -1
-                } else if a > b {
-                    1
-                } else {
-                    0
-                }
-            }
-            (CellValue::Text(a), CellValue::Text(b)) => {
-                a.to_lowercase().cmp(&b.to_lowercase()) as i8
-            }
-            (CellValue::Boolean(a), CellValue::Boolean(b)) => (*a as i8) - (*b as i8),
-            // Type coercion for comparisons
-            _ => {
-                let a_str = left.as_text();
-                let b_str = right.as_text();
-                a_str.cmp(&b_str) as i8
-            }
+impl<F, C, V> CrossSheetEvaluator<F, C, V>
+where
+    F: Fn(Option<&str>, u32, u32) -> CellValue,
+    C: Fn(Option<&str>, u32, u32) -> Option<CellContent>,
+    V: Fn(Option<&str>, u32) -> RowVisibility,
+{
+    /// Provide access to raw cell contents (needed by ISFORMULA)
+    pub fn with_cell_content<C2>(self, get_cell_content: C2) -> CrossSheetEvaluator<F, C2, V>
+    where
+        C2: Fn(Option<&str>, u32, u32) -> Option<CellContent>,
+    {
+        CrossSheetEvaluator {
+            get_cell_value: self.get_cell_value,
+            get_cell_content: Some(get_cell_content),
+            get_row_visibility: self.get_row_visibility,
+            current_sheet: self.current_sheet,
+            date_context: self.date_context,
+            current_cell: self.current_cell,
+            implicit_intersection: self.implicit_intersection,
+            limits: self.limits,
         }
     }
 
-    fn evaluate_unary(&self, op: UnaryOp, operand: &Expr) -> CellValue {
-        let value = self.evaluate(operand);
-
-        if let CellValue::Error(e) = &value {
-            return CellValue::Error(e.clone());
-        }
-
-        match op {
-            UnaryOp::Neg => match value.as_number() {
-                Some(n) => CellValue::Number(-n),
-                None => CellValue::Error(CellError::InvalidValue),
-            },
-            UnaryOp::Pos => match value.as_number() {
-                Some(n) => CellValue::Number(n),
-                None => CellValue::Error(CellError::InvalidValue),
-            },
-            UnaryOp::Percent => match value.as_number() {
-                Some(n) => CellValue::Number(n / 100.0),
-                None => CellValue::Error(CellError::InvalidValue),
-            },
+    /// Provide row visibility, so SUBTOTAL can leave out hidden rows
+    pub fn with_row_visibility<V2>(self, get_row_visibility: V2) -> CrossSheetEvaluator<F, C, V2>
+    where
+        V2: Fn(Option<&str>, u32) -> RowVisibility,
+    {
+        CrossSheetEvaluator {
+            get_cell_value: self.get_cell_value,
+            get_cell_content: self.get_cell_content,
+            get_row_visibility: Some(get_row_visibility),
+            current_sheet: self.current_sheet,
+            date_context: self.date_context,
+            current_cell: self.current_cell,
+            implicit_intersection: self.implicit_intersection,
+            limits: self.limits,
         }
     }
 
-    fn evaluate_function(&self, name: &str, args: &[Expr]) -> CellValue {
-        // Collect values, expanding ranges
-        let values: Vec<CellValue> = args
-            .iter()
-            .flat_map(|arg| self.expand_argument(arg))
-            .collect();
-
-        match name.to_uppercase().as_str() {
-            // Math functions
-            "SUM" => functions::math::sum(&values),
-            "AVERAGE" | "AVG" => functions::math::average(&values),
-            "COUNT" => functions::math::count(&values),
-            "COUNTA" => functions::math::counta(&values),
-            "MIN" => functions::math::min(&values),
-            "MAX" => functions::math::max(&values),
-            "ABS" => functions::math::abs(&values),
-            "ROUND" => functions::math::round(&values),
-            "FLOOR" => functions::math::floor(&values),
-            "CEILING" | "CEIL" => functions::math::ceiling(&values),
-            "SQRT" => functions::math::sqrt(&values),
-            "POWER" | "POW" => functions::math::power(&values),
-
-            // Conditional functions - need special handling
-            "COUNTIF" => {
-                if args.len() < 2 {
-                    return CellValue::Error(CellError::InvalidValue);
-                }
-                let range_values = self.expand_argument(&args[0]);
-                let criteria = self.evaluate(&args[1]);
-                functions::math::countif(&range_values, &criteria)
-            }
-            "SUMIF" => {
-                if args.len() < 2 {
-                    return CellValue::Error(CellError::InvalidValue);
-                }
-                let criteria_range = self.expand_argument(&args[0]);
-                let criteria = self.evaluate(&args[1]);
-                let sum_range = if args.len() > 2 {
-                    Some(self.expand_argument(&args[2]))
-                } else {
-                    None
-                };
-                functions::math::sumif(&criteria_range, &criteria, sum_range.as_deref())
-            }
-            "AVERAGEIF" => {
-                if args.len() < 2 {
-                    return CellValue::Error(CellError::InvalidValue);
-                }
-                let criteria_range = self.expand_argument(&args[0]);
-                let criteria = self.evaluate(&args[1]);
-                let avg_range = if args.len() > 2 {
-                    Some(self.expand_argument(&args[2]))
-                } else {
-                    None
-                };
-                functions::math::averageif(&criteria_range, &criteria, avg_range.as_deref())
-            }
-
-            // Logical functions
-            "IF" => {
-                // IF needs special handling - don't expand args
-                let arg_values: Vec<CellValue> = args.iter().map(|a| self.evaluate(a)).collect();
-                functions::logical::if_fn(&arg_values)
-            }
-            "AND" => functions::logical::and(&values),
-            "OR" => functions::logical::or(&values),
-            "NOT" => functions::logical::not(&values),
-            "TRUE" => CellValue::Boolean(true),
-            "FALSE" => CellValue::Boolean(false),
-
-            // Text functions
-            "CONCAT" | "CONCATENATE" => functions::text::concat(&values),
-            "LEN" => functions::text::len(&values),
-            "UPPER" => functions::text::upper(&values),
-            "LOWER" => functions::text::lower(&values),
-            "TRIM" => functions::text::trim(&values),
-            "LEFT" => functions::text::left(&values),
-            "RIGHT" => functions::text::right(&values),
-            "MID" => functions::text::mid(&values),
-
-            // Date/Time functions
-            "TODAY" => functions::datetime::today(&values),
-            "NOW" => functions::datetime::now(&values),
-            "DATE" => functions::datetime::date(&values),
-            "TIME" => functions::datetime::time(&values),
-            "YEAR" => functions::datetime::year(&values),
-            "MONTH" => functions::datetime::month(&values),
-            "DAY" => functions::datetime::day(&values),
-            "HOUR" => functions::datetime::hour(&values),
-            "MINUTE" => functions::datetime::minute(&values),
-            "SECOND" => functions::datetime::second(&values),
-            "DATEDIF" => functions::datetime::datedif(&values),
-
-            // Lookup functions
-            "MATCH" => {
-                if args.len() < 2 {
-                    return CellValue::Error(CellError::InvalidValue);
-                }
-                let lookup_value = self.evaluate(&args[0]);
-                let lookup_array = self.expand_argument(&args[1]);
-                let match_type = if args.len() > 2 {
-                    match self.evaluate(&args[2]) {
-                        CellValue::Number(n) => n as i32,
-                        _ => 1,
-                    }
-                } else {
-                    1
-                };
-                functions::lookup::match_fn(&lookup_value, &lookup_array, match_type)
-            }
+    /// Set the date system and clock used by date/time functions
+    pub fn with_date_context(mut self, date_context: DateContext) -> Self {
+        self.date_context = date_context;
+        self
+    }
 
-            "VLOOKUP" => {
-                if args.len() < 3 {
-                    return CellValue::Error(CellError::InvalidValue);
-                }
+    /// Set the (row, col) of the cell holding the formula, used by the `@`
+    /// operator to pick the value in line with it
+    pub fn with_current_cell(mut self, row: u32, col: u32) -> Self {
+        self.current_cell = Some((row, col));
+        self
+    }
 
-                let lookup_value = self.evaluate(&args[0]);
+    /// Resolve multi-cell results in scalar context by implicit
+    /// intersection, as legacy spreadsheets do, instead of returning #VALUE!
+    pub fn with_implicit_intersection(mut self, enabled: bool) -> Self {
+        self.implicit_intersection = enabled;
+        self
+    }
 
-                // For table_array, we need dimensions
-                let (table_data, num_rows, num_cols) = self.expand_range_with_dimensions(&args[1]);
+    /// Set the resource limits for each evaluation
+    pub fn with_limits(mut self, limits: EvalLimits) -> Self {
+        self.limits = limits;
+        self
+    }
 
-                let col_index = match self.evaluate(&args[2]) {
-                    CellValue::Number(n) => n as usize,
-                    _ => return CellValue::Error(CellError::InvalidValue),
-                };
+    /// Evaluate an expression AST to a value
+    pub fn evaluate(&self, expr: &Expr) -> CellValue {
+        self.core().evaluate_root(expr)
+    }
 
-                let approximate = if args.len() > 3 {
-                    match self.evaluate(&args[3]) {
-                        CellValue::Boolean(b) => b,
-                        CellValue::Number(n) => n != 0.0,
-                        _ => true,
-                    }
-                } else {
-                    true
-                };
+    /// Evaluate an expression, recording the value of every sub-expression
+    pub fn trace(&self, expr: &Expr) -> TraceNode {
+        self.core().trace(expr, expr)
+    }
 
-                functions::lookup::vlookup(&lookup_value, &table_data, num_rows, num_cols, col_index, approximate)
-            }
+    /// Trace `expr` with the values of `resolved`, a copy of it whose
+    /// structured references have been replaced by the cells they cover
+    pub(crate) fn trace_resolved(&self, expr: &Expr, resolved: &Expr) -> TraceNode {
+        self.core().trace(expr, resolved)
+    }
 
-            "HLOOKUP" => {
-                if args.len() < 3 {
-                    return CellValue::Error(CellError::InvalidValue);
-                }
+    fn core(&self) -> EvalCore<'_> {
+        let get_cell_content = self
+            .get_cell_content
+            .as_ref()
+            .map(|c| c as &ContentLookup);
+        let get_row_visibility = self
+            .get_row_visibility
+            .as_ref()
+            .map(|v| v as &RowLookup);
+
+        EvalCore {
+            get_cell_value: &self.get_cell_value,
+            get_cell_content,
+            get_row_visibility,
+            current_sheet: self.current_sheet.as_deref(),
+            date_context: self.date_context,
+            current_cell: self.current_cell,
+            implicit_intersection: self.implicit_intersection,
+            limits: self.limits,
+            steps: Cell::new(0),
+            recorded: RefCell::new(None),
+        }
+    }
+}
 
-                let lookup_value = self.evaluate(&args[0]);
+/// A rectangular block of cells on one sheet (`None` is the current sheet)
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Area<'a> {
+    sheet: Option<&'a str>,
+    top: u32,
+    left: u32,
+    bottom: u32,
+    right: u32,
+}
 
-                // For table_array, we need dimensions
-                let (table_data, num_rows, num_cols) = self.expand_range_with_dimensions(&args[1]);
+impl<'a> Area<'a> {
+    fn rows(&self) -> usize {
+        (self.bottom - self.top + 1) as usize
+    }
 
-                let row_index = match self.evaluate(&args[2]) {
-                    CellValue::Number(n) => n as usize,
-                    _ => return CellValue::Error(CellError::InvalidValue),
-                };
+    fn cols(&self) -> usize {
+        (self.right - self.left + 1) as usize
+    }
 
-                let approximate = if args.len() > 3 {
-                    match self.evaluate(&args[3]) {
-                        CellValue::Boolean(b) => b,
-                        CellValue::Number(n) => n != 0.0,
-                        _ => true,
-                    }
-                } else {
-                    true
-                };
+    /// The cells this area shares with another, if any
+    fn intersect(&self, other: &Area<'a>) -> Option<Area<'a>> {
+        let same_sheet = match (self.sheet, other.sheet) {
+            (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+            (a, b) => a == b,
+        };
+        let area = Area {
+            sheet: self.sheet,
+            top: self.top.max(other.top),
+            left: self.left.max(other.left),
+            bottom: self.bottom.min(other.bottom),
+            right: self.right.min(other.right),
+        };
+        (same_sheet && area.top <= area.bottom && area.left <= area.right).then_some(area)
+    }
+}
 
-                functions::lookup::hlookup(&lookup_value, &table_data, num_rows, num_cols, row_index, approximate)
-            }
+type ValueLookup<'a> = dyn Fn(Option<&str>, u32, u32) -> CellValue + 'a;
+type ContentLookup<'a> = dyn Fn(Option<&str>, u32, u32) -> Option<CellContent> + 'a;
+type RowLookup<'a> = dyn Fn(Option<&str>, u32) -> RowVisibility + 'a;
+
+/// Evaluation logic shared by `Evaluator` and `CrossSheetEvaluator`
+struct EvalCore<'a> {
+    get_cell_value: &'a ValueLookup<'a>,
+    get_cell_content: Option<&'a ContentLookup<'a>>,
+    get_row_visibility: Option<&'a RowLookup<'a>>,
+    current_sheet: Option<&'a str>,
+    date_context: DateContext,
+    /// Cell holding the formula being evaluated
+    current_cell: Option<(u32, u32)>,
+    /// Whether multi-cell results in scalar context are implicitly intersected
+    implicit_intersection: bool,
+    limits: EvalLimits,
+    /// Evaluation steps taken so far, counted against `limits.max_steps`
+    steps: Cell<u64>,
+    /// While tracing, the first value each sub-expression evaluated to
+    recorded: RefCell<Option<HashMap<*const Expr, CellValue>>>,
+}
 
-            _ => CellValue::Error(CellError::InvalidName),
+impl EvalCore<'_> {
+    /// Evaluate a whole formula, refusing trees too deep to recurse through
+    fn evaluate_root(&self, expr: &Expr) -> CellValue {
+        if expr.depth() > self.limits.max_depth {
+            return CellValue::Error(CellError::CalcError);
         }
+        self.evaluate(expr)
     }
 
-    /// Expand a range with dimensions needed for VLOOKUP/HLOOKUP
-    fn expand_range_with_dimensions(&self, expr: &Expr) -> (Vec<CellValue>, usize, usize) {
-        match expr {
-            Expr::Range { start, end } => {
-                let (start_row, start_col) = self.get_cell_coords(start);
-                let (end_row, end_col) = self.get_cell_coords(end);
-
-                let min_row = start_row.min(end_row);
-                let max_row = start_row.max(end_row);
-                let min_col = start_col.min(end_col);
-                let max_col = start_col.max(end_col);
-
-                let num_rows = (max_row - min_row + 1) as usize;
-                let num_cols = (max_col - min_col + 1) as usize;
-
-                let mut values = Vec::with_capacity(num_rows * num_cols);
-                for row in min_row..=max_row {
-                    for col in min_col..=max_col {
-                        values.push((self.get_cell_value)(row, col));
-                    }
-                }
+    /// Evaluate `resolved` once, then trace `expr` (the same formula as
+    /// written) with the value each sub-expression took along the way
+    fn trace(&self, expr: &Expr, resolved: &Expr) -> TraceNode {
+        self.recorded.replace(Some(HashMap::new()));
+        self.evaluate_root(resolved);
+        trace_resolved(expr, resolved, &|e| self.recorded_value(e))
+    }
 
-                (values, num_rows, num_cols)
-            }
-            _ => {
-                let value = self.evaluate(expr);
-                (vec![value], 1, 1)
-            }
+    /// Note the value a sub-expression evaluated to, if tracing
+    fn record(&self, expr: &Expr, value: impl FnOnce() -> CellValue) {
+        if let Some(recorded) = self.recorded.borrow_mut().as_mut() {
+            recorded.entry(expr as *const Expr).or_insert_with(value);
         }
     }
 
-    /// Get cell coordinates from a CellRef expression
-    fn get_cell_coords(&self, expr: &Expr) -> (u32, u32) {
-        match expr {
-            Expr::CellRef { col, row, .. } => (*row, *col),
-            _ => (0, 0),
-        }
+    /// The value a sub-expression took while tracing
+    ///
+    /// Parts the evaluation skipped, such as the branch IF didn't take,
+    /// are evaluated on their own with a fresh step budget.
+    fn recorded_value(&self, expr: &Expr) -> CellValue {
+        let recorded = self
+            .recorded
+            .borrow()
+            .as_ref()
+            .and_then(|recorded| recorded.get(&(expr as *const Expr)).cloned());
+        recorded.unwrap_or_else(|| {
+            self.steps.set(0);
+            self.evaluate(expr)
+        })
     }
 
-    /// Expand an argument, handling ranges
-    fn expand_argument(&self, expr: &Expr) -> Vec<CellValue> {
-        match expr {
-            Expr::Range { start, end } => {
-                if let (
-                    Expr::CellRef {
-                        row: r1, col: c1, ..
-                    },
-                    Expr::CellRef {
-                        row: r2, col: c2, ..
-                    },
-                ) = (start.as_ref(), end.as_ref())
-                {
-                    let min_row = (*r1).min(*r2);
-                    let max_row = (*r1).max(*r2);
-                    let min_col = (*c1).min(*c2);
-                    let max_col = (*c1).max(*c2);
-
-                    let mut values = Vec::new();
-                    for row in min_row..=max_row {
-                        for col in min_col..=max_col {
-                            values.push((self.get_cell_value)(row, col));
-                        }
-                    }
-                    values
-                } else {
-                    vec![CellValue::Error(CellError::InvalidReference)]
-                }
-            }
-            _ => vec![self.evaluate(expr)],
+    /// Count `steps` against the step budget, failing with #CALC! once it runs out
+    fn charge(&self, steps: u64) -> Result<(), CellError> {
+        let taken = self.steps.get().saturating_add(steps);
+        self.steps.set(taken);
+        if taken > self.limits.max_steps {
+            Err(CellError::CalcError)
+        } else {
+            Ok(())
         }
     }
-}
 
-/// Evaluator with cross-sheet reference support
-pub struct CrossSheetEvaluator<F>
-where
-    F: Fn(Option<&str>, u32, u32) -> CellValue,
-{
-    get_cell_value: F,
-    current_sheet: Option<String>,
-}
-
-impl<F> CrossSheetEvaluator<F>
-where
-    F: Fn(Option<&str>, u32, u32) -> CellValue,
-{
-    pub fn new(get_cell_value: F) -> Self {
-        Self {
-            get_cell_value,
-            current_sheet: None,
+    /// Check that a rows x cols block fits the array limit and charge a step
+    /// for each of its cells
+    fn reserve(&self, rows: usize, cols: usize) -> Result<(), CellError> {
+        let cells = rows.saturating_mul(cols);
+        if cells > self.limits.max_array_size {
+            return Err(CellError::NumError);
         }
+        self.charge(cells as u64)
     }
 
-    /// Create evaluator with current sheet context
-    pub fn with_sheet(get_cell_value: F, current_sheet: &str) -> Self {
-        Self {
-            get_cell_value,
-            current_sheet: Some(current_sheet.to_string()),
+    /// Replace text longer than the string limit with #VALUE!
+    fn limit_text(&self, value: CellValue) -> CellValue {
+        match &value {
+            CellValue::Text(s)
+                if s.len() > self.limits.max_string_length
+                    && s.chars().count() > self.limits.max_string_length =>
+            {
+                CellValue::Error(CellError::InvalidValue)
+            }
+            _ => value,
         }
     }
 
-    /// Evaluate an expression AST to a value
-    pub fn evaluate(&self, expr: &Expr) -> CellValue {
-        match expr {
+    fn evaluate(&self, expr: &Expr) -> CellValue {
+        if let Err(e) = self.charge(1) {
+            return CellValue::Error(e);
+        }
+        let value = match expr {
             Expr::Number(n) => CellValue::Number(*n),
             Expr::String(s) => CellValue::Text(s.clone()),
             Expr::Boolean(b) => CellValue::Boolean(*b),
@@ -441,12 +416,7 @@ where
 
             Expr::CellRef { row, col, .. } => {
                 // Use current sheet context for unqualified references
-                (self.get_cell_value)(self.current_sheet.as_deref(), *row, *col)
-            }
-
-            Expr::Range { .. } => {
-                // Ranges should be expanded by functions, not evaluated directly
-                CellValue::Error(CellError::InvalidValue)
+                (self.get_cell_value)(self.current_sheet, *row, *col)
             }
 
             Expr::SheetRef { sheet_name, reference } => {
@@ -454,14 +424,27 @@ where
                 self.evaluate_with_sheet(reference, sheet_name)
             }
 
-            Expr::Binary { left, op, right } => self.evaluate_binary(left, *op, right),
-
-            Expr::Unary { op, operand } => self.evaluate_unary(*op, operand),
-
-            Expr::FunctionCall { name, args } => self.evaluate_function(name, args),
+            // Ranges and operators may produce several values; reduce them
+            // to the one this cell can hold
+            Expr::Range { .. }
+            | Expr::Array(_)
+            | Expr::Binary { .. }
+            | Expr::Unary { .. }
+            | Expr::Union(_)
+            | Expr::Intersection { .. } => self.to_scalar(self.evaluate_array(expr)),
+
+            Expr::FunctionCall { name, args } => {
+                self.limit_text(self.evaluate_function(name, args))
+            }
 
             Expr::Grouped(inner) => self.evaluate(inner),
-        }
+
+            // The calc engine resolves structured references against the
+            // workbook's tables before evaluating, so any left name no table
+            Expr::Structured(_) => CellValue::Error(CellError::InvalidReference),
+        };
+        self.record(expr, || value.clone());
+        value
     }
 
     /// Evaluate an expression with a specific sheet context
@@ -470,30 +453,236 @@ where
             Expr::CellRef { row, col, .. } => {
                 (self.get_cell_value)(Some(sheet_name), *row, *col)
             }
-            Expr::Range { .. } => {
-                // Ranges in sheet refs should be handled by functions
-                CellValue::Error(CellError::InvalidValue)
+            Expr::Range { start, end } => {
+                self.to_scalar(self.range_array(start, end, Some(sheet_name)))
             }
             _ => self.evaluate(expr),
         }
     }
 
-    fn evaluate_binary(&self, left: &Expr, op: BinaryOp, right: &Expr) -> CellValue {
-        let left_val = self.evaluate(left);
-        let right_val = self.evaluate(right);
+    /// Evaluate an expression in array context
+    ///
+    /// Ranges become arrays of their cells and operators apply element by
+    /// element, broadcasting single rows, columns and values. Everything
+    /// else evaluates to a 1x1 array.
+    fn evaluate_array(&self, expr: &Expr) -> Array {
+        if let Err(e) = self.charge(1) {
+            return Array::from_scalar(CellValue::Error(e));
+        }
+        let array = match expr {
+            Expr::Range { start, end } => self.range_array(start, end, self.current_sheet),
+            Expr::SheetRef { sheet_name, reference } => match reference.as_ref() {
+                Expr::Range { start, end } => self.range_array(start, end, Some(sheet_name)),
+                _ => Array::from_scalar(self.evaluate(expr)),
+            },
+            Expr::Binary { .. } => self.evaluate_chain(expr),
+            Expr::Unary {
+                op: UnaryOp::ImplicitIntersection,
+                operand,
+            } => Array::from_scalar(self.intersect(self.evaluate_array(operand))),
+            Expr::Unary { op, operand } => self
+                .evaluate_array(operand)
+                .map(|value| self.unary_value(*op, value)),
+            Expr::Grouped(inner) => self.evaluate_array(inner),
+            Expr::Array(rows) => {
+                let cols = rows.first().map_or(0, Vec::len);
+                // The parser rejects ragged rows, but trees built in code may have them
+                if rows.iter().any(|row| row.len() != cols) {
+                    Array::from_scalar(CellValue::Error(CellError::InvalidValue))
+                } else {
+                    let values = rows.iter().flatten().map(|item| self.evaluate(item)).collect();
+                    Array::new(rows.len(), cols, values)
+                }
+            }
+            // A multi-area reference has no single shape to compute with
+            Expr::Union(_) | Expr::Intersection { .. } => match self.resolve_areas(expr) {
+                Ok(areas) if areas.len() == 1 => self.area_array(&areas[0]),
+                Ok(_) => Array::from_scalar(CellValue::Error(CellError::InvalidValue)),
+                Err(e) => Array::from_scalar(CellValue::Error(e)),
+            },
+            _ => Array::from_scalar(self.evaluate(expr)),
+        };
+        self.record(expr, || self.to_scalar(array.clone()));
+        array
+    }
+
+    /// Evaluate a chain of binary operations such as `A1+A2+...+A1000`
+    /// from left to right, in a loop rather than by recursion
+    fn evaluate_chain(&self, expr: &Expr) -> Array {
+        let (first, links) = expr.chain();
+        // The chain's own step was charged by evaluate_array
+        if let Err(e) = self.charge(links.len() as u64 - 1) {
+            return Array::from_scalar(CellValue::Error(e));
+        }
+        let mut value = self.evaluate_array(first);
+        for (op, right) in links {
+            let right = self.evaluate_array(right);
+            // A row against a column expands to their product
+            let rows = value.rows().max(right.rows());
+            let cols = value.cols().max(right.cols());
+            if let Err(e) = self.reserve(rows, cols) {
+                return Array::from_scalar(CellValue::Error(e));
+            }
+            value = Array::broadcast(&value, &right, |a, b| self.binary_value(a, op, b));
+        }
+        value
+    }
+
+    /// Resolve a reference expression into the rectangular areas it covers
+    ///
+    /// Unions keep each area separately; an intersection keeps the overlap
+    /// of every pair of areas and is #NULL! when nothing overlaps. Returns
+    /// #VALUE! if the expression is not a reference.
+    fn resolve_areas<'e>(&'e self, expr: &'e Expr) -> Result<Vec<Area<'e>>, CellError> {
+        self.resolve_areas_in(expr, self.current_sheet)
+    }
+
+    fn resolve_areas_in<'e>(
+        &'e self,
+        expr: &'e Expr,
+        sheet: Option<&'e str>,
+    ) -> Result<Vec<Area<'e>>, CellError> {
+        match expr {
+            Expr::CellRef { row, col, .. } => Ok(vec![Area {
+                sheet,
+                top: *row,
+                left: *col,
+                bottom: *row,
+                right: *col,
+            }]),
+            Expr::Range { start, end } => {
+                let (r1, c1) = self.get_cell_coords(start);
+                let (r2, c2) = self.get_cell_coords(end);
+                Ok(vec![Area {
+                    sheet,
+                    top: r1.min(r2),
+                    left: c1.min(c2),
+                    bottom: r1.max(r2),
+                    right: c1.max(c2),
+                }])
+            }
+            Expr::SheetRef { sheet_name, reference } => {
+                self.resolve_areas_in(reference, Some(sheet_name))
+            }
+            Expr::Grouped(inner) => self.resolve_areas_in(inner, sheet),
+            Expr::Union(parts) => {
+                let mut areas = Vec::new();
+                for part in parts {
+                    areas.extend(self.resolve_areas_in(part, sheet)?);
+                }
+                Ok(areas)
+            }
+            Expr::Intersection { left, right } => {
+                let left = self.resolve_areas_in(left, sheet)?;
+                let right = self.resolve_areas_in(right, sheet)?;
+                let areas: Vec<Area> = left
+                    .iter()
+                    .flat_map(|a| right.iter().filter_map(move |b| a.intersect(b)))
+                    .collect();
+                if areas.is_empty() {
+                    Err(CellError::NullError)
+                } else {
+                    Ok(areas)
+                }
+            }
+            _ => Err(CellError::InvalidValue),
+        }
+    }
+
+    /// Read an area into an array that remembers its position
+    fn area_array(&self, area: &Area) -> Array {
+        if let Err(e) = self.reserve(area.rows(), area.cols()) {
+            return Array::from_scalar(CellValue::Error(e));
+        }
+        let mut values = Vec::with_capacity(area.rows() * area.cols());
+        for row in area.top..=area.bottom {
+            for col in area.left..=area.right {
+                values.push((self.get_cell_value)(area.sheet, row, col));
+            }
+        }
+        Array::new(area.rows(), area.cols(), values).with_origin(area.top, area.left)
+    }
+
+    /// Read a rectangular range into an array that remembers its position
+    fn range_array(&self, start: &Expr, end: &Expr, sheet: Option<&str>) -> Array {
+        let (Expr::CellRef { row: r1, col: c1, .. }, Expr::CellRef { row: r2, col: c2, .. }) =
+            (start, end)
+        else {
+            return Array::from_scalar(CellValue::Error(CellError::InvalidReference));
+        };
+        self.area_array(&Area {
+            sheet,
+            top: (*r1).min(*r2),
+            left: (*c1).min(*c2),
+            bottom: (*r1).max(*r2),
+            right: (*c1).max(*c2),
+        })
+    }
+
+    /// Reduce an array to the single value a cell can hold
+    ///
+    /// A 1x1 array yields its value. Larger arrays are #VALUE! unless
+    /// implicit intersection is enabled.
+    fn to_scalar(&self, array: Array) -> CellValue {
+        if array.rows() == 1 && array.cols() == 1 {
+            array.into_values().swap_remove(0)
+        } else if self.implicit_intersection {
+            self.intersect(array)
+        } else {
+            CellValue::Error(CellError::InvalidValue)
+        }
+    }
+
+    /// Pick the element of an array in line with the current cell
+    ///
+    /// A single column yields the value in the current cell's row and a
+    /// single row the value in its column; a 2D range needs both to line
+    /// up. Arrays not read from a range yield their top-left value. Returns
+    /// #VALUE! when there is no such element.
+    fn intersect(&self, array: Array) -> CellValue {
+        let (row, col) = match (array.origin(), self.current_cell) {
+            _ if array.rows() == 1 && array.cols() == 1 => (Some(0), Some(0)),
+            (None, _) => (Some(0), Some(0)),
+            (Some(_), None) => (None, None),
+            (Some((origin_row, origin_col)), Some((cell_row, cell_col))) => {
+                let line_up = |len: usize, origin: u32, pos: u32| {
+                    if len == 1 {
+                        Some(0)
+                    } else {
+                        pos.checked_sub(origin)
+                            .map(|offset| offset as usize)
+                            .filter(|offset| *offset < len)
+                    }
+                };
+                (
+                    line_up(array.rows(), origin_row, cell_row),
+                    line_up(array.cols(), origin_col, cell_col),
+                )
+            }
+        };
+        match (row, col) {
+            (Some(row), Some(col)) => array
+                .get(row, col)
+                .cloned()
+                .unwrap_or(CellValue::Error(CellError::InvalidValue)),
+            _ => CellValue::Error(CellError::InvalidValue),
+        }
+    }
 
+    /// Apply a binary operator to two single values
+    fn binary_value(&self, left_val: &CellValue, op: BinaryOp, right_val: &CellValue) -> CellValue {
         // Propagate errors
-        if let CellValue::Error(e) = &left_val {
+        if let CellValue::Error(e) = left_val {
             return CellValue::Error(e.clone());
         }
-        if let CellValue::Error(e) = &right_val {
+        if let CellValue::Error(e) = right_val {
             return CellValue::Error(e.clone());
         }
 
         match op {
-            BinaryOp::Add => self.numeric_op(&left_val, &right_val, |a, b| a + b),
-            BinaryOp::Sub => self.numeric_op(&left_val, &right_val, |a, b| a - b),
-            BinaryOp::Mul => self.numeric_op(&left_val, &right_val, |a, b| a * b),
+            BinaryOp::Add => self.numeric_op(left_val, right_val, |a, b| a + b),
+            BinaryOp::Sub => self.numeric_op(left_val, right_val, |a, b| a - b),
+            BinaryOp::Mul => self.numeric_op(left_val, right_val, |a, b| a * b),
             BinaryOp::Div => {
                 match (left_val.as_number(), right_val.as_number()) {
                     (Some(a), Some(b)) => {
@@ -506,20 +695,20 @@ where
                     _ => CellValue::Error(CellError::InvalidValue),
                 }
             }
-            BinaryOp::Pow => self.numeric_op(&left_val, &right_val, |a, b| a.powf(b)),
+            BinaryOp::Pow => self.numeric_op(left_val, right_val, |a, b| a.powf(b)),
 
             BinaryOp::Concat => {
                 let left_str = left_val.as_text();
                 let right_str = right_val.as_text();
-                CellValue::Text(format!("{}{}", left_str, right_str))
+                self.limit_text(CellValue::Text(format!("{}{}", left_str, right_str)))
             }
 
-            BinaryOp::Eq => CellValue::Boolean(self.compare_values(&left_val, &right_val) == 0),
-            BinaryOp::Ne => CellValue::Boolean(self.compare_values(&left_val, &right_val) != 0),
-            BinaryOp::Lt => CellValue::Boolean(self.compare_values(&left_val, &right_val) < 0),
-            BinaryOp::Gt => CellValue::Boolean(self.compare_values(&left_val, &right_val) > 0),
-            BinaryOp::Le => CellValue::Boolean(self.compare_values(&left_val, &right_val) <= 0),
-            BinaryOp::Ge => CellValue::Boolean(self.compare_values(&left_val, &right_val) >= 0),
+            BinaryOp::Eq => CellValue::Boolean(self.compare_values(left_val, right_val) == 0),
+            BinaryOp::Ne => CellValue::Boolean(self.compare_values(left_val, right_val) != 0),
+            BinaryOp::Lt => CellValue::Boolean(self.compare_values(left_val, right_val) < 0),
+            BinaryOp::Gt => CellValue::Boolean(self.compare_values(left_val, right_val) > 0),
+            BinaryOp::Le => CellValue::Boolean(self.compare_values(left_val, right_val) <= 0),
+            BinaryOp::Ge => CellValue::Boolean(self.compare_values(left_val, right_val) >= 0),
         }
     }
 
@@ -545,12 +734,21 @@ where
     fn compare_values(&self, left: &CellValue, right: &CellValue) -> i8 {
         match (left, right) {
             (CellValue::Number(a), CellValue::Number(b)) => {
-                if a < b { -1 } else if a > b { 1 } else { 0 }
+                // Compare at 15 significant digits, so 0.1 + 0.2 = 0.3
+                let (a, b) = (round_significant(*a), round_significant(*b));
+                if a < b {
+                    -1
+                } else if a > b {
+                    1
+                } else {
+                    0
+                }
             }
             (CellValue::Text(a), CellValue::Text(b)) => {
                 a.to_lowercase().cmp(&b.to_lowercase()) as i8
             }
             (CellValue::Boolean(a), CellValue::Boolean(b)) => (*a as i8) - (*b as i8),
+            // Type coercion for comparisons
             _ => {
                 let a_str = left.as_text();
                 let b_str = right.as_text();
@@ -559,9 +757,8 @@ where
         }
     }
 
-    fn evaluate_unary(&self, op: UnaryOp, operand: &Expr) -> CellValue {
-        let value = self.evaluate(operand);
-
+    /// Apply a unary operator to a single value
+    fn unary_value(&self, op: UnaryOp, value: CellValue) -> CellValue {
         if let CellValue::Error(e) = &value {
             return CellValue::Error(e.clone());
         }
@@ -579,32 +776,19 @@ where
                 Some(n) => CellValue::Number(n / 100.0),
                 None => CellValue::Error(CellError::InvalidValue),
             },
+            // A single value lines up with every cell
+            UnaryOp::ImplicitIntersection => value,
         }
     }
 
     fn evaluate_function(&self, name: &str, args: &[Expr]) -> CellValue {
-        // Collect values, expanding ranges
-        let values: Vec<CellValue> = args
-            .iter()
-            .flat_map(|arg| self.expand_argument(arg))
-            .collect();
+        let name = name.to_uppercase();
 
-        match name.to_uppercase().as_str() {
-            // Math functions
-            "SUM" => functions::math::sum(&values),
-            "AVERAGE" | "AVG" => functions::math::average(&values),
-            "COUNT" => functions::math::count(&values),
-            "COUNTA" => functions::math::counta(&values),
-            "MIN" => functions::math::min(&values),
-            "MAX" => functions::math::max(&values),
-            "ABS" => functions::math::abs(&values),
-            "ROUND" => functions::math::round(&values),
-            "FLOOR" => functions::math::floor(&values),
-            "CEILING" | "CEIL" => functions::math::ceiling(&values),
-            "SQRT" => functions::math::sqrt(&values),
-            "POWER" | "POW" => functions::math::power(&values),
-
-            // Conditional functions
+        // Functions that need the unevaluated arguments, either because they
+        // short-circuit (so errors in untaken branches don't propagate) or
+        // because they inspect references rather than values
+        match name.as_str() {
+            // Conditional functions - need special handling
             "COUNTIF" => {
                 if args.len() < 2 {
                     return CellValue::Error(CellError::InvalidValue);
@@ -639,40 +823,112 @@ where
                 };
                 functions::math::averageif(&criteria_range, &criteria, avg_range.as_deref())
             }
+            "SUBTOTAL" => self.subtotal(args),
+            "SUMPRODUCT" => {
+                // Each argument is a separate array, so don't flatten them together
+                let arrays: Vec<Vec<CellValue>> =
+                    args.iter().map(|arg| self.expand_argument(arg)).collect();
+                functions::math::sumproduct(&arrays)
+            }
 
             // Logical functions
             "IF" => {
+                // IF needs special handling - don't expand args
                 let arg_values: Vec<CellValue> = args.iter().map(|a| self.evaluate(a)).collect();
                 functions::logical::if_fn(&arg_values)
             }
-            "AND" => functions::logical::and(&values),
-            "OR" => functions::logical::or(&values),
-            "NOT" => functions::logical::not(&values),
-            "TRUE" => CellValue::Boolean(true),
-            "FALSE" => CellValue::Boolean(false),
-
-            // Text functions
-            "CONCAT" | "CONCATENATE" => functions::text::concat(&values),
-            "LEN" => functions::text::len(&values),
-            "UPPER" => functions::text::upper(&values),
-            "LOWER" => functions::text::lower(&values),
-            "TRIM" => functions::text::trim(&values),
-            "LEFT" => functions::text::left(&values),
-            "RIGHT" => functions::text::right(&values),
-            "MID" => functions::text::mid(&values),
+            "IFERROR" | "IFNA" => {
+                if args.len() != 2 {
+                    return CellValue::Error(CellError::InvalidValue);
+                }
+                match self.evaluate(&args[0]) {
+                    CellValue::Error(CellError::NotAvailable) => self.evaluate(&args[1]),
+                    CellValue::Error(_) if name == "IFERROR" => self.evaluate(&args[1]),
+                    value => value,
+                }
+            }
+            "IFS" => {
+                if args.is_empty() || !args.len().is_multiple_of(2) {
+                    return CellValue::Error(CellError::InvalidValue);
+                }
+                for pair in args.chunks(2) {
+                    match functions::logical::condition(&self.evaluate(&pair[0])) {
+                        Ok(true) => return self.evaluate(&pair[1]),
+                        Ok(false) => {}
+                        Err(e) => return CellValue::Error(e),
+                    }
+                }
+                CellValue::Error(CellError::NotAvailable)
+            }
+            "SWITCH" => {
+                if args.len() < 3 {
+                    return CellValue::Error(CellError::InvalidValue);
+                }
+                let value = self.evaluate(&args[0]);
+                if let CellValue::Error(e) = value {
+                    return CellValue::Error(e);
+                }
+                let cases = &args[1..];
+                for pair in cases.chunks_exact(2) {
+                    let case = self.evaluate(&pair[0]);
+                    if let CellValue::Error(e) = case {
+                        return CellValue::Error(e);
+                    }
+                    if self.compare_values(&value, &case) == 0 {
+                        return self.evaluate(&pair[1]);
+                    }
+                }
+                // An odd number of case arguments means the last one is the default
+                match cases.chunks_exact(2).remainder() {
+                    [default] => self.evaluate(default),
+                    _ => CellValue::Error(CellError::NotAvailable),
+                }
+            }
+            "CHOOSE" => {
+                if args.len() < 2 {
+                    return CellValue::Error(CellError::InvalidValue);
+                }
+                let index = match self.evaluate(&args[0]) {
+                    CellValue::Error(e) => return CellValue::Error(e),
+                    value => match value.as_number() {
+                        Some(n) => n.trunc(),
+                        None => return CellValue::Error(CellError::InvalidValue),
+                    },
+                };
+                if index < 1.0 || index >= args.len() as f64 {
+                    return CellValue::Error(CellError::InvalidValue);
+                }
+                self.evaluate(&args[index as usize])
+            }
 
-            // Date/Time functions
-            "TODAY" => functions::datetime::today(&values),
-            "NOW" => functions::datetime::now(&values),
-            "DATE" => functions::datetime::date(&values),
-            "TIME" => functions::datetime::time(&values),
-            "YEAR" => functions::datetime::year(&values),
-            "MONTH" => functions::datetime::month(&values),
-            "DAY" => functions::datetime::day(&values),
-            "HOUR" => functions::datetime::hour(&values),
-            "MINUTE" => functions::datetime::minute(&values),
-            "SECOND" => functions::datetime::second(&values),
-            "DATEDIF" => functions::datetime::datedif(&values),
+            // Information functions that look at the reference, not its value
+            "ISREF" => {
+                if args.len() != 1 {
+                    return CellValue::Error(CellError::InvalidValue);
+                }
+                CellValue::Boolean(self.is_reference(&args[0]))
+            }
+            "AREAS" => {
+                if args.len() != 1 {
+                    return CellValue::Error(CellError::InvalidValue);
+                }
+                match self.resolve_areas(&args[0]) {
+                    Ok(areas) => CellValue::Number(areas.len() as f64),
+                    Err(e) => CellValue::Error(e),
+                }
+            }
+            "ISFORMULA" => {
+                if args.len() != 1 {
+                    return CellValue::Error(CellError::InvalidValue);
+                }
+                match self.reference_content(&args[0]) {
+                    Ok(content) => CellValue::Boolean(matches!(
+                        content,
+                        Some(CellContent::Formula { .. })
+                    )),
+                    Err(e) => CellValue::Error(e),
+                }
+            }
 
             // Lookup functions
             "MATCH" => {
@@ -748,15 +1004,239 @@ where
                 functions::lookup::hlookup(&lookup_value, &table_data, num_rows, num_cols, row_index, approximate)
             }
 
+            _ => {
+                // Collect values, expanding ranges
+                let values: Vec<CellValue> = args
+                    .iter()
+                    .flat_map(|arg| self.expand_argument(arg))
+                    .collect();
+                self.call_function(&name, &values)
+            }
+        }
+    }
+
+    /// Call a function that operates on its (range-expanded) argument values
+    fn call_function(&self, name: &str, values: &[CellValue]) -> CellValue {
+        match name {
+            // Math functions
+            "SUM" => functions::math::sum(values),
+            "AVERAGE" | "AVG" => functions::math::average(values),
+            "COUNT" => functions::math::count(values),
+            "COUNTA" => functions::math::counta(values),
+            "MIN" => functions::math::min(values),
+            "MAX" => functions::math::max(values),
+            "ABS" => functions::math::abs(values),
+            "ROUND" => functions::math::round(values),
+            "FLOOR" => functions::math::floor(values),
+            "CEILING" | "CEIL" => functions::math::ceiling(values),
+            "SQRT" => functions::math::sqrt(values),
+            "POWER" | "POW" => functions::math::power(values),
+            "MOD" => functions::math::mod_fn(values),
+            "QUOTIENT" => functions::math::quotient(values),
+            "INT" => functions::math::int(values),
+            "TRUNC" => functions::math::trunc(values),
+            "ROUNDUP" => functions::math::roundup(values),
+            "ROUNDDOWN" => functions::math::rounddown(values),
+            "MROUND" => functions::math::mround(values),
+            "SIGN" => functions::math::sign(values),
+            "PRODUCT" => functions::math::product(values),
+            "VAR" | "VAR.S" => functions::math::var(values),
+            "VARP" | "VAR.P" => functions::math::varp(values),
+            "STDEV" | "STDEV.S" => functions::math::stdev(values),
+            "STDEVP" | "STDEV.P" => functions::math::stdevp(values),
+            "SUMSQ" => functions::math::sumsq(values),
+            "EXP" => functions::math::exp(values),
+            "LN" => functions::math::ln(values),
+            "LOG" => functions::math::log(values),
+            "LOG10" => functions::math::log10(values),
+            "PI" => functions::math::pi(values),
+            "SIN" => functions::math::sin(values),
+            "COS" => functions::math::cos(values),
+            "TAN" => functions::math::tan(values),
+            "ASIN" => functions::math::asin(values),
+            "ACOS" => functions::math::acos(values),
+            "ATAN" => functions::math::atan(values),
+            "ATAN2" => functions::math::atan2(values),
+            "SINH" => functions::math::sinh(values),
+            "COSH" => functions::math::cosh(values),
+            "TANH" => functions::math::tanh(values),
+            "DEGREES" => functions::math::degrees(values),
+            "RADIANS" => functions::math::radians(values),
+            "RAND" => functions::math::rand(values),
+            "RANDBETWEEN" => functions::math::randbetween(values),
+            "GCD" => functions::math::gcd(values),
+            "LCM" => functions::math::lcm(values),
+            "FACT" => functions::math::fact(values),
+            "COMBIN" => functions::math::combin(values),
+            "PERMUT" => functions::math::permut(values),
+
+            // Logical functions
+            "AND" => functions::logical::and(values),
+            "OR" => functions::logical::or(values),
+            "NOT" => functions::logical::not(values),
+            "TRUE" => CellValue::Boolean(true),
+            "FALSE" => CellValue::Boolean(false),
+
+            // Information functions
+            "ISBLANK" => functions::information::isblank(values),
+            "ISNUMBER" => functions::information::isnumber(values),
+            "ISTEXT" => functions::information::istext(values),
+            "ISLOGICAL" => functions::information::islogical(values),
+            "ISERROR" => functions::information::iserror(values),
+            "ISERR" => functions::information::iserr(values),
+            "ISNA" => functions::information::isna(values),
+            "ERROR.TYPE" => functions::information::error_type(values),
+            "NA" => functions::information::na(values),
+            "TYPE" => functions::information::type_fn(values),
+            "N" => functions::information::n(values),
+            "T" => functions::information::t(values),
+
+            // Text functions
+            "CONCAT" | "CONCATENATE" => functions::text::concat(values),
+            "LEN" => functions::text::len(values),
+            "UPPER" => functions::text::upper(values),
+            "LOWER" => functions::text::lower(values),
+            "TRIM" => functions::text::trim(values),
+            "LEFT" => functions::text::left(values),
+            "RIGHT" => functions::text::right(values),
+            "MID" => functions::text::mid(values),
+            "HYPERLINK" => functions::text::hyperlink(values),
+
+            // Date/Time functions
+            "TODAY" => functions::datetime::today(values, &self.date_context),
+            "NOW" => functions::datetime::now(values, &self.date_context),
+            "DATE" => functions::datetime::date(values, &self.date_context),
+            "TIME" => functions::datetime::time(values),
+            "YEAR" => functions::datetime::year(values, &self.date_context),
+            "MONTH" => functions::datetime::month(values, &self.date_context),
+            "DAY" => functions::datetime::day(values, &self.date_context),
+            "HOUR" => functions::datetime::hour(values),
+            "MINUTE" => functions::datetime::minute(values),
+            "SECOND" => functions::datetime::second(values),
+            "DATEDIF" => functions::datetime::datedif(values, &self.date_context),
+            "DATEVALUE" => functions::datetime::datevalue(values, &self.date_context),
+            "TIMEVALUE" => functions::datetime::timevalue(values, &self.date_context),
+            "EDATE" => functions::datetime::edate(values, &self.date_context),
+            "EOMONTH" => functions::datetime::eomonth(values, &self.date_context),
+            "WEEKDAY" => functions::datetime::weekday(values, &self.date_context),
+            "WEEKNUM" => functions::datetime::weeknum(values, &self.date_context),
+            "ISOWEEKNUM" => functions::datetime::isoweeknum(values, &self.date_context),
+            "WORKDAY" => functions::datetime::workday(values, &self.date_context),
+            "WORKDAY.INTL" => functions::datetime::workday_intl(values, &self.date_context),
+            "NETWORKDAYS" => functions::datetime::networkdays(values, &self.date_context),
+            "NETWORKDAYS.INTL" => functions::datetime::networkdays_intl(values, &self.date_context),
+            "YEARFRAC" => functions::datetime::yearfrac(values, &self.date_context),
+
             _ => CellValue::Error(CellError::InvalidName),
         }
     }
 
+    /// Check whether an argument is a reference to a valid location
+    ///
+    /// Decided from the argument's shape, not the values it refers to: any
+    /// cell or range reference counts, as long as its sheet exists.
+    fn is_reference(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::CellRef { .. } | Expr::Range { .. } => true,
+            Expr::SheetRef { .. } | Expr::Union(_) | Expr::Intersection { .. } => {
+                self.resolve_areas(expr).is_ok_and(|areas| {
+                    areas.iter().all(|area| {
+                        area.sheet.is_none_or(|sheet| self.sheet_exists(sheet, area.top, area.left))
+                    })
+                })
+            }
+            Expr::Grouped(inner) => self.is_reference(inner),
+            _ => false,
+        }
+    }
+
+    /// Check whether a sheet exists by looking at one of its cells
+    ///
+    /// A missing sheet reads as #REF! with nothing behind it, while a cell
+    /// on a real sheet that holds #REF! has content. Without a content
+    /// lookup the two can't be told apart.
+    fn sheet_exists(&self, sheet: &str, row: u32, col: u32) -> bool {
+        let missing = CellValue::Error(CellError::InvalidReference);
+        (self.get_cell_value)(Some(sheet), row, col) != missing
+            || self
+                .get_cell_content
+                .is_some_and(|lookup| lookup(Some(sheet), row, col).is_some())
+    }
+
+    /// Look up the content of the cell an argument refers to
+    ///
+    /// Ranges resolve to their top-left cell. Returns #VALUE! if the argument
+    /// is not a reference.
+    fn reference_content(&self, expr: &Expr) -> Result<Option<CellContent>, CellError> {
+        let (sheet, target) = match expr {
+            Expr::SheetRef { sheet_name, reference } => (Some(sheet_name.as_str()), reference.as_ref()),
+            Expr::Grouped(inner) => return self.reference_content(inner),
+            _ => (self.current_sheet, expr),
+        };
+        let (row, col) = match target {
+            Expr::CellRef { row, col, .. } => (*row, *col),
+            Expr::Range { start, .. } => self.get_cell_coords(start),
+            _ => return Err(CellError::InvalidValue),
+        };
+        Ok(self
+            .get_cell_content
+            .and_then(|get_cell_content| get_cell_content(sheet, row, col)))
+    }
+
+    /// SUBTOTAL over the cells its references cover
+    ///
+    /// Rows hidden by a filter are always left out, and rows hidden by the
+    /// user too for codes 101-111. Cells holding SUBTOTAL formulas are left
+    /// out so nested subtotals aren't counted twice.
+    fn subtotal(&self, args: &[Expr]) -> CellValue {
+        let Some((code, refs)) = args.split_first() else {
+            return CellValue::Error(CellError::InvalidValue);
+        };
+        let code = self.evaluate(code);
+        let skip_hidden = code.as_number().is_some_and(|n| n >= 101.0);
+        let mut values = vec![code];
+        for arg in refs {
+            let Ok(areas) = self.resolve_areas(arg) else {
+                values.extend(self.expand_argument(arg));
+                continue;
+            };
+            for area in areas {
+                if let Err(e) = self.reserve(area.rows(), area.cols()) {
+                    return CellValue::Error(e);
+                }
+                for row in area.top..=area.bottom {
+                    let shown = match self.get_row_visibility.map(|v| v(area.sheet, row)) {
+                        Some(RowVisibility::Filtered) => false,
+                        Some(RowVisibility::Hidden) => !skip_hidden,
+                        _ => true,
+                    };
+                    if !shown {
+                        continue;
+                    }
+                    for col in area.left..=area.right {
+                        if !self.is_subtotal_cell(area.sheet, row, col) {
+                            values.push((self.get_cell_value)(area.sheet, row, col));
+                        }
+                    }
+                }
+            }
+        }
+        functions::math::subtotal(&values)
+    }
+
+    fn is_subtotal_cell(&self, sheet: Option<&str>, row: u32, col: u32) -> bool {
+        let content = self.get_cell_content.and_then(|lookup| lookup(sheet, row, col));
+        // Only formulas mentioning the name are worth parsing
+        matches!(content, Some(CellContent::Formula { expression, .. })
+            if expression.to_ascii_uppercase().contains("SUBTOTAL")
+                && NomParser::new().parse(&expression).is_ok_and(|ast| ast.calls_function("SUBTOTAL")))
+    }
+
     /// Expand an argument, handling ranges and sheet references
     fn expand_argument(&self, expr: &Expr) -> Vec<CellValue> {
         match expr {
             Expr::Range { start, end } => {
-                self.expand_range(start, end, self.current_sheet.as_deref())
+                self.expand_range(start, end, self.current_sheet)
             }
             Expr::SheetRef { sheet_name, reference } => {
                 match reference.as_ref() {
@@ -769,6 +1249,25 @@ where
                     _ => vec![self.evaluate(expr)],
                 }
             }
+            // Multi-area references contribute every cell of every area
+            _ if matches!(
+                expr.strip_groups(),
+                Expr::Union(_) | Expr::Intersection { .. }
+            ) =>
+            {
+                match self.resolve_areas(expr) {
+                    Ok(areas) => areas
+                        .iter()
+                        .flat_map(|area| self.area_array(area).into_values())
+                        .collect(),
+                    Err(e) => vec![CellValue::Error(e)],
+                }
+            }
+            // Array constants and operators over ranges produce arrays,
+            // e.g. SUM({1,2,3}) or SUM(A1:A3*B1:B3)
+            Expr::Array(_) | Expr::Binary { .. } | Expr::Unary { .. } | Expr::Grouped(_) => {
+                self.evaluate_array(expr).into_values()
+            }
             _ => vec![self.evaluate(expr)],
         }
     }
@@ -785,7 +1284,13 @@ where
             let min_col = (*c1).min(*c2);
             let max_col = (*c1).max(*c2);
 
-            let mut values = Vec::new();
+            let rows = (max_row - min_row + 1) as usize;
+            let cols = (max_col - min_col + 1) as usize;
+            if let Err(e) = self.reserve(rows, cols) {
+                return vec![CellValue::Error(e)];
+            }
+
+            let mut values = Vec::with_capacity(rows * cols);
             for row in min_row..=max_row {
                 for col in min_col..=max_col {
                     values.push((self.get_cell_value)(sheet, row, col));
@@ -797,68 +1302,48 @@ where
         }
     }
 
-    /// Expand a range with dimensions needed for VLOOKUP/HLOOKUP
+    /// Expand a range or array with dimensions needed for VLOOKUP/HLOOKUP
     fn expand_range_with_dimensions(&self, expr: &Expr) -> (Vec<CellValue>, usize, usize) {
-        match expr {
-            Expr::Range { start, end } => {
-                let (start_row, start_col) = self.get_cell_coords_from_expr(start);
-                let (end_row, end_col) = self.get_cell_coords_from_expr(end);
-
-                let min_row = start_row.min(end_row);
-                let max_row = start_row.max(end_row);
-                let min_col = start_col.min(end_col);
-                let max_col = start_col.max(end_col);
+        let (sheet, start, end) = match expr {
+            Expr::Range { start, end } => (self.current_sheet, start, end),
+            Expr::SheetRef { sheet_name, reference } => match reference.as_ref() {
+                Expr::Range { start, end } => (Some(sheet_name.as_str()), start, end),
+                _ => return (vec![self.evaluate(expr)], 1, 1),
+            },
+            // Array constants such as {"a",1;"b",2} carry their own shape
+            _ => {
+                let array = self.evaluate_array(expr);
+                let (rows, cols) = (array.rows(), array.cols());
+                return (array.into_values(), rows, cols);
+            }
+        };
 
-                let num_rows = (max_row - min_row + 1) as usize;
-                let num_cols = (max_col - min_col + 1) as usize;
+        let (start_row, start_col) = self.get_cell_coords(start);
+        let (end_row, end_col) = self.get_cell_coords(end);
 
-                let mut values = Vec::with_capacity(num_rows * num_cols);
-                for row in min_row..=max_row {
-                    for col in min_col..=max_col {
-                        values.push((self.get_cell_value)(self.current_sheet.as_deref(), row, col));
-                    }
-                }
+        let min_row = start_row.min(end_row);
+        let max_row = start_row.max(end_row);
+        let min_col = start_col.min(end_col);
+        let max_col = start_col.max(end_col);
 
-                (values, num_rows, num_cols)
-            }
-            Expr::SheetRef { sheet_name, reference } => {
-                match reference.as_ref() {
-                    Expr::Range { start, end } => {
-                        let (start_row, start_col) = self.get_cell_coords_from_expr(start);
-                        let (end_row, end_col) = self.get_cell_coords_from_expr(end);
-
-                        let min_row = start_row.min(end_row);
-                        let max_row = start_row.max(end_row);
-                        let min_col = start_col.min(end_col);
-                        let max_col = start_col.max(end_col);
-
-                        let num_rows = (max_row - min_row + 1) as usize;
-                        let num_cols = (max_col - min_col + 1) as usize;
-
-                        let mut values = Vec::with_capacity(num_rows * num_cols);
-                        for row in min_row..=max_row {
-                            for col in min_col..=max_col {
-                                values.push((self.get_cell_value)(Some(sheet_name), row, col));
-                            }
-                        }
+        let num_rows = (max_row - min_row + 1) as usize;
+        let num_cols = (max_col - min_col + 1) as usize;
+        if let Err(e) = self.reserve(num_rows, num_cols) {
+            return (vec![CellValue::Error(e)], 1, 1);
+        }
 
-                        (values, num_rows, num_cols)
-                    }
-                    _ => {
-                        let value = self.evaluate(expr);
-                        (vec![value], 1, 1)
-                    }
-                }
-            }
-            _ => {
-                let value = self.evaluate(expr);
-                (vec![value], 1, 1)
+        let mut values = Vec::with_capacity(num_rows * num_cols);
+        for row in min_row..=max_row {
+            for col in min_col..=max_col {
+                values.push((self.get_cell_value)(sheet, row, col));
             }
         }
+
+        (values, num_rows, num_cols)
     }
 
     /// Get cell coordinates from a CellRef expression
-    fn get_cell_coords_from_expr(&self, expr: &Expr) -> (u32, u32) {
+    fn get_cell_coords(&self, expr: &Expr) -> (u32, u32) {
         match expr {
             Expr::CellRef { col, row, .. } => (*row, *col),
             _ => (0, 0),
@@ -1138,4 +1623,396 @@ mod tests {
         });
         assert_eq!(result, CellValue::Number(2.0)); // Found at position 2
     }
+
+    #[test]
+    fn test_iferror_catches_errors() {
+        assert_eq!(eval("IFERROR(1/0, 0)"), CellValue::Number(0.0));
+        assert_eq!(eval("IFERROR(1+1, 0)"), CellValue::Number(2.0));
+        assert_eq!(eval("IFERROR(NA(), \"missing\")"), CellValue::Text("missing".to_string()));
+        // Errors inside the fallback expression still surface
+        assert_eq!(eval("IFERROR(1/0, 1/0)"), CellValue::Error(CellError::DivisionByZero));
+        // The caught value can take part in further arithmetic
+        assert_eq!(eval("IFERROR(1/0, 5) * 2"), CellValue::Number(10.0));
+    }
+
+    #[test]
+    fn test_ifna_only_catches_na() {
+        assert_eq!(eval("IFNA(NA(), 1)"), CellValue::Number(1.0));
+        assert_eq!(eval("IFNA(1/0, 1)"), CellValue::Error(CellError::DivisionByZero));
+        let result = eval_with_cells("IFNA(MATCH(99, A1:A3, 0), \"none\")", |row, col| {
+            if col == 0 && row < 3 {
+                CellValue::Number(row as f64)
+            } else {
+                CellValue::Empty
+            }
+        });
+        assert_eq!(result, CellValue::Text("none".to_string()));
+    }
+
+    #[test]
+    fn test_ifs() {
+        assert_eq!(eval("IFS(1 > 2, \"a\", 2 > 1, \"b\")"), CellValue::Text("b".to_string()));
+        assert_eq!(eval("IFS(FALSE, 1)"), CellValue::Error(CellError::NotAvailable));
+        // Later branches are never evaluated once a condition matches
+        assert_eq!(eval("IFS(TRUE, 1, 1/0, 2)"), CellValue::Number(1.0));
+        assert_eq!(eval("IFS(TRUE)"), CellValue::Error(CellError::InvalidValue));
+    }
+
+    #[test]
+    fn test_switch() {
+        assert_eq!(eval("SWITCH(2, 1, \"one\", 2, \"two\")"), CellValue::Text("two".to_string()));
+        assert_eq!(eval("SWITCH(3, 1, \"one\", 2, \"two\", \"other\")"), CellValue::Text("other".to_string()));
+        assert_eq!(eval("SWITCH(3, 1, \"one\")"), CellValue::Error(CellError::NotAvailable));
+        assert_eq!(eval("SWITCH(\"B\", \"a\", 1, \"b\", 2)"), CellValue::Number(2.0));
+    }
+
+    #[test]
+    fn test_choose() {
+        assert_eq!(eval("CHOOSE(2, \"a\", \"b\", \"c\")"), CellValue::Text("b".to_string()));
+        assert_eq!(eval("CHOOSE(1, 10, 1/0)"), CellValue::Number(10.0));
+        assert_eq!(eval("CHOOSE(4, 1, 2, 3)"), CellValue::Error(CellError::InvalidValue));
+        assert_eq!(eval("CHOOSE(0, 1, 2, 3)"), CellValue::Error(CellError::InvalidValue));
+    }
+
+    #[test]
+    fn test_information_functions() {
+        assert_eq!(eval("ISERROR(1/0)"), CellValue::Boolean(true));
+        assert_eq!(eval("ISERR(NA())"), CellValue::Boolean(false));
+        assert_eq!(eval("ISNA(NA())"), CellValue::Boolean(true));
+        assert_eq!(eval("ERROR.TYPE(1/0)"), CellValue::Number(2.0));
+        assert_eq!(eval("ISBLANK(A1)"), CellValue::Boolean(true));
+        assert_eq!(eval("ISNUMBER(\"1\")"), CellValue::Boolean(false));
+        assert_eq!(eval("TYPE(\"x\")"), CellValue::Number(2.0));
+        assert_eq!(eval("N(TRUE)"), CellValue::Number(1.0));
+        assert_eq!(eval("T(5)"), CellValue::Text(String::new()));
+        assert_eq!(eval("ISREF(A1)"), CellValue::Boolean(true));
+        assert_eq!(eval("ISREF(A1:B2)"), CellValue::Boolean(true));
+        assert_eq!(eval("ISREF(1)"), CellValue::Boolean(false));
+    }
+
+    #[test]
+    fn test_isformula_uses_cell_content() {
+        use crate::parser_nom::NomParser;
+        let ast = NomParser::new().parse("ISFORMULA(A1)").unwrap();
+        let values = |_sheet: Option<&str>, _row: u32, _col: u32| CellValue::Number(2.0);
+        let contents = |_sheet: Option<&str>, row: u32, _col: u32| match row {
+            0 => Some(CellContent::Formula {
+                expression: "=1+1".to_string(),
+                cached_value: CellValue::Number(2.0),
+            }),
+            _ => Some(CellContent::Value {
+                value: CellValue::Number(2.0),
+                original_input: None,
+            }),
+        };
+
+        let evaluator = CrossSheetEvaluator::new(values).with_cell_content(contents);
+        assert_eq!(evaluator.evaluate(&ast), CellValue::Boolean(true));
+
+        let ast = NomParser::new().parse("ISFORMULA(A2)").unwrap();
+        assert_eq!(evaluator.evaluate(&ast), CellValue::Boolean(false));
+
+        // Without a content lookup nothing is known to be a formula
+        let ast = NomParser::new().parse("ISFORMULA(A1)").unwrap();
+        assert_eq!(CrossSheetEvaluator::new(values).evaluate(&ast), CellValue::Boolean(false));
+    }
+
+    #[test]
+    fn test_isref_missing_sheet() {
+        let result = eval_cross_sheet("ISREF(Missing!A1)", |sheet, _row, _col| {
+            if sheet == Some("Missing") {
+                CellValue::Error(CellError::InvalidReference)
+            } else {
+                CellValue::Empty
+            }
+        });
+        assert_eq!(result, CellValue::Boolean(false));
+
+        // A cell holding #REF! is still a reference
+        use crate::parser_nom::NomParser;
+        let values = |_: Option<&str>, _, _| CellValue::Error(CellError::InvalidReference);
+        let contents = |sheet: Option<&str>, _, _| {
+            (sheet == Some("Other")).then_some(CellContent::Value {
+                value: CellValue::Error(CellError::InvalidReference),
+                original_input: None,
+            })
+        };
+        let evaluator = CrossSheetEvaluator::with_sheet(values, "Sheet1").with_cell_content(contents);
+        let isref = |formula| evaluator.evaluate(&NomParser::new().parse(formula).unwrap());
+        assert_eq!(isref("ISREF(Other!A1)"), CellValue::Boolean(true));
+        assert_eq!(isref("ISREF(A1)"), CellValue::Boolean(true));
+        assert_eq!(isref("ISREF((Other!A1,Missing!B2))"), CellValue::Boolean(false));
+    }
+
+    #[test]
+    fn test_workday_with_holiday_range() {
+        // A1:A2 hold holidays (2024-06-17 and a blank cell)
+        let result = eval_with_cells("WORKDAY.INTL(DATE(2024,6,14), 2, 1, A1:A2)", |row, col| {
+            match (row, col) {
+                (0, 0) => CellValue::Number(45460.0),
+                _ => CellValue::Empty,
+            }
+        });
+        assert_eq!(result, CellValue::Number(45462.0)); // 2024-06-19
+    }
+
+    #[test]
+    fn test_date_context() {
+        use crate::parser_nom::NomParser;
+        use rusheet_core::DateSystem;
+
+        let parser = NomParser::new();
+        let ast = parser.parse("YEAR(0) + TODAY() * 0").unwrap();
+        let mac = DateContext::new(DateSystem::Excel1904).with_fixed_now(0.0);
+
+        let evaluator = Evaluator::new(|_row, _col| CellValue::Empty).with_date_context(mac);
+        assert_eq!(evaluator.evaluate(&ast), CellValue::Number(1904.0));
+
+        let evaluator = CrossSheetEvaluator::with_sheet(|_, _, _| CellValue::Empty, "Sheet1")
+            .with_date_context(mac);
+        assert_eq!(evaluator.evaluate(&ast), CellValue::Number(1904.0));
+
+        // The default 1900 system has no serial 0
+        let ast = parser.parse("YEAR(0)").unwrap();
+        let evaluator = Evaluator::new(|_row, _col| CellValue::Empty);
+        assert_eq!(evaluator.evaluate(&ast), CellValue::Error(CellError::NumError));
+    }
+
+    #[test]
+    fn test_fifteen_digit_comparison() {
+        assert_eq!(eval("0.1+0.2=0.3"), CellValue::Boolean(true));
+        assert_eq!(eval("0.1+0.2<>0.3"), CellValue::Boolean(false));
+        assert_eq!(eval("0.3>0.1+0.2"), CellValue::Boolean(false));
+        assert_eq!(eval("1.0000000000001>1"), CellValue::Boolean(true));
+    }
+
+    #[test]
+    fn test_function_names_resembling_tokens() {
+        use crate::parser_nom::NomParser;
+
+        // TRUNC starts with TRUE; LOG10 and ATAN2 look like cell references
+        for (formula, expected) in [
+            ("TRUNC(8.9)", 8.0),
+            ("LOG10(100)", 2.0),
+            ("ATAN2(1,0)", 0.0),
+            ("TRUE()+1", 2.0),
+        ] {
+            let ast = NomParser::new().parse(formula).unwrap();
+            let evaluator = Evaluator::new(|_row, _col| CellValue::Empty);
+            assert_eq!(evaluator.evaluate(&ast), CellValue::Number(expected), "{}", formula);
+            assert_eq!(eval(formula), CellValue::Number(expected), "{}", formula);
+        }
+    }
+
+    #[test]
+    fn test_sumproduct_ranges() {
+        // A1:A3 = 1,2,3 and B1:B3 = 4,5,6
+        let result = eval_with_cells("SUMPRODUCT(A1:A3, B1:B3)", |row, col| {
+            CellValue::Number((row + 1 + col * 3) as f64)
+        });
+        assert_eq!(result, CellValue::Number(32.0));
+
+        let result = eval_with_cells("SUMPRODUCT(A1:A3, B1:B2)", |_, _| CellValue::Number(1.0));
+        assert_eq!(result, CellValue::Error(CellError::InvalidValue));
+    }
+
+    /// A1:A3 = 1,2,3 and B1:B3 = 4,5,6
+    fn grid(row: u32, col: u32) -> CellValue {
+        CellValue::Number((row + 1 + col * 3) as f64)
+    }
+
+    fn eval_at(input: &str, row: u32, col: u32, implicit_intersection: bool) -> CellValue {
+        let ast = crate::parser_nom::NomParser::new().parse(input).unwrap();
+        Evaluator::new(grid)
+            .with_current_cell(row, col)
+            .with_implicit_intersection(implicit_intersection)
+            .evaluate(&ast)
+    }
+
+    #[test]
+    fn test_array_arithmetic() {
+        assert_eq!(eval_with_cells("SUM(A1:A3*B1:B3)", grid), CellValue::Number(32.0));
+        assert_eq!(eval_with_cells("SUM(-A1:A3)", grid), CellValue::Number(-6.0));
+        assert_eq!(eval_with_cells("SUM((A1:A3+1)*2)", grid), CellValue::Number(18.0));
+        assert_eq!(
+            eval_with_cells("SUMPRODUCT((A1:A3>1)*B1:B3)", grid),
+            CellValue::Number(11.0)
+        );
+
+        // Column plus row broadcasts to a 3x2 table: A1:A3 + {1, 4}
+        assert_eq!(eval_with_cells("SUM(A1:A3+A1:B1)", grid), CellValue::Number(27.0));
+
+        // Mismatched sizes fill the gap with #N/A
+        assert_eq!(
+            eval_with_cells("SUM(A1:A3+B1:B2)", grid),
+            CellValue::Error(CellError::NotAvailable)
+        );
+    }
+
+    #[test]
+    fn test_array_in_scalar_context() {
+        // Without implicit intersection a multi-cell result can't fit in one cell
+        assert_eq!(eval_at("A1:A3*2", 1, 3, false), CellValue::Error(CellError::InvalidValue));
+        assert_eq!(eval_at("A2:A2*2", 1, 3, false), CellValue::Number(4.0));
+
+        // Legacy mode picks the value in the formula's row
+        assert_eq!(eval_at("A1:A3*2", 1, 3, true), CellValue::Number(4.0));
+        assert_eq!(eval_at("A1:A3", 2, 3, true), CellValue::Number(3.0));
+        assert_eq!(eval_at("A1:A3", 5, 3, true), CellValue::Error(CellError::InvalidValue));
+    }
+
+    #[test]
+    fn test_implicit_intersection_operator() {
+        // Same row for a column, same column for a row
+        assert_eq!(eval_at("@A1:A3", 2, 5, false), CellValue::Number(3.0));
+        assert_eq!(eval_at("@A1:B1", 7, 1, false), CellValue::Number(4.0));
+        assert_eq!(eval_at("@A1:B3", 1, 1, false), CellValue::Number(5.0));
+        assert_eq!(eval_at("@A1:A3+10", 0, 5, false), CellValue::Number(11.0));
+
+        // No cell in line with the formula
+        assert_eq!(eval_at("@A1:A3", 4, 5, false), CellValue::Error(CellError::InvalidValue));
+        assert_eq!(eval_at("@A1:B3", 1, 4, false), CellValue::Error(CellError::InvalidValue));
+
+        // Single values pass through
+        assert_eq!(eval_at("@B2", 9, 9, false), CellValue::Number(5.0));
+    }
+
+    fn eval_nom(input: &str) -> CellValue {
+        let ast = crate::parser_nom::NomParser::new().parse(input).unwrap();
+        Evaluator::new(|row, col| CellValue::Number((row * 10 + col) as f64)).evaluate(&ast)
+    }
+
+    #[test]
+    fn test_reference_union() {
+        // A1:A2 = 0,10 and C1:C2 = 2,12
+        assert_eq!(eval_nom("SUM((A1:A2,C1:C2))"), CellValue::Number(24.0));
+        assert_eq!(eval_nom("COUNT((A1:A2,C1:C2,B1))"), CellValue::Number(5.0));
+        assert_eq!(eval_nom("AREAS((A1:A2,C1:C2,B1))"), CellValue::Number(3.0));
+        assert_eq!(eval_nom("ISREF((A1,B1))"), CellValue::Boolean(true));
+
+        // A multi-area reference has no single value
+        assert_eq!(eval_nom("(A1,B1)"), CellValue::Error(CellError::InvalidValue));
+    }
+
+    #[test]
+    fn test_reference_intersection() {
+        // A1:C3 B2:D4 is B2:C3 = 11 + 12 + 21 + 22
+        assert_eq!(eval_nom("SUM(A1:C3 B2:D4)"), CellValue::Number(66.0));
+        assert_eq!(eval_nom("A1:C3 B2"), CellValue::Number(11.0));
+        assert_eq!(eval_nom("B1:B5 A3:C3 * 2"), CellValue::Number(42.0));
+        assert_eq!(eval_nom("AREAS(A1:C3 B2:D4)"), CellValue::Number(1.0));
+
+        // Intersecting a union keeps each overlap
+        assert_eq!(eval_nom("SUM((A1:A3,C1:C3) A2:C2)"), CellValue::Number(22.0));
+
+        // Disjoint ranges have an empty intersection
+        assert_eq!(eval_nom("A1:A3 C1:C3"), CellValue::Error(CellError::NullError));
+        assert_eq!(eval_nom("SUM(A1:A3 C1:C3)"), CellValue::Error(CellError::NullError));
+        assert_eq!(eval_nom("ISREF(A1:A3 C1:C3)"), CellValue::Boolean(false));
+    }
+
+    #[test]
+    fn test_array_constants() {
+        // A1:A3 = 0,10,20
+        assert_eq!(eval_nom("SUMPRODUCT(A1:A3,{1;2;3})"), CellValue::Number(80.0));
+        assert_eq!(eval_nom("SUM({1,2;3,4})"), CellValue::Number(10.0));
+        assert_eq!(eval_nom("SUM({1,2,3}*2)"), CellValue::Number(12.0));
+        assert_eq!(eval_nom("VLOOKUP(\"b\",{\"a\",1;\"b\",2},2,0)"), CellValue::Number(2.0));
+        assert_eq!(eval_nom("HLOOKUP(\"b\",{\"a\",\"b\";1,2},2,0)"), CellValue::Number(2.0));
+        assert_eq!(eval_nom("MATCH(\"mid\",{\"lo\",\"mid\",\"hi\"},0)"), CellValue::Number(2.0));
+        assert_eq!(eval_nom("COUNTIF({1,#N/A,3},\">1\")"), CellValue::Number(1.0));
+
+        // Only a single-element array fits in one cell
+        assert_eq!(eval_nom("{5}"), CellValue::Number(5.0));
+        assert_eq!(eval_nom("{1,2}"), CellValue::Error(CellError::InvalidValue));
+
+        // Ragged rows built in code are #VALUE! rather than a panic
+        let ragged = Expr::Array(vec![
+            vec![Expr::number(1.0), Expr::number(2.0)],
+            vec![Expr::number(3.0)],
+        ]);
+        let evaluator = Evaluator::new(|_, _| CellValue::Empty);
+        assert_eq!(evaluator.evaluate(&ragged), CellValue::Error(CellError::InvalidValue));
+        assert_eq!(
+            evaluator.evaluate(&Expr::function("SUM", vec![ragged])),
+            CellValue::Error(CellError::InvalidValue)
+        );
+    }
+
+    fn eval_limited(input: &str, limits: EvalLimits) -> CellValue {
+        let ast = crate::parser_nom::NomParser::new().parse(input).unwrap();
+        Evaluator::new(|_, _| CellValue::Text("ab".to_string()))
+            .with_limits(limits)
+            .evaluate(&ast)
+    }
+
+    #[test]
+    fn test_step_budget() {
+        let limits = EvalLimits {
+            max_steps: 100,
+            ..Default::default()
+        };
+        assert_eq!(eval_limited("SUM(A1:A50)", limits), CellValue::Number(0.0));
+        assert_eq!(
+            eval_limited("SUM(A1:A200)", limits),
+            CellValue::Error(CellError::CalcError)
+        );
+
+        // Catching the error doesn't buy more budget
+        assert_eq!(
+            eval_limited("IFERROR(SUM(A1:A200),0)", limits),
+            CellValue::Error(CellError::CalcError)
+        );
+    }
+
+    #[test]
+    fn test_string_length_limit() {
+        let limits = EvalLimits {
+            max_string_length: 6,
+            ..Default::default()
+        };
+        assert_eq!(eval_limited("A1&A2&A3", limits), CellValue::Text("ababab".to_string()));
+        assert_eq!(
+            eval_limited("A1&A2&A3&A4", limits),
+            CellValue::Error(CellError::InvalidValue)
+        );
+        assert_eq!(
+            eval_limited("CONCAT(A1:A4)", limits),
+            CellValue::Error(CellError::InvalidValue)
+        );
+    }
+
+    #[test]
+    fn test_array_size_limit() {
+        let limits = EvalLimits {
+            max_array_size: 100,
+            ..Default::default()
+        };
+        assert_eq!(eval_limited("SUM(A1:J10)", limits), CellValue::Number(0.0));
+        assert_eq!(eval_limited("SUM(A1:K10)", limits), CellValue::Error(CellError::NumError));
+        assert_eq!(eval_limited("SUM(A1:A1048576)", limits), CellValue::Error(CellError::NumError));
+
+        // A row against a column expands to rows x columns
+        assert_eq!(
+            eval_limited("SUM(A1:A20&A1:T1)", limits),
+            CellValue::Error(CellError::NumError)
+        );
+    }
+
+    #[test]
+    fn test_depth_limit_on_built_trees() {
+        // Trees built in code skip the parser's check
+        let mut expr = Expr::number(1.0);
+        for _ in 0..100 {
+            expr = Expr::binary(Expr::number(1.0), BinaryOp::Add, expr);
+        }
+        let evaluator = Evaluator::new(|_, _| CellValue::Empty);
+        assert_eq!(evaluator.evaluate(&expr), CellValue::Number(101.0));
+
+        let evaluator = evaluator.with_limits(EvalLimits {
+            max_depth: 50,
+            ..Default::default()
+        });
+        assert_eq!(evaluator.evaluate(&expr), CellValue::Error(CellError::CalcError));
+    }
 }
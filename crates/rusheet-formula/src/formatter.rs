@@ -0,0 +1,567 @@
+//! Formula pretty-printer
+//!
+//! Unlike `Display for Expr`, which writes the tree back compactly and as
+//! stored, the formatter derives parentheses from operator precedence and
+//! can add spacing and break long function calls across lines.
+
+use crate::ast::{quote_sheet_name, Expr, UnaryOp};
+use crate::parser_nom::NomParser;
+
+/// Precedence of prefix `-`, `+` and `@`, which bind tighter than `^`
+const PREFIX_PRECEDENCE: u8 = 6;
+/// Precedence of postfix `%`
+const PERCENT_PRECEDENCE: u8 = 7;
+/// Precedence of the space (intersection) operator
+const INTERSECTION_PRECEDENCE: u8 = 8;
+/// Precedence of literals, references and function calls
+const ATOM_PRECEDENCE: u8 = 9;
+
+/// Options for [`format_expr`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+    /// Write function names in uppercase
+    pub uppercase_functions: bool,
+    /// Put spaces around binary operators and after argument separators
+    pub spaces: bool,
+    /// Keep parentheses written in the formula even where precedence makes
+    /// them redundant
+    pub keep_parens: bool,
+    /// Break function calls that would make a line longer than this across
+    /// several lines, one argument per line
+    pub max_width: Option<usize>,
+    /// Spaces per indentation level for broken function calls
+    pub indent: usize,
+}
+
+impl Default for FormatOptions {
+    /// Compact single-line output with minimal parentheses
+    fn default() -> Self {
+        Self {
+            uppercase_functions: true,
+            spaces: false,
+            keep_parens: false,
+            max_width: None,
+            indent: 4,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// Spaced output that breaks calls longer than 60 characters, for reading
+    pub fn pretty() -> Self {
+        Self {
+            spaces: true,
+            max_width: Some(60),
+            ..Self::default()
+        }
+    }
+}
+
+/// Format a parsed expression
+///
+/// Parentheses are added wherever precedence requires them, so any tree
+/// formats to text that parses back to the same [normalized] expression.
+///
+/// [normalized]: Expr::normalized
+pub fn format_expr(expr: &Expr, options: &FormatOptions) -> String {
+    let mut out = String::new();
+    Formatter { options }.write(expr, 0, &mut out);
+    out
+}
+
+/// Reformat a formula string, keeping its leading `=`
+///
+/// # Returns
+/// The formatted formula, or `None` if the formula cannot be parsed
+///
+/// # Examples
+///
+/// ```
+/// use rusheet_formula::{format_formula, FormatOptions};
+///
+/// let formatted = format_formula("=((a1+b1))*sum(1,2)", &FormatOptions::default());
+/// assert_eq!(formatted, Some("=(A1+B1)*SUM(1,2)".to_string()));
+/// ```
+pub fn format_formula(formula: &str, options: &FormatOptions) -> Option<String> {
+    let ast = NomParser::new().parse(formula).ok()?;
+    Some(format!("={}", format_expr(&ast, options)))
+}
+
+struct Formatter<'a> {
+    options: &'a FormatOptions,
+}
+
+impl Formatter<'_> {
+    fn precedence(&self, expr: &Expr) -> u8 {
+        match expr {
+            Expr::Binary { op, .. } => op.precedence(),
+            // Written with a leading `-`, so it reads back as a negation
+            Expr::Number(n) if *n < 0.0 => PREFIX_PRECEDENCE,
+            Expr::Unary {
+                op: UnaryOp::Percent,
+                ..
+            } => PERCENT_PRECEDENCE,
+            Expr::Unary { .. } => PREFIX_PRECEDENCE,
+            Expr::Intersection { .. } => INTERSECTION_PRECEDENCE,
+            Expr::Grouped(inner) if !self.options.keep_parens => self.precedence(inner),
+            _ => ATOM_PRECEDENCE,
+        }
+    }
+
+    /// Write an operand, parenthesized if its precedence is below `min`
+    fn write_operand(&self, expr: &Expr, min: u8, depth: usize, out: &mut String) {
+        if self.precedence(expr) < min {
+            out.push('(');
+            self.write(expr, depth, out);
+            out.push(')');
+        } else {
+            self.write(expr, depth, out);
+        }
+    }
+
+    fn write(&self, expr: &Expr, depth: usize, out: &mut String) {
+        match expr {
+            Expr::Number(n) => out.push_str(&format_number(*n)),
+            Expr::Grouped(inner) => {
+                // A union writes its own parentheses
+                if self.options.keep_parens && !matches!(inner.as_ref(), Expr::Union(_)) {
+                    out.push('(');
+                    self.write(inner, depth, out);
+                    out.push(')');
+                } else {
+                    self.write(inner, depth, out);
+                }
+            }
+            Expr::SheetRef {
+                sheet_name,
+                reference,
+            } => {
+                out.push_str(&quote_sheet_name(sheet_name));
+                out.push('!');
+                self.write(reference, depth, out);
+            }
+            Expr::Binary { op, .. } => {
+                // Operators of equal precedence group away from the associative side
+                let prec = op.precedence();
+                let (left_min, right_min) = if op.is_right_associative() {
+                    (prec + 1, prec)
+                } else {
+                    (prec, prec + 1)
+                };
+                // Written in a loop; only a chain of `^` nested on the left
+                // needs parentheses within it
+                let (first, links) = expr.chain();
+                let inner_parens = op.is_right_associative();
+                if inner_parens {
+                    out.push_str(&"(".repeat(links.len() - 1));
+                }
+                self.write_operand(first, left_min, depth, out);
+                for (i, (op, right)) in links.into_iter().enumerate() {
+                    if inner_parens && i > 0 {
+                        out.push(')');
+                    }
+                    if self.options.spaces {
+                        out.push_str(&format!(" {} ", op));
+                    } else {
+                        out.push_str(&op.to_string());
+                    }
+                    self.write_operand(right, right_min, depth, out);
+                }
+            }
+            Expr::Unary {
+                op: UnaryOp::Percent,
+                operand,
+            } => {
+                self.write_operand(operand, PERCENT_PRECEDENCE, depth, out);
+                out.push('%');
+            }
+            Expr::Unary { op, operand } => {
+                out.push(match op {
+                    UnaryOp::Neg => '-',
+                    UnaryOp::Pos => '+',
+                    _ => '@',
+                });
+                self.write_operand(operand, PREFIX_PRECEDENCE, depth, out);
+            }
+            Expr::FunctionCall { name, args } => self.write_call(name, args, depth, out),
+            Expr::Array(rows) => {
+                out.push('{');
+                for (i, row) in rows.iter().enumerate() {
+                    if i > 0 {
+                        out.push(';');
+                    }
+                    for (j, item) in row.iter().enumerate() {
+                        if j > 0 {
+                            out.push(',');
+                        }
+                        self.write(item, depth, out);
+                    }
+                }
+                out.push('}');
+            }
+            Expr::Union(areas) => {
+                out.push('(');
+                for (i, area) in areas.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(self.separator());
+                    }
+                    self.write(area, depth, out);
+                }
+                out.push(')');
+            }
+            Expr::Intersection { left, right } => {
+                // Only a single reference can follow the space
+                self.write_operand(left, INTERSECTION_PRECEDENCE, depth, out);
+                out.push(' ');
+                self.write_operand(right, ATOM_PRECEDENCE, depth, out);
+            }
+            // Literals, cell references, ranges and structured references are written as stored
+            other => out.push_str(&other.to_string()),
+        }
+    }
+
+    fn write_call(&self, name: &str, args: &[Expr], depth: usize, out: &mut String) {
+        let name = if self.options.uppercase_functions {
+            name.to_uppercase()
+        } else {
+            name.to_string()
+        };
+
+        let mut flat = String::new();
+        let single_line = Formatter {
+            options: &FormatOptions {
+                max_width: None,
+                ..self.options.clone()
+            },
+        };
+        single_line.write_args(&name, args, &mut flat);
+
+        let column = out.len() - out.rfind('\n').map_or(0, |i| i + 1);
+        let too_long = self
+            .options
+            .max_width
+            .is_some_and(|width| column + flat.len() > width);
+        if !too_long || args.is_empty() {
+            out.push_str(&flat);
+            return;
+        }
+
+        let indent = |depth: usize| " ".repeat(depth * self.options.indent);
+        out.push_str(&name);
+        out.push_str("(\n");
+        for (i, arg) in args.iter().enumerate() {
+            out.push_str(&indent(depth + 1));
+            self.write(arg, depth + 1, out);
+            if i + 1 < args.len() {
+                out.push(',');
+            }
+            out.push('\n');
+        }
+        out.push_str(&indent(depth));
+        out.push(')');
+    }
+
+    fn write_args(&self, name: &str, args: &[Expr], out: &mut String) {
+        out.push_str(name);
+        out.push('(');
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                out.push_str(self.separator());
+            }
+            self.write(arg, 0, out);
+        }
+        out.push(')');
+    }
+
+    fn separator(&self) -> &'static str {
+        if self.options.spaces {
+            ", "
+        } else {
+            ","
+        }
+    }
+}
+
+/// Write a number so that it parses back to the same value
+fn format_number(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        format!("{}", n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::BinaryOp;
+    use rusheet_core::CellError;
+
+    fn reformat(formula: &str, options: &FormatOptions) -> String {
+        format_formula(formula, options).unwrap()
+    }
+
+    #[test]
+    fn test_minimal_parentheses() {
+        let options = FormatOptions::default();
+        assert_eq!(reformat("=((1+2))*3", &options), "=(1+2)*3");
+        assert_eq!(reformat("=1+(2*3)", &options), "=1+2*3");
+        assert_eq!(reformat("=1-(2-3)", &options), "=1-(2-3)");
+        assert_eq!(reformat("=(1-2)-3", &options), "=1-2-3");
+        assert_eq!(reformat("=(2^3)^2", &options), "=(2^3)^2");
+        assert_eq!(reformat("=2^(3^2)", &options), "=2^3^2");
+        assert_eq!(reformat("=-(2^2)", &options), "=-(2^2)");
+        assert_eq!(reformat("=(-2)^2", &options), "=-2^2");
+        assert_eq!(reformat("=(-A1)%", &options), "=(-A1)%");
+        assert_eq!(
+            reformat("=SUM((A1:A5,C1:C5))", &options),
+            "=SUM((A1:A5,C1:C5))"
+        );
+        assert_eq!(reformat("=A1:C5 (B2:D4 C3)", &options), "=A1:C5 (B2:D4 C3)");
+    }
+
+    #[test]
+    fn test_constructed_trees_get_parentheses() {
+        // Display writes this as 1+2*3, which means something else
+        let expr = Expr::binary(
+            Expr::binary(Expr::number(1.0), BinaryOp::Add, Expr::number(2.0)),
+            BinaryOp::Mul,
+            Expr::number(3.0),
+        );
+        assert_eq!(format_expr(&expr, &FormatOptions::default()), "(1+2)*3");
+
+        // -1% would read back as -(1%)
+        let expr = Expr::unary(UnaryOp::Percent, Expr::number(-1.0));
+        assert_eq!(format_expr(&expr, &FormatOptions::default()), "(-1)%");
+    }
+
+    #[test]
+    fn test_keep_parens_and_casing() {
+        let options = FormatOptions {
+            keep_parens: true,
+            uppercase_functions: false,
+            ..Default::default()
+        };
+        let expr = Expr::function(
+            "Sum",
+            vec![Expr::Grouped(Box::new(Expr::binary(
+                Expr::cell_ref(0, 0),
+                BinaryOp::Mul,
+                Expr::number(2.0),
+            )))],
+        );
+        assert_eq!(format_expr(&expr, &options), "Sum((A1*2))");
+        assert_eq!(format_expr(&expr, &FormatOptions::default()), "SUM(A1*2)");
+    }
+
+    #[test]
+    fn test_spacing() {
+        let options = FormatOptions {
+            spaces: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            reformat("=IF(A1>=10,A1*2,-1)&\"x\"", &options),
+            "=IF(A1 >= 10, A1 * 2, -1) & \"x\""
+        );
+    }
+
+    #[test]
+    fn test_breaks_long_nested_ifs() {
+        let options = FormatOptions {
+            max_width: Some(40),
+            indent: 2,
+            ..FormatOptions::pretty()
+        };
+        let formatted = reformat(
+            "=IF(A1>90,\"A\",IF(A1>80,\"B\",IF(A1>70,\"C\",\"F\")))",
+            &options,
+        );
+        assert_eq!(
+            formatted,
+            "=IF(\n  A1 > 90,\n  \"A\",\n  IF(\n    A1 > 80,\n    \"B\",\n    IF(A1 > 70, \"C\", \"F\")\n  )\n)"
+        );
+
+        // Line breaks parse back to the same formula
+        let reparsed = NomParser::new().parse(&formatted).unwrap();
+        assert_eq!(
+            format_expr(&reparsed, &FormatOptions::default()),
+            "IF(A1>90,\"A\",IF(A1>80,\"B\",IF(A1>70,\"C\",\"F\")))"
+        );
+    }
+
+    #[test]
+    fn test_sheet_names_that_need_quotes() {
+        let options = FormatOptions::default();
+        assert_eq!(reformat("='2024'!A1", &options), "='2024'!A1");
+        assert_eq!(reformat("='True'!A1", &options), "='True'!A1");
+        assert_eq!(reformat("='It''s'!A1:B2", &options), "='It''s'!A1:B2");
+        assert_eq!(reformat("=Data_1!A1", &options), "=Data_1!A1");
+    }
+
+    /// Small deterministic generator for random formula trees
+    struct Generator(u64);
+
+    impl Generator {
+        fn next(&mut self, bound: usize) -> usize {
+            // xorshift64
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as usize
+        }
+
+        fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+            &items[self.next(items.len())]
+        }
+
+        fn literal(&mut self) -> Expr {
+            match self.next(4) {
+                0 => {
+                    let n = self.next(1000) as f64 / *self.pick(&[1.0, 4.0, 8.0]);
+                    if self.next(2) == 0 {
+                        Expr::Number(-n)
+                    } else {
+                        Expr::Number(n)
+                    }
+                }
+                1 => Expr::string(*self.pick(&["", "text", "say \"hi\"", "a,b;c"])),
+                2 => Expr::Boolean(self.next(2) == 0),
+                _ => Expr::Error(
+                    self.pick(&[
+                        CellError::DivisionByZero,
+                        CellError::InvalidValue,
+                        CellError::InvalidReference,
+                        CellError::InvalidName,
+                        CellError::NotAvailable,
+                        CellError::NullError,
+                        CellError::NumError,
+                    ])
+                    .clone(),
+                ),
+            }
+        }
+
+        fn cell_ref(&mut self) -> Expr {
+            Expr::CellRef {
+                col: self.next(800) as u32,
+                row: self.next(5000) as u32,
+                abs_col: self.next(2) == 0,
+                abs_row: self.next(2) == 0,
+            }
+        }
+
+        /// A reference that can stand on its own after the space operator
+        fn simple_reference(&mut self) -> Expr {
+            let reference = if self.next(2) == 0 {
+                self.cell_ref()
+            } else {
+                Expr::range(self.cell_ref(), self.cell_ref())
+            };
+            if self.next(3) == 0 {
+                Expr::SheetRef {
+                    sheet_name: self
+                        .pick(&["Sheet1", "My Sheet", "It's", "2024", "A1"])
+                        .to_string(),
+                    reference: Box::new(reference),
+                }
+            } else {
+                reference
+            }
+        }
+
+        fn reference(&mut self, depth: usize) -> Expr {
+            match self.next(if depth == 0 { 1 } else { 4 }) {
+                0 | 1 => self.simple_reference(),
+                2 => Expr::Union(
+                    (0..2 + self.next(2))
+                        .map(|_| self.reference(depth - 1))
+                        .collect(),
+                ),
+                _ => Expr::intersection(self.reference(depth - 1), self.reference(depth - 1)),
+            }
+        }
+
+        fn expr(&mut self, depth: usize) -> Expr {
+            if depth == 0 {
+                return match self.next(3) {
+                    0 => self.simple_reference(),
+                    _ => self.literal(),
+                };
+            }
+            match self.next(8) {
+                0 => self.literal(),
+                1 => self.reference(depth),
+                2 | 3 => {
+                    let op = *self.pick(&[
+                        BinaryOp::Add,
+                        BinaryOp::Sub,
+                        BinaryOp::Mul,
+                        BinaryOp::Div,
+                        BinaryOp::Pow,
+                        BinaryOp::Concat,
+                        BinaryOp::Eq,
+                        BinaryOp::Ne,
+                        BinaryOp::Lt,
+                        BinaryOp::Gt,
+                        BinaryOp::Le,
+                        BinaryOp::Ge,
+                    ]);
+                    Expr::binary(self.expr(depth - 1), op, self.expr(depth - 1))
+                }
+                4 => {
+                    let op = *self.pick(&[
+                        UnaryOp::Neg,
+                        UnaryOp::Pos,
+                        UnaryOp::Percent,
+                        UnaryOp::ImplicitIntersection,
+                    ]);
+                    Expr::unary(op, self.expr(depth - 1))
+                }
+                5 => {
+                    let name = *self.pick(&["SUM", "IF", "LOG10", "ERROR.TYPE", "NOW"]);
+                    let args = (0..self.next(4)).map(|_| self.expr(depth - 1)).collect();
+                    Expr::function(name, args)
+                }
+                6 => Expr::Grouped(Box::new(self.expr(depth - 1))),
+                _ => {
+                    let (rows, cols) = (1 + self.next(3), 1 + self.next(3));
+                    Expr::Array(
+                        (0..rows)
+                            .map(|_| (0..cols).map(|_| self.literal()).collect())
+                            .collect(),
+                    )
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_generated_trees() {
+        let option_sets = [
+            FormatOptions::default(),
+            FormatOptions::pretty(),
+            FormatOptions {
+                max_width: Some(10),
+                ..FormatOptions::pretty()
+            },
+            FormatOptions {
+                keep_parens: true,
+                ..Default::default()
+            },
+        ];
+        let parser = NomParser::new();
+        let mut generator = Generator(0x2545_f491_4f6c_dd1d);
+
+        for _ in 0..2000 {
+            let expr = generator.expr(4);
+            for options in &option_sets {
+                let formatted = format_expr(&expr, options);
+                let parsed = parser
+                    .parse(&formatted)
+                    .unwrap_or_else(|e| panic!("{formatted:?} failed to parse: {e}"));
+                assert_eq!(parsed.normalized(), expr.normalized(), "{formatted:?}");
+            }
+        }
+    }
+}
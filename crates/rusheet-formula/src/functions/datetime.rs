@@ -1,158 +1,86 @@
 use rusheet_core::cell::CellValue;
+use rusheet_core::date::{
+    self, date_to_serial, days_in_month, is_leap_year, serial_to_date, DateSystem,
+};
+use rusheet_core::date_text::{parse_date_text, parse_time_text, split_date_time, DateOrder};
 use rusheet_core::error::CellError;
-
-// Excel date constants
-const EXCEL_EPOCH_YEAR: i32 = 1900;
-const EXCEL_LEAP_YEAR_BUG_DAY: i64 = 60; // Feb 29, 1900 (phantom day)
-
-/// Convert a date to Excel serial number (days since January 1, 1900)
-/// Excel has a bug where it treats 1900 as a leap year, so we account for that
-fn date_to_serial(year: i32, month: u32, day: u32) -> Result<f64, CellError> {
-    // Validate inputs
-    if year < 1900 || year > 9999 {
-        return Err(CellError::NumError);
-    }
-    if month < 1 || month > 12 {
-        return Err(CellError::NumError);
-    }
-    if day < 1 || day > days_in_month(year, month) {
-        return Err(CellError::NumError);
-    }
-
-    // Calculate days from epoch (1900/1/1 = serial 1)
-    let mut days = 0i64;
-
-    // Add days for complete years
-    for y in EXCEL_EPOCH_YEAR..year {
-        days += if is_leap_year(y) { 366 } else { 365 };
-    }
-
-    // Add days for complete months in the current year
-    for m in 1..month {
-        days += days_in_month(year, m) as i64;
-    }
-
-    // Add the day
-    days += day as i64;
-
-    // Excel's leap year bug: Excel treats 1900 as a leap year (it's not)
-    // For dates on or after March 1, 1900, we add 1 to match Excel's serial numbers
-    if year > 1900 || (year == 1900 && month > 2) {
-        days += 1;
-    }
-
-    Ok(days as f64)
+use std::collections::HashSet;
+
+/// Calendar settings used when evaluating date and time functions
+///
+/// Carries the workbook's date system and date order, the user's UTC offset
+/// for TODAY and NOW, and an optional fixed clock so results can be
+/// reproduced in tests.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DateContext {
+    /// Epoch that date serials count from
+    pub date_system: DateSystem,
+    /// Order of day, month and year when reading date text
+    pub date_order: DateOrder,
+    /// Offset from UTC in minutes (e.g. 480 for Asia/Taipei)
+    pub utc_offset_minutes: i32,
+    /// Current time in milliseconds since the Unix epoch, or `None` for the system clock
+    pub now_unix_ms: Option<f64>,
 }
 
-/// Convert Excel serial number to (year, month, day)
-fn serial_to_date(serial: f64) -> Result<(i32, u32, u32), CellError> {
-    if serial < 1.0 || serial >= 2958466.0 {
-        // Valid range: 1900-01-01 to 9999-12-31
-        return Err(CellError::NumError);
-    }
-
-    let mut days = serial.floor() as i64;
-
-    // Account for Excel's leap year bug (serial 60 is the phantom Feb 29, 1900)
-    // For serials > 60, we need to subtract 1 to get the real date
-    if days > EXCEL_LEAP_YEAR_BUG_DAY {
-        days -= 1;
-    } else if days == EXCEL_LEAP_YEAR_BUG_DAY {
-        // This is the phantom date Feb 29, 1900
-        return Ok((1900, 2, 29));
-    }
-
-    // Find the year
-    let mut year = EXCEL_EPOCH_YEAR;
-    let mut remaining_days = days;
-
-    while remaining_days > 0 {
-        let days_in_year = if is_leap_year(year) { 366 } else { 365 };
-        if remaining_days <= days_in_year {
-            break;
+impl DateContext {
+    /// Create a context for the given date system using the system clock in UTC
+    pub fn new(date_system: DateSystem) -> Self {
+        Self {
+            date_system,
+            ..Self::default()
         }
-        remaining_days -= days_in_year;
-        year += 1;
     }
 
-    // Find the month
-    let mut month = 1u32;
-    while month <= 12 {
-        let dim = days_in_month(year, month) as i64;
-        if remaining_days <= dim {
-            break;
-        }
-        remaining_days -= dim;
-        month += 1;
+    /// Set the order of day, month and year used by DATEVALUE and TIMEVALUE
+    pub fn with_date_order(mut self, order: DateOrder) -> Self {
+        self.date_order = order;
+        self
     }
 
-    let day = remaining_days as u32;
-
-    if day < 1 || day > days_in_month(year, month) {
-        return Err(CellError::NumError);
+    /// Set the offset from UTC used for TODAY and NOW
+    pub fn with_utc_offset(mut self, minutes: i32) -> Self {
+        self.utc_offset_minutes = minutes;
+        self
     }
 
-    Ok((year, month, day))
-}
-
-/// Check if a year is a leap year
-fn is_leap_year(year: i32) -> bool {
-    (year % 4 == 0 && year % 100 != 0) || (year % 400 == 0)
-}
+    /// Pin the clock to a fixed instant (milliseconds since the Unix epoch)
+    pub fn with_fixed_now(mut self, unix_ms: f64) -> Self {
+        self.now_unix_ms = Some(unix_ms);
+        self
+    }
 
-/// Get the number of days in a month
-fn days_in_month(year: i32, month: u32) -> u32 {
-    match month {
-        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
-        4 | 6 | 9 | 11 => 30,
-        2 => {
-            if is_leap_year(year) {
-                29
-            } else {
-                28
-            }
-        }
-        _ => 0,
+    /// Current local date and time as a serial number
+    pub fn now_serial(&self) -> f64 {
+        let unix_ms = self.now_unix_ms.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis() as f64)
+                .unwrap_or(0.0)
+        });
+        let local_ms = unix_ms + self.utc_offset_minutes as f64 * 60_000.0;
+        self.date_system.unix_epoch_serial() + local_ms / 86_400_000.0
     }
 }
 
-/// TODAY - Returns current date as serial number (no time component)
-pub fn today(values: &[CellValue]) -> CellValue {
+/// TODAY - Returns current local date as serial number (no time component)
+pub fn today(values: &[CellValue], ctx: &DateContext) -> CellValue {
     if !values.is_empty() {
         return CellValue::Error(CellError::InvalidValue);
     }
-
-    // Get current date in UTC
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap();
-    let days_since_epoch = now.as_secs() / 86400;
-
-    // Unix epoch is Jan 1, 1970 = Excel serial 25569
-    let excel_serial = 25569.0 + days_since_epoch as f64;
-
-    CellValue::Number(excel_serial)
+    CellValue::Number(ctx.now_serial().floor())
 }
 
-/// NOW - Returns current date and time as serial number
-pub fn now(values: &[CellValue]) -> CellValue {
+/// NOW - Returns current local date and time as serial number
+pub fn now(values: &[CellValue], ctx: &DateContext) -> CellValue {
     if !values.is_empty() {
         return CellValue::Error(CellError::InvalidValue);
     }
-
-    // Get current datetime in UTC
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap();
-
-    // Unix epoch is Jan 1, 1970 = Excel serial 25569
-    let excel_serial = 25569.0 + now.as_secs() as f64 / 86400.0;
-
-    CellValue::Number(excel_serial)
+    CellValue::Number(ctx.now_serial())
 }
 
 /// DATE - Creates a date serial from year, month, day
-pub fn date(values: &[CellValue]) -> CellValue {
+pub fn date(values: &[CellValue], ctx: &DateContext) -> CellValue {
     if values.len() != 3 {
         return CellValue::Error(CellError::InvalidValue);
     }
@@ -179,7 +107,7 @@ pub fn date(values: &[CellValue]) -> CellValue {
         None => return CellValue::Error(CellError::InvalidValue),
     };
 
-    match date_to_serial(year, month, day) {
+    match date_to_serial(year, month, day, ctx.date_system) {
         Ok(serial) => CellValue::Number(serial),
         Err(e) => CellValue::Error(e),
     }
@@ -227,7 +155,7 @@ pub fn time(values: &[CellValue]) -> CellValue {
 }
 
 /// YEAR - Extracts year from date serial
-pub fn year(values: &[CellValue]) -> CellValue {
+pub fn year(values: &[CellValue], ctx: &DateContext) -> CellValue {
     if values.len() != 1 {
         return CellValue::Error(CellError::InvalidValue);
     }
@@ -241,14 +169,14 @@ pub fn year(values: &[CellValue]) -> CellValue {
         None => return CellValue::Error(CellError::InvalidValue),
     };
 
-    match serial_to_date(serial) {
+    match serial_to_date(serial, ctx.date_system) {
         Ok((year, _, _)) => CellValue::Number(year as f64),
         Err(e) => CellValue::Error(e),
     }
 }
 
 /// MONTH - Extracts month from date serial
-pub fn month(values: &[CellValue]) -> CellValue {
+pub fn month(values: &[CellValue], ctx: &DateContext) -> CellValue {
     if values.len() != 1 {
         return CellValue::Error(CellError::InvalidValue);
     }
@@ -262,14 +190,14 @@ pub fn month(values: &[CellValue]) -> CellValue {
         None => return CellValue::Error(CellError::InvalidValue),
     };
 
-    match serial_to_date(serial) {
+    match serial_to_date(serial, ctx.date_system) {
         Ok((_, month, _)) => CellValue::Number(month as f64),
         Err(e) => CellValue::Error(e),
     }
 }
 
 /// DAY - Extracts day from date serial
-pub fn day(values: &[CellValue]) -> CellValue {
+pub fn day(values: &[CellValue], ctx: &DateContext) -> CellValue {
     if values.len() != 1 {
         return CellValue::Error(CellError::InvalidValue);
     }
@@ -283,7 +211,7 @@ pub fn day(values: &[CellValue]) -> CellValue {
         None => return CellValue::Error(CellError::InvalidValue),
     };
 
-    match serial_to_date(serial) {
+    match serial_to_date(serial, ctx.date_system) {
         Ok((_, _, day)) => CellValue::Number(day as f64),
         Err(e) => CellValue::Error(e),
     }
@@ -372,7 +300,7 @@ pub fn second(values: &[CellValue]) -> CellValue {
 
 /// DATEDIF - Calculates difference between dates
 /// unit: "Y" = years, "M" = months, "D" = days
-pub fn datedif(values: &[CellValue]) -> CellValue {
+pub fn datedif(values: &[CellValue], ctx: &DateContext) -> CellValue {
     if values.len() != 3 {
         return CellValue::Error(CellError::InvalidValue);
     }
@@ -403,12 +331,12 @@ pub fn datedif(values: &[CellValue]) -> CellValue {
         return CellValue::Error(CellError::NumError);
     }
 
-    let (start_year, start_month, start_day) = match serial_to_date(start_serial) {
+    let (start_year, start_month, start_day) = match serial_to_date(start_serial, ctx.date_system) {
         Ok(date) => date,
         Err(e) => return CellValue::Error(e),
     };
 
-    let (end_year, end_month, end_day) = match serial_to_date(end_serial) {
+    let (end_year, end_month, end_day) = match serial_to_date(end_serial, ctx.date_system) {
         Ok(date) => date,
         Err(e) => return CellValue::Error(e),
     };
@@ -439,55 +367,469 @@ pub fn datedif(values: &[CellValue]) -> CellValue {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Read a numeric argument, propagating errors
+fn number_arg(value: &CellValue) -> Result<f64, CellError> {
+    match value {
+        CellValue::Error(e) => Err(e.clone()),
+        other => other.as_number().ok_or(CellError::InvalidValue),
+    }
+}
 
-    #[test]
-    fn test_date_to_serial() {
-        // Test January 1, 1900 (day 1)
-        assert_eq!(date_to_serial(1900, 1, 1).unwrap(), 1.0);
+/// Read a date serial argument, truncating any time component
+fn date_arg(value: &CellValue, system: DateSystem) -> Result<i64, CellError> {
+    let serial = number_arg(value)?.floor();
+    serial_to_date(serial, system)?;
+    Ok(serial as i64)
+}
+
+/// Day of the week for a serial, 0 = Monday through 6 = Sunday
+///
+/// Serial 1 (1900-01-01) is a Sunday in Excel's calendar because of the
+/// phantom Feb 29, 1900, so weekdays match Excel for every serial. 1904
+/// serials are shifted onto the 1900 calendar first.
+fn day_of_week(serial: i64, system: DateSystem) -> u32 {
+    let serial = date::convert_serial(serial as f64, system, DateSystem::Excel1900) as i64;
+    (serial + 5).rem_euclid(7) as u32
+}
+
+/// Day of the year (1-based) for a serial
+fn day_of_year(serial: i64, system: DateSystem) -> Result<i64, CellError> {
+    let (year, _, _) = serial_to_date(serial as f64, system)?;
+    Ok(serial - date_to_serial(year, 1, 1, system)? as i64 + 1)
+}
+
+/// Add a number of months to a year/month pair
+fn add_months(year: i32, month: u32, months: i32) -> (i32, u32) {
+    let total = year * 12 + (month as i32 - 1) + months;
+    (total.div_euclid(12), (total.rem_euclid(12) + 1) as u32)
+}
+
+/// Convert a result serial to a value, checking it is a valid date
+fn serial_result(serial: i64, system: DateSystem) -> CellValue {
+    match serial_to_date(serial as f64, system) {
+        Ok(_) => CellValue::Number(serial as f64),
+        Err(e) => CellValue::Error(e),
+    }
+}
+
+/// Weekend days indexed Monday (0) through Sunday (6)
+type WeekendMask = [bool; 7];
+
+const DEFAULT_WEEKEND: WeekendMask = [false, false, false, false, false, true, true];
+
+/// Parse a WORKDAY.INTL/NETWORKDAYS.INTL weekend argument
+///
+/// Accepts the numeric codes 1-7 (two-day weekends starting Sat/Sun, Sun/Mon, ...),
+/// 11-17 (single days, Sunday through Saturday), or a seven character string of
+/// 0s and 1s starting on Monday where 1 marks a weekend day.
+fn parse_weekend(value: &CellValue) -> Result<WeekendMask, CellError> {
+    let mut mask = [false; 7];
+    match value {
+        CellValue::Error(e) => return Err(e.clone()),
+        CellValue::Empty => return Ok(DEFAULT_WEEKEND),
+        CellValue::Text(s) if s.len() == 7 => {
+            for (i, c) in s.chars().enumerate() {
+                mask[i] = match c {
+                    '0' => false,
+                    '1' => true,
+                    _ => return Err(CellError::InvalidValue),
+                };
+            }
+            if mask.iter().all(|&weekend| weekend) {
+                return Err(CellError::InvalidValue);
+            }
+        }
+        other => {
+            let code = other.as_number().ok_or(CellError::InvalidValue)? as i32;
+            match code {
+                // 1 = Saturday/Sunday, 2 = Sunday/Monday, ..., 7 = Friday/Saturday
+                1..=7 => {
+                    let first = (code as usize + 4) % 7;
+                    mask[first] = true;
+                    mask[(first + 1) % 7] = true;
+                }
+                // 11 = Sunday only, 12 = Monday only, ..., 17 = Saturday only
+                11..=17 => mask[(code as usize - 11 + 6) % 7] = true,
+                _ => return Err(CellError::NumError),
+            }
+        }
+    }
+    Ok(mask)
+}
 
-        // Test January 2, 1900 (day 2)
-        assert_eq!(date_to_serial(1900, 1, 2).unwrap(), 2.0);
+/// Collect holiday serials, ignoring blank cells
+fn holiday_set(values: &[CellValue], system: DateSystem) -> Result<HashSet<i64>, CellError> {
+    values
+        .iter()
+        .filter(|v| !v.is_empty())
+        .map(|v| date_arg(v, system))
+        .collect()
+}
 
-        // Test Feb 28, 1900 (day 59, before leap year bug)
-        assert_eq!(date_to_serial(1900, 2, 28).unwrap(), 59.0);
+fn is_workday(
+    serial: i64,
+    weekend: &WeekendMask,
+    holidays: &HashSet<i64>,
+    system: DateSystem,
+) -> bool {
+    !weekend[day_of_week(serial, system) as usize] && !holidays.contains(&serial)
+}
 
-        // Test March 1, 1900 (day 61, after leap year bug adjustment)
-        assert_eq!(date_to_serial(1900, 3, 1).unwrap(), 61.0);
+fn workday_impl(
+    start: &CellValue,
+    days: &CellValue,
+    weekend: &WeekendMask,
+    holidays: &[CellValue],
+    system: DateSystem,
+) -> Result<i64, CellError> {
+    let mut serial = date_arg(start, system)?;
+    let days = number_arg(days)?.trunc() as i64;
+    let holidays = holiday_set(holidays, system)?;
+
+    let step = days.signum();
+    let mut remaining = days.abs();
+    while remaining > 0 {
+        serial += step;
+        serial_to_date(serial as f64, system)?;
+        if is_workday(serial, weekend, &holidays, system) {
+            remaining -= 1;
+        }
+    }
+    Ok(serial)
+}
 
-        // Test a known date: January 1, 2000
-        assert_eq!(date_to_serial(2000, 1, 1).unwrap(), 36526.0);
+fn networkdays_impl(
+    start: &CellValue,
+    end: &CellValue,
+    weekend: &WeekendMask,
+    holidays: &[CellValue],
+    system: DateSystem,
+) -> Result<f64, CellError> {
+    let start = date_arg(start, system)?;
+    let end = date_arg(end, system)?;
+    let holidays = holiday_set(holidays, system)?;
+
+    let count = (start.min(end)..=start.max(end))
+        .filter(|&serial| is_workday(serial, weekend, &holidays, system))
+        .count() as f64;
+    Ok(if start > end { -count } else { count })
+}
 
-        // Test June 15, 2024
-        // Calculated: 45290 days (1900-2023) + 167 days (Jan-Jun 15 in 2024) + 1 (Excel bug) = 45458
-        assert_eq!(date_to_serial(2024, 6, 15).unwrap(), 45458.0);
+/// WORKDAY - Date a number of working days before or after a start date
+/// WORKDAY(start, days, [holidays])
+pub fn workday(values: &[CellValue], ctx: &DateContext) -> CellValue {
+    if values.len() < 2 {
+        return CellValue::Error(CellError::InvalidValue);
     }
+    match workday_impl(&values[0], &values[1], &DEFAULT_WEEKEND, &values[2..], ctx.date_system) {
+        Ok(serial) => serial_result(serial, ctx.date_system),
+        Err(e) => CellValue::Error(e),
+    }
+}
 
-    #[test]
-    fn test_serial_to_date() {
-        // Test day 1
-        assert_eq!(serial_to_date(1.0).unwrap(), (1900, 1, 1));
+/// WORKDAY.INTL - WORKDAY with a custom weekend
+/// WORKDAY.INTL(start, days, [weekend], [holidays])
+pub fn workday_intl(values: &[CellValue], ctx: &DateContext) -> CellValue {
+    if values.len() < 2 {
+        return CellValue::Error(CellError::InvalidValue);
+    }
+    let weekend = match values.get(2).map(parse_weekend).unwrap_or(Ok(DEFAULT_WEEKEND)) {
+        Ok(mask) => mask,
+        Err(e) => return CellValue::Error(e),
+    };
+    let holidays = values.get(3..).unwrap_or(&[]);
+    match workday_impl(&values[0], &values[1], &weekend, holidays, ctx.date_system) {
+        Ok(serial) => serial_result(serial, ctx.date_system),
+        Err(e) => CellValue::Error(e),
+    }
+}
 
-        // Test day 2
-        assert_eq!(serial_to_date(2.0).unwrap(), (1900, 1, 2));
+/// NETWORKDAYS - Number of working days between two dates (inclusive)
+/// NETWORKDAYS(start, end, [holidays])
+pub fn networkdays(values: &[CellValue], ctx: &DateContext) -> CellValue {
+    if values.len() < 2 {
+        return CellValue::Error(CellError::InvalidValue);
+    }
+    match networkdays_impl(&values[0], &values[1], &DEFAULT_WEEKEND, &values[2..], ctx.date_system) {
+        Ok(n) => CellValue::Number(n),
+        Err(e) => CellValue::Error(e),
+    }
+}
 
-        // Test day 59 (Feb 28, 1900)
-        assert_eq!(serial_to_date(59.0).unwrap(), (1900, 2, 28));
+/// NETWORKDAYS.INTL - NETWORKDAYS with a custom weekend
+/// NETWORKDAYS.INTL(start, end, [weekend], [holidays])
+pub fn networkdays_intl(values: &[CellValue], ctx: &DateContext) -> CellValue {
+    if values.len() < 2 {
+        return CellValue::Error(CellError::InvalidValue);
+    }
+    let weekend = match values.get(2).map(parse_weekend).unwrap_or(Ok(DEFAULT_WEEKEND)) {
+        Ok(mask) => mask,
+        Err(e) => return CellValue::Error(e),
+    };
+    let holidays = values.get(3..).unwrap_or(&[]);
+    match networkdays_impl(&values[0], &values[1], &weekend, holidays, ctx.date_system) {
+        Ok(n) => CellValue::Number(n),
+        Err(e) => CellValue::Error(e),
+    }
+}
 
-        // Test day 60 (Feb 29, 1900 - phantom date)
-        assert_eq!(serial_to_date(60.0).unwrap(), (1900, 2, 29));
+/// Shared implementation of EDATE and EOMONTH
+fn shift_months(
+    values: &[CellValue],
+    end_of_month: bool,
+    system: DateSystem,
+) -> Result<f64, CellError> {
+    if values.len() != 2 {
+        return Err(CellError::InvalidValue);
+    }
+    let start = date_arg(&values[0], system)?;
+    let months = number_arg(&values[1])?.trunc() as i32;
 
-        // Test day 61 (March 1, 1900, after leap year bug)
-        assert_eq!(serial_to_date(61.0).unwrap(), (1900, 3, 1));
+    let (year, month, day) = serial_to_date(start as f64, system)?;
+    let (year, month) = add_months(year, month, months);
+    if !(1900..=9999).contains(&year) {
+        return Err(CellError::NumError);
+    }
+    let last_day = days_in_month(year, month);
+    let day = if end_of_month { last_day } else { day.min(last_day) };
+    date_to_serial(year, month, day, system)
+}
 
-        // Test January 1, 2000
-        assert_eq!(serial_to_date(36526.0).unwrap(), (2000, 1, 1));
+/// EDATE - Date a number of months before or after a start date
+pub fn edate(values: &[CellValue], ctx: &DateContext) -> CellValue {
+    match shift_months(values, false, ctx.date_system) {
+        Ok(serial) => CellValue::Number(serial),
+        Err(e) => CellValue::Error(e),
+    }
+}
 
-        // Test June 15, 2024 (serial 45458)
-        assert_eq!(serial_to_date(45458.0).unwrap(), (2024, 6, 15));
+/// EOMONTH - Last day of the month a number of months before or after a start date
+pub fn eomonth(values: &[CellValue], ctx: &DateContext) -> CellValue {
+    match shift_months(values, true, ctx.date_system) {
+        Ok(serial) => CellValue::Number(serial),
+        Err(e) => CellValue::Error(e),
+    }
+}
+
+/// WEEKDAY - Day of the week for a date
+/// return_type: 1 = Sun..Sat as 1-7 (default), 2 = Mon..Sun as 1-7, 3 = Mon..Sun as 0-6,
+/// 11-17 = 1-7 starting Monday through Sunday respectively
+pub fn weekday(values: &[CellValue], ctx: &DateContext) -> CellValue {
+    if values.is_empty() || values.len() > 2 {
+        return CellValue::Error(CellError::InvalidValue);
     }
+    let serial = match date_arg(&values[0], ctx.date_system) {
+        Ok(s) => s,
+        Err(e) => return CellValue::Error(e),
+    };
+    let return_type = match values.get(1).map(number_arg).unwrap_or(Ok(1.0)) {
+        Ok(n) => n as i32,
+        Err(e) => return CellValue::Error(e),
+    };
+
+    // Monday = 0 .. Sunday = 6
+    let dow = day_of_week(serial, ctx.date_system) as i32;
+    let result = match return_type {
+        1 | 17 => (dow + 1) % 7 + 1,
+        2 | 11 => dow + 1,
+        3 => dow,
+        12..=16 => (dow - (return_type - 11)).rem_euclid(7) + 1,
+        _ => return CellValue::Error(CellError::NumError),
+    };
+    CellValue::Number(result as f64)
+}
+
+/// WEEKNUM - Week number of the year, where week 1 contains January 1
+/// return_type: 1/17 = weeks start Sunday (default), 2/11 = Monday, 12-16 = Tuesday
+/// through Saturday, 21 = ISO 8601 week numbering
+pub fn weeknum(values: &[CellValue], ctx: &DateContext) -> CellValue {
+    if values.is_empty() || values.len() > 2 {
+        return CellValue::Error(CellError::InvalidValue);
+    }
+    let serial = match date_arg(&values[0], ctx.date_system) {
+        Ok(s) => s,
+        Err(e) => return CellValue::Error(e),
+    };
+    let return_type = match values.get(1).map(number_arg).unwrap_or(Ok(1.0)) {
+        Ok(n) => n as i32,
+        Err(e) => return CellValue::Error(e),
+    };
+
+    // First day of the week, Monday = 0 .. Sunday = 6
+    let week_start = match return_type {
+        1 | 17 => 6,
+        2 | 11 => 0,
+        12..=16 => return_type - 11,
+        21 => {
+            return match iso_week_number(serial, ctx.date_system) {
+                Ok(w) => CellValue::Number(w as f64),
+                Err(e) => CellValue::Error(e),
+            }
+        }
+        _ => return CellValue::Error(CellError::NumError),
+    };
+
+    let doy = match day_of_year(serial, ctx.date_system) {
+        Ok(d) => d,
+        Err(e) => return CellValue::Error(e),
+    };
+    let jan1 = serial - doy + 1;
+    let offset = (day_of_week(jan1, ctx.date_system) as i64 - week_start as i64).rem_euclid(7);
+    CellValue::Number(((doy - 1 + offset) / 7 + 1) as f64)
+}
+
+/// ISOWEEKNUM - ISO 8601 week number of the year
+pub fn isoweeknum(values: &[CellValue], ctx: &DateContext) -> CellValue {
+    if values.len() != 1 {
+        return CellValue::Error(CellError::InvalidValue);
+    }
+    let system = ctx.date_system;
+    match date_arg(&values[0], system).and_then(|serial| iso_week_number(serial, system)) {
+        Ok(w) => CellValue::Number(w as f64),
+        Err(e) => CellValue::Error(e),
+    }
+}
+
+/// Number of ISO weeks (52 or 53) in a year
+fn iso_weeks_in_year(year: i32, system: DateSystem) -> Result<i64, CellError> {
+    let jan1 = date_to_serial(year, 1, 1, system)? as i64;
+    let dow = day_of_week(jan1, system);
+    Ok(if dow == 3 || (dow == 2 && is_leap_year(year)) { 53 } else { 52 })
+}
+
+/// ISO week number: weeks start on Monday and week 1 contains the year's first Thursday
+fn iso_week_number(serial: i64, system: DateSystem) -> Result<i64, CellError> {
+    let (year, _, _) = serial_to_date(serial as f64, system)?;
+    let doy = day_of_year(serial, system)?;
+    let week = (doy - day_of_week(serial, system) as i64 + 9) / 7;
+    if week < 1 {
+        iso_weeks_in_year(year - 1, system)
+    } else if week > iso_weeks_in_year(year, system)? {
+        Ok(1)
+    } else {
+        Ok(week)
+    }
+}
+
+/// YEARFRAC - Fraction of a year between two dates
+/// basis: 0 = US 30/360 (default), 1 = actual/actual, 2 = actual/360,
+/// 3 = actual/365, 4 = European 30/360
+pub fn yearfrac(values: &[CellValue], ctx: &DateContext) -> CellValue {
+    match yearfrac_impl(values, ctx.date_system) {
+        Ok(frac) => CellValue::Number(frac),
+        Err(e) => CellValue::Error(e),
+    }
+}
+
+fn yearfrac_impl(values: &[CellValue], system: DateSystem) -> Result<f64, CellError> {
+    if values.len() < 2 || values.len() > 3 {
+        return Err(CellError::InvalidValue);
+    }
+    let a = date_arg(&values[0], system)?;
+    let b = date_arg(&values[1], system)?;
+    let basis = values.get(2).map(number_arg).unwrap_or(Ok(0.0))?.trunc() as i32;
+    let (start, end) = (a.min(b), a.max(b));
+    let (sy, sm, sd) = serial_to_date(start as f64, system)?;
+    let (ey, em, ed) = serial_to_date(end as f64, system)?;
+    let days = (end - start) as f64;
+
+    let thirty_360 = |sd: u32, ed: u32| {
+        ((ey - sy) * 360 + (em as i32 - sm as i32) * 30 + (ed as i32 - sd as i32)) as f64 / 360.0
+    };
+
+    match basis {
+        0 => {
+            let start_is_eom = sd == days_in_month(sy, sm);
+            let end_is_eom = ed == days_in_month(ey, em);
+            let (sd, ed) = if sd >= 30 && ed == 31 {
+                (30, 30)
+            } else if sd == 31 {
+                (30, ed)
+            } else if sm == 2 && em == 2 && start_is_eom && end_is_eom {
+                (30, 30)
+            } else if sm == 2 && start_is_eom {
+                (30, ed)
+            } else {
+                (sd, ed)
+            };
+            Ok(thirty_360(sd, ed))
+        }
+        1 => {
+            let within_a_year = sy == ey
+                || (ey == sy + 1 && (sm > em || (sm == em && sd >= ed)));
+            if within_a_year {
+                let feb29_between = (sy..=ey).any(|y| {
+                    is_leap_year(y)
+                        && date_to_serial(y, 2, 29, system)
+                            .is_ok_and(|s| (start..=end).contains(&(s as i64)))
+                });
+                let year_length = if (sy == ey && is_leap_year(sy)) || feb29_between {
+                    366.0
+                } else {
+                    365.0
+                };
+                Ok(days / year_length)
+            } else {
+                let years = (ey - sy + 1) as f64;
+                let span = date_to_serial(ey + 1, 1, 1, system)? - date_to_serial(sy, 1, 1, system)?;
+                Ok(days / (span / years))
+            }
+        }
+        2 => Ok(days / 360.0),
+        3 => Ok(days / 365.0),
+        4 => Ok(thirty_360(sd.min(30), ed.min(30))),
+        _ => Err(CellError::NumError),
+    }
+}
+
+/// DATEVALUE - Convert date text to a date serial (any time part is ignored)
+pub fn datevalue(values: &[CellValue], ctx: &DateContext) -> CellValue {
+    if values.len() != 1 {
+        return CellValue::Error(CellError::InvalidValue);
+    }
+    let text = match &values[0] {
+        CellValue::Text(s) => s,
+        CellValue::Error(e) => return CellValue::Error(e.clone()),
+        _ => return CellValue::Error(CellError::InvalidValue),
+    };
+    let (date_part, time_part) = split_date_time(text);
+    if time_part.is_some_and(|t| parse_time_text(t).is_none()) {
+        return CellValue::Error(CellError::InvalidValue);
+    }
+    match date_part.and_then(|d| parse_date_text(d, ctx.date_order)) {
+        Some((y, m, d)) => match date_to_serial(y, m, d, ctx.date_system) {
+            Ok(serial) => CellValue::Number(serial),
+            Err(_) => CellValue::Error(CellError::InvalidValue),
+        },
+        None => CellValue::Error(CellError::InvalidValue),
+    }
+}
+
+/// TIMEVALUE - Convert time text to a fraction of a day (any date part is ignored)
+pub fn timevalue(values: &[CellValue], ctx: &DateContext) -> CellValue {
+    if values.len() != 1 {
+        return CellValue::Error(CellError::InvalidValue);
+    }
+    let text = match &values[0] {
+        CellValue::Text(s) => s,
+        CellValue::Error(e) => return CellValue::Error(e.clone()),
+        _ => return CellValue::Error(CellError::InvalidValue),
+    };
+    let (date_part, time_part) = split_date_time(text);
+    if let (Some(date), Some(_)) = (date_part, time_part) {
+        if parse_date_text(date, ctx.date_order).is_none() {
+            return CellValue::Error(CellError::InvalidValue);
+        }
+    }
+    match time_part.and_then(parse_time_text) {
+        Some(fraction) => CellValue::Number(fraction),
+        None => CellValue::Error(CellError::InvalidValue),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
     #[test]
     fn test_date_function() {
@@ -495,18 +837,18 @@ mod tests {
             CellValue::Number(2024.0),
             CellValue::Number(6.0),
             CellValue::Number(15.0),
-        ]);
+        ], &ctx());
         assert_eq!(result, CellValue::Number(45458.0));
 
         // Test error cases
         assert!(matches!(
-            date(&[CellValue::Number(1899.0), CellValue::Number(1.0), CellValue::Number(1.0)]),
+            date(&[CellValue::Number(1899.0), CellValue::Number(1.0), CellValue::Number(1.0)], &ctx()),
             CellValue::Error(CellError::NumError)
         ));
 
         // Test wrong number of arguments
         assert!(matches!(
-            date(&[CellValue::Number(2024.0), CellValue::Number(6.0)]),
+            date(&[CellValue::Number(2024.0), CellValue::Number(6.0)], &ctx()),
             CellValue::Error(CellError::InvalidValue)
         ));
     }
@@ -550,15 +892,15 @@ mod tests {
         // Test June 15, 2024 (serial 45458)
         let serial = CellValue::Number(45458.0);
 
-        assert_eq!(year(&[serial.clone()]), CellValue::Number(2024.0));
-        assert_eq!(month(&[serial.clone()]), CellValue::Number(6.0));
-        assert_eq!(day(&[serial]), CellValue::Number(15.0));
+        assert_eq!(year(std::slice::from_ref(&serial), &ctx()), CellValue::Number(2024.0));
+        assert_eq!(month(std::slice::from_ref(&serial), &ctx()), CellValue::Number(6.0));
+        assert_eq!(day(&[serial], &ctx()), CellValue::Number(15.0));
 
         // Test January 1, 1900
         let serial = CellValue::Number(1.0);
-        assert_eq!(year(&[serial.clone()]), CellValue::Number(1900.0));
-        assert_eq!(month(&[serial.clone()]), CellValue::Number(1.0));
-        assert_eq!(day(&[serial]), CellValue::Number(1.0));
+        assert_eq!(year(std::slice::from_ref(&serial), &ctx()), CellValue::Number(1900.0));
+        assert_eq!(month(std::slice::from_ref(&serial), &ctx()), CellValue::Number(1.0));
+        assert_eq!(day(&[serial], &ctx()), CellValue::Number(1.0));
     }
 
     #[test]
@@ -567,82 +909,82 @@ mod tests {
         let time_fraction = (18.0 * 3600.0 + 30.0 * 60.0 + 45.0) / 86400.0;
         let serial = CellValue::Number(time_fraction);
 
-        assert_eq!(hour(&[serial.clone()]), CellValue::Number(18.0));
-        assert_eq!(minute(&[serial.clone()]), CellValue::Number(30.0));
+        assert_eq!(hour(std::slice::from_ref(&serial)), CellValue::Number(18.0));
+        assert_eq!(minute(std::slice::from_ref(&serial)), CellValue::Number(30.0));
         assert_eq!(second(&[serial]), CellValue::Number(45.0));
 
         // Test full datetime (date + time)
         let datetime = 45467.0 + time_fraction; // June 15, 2024 18:30:45
         let serial = CellValue::Number(datetime);
 
-        assert_eq!(hour(&[serial.clone()]), CellValue::Number(18.0));
-        assert_eq!(minute(&[serial.clone()]), CellValue::Number(30.0));
+        assert_eq!(hour(std::slice::from_ref(&serial)), CellValue::Number(18.0));
+        assert_eq!(minute(std::slice::from_ref(&serial)), CellValue::Number(30.0));
         assert_eq!(second(&[serial]), CellValue::Number(45.0));
     }
 
     #[test]
     fn test_datedif_days() {
         // 10 days difference
-        let start = CellValue::Number(date_to_serial(2024, 1, 1).unwrap());
-        let end = CellValue::Number(date_to_serial(2024, 1, 11).unwrap());
+        let start = CellValue::Number(date_to_serial(2024, 1, 1, DateSystem::Excel1900).unwrap());
+        let end = CellValue::Number(date_to_serial(2024, 1, 11, DateSystem::Excel1900).unwrap());
         let unit = CellValue::Text("D".to_string());
 
-        assert_eq!(datedif(&[start, end, unit]), CellValue::Number(10.0));
+        assert_eq!(datedif(&[start, end, unit], &ctx()), CellValue::Number(10.0));
     }
 
     #[test]
     fn test_datedif_months() {
         // Exactly 3 months
-        let start = CellValue::Number(date_to_serial(2024, 1, 15).unwrap());
-        let end = CellValue::Number(date_to_serial(2024, 4, 15).unwrap());
+        let start = CellValue::Number(date_to_serial(2024, 1, 15, DateSystem::Excel1900).unwrap());
+        let end = CellValue::Number(date_to_serial(2024, 4, 15, DateSystem::Excel1900).unwrap());
         let unit = CellValue::Text("M".to_string());
 
-        assert_eq!(datedif(&[start, end, unit]), CellValue::Number(3.0));
+        assert_eq!(datedif(&[start, end, unit], &ctx()), CellValue::Number(3.0));
 
         // 2 months (not quite 3)
-        let start = CellValue::Number(date_to_serial(2024, 1, 15).unwrap());
-        let end = CellValue::Number(date_to_serial(2024, 4, 14).unwrap());
+        let start = CellValue::Number(date_to_serial(2024, 1, 15, DateSystem::Excel1900).unwrap());
+        let end = CellValue::Number(date_to_serial(2024, 4, 14, DateSystem::Excel1900).unwrap());
         let unit = CellValue::Text("M".to_string());
 
-        assert_eq!(datedif(&[start, end, unit]), CellValue::Number(2.0));
+        assert_eq!(datedif(&[start, end, unit], &ctx()), CellValue::Number(2.0));
     }
 
     #[test]
     fn test_datedif_years() {
         // Exactly 2 years
-        let start = CellValue::Number(date_to_serial(2022, 6, 15).unwrap());
-        let end = CellValue::Number(date_to_serial(2024, 6, 15).unwrap());
+        let start = CellValue::Number(date_to_serial(2022, 6, 15, DateSystem::Excel1900).unwrap());
+        let end = CellValue::Number(date_to_serial(2024, 6, 15, DateSystem::Excel1900).unwrap());
         let unit = CellValue::Text("Y".to_string());
 
-        assert_eq!(datedif(&[start, end, unit]), CellValue::Number(2.0));
+        assert_eq!(datedif(&[start, end, unit], &ctx()), CellValue::Number(2.0));
 
         // Not quite 2 years
-        let start = CellValue::Number(date_to_serial(2022, 6, 15).unwrap());
-        let end = CellValue::Number(date_to_serial(2024, 6, 14).unwrap());
+        let start = CellValue::Number(date_to_serial(2022, 6, 15, DateSystem::Excel1900).unwrap());
+        let end = CellValue::Number(date_to_serial(2024, 6, 14, DateSystem::Excel1900).unwrap());
         let unit = CellValue::Text("Y".to_string());
 
-        assert_eq!(datedif(&[start, end, unit]), CellValue::Number(1.0));
+        assert_eq!(datedif(&[start, end, unit], &ctx()), CellValue::Number(1.0));
     }
 
     #[test]
     fn test_datedif_errors() {
         // Start date after end date
-        let start = CellValue::Number(date_to_serial(2024, 6, 15).unwrap());
-        let end = CellValue::Number(date_to_serial(2024, 1, 1).unwrap());
+        let start = CellValue::Number(date_to_serial(2024, 6, 15, DateSystem::Excel1900).unwrap());
+        let end = CellValue::Number(date_to_serial(2024, 1, 1, DateSystem::Excel1900).unwrap());
         let unit = CellValue::Text("D".to_string());
 
         assert!(matches!(
-            datedif(&[start, end, unit]),
+            datedif(&[start, end, unit], &ctx()),
             CellValue::Error(CellError::NumError)
         ));
 
         // Invalid unit
-        let start = CellValue::Number(date_to_serial(2024, 1, 1).unwrap());
-        let end = CellValue::Number(date_to_serial(2024, 6, 15).unwrap());
+        let start = CellValue::Number(date_to_serial(2024, 1, 1, DateSystem::Excel1900).unwrap());
+        let end = CellValue::Number(date_to_serial(2024, 6, 15, DateSystem::Excel1900).unwrap());
         let unit = CellValue::Text("X".to_string());
 
         assert!(matches!(
-            datedif(&[start, end, unit]),
+            datedif(&[start, end, unit], &ctx()),
             CellValue::Error(CellError::InvalidValue)
         ));
     }
@@ -650,7 +992,7 @@ mod tests {
     #[test]
     fn test_today_now() {
         // TODAY should return no fractional part
-        let today_result = today(&[]);
+        let today_result = today(&[], &ctx());
         if let CellValue::Number(n) = today_result {
             assert_eq!(n.fract(), 0.0);
         } else {
@@ -658,67 +1000,247 @@ mod tests {
         }
 
         // NOW should return a number (may have fractional part)
-        let now_result = now(&[]);
+        let now_result = now(&[], &ctx());
         assert!(matches!(now_result, CellValue::Number(_)));
 
         // Both should reject arguments
         assert!(matches!(
-            today(&[CellValue::Number(1.0)]),
+            today(&[CellValue::Number(1.0)], &ctx()),
             CellValue::Error(CellError::InvalidValue)
         ));
         assert!(matches!(
-            now(&[CellValue::Number(1.0)]),
+            now(&[CellValue::Number(1.0)], &ctx()),
             CellValue::Error(CellError::InvalidValue)
         ));
     }
 
+    #[test]
+    fn test_today_now_with_fixed_clock_and_offset() {
+        // 2024-06-15 20:00 UTC
+        let utc = DateContext::default().with_fixed_now(1_718_481_600_000.0);
+        assert_eq!(today(&[], &utc), CellValue::Number(45458.0));
+        assert!(matches!(now(&[], &utc), CellValue::Number(n) if (n - (45458.0 + 20.0 / 24.0)).abs() < 1e-9));
+
+        // Already 04:00 on June 16 in Asia/Taipei (UTC+8)
+        let taipei = utc.with_utc_offset(480);
+        assert_eq!(today(&[], &taipei), CellValue::Number(45459.0));
+        assert!(matches!(now(&[], &taipei), CellValue::Number(n) if (n - (45459.0 + 4.0 / 24.0)).abs() < 1e-9));
+
+        let mac = DateContext::new(DateSystem::Excel1904)
+            .with_utc_offset(480)
+            .with_fixed_now(1_718_481_600_000.0);
+        assert_eq!(today(&[], &mac), CellValue::Number(45459.0 - 1462.0));
+    }
+
+    #[test]
+    fn test_1904_date_system() {
+        let mac = DateContext::new(DateSystem::Excel1904);
+        let jun15 = num(45458.0 - 1462.0);
+
+        assert_eq!(
+            date(&[num(2024.0), num(6.0), num(15.0)], &mac),
+            CellValue::Number(45458.0 - 1462.0)
+        );
+        assert_eq!(year(std::slice::from_ref(&jun15), &mac), CellValue::Number(2024.0));
+        assert_eq!(month(std::slice::from_ref(&jun15), &mac), CellValue::Number(6.0));
+        assert_eq!(day(std::slice::from_ref(&jun15), &mac), CellValue::Number(15.0));
+
+        // 2024-06-15 is a Saturday in either system
+        assert_eq!(weekday(std::slice::from_ref(&jun15), &mac), CellValue::Number(7.0));
+        assert_eq!(weekday(&[num(45458.0)], &ctx()), CellValue::Number(7.0));
+
+        // Serial 0 is 1904-01-01; dates before the epoch are out of range
+        assert_eq!(year(&[num(0.0)], &mac), CellValue::Number(1904.0));
+        assert_eq!(
+            date(&[num(1903.0), num(12.0), num(31.0)], &mac),
+            CellValue::Error(CellError::NumError)
+        );
+
+        assert_eq!(
+            datevalue(&[text("2024-06-15")], &mac),
+            CellValue::Number(45458.0 - 1462.0)
+        );
+        assert_eq!(
+            edate(&[jun15, num(1.0)], &mac),
+            CellValue::Number(45488.0 - 1462.0)
+        );
+    }
+
     #[test]
     fn test_error_propagation() {
         let error = CellValue::Error(CellError::DivisionByZero);
 
         assert!(matches!(
-            date(&[error.clone(), CellValue::Number(1.0), CellValue::Number(1.0)]),
+            date(&[error.clone(), CellValue::Number(1.0), CellValue::Number(1.0)], &ctx()),
             CellValue::Error(CellError::DivisionByZero)
         ));
 
         assert!(matches!(
-            year(&[error.clone()]),
+            year(std::slice::from_ref(&error), &ctx()),
             CellValue::Error(CellError::DivisionByZero)
         ));
 
         assert!(matches!(
-            datedif(&[error.clone(), CellValue::Number(1.0), CellValue::Text("D".to_string())]),
+            datedif(&[error.clone(), CellValue::Number(1.0), CellValue::Text("D".to_string())], &ctx()),
             CellValue::Error(CellError::DivisionByZero)
         ));
     }
 
+    fn ctx() -> DateContext {
+        DateContext::default()
+    }
+
+    fn num(n: f64) -> CellValue {
+        CellValue::Number(n)
+    }
+
+    fn text(s: &str) -> CellValue {
+        CellValue::Text(s.to_string())
+    }
+
+    fn serial(y: i32, m: u32, d: u32) -> CellValue {
+        CellValue::Number(date_to_serial(y, m, d, DateSystem::Excel1900).unwrap())
+    }
+
     #[test]
-    fn test_leap_year() {
-        // 2000 is a leap year (divisible by 400)
-        assert!(is_leap_year(2000));
+    fn test_workday() {
+        // Friday 2024-06-14 + 1 working day = Monday 2024-06-17
+        assert_eq!(workday(&[serial(2024, 6, 14), num(1.0)], &ctx()), serial(2024, 6, 17));
+        // Negative days go backwards over the weekend
+        assert_eq!(workday(&[serial(2024, 6, 17), num(-1.0)], &ctx()), serial(2024, 6, 14));
+        // Holidays are skipped, blanks in the holiday range ignored
+        assert_eq!(
+            workday(&[serial(2024, 6, 14), num(1.0), serial(2024, 6, 17), CellValue::Empty], &ctx()),
+            serial(2024, 6, 18)
+        );
+        assert_eq!(workday(&[serial(2024, 6, 15), num(0.0)], &ctx()), serial(2024, 6, 15));
+    }
 
-        // 2024 is a leap year (divisible by 4, not by 100)
-        assert!(is_leap_year(2024));
+    #[test]
+    fn test_workday_intl() {
+        // Friday/Saturday weekend (code 7): Thursday + 1 = Sunday
+        assert_eq!(workday_intl(&[serial(2024, 6, 13), num(1.0), num(7.0)], &ctx()), serial(2024, 6, 16));
+        // Sunday-only weekend (code 11): Saturday + 1 = Monday
+        assert_eq!(workday_intl(&[serial(2024, 6, 15), num(1.0), num(11.0)], &ctx()), serial(2024, 6, 17));
+        // Mask string with Wednesday off
+        assert_eq!(
+            workday_intl(&[serial(2024, 6, 11), num(1.0), text("0010000")], &ctx()),
+            serial(2024, 6, 13)
+        );
+        assert_eq!(
+            workday_intl(&[serial(2024, 6, 11), num(1.0), text("1111111")], &ctx()),
+            CellValue::Error(CellError::InvalidValue)
+        );
+        assert_eq!(
+            workday_intl(&[serial(2024, 6, 11), num(1.0), num(9.0)], &ctx()),
+            CellValue::Error(CellError::NumError)
+        );
+    }
 
-        // 1900 is NOT a leap year (divisible by 100 but not 400)
-        assert!(!is_leap_year(1900));
+    #[test]
+    fn test_networkdays() {
+        // June 2024 has 20 weekdays
+        assert_eq!(networkdays(&[serial(2024, 6, 1), serial(2024, 6, 30)], &ctx()), num(20.0));
+        assert_eq!(
+            networkdays(&[serial(2024, 6, 1), serial(2024, 6, 30), serial(2024, 6, 19)], &ctx()),
+            num(19.0)
+        );
+        assert_eq!(networkdays(&[serial(2024, 6, 30), serial(2024, 6, 1)], &ctx()), num(-20.0));
+        // Six-day working week with Sunday off
+        assert_eq!(
+            networkdays_intl(&[serial(2024, 6, 1), serial(2024, 6, 30), num(11.0)], &ctx()),
+            num(25.0)
+        );
+    }
 
-        // 2023 is not a leap year
-        assert!(!is_leap_year(2023));
+    #[test]
+    fn test_edate_eomonth() {
+        assert_eq!(edate(&[serial(2024, 1, 31), num(1.0)], &ctx()), serial(2024, 2, 29));
+        assert_eq!(edate(&[serial(2024, 3, 15), num(-3.0)], &ctx()), serial(2023, 12, 15));
+        assert_eq!(eomonth(&[serial(2024, 1, 15), num(1.0)], &ctx()), serial(2024, 2, 29));
+        assert_eq!(eomonth(&[serial(2024, 1, 15), num(0.0)], &ctx()), serial(2024, 1, 31));
+        assert_eq!(eomonth(&[serial(2024, 1, 15), num(-13.0)], &ctx()), serial(2022, 12, 31));
     }
 
     #[test]
-    fn test_days_in_month() {
-        // January has 31 days
-        assert_eq!(days_in_month(2024, 1), 31);
+    fn test_weekday() {
+        // 2024-06-15 is a Saturday
+        let sat = serial(2024, 6, 15);
+        assert_eq!(weekday(std::slice::from_ref(&sat), &ctx()), num(7.0));
+        assert_eq!(weekday(&[sat.clone(), num(2.0)], &ctx()), num(6.0));
+        assert_eq!(weekday(&[sat.clone(), num(3.0)], &ctx()), num(5.0));
+        assert_eq!(weekday(&[sat.clone(), num(16.0)], &ctx()), num(1.0));
+        assert_eq!(weekday(&[sat.clone(), num(12.0)], &ctx()), num(5.0));
+        assert_eq!(weekday(&[sat, num(5.0)], &ctx()), CellValue::Error(CellError::NumError));
+        // Excel considers serial 1 a Sunday
+        assert_eq!(weekday(&[num(1.0)], &ctx()), num(1.0));
+    }
 
-        // February in leap year has 29 days
-        assert_eq!(days_in_month(2024, 2), 29);
+    #[test]
+    fn test_weeknum() {
+        // 2024-01-01 is a Monday
+        assert_eq!(weeknum(&[serial(2024, 1, 6)], &ctx()), num(1.0));
+        assert_eq!(weeknum(&[serial(2024, 1, 7)], &ctx()), num(2.0));
+        assert_eq!(weeknum(&[serial(2024, 1, 7), num(2.0)], &ctx()), num(1.0));
+        assert_eq!(weeknum(&[serial(2024, 12, 31)], &ctx()), num(53.0));
+        assert_eq!(weeknum(&[serial(2021, 1, 1), num(21.0)], &ctx()), num(53.0));
+    }
 
-        // February in non-leap year has 28 days
-        assert_eq!(days_in_month(2023, 2), 28);
+    #[test]
+    fn test_isoweeknum() {
+        assert_eq!(isoweeknum(&[serial(2024, 1, 1)], &ctx()), num(1.0));
+        // 2021-01-01 (Friday) belongs to week 53 of 2020
+        assert_eq!(isoweeknum(&[serial(2021, 1, 1)], &ctx()), num(53.0));
+        // 2024-12-30 (Monday) is in week 1 of 2025
+        assert_eq!(isoweeknum(&[serial(2024, 12, 30)], &ctx()), num(1.0));
+        assert_eq!(isoweeknum(&[serial(2024, 6, 15)], &ctx()), num(24.0));
+    }
 
-        // April has 30 days
-        assert_eq!(days_in_month(2024, 4), 30);
+    #[test]
+    fn test_yearfrac() {
+        let start = serial(2012, 1, 1);
+        let end = serial(2012, 7, 30);
+        let close = |v: CellValue, expected: f64| match v {
+            CellValue::Number(n) => assert!((n - expected).abs() < 1e-9, "{} != {}", n, expected),
+            other => panic!("expected number, got {:?}", other),
+        };
+        close(yearfrac(&[start.clone(), end.clone()], &ctx()), 0.580555555555556);
+        close(yearfrac(&[start.clone(), end.clone(), num(1.0)], &ctx()), 0.576502732240437);
+        close(yearfrac(&[start.clone(), end.clone(), num(2.0)], &ctx()), 0.586111111111111);
+        close(yearfrac(&[start.clone(), end.clone(), num(3.0)], &ctx()), 0.578082191780822);
+        close(yearfrac(&[start.clone(), end.clone(), num(4.0)], &ctx()), 0.580555555555556);
+        // Argument order doesn't matter
+        close(yearfrac(&[end.clone(), start.clone(), num(3.0)], &ctx()), 0.578082191780822);
+        // Spans over a year average the year lengths
+        close(yearfrac(&[serial(2011, 6, 1), serial(2013, 6, 1), num(1.0)], &ctx()), 731.0 / (1096.0 / 3.0));
+        assert_eq!(
+            yearfrac(&[start, end, num(5.0)], &ctx()),
+            CellValue::Error(CellError::NumError)
+        );
+    }
+
+    #[test]
+    fn test_datevalue() {
+        assert_eq!(datevalue(&[text("2024-06-15")], &ctx()), serial(2024, 6, 15));
+        assert_eq!(datevalue(&[text("6/15/2024")], &ctx()), serial(2024, 6, 15));
+        assert_eq!(datevalue(&[text("15-Jun-2024")], &ctx()), serial(2024, 6, 15));
+        assert_eq!(datevalue(&[text("June 15, 2024")], &ctx()), serial(2024, 6, 15));
+        assert_eq!(datevalue(&[text("2024-06-15 18:30")], &ctx()), serial(2024, 6, 15));
+        assert_eq!(datevalue(&[text("2024-02-30")], &ctx()), CellValue::Error(CellError::InvalidValue));
+        assert_eq!(datevalue(&[text("hello")], &ctx()), CellValue::Error(CellError::InvalidValue));
+
+        let dmy = ctx().with_date_order(DateOrder::DayMonthYear);
+        assert_eq!(datevalue(&[text("15/6/2024")], &dmy), serial(2024, 6, 15));
+        assert_eq!(datevalue(&[text("6/15/2024")], &dmy), CellValue::Error(CellError::InvalidValue));
+    }
+
+    #[test]
+    fn test_timevalue() {
+        assert_eq!(timevalue(&[text("18:00")], &ctx()), num(0.75));
+        assert_eq!(timevalue(&[text("6:00 PM")], &ctx()), num(0.75));
+        assert_eq!(timevalue(&[text("12:00 am")], &ctx()), num(0.0));
+        assert_eq!(timevalue(&[text("2024-06-15 06:00")], &ctx()), num(0.25));
+        assert_eq!(timevalue(&[text("25:00")], &ctx()), CellValue::Error(CellError::InvalidValue));
+        assert_eq!(timevalue(&[text("2024-06-15")], &ctx()), CellValue::Error(CellError::InvalidValue));
     }
 }
@@ -0,0 +1,183 @@
+use rusheet_core::{CellError, CellValue};
+
+/// Get the first argument, or #VALUE! if none was supplied
+fn first(values: &[CellValue]) -> Result<&CellValue, CellValue> {
+    values.first().ok_or(CellValue::Error(CellError::InvalidValue))
+}
+
+/// ISBLANK - Check if a value is empty
+pub fn isblank(values: &[CellValue]) -> CellValue {
+    match first(values) {
+        Ok(v) => CellValue::Boolean(v.is_empty()),
+        Err(e) => e,
+    }
+}
+
+/// ISNUMBER - Check if a value is a number
+pub fn isnumber(values: &[CellValue]) -> CellValue {
+    match first(values) {
+        Ok(v) => CellValue::Boolean(matches!(v, CellValue::Number(_))),
+        Err(e) => e,
+    }
+}
+
+/// ISTEXT - Check if a value is text
+pub fn istext(values: &[CellValue]) -> CellValue {
+    match first(values) {
+        Ok(v) => CellValue::Boolean(matches!(v, CellValue::Text(_))),
+        Err(e) => e,
+    }
+}
+
+/// ISLOGICAL - Check if a value is a boolean
+pub fn islogical(values: &[CellValue]) -> CellValue {
+    match first(values) {
+        Ok(v) => CellValue::Boolean(matches!(v, CellValue::Boolean(_))),
+        Err(e) => e,
+    }
+}
+
+/// ISERROR - Check if a value is any error
+pub fn iserror(values: &[CellValue]) -> CellValue {
+    match first(values) {
+        Ok(v) => CellValue::Boolean(matches!(v, CellValue::Error(_))),
+        Err(e) => e,
+    }
+}
+
+/// ISERR - Check if a value is any error except #N/A
+pub fn iserr(values: &[CellValue]) -> CellValue {
+    match first(values) {
+        Ok(v) => CellValue::Boolean(
+            matches!(v, CellValue::Error(e) if *e != CellError::NotAvailable),
+        ),
+        Err(e) => e,
+    }
+}
+
+/// ISNA - Check if a value is the #N/A error
+pub fn isna(values: &[CellValue]) -> CellValue {
+    match first(values) {
+        Ok(v) => CellValue::Boolean(matches!(v, CellValue::Error(CellError::NotAvailable))),
+        Err(e) => e,
+    }
+}
+
+/// ERROR.TYPE - Get the Excel error number of an error value
+///
+/// Returns #N/A when the value is not an error.
+pub fn error_type(values: &[CellValue]) -> CellValue {
+    let code = match first(values) {
+        Ok(CellValue::Error(e)) => match e {
+            CellError::NullError => 1.0,
+            CellError::DivisionByZero => 2.0,
+            CellError::InvalidValue => 3.0,
+            CellError::InvalidReference => 4.0,
+            CellError::InvalidName => 5.0,
+            CellError::NumError => 6.0,
+            CellError::NotAvailable => 7.0,
+            CellError::CalcError => 14.0,
+            CellError::CircularReference => return CellValue::Error(CellError::NotAvailable),
+        },
+        Ok(_) => return CellValue::Error(CellError::NotAvailable),
+        Err(e) => return e,
+    };
+    CellValue::Number(code)
+}
+
+/// NA - Return the #N/A error
+pub fn na(_values: &[CellValue]) -> CellValue {
+    CellValue::Error(CellError::NotAvailable)
+}
+
+/// TYPE - Get the type code of a value (1=number, 2=text, 4=logical, 16=error)
+pub fn type_fn(values: &[CellValue]) -> CellValue {
+    let code = match first(values) {
+        Ok(CellValue::Empty) | Ok(CellValue::Number(_)) => 1.0,
+        Ok(CellValue::Text(_)) => 2.0,
+        Ok(CellValue::Boolean(_)) => 4.0,
+        Ok(CellValue::Error(_)) => 16.0,
+        Err(e) => return e,
+    };
+    CellValue::Number(code)
+}
+
+/// N - Convert a value to a number (text becomes 0)
+pub fn n(values: &[CellValue]) -> CellValue {
+    match first(values) {
+        Ok(CellValue::Number(n)) => CellValue::Number(*n),
+        Ok(CellValue::Boolean(b)) => CellValue::Number(if *b { 1.0 } else { 0.0 }),
+        Ok(CellValue::Error(e)) => CellValue::Error(e.clone()),
+        Ok(CellValue::Empty) | Ok(CellValue::Text(_)) => CellValue::Number(0.0),
+        Err(e) => e,
+    }
+}
+
+/// T - Return text values as-is, anything else as an empty string
+pub fn t(values: &[CellValue]) -> CellValue {
+    match first(values) {
+        Ok(CellValue::Text(s)) => CellValue::Text(s.clone()),
+        Ok(CellValue::Error(e)) => CellValue::Error(e.clone()),
+        Ok(_) => CellValue::Text(String::new()),
+        Err(e) => e,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_functions() {
+        assert_eq!(isblank(&[CellValue::Empty]), CellValue::Boolean(true));
+        assert_eq!(isblank(&[CellValue::Text(String::new())]), CellValue::Boolean(false));
+        assert_eq!(isnumber(&[CellValue::Number(1.0)]), CellValue::Boolean(true));
+        assert_eq!(isnumber(&[CellValue::Text("1".to_string())]), CellValue::Boolean(false));
+        assert_eq!(istext(&[CellValue::Text("a".to_string())]), CellValue::Boolean(true));
+        assert_eq!(islogical(&[CellValue::Boolean(false)]), CellValue::Boolean(true));
+        assert_eq!(islogical(&[CellValue::Number(0.0)]), CellValue::Boolean(false));
+    }
+
+    #[test]
+    fn test_error_checks() {
+        let na_err = CellValue::Error(CellError::NotAvailable);
+        let div_err = CellValue::Error(CellError::DivisionByZero);
+
+        assert_eq!(iserror(std::slice::from_ref(&na_err)), CellValue::Boolean(true));
+        assert_eq!(iserror(std::slice::from_ref(&div_err)), CellValue::Boolean(true));
+        assert_eq!(iserr(std::slice::from_ref(&na_err)), CellValue::Boolean(false));
+        assert_eq!(iserr(std::slice::from_ref(&div_err)), CellValue::Boolean(true));
+        assert_eq!(isna(&[na_err]), CellValue::Boolean(true));
+        assert_eq!(isna(&[div_err]), CellValue::Boolean(false));
+        assert_eq!(iserror(&[CellValue::Number(1.0)]), CellValue::Boolean(false));
+    }
+
+    #[test]
+    fn test_error_type() {
+        assert_eq!(error_type(&[CellValue::Error(CellError::NullError)]), CellValue::Number(1.0));
+        assert_eq!(error_type(&[CellValue::Error(CellError::DivisionByZero)]), CellValue::Number(2.0));
+        assert_eq!(error_type(&[CellValue::Error(CellError::NotAvailable)]), CellValue::Number(7.0));
+        assert_eq!(error_type(&[CellValue::Error(CellError::CalcError)]), CellValue::Number(14.0));
+        assert_eq!(
+            error_type(&[CellValue::Number(1.0)]),
+            CellValue::Error(CellError::NotAvailable)
+        );
+    }
+
+    #[test]
+    fn test_type() {
+        assert_eq!(type_fn(&[CellValue::Number(1.0)]), CellValue::Number(1.0));
+        assert_eq!(type_fn(&[CellValue::Text("a".to_string())]), CellValue::Number(2.0));
+        assert_eq!(type_fn(&[CellValue::Boolean(true)]), CellValue::Number(4.0));
+        assert_eq!(type_fn(&[CellValue::Error(CellError::NumError)]), CellValue::Number(16.0));
+    }
+
+    #[test]
+    fn test_n_and_t() {
+        assert_eq!(n(&[CellValue::Number(5.0)]), CellValue::Number(5.0));
+        assert_eq!(n(&[CellValue::Boolean(true)]), CellValue::Number(1.0));
+        assert_eq!(n(&[CellValue::Text("7".to_string())]), CellValue::Number(0.0));
+        assert_eq!(t(&[CellValue::Text("hi".to_string())]), CellValue::Text("hi".to_string()));
+        assert_eq!(t(&[CellValue::Number(5.0)]), CellValue::Text(String::new()));
+    }
+}
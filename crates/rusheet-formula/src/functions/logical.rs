@@ -1,5 +1,15 @@
 use rusheet_core::{CellError, CellValue};
 
+/// Interpret a value as a condition for IF-style functions
+pub fn condition(value: &CellValue) -> Result<bool, CellError> {
+    match value {
+        CellValue::Boolean(b) => Ok(*b),
+        CellValue::Number(n) => Ok(*n != 0.0),
+        CellValue::Error(e) => Err(e.clone()),
+        _ => Err(CellError::InvalidValue),
+    }
+}
+
 /// IF - Conditional evaluation
 pub fn if_fn(values: &[CellValue]) -> CellValue {
     if values.is_empty() {
@@ -7,11 +17,9 @@ pub fn if_fn(values: &[CellValue]) -> CellValue {
     }
 
     // Get condition
-    let condition = match &values[0] {
-        CellValue::Boolean(b) => *b,
-        CellValue::Number(n) => *n != 0.0,
-        CellValue::Error(e) => return CellValue::Error(e.clone()),
-        _ => return CellValue::Error(CellError::InvalidValue),
+    let condition = match condition(&values[0]) {
+        Ok(b) => b,
+        Err(e) => return CellValue::Error(e),
     };
 
     if condition {
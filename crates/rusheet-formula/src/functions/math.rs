@@ -1,30 +1,31 @@
-use rusheet_core::{CellError, CellValue};
+use rusheet_core::{numbers_equal, round_significant, CellError, CellValue, CompensatedSum};
+use std::cell::Cell;
 
 /// SUM - Sum all numeric values
 pub fn sum(values: &[CellValue]) -> CellValue {
-    let mut total = 0.0;
+    let mut total = CompensatedSum::new();
 
     for value in values {
         match value {
-            CellValue::Number(n) => total += n,
-            CellValue::Boolean(b) => total += if *b { 1.0 } else { 0.0 },
+            CellValue::Number(n) => total.add(*n),
+            CellValue::Boolean(b) => total.add(if *b { 1.0 } else { 0.0 }),
             CellValue::Error(e) => return CellValue::Error(e.clone()),
             _ => {} // Skip empty and text
         }
     }
 
-    CellValue::Number(total)
+    CellValue::Number(total.total())
 }
 
 /// AVERAGE - Average of numeric values
 pub fn average(values: &[CellValue]) -> CellValue {
-    let mut total = 0.0;
+    let mut total = CompensatedSum::new();
     let mut count = 0;
 
     for value in values {
         match value {
             CellValue::Number(n) => {
-                total += n;
+                total.add(*n);
                 count += 1;
             }
             CellValue::Error(e) => return CellValue::Error(e.clone()),
@@ -35,7 +36,7 @@ pub fn average(values: &[CellValue]) -> CellValue {
     if count == 0 {
         CellValue::Error(CellError::DivisionByZero)
     } else {
-        CellValue::Number(total / count as f64)
+        CellValue::Number(total.total() / count as f64)
     }
 }
 
@@ -129,8 +130,7 @@ pub fn round(values: &[CellValue]) -> CellValue {
         0
     };
 
-    let factor = 10_f64.powi(decimals);
-    CellValue::Number((num * factor).round() / factor)
+    CellValue::Number(round_with(num, decimals, f64::round))
 }
 
 /// FLOOR - Round down to specified decimal places
@@ -218,6 +218,518 @@ pub fn power(values: &[CellValue]) -> CellValue {
     }
 }
 
+/// Read a numeric argument, propagating errors
+fn number_arg(value: &CellValue) -> Result<f64, CellError> {
+    match value {
+        CellValue::Error(e) => Err(e.clone()),
+        other => other.as_number().ok_or(CellError::InvalidValue),
+    }
+}
+
+/// Read an optional numeric argument, falling back to a default
+fn optional_arg(values: &[CellValue], index: usize, default: f64) -> Result<f64, CellError> {
+    values.get(index).map(number_arg).unwrap_or(Ok(default))
+}
+
+/// Wrap a numeric result, turning NaN and infinities into #NUM!
+fn number_result(result: Result<f64, CellError>) -> CellValue {
+    match result {
+        Ok(n) if n.is_finite() => CellValue::Number(n),
+        Ok(_) => CellValue::Error(CellError::NumError),
+        Err(e) => CellValue::Error(e),
+    }
+}
+
+/// Apply a function of exactly one numeric argument
+fn unary(values: &[CellValue], f: impl Fn(f64) -> Result<f64, CellError>) -> CellValue {
+    if values.len() != 1 {
+        return CellValue::Error(CellError::InvalidValue);
+    }
+    number_result(number_arg(&values[0]).and_then(f))
+}
+
+/// Apply a function of exactly two numeric arguments
+fn binary(values: &[CellValue], f: impl Fn(f64, f64) -> Result<f64, CellError>) -> CellValue {
+    if values.len() != 2 {
+        return CellValue::Error(CellError::InvalidValue);
+    }
+    number_result(number_arg(&values[0]).and_then(|a| f(a, number_arg(&values[1])?)))
+}
+
+/// Round `num` to `digits` decimal places using the given rounding function
+///
+/// The scaled value is first cut to 15 significant digits so that, for
+/// example, 2.675 rounds to 2.68 even though it is stored as 2.67499999...
+fn round_with(num: f64, digits: i32, round: impl Fn(f64) -> f64) -> f64 {
+    let factor = 10_f64.powi(digits);
+    round(round_significant(num * factor)) / factor
+}
+
+/// Shared implementation of TRUNC, ROUNDUP and ROUNDDOWN
+fn round_digits(
+    values: &[CellValue],
+    digits_required: bool,
+    round: impl Fn(f64) -> f64,
+) -> CellValue {
+    if values.is_empty() || values.len() > 2 || (digits_required && values.len() != 2) {
+        return CellValue::Error(CellError::InvalidValue);
+    }
+    number_result(number_arg(&values[0]).and_then(|num| {
+        let digits = optional_arg(values, 1, 0.0)?.trunc() as i32;
+        Ok(round_with(num, digits, &round))
+    }))
+}
+
+/// Largest n whose factorial fits in an f64
+const MAX_FACTORIAL: f64 = 170.0;
+
+/// Truncate a number to an integer argument, rejecting negatives with #NUM!
+fn non_negative_int(n: f64) -> Result<f64, CellError> {
+    let n = n.trunc();
+    if n < 0.0 {
+        Err(CellError::NumError)
+    } else {
+        Ok(n)
+    }
+}
+
+/// MOD - Remainder after division, with the sign of the divisor
+pub fn mod_fn(values: &[CellValue]) -> CellValue {
+    binary(values, |n, d| {
+        if d == 0.0 {
+            return Err(CellError::DivisionByZero);
+        }
+        Ok(n - d * (n / d).floor())
+    })
+}
+
+/// QUOTIENT - Integer portion of a division
+pub fn quotient(values: &[CellValue]) -> CellValue {
+    binary(values, |n, d| {
+        if d == 0.0 {
+            return Err(CellError::DivisionByZero);
+        }
+        Ok((n / d).trunc())
+    })
+}
+
+/// INT - Round down to the nearest integer
+pub fn int(values: &[CellValue]) -> CellValue {
+    unary(values, |n| Ok(n.floor()))
+}
+
+/// TRUNC - Truncate toward zero to a number of decimal places (default 0)
+pub fn trunc(values: &[CellValue]) -> CellValue {
+    round_digits(values, false, f64::trunc)
+}
+
+/// ROUNDUP - Round away from zero to a number of decimal places
+pub fn roundup(values: &[CellValue]) -> CellValue {
+    round_digits(values, true, |n| if n < 0.0 { n.floor() } else { n.ceil() })
+}
+
+/// ROUNDDOWN - Round toward zero to a number of decimal places
+pub fn rounddown(values: &[CellValue]) -> CellValue {
+    round_digits(values, true, f64::trunc)
+}
+
+/// MROUND - Round to the nearest multiple
+pub fn mround(values: &[CellValue]) -> CellValue {
+    binary(values, |n, multiple| {
+        if multiple == 0.0 {
+            return Ok(0.0);
+        }
+        if n != 0.0 && n.signum() != multiple.signum() {
+            return Err(CellError::NumError);
+        }
+        Ok(round_significant(n / multiple).round() * multiple)
+    })
+}
+
+/// SIGN - 1 for positive, -1 for negative, 0 for zero
+pub fn sign(values: &[CellValue]) -> CellValue {
+    unary(values, |n| Ok(if n == 0.0 { 0.0 } else { n.signum() }))
+}
+
+/// PRODUCT - Multiply all numeric values
+pub fn product(values: &[CellValue]) -> CellValue {
+    let mut result: Option<f64> = None;
+
+    for value in values {
+        match value {
+            CellValue::Number(n) => result = Some(result.unwrap_or(1.0) * n),
+            CellValue::Boolean(b) => result = Some(result.unwrap_or(1.0) * if *b { 1.0 } else { 0.0 }),
+            CellValue::Error(e) => return CellValue::Error(e.clone()),
+            _ => {} // Skip empty and text
+        }
+    }
+
+    number_result(Ok(result.unwrap_or(0.0)))
+}
+
+/// SUMSQ - Sum of the squares of numeric values
+pub fn sumsq(values: &[CellValue]) -> CellValue {
+    let mut total = CompensatedSum::new();
+
+    for value in values {
+        match value {
+            CellValue::Number(n) => total.add(n * n),
+            CellValue::Boolean(true) => total.add(1.0),
+            CellValue::Error(e) => return CellValue::Error(e.clone()),
+            _ => {}
+        }
+    }
+
+    number_result(Ok(total.total()))
+}
+
+/// Variance of the numeric values, of a sample or of the whole population
+fn variance(values: &[CellValue], sample: bool) -> Result<f64, CellError> {
+    let mut numbers = Vec::new();
+    for value in values {
+        match value {
+            CellValue::Number(n) => numbers.push(*n),
+            CellValue::Error(e) => return Err(e.clone()),
+            _ => {} // Skip empty, text and boolean, like AVERAGE
+        }
+    }
+
+    let n = numbers.len() as f64;
+    if numbers.len() < if sample { 2 } else { 1 } {
+        return Err(CellError::DivisionByZero);
+    }
+    let mut total = CompensatedSum::new();
+    numbers.iter().for_each(|x| total.add(*x));
+    let mean = total.total() / n;
+    let mut squares = CompensatedSum::new();
+    numbers.iter().for_each(|x| squares.add((x - mean) * (x - mean)));
+    Ok(squares.total() / if sample { n - 1.0 } else { n })
+}
+
+/// VAR / VAR.S - Sample variance
+pub fn var(values: &[CellValue]) -> CellValue {
+    number_result(variance(values, true))
+}
+
+/// VARP / VAR.P - Population variance
+pub fn varp(values: &[CellValue]) -> CellValue {
+    number_result(variance(values, false))
+}
+
+/// STDEV / STDEV.S - Sample standard deviation
+pub fn stdev(values: &[CellValue]) -> CellValue {
+    number_result(variance(values, true).map(f64::sqrt))
+}
+
+/// STDEVP / STDEV.P - Population standard deviation
+pub fn stdevp(values: &[CellValue]) -> CellValue {
+    number_result(variance(values, false).map(f64::sqrt))
+}
+
+/// SUBTOTAL - Aggregate values with the function numbered by the first argument
+///
+/// 1-11 are AVERAGE, COUNT, COUNTA, MAX, MIN, PRODUCT, STDEV, STDEVP, SUM,
+/// VAR and VARP. 101-111 name the same functions; the evaluator leaves out
+/// hidden rows before the values get here.
+pub fn subtotal(values: &[CellValue]) -> CellValue {
+    let Some((code, values)) = values.split_first() else {
+        return CellValue::Error(CellError::InvalidValue);
+    };
+    let code = match code {
+        CellValue::Number(n) => n.trunc() as i64,
+        CellValue::Error(e) => return CellValue::Error(e.clone()),
+        _ => return CellValue::Error(CellError::InvalidValue),
+    };
+    if !(1..=11).contains(&code) && !(101..=111).contains(&code) {
+        return CellValue::Error(CellError::InvalidValue);
+    }
+    match code % 100 {
+        1 => average(values),
+        2 => count(values),
+        3 => counta(values),
+        4 => max(values),
+        5 => min(values),
+        6 => product(values),
+        7 => stdev(values),
+        8 => stdevp(values),
+        9 => sum(values),
+        10 => var(values),
+        _ => varp(values),
+    }
+}
+
+/// SUMPRODUCT - Sum of the products of corresponding entries in equally sized arrays
+///
+/// Entries that are not numbers count as zero.
+pub fn sumproduct(arrays: &[Vec<CellValue>]) -> CellValue {
+    let len = match arrays.first() {
+        Some(first) => first.len(),
+        None => return CellValue::Error(CellError::InvalidValue),
+    };
+    if arrays.iter().any(|a| a.len() != len) {
+        return CellValue::Error(CellError::InvalidValue);
+    }
+
+    let mut total = CompensatedSum::new();
+    for i in 0..len {
+        let mut term = 1.0;
+        for array in arrays {
+            match &array[i] {
+                CellValue::Number(n) => term *= n,
+                CellValue::Error(e) => return CellValue::Error(e.clone()),
+                _ => term = 0.0,
+            }
+        }
+        total.add(term);
+    }
+
+    number_result(Ok(total.total()))
+}
+
+/// EXP - e raised to a power
+pub fn exp(values: &[CellValue]) -> CellValue {
+    unary(values, |n| Ok(n.exp()))
+}
+
+/// LN - Natural logarithm
+pub fn ln(values: &[CellValue]) -> CellValue {
+    unary(values, |n| if n <= 0.0 { Err(CellError::NumError) } else { Ok(n.ln()) })
+}
+
+/// LOG - Logarithm to a base (default 10)
+pub fn log(values: &[CellValue]) -> CellValue {
+    if values.is_empty() || values.len() > 2 {
+        return CellValue::Error(CellError::InvalidValue);
+    }
+    number_result(number_arg(&values[0]).and_then(|n| {
+        let base = optional_arg(values, 1, 10.0)?;
+        if n <= 0.0 || base <= 0.0 {
+            Err(CellError::NumError)
+        } else if base == 1.0 {
+            Err(CellError::DivisionByZero)
+        } else {
+            Ok(n.log(base))
+        }
+    }))
+}
+
+/// LOG10 - Base-10 logarithm
+pub fn log10(values: &[CellValue]) -> CellValue {
+    unary(values, |n| if n <= 0.0 { Err(CellError::NumError) } else { Ok(n.log10()) })
+}
+
+/// PI - The constant pi
+pub fn pi(values: &[CellValue]) -> CellValue {
+    if !values.is_empty() {
+        return CellValue::Error(CellError::InvalidValue);
+    }
+    CellValue::Number(std::f64::consts::PI)
+}
+
+/// SIN - Sine of an angle in radians
+pub fn sin(values: &[CellValue]) -> CellValue {
+    unary(values, |n| Ok(n.sin()))
+}
+
+/// COS - Cosine of an angle in radians
+pub fn cos(values: &[CellValue]) -> CellValue {
+    unary(values, |n| Ok(n.cos()))
+}
+
+/// TAN - Tangent of an angle in radians
+pub fn tan(values: &[CellValue]) -> CellValue {
+    unary(values, |n| Ok(n.tan()))
+}
+
+/// ASIN - Arcsine in radians
+pub fn asin(values: &[CellValue]) -> CellValue {
+    unary(values, |n| if n.abs() > 1.0 { Err(CellError::NumError) } else { Ok(n.asin()) })
+}
+
+/// ACOS - Arccosine in radians
+pub fn acos(values: &[CellValue]) -> CellValue {
+    unary(values, |n| if n.abs() > 1.0 { Err(CellError::NumError) } else { Ok(n.acos()) })
+}
+
+/// ATAN - Arctangent in radians
+pub fn atan(values: &[CellValue]) -> CellValue {
+    unary(values, |n| Ok(n.atan()))
+}
+
+/// ATAN2 - Arctangent of the point (x, y); note Excel's x-first argument order
+pub fn atan2(values: &[CellValue]) -> CellValue {
+    binary(values, |x, y| {
+        if x == 0.0 && y == 0.0 {
+            Err(CellError::DivisionByZero)
+        } else {
+            Ok(y.atan2(x))
+        }
+    })
+}
+
+/// SINH - Hyperbolic sine
+pub fn sinh(values: &[CellValue]) -> CellValue {
+    unary(values, |n| Ok(n.sinh()))
+}
+
+/// COSH - Hyperbolic cosine
+pub fn cosh(values: &[CellValue]) -> CellValue {
+    unary(values, |n| Ok(n.cosh()))
+}
+
+/// TANH - Hyperbolic tangent
+pub fn tanh(values: &[CellValue]) -> CellValue {
+    unary(values, |n| Ok(n.tanh()))
+}
+
+/// DEGREES - Convert radians to degrees
+pub fn degrees(values: &[CellValue]) -> CellValue {
+    unary(values, |n| Ok(n.to_degrees()))
+}
+
+/// RADIANS - Convert degrees to radians
+pub fn radians(values: &[CellValue]) -> CellValue {
+    unary(values, |n| Ok(n.to_radians()))
+}
+
+thread_local! {
+    static RNG_STATE: Cell<u64> = Cell::new(initial_seed());
+}
+
+/// Seed for the random number generator when the host hasn't provided one
+fn initial_seed() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish()
+}
+
+/// Seed the generator behind RAND and RANDBETWEEN
+///
+/// Hosts without OS randomness (such as the browser) should call this once
+/// with a seed from their own source of entropy.
+pub fn seed_random(seed: u64) {
+    RNG_STATE.with(|state| state.set(seed));
+}
+
+/// Next uniformly distributed number in [0, 1) (SplitMix64)
+fn next_random() -> f64 {
+    RNG_STATE.with(|state| {
+        let next = state.get().wrapping_add(0x9E37_79B9_7F4A_7C15);
+        state.set(next);
+        let mut z = next;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    })
+}
+
+/// RAND - Random number in [0, 1)
+pub fn rand(values: &[CellValue]) -> CellValue {
+    if !values.is_empty() {
+        return CellValue::Error(CellError::InvalidValue);
+    }
+    CellValue::Number(next_random())
+}
+
+/// RANDBETWEEN - Random integer between two bounds (inclusive)
+pub fn randbetween(values: &[CellValue]) -> CellValue {
+    binary(values, |low, high| {
+        let (low, high) = (low.ceil(), high.floor());
+        if low > high {
+            return Err(CellError::NumError);
+        }
+        Ok(low + (next_random() * (high - low + 1.0)).floor())
+    })
+}
+
+fn gcd_pair(mut a: f64, mut b: f64) -> f64 {
+    while b != 0.0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Collect the integer arguments of GCD/LCM
+fn integer_args(values: &[CellValue]) -> Result<Vec<f64>, CellError> {
+    values
+        .iter()
+        .filter(|v| !v.is_empty())
+        .map(|v| number_arg(v).and_then(non_negative_int))
+        .collect()
+}
+
+/// GCD - Greatest common divisor
+pub fn gcd(values: &[CellValue]) -> CellValue {
+    number_result(integer_args(values).and_then(|nums| {
+        if nums.is_empty() {
+            return Err(CellError::InvalidValue);
+        }
+        Ok(nums.into_iter().fold(0.0, gcd_pair))
+    }))
+}
+
+/// LCM - Least common multiple
+pub fn lcm(values: &[CellValue]) -> CellValue {
+    number_result(integer_args(values).and_then(|nums| {
+        if nums.is_empty() {
+            return Err(CellError::InvalidValue);
+        }
+        Ok(nums.into_iter().fold(1.0, |acc, n| {
+            if acc == 0.0 || n == 0.0 {
+                0.0
+            } else {
+                acc / gcd_pair(acc, n) * n
+            }
+        }))
+    }))
+}
+
+/// FACT - Factorial
+pub fn fact(values: &[CellValue]) -> CellValue {
+    unary(values, |n| {
+        let n = non_negative_int(n)?;
+        if n > MAX_FACTORIAL {
+            return Err(CellError::NumError);
+        }
+        Ok((1..=n as u64).fold(1.0, |acc, k| acc * k as f64))
+    })
+}
+
+/// COMBIN - Number of combinations of k items from n
+pub fn combin(values: &[CellValue]) -> CellValue {
+    binary(values, |n, k| {
+        let (n, k) = (non_negative_int(n)?, non_negative_int(k)?);
+        if k > n {
+            return Err(CellError::NumError);
+        }
+        // C(n, k) >= 2^k once n >= 2k, so large k always overflows
+        let k = k.min(n - k);
+        if k > 1024.0 {
+            return Err(CellError::NumError);
+        }
+        // Multiply and divide alternately so intermediate values stay small
+        let k = k as u64;
+        Ok((1..=k).fold(1.0, |acc, i| acc * (n - k as f64 + i as f64) / i as f64).round())
+    })
+}
+
+/// PERMUT - Number of permutations of k items from n
+pub fn permut(values: &[CellValue]) -> CellValue {
+    binary(values, |n, k| {
+        let (n, k) = (non_negative_int(n)?, non_negative_int(k)?);
+        if k > n {
+            return Err(CellError::NumError);
+        }
+        // At least k! permutations, which overflows past 170
+        if k > MAX_FACTORIAL {
+            return Err(CellError::NumError);
+        }
+        Ok((0..k as u64).fold(1.0, |acc, i| acc * (n - i as f64)))
+    })
+}
+
 /// Criteria for conditional functions (COUNTIF, SUMIF, etc.)
 #[derive(Debug, Clone)]
 pub enum Criteria {
@@ -281,22 +793,22 @@ impl Criteria {
     pub fn matches(&self, value: &CellValue) -> bool {
         match self {
             Criteria::Equal(target) => match (value, target) {
-                (CellValue::Number(a), CellValue::Number(b)) => (a - b).abs() < 1e-10,
+                (CellValue::Number(a), CellValue::Number(b)) => numbers_equal(*a, *b),
                 (CellValue::Text(a), CellValue::Text(b)) => a.to_lowercase() == b.to_lowercase(),
                 (CellValue::Boolean(a), CellValue::Boolean(b)) => a == b,
                 (CellValue::Empty, CellValue::Text(s)) if s.is_empty() => true,
                 _ => false,
             },
             Criteria::NotEqual(target) => match (value, target) {
-                (CellValue::Number(a), CellValue::Number(b)) => (a - b).abs() >= 1e-10,
+                (CellValue::Number(a), CellValue::Number(b)) => !numbers_equal(*a, *b),
                 (CellValue::Text(a), CellValue::Text(b)) => a.to_lowercase() != b.to_lowercase(),
                 (CellValue::Boolean(a), CellValue::Boolean(b)) => a != b,
                 _ => true,
             },
-            Criteria::GreaterThan(n) => value.as_number().map_or(false, |v| v > *n),
-            Criteria::GreaterThanOrEqual(n) => value.as_number().map_or(false, |v| v >= *n),
-            Criteria::LessThan(n) => value.as_number().map_or(false, |v| v < *n),
-            Criteria::LessThanOrEqual(n) => value.as_number().map_or(false, |v| v <= *n),
+            Criteria::GreaterThan(n) => value.as_number().is_some_and(|v| v > *n),
+            Criteria::GreaterThanOrEqual(n) => value.as_number().is_some_and(|v| v >= *n),
+            Criteria::LessThan(n) => value.as_number().is_some_and(|v| v < *n),
+            Criteria::LessThanOrEqual(n) => value.as_number().is_some_and(|v| v <= *n),
         }
     }
 }
@@ -327,18 +839,18 @@ pub fn sumif(
 
     let sum_values = sum_range.unwrap_or(criteria_range);
 
-    let mut total = 0.0;
+    let mut total = CompensatedSum::new();
     for (i, value) in criteria_range.iter().enumerate() {
         if criteria.matches(value) {
             if let Some(sum_val) = sum_values.get(i) {
                 if let Some(n) = sum_val.as_number() {
-                    total += n;
+                    total.add(n);
                 }
             }
         }
     }
 
-    CellValue::Number(total)
+    CellValue::Number(total.total())
 }
 
 /// AVERAGEIF - Average cells where criteria matches
@@ -355,13 +867,13 @@ pub fn averageif(
 
     let avg_values = average_range.unwrap_or(criteria_range);
 
-    let mut total = 0.0;
+    let mut total = CompensatedSum::new();
     let mut count = 0;
     for (i, value) in criteria_range.iter().enumerate() {
         if criteria.matches(value) {
             if let Some(avg_val) = avg_values.get(i) {
                 if let Some(n) = avg_val.as_number() {
-                    total += n;
+                    total.add(n);
                     count += 1;
                 }
             }
@@ -371,7 +883,7 @@ pub fn averageif(
     if count == 0 {
         CellValue::Error(CellError::DivisionByZero)
     } else {
-        CellValue::Number(total / count as f64)
+        CellValue::Number(total.total() / count as f64)
     }
 }
 
@@ -422,6 +934,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_round() {
         let values = vec![CellValue::Number(3.14159), CellValue::Number(2.0)];
         assert_eq!(round(&values), CellValue::Number(3.14));
@@ -493,4 +1006,162 @@ mod tests {
         // Average where > 3
         assert_eq!(averageif(&values, &CellValue::Text(">3".to_string()), None), CellValue::Number(6.0)); // (4+6+8)/3
     }
+
+    fn num(n: f64) -> CellValue {
+        CellValue::Number(n)
+    }
+
+    fn approx(value: CellValue, expected: f64) -> bool {
+        matches!(value, CellValue::Number(n) if (n - expected).abs() < 1e-12)
+    }
+
+    #[test]
+    fn test_sum_is_compensated() {
+        let values = vec![num(0.1); 10];
+        assert_eq!(sum(&values), num(1.0));
+        assert_eq!(sum(&[num(1e16), num(1.0), num(-1e16)]), num(1.0));
+    }
+
+    #[test]
+    fn test_mod_and_quotient() {
+        assert_eq!(mod_fn(&[num(10.0), num(3.0)]), num(1.0));
+        assert_eq!(mod_fn(&[num(-3.0), num(2.0)]), num(1.0)); // Sign of divisor
+        assert_eq!(mod_fn(&[num(3.0), num(-2.0)]), num(-1.0));
+        assert_eq!(mod_fn(&[num(1.0), num(0.0)]), CellValue::Error(CellError::DivisionByZero));
+        assert_eq!(quotient(&[num(-10.0), num(3.0)]), num(-3.0));
+    }
+
+    #[test]
+    fn test_rounding_family() {
+        assert_eq!(int(&[num(-8.9)]), num(-9.0));
+        assert_eq!(trunc(&[num(-8.9)]), num(-8.0));
+        assert_eq!(trunc(&[num(8.987), num(2.0)]), num(8.98));
+        assert_eq!(roundup(&[num(3.2), num(0.0)]), num(4.0));
+        assert_eq!(roundup(&[num(-5.12345), num(1.0)]), num(-5.2));
+        assert_eq!(roundup(&[num(31415.92654), num(-2.0)]), num(31500.0));
+        assert_eq!(rounddown(&[num(5.12345), num(3.0)]), num(5.123));
+        assert_eq!(rounddown(&[num(-5.12345), num(1.0)]), num(-5.1));
+        assert_eq!(roundup(&[num(3.2)]), CellValue::Error(CellError::InvalidValue));
+
+        // Decimal inputs that aren't exact in binary still round like Excel
+        assert_eq!(round(&[num(2.675), num(2.0)]), num(2.68));
+        assert_eq!(roundup(&[num(0.1 * 3.0), num(1.0)]), num(0.3));
+
+        assert_eq!(mround(&[num(10.0), num(3.0)]), num(9.0));
+        assert_eq!(mround(&[num(-10.0), num(-3.0)]), num(-9.0));
+        assert!(approx(mround(&[num(1.3), num(0.2)]), 1.4));
+        assert_eq!(mround(&[num(5.0), num(-2.0)]), CellValue::Error(CellError::NumError));
+
+        assert_eq!(sign(&[num(-0.5)]), num(-1.0));
+        assert_eq!(sign(&[num(0.0)]), num(0.0));
+    }
+
+    #[test]
+    fn test_products() {
+        assert_eq!(product(&[num(5.0), num(15.0), num(30.0)]), num(2250.0));
+        assert_eq!(product(&[CellValue::Text("x".to_string())]), num(0.0));
+        assert_eq!(sumsq(&[num(3.0), num(4.0)]), num(25.0));
+
+        let a = vec![num(3.0), num(4.0), num(8.0)];
+        let b = vec![num(2.0), CellValue::Text("x".to_string()), num(6.0)];
+        assert_eq!(sumproduct(&[a.clone(), b]), num(54.0));
+        assert_eq!(sumproduct(&[a.clone(), vec![num(1.0)]]), CellValue::Error(CellError::InvalidValue));
+        assert_eq!(sumproduct(&[a]), num(15.0));
+    }
+
+    #[test]
+    fn test_logs_and_exponentials() {
+        assert!(approx(exp(&[num(1.0)]), std::f64::consts::E));
+        assert!(approx(ln(&[num(std::f64::consts::E)]), 1.0));
+        assert_eq!(ln(&[num(0.0)]), CellValue::Error(CellError::NumError));
+        assert!(approx(log(&[num(8.0), num(2.0)]), 3.0));
+        assert!(approx(log(&[num(100.0)]), 2.0));
+        assert_eq!(log(&[num(10.0), num(1.0)]), CellValue::Error(CellError::DivisionByZero));
+        assert_eq!(log10(&[num(1000.0)]), num(3.0));
+        assert_eq!(log10(&[num(-1.0)]), CellValue::Error(CellError::NumError));
+    }
+
+    #[test]
+    fn test_trigonometry() {
+        assert_eq!(pi(&[]), num(std::f64::consts::PI));
+        assert!(approx(sin(&[num(std::f64::consts::PI / 2.0)]), 1.0));
+        assert!(approx(cos(&[num(0.0)]), 1.0));
+        assert!(approx(tan(&[num(std::f64::consts::PI / 4.0)]), 1.0));
+        assert!(approx(asin(&[num(1.0)]), std::f64::consts::PI / 2.0));
+        assert_eq!(acos(&[num(2.0)]), CellValue::Error(CellError::NumError));
+        assert!(approx(atan(&[num(1.0)]), std::f64::consts::PI / 4.0));
+        // ATAN2 takes x first
+        assert!(approx(atan2(&[num(1.0), num(1.0)]), std::f64::consts::PI / 4.0));
+        assert!(approx(atan2(&[num(-1.0), num(0.0)]), std::f64::consts::PI));
+        assert_eq!(atan2(&[num(0.0), num(0.0)]), CellValue::Error(CellError::DivisionByZero));
+        assert!(approx(sinh(&[num(0.0)]), 0.0));
+        assert!(approx(cosh(&[num(0.0)]), 1.0));
+        assert!(approx(tanh(&[num(0.0)]), 0.0));
+        assert!(approx(degrees(&[num(std::f64::consts::PI)]), 180.0));
+        assert!(approx(radians(&[num(180.0)]), std::f64::consts::PI));
+    }
+
+    #[test]
+    fn test_random() {
+        seed_random(42);
+        let first = rand(&[]);
+        seed_random(42);
+        assert_eq!(rand(&[]), first);
+
+        for _ in 0..100 {
+            assert!(matches!(rand(&[]), CellValue::Number(n) if (0.0..1.0).contains(&n)));
+            assert!(matches!(
+                randbetween(&[num(1.0), num(6.0)]),
+                CellValue::Number(n) if (1.0..=6.0).contains(&n) && n.fract() == 0.0
+            ));
+        }
+        assert_eq!(randbetween(&[num(3.0), num(3.0)]), num(3.0));
+        assert_eq!(randbetween(&[num(5.0), num(1.0)]), CellValue::Error(CellError::NumError));
+    }
+
+    #[test]
+    fn test_number_theory() {
+        assert_eq!(gcd(&[num(24.0), num(36.0)]), num(12.0));
+        assert_eq!(gcd(&[num(5.0), num(0.0)]), num(5.0));
+        assert_eq!(gcd(&[num(-1.0)]), CellValue::Error(CellError::NumError));
+        assert_eq!(lcm(&[num(4.0), num(6.0), num(10.0)]), num(60.0));
+        assert_eq!(lcm(&[num(3.0), num(0.0)]), num(0.0));
+
+        assert_eq!(fact(&[num(5.0)]), num(120.0));
+        assert_eq!(fact(&[num(0.0)]), num(1.0));
+        assert_eq!(fact(&[num(1.9)]), num(1.0));
+        assert_eq!(fact(&[num(-1.0)]), CellValue::Error(CellError::NumError));
+        assert_eq!(fact(&[num(171.0)]), CellValue::Error(CellError::NumError));
+
+        assert_eq!(combin(&[num(8.0), num(2.0)]), num(28.0));
+        assert_eq!(combin(&[num(2.0), num(3.0)]), CellValue::Error(CellError::NumError));
+        assert_eq!(permut(&[num(100.0), num(3.0)]), num(970200.0));
+        assert_eq!(permut(&[num(3.0), num(0.0)]), num(1.0));
+    }
+
+    #[test]
+    fn test_variance_and_subtotal() {
+        let values: Vec<_> = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]
+            .into_iter()
+            .map(CellValue::Number)
+            .collect();
+        assert_eq!(varp(&values), CellValue::Number(4.0));
+        assert_eq!(stdevp(&values), CellValue::Number(2.0));
+        assert_eq!(var(&values[..2]), CellValue::Number(2.0));
+        assert_eq!(
+            stdev(&[CellValue::Number(1.0)]),
+            CellValue::Error(CellError::DivisionByZero)
+        );
+
+        let with_code = |code: f64| {
+            let mut args = vec![CellValue::Number(code)];
+            args.extend(values.iter().cloned());
+            subtotal(&args)
+        };
+        assert_eq!(with_code(9.0), CellValue::Number(40.0));
+        assert_eq!(with_code(109.0), CellValue::Number(40.0));
+        assert_eq!(with_code(104.0), CellValue::Number(9.0));
+        assert_eq!(with_code(2.0), CellValue::Number(8.0));
+        assert_eq!(with_code(12.0), CellValue::Error(CellError::InvalidValue));
+    }
 }
@@ -1,4 +1,5 @@
 pub mod datetime;
+pub mod information;
 pub mod logical;
 pub mod lookup;
 pub mod math;
@@ -139,6 +139,20 @@ pub fn mid(values: &[CellValue]) -> CellValue {
     CellValue::Text(result)
 }
 
+/// HYPERLINK - Show a label for a link
+///
+/// The link itself is the first argument; the cell shows the label, or the
+/// link text when there is none. `CalcEngine::hyperlink_at` resolves the
+/// target for formulas that are a `HYPERLINK` call.
+pub fn hyperlink(values: &[CellValue]) -> CellValue {
+    match values {
+        [_, label] => label.clone(),
+        [CellValue::Error(e)] => CellValue::Error(e.clone()),
+        [location] => CellValue::Text(location.as_text()),
+        _ => CellValue::Error(CellError::InvalidValue),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -184,4 +198,15 @@ mod tests {
         let result = mid(&[text, CellValue::Number(7.0), CellValue::Number(5.0)]);
         assert_eq!(result, CellValue::Text("World".to_string()));
     }
+
+    #[test]
+    fn test_hyperlink() {
+        let url = CellValue::Text("https://example.com".to_string());
+        assert_eq!(hyperlink(std::slice::from_ref(&url)), url);
+
+        let result = hyperlink(&[url, CellValue::Number(42.0)]);
+        assert_eq!(result, CellValue::Number(42.0));
+
+        assert_eq!(hyperlink(&[]), CellValue::Error(CellError::InvalidValue));
+    }
 }
@@ -19,6 +19,7 @@ pub enum Token {
     Power,   // ^
     Percent, // %
     Concat,  // &
+    At,      // @ (implicit intersection)
 
     // Comparison
     Equal,        // =
@@ -34,7 +35,10 @@ pub enum Token {
     Comma,
     Colon,
     Semicolon,
-    Exclaim, // ! for sheet references
+    Backslash,  // \ between array columns in some locales
+    LeftBrace,  // { opens an array constant
+    RightBrace, // }
+    Exclaim,    // ! for sheet references
 
     // Identifier (function names)
     Identifier(String),
@@ -137,6 +141,10 @@ impl Lexer {
                 self.advance();
                 Ok(Token::Concat)
             }
+            '@' => {
+                self.advance();
+                Ok(Token::At)
+            }
 
             // Comparison
             '=' => {
@@ -188,6 +196,18 @@ impl Lexer {
                 self.advance();
                 Ok(Token::Semicolon)
             }
+            '\\' => {
+                self.advance();
+                Ok(Token::Backslash)
+            }
+            '{' => {
+                self.advance();
+                Ok(Token::LeftBrace)
+            }
+            '}' => {
+                self.advance();
+                Ok(Token::RightBrace)
+            }
             '!' => {
                 self.advance();
                 Ok(Token::Exclaim)
@@ -287,10 +307,21 @@ impl Lexer {
                     s.push(c);
                     self.advance();
                 }
+                // Dotted function names such as ERROR.TYPE
+                '.' if has_letter && !has_dollar => {
+                    s.push(c);
+                    self.advance();
+                }
                 _ => break,
             }
         }
 
+        // A name followed by '(' is a function call, even if it looks like
+        // a boolean or cell reference (TRUE(), LOG10, ATAN2)
+        if self.peek() == Some('(') && !has_dollar {
+            return Ok(Token::Identifier(s));
+        }
+
         // Check for boolean
         match s.as_str() {
             "TRUE" => return Ok(Token::Boolean(true)),
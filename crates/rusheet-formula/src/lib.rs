@@ -1,21 +1,43 @@
+pub mod array;
 pub mod ast;
+pub mod calc;
 pub mod dependency;
 pub mod evaluator;
+pub mod formatter;
 pub mod functions;
 pub mod lexer;
+pub mod limits;
 pub mod parser;
 pub mod parser_nom;
 pub mod reference_shifter;
+pub mod sheet_refs;
+pub mod structured;
+pub mod trace;
+pub mod what_if;
 
-pub use ast::{BinaryOp, Expr, UnaryOp};
+pub use array::Array;
+pub use ast::{BinaryOp, Expr, StructuredRef, TableArea, UnaryOp};
+pub use calc::{CalcEngine, ChangeReport};
 pub use dependency::DependencyGraph;
-pub use evaluator::{Evaluator, CrossSheetEvaluator};
+pub use evaluator::{Evaluator, CrossSheetEvaluator, RowVisibility};
+pub use formatter::{format_expr, format_formula, FormatOptions};
+pub use functions::datetime::DateContext;
 pub use lexer::{Lexer, Token};
+pub use limits::EvalLimits;
 pub use parser::Parser;
-pub use parser_nom::NomParser;
-pub use reference_shifter::{shift_formula_cols, shift_formula_rows};
+pub use parser_nom::{ArraySeparators, NomParser};
+pub use reference_shifter::{
+    move_expr, move_formula, move_formula_in, shift_formula_cols, shift_formula_rows,
+    shift_location_cols, shift_location_rows, translate_expr, translate_formula,
+};
+pub use sheet_refs::{delete_sheet, remove_sheet_from_formula, rename_sheet, rename_sheet_in_formula};
+pub use structured::{
+    has_structured_refs, rename_table, rename_table_in_formula, resolve_structured_refs,
+};
+pub use trace::TraceNode;
+pub use what_if::{GoalSeekOptions, GoalSeekResult};
 
-use rusheet_core::{CellError, CellValue};
+use rusheet_core::{CellContent, CellError, CellValue};
 
 /// Parse and evaluate a formula expression
 ///
@@ -58,6 +80,42 @@ pub fn evaluate_formula_cross_sheet(
     evaluator.evaluate(&ast)
 }
 
+/// Parse and evaluate a formula with cross-sheet references and access to
+/// raw cell contents
+///
+/// `get_cell_content` lets functions such as ISFORMULA see whether a cell
+/// holds a formula rather than only its computed value. `date_context`
+/// supplies the workbook's date system and the clock for TODAY/NOW.
+/// `current_cell` is the (row, col) holding the formula, which `@` and
+/// `implicit_intersection` use to pick values in line with it.
+pub fn evaluate_formula_with_content(
+    expression: &str,
+    current_sheet: Option<&str>,
+    current_cell: (u32, u32),
+    implicit_intersection: bool,
+    date_context: DateContext,
+    get_cell_value: impl Fn(Option<&str>, u32, u32) -> CellValue,
+    get_cell_content: impl Fn(Option<&str>, u32, u32) -> Option<CellContent>,
+) -> CellValue {
+    let parser = NomParser::new();
+    let ast = match parser.parse(expression) {
+        Ok(ast) => ast,
+        Err(_) => return CellValue::Error(CellError::InvalidValue),
+    };
+
+    let evaluator = if let Some(sheet) = current_sheet {
+        CrossSheetEvaluator::with_sheet(get_cell_value, sheet)
+    } else {
+        CrossSheetEvaluator::new(get_cell_value)
+    };
+    evaluator
+        .with_cell_content(get_cell_content)
+        .with_date_context(date_context)
+        .with_current_cell(current_cell.0, current_cell.1)
+        .with_implicit_intersection(implicit_intersection)
+        .evaluate(&ast)
+}
+
 /// Extract cell references from a formula expression
 ///
 /// Uses the nom-based parser for robust parsing.
@@ -109,9 +167,12 @@ fn collect_references(expr: &Expr) -> Vec<(u32, u32)> {
                 }
             }
         }
-        Expr::Binary { left, right, .. } => {
-            refs.extend(collect_references(left));
-            refs.extend(collect_references(right));
+        Expr::Binary { .. } => {
+            let (first, links) = expr.chain();
+            refs.extend(collect_references(first));
+            for (_, right) in links {
+                refs.extend(collect_references(right));
+            }
         }
         Expr::Unary { operand, .. } => {
             refs.extend(collect_references(operand));
@@ -124,6 +185,15 @@ fn collect_references(expr: &Expr) -> Vec<(u32, u32)> {
         Expr::Grouped(inner) => {
             refs.extend(collect_references(inner));
         }
+        Expr::Union(areas) => {
+            for area in areas {
+                refs.extend(collect_references(area));
+            }
+        }
+        Expr::Intersection { left, right } => {
+            refs.extend(collect_references(left));
+            refs.extend(collect_references(right));
+        }
         _ => {}
     }
 
@@ -131,7 +201,7 @@ fn collect_references(expr: &Expr) -> Vec<(u32, u32)> {
 }
 
 /// Recursively collect cell references from an AST, including sheet context
-fn collect_references_cross_sheet(expr: &Expr, sheet: Option<&str>) -> Vec<(Option<String>, u32, u32)> {
+pub(crate) fn collect_references_cross_sheet(expr: &Expr, sheet: Option<&str>) -> Vec<(Option<String>, u32, u32)> {
     let mut refs = Vec::new();
 
     match expr {
@@ -154,9 +224,12 @@ fn collect_references_cross_sheet(expr: &Expr, sheet: Option<&str>) -> Vec<(Opti
         Expr::SheetRef { sheet_name, reference } => {
             refs.extend(collect_references_cross_sheet(reference, Some(sheet_name)));
         }
-        Expr::Binary { left, right, .. } => {
-            refs.extend(collect_references_cross_sheet(left, sheet));
-            refs.extend(collect_references_cross_sheet(right, sheet));
+        Expr::Binary { .. } => {
+            let (first, links) = expr.chain();
+            refs.extend(collect_references_cross_sheet(first, sheet));
+            for (_, right) in links {
+                refs.extend(collect_references_cross_sheet(right, sheet));
+            }
         }
         Expr::Unary { operand, .. } => {
             refs.extend(collect_references_cross_sheet(operand, sheet));
@@ -169,6 +242,15 @@ fn collect_references_cross_sheet(expr: &Expr, sheet: Option<&str>) -> Vec<(Opti
         Expr::Grouped(inner) => {
             refs.extend(collect_references_cross_sheet(inner, sheet));
         }
+        Expr::Union(areas) => {
+            for area in areas {
+                refs.extend(collect_references_cross_sheet(area, sheet));
+            }
+        }
+        Expr::Intersection { left, right } => {
+            refs.extend(collect_references_cross_sheet(left, sheet));
+            refs.extend(collect_references_cross_sheet(right, sheet));
+        }
         _ => {}
     }
 
@@ -0,0 +1,54 @@
+//! Resource limits for parsing and evaluating untrusted formulas
+//!
+//! Every limit surfaces as an error value in the cell rather than a panic
+//! or a stack overflow:
+//!
+//! | Limit               | Exceeded when                              | Result    |
+//! |---------------------|--------------------------------------------|-----------|
+//! | `max_nesting`       | parentheses or calls nest deeper than this | `#CALC!`  |
+//! | `max_depth`         | the expression tree is deeper than this    | `#CALC!`  |
+//! | `max_length`        | the formula text is longer than this       | `#CALC!`  |
+//! | `max_steps`         | evaluation takes more steps than this      | `#CALC!`  |
+//! | `max_string_length` | a text result is longer than this          | `#VALUE!` |
+//! | `max_array_size`    | a range or array result holds more cells   | `#NUM!`   |
+
+pub(crate) const DEFAULT_MAX_NESTING: usize = 64;
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 512;
+pub(crate) const DEFAULT_MAX_LENGTH: usize = 8_192;
+
+/// Limits applied while parsing and evaluating one formula
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvalLimits {
+    /// Deepest nesting of parentheses, function calls, prefix operators and
+    /// `^` chains, which the parser recurses through
+    pub max_nesting: usize,
+    /// Deepest expression tree a formula may have, which the evaluator
+    /// recurses through; a chain such as `A1+A2+...` counts as one level
+    pub max_depth: usize,
+    /// Longest formula text, in characters, which bounds how long operator
+    /// chains can grow
+    pub max_length: usize,
+    /// Most evaluation steps per formula, where each sub-expression evaluated
+    /// and each cell read from a range is one step
+    pub max_steps: u64,
+    /// Longest text a formula may produce, in characters
+    pub max_string_length: usize,
+    /// Most cells a range or array operation may hold
+    pub max_array_size: usize,
+}
+
+impl Default for EvalLimits {
+    /// Limits generous enough for any realistic sheet: nesting matches
+    /// Excel's 64 levels, formulas its 8,192 characters, text its 32,767
+    /// characters per cell and arrays may hold about four full columns
+    fn default() -> Self {
+        Self {
+            max_nesting: DEFAULT_MAX_NESTING,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_length: DEFAULT_MAX_LENGTH,
+            max_steps: 10_000_000,
+            max_string_length: 32_767,
+            max_array_size: 1 << 22,
+        }
+    }
+}
@@ -1,11 +1,13 @@
 use crate::ast::{BinaryOp, Expr, UnaryOp};
 use crate::lexer::Token;
+use crate::parser_nom::ArraySeparators;
 use rusheet_core::col_from_label;
 
 /// Parser for formula expressions
 pub struct Parser {
     tokens: Vec<Token>,
     position: usize,
+    array_separators: ArraySeparators,
 }
 
 impl Parser {
@@ -13,9 +15,16 @@ impl Parser {
         Self {
             tokens,
             position: 0,
+            array_separators: ArraySeparators::STANDARD,
         }
     }
 
+    /// Read array constants with the given separators
+    pub fn with_array_separators(mut self, separators: ArraySeparators) -> Self {
+        self.array_separators = separators;
+        self
+    }
+
     /// Parse the token stream into an AST
     pub fn parse(&mut self) -> Result<Expr, String> {
         let expr = self.parse_expression()?;
@@ -156,6 +165,11 @@ impl Parser {
                 let operand = self.parse_unary()?;
                 Ok(Expr::unary(UnaryOp::Pos, operand))
             }
+            Token::At => {
+                self.advance();
+                let operand = self.parse_unary()?;
+                Ok(Expr::unary(UnaryOp::ImplicitIntersection, operand))
+            }
             _ => self.parse_postfix(),
         }
     }
@@ -211,10 +225,51 @@ impl Parser {
                 self.advance();
                 Ok(Expr::Error(e))
             }
+            Token::LeftBrace => {
+                self.advance();
+                self.parse_array()
+            }
             _ => Err(format!("Unexpected token: {:?}", self.peek())),
         }
     }
 
+    /// Parse an array constant after its opening brace
+    fn parse_array(&mut self) -> Result<Expr, String> {
+        let column = separator_token(self.array_separators.column)?;
+        let row = separator_token(self.array_separators.row)?;
+
+        let mut rows = vec![vec![self.parse_array_element()?]];
+        loop {
+            let token = self.advance().clone();
+            match token {
+                Token::RightBrace => break,
+                t if t == column => rows.last_mut().unwrap().push(self.parse_array_element()?),
+                t if t == row => rows.push(vec![self.parse_array_element()?]),
+                t => return Err(format!("Expected separator or '}}' in array, got {:?}", t)),
+            }
+        }
+
+        if rows.iter().any(|r| r.len() != rows[0].len()) {
+            return Err("Array rows must have the same number of columns".to_string());
+        }
+        Ok(Expr::Array(rows))
+    }
+
+    fn parse_array_element(&mut self) -> Result<Expr, String> {
+        let negative = matches!(self.peek(), Token::Minus);
+        if negative {
+            self.advance();
+        }
+        match self.advance().clone() {
+            Token::Number(n) if negative => Ok(Expr::Number(-n)),
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::String(s) if !negative => Ok(Expr::String(s)),
+            Token::Boolean(b) if !negative => Ok(Expr::Boolean(b)),
+            Token::Error(e) if !negative => Ok(Expr::Error(e)),
+            t => Err(format!("Array elements must be literals, got {:?}", t)),
+        }
+    }
+
     fn parse_cell_ref_or_range(&mut self, ref_str: &str) -> Result<Expr, String> {
         let cell_ref = parse_cell_reference(ref_str)?;
 
@@ -265,6 +320,16 @@ impl Parser {
     }
 }
 
+/// The token an array separator character lexes to
+fn separator_token(separator: char) -> Result<Token, String> {
+    match separator {
+        ',' => Ok(Token::Comma),
+        ';' => Ok(Token::Semicolon),
+        '\\' => Ok(Token::Backslash),
+        c => Err(format!("Unsupported array separator: {}", c)),
+    }
+}
+
 /// Parse a cell reference string (e.g., "A1", "$B$2") into an Expr::CellRef
 fn parse_cell_reference(ref_str: &str) -> Result<Expr, String> {
     let mut chars = ref_str.chars().peekable();
@@ -402,4 +467,31 @@ mod tests {
         let expr = parse("(1 + 2) * 3").unwrap();
         assert!(matches!(expr, Expr::Binary { op: BinaryOp::Mul, .. }));
     }
+
+    #[test]
+    fn test_array_constant() {
+        let expr = parse("{1,-2;\"a\",TRUE}").unwrap();
+        let rows = vec![
+            vec![Expr::Number(1.0), Expr::Number(-2.0)],
+            vec![Expr::String("a".to_string()), Expr::Boolean(true)],
+        ];
+        assert_eq!(expr, Expr::Array(rows));
+        assert!(parse("{1,2;3}").is_err());
+        assert!(parse("{A1}").is_err());
+
+        let tokens = Lexer::new("{1\\2;3\\4}").tokenize().unwrap();
+        let separators = ArraySeparators { column: '\\', row: ';' };
+        let expr = Parser::new(tokens).with_array_separators(separators).parse();
+        assert_eq!(expr.unwrap(), parse("{1,2;3,4}").unwrap());
+        assert!(parse("{1\\2}").is_err());
+    }
+
+    #[test]
+    fn test_implicit_intersection() {
+        let expr = parse("@A1:A3").unwrap();
+        assert!(matches!(
+            expr,
+            Expr::Unary { op: UnaryOp::ImplicitIntersection, .. }
+        ));
+    }
 }
@@ -6,14 +6,16 @@
 use nom::{
     branch::alt,
     bytes::complete::{tag, tag_no_case, take_while, take_while1},
-    character::complete::{char, multispace0, one_of},
-    combinator::{map, opt, recognize, value},
-    multi::{fold_many0, many0, separated_list0},
-    sequence::{delimited, pair, tuple},
+    character::complete::{char, multispace0, multispace1, one_of, satisfy},
+    combinator::{map, map_opt, not, opt, peek, recognize, value, verify},
+    multi::{many0, separated_list0, separated_list1},
+    sequence::{delimited, pair, preceded, terminated, tuple},
     IResult,
 };
+use std::cell::Cell;
 
-use crate::ast::{BinaryOp, Expr, UnaryOp};
+use crate::ast::{BinaryOp, Expr, StructuredRef, TableArea, UnaryOp};
+use crate::limits::{EvalLimits, DEFAULT_MAX_DEPTH, DEFAULT_MAX_LENGTH, DEFAULT_MAX_NESTING};
 use rusheet_core::CellError;
 
 // =============================================================================
@@ -24,6 +26,18 @@ use rusheet_core::CellError;
 pub struct ParseError {
     pub message: String,
     pub position: usize,
+    pub kind: ParseErrorKind,
+}
+
+/// Why a formula failed to parse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// The formula is not valid syntax
+    Syntax,
+    /// The formula nests deeper than the parser's nesting or depth limit
+    TooDeep,
+    /// The formula is longer than the parser's length limit
+    TooLong,
 }
 
 impl std::fmt::Display for ParseError {
@@ -34,10 +48,82 @@ impl std::fmt::Display for ParseError {
 
 impl std::error::Error for ParseError {}
 
+// =============================================================================
+// Configuration
+// =============================================================================
+
+/// Separators between the elements of array constants such as `{1,2;3,4}`
+///
+/// Locales where the comma is the decimal separator write columns with
+/// `\` instead. Each separator is one of `,`, `;` and `\`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArraySeparators {
+    /// Between the columns of a row
+    pub column: char,
+    /// Between rows
+    pub row: char,
+}
+
+impl ArraySeparators {
+    /// `,` between columns and `;` between rows, as formulas are stored
+    pub const STANDARD: Self = Self { column: ',', row: ';' };
+}
+
+impl Default for ArraySeparators {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
 // =============================================================================
 // Helper Combinators
 // =============================================================================
 
+thread_local! {
+    /// Nesting levels the parse in progress on this thread may still descend
+    static NESTING_LEFT: Cell<usize> = const { Cell::new(DEFAULT_MAX_NESTING) };
+    /// Deepest tree the parse in progress on this thread may build
+    static MAX_DEPTH: Cell<usize> = const { Cell::new(DEFAULT_MAX_DEPTH) };
+    /// Array constant separators for the parse in progress on this thread
+    static ARRAY_SEPARATORS: Cell<ArraySeparators> = const { Cell::new(ArraySeparators::STANDARD) };
+}
+
+fn too_deep(input: &str) -> nom::Err<nom::error::Error<&str>> {
+    nom::Err::Failure(nom::error::Error::new(input, nom::error::ErrorKind::TooLarge))
+}
+
+/// Fail if a tree `depth` levels deep would exceed the depth limit
+///
+/// Operator chains and repeated `%` deepen the tree in a loop rather than
+/// through recursion, so they are checked as they grow. A chain of binary
+/// operators stays at one level however long it gets; the length limit is
+/// what keeps it short enough to walk and drop without overflowing the stack.
+fn check_depth(input: &str, depth: usize) -> Result<(), nom::Err<nom::error::Error<&str>>> {
+    if depth > MAX_DEPTH.get() {
+        Err(too_deep(input))
+    } else {
+        Ok(())
+    }
+}
+
+/// Run `parser` one nesting level deeper
+///
+/// Fails without backtracking once the nesting limit is reached, so deeply
+/// nested input can't overflow the stack of these recursive parsers.
+fn nested<'a, O>(
+    input: &'a str,
+    parser: impl FnOnce(&'a str) -> IResult<&'a str, O>,
+) -> IResult<&'a str, O> {
+    let left = NESTING_LEFT.get();
+    if left == 0 {
+        return Err(too_deep(input));
+    }
+    NESTING_LEFT.set(left - 1);
+    let result = parser(input);
+    NESTING_LEFT.set(left);
+    result
+}
+
 /// Skip whitespace
 fn ws<'a, F, O>(inner: F) -> impl FnMut(&'a str) -> IResult<&'a str, O>
 where
@@ -102,11 +188,19 @@ fn parse_string(input: &str) -> IResult<&str, Expr> {
 /// Parse a boolean literal
 fn parse_boolean(input: &str) -> IResult<&str, Expr> {
     alt((
-        value(Expr::Boolean(true), tag_no_case("TRUE")),
-        value(Expr::Boolean(false), tag_no_case("FALSE")),
+        value(Expr::Boolean(true), terminated(tag_no_case("TRUE"), token_end)),
+        value(Expr::Boolean(false), terminated(tag_no_case("FALSE"), token_end)),
     ))(input)
 }
 
+/// Succeed only if the previous token isn't the prefix of a longer name,
+/// so that TRUNC( isn't read as TRUE and LOG10( isn't read as a cell reference
+fn token_end(input: &str) -> IResult<&str, ()> {
+    not(satisfy(|c: char| {
+        c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '('
+    }))(input)
+}
+
 /// Parse an error literal
 fn parse_error_literal(input: &str) -> IResult<&str, Expr> {
     alt((
@@ -117,6 +211,7 @@ fn parse_error_literal(input: &str) -> IResult<&str, Expr> {
         value(Expr::Error(CellError::NotAvailable), tag("#N/A")),
         value(Expr::Error(CellError::NullError), tag("#NULL!")),
         value(Expr::Error(CellError::NumError), tag("#NUM!")),
+        value(Expr::Error(CellError::CalcError), tag("#CALC!")),
     ))(input)
 }
 
@@ -135,7 +230,7 @@ fn parse_cell_ref(input: &str) -> IResult<&str, Expr> {
     let (input, abs_col) = opt(char('$'))(input)?;
     let (input, col_letters) = take_while1(|c: char| c.is_ascii_alphabetic())(input)?;
     let (input, abs_row) = opt(char('$'))(input)?;
-    let (input, row_digits) = take_while1(|c: char| c.is_ascii_digit())(input)?;
+    let (input, row_digits) = terminated(take_while1(|c: char| c.is_ascii_digit()), token_end)(input)?;
 
     let col = col_letters_to_index(col_letters);
     let row: u32 = row_digits.parse::<u32>().unwrap_or(1).saturating_sub(1);
@@ -151,9 +246,12 @@ fn parse_cell_ref(input: &str) -> IResult<&str, Expr> {
     ))
 }
 
-/// Parse an identifier (function name)
+/// Parse an identifier (function name, e.g. SUM or ERROR.TYPE)
 fn parse_identifier(input: &str) -> IResult<&str, &str> {
-    take_while1(|c: char| c.is_ascii_alphanumeric() || c == '_')(input)
+    recognize(pair(
+        take_while1(|c: char| c.is_ascii_alphanumeric() || c == '_'),
+        take_while(|c: char| c.is_ascii_alphanumeric() || c == '_' || c == '.'),
+    ))(input)
 }
 
 /// Parse a sheet name (quoted or unquoted)
@@ -180,6 +278,137 @@ fn parse_sheet_name(input: &str) -> IResult<&str, String> {
     ))(input)
 }
 
+// =============================================================================
+// Structured References
+// =============================================================================
+
+/// Parse a structured table reference: `Sales[Amount]`, `Sales[@Amount]`,
+/// `Sales[#Totals]`, `Sales[[#Totals],[Amount]]`, `Sales[[Jan]:[Mar]]`, or
+/// `[@Amount]` for the table the formula is in
+fn parse_structured_ref(input: &str) -> IResult<&str, Expr> {
+    let table_name = recognize(pair(
+        satisfy(|c: char| c.is_alphabetic() || c == '_'),
+        take_while(|c: char| c.is_alphanumeric() || c == '_' || c == '.'),
+    ));
+    let (input, table) = opt(terminated(table_name, peek(char('['))))(input)?;
+    let (input, (area, columns)) = delimited(char('['), structured_body, char(']'))(input)?;
+
+    Ok((
+        input,
+        Expr::Structured(StructuredRef {
+            table: table.map(str::to_string),
+            area,
+            columns,
+        }),
+    ))
+}
+
+type ColumnSpan = (String, String);
+
+/// Parse the inside of a structured reference's outer brackets
+fn structured_body(input: &str) -> IResult<&str, (TableArea, Option<ColumnSpan>)> {
+    let this_row = map(
+        preceded(
+            char('@'),
+            opt(alt((column_span, map(column_name, |c| (c.clone(), c))))),
+        ),
+        |columns| (TableArea::ThisRow, columns),
+    );
+    alt((
+        this_row,
+        map_opt(separated_list1(ws(char(',')), structured_item), combine_items),
+        map(area_specifier, |area| (area, None)),
+        map(column_name, |c| (TableArea::Data, Some((c.clone(), c)))),
+        map(multispace0, |_| (TableArea::Data, None)),
+    ))(input)
+}
+
+enum StructuredItem {
+    Area(TableArea),
+    Columns(ColumnSpan),
+}
+
+/// Parse `[#Totals]` or `[Amount]` / `[Jan]:[Mar]` in a list of specifiers
+fn structured_item(input: &str) -> IResult<&str, StructuredItem> {
+    alt((
+        map(delimited(char('['), area_specifier, char(']')), StructuredItem::Area),
+        map(column_span, StructuredItem::Columns),
+    ))(input)
+}
+
+/// Combine a list of specifiers: areas first, then at most one column span
+fn combine_items(items: Vec<StructuredItem>) -> Option<(TableArea, Option<ColumnSpan>)> {
+    let mut areas = Vec::new();
+    let mut columns = None;
+    for item in items {
+        match item {
+            StructuredItem::Area(_) if columns.is_some() => return None,
+            StructuredItem::Area(area) => areas.push(area),
+            StructuredItem::Columns(_) if columns.is_some() => return None,
+            StructuredItem::Columns(span) => columns = Some(span),
+        }
+    }
+    let area = match areas.as_slice() {
+        [] => TableArea::Data,
+        [area] => *area,
+        [TableArea::Headers, TableArea::Data] => TableArea::HeadersAndData,
+        [TableArea::Data, TableArea::Totals] => TableArea::DataAndTotals,
+        _ => return None,
+    };
+    Some((area, columns))
+}
+
+/// Parse a special item specifier such as `#Totals`
+fn area_specifier(input: &str) -> IResult<&str, TableArea> {
+    alt((
+        value(TableArea::All, tag_no_case("#All")),
+        value(TableArea::Data, tag_no_case("#Data")),
+        value(TableArea::Headers, tag_no_case("#Headers")),
+        value(TableArea::Totals, tag_no_case("#Totals")),
+        value(TableArea::ThisRow, tag_no_case("#This Row")),
+    ))(input)
+}
+
+/// Parse `[Amount]` or `[Jan]:[Mar]`
+fn column_span(input: &str) -> IResult<&str, ColumnSpan> {
+    let bracketed = || delimited(char('['), column_name, char(']'));
+    let (input, first) = bracketed()(input)?;
+    let (input, last) = opt(preceded(ws(char(':')), bracketed()))(input)?;
+    let last = last.unwrap_or_else(|| first.clone());
+    Ok((input, (first, last)))
+}
+
+/// Parse a column name up to the closing `]`
+///
+/// `'` escapes the character after it, so `'[`, `']`, `'#` and `''` are
+/// literal. A name can't start with `#` or `@`.
+fn column_name(input: &str) -> IResult<&str, String> {
+    let mut name = String::new();
+    let mut chars = input.char_indices();
+    let mut end = input.len();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' => match chars.next() {
+                Some((_, escaped)) => name.push(escaped),
+                None => break,
+            },
+            ']' => {
+                end = i;
+                break;
+            }
+            '[' => break,
+            '#' | '@' if i == 0 => break,
+            c => name.push(c),
+        }
+    }
+    if name.is_empty() || end == input.len() {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Verify,
+        )));
+    }
+    Ok((&input[end..], name))
+}
 
 // =============================================================================
 // Operator Parsers
@@ -223,13 +452,14 @@ fn parse_primary(input: &str) -> IResult<&str, Expr> {
     let (input, _) = multispace0(input)?;
 
     alt((
-        // Parenthesized expression
-        map(
-            delimited(char('('), parse_expression, char(')')),
-            |e| Expr::Grouped(Box::new(e)),
-        ),
+        // Parenthesized expression or reference union
+        parse_grouped,
+        // Array constant
+        parse_array,
         // Error literal
         parse_error_literal,
+        // Structured reference (before cell refs, which a table name can look like)
+        parse_structured_ref,
         // Boolean (before identifier to avoid conflict)
         parse_boolean,
         // String literal
@@ -241,6 +471,55 @@ fn parse_primary(input: &str) -> IResult<&str, Expr> {
     ))(input)
 }
 
+/// Parse a parenthesized expression
+///
+/// A comma inside the parentheses is the union operator, so `(A1:A5,C1:C5)`
+/// is a single two-area reference. Every part of a union must be a reference.
+fn parse_grouped(input: &str) -> IResult<&str, Expr> {
+    let (input, mut items) = delimited(
+        char('('),
+        verify(
+            separated_list1(ws(char(',')), parse_expression),
+            |items: &Vec<Expr>| items.len() == 1 || items.iter().all(Expr::is_reference),
+        ),
+        char(')'),
+    )(input)?;
+
+    let inner = if items.len() == 1 {
+        items.remove(0)
+    } else {
+        Expr::Union(items)
+    };
+    Ok((input, Expr::Grouped(Box::new(inner))))
+}
+
+/// Parse an array constant (e.g., {1,2,3;4,5,6})
+///
+/// Columns and rows are separated as the parser's [`ArraySeparators`] say.
+/// Elements must be literals and every row must have the same number of
+/// columns.
+fn parse_array(input: &str) -> IResult<&str, Expr> {
+    let separators = ARRAY_SEPARATORS.get();
+    let element = ws(alt((
+        parse_error_literal,
+        parse_boolean,
+        parse_string,
+        parse_number,
+    )));
+    let row = separated_list1(char(separators.column), element);
+
+    map(
+        delimited(
+            char('{'),
+            verify(separated_list1(char(separators.row), row), |rows: &Vec<Vec<Expr>>| {
+                rows.iter().all(|r| r.len() == rows[0].len())
+            }),
+            char('}'),
+        ),
+        Expr::Array,
+    )(input)
+}
+
 /// Parse either a cell reference, range, sheet reference, or function call
 fn parse_cell_ref_or_function(input: &str) -> IResult<&str, Expr> {
     // First, try to parse as sheet reference (Sheet1!A1 or 'Sheet Name'!A1:B2)
@@ -248,9 +527,9 @@ fn parse_cell_ref_or_function(input: &str) -> IResult<&str, Expr> {
         // Check for ! after sheet name
         if let Ok((remaining, _)) = char::<&str, nom::error::Error<&str>>('!')(remaining) {
             // Parse the cell reference or range after !
-            if let Ok((remaining, cell_ref)) = parse_cell_ref(remaining) {
+            if let Ok((after_ref, cell_ref)) = parse_cell_ref(remaining) {
                 // Check if followed by a colon (range)
-                let (remaining, _) = multispace0(remaining)?;
+                let (remaining, _) = multispace0(after_ref)?;
                 if let Ok((remaining, _)) = char::<&str, nom::error::Error<&str>>(':')(remaining) {
                     let (remaining, _) = multispace0(remaining)?;
                     let (remaining, end_ref) = parse_cell_ref(remaining)?;
@@ -262,7 +541,7 @@ fn parse_cell_ref_or_function(input: &str) -> IResult<&str, Expr> {
                         }),
                     }));
                 }
-                return Ok((remaining, Expr::SheetRef {
+                return Ok((after_ref, Expr::SheetRef {
                     sheet_name,
                     reference: Box::new(cell_ref),
                 }));
@@ -271,9 +550,9 @@ fn parse_cell_ref_or_function(input: &str) -> IResult<&str, Expr> {
     }
 
     // Try to parse as cell reference first
-    if let Ok((remaining, cell_ref)) = parse_cell_ref(input) {
+    if let Ok((after_ref, cell_ref)) = parse_cell_ref(input) {
         // Check if followed by a colon (range)
-        let (remaining, _) = multispace0(remaining)?;
+        let (remaining, _) = multispace0(after_ref)?;
         if let Ok((remaining, _)) = char::<&str, nom::error::Error<&str>>(':')(remaining) {
             let (remaining, _) = multispace0(remaining)?;
             let (remaining, end_ref) = parse_cell_ref(remaining)?;
@@ -282,7 +561,7 @@ fn parse_cell_ref_or_function(input: &str) -> IResult<&str, Expr> {
                 end: Box::new(end_ref),
             }));
         }
-        return Ok((remaining, cell_ref));
+        return Ok((after_ref, cell_ref));
     }
 
     // Try to parse as function call
@@ -308,12 +587,37 @@ fn parse_cell_ref_or_function(input: &str) -> IResult<&str, Expr> {
     }))
 }
 
+/// Parse reference intersections (`A1:C5 B2:D4`)
+///
+/// Whitespace between two references is the intersection operator, which
+/// binds tighter than every other operator.
+fn parse_intersection(input: &str) -> IResult<&str, Expr> {
+    let (mut input, mut expr) = parse_primary(input)?;
+    let mut depth = expr.depth();
+
+    while expr.is_reference() {
+        match pair(multispace1, verify(parse_primary, Expr::is_reference))(input) {
+            Ok((remaining, (_, right))) => {
+                depth = depth.max(right.depth()) + 1;
+                check_depth(input, depth)?;
+                expr = Expr::intersection(expr, right);
+                input = remaining;
+            }
+            Err(e @ nom::Err::Failure(_)) => return Err(e),
+            Err(_) => break,
+        }
+    }
+
+    Ok((input, expr))
+}
+
 /// Parse a postfix expression (percent)
 fn parse_postfix(input: &str) -> IResult<&str, Expr> {
-    let (input, expr) = parse_primary(input)?;
+    let (input, expr) = parse_intersection(input)?;
     let (input, _) = multispace0(input)?;
 
     let (input, percents) = many0(char('%'))(input)?;
+    check_depth(input, expr.depth() + percents.len())?;
 
     let result = percents.into_iter().fold(expr, |acc, _| {
         Expr::Unary {
@@ -325,25 +629,32 @@ fn parse_postfix(input: &str) -> IResult<&str, Expr> {
     Ok((input, result))
 }
 
-/// Parse a unary expression (prefix - or +)
+/// Parse a unary expression (prefix -, + or @)
 fn parse_unary(input: &str) -> IResult<&str, Expr> {
     let (input, _) = multispace0(input)?;
 
     alt((
         map(
-            pair(char('-'), parse_unary),
+            pair(char('-'), |i| nested(i, parse_unary)),
             |(_, e)| Expr::Unary {
                 op: UnaryOp::Neg,
                 operand: Box::new(e),
             },
         ),
         map(
-            pair(char('+'), parse_unary),
+            pair(char('+'), |i| nested(i, parse_unary)),
             |(_, e)| Expr::Unary {
                 op: UnaryOp::Pos,
                 operand: Box::new(e),
             },
         ),
+        map(
+            pair(char('@'), |i| nested(i, parse_unary)),
+            |(_, e)| Expr::Unary {
+                op: UnaryOp::ImplicitIntersection,
+                operand: Box::new(e),
+            },
+        ),
         parse_postfix,
     ))(input)
 }
@@ -355,7 +666,7 @@ fn parse_power(input: &str) -> IResult<&str, Expr> {
 
     if let Ok((input, _)) = char::<&str, nom::error::Error<&str>>('^')(input) {
         let (input, _) = multispace0(input)?;
-        let (input, exp) = parse_power(input)?; // Right-associative recursion
+        let (input, exp) = nested(input, parse_power)?; // Right-associative recursion
         Ok((input, Expr::Binary {
             left: Box::new(base),
             op: BinaryOp::Pow,
@@ -366,70 +677,58 @@ fn parse_power(input: &str) -> IResult<&str, Expr> {
     }
 }
 
+/// Parse a left-associative chain of binary operators
+fn parse_chain<'a>(
+    input: &'a str,
+    operand: fn(&'a str) -> IResult<&'a str, Expr>,
+    operator: fn(&'a str) -> IResult<&'a str, BinaryOp>,
+) -> IResult<&'a str, Expr> {
+    let (mut input, mut expr) = operand(input)?;
+    let mut depth = expr.depth();
+
+    loop {
+        match pair(ws(operator), operand)(input) {
+            Ok((remaining, (op, right))) => {
+                // A chain of one precedence stays at one level; see Expr::chain
+                let extends = matches!(&expr, Expr::Binary { op: last, .. } if last.precedence() == op.precedence());
+                depth = if extends { depth } else { depth + 1 }.max(right.depth() + 1);
+                check_depth(input, depth)?;
+                expr = Expr::binary(expr, op, right);
+                input = remaining;
+            }
+            Err(e @ nom::Err::Failure(_)) => return Err(e),
+            Err(_) => return Ok((input, expr)),
+        }
+    }
+}
+
 /// Parse multiplicative expressions (*, /)
 fn parse_multiplicative(input: &str) -> IResult<&str, Expr> {
-    let (input, init) = parse_power(input)?;
-
-    fold_many0(
-        pair(ws(parse_multiplicative_op), parse_power),
-        move || init.clone(),
-        |acc, (op, val)| Expr::Binary {
-            left: Box::new(acc),
-            op,
-            right: Box::new(val),
-        },
-    )(input)
+    parse_chain(input, parse_power, parse_multiplicative_op)
 }
 
 /// Parse additive expressions (+, -)
 fn parse_additive(input: &str) -> IResult<&str, Expr> {
-    let (input, init) = parse_multiplicative(input)?;
-
-    fold_many0(
-        pair(ws(parse_additive_op), parse_multiplicative),
-        move || init.clone(),
-        |acc, (op, val)| Expr::Binary {
-            left: Box::new(acc),
-            op,
-            right: Box::new(val),
-        },
-    )(input)
+    parse_chain(input, parse_multiplicative, parse_additive_op)
 }
 
 /// Parse concatenation expressions (&)
 fn parse_concat(input: &str) -> IResult<&str, Expr> {
-    let (input, init) = parse_additive(input)?;
-
-    fold_many0(
-        pair(ws(parse_concat_op), parse_additive),
-        move || init.clone(),
-        |acc, (op, val)| Expr::Binary {
-            left: Box::new(acc),
-            op,
-            right: Box::new(val),
-        },
-    )(input)
+    parse_chain(input, parse_additive, parse_concat_op)
 }
 
 /// Parse comparison expressions (=, <>, <, >, <=, >=)
 fn parse_comparison(input: &str) -> IResult<&str, Expr> {
-    let (input, init) = parse_concat(input)?;
-
-    fold_many0(
-        pair(ws(parse_comparison_op), parse_concat),
-        move || init.clone(),
-        |acc, (op, val)| Expr::Binary {
-            left: Box::new(acc),
-            op,
-            right: Box::new(val),
-        },
-    )(input)
+    parse_chain(input, parse_concat, parse_comparison_op)
 }
 
 /// Parse a complete expression
+///
+/// Parentheses and function arguments recurse through here, so this is
+/// where most nesting is counted.
 pub fn parse_expression(input: &str) -> IResult<&str, Expr> {
     let (input, _) = multispace0(input)?;
-    parse_comparison(input)
+    nested(input, parse_comparison)
 }
 
 // =============================================================================
@@ -437,39 +736,104 @@ pub fn parse_expression(input: &str) -> IResult<&str, Expr> {
 // =============================================================================
 
 /// Parser struct for compatibility with existing code
-pub struct NomParser;
+#[derive(Debug, Clone, Copy)]
+pub struct NomParser {
+    max_nesting: usize,
+    max_depth: usize,
+    max_length: usize,
+    array_separators: ArraySeparators,
+}
 
 impl NomParser {
     pub fn new() -> Self {
-        NomParser
+        NomParser {
+            max_nesting: DEFAULT_MAX_NESTING,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_length: DEFAULT_MAX_LENGTH,
+            array_separators: ArraySeparators::STANDARD,
+        }
+    }
+
+    /// Read array constants with the given separators
+    pub fn with_array_separators(mut self, separators: ArraySeparators) -> Self {
+        self.array_separators = separators;
+        self
+    }
+
+    /// Reject formulas that nest deeper or run longer than the limits allow
+    ///
+    /// Only [`EvalLimits::max_nesting`], [`EvalLimits::max_depth`] and
+    /// [`EvalLimits::max_length`] apply to parsing.
+    pub fn with_limits(mut self, limits: &EvalLimits) -> Self {
+        self.max_nesting = limits.max_nesting;
+        self.max_depth = limits.max_depth;
+        self.max_length = limits.max_length;
+        self
     }
 
     /// Parse a formula string into an AST
     pub fn parse(&self, input: &str) -> Result<Expr, ParseError> {
         // Strip leading '=' if present
         let input = input.strip_prefix('=').unwrap_or(input);
+        if input.len() > self.max_length && input.chars().count() > self.max_length {
+            return Err(ParseError {
+                message: format!("Formula is longer than {} characters", self.max_length),
+                position: 0,
+                kind: ParseErrorKind::TooLong,
+            });
+        }
 
-        match parse_expression(input) {
+        let outer = (
+            NESTING_LEFT.replace(self.max_nesting),
+            MAX_DEPTH.replace(self.max_depth),
+            ARRAY_SEPARATORS.replace(self.array_separators),
+        );
+        let result = parse_expression(input);
+        NESTING_LEFT.set(outer.0);
+        MAX_DEPTH.set(outer.1);
+        ARRAY_SEPARATORS.set(outer.2);
+
+        match result {
             Ok((remaining, expr)) => {
                 // Check that all input was consumed
                 let remaining = remaining.trim();
-                if remaining.is_empty() {
-                    Ok(expr)
-                } else {
+                if !remaining.is_empty() {
                     Err(ParseError {
                         message: format!("Unexpected input: '{}'", remaining),
                         position: input.len() - remaining.len(),
+                        kind: ParseErrorKind::Syntax,
                     })
+                } else if expr.depth() > self.max_depth {
+                    Err(self.too_deep(0))
+                } else {
+                    Ok(expr)
                 }
             }
+            Err(nom::Err::Failure(e)) if e.code == nom::error::ErrorKind::TooLarge => {
+                Err(self.too_deep(input.len() - e.input.len()))
+            }
             Err(e) => Err(ParseError {
                 message: format!("Parse error: {:?}", e),
                 position: 0,
+                kind: ParseErrorKind::Syntax,
             }),
         }
     }
 }
 
+impl NomParser {
+    fn too_deep(&self, position: usize) -> ParseError {
+        ParseError {
+            message: format!(
+                "Formula is nested too deeply (limits: {} levels of nesting, {} levels in all)",
+                self.max_nesting, self.max_depth
+            ),
+            position,
+            kind: ParseErrorKind::TooDeep,
+        }
+    }
+}
+
 impl Default for NomParser {
     fn default() -> Self {
         Self::new()
@@ -489,6 +853,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_number() {
         assert_eq!(parse("123"), Ok(Expr::Number(123.0)));
         assert_eq!(parse("3.14"), Ok(Expr::Number(3.14)));
@@ -643,6 +1008,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_implicit_intersection() {
+        let result = parse("@A1:A10 * 2").unwrap();
+        if let Expr::Binary { left, op, .. } = result {
+            assert_eq!(op, BinaryOp::Mul);
+            assert!(matches!(
+                *left,
+                Expr::Unary { op: UnaryOp::ImplicitIntersection, .. }
+            ));
+        } else {
+            panic!("Expected binary expression");
+        }
+        assert_eq!(parse("@A1:A10").unwrap().to_string(), "@A1:A10");
+    }
+
     #[test]
     fn test_concat() {
         let result = parse("\"Hello\" & \" \" & \"World\"");
@@ -707,4 +1087,183 @@ mod tests {
             panic!("Expected FunctionCall with SheetRef");
         }
     }
+
+    #[test]
+    fn test_reference_intersection() {
+        let expr = parse("A1:C5 B2:D4").unwrap();
+        assert!(matches!(expr, Expr::Intersection { .. }));
+        assert_eq!(expr.to_string(), "A1:C5 B2:D4");
+
+        // Intersection binds tighter than arithmetic
+        let expr = parse("A1:C5 B2 + 1").unwrap();
+        if let Expr::Binary { left, op, .. } = expr {
+            assert_eq!(op, BinaryOp::Add);
+            assert!(matches!(*left, Expr::Intersection { .. }));
+        } else {
+            panic!("Expected binary expression");
+        }
+
+        // Whitespace around ordinary operators is not an intersection
+        assert!(matches!(parse("A1 + B1"), Ok(Expr::Binary { .. })));
+        assert!(matches!(parse("SUM( A1 , B1 )"), Ok(Expr::FunctionCall { .. })));
+        assert!(parse("1 2").is_err());
+    }
+
+    #[test]
+    fn test_reference_union() {
+        let expr = parse("SUM((A1:A5,C1:C5))").unwrap();
+        if let Expr::FunctionCall { args, .. } = &expr {
+            assert_eq!(args.len(), 1);
+            assert!(matches!(args[0].strip_groups(), Expr::Union(areas) if areas.len() == 2));
+        } else {
+            panic!("Expected function call");
+        }
+        assert_eq!(expr.to_string(), "SUM((A1:A5,C1:C5))");
+
+        // Separate arguments are still separate arguments
+        if let Ok(Expr::FunctionCall { args, .. }) = parse("SUM(A1:A5,C1:C5)") {
+            assert_eq!(args.len(), 2);
+        } else {
+            panic!("Expected function call");
+        }
+
+        // Only references can be unioned
+        assert!(parse("(1,2)").is_err());
+    }
+
+    #[test]
+    fn test_array_constant() {
+        let expr = parse("{1,2,3;4,5,6}").unwrap();
+        if let Expr::Array(rows) = &expr {
+            assert_eq!(rows.len(), 2);
+            assert_eq!(rows[1], vec![Expr::Number(4.0), Expr::Number(5.0), Expr::Number(6.0)]);
+        } else {
+            panic!("Expected array constant");
+        }
+
+        // Mixed literal types, negative numbers and errors round-trip
+        let input = "{\"a\",-1.5;TRUE,#N/A}";
+        assert_eq!(parse(input).unwrap().to_string(), input);
+        assert_eq!(
+            parse("VLOOKUP(\"b\", { \"a\" , 1 ; \"b\" , 2 }, 2, 0)").unwrap().to_string(),
+            "VLOOKUP(\"b\",{\"a\",1;\"b\",2},2,0)"
+        );
+
+        // Backslash column separator where the locale asks for it
+        let separators = ArraySeparators { column: '\\', row: ';' };
+        let local = NomParser::new().with_array_separators(separators);
+        assert_eq!(local.parse("{1\\2;3\\4}").unwrap(), parse("{1,2;3,4}").unwrap());
+        assert!(parse("{1\\2;3\\4}").is_err());
+        assert!(local.parse("{1,2}").is_err());
+
+        // Rows must be the same length and elements must be literals
+        assert!(parse("{1,2;3}").is_err());
+        assert!(parse("{A1,2}").is_err());
+        assert!(parse("{}").is_err());
+    }
+
+    #[test]
+    fn test_structured_reference() {
+        let structured = |table: Option<&str>, area, columns: Option<(&str, &str)>| {
+            Expr::Structured(StructuredRef {
+                table: table.map(str::to_string),
+                area,
+                columns: columns.map(|(a, b)| (a.to_string(), b.to_string())),
+            })
+        };
+        let column = |name| Some((name, name));
+
+        assert_eq!(
+            parse("Sales[Amount]").unwrap(),
+            structured(Some("Sales"), TableArea::Data, column("Amount"))
+        );
+        assert_eq!(
+            parse("Sales[@Amount]").unwrap(),
+            structured(Some("Sales"), TableArea::ThisRow, column("Amount"))
+        );
+        assert_eq!(
+            parse("[@[Unit Price]]").unwrap(),
+            structured(None, TableArea::ThisRow, column("Unit Price"))
+        );
+        assert_eq!(
+            parse("Sales[#totals]").unwrap(),
+            structured(Some("Sales"), TableArea::Totals, None)
+        );
+        assert_eq!(parse("Sales[]").unwrap(), structured(Some("Sales"), TableArea::Data, None));
+        assert_eq!(
+            parse("Sales[[#Totals], [Amount]]").unwrap(),
+            structured(Some("Sales"), TableArea::Totals, column("Amount"))
+        );
+        assert_eq!(
+            parse("Sales[[#Headers],[#Data],[Jan]:[Mar]]").unwrap(),
+            structured(Some("Sales"), TableArea::HeadersAndData, Some(("Jan", "Mar")))
+        );
+        assert_eq!(
+            parse("T[Q1 '[est']]").unwrap(),
+            structured(Some("T"), TableArea::Data, column("Q1 [est]"))
+        );
+
+        // A table name that looks like a cell reference is still a table
+        assert!(matches!(parse("Q1[Amount]").unwrap(), Expr::Structured(_)));
+        assert!(matches!(
+            parse("SUM(Sales[Amount])*[@Rate]").unwrap(),
+            Expr::Binary { .. }
+        ));
+
+        // Round-trips through Display
+        for formula in [
+            "Sales[Amount]",
+            "Sales[@[Unit Price]]",
+            "Sales[[#Totals],[Amount]]",
+            "Sales[[Jan]:[Mar]]",
+            "[@]",
+        ] {
+            assert_eq!(parse(formula).unwrap().to_string(), formula);
+        }
+
+        assert!(parse("Sales[[#Totals],[#Headers]]").is_err());
+        assert!(parse("Sales[[Jan],[Feb]]").is_err());
+        assert!(parse("Sales[Amount").is_err());
+    }
+
+    #[test]
+    fn test_nesting_limits() {
+        let parser = NomParser::new();
+
+        // Nesting is rejected before it can overflow the stack
+        for formula in [
+            format!("={}1{}", "(".repeat(1_000), ")".repeat(1_000)),
+            format!("={}1{}", "ABS(".repeat(1_000), ")".repeat(1_000)),
+            format!("={}1", "-".repeat(4_000)),
+            format!("=2{}", "^2".repeat(4_000)),
+            format!("=1{}", "%".repeat(8_000)),
+        ] {
+            let err = parser.parse(&formula).unwrap_err();
+            assert_eq!(err.kind, ParseErrorKind::TooDeep);
+        }
+
+        let limits = EvalLimits {
+            max_nesting: 3,
+            max_depth: 5,
+            ..Default::default()
+        };
+        let parser = NomParser::new().with_limits(&limits);
+        assert!(parser.parse("=((1))").is_ok());
+        assert_eq!(parser.parse("=(((1)))").unwrap_err().kind, ParseErrorKind::TooDeep);
+        assert!(parser.parse("=1%%%%").is_ok());
+        assert_eq!(parser.parse("=1%%%%%").unwrap_err().kind, ParseErrorKind::TooDeep);
+
+        // Operator chains count as one level, however long
+        assert!(parser.parse("=1+1+1+1+1+1*2*2").is_ok());
+        assert_eq!(parser.parse("=1+(1+(1+(1+1)))").unwrap_err().kind, ParseErrorKind::TooDeep);
+        let chain = format!("=1{}", "+1".repeat(4_000));
+        assert_eq!(NomParser::new().parse(&chain).unwrap().depth(), 2);
+
+        // Formulas are as long as Excel allows
+        let chain = format!("=1{}", "+1".repeat(5_000));
+        assert_eq!(NomParser::new().parse(&chain).unwrap_err().kind, ParseErrorKind::TooLong);
+
+        // Ordinary syntax errors are told apart
+        assert_eq!(parser.parse("=1+").unwrap_err().kind, ParseErrorKind::Syntax);
+    }
 }
@@ -1,5 +1,6 @@
 use crate::ast::Expr;
 use crate::parser_nom::NomParser;
+use rusheet_core::{CellCoord, CellError, CellRange, Sheet};
 
 /// Shift formula references when rows are inserted/deleted.
 ///
@@ -110,6 +111,58 @@ pub fn shift_formula_cols(formula: &str, at_col: u32, delta: i32) -> Option<Stri
     Some(format!("={}", shifted_ast))
 }
 
+/// Shift a hyperlink location on sheet `sheet_name` when rows are inserted/deleted.
+///
+/// Unqualified references and references to `sheet_name` shift; locations
+/// on other sheets, and ones that aren't references (defined names), are
+/// returned unchanged.
+///
+/// # Returns
+/// The shifted location, or `None` if the cells it points at were deleted
+///
+/// # Examples
+///
+/// ```
+/// use rusheet_formula::shift_location_rows;
+///
+/// let result = shift_location_rows("Sheet1!B3:C4", "Sheet1", 1, 2);
+/// assert_eq!(result, Some("Sheet1!B5:C6".to_string()));
+///
+/// let result = shift_location_rows("Sheet2!B3", "Sheet1", 1, 2);
+/// assert_eq!(result, Some("Sheet2!B3".to_string()));
+/// ```
+pub fn shift_location_rows(location: &str, sheet_name: &str, at_row: u32, delta: i32) -> Option<String> {
+    shift_location(location, sheet_name, |expr| shift_expr_rows(expr, at_row, delta))
+}
+
+/// Shift a hyperlink location on sheet `sheet_name` when columns are inserted/deleted.
+///
+/// See [`shift_location_rows`].
+pub fn shift_location_cols(location: &str, sheet_name: &str, at_col: u32, delta: i32) -> Option<String> {
+    shift_location(location, sheet_name, |expr| shift_expr_cols(expr, at_col, delta))
+}
+
+fn shift_location(
+    location: &str,
+    sheet_name: &str,
+    shift: impl Fn(&Expr) -> Option<Expr>,
+) -> Option<String> {
+    let parser = NomParser::new();
+    let Ok(ast) = parser.parse(location) else {
+        return Some(location.to_string());
+    };
+    if matches!(&ast, Expr::SheetRef { sheet_name: name, .. } if name != sheet_name) {
+        return Some(location.to_string());
+    }
+
+    let shifted = shift(&ast)?;
+    if shifted == ast {
+        // Keep the text as written
+        return Some(location.to_string());
+    }
+    Some(shifted.to_string())
+}
+
 /// Recursively shift row references in an expression
 fn shift_expr_rows(expr: &Expr, at_row: u32, delta: i32) -> Option<Expr> {
     match expr {
@@ -155,14 +208,13 @@ fn shift_expr_rows(expr: &Expr, at_row: u32, delta: i32) -> Option<Expr> {
                 end: Box::new(shifted_end),
             })
         }
-        Expr::Binary { left, op, right } => {
-            let shifted_left = shift_expr_rows(left, at_row, delta)?;
-            let shifted_right = shift_expr_rows(right, at_row, delta)?;
-            Some(Expr::Binary {
-                left: Box::new(shifted_left),
-                op: *op,
-                right: Box::new(shifted_right),
-            })
+        Expr::Binary { .. } => {
+            let (first, links) = expr.chain();
+            let mut shifted = shift_expr_rows(first, at_row, delta)?;
+            for (op, right) in links {
+                shifted = Expr::binary(shifted, op, shift_expr_rows(right, at_row, delta)?);
+            }
+            Some(shifted)
         }
         Expr::Unary { op, operand } => {
             let shifted_operand = shift_expr_rows(operand, at_row, delta)?;
@@ -185,6 +237,18 @@ fn shift_expr_rows(expr: &Expr, at_row: u32, delta: i32) -> Option<Expr> {
             let shifted_inner = shift_expr_rows(inner, at_row, delta)?;
             Some(Expr::Grouped(Box::new(shifted_inner)))
         }
+        Expr::Union(areas) => {
+            let mut shifted_areas = Vec::new();
+            for area in areas {
+                shifted_areas.push(shift_expr_rows(area, at_row, delta)?);
+            }
+            Some(Expr::Union(shifted_areas))
+        }
+        Expr::Intersection { left, right } => {
+            let shifted_left = shift_expr_rows(left, at_row, delta)?;
+            let shifted_right = shift_expr_rows(right, at_row, delta)?;
+            Some(Expr::intersection(shifted_left, shifted_right))
+        }
         Expr::SheetRef {
             sheet_name,
             reference,
@@ -245,14 +309,13 @@ fn shift_expr_cols(expr: &Expr, at_col: u32, delta: i32) -> Option<Expr> {
                 end: Box::new(shifted_end),
             })
         }
-        Expr::Binary { left, op, right } => {
-            let shifted_left = shift_expr_cols(left, at_col, delta)?;
-            let shifted_right = shift_expr_cols(right, at_col, delta)?;
-            Some(Expr::Binary {
-                left: Box::new(shifted_left),
-                op: *op,
-                right: Box::new(shifted_right),
-            })
+        Expr::Binary { .. } => {
+            let (first, links) = expr.chain();
+            let mut shifted = shift_expr_cols(first, at_col, delta)?;
+            for (op, right) in links {
+                shifted = Expr::binary(shifted, op, shift_expr_cols(right, at_col, delta)?);
+            }
+            Some(shifted)
         }
         Expr::Unary { op, operand } => {
             let shifted_operand = shift_expr_cols(operand, at_col, delta)?;
@@ -275,6 +338,18 @@ fn shift_expr_cols(expr: &Expr, at_col: u32, delta: i32) -> Option<Expr> {
             let shifted_inner = shift_expr_cols(inner, at_col, delta)?;
             Some(Expr::Grouped(Box::new(shifted_inner)))
         }
+        Expr::Union(areas) => {
+            let mut shifted_areas = Vec::new();
+            for area in areas {
+                shifted_areas.push(shift_expr_cols(area, at_col, delta)?);
+            }
+            Some(Expr::Union(shifted_areas))
+        }
+        Expr::Intersection { left, right } => {
+            let shifted_left = shift_expr_cols(left, at_col, delta)?;
+            let shifted_right = shift_expr_cols(right, at_col, delta)?;
+            Some(Expr::intersection(shifted_left, shifted_right))
+        }
         Expr::SheetRef {
             sheet_name,
             reference,
@@ -290,6 +365,254 @@ fn shift_expr_cols(expr: &Expr, at_col: u32, delta: i32) -> Option<Expr> {
     }
 }
 
+/// Translate a formula copied from one cell to another.
+///
+/// Relative references move by the same offset as the formula, while
+/// `$`-anchored rows and columns stay put. A reference pushed off the grid
+/// becomes `#REF!`, as it does when pasting in Excel.
+///
+/// # Returns
+/// The translated formula string, or `None` if the formula cannot be parsed
+///
+/// # Examples
+///
+/// ```
+/// use rusheet_core::CellCoord;
+/// use rusheet_formula::translate_formula;
+///
+/// // Copy C2 to C3
+/// let result = translate_formula("=A2+$B$1", CellCoord::new(1, 2), CellCoord::new(2, 2));
+/// assert_eq!(result, Some("=A3+$B$1".to_string()));
+///
+/// // Copy B2 to A1: A1 has nowhere to go
+/// let result = translate_formula("=A1+B2", CellCoord::new(1, 1), CellCoord::new(0, 0));
+/// assert_eq!(result, Some("=#REF!+A1".to_string()));
+/// ```
+pub fn translate_formula(formula: &str, from: CellCoord, to: CellCoord) -> Option<String> {
+    let parser = NomParser::new();
+    let ast = parser.parse(formula).ok()?;
+
+    Some(format!("={}", translate_expr(&ast, from, to)))
+}
+
+/// Translate the references in a parsed formula copied from `from` to `to`.
+///
+/// See [`translate_formula`] for the rules.
+pub fn translate_expr(expr: &Expr, from: CellCoord, to: CellCoord) -> Expr {
+    let row_delta = to.row as i64 - from.row as i64;
+    let col_delta = to.col as i64 - from.col as i64;
+
+    map_references(expr, None, &|_, reference| match reference {
+        Expr::Range { start, end } => {
+            match (
+                offset_cell_ref(start, row_delta, col_delta, false),
+                offset_cell_ref(end, row_delta, col_delta, false),
+            ) {
+                (Some(start), Some(end)) => Expr::range(start, end),
+                _ => Expr::Error(CellError::InvalidReference),
+            }
+        }
+        _ => offset_cell_ref(reference, row_delta, col_delta, false)
+            .unwrap_or(Expr::Error(CellError::InvalidReference)),
+    })
+}
+
+/// Rewrite a formula after the cells in `source` are cut and pasted with
+/// their top-left corner at `dest`.
+///
+/// References pointing into the moved block follow it, including
+/// `$`-anchored ones; a range follows only when it lies entirely inside the
+/// block. Everything else is left alone, so a moved formula keeps pointing
+/// at the same cells. Only unqualified references are rewritten; see
+/// [`move_formula_in`] for references qualified with a sheet name.
+///
+/// # Returns
+/// The rewritten formula string, or `None` if the formula cannot be parsed
+///
+/// # Examples
+///
+/// ```
+/// use rusheet_core::{CellCoord, CellRange};
+/// use rusheet_formula::move_formula;
+///
+/// // A1:A2 moved to C1:C2
+/// let source = CellRange::new(CellCoord::new(0, 0), CellCoord::new(1, 0));
+/// let result = move_formula("=SUM($A$1:A2)+B1", &source, CellCoord::new(0, 2));
+/// assert_eq!(result, Some("=SUM($C$1:C2)+B1".to_string()));
+/// ```
+pub fn move_formula(formula: &str, source: &CellRange, dest: CellCoord) -> Option<String> {
+    let parser = NomParser::new();
+    let ast = parser.parse(formula).ok()?;
+
+    Some(format!("={}", move_expr(&ast, source, dest)))
+}
+
+/// Rewrite the references in a parsed formula for a cut-and-paste move.
+///
+/// See [`move_formula`] for the rules.
+pub fn move_expr(expr: &Expr, source: &CellRange, dest: CellCoord) -> Expr {
+    move_refs(expr, source, dest, &|sheet| sheet.is_none())
+}
+
+/// Rewrite a formula anywhere in the workbook after the cells in `source`
+/// on sheet `sheet_name` are cut and pasted with their top-left corner at
+/// `dest`.
+///
+/// References qualified with `sheet_name` follow the block as in
+/// [`move_formula`]; unqualified ones do only when `same_sheet` says the
+/// formula is on that sheet.
+///
+/// # Examples
+///
+/// ```
+/// use rusheet_core::{CellCoord, CellRange};
+/// use rusheet_formula::move_formula_in;
+///
+/// let source = CellRange::new(CellCoord::new(0, 0), CellCoord::new(0, 0));
+/// let result = move_formula_in("=Sheet1!A1+A1", "Sheet1", false, &source, CellCoord::new(0, 1));
+/// assert_eq!(result, Some("=Sheet1!B1+A1".to_string()));
+/// ```
+pub fn move_formula_in(
+    formula: &str,
+    sheet_name: &str,
+    same_sheet: bool,
+    source: &CellRange,
+    dest: CellCoord,
+) -> Option<String> {
+    let parser = NomParser::new();
+    let ast = parser.parse(formula).ok()?;
+
+    let moved = move_refs(&ast, source, dest, &|sheet| match sheet {
+        Some(sheet) => sheet.eq_ignore_ascii_case(sheet_name),
+        None => same_sheet,
+    });
+    Some(format!("={}", moved))
+}
+
+/// Move references into `source` that are on a sheet `on_moved_sheet` accepts
+fn move_refs(
+    expr: &Expr,
+    source: &CellRange,
+    dest: CellCoord,
+    on_moved_sheet: &dyn Fn(Option<&str>) -> bool,
+) -> Expr {
+    let row_delta = dest.row as i64 - source.start.row as i64;
+    let col_delta = dest.col as i64 - source.start.col as i64;
+    let inside = |reference: &Expr| match reference {
+        Expr::CellRef { row, col, .. } => source.contains(CellCoord::new(*row, *col)),
+        _ => false,
+    };
+
+    map_references(expr, None, &|sheet, reference| {
+        if !on_moved_sheet(sheet) {
+            return reference.clone();
+        }
+        match reference {
+            Expr::Range { start, end } if inside(start) && inside(end) => {
+                match (
+                    offset_cell_ref(start, row_delta, col_delta, true),
+                    offset_cell_ref(end, row_delta, col_delta, true),
+                ) {
+                    (Some(start), Some(end)) => Expr::range(start, end),
+                    _ => Expr::Error(CellError::InvalidReference),
+                }
+            }
+            Expr::CellRef { .. } if inside(reference) => {
+                offset_cell_ref(reference, row_delta, col_delta, true)
+                    .unwrap_or(Expr::Error(CellError::InvalidReference))
+            }
+            _ => reference.clone(),
+        }
+    })
+}
+
+/// Offset a cell reference, skipping `$`-anchored parts unless `ignore_anchors`
+/// is set. Returns `None` if the reference leaves the grid.
+fn offset_cell_ref(expr: &Expr, row_delta: i64, col_delta: i64, ignore_anchors: bool) -> Option<Expr> {
+    match expr {
+        Expr::CellRef {
+            col,
+            row,
+            abs_col,
+            abs_row,
+        } => {
+            let new_row = if *abs_row && !ignore_anchors {
+                *row as i64
+            } else {
+                *row as i64 + row_delta
+            };
+            let new_col = if *abs_col && !ignore_anchors {
+                *col as i64
+            } else {
+                *col as i64 + col_delta
+            };
+
+            if !(0..Sheet::MAX_ROWS as i64).contains(&new_row)
+                || !(0..Sheet::MAX_COLS as i64).contains(&new_col)
+            {
+                return None;
+            }
+
+            Some(Expr::CellRef {
+                col: new_col as u32,
+                row: new_row as u32,
+                abs_col: *abs_col,
+                abs_row: *abs_row,
+            })
+        }
+        _ => Some(expr.clone()),
+    }
+}
+
+/// Rebuild an expression, passing every cell reference and range to `f`
+/// along with the sheet it is qualified by.
+///
+/// A sheet-qualified reference that `f` turns into an error loses its
+/// sheet prefix, since `Sheet1!#REF!` is not a valid formula.
+fn map_references(
+    expr: &Expr,
+    sheet: Option<&str>,
+    f: &dyn Fn(Option<&str>, &Expr) -> Expr,
+) -> Expr {
+    match expr {
+        Expr::CellRef { .. } | Expr::Range { .. } => f(sheet, expr),
+        Expr::SheetRef {
+            sheet_name,
+            reference,
+        } => match map_references(reference, Some(sheet_name), f) {
+            Expr::Error(e) => Expr::Error(e),
+            mapped => Expr::SheetRef {
+                sheet_name: sheet_name.clone(),
+                reference: Box::new(mapped),
+            },
+        },
+        Expr::Binary { .. } => {
+            let (first, links) = expr.chain();
+            links.into_iter().fold(map_references(first, sheet, f), |left, (op, right)| {
+                Expr::binary(left, op, map_references(right, sheet, f))
+            })
+        }
+        Expr::Unary { op, operand } => Expr::unary(*op, map_references(operand, sheet, f)),
+        Expr::FunctionCall { name, args } => Expr::FunctionCall {
+            name: name.clone(),
+            args: args.iter().map(|arg| map_references(arg, sheet, f)).collect(),
+        },
+        Expr::Grouped(inner) => Expr::Grouped(Box::new(map_references(inner, sheet, f))),
+        Expr::Union(areas) => Expr::Union(
+            areas
+                .iter()
+                .map(|area| map_references(area, sheet, f))
+                .collect(),
+        ),
+        Expr::Intersection { left, right } => Expr::intersection(
+            map_references(left, sheet, f),
+            map_references(right, sheet, f),
+        ),
+        // Literals don't contain references
+        _ => expr.clone(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -432,4 +755,118 @@ mod tests {
         let result = shift_formula_rows(formula, 0, 1);
         assert_eq!(result, Some("=(A2+B2)*C2".to_string()));
     }
+
+    #[test]
+    fn test_union_and_intersection_shift() {
+        let result = shift_formula_rows("=SUM((A1:A5,C1:C5))", 0, 1);
+        assert_eq!(result, Some("=SUM((A2:A6,C2:C6))".to_string()));
+
+        let result = shift_formula_cols("=SUM(A1:C5 B2:D4)", 1, 1);
+        assert_eq!(result, Some("=SUM(A1:D5 C2:E4)".to_string()));
+    }
+
+    #[test]
+    fn test_shift_hyperlink_locations() {
+        assert_eq!(shift_location_cols("B2", "Sheet1", 0, 1), Some("C2".to_string()));
+        assert_eq!(
+            shift_location_rows("'My Data'!A5", "My Data", 2, -1),
+            Some("'My Data'!A4".to_string())
+        );
+        // Targets that were deleted, names and other sheets
+        assert_eq!(shift_location_rows("Sheet1!A3", "Sheet1", 2, -1), None);
+        assert_eq!(shift_location_rows("Totals", "Sheet1", 0, 1), Some("Totals".to_string()));
+        assert_eq!(shift_location_rows("sheet2!a9", "Sheet1", 0, 1), Some("sheet2!a9".to_string()));
+    }
+
+    #[test]
+    fn test_translate_relative_reference() {
+        // Copy C2 down to C3
+        let result = translate_formula("=A2*B2", CellCoord::new(1, 2), CellCoord::new(2, 2));
+        assert_eq!(result, Some("=A3*B3".to_string()));
+
+        // Copy C2 right to E4
+        let result = translate_formula("=A2*B2", CellCoord::new(1, 2), CellCoord::new(3, 4));
+        assert_eq!(result, Some("=C4*D4".to_string()));
+    }
+
+    #[test]
+    fn test_translate_honours_anchors() {
+        let result = translate_formula(
+            "=$A$1+$A1+A$1+A1",
+            CellCoord::new(0, 1),
+            CellCoord::new(2, 3),
+        );
+        assert_eq!(result, Some("=$A$1+$A3+C$1+C3".to_string()));
+    }
+
+    #[test]
+    fn test_translate_off_grid_becomes_ref_error() {
+        let result = translate_formula("=A1+B2", CellCoord::new(1, 1), CellCoord::new(0, 0));
+        assert_eq!(result, Some("=#REF!+A1".to_string()));
+
+        // Either end of a range leaving the grid invalidates the range
+        let result = translate_formula("=SUM(A1:A3)", CellCoord::new(3, 0), CellCoord::new(2, 0));
+        assert_eq!(result, Some("=SUM(#REF!)".to_string()));
+    }
+
+    #[test]
+    fn test_translate_sheet_ref_and_areas() {
+        let result = translate_formula(
+            "=Sheet2!A1+SUM((A1:A2,C1:C2))+SUM(A1:C5 B2:D4)",
+            CellCoord::new(0, 5),
+            CellCoord::new(1, 5),
+        );
+        assert_eq!(
+            result,
+            Some("=Sheet2!A2+SUM((A2:A3,C2:C3))+SUM(A2:C6 B3:D5)".to_string())
+        );
+
+        let result = translate_formula("=Sheet2!A1", CellCoord::new(1, 0), CellCoord::new(0, 0));
+        assert_eq!(result, Some("=#REF!".to_string()));
+    }
+
+    #[test]
+    fn test_translate_invalid_formula() {
+        assert_eq!(
+            translate_formula("=SUM(", CellCoord::new(0, 0), CellCoord::new(1, 0)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_move_references_into_block_follow() {
+        // B1:B2 cut and pasted at D5
+        let source = CellRange::new(CellCoord::new(0, 1), CellCoord::new(1, 1));
+        let dest = CellCoord::new(4, 3);
+
+        let result = move_formula("=B1+$B$2+A1", &source, dest);
+        assert_eq!(result, Some("=D5+$D$6+A1".to_string()));
+
+        let result = move_formula("=SUM(B1:B2)", &source, dest);
+        assert_eq!(result, Some("=SUM(D5:D6)".to_string()));
+    }
+
+    #[test]
+    fn test_move_partial_range_unchanged() {
+        let source = CellRange::new(CellCoord::new(0, 1), CellCoord::new(1, 1));
+        let result = move_formula("=SUM(B1:B3)", &source, CellCoord::new(4, 3));
+        assert_eq!(result, Some("=SUM(B1:B3)".to_string()));
+    }
+
+    #[test]
+    fn test_move_ignores_other_sheets() {
+        let source = CellRange::new(CellCoord::new(0, 0), CellCoord::new(0, 0));
+        let result = move_formula("=Sheet2!A1+A1", &source, CellCoord::new(0, 1));
+        assert_eq!(result, Some("=Sheet2!A1+B1".to_string()));
+    }
+
+    #[test]
+    fn test_move_sheet_qualified() {
+        let source = CellRange::new(CellCoord::new(0, 0), CellCoord::new(1, 0));
+        let dest = CellCoord::new(0, 2);
+        let result = move_formula_in("=SUM(sheet1!A1:A2)+A1+Sheet2!A1", "Sheet1", true, &source, dest);
+        assert_eq!(result, Some("=SUM(sheet1!C1:C2)+C1+Sheet2!A1".to_string()));
+        let result = move_formula_in("='Sheet1'!A2+A2", "Sheet1", false, &source, dest);
+        assert_eq!(result, Some("=Sheet1!C2+A2".to_string()));
+    }
 }
@@ -0,0 +1,242 @@
+use crate::ast::Expr;
+use crate::parser_nom::NomParser;
+use rusheet_core::{CellError, RusheetError, Sheet, Workbook};
+
+/// Rewrite references to sheet `old_name` so they point at `new_name`.
+///
+/// # Returns
+/// The rewritten formula string, or `None` if the formula cannot be parsed
+///
+/// # Examples
+///
+/// ```
+/// use rusheet_formula::rename_sheet_in_formula;
+///
+/// let result = rename_sheet_in_formula("=Data!A1+SUM(Data!B1:B5)", "Data", "Q1 Data");
+/// assert_eq!(result, Some("='Q1 Data'!A1+SUM('Q1 Data'!B1:B5)".to_string()));
+/// ```
+pub fn rename_sheet_in_formula(formula: &str, old_name: &str, new_name: &str) -> Option<String> {
+    let parser = NomParser::new();
+    let ast = parser.parse(formula).ok()?;
+
+    let renamed = map_sheet_refs(&ast, &|sheet_name, reference| {
+        let sheet_name = if sheet_name == old_name {
+            new_name
+        } else {
+            sheet_name
+        };
+        Expr::SheetRef {
+            sheet_name: sheet_name.to_string(),
+            reference: Box::new(reference.clone()),
+        }
+    });
+    Some(format!("={}", renamed))
+}
+
+/// Replace references to the deleted sheet `name` with `#REF!`.
+///
+/// # Returns
+/// The rewritten formula string, or `None` if the formula cannot be parsed
+///
+/// # Examples
+///
+/// ```
+/// use rusheet_formula::remove_sheet_from_formula;
+///
+/// let result = remove_sheet_from_formula("=Data!A1+A1", "Data");
+/// assert_eq!(result, Some("=#REF!+A1".to_string()));
+/// ```
+pub fn remove_sheet_from_formula(formula: &str, name: &str) -> Option<String> {
+    let parser = NomParser::new();
+    let ast = parser.parse(formula).ok()?;
+
+    let removed = map_sheet_refs(&ast, &|sheet_name, reference| {
+        if sheet_name == name {
+            Expr::Error(CellError::InvalidReference)
+        } else {
+            Expr::SheetRef {
+                sheet_name: sheet_name.to_string(),
+                reference: Box::new(reference.clone()),
+            }
+        }
+    });
+    Some(format!("={}", removed))
+}
+
+/// Rename a sheet and rewrite every reference to it across the workbook.
+///
+/// Formulas are updated in place but not recalculated.
+pub fn rename_sheet(
+    workbook: &mut Workbook,
+    index: usize,
+    new_name: impl Into<String>,
+) -> Result<(), RusheetError> {
+    let new_name = new_name.into();
+    let old_name = workbook
+        .get_sheet(index)
+        .ok_or(RusheetError::SheetNotFound(index))?
+        .name
+        .clone();
+
+    workbook.rename_sheet(index, new_name.clone())?;
+    if old_name != new_name {
+        workbook.rewrite_formulas(|formula| {
+            rename_sheet_in_formula(formula, &old_name, &new_name).filter(|f| f != formula)
+        });
+    }
+    Ok(())
+}
+
+/// Delete a sheet, turning references to it in the remaining sheets into `#REF!`.
+///
+/// Formulas are updated in place but not recalculated. Sheets are referenced
+/// by name, so moving a sheet with [`Workbook::move_sheet`] needs no rewrite.
+pub fn delete_sheet(workbook: &mut Workbook, index: usize) -> Result<Sheet, RusheetError> {
+    let sheet = workbook.remove_sheet(index)?;
+    workbook.rewrite_formulas(|formula| {
+        remove_sheet_from_formula(formula, &sheet.name).filter(|f| f != formula)
+    });
+    Ok(sheet)
+}
+
+/// Rebuild an expression, passing every sheet-qualified reference to `f`
+/// as its sheet name and the reference inside it.
+fn map_sheet_refs(expr: &Expr, f: &dyn Fn(&str, &Expr) -> Expr) -> Expr {
+    match expr {
+        Expr::SheetRef {
+            sheet_name,
+            reference,
+        } => f(sheet_name, reference),
+        Expr::Binary { .. } => {
+            let (first, links) = expr.chain();
+            links.into_iter().fold(map_sheet_refs(first, f), |left, (op, right)| {
+                Expr::binary(left, op, map_sheet_refs(right, f))
+            })
+        }
+        Expr::Unary { op, operand } => Expr::unary(*op, map_sheet_refs(operand, f)),
+        Expr::FunctionCall { name, args } => Expr::FunctionCall {
+            name: name.clone(),
+            args: args.iter().map(|arg| map_sheet_refs(arg, f)).collect(),
+        },
+        Expr::Grouped(inner) => Expr::Grouped(Box::new(map_sheet_refs(inner, f))),
+        Expr::Union(areas) => {
+            Expr::Union(areas.iter().map(|area| map_sheet_refs(area, f)).collect())
+        }
+        Expr::Intersection { left, right } => {
+            Expr::intersection(map_sheet_refs(left, f), map_sheet_refs(right, f))
+        }
+        // Literals and unqualified references don't name a sheet
+        _ => expr.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusheet_core::{
+        CellCoord, CellRange, DataValidationRule, Hyperlink, ListSource, ValidationCriteria,
+    };
+
+    fn formula_at(workbook: &Workbook, sheet: usize, coord: CellCoord) -> Option<String> {
+        workbook.sheets[sheet]
+            .get_cell(coord)?
+            .content
+            .formula_expression()
+            .map(String::from)
+    }
+
+    #[test]
+    fn test_rename_sheet_in_formula() {
+        let result = rename_sheet_in_formula("=Sheet2!A1*2+A1", "Sheet2", "Totals");
+        assert_eq!(result, Some("=Totals!A1*2+A1".to_string()));
+
+        // Other sheets are left alone
+        let result = rename_sheet_in_formula("=Sheet3!A1", "Sheet2", "Totals");
+        assert_eq!(result, Some("=Sheet3!A1".to_string()));
+
+        // Names needing quotes round-trip
+        let result = rename_sheet_in_formula("='Bob''s'!A1", "Bob's", "Alice's");
+        assert_eq!(result, Some("='Alice''s'!A1".to_string()));
+    }
+
+    #[test]
+    fn test_remove_sheet_from_formula() {
+        let result = remove_sheet_from_formula("=SUM(Sheet2!A1:A5)+Sheet3!B1", "Sheet2");
+        assert_eq!(result, Some("=SUM(#REF!)+Sheet3!B1".to_string()));
+
+        assert_eq!(remove_sheet_from_formula("=SUM(", "Sheet2"), None);
+    }
+
+    #[test]
+    fn test_rename_sheet_rewrites_workbook() {
+        let mut wb = Workbook::new("Test");
+        wb.add_sheet("Sheet2").unwrap();
+        wb.sheets[0].set_cell_value(CellCoord::new(0, 0), "=Sheet2!A1+1");
+        wb.sheets[1].set_cell_value(CellCoord::new(0, 1), "=Sheet1!A1");
+        let link = Hyperlink::location("Sheet2!C3");
+        wb.sheets[0].set_hyperlink(CellCoord::new(1, 0), Some(link));
+        wb.sheets[0].add_data_validation(DataValidationRule::new(
+            "list".to_string(),
+            CellRange::new(CellCoord::new(0, 2), CellCoord::new(0, 2)),
+            ValidationCriteria::List {
+                source: ListSource::Range {
+                    reference: "Sheet2!B1:B5".to_string(),
+                },
+                show_dropdown: true,
+            },
+        ));
+
+        rename_sheet(&mut wb, 1, "Inputs").unwrap();
+
+        assert_eq!(wb.sheets[1].name, "Inputs");
+        assert_eq!(
+            formula_at(&wb, 0, CellCoord::new(0, 0)),
+            Some("=Inputs!A1+1".to_string())
+        );
+        assert_eq!(
+            formula_at(&wb, 1, CellCoord::new(0, 1)),
+            Some("=Sheet1!A1".to_string())
+        );
+        assert_eq!(
+            wb.sheets[0].get_data_validation_rules()[0].criteria,
+            ValidationCriteria::List {
+                source: ListSource::Range {
+                    reference: "Inputs!B1:B5".to_string(),
+                },
+                show_dropdown: true,
+            }
+        );
+
+        assert_eq!(
+            wb.sheets[0].get_hyperlink(CellCoord::new(1, 0)),
+            Some(&Hyperlink::location("Inputs!C3"))
+        );
+
+        // A failed rename leaves formulas alone
+        assert!(rename_sheet(&mut wb, 1, "Sheet1").is_err());
+        assert_eq!(
+            formula_at(&wb, 0, CellCoord::new(0, 0)),
+            Some("=Inputs!A1+1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_delete_sheet_invalidates_references() {
+        let mut wb = Workbook::new("Test");
+        wb.add_sheet("Sheet2").unwrap();
+        wb.sheets[0].set_cell_value(CellCoord::new(0, 0), "=Sheet2!A1+A2");
+        wb.sheets[0].set_hyperlink(CellCoord::new(1, 0), Some(Hyperlink::location("Sheet2!A1")));
+
+        let removed = delete_sheet(&mut wb, 1).unwrap();
+
+        assert_eq!(removed.name, "Sheet2");
+        assert_eq!(
+            formula_at(&wb, 0, CellCoord::new(0, 0)),
+            Some("=#REF!+A2".to_string())
+        );
+        assert_eq!(
+            wb.sheets[0].get_hyperlink(CellCoord::new(1, 0)),
+            Some(&Hyperlink::location("#REF!"))
+        );
+    }
+}
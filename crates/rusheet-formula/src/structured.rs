@@ -0,0 +1,241 @@
+//! Structured table references
+//!
+//! `Sales[Amount]` names part of a table rather than a block of cells, so
+//! the formula keeps meaning the same thing as the table grows, moves or is
+//! sorted. Formulas store the reference as written; the calc engine resolves
+//! it against the table's current geometry each time the formula is
+//! evaluated or its dependencies are collected.
+
+use crate::ast::{Expr, StructuredRef, TableArea};
+use crate::parser_nom::NomParser;
+use rusheet_core::{CellCoord, CellError, CellRange, RusheetError, Workbook};
+
+/// Check whether an expression contains a structured reference
+pub fn has_structured_refs(expr: &Expr) -> bool {
+    match expr {
+        Expr::Structured(_) => true,
+        Expr::Binary { .. } => {
+            let (first, links) = expr.chain();
+            has_structured_refs(first) || links.into_iter().any(|(_, right)| has_structured_refs(right))
+        }
+        Expr::Intersection { left, right } => has_structured_refs(left) || has_structured_refs(right),
+        Expr::Unary { operand, .. } => has_structured_refs(operand),
+        Expr::Grouped(inner) => has_structured_refs(inner),
+        Expr::FunctionCall { args, .. } | Expr::Union(args) => {
+            args.iter().any(has_structured_refs)
+        }
+        _ => false,
+    }
+}
+
+/// Replace the structured references in a formula at `coord` on sheet
+/// `sheet_index` with the cells they cover
+///
+/// References to a missing table, column or totals row become `#REF!`, and
+/// `[@Column]` outside the table's data rows becomes `#VALUE!`.
+pub fn resolve_structured_refs(
+    expr: &Expr,
+    workbook: &Workbook,
+    sheet_index: usize,
+    coord: CellCoord,
+) -> Expr {
+    map_structured_refs(expr, &|reference| {
+        resolve(reference, workbook, sheet_index, coord).unwrap_or_else(Expr::Error)
+    })
+}
+
+fn resolve(
+    reference: &StructuredRef,
+    workbook: &Workbook,
+    sheet_index: usize,
+    coord: CellCoord,
+) -> Result<Expr, CellError> {
+    let (table_sheet, table) = match &reference.table {
+        Some(name) => workbook.table(name).ok_or(CellError::InvalidReference)?,
+        None => workbook
+            .get_sheet(sheet_index)
+            .and_then(|sheet| sheet.table_at(coord))
+            .map(|table| (sheet_index, table))
+            .ok_or(CellError::InvalidReference)?,
+    };
+    let sheet = workbook
+        .get_sheet(table_sheet)
+        .ok_or(CellError::InvalidReference)?;
+
+    let (first, last) = match &reference.columns {
+        Some((first, last)) => {
+            let first = table.column_index(sheet, first).ok_or(CellError::InvalidReference)?;
+            let last = table.column_index(sheet, last).ok_or(CellError::InvalidReference)?;
+            (first.min(last), first.max(last))
+        }
+        None => (0, table.col_count() - 1),
+    };
+
+    let range = table.range;
+    let header = table.header_row();
+    let data_end = range.end.row - u32::from(table.totals_row);
+    let (top, bottom) = match reference.area {
+        TableArea::All => (header, range.end.row),
+        TableArea::Headers => (header, header),
+        TableArea::HeadersAndData => (header, data_end),
+        TableArea::Data => {
+            let data = table.data_range().ok_or(CellError::InvalidReference)?;
+            (data.start.row, data.end.row)
+        }
+        TableArea::Totals => {
+            let row = table.totals_row_index().ok_or(CellError::InvalidReference)?;
+            (row, row)
+        }
+        TableArea::DataAndTotals if header < range.end.row => (header + 1, range.end.row),
+        TableArea::DataAndTotals => return Err(CellError::InvalidReference),
+        TableArea::ThisRow if header < coord.row && coord.row <= data_end => (coord.row, coord.row),
+        TableArea::ThisRow => return Err(CellError::InvalidValue),
+    };
+
+    let area = CellRange::new(
+        CellCoord::new(top, range.start.col + first),
+        CellCoord::new(bottom, range.start.col + last),
+    );
+    let cell = |c: CellCoord| Expr::cell_ref(c.col, c.row);
+    let reference = if area.is_single_cell() {
+        cell(area.start)
+    } else {
+        Expr::range(cell(area.start), cell(area.end))
+    };
+    Ok(if table_sheet == sheet_index {
+        reference
+    } else {
+        Expr::SheetRef {
+            sheet_name: sheet.name.clone(),
+            reference: Box::new(reference),
+        }
+    })
+}
+
+/// Rewrite references to table `old_name` so they name `new_name`
+///
+/// # Returns
+/// The rewritten formula string, or `None` if the formula cannot be parsed
+///
+/// # Examples
+///
+/// ```
+/// use rusheet_formula::rename_table_in_formula;
+///
+/// let result = rename_table_in_formula("=SUM(Sales[Amount])*[@Rate]", "sales", "Orders");
+/// assert_eq!(result, Some("=SUM(Orders[Amount])*[@Rate]".to_string()));
+/// ```
+pub fn rename_table_in_formula(formula: &str, old_name: &str, new_name: &str) -> Option<String> {
+    let parser = NomParser::new();
+    let ast = parser.parse(formula).ok()?;
+
+    let renamed = map_structured_refs(&ast, &|reference| {
+        let mut reference = reference.clone();
+        if reference
+            .table
+            .as_deref()
+            .is_some_and(|table| table.eq_ignore_ascii_case(old_name))
+        {
+            reference.table = Some(new_name.to_string());
+        }
+        Expr::Structured(reference)
+    });
+    Some(format!("={}", renamed))
+}
+
+/// Rename a table and rewrite every reference to it across the workbook
+///
+/// Formulas are updated in place but not recalculated.
+pub fn rename_table(workbook: &mut Workbook, name: &str, new_name: &str) -> Result<(), RusheetError> {
+    workbook.rename_table(name, new_name)?;
+    workbook.rewrite_formulas(|formula| {
+        rename_table_in_formula(formula, name, new_name).filter(|f| f != formula)
+    });
+    Ok(())
+}
+
+/// Rebuild an expression, passing every structured reference to `f`
+fn map_structured_refs(expr: &Expr, f: &dyn Fn(&StructuredRef) -> Expr) -> Expr {
+    match expr {
+        Expr::Structured(reference) => f(reference),
+        Expr::Binary { .. } => {
+            let (first, links) = expr.chain();
+            links.into_iter().fold(map_structured_refs(first, f), |left, (op, right)| {
+                Expr::binary(left, op, map_structured_refs(right, f))
+            })
+        }
+        Expr::Unary { op, operand } => Expr::unary(*op, map_structured_refs(operand, f)),
+        Expr::FunctionCall { name, args } => Expr::FunctionCall {
+            name: name.clone(),
+            args: args.iter().map(|arg| map_structured_refs(arg, f)).collect(),
+        },
+        Expr::Grouped(inner) => Expr::Grouped(Box::new(map_structured_refs(inner, f))),
+        Expr::Union(areas) => {
+            Expr::Union(areas.iter().map(|area| map_structured_refs(area, f)).collect())
+        }
+        Expr::Intersection { left, right } => {
+            Expr::intersection(map_structured_refs(left, f), map_structured_refs(right, f))
+        }
+        // Literals and cell references don't name a table
+        _ => expr.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusheet_core::Table;
+
+    fn workbook() -> Workbook {
+        let mut workbook = Workbook::new("Test");
+        workbook.add_sheet("Other").unwrap();
+        let sheet = workbook.get_sheet_mut(1).unwrap();
+        for (r, row) in [["Item", "Jan", "Feb"], ["a", "1", "2"], ["b", "3", "4"]].iter().enumerate() {
+            for (c, value) in row.iter().enumerate() {
+                sheet.set_cell_value(CellCoord::new(r as u32 + 1, c as u32 + 1), value);
+            }
+        }
+        let table = Table::new("Sales", CellRange::from_a1("B2:D5").unwrap()).with_totals_row(true);
+        workbook.add_table(1, table).unwrap();
+        workbook
+    }
+
+    fn resolved(formula: &str, sheet_index: usize, at: &str) -> String {
+        let ast = NomParser::new().parse(formula).unwrap();
+        let coord = CellCoord::from_a1(at).unwrap();
+        resolve_structured_refs(&ast, &workbook(), sheet_index, coord).to_string()
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolved("=SUM(Sales[Jan])", 0, "A1"), "SUM(Other!C3:C4)");
+        assert_eq!(resolved("=Sales[[#Totals],[Feb]]", 1, "A1"), "D5");
+        assert_eq!(resolved("=Sales[#All]", 1, "A1"), "B2:D5");
+        assert_eq!(resolved("=Sales[#Headers]", 1, "A1"), "B2:D2");
+        assert_eq!(resolved("=SALES[[Jan]:[Feb]]", 1, "A1"), "C3:D4");
+        assert_eq!(resolved("=Sales[[#Headers],[#Data],[Item]]", 1, "A1"), "B2:B4");
+        assert_eq!(resolved("=[@Jan]+[@Feb]", 1, "B4"), "C4+D4");
+        assert_eq!(resolved("=Sales[@Jan]", 1, "E5"), "#VALUE!");
+        assert_eq!(resolved("=[@Jan]", 1, "A1"), "#REF!");
+        assert_eq!(resolved("=Sales[Mar]", 1, "A1"), "#REF!");
+        assert_eq!(resolved("=Orders[Jan]", 1, "A1"), "#REF!");
+    }
+
+    #[test]
+    fn test_rename_table() {
+        let mut workbook = workbook();
+        workbook
+            .active_sheet_mut()
+            .set_cell_value(CellCoord::new(0, 0), "=SUM(Sales[Jan])");
+        rename_table(&mut workbook, "Sales", "Orders").unwrap();
+        assert!(workbook.table("Orders").is_some());
+        assert_eq!(
+            workbook.active_sheet().get_cell(CellCoord::new(0, 0)).unwrap().content.formula_expression(),
+            Some("=SUM(Orders[Jan])")
+        );
+        assert_eq!(
+            rename_table(&mut workbook, "Orders", "A1"),
+            Err(RusheetError::InvalidTableName("A1".to_string()))
+        );
+    }
+}
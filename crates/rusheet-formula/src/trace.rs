@@ -0,0 +1,309 @@
+//! Step-by-step formula evaluation, for showing users how a result came about
+
+use crate::ast::{Expr, TableArea, UnaryOp};
+use rusheet_core::CellValue;
+use serde::Serialize;
+
+/// One sub-expression of a traced formula and the value it evaluated to
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TraceNode {
+    /// The sub-expression as formula text
+    pub text: String,
+    /// Byte offset of `text` within the root node's text
+    pub start: usize,
+    /// Byte offset just past `text` within the root node's text
+    pub end: usize,
+    /// The value, or `None` for a multi-cell reference such as `A1:B5`,
+    /// which is passed on as a reference rather than evaluated
+    pub value: Option<CellValue>,
+    /// Traces of the operands or arguments, in formula order
+    pub children: Vec<TraceNode>,
+}
+
+impl TraceNode {
+    /// Visit this node and all of its descendants, parents first
+    pub fn walk(&self, visit: &mut impl FnMut(&TraceNode)) {
+        visit(self);
+        for child in &self.children {
+            child.walk(visit);
+        }
+    }
+}
+
+/// Trace `expr` with the values of the matching parts of `resolved`,
+/// taking each sub-expression's value from `value_of`
+///
+/// The root's text is the formula without its leading `=`; spans are
+/// positions in that text. `resolved` must have the same shape as `expr` apart from its leaves, as
+/// when structured references have been replaced by the cells they cover.
+pub(crate) fn trace_resolved(
+    expr: &Expr,
+    resolved: &Expr,
+    value_of: &dyn Fn(&Expr) -> CellValue,
+) -> TraceNode {
+    trace_at(expr, resolved, 0, value_of)
+}
+
+fn trace_at(
+    expr: &Expr,
+    resolved: &Expr,
+    start: usize,
+    value_of: &dyn Fn(&Expr) -> CellValue,
+) -> TraceNode {
+    let text = expr.to_string();
+    let end = start + text.len();
+    let children = operands(expr, text.len())
+        .into_iter()
+        .zip(operands(resolved, 0))
+        .map(|((child, offset), (resolved, _))| trace_at(child, resolved, start + offset, value_of))
+        .collect();
+
+    TraceNode {
+        value: (!is_area(expr)).then(|| value_of(resolved)),
+        text,
+        start,
+        end,
+        children,
+    }
+}
+
+/// An expression's operands or arguments with their offsets within its
+/// text of length `len`, following Expr's Display
+fn operands(expr: &Expr, len: usize) -> Vec<(&Expr, usize)> {
+    match expr {
+        // A chain such as `A1+A2+A3` is one node with an operand per link
+        Expr::Binary { .. } => {
+            let (first, links) = expr.chain();
+            let mut offset = first.to_string().len();
+            let mut operands = vec![(first, 0)];
+            for (op, right) in links {
+                offset += op.to_string().len();
+                operands.push((right, offset));
+                offset += right.to_string().len();
+            }
+            operands
+        }
+        Expr::Intersection { left, right } => {
+            vec![(left, 0), (right, len.saturating_sub(right.to_string().len()))]
+        }
+        Expr::Unary {
+            op: UnaryOp::Percent,
+            operand,
+        } => vec![(operand, 0)],
+        Expr::Unary { operand, .. } => vec![(operand, 1)],
+        Expr::Grouped(inner) => vec![(inner, 1)],
+        Expr::FunctionCall { name, args } => sequence(args.iter(), name.len() + 1),
+        Expr::Array(rows) => sequence(rows.iter().flatten(), 1),
+        Expr::Union(areas) => sequence(areas.iter(), 0),
+        // References are leaves; a range's corners aren't evaluated on their own
+        _ => Vec::new(),
+    }
+}
+
+/// Re-express a trace's text and spans in terms of the formula as typed
+///
+/// A trace's text is the parsed formula printed back, which may differ from
+/// what was typed in spacing, case and number formatting. Both are split
+/// into tokens, and when these line up one to one each node takes the typed
+/// text its tokens cover. Otherwise the trace is returned unchanged.
+pub(crate) fn with_source_text(trace: TraceNode, formula: &str) -> TraceNode {
+    let source = formula.strip_prefix('=').unwrap_or(formula);
+    let printed = tokens(&trace.text);
+    let typed = tokens(source);
+    let lines_up = printed.len() == typed.len()
+        && printed
+            .iter()
+            .zip(&typed)
+            .all(|(&(a, b), &(c, d))| same_token(&trace.text[a..b], &source[c..d]));
+    let Some(&(base, _)) = typed.first().filter(|_| lines_up) else {
+        return trace;
+    };
+    remap(&trace, &printed, &typed, source, base).unwrap_or(trace)
+}
+
+fn remap(
+    node: &TraceNode,
+    printed: &[(usize, usize)],
+    typed: &[(usize, usize)],
+    source: &str,
+    base: usize,
+) -> Option<TraceNode> {
+    let first = printed.partition_point(|&(start, _)| start < node.start);
+    let last = printed.partition_point(|&(_, end)| end <= node.end).checked_sub(1)?;
+    if first > last {
+        return None;
+    }
+    let (start, end) = (typed[first].0, typed[last].1);
+    Some(TraceNode {
+        text: source[start..end].to_string(),
+        start: start - base,
+        end: end - base,
+        value: node.value.clone(),
+        children: node
+            .children
+            .iter()
+            .map(|child| remap(child, printed, typed, source, base))
+            .collect::<Option<_>>()?,
+    })
+}
+
+/// Byte spans of a formula's tokens, skipping whitespace
+///
+/// Quoted text is one token, as is each run of letters, digits and `.$_`;
+/// any other character is a token of its own.
+fn tokens(text: &str) -> Vec<(usize, usize)> {
+    let word = |c: char| c.is_alphanumeric() || matches!(c, '.' | '$' | '_');
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let mut end = start + c.len_utf8();
+        if c == '"' || c == '\'' {
+            // A doubled quote stands for the quote itself
+            while let Some((i, next)) = chars.next() {
+                end = i + next.len_utf8();
+                if next == c {
+                    match chars.next_if(|&(_, after)| after == c) {
+                        Some((i, after)) => end = i + after.len_utf8(),
+                        None => break,
+                    }
+                }
+            }
+        } else if word(c) {
+            while let Some((i, next)) = chars.next_if(|&(_, next)| word(next)) {
+                end = i + next.len_utf8();
+            }
+        }
+        tokens.push((start, end));
+    }
+    tokens
+}
+
+/// Whether a printed token stands for a typed one
+fn same_token(printed: &str, typed: &str) -> bool {
+    printed.eq_ignore_ascii_case(typed)
+        || matches!((printed.parse::<f64>(), typed.parse::<f64>()), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Offsets of comma- or semicolon-separated items starting at `first`
+fn sequence<'e>(items: impl Iterator<Item = &'e Expr>, first: usize) -> Vec<(&'e Expr, usize)> {
+    let mut offset = first;
+    items
+        .map(|item| {
+            let at = offset;
+            offset += item.to_string().len() + 1;
+            (item, at)
+        })
+        .collect()
+}
+
+/// Whether an expression is a reference that may cover several cells
+fn is_area(expr: &Expr) -> bool {
+    match expr {
+        Expr::Range { .. } | Expr::Union(_) | Expr::Intersection { .. } => true,
+        Expr::SheetRef { reference, .. } => is_area(reference),
+        Expr::Grouped(inner) => is_area(inner),
+        Expr::Structured(reference) => {
+            reference.area != TableArea::ThisRow
+                || reference.columns.as_ref().is_none_or(|(first, last)| first != last)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_nom::NomParser;
+    use crate::CrossSheetEvaluator;
+
+    fn trace(formula: &str) -> TraceNode {
+        let ast = NomParser::new().parse(formula).unwrap();
+        CrossSheetEvaluator::with_sheet(
+            |_, row, col| match (row, col) {
+                (0, 0) => CellValue::Number(4.0),
+                (1, 0) => CellValue::Number(6.0),
+                _ => CellValue::Empty,
+            },
+            "Sheet1",
+        )
+        .trace(&ast)
+    }
+
+    #[test]
+    fn test_trace_binary() {
+        let root = trace("=(A1+2)*3");
+        assert_eq!(root.text, "(A1+2)*3");
+        assert_eq!(root.value, Some(CellValue::Number(18.0)));
+
+        let grouped = &root.children[0];
+        assert_eq!(grouped.text, "(A1+2)");
+        let sum = &grouped.children[0];
+        assert_eq!((sum.start, sum.end), (1, 5));
+        assert_eq!(sum.value, Some(CellValue::Number(6.0)));
+        assert_eq!(sum.children[0].value, Some(CellValue::Number(4.0)));
+
+        let three = &root.children[1];
+        assert_eq!(&root.text[three.start..three.end], "3");
+    }
+
+    #[test]
+    fn test_trace_chain() {
+        // A1 = 4, A2 = 6
+        let root = trace("=A1+A2-1+2*3");
+        assert_eq!(root.value, Some(CellValue::Number(15.0)));
+        let operands: Vec<_> = root.children.iter().map(|c| (c.text.as_str(), c.start)).collect();
+        assert_eq!(operands, [("A1", 0), ("A2", 3), ("1", 6), ("2*3", 8)]);
+        assert_eq!(root.children[3].value, Some(CellValue::Number(6.0)));
+    }
+
+    #[test]
+    fn test_trace_function_arguments() {
+        let root = trace("=LOG10(SUM(A1:A2,90))");
+        assert_eq!(root.value, Some(CellValue::Number(2.0)));
+
+        let sum = &root.children[0];
+        assert_eq!(sum.value, Some(CellValue::Number(100.0)));
+
+        // The range is passed on as a reference, not evaluated
+        let range = &sum.children[0];
+        assert_eq!(&root.text[range.start..range.end], "A1:A2");
+        assert_eq!(range.value, None);
+        assert!(range.children.is_empty());
+
+        let ninety = &sum.children[1];
+        assert_eq!(&root.text[ninety.start..ninety.end], "90");
+    }
+
+    #[test]
+    fn test_spans_match_text() {
+        let root = trace("=IF(A1>3,{1,2;3,4},-A2%)&\"x\"");
+        root.walk(&mut |node| assert_eq!(&root.text[node.start..node.end], node.text));
+    }
+
+    #[test]
+    fn test_source_text() {
+        let formula = "= sum( a1 , 2.50 )*'My Sheet'!B2";
+        let root = with_source_text(trace(formula), formula);
+        assert_eq!(root.text, "sum( a1 , 2.50 )*'My Sheet'!B2");
+        root.walk(&mut |node| assert_eq!(&root.text[node.start..node.end], node.text));
+        let sum = &root.children[0];
+        assert_eq!(sum.text, "sum( a1 , 2.50 )");
+        assert_eq!(sum.value, Some(CellValue::Number(6.5)));
+        assert_eq!(sum.children[1].text, "2.50");
+
+        // Text that can't be lined up keeps the printed form
+        let formula = "=1E+3";
+        assert_eq!(with_source_text(trace(formula), formula).text, "1000");
+    }
+
+    #[test]
+    fn test_untaken_branch_is_traced() {
+        let root = trace("=IF(A1>3,A2,1/0)");
+        assert_eq!(root.value, Some(CellValue::Number(6.0)));
+        let untaken = &root.children[2];
+        assert_eq!(untaken.value, Some(CellValue::Error(rusheet_core::CellError::DivisionByZero)));
+    }
+}
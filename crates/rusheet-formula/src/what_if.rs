@@ -0,0 +1,454 @@
+//! What-if analysis: goal seek and data tables
+//!
+//! Both work by writing trial values into input cells and recalculating
+//! incrementally. Input cells are restored afterwards, so the workbook is
+//! left as it was found.
+
+use rusheet_core::{Cell, CellContent, CellCoord, CellRange, CellValue, RusheetError};
+use serde::Serialize;
+
+use crate::calc::CalcEngine;
+
+/// Limits for [`CalcEngine::goal_seek`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GoalSeekOptions {
+    /// How close the target must get to the goal
+    pub tolerance: f64,
+    /// Trial values to try before giving up
+    pub max_iterations: usize,
+}
+
+impl Default for GoalSeekOptions {
+    fn default() -> Self {
+        Self {
+            tolerance: 0.001,
+            max_iterations: 100,
+        }
+    }
+}
+
+/// Outcome of a goal seek
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoalSeekResult {
+    /// Best input value found
+    pub input: f64,
+    /// Target cell's value with that input
+    pub value: f64,
+    /// Number of trial values evaluated
+    pub iterations: usize,
+    /// Whether the target got within tolerance of the goal
+    pub converged: bool,
+}
+
+impl CalcEngine {
+    /// Find the value of `input` that makes the formula in `target` equal `goal`
+    ///
+    /// Uses the secant method, falling back to bisection once the goal has
+    /// been bracketed. Both cells are on the active sheet. The input cell
+    /// keeps its original value; apply [`GoalSeekResult::input`] to accept
+    /// the solution.
+    pub fn goal_seek(
+        &mut self,
+        target: CellCoord,
+        goal: f64,
+        input: CellCoord,
+        options: GoalSeekOptions,
+    ) -> Result<GoalSeekResult, RusheetError> {
+        if !self.is_formula(target) {
+            return Err(RusheetError::Generic(
+                "Goal seek target must contain a formula".to_string(),
+            ));
+        }
+        let start = self.value_at(input).as_number().unwrap_or(0.0);
+
+        self.with_inputs(&[input], |engine| {
+            let mut best: Option<(f64, f64)> = None;
+            let mut eval = |x: f64| {
+                engine.set_input(input, CellValue::Number(x));
+                let value = engine.value_at(target).as_number()?;
+                if best.is_none_or(|(_, v)| (value - goal).abs() < (v - goal).abs()) {
+                    best = Some((x, value));
+                }
+                Some(value - goal)
+            };
+
+            let step = if start == 0.0 {
+                0.01
+            } else {
+                start.abs() * 0.01
+            };
+            let (mut x0, mut f0) = (start, eval(start));
+            let (mut x1, mut f1) = (start + step, eval(start + step));
+            let mut iterations = 2;
+            // An input value, the target's distance from the goal there, and
+            // an input value on the other side of the goal
+            let mut bracket: Option<(f64, f64, f64)> = None;
+
+            while iterations < options.max_iterations {
+                if let (Some(a), Some(b)) = (f0, f1) {
+                    if a.abs() <= options.tolerance || b.abs() <= options.tolerance {
+                        break;
+                    }
+                    if bracket.is_none() && a.signum() != b.signum() {
+                        bracket = Some((x0, a, x1));
+                    }
+                }
+
+                let secant = match (f0, f1) {
+                    (Some(a), Some(b)) if a != b => Some(x1 - b * (x1 - x0) / (b - a)),
+                    _ => None,
+                };
+                let next = match (secant, bracket) {
+                    (Some(x), Some((lo, _, hi))) if x > lo.min(hi) && x < lo.max(hi) => x,
+                    (_, Some((lo, _, hi))) => (lo + hi) / 2.0,
+                    (Some(x), None) if x.is_finite() => x,
+                    // Flat or erroring: step further out and try again
+                    _ => x1 + (x1 - x0) * 2.0,
+                };
+                let f_next = eval(next);
+                iterations += 1;
+
+                // Narrow the bracket, keeping the goal inside it
+                if let (Some((lo, f_lo, hi)), Some(f)) = (bracket, f_next) {
+                    bracket = if f.signum() == f_lo.signum() {
+                        Some((next, f, hi))
+                    } else {
+                        Some((lo, f_lo, next))
+                    };
+                }
+                (x0, f0) = (x1, f1);
+                (x1, f1) = (next, f_next);
+            }
+
+            let (input, value) = best.ok_or_else(|| {
+                RusheetError::Generic("Goal seek target did not evaluate to a number".to_string())
+            })?;
+            Ok(GoalSeekResult {
+                input,
+                value,
+                iterations,
+                converged: (value - goal).abs() <= options.tolerance,
+            })
+        })?
+    }
+
+    /// Evaluate `outputs` once for each of `values` placed in `input`
+    ///
+    /// Returns one row per input value, with one column per output cell.
+    pub fn data_table(
+        &mut self,
+        input: CellCoord,
+        values: &[CellValue],
+        outputs: &[CellCoord],
+    ) -> Result<Vec<Vec<CellValue>>, RusheetError> {
+        self.with_inputs(&[input], |engine| {
+            values
+                .iter()
+                .map(|value| {
+                    engine.set_input(input, value.clone());
+                    outputs
+                        .iter()
+                        .map(|&output| engine.value_at(output))
+                        .collect()
+                })
+                .collect()
+        })
+    }
+
+    /// Evaluate `output` for every combination of two inputs
+    ///
+    /// Returns one row per value of `col_input` and one column per value of
+    /// `row_input`, matching a table whose top row feeds `row_input` and
+    /// whose left column feeds `col_input`.
+    pub fn data_table_2d(
+        &mut self,
+        output: CellCoord,
+        row_input: CellCoord,
+        row_values: &[CellValue],
+        col_input: CellCoord,
+        col_values: &[CellValue],
+    ) -> Result<Vec<Vec<CellValue>>, RusheetError> {
+        self.with_inputs(&[row_input, col_input], |engine| {
+            col_values
+                .iter()
+                .map(|col_value| {
+                    engine.set_input(col_input, col_value.clone());
+                    row_values
+                        .iter()
+                        .map(|row_value| {
+                            engine.set_input(row_input, row_value.clone());
+                            engine.value_at(output)
+                        })
+                        .collect()
+                })
+                .collect()
+        })
+    }
+
+    /// Evaluate a data table laid out on the active sheet like `TABLE()`
+    ///
+    /// With only `col_input`, input values run down the first column of
+    /// `range` and the formulas to evaluate sit along its first row. With
+    /// only `row_input` the layout is transposed. With both, the formula is in
+    /// the top-left corner, values for `row_input` run along the first row
+    /// and values for `col_input` down the first column. Returns the values
+    /// for the rest of the range, row by row.
+    pub fn data_table_range(
+        &mut self,
+        range: CellRange,
+        row_input: Option<CellCoord>,
+        col_input: Option<CellCoord>,
+    ) -> Result<Vec<Vec<CellValue>>, RusheetError> {
+        let (top, left) = (range.start.row, range.start.col);
+        let first_row: Vec<_> = (left + 1..=range.end.col)
+            .map(|col| CellCoord::new(top, col))
+            .collect();
+        let first_col: Vec<_> = (top + 1..=range.end.row)
+            .map(|row| CellCoord::new(row, left))
+            .collect();
+        let values_in = |engine: &Self, cells: &[CellCoord]| -> Vec<CellValue> {
+            cells.iter().map(|&c| engine.value_at(c)).collect()
+        };
+
+        match (row_input, col_input) {
+            (Some(row_input), Some(col_input)) => {
+                let row_values = values_in(self, &first_row);
+                let col_values = values_in(self, &first_col);
+                self.data_table_2d(range.start, row_input, &row_values, col_input, &col_values)
+            }
+            (None, Some(col_input)) => {
+                let values = values_in(self, &first_col);
+                self.data_table(col_input, &values, &first_row)
+            }
+            (Some(row_input), None) => {
+                let values = values_in(self, &first_row);
+                let by_value = self.data_table(row_input, &values, &first_col)?;
+                // One row per formula, one column per input value
+                Ok((0..first_col.len())
+                    .map(|i| by_value.iter().map(|row| row[i].clone()).collect())
+                    .collect())
+            }
+            (None, None) => Err(RusheetError::Generic(
+                "Data table needs a row or column input cell".to_string(),
+            )),
+        }
+    }
+
+    /// Run `f` with `inputs` free to overwrite, restoring them afterwards
+    fn with_inputs<R>(
+        &mut self,
+        inputs: &[CellCoord],
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> Result<R, RusheetError> {
+        if let Some(input) = inputs.iter().find(|&&c| self.is_formula(c)) {
+            return Err(RusheetError::Generic(format!(
+                "Input cell ({}, {}) must contain a value, not a formula",
+                input.row, input.col
+            )));
+        }
+
+        let sheet = self.workbook().active_sheet();
+        let saved: Vec<(CellCoord, Option<Cell>)> = inputs
+            .iter()
+            .map(|&c| (c, sheet.get_cell(c).cloned()))
+            .collect();
+
+        let result = f(self);
+
+        let sheet = self.workbook_mut().active_sheet_mut();
+        for (coord, cell) in saved {
+            match cell {
+                Some(cell) => sheet.set_cell(coord, cell),
+                None => sheet.remove_cell(coord),
+            }
+        }
+        self.cells_changed(inputs.iter().copied());
+        self.recalculate();
+
+        Ok(result)
+    }
+
+    /// Place a trial value in an input cell and recalculate what depends on it
+    fn set_input(&mut self, coord: CellCoord, value: CellValue) {
+        self.workbook_mut()
+            .active_sheet_mut()
+            .get_cell_mut(coord)
+            .content = CellContent::Value {
+            value,
+            original_input: None,
+        };
+        self.cell_changed(coord);
+        self.recalculate();
+    }
+
+    fn value_at(&self, coord: CellCoord) -> CellValue {
+        self.workbook().active_sheet().get_cell_value(coord).clone()
+    }
+
+    fn is_formula(&self, coord: CellCoord) -> bool {
+        self.workbook()
+            .active_sheet()
+            .get_cell(coord)
+            .is_some_and(|c| c.content.is_formula())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine(inputs: &[(u32, u32, &str)]) -> CalcEngine {
+        let mut engine = CalcEngine::default();
+        for &(row, col, input) in inputs {
+            engine.set_cell_input(CellCoord::new(row, col), input);
+        }
+        engine
+    }
+
+    #[test]
+    fn test_goal_seek_linear() {
+        // B1 = A1 * 3 + 2; find A1 such that B1 = 20
+        let mut engine = engine(&[(0, 0, "1"), (0, 1, "=A1*3+2")]);
+        let result = engine
+            .goal_seek(
+                CellCoord::new(0, 1),
+                20.0,
+                CellCoord::new(0, 0),
+                GoalSeekOptions::default(),
+            )
+            .unwrap();
+
+        assert!(result.converged);
+        assert!((result.input - 6.0).abs() < 1e-6);
+        // The input is restored
+        assert_eq!(
+            engine.value_at(CellCoord::new(0, 0)),
+            CellValue::Number(1.0)
+        );
+        assert_eq!(
+            engine.value_at(CellCoord::new(0, 1)),
+            CellValue::Number(5.0)
+        );
+    }
+
+    #[test]
+    fn test_goal_seek_nonlinear() {
+        // Monthly payment on a loan: find the rate where it comes to 1000
+        let mut engine = engine(&[(0, 0, "0.01"), (0, 1, "=30000*A1/(1-(1+A1)^(-36))")]);
+        let options = GoalSeekOptions {
+            tolerance: 1e-6,
+            ..Default::default()
+        };
+        let result = engine
+            .goal_seek(CellCoord::new(0, 1), 1000.0, CellCoord::new(0, 0), options)
+            .unwrap();
+
+        assert!(result.converged);
+        assert!((result.value - 1000.0).abs() <= 1e-6);
+        assert!(result.input > 0.0 && result.input < 0.05);
+    }
+
+    #[test]
+    fn test_goal_seek_unreachable() {
+        let mut engine = engine(&[(0, 0, "1"), (0, 1, "=A1*A1")]);
+        let options = GoalSeekOptions {
+            max_iterations: 20,
+            ..Default::default()
+        };
+        let result = engine
+            .goal_seek(CellCoord::new(0, 1), -4.0, CellCoord::new(0, 0), options)
+            .unwrap();
+
+        assert!(!result.converged);
+        assert!(result.iterations <= 20);
+    }
+
+    #[test]
+    fn test_goal_seek_rejects_formula_input() {
+        let mut engine = engine(&[(0, 0, "=1"), (0, 1, "=A1*2")]);
+        let options = GoalSeekOptions::default();
+        assert!(engine
+            .goal_seek(CellCoord::new(0, 1), 4.0, CellCoord::new(0, 0), options)
+            .is_err());
+        assert!(engine
+            .goal_seek(CellCoord::new(0, 2), 4.0, CellCoord::new(0, 1), options)
+            .is_err());
+    }
+
+    #[test]
+    fn test_data_table() {
+        // B1 = A1 * 2, C1 = A1 + 1
+        let mut engine = engine(&[(0, 0, "0"), (0, 1, "=A1*2"), (0, 2, "=A1+1")]);
+        let values = [CellValue::Number(1.0), CellValue::Number(5.0)];
+        let table = engine
+            .data_table(
+                CellCoord::new(0, 0),
+                &values,
+                &[CellCoord::new(0, 1), CellCoord::new(0, 2)],
+            )
+            .unwrap();
+
+        assert_eq!(
+            table,
+            vec![
+                vec![CellValue::Number(2.0), CellValue::Number(2.0)],
+                vec![CellValue::Number(10.0), CellValue::Number(6.0)],
+            ]
+        );
+        assert_eq!(engine.value_at(CellCoord::new(0, 0)), CellValue::Number(0.0));
+        assert_eq!(
+            engine.value_at(CellCoord::new(0, 2)),
+            CellValue::Number(1.0)
+        );
+    }
+
+    #[test]
+    fn test_data_table_range_two_inputs() {
+        // A1 * B1, tabulated in D1:F3 with A1 along the top and B1 down the side
+        let mut engine = engine(&[
+            (0, 0, "1"),
+            (0, 1, "1"),
+            (0, 3, "=A1*B1"),
+            (0, 4, "2"),
+            (0, 5, "3"),
+            (1, 3, "10"),
+            (2, 3, "20"),
+        ]);
+        let range = CellRange::new(CellCoord::new(0, 3), CellCoord::new(2, 5));
+        let table = engine
+            .data_table_range(
+                range,
+                Some(CellCoord::new(0, 0)),
+                Some(CellCoord::new(0, 1)),
+            )
+            .unwrap();
+
+        assert_eq!(
+            table,
+            vec![
+                vec![CellValue::Number(20.0), CellValue::Number(30.0)],
+                vec![CellValue::Number(40.0), CellValue::Number(60.0)],
+            ]
+        );
+        assert_eq!(
+            engine.value_at(CellCoord::new(0, 3)),
+            CellValue::Number(1.0)
+        );
+    }
+
+    #[test]
+    fn test_data_table_range_row_input() {
+        // Input values along the top of D1:F2, formula down the side
+        let mut engine = engine(&[(0, 4, "2"), (0, 5, "3"), (1, 3, "=A1^2")]);
+        let range = CellRange::new(CellCoord::new(0, 3), CellCoord::new(1, 5));
+        let table = engine
+            .data_table_range(range, Some(CellCoord::new(0, 0)), None)
+            .unwrap();
+
+        assert_eq!(
+            table,
+            vec![vec![CellValue::Number(4.0), CellValue::Number(9.0)]]
+        );
+    }
+}
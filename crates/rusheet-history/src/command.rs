@@ -1,6 +1,15 @@
-use rusheet_core::{Cell, CellContent, CellCoord, CellFormat, CellRange, CellValue, Sheet};
+use rusheet_core::{
+    parse_cell_input_with, Axis, BorderLine, BorderTarget, Borders, Cell, CellContent, CellCoord,
+    CellRange, CellValue, Comment, CommentReply, DateOrder, DateSystem, FormatDiff, Hyperlink,
+    LinkTarget, NamedStyle, Outline, PivotTable, ReplacedCells, RichText, RusheetError, Sheet, SummaryPosition,
+    Table, TableStyle, TotalsFunction, Workbook,
+};
 use rusheet_core::sheet::FilterState;
-use rusheet_formula::{shift_formula_rows, shift_formula_cols};
+use rusheet_core::state::{ClipboardData, ClipboardMode};
+use rusheet_formula::{
+    move_formula_in, rename_table_in_formula, shift_formula_cols, shift_formula_rows,
+    shift_location_cols, shift_location_rows, translate_formula,
+};
 use std::collections::HashSet;
 
 /// Type alias for boxed commands
@@ -14,9 +23,35 @@ pub trait Command: std::fmt::Debug + Send + Sync {
     /// Undo the command, returning affected cell coordinates
     fn undo(&mut self, sheet: &mut Sheet) -> Vec<CellCoord>;
 
+    /// Execute the command on sheet `sheet_index` of a workbook
+    ///
+    /// Commands whose effects reach beyond the edited sheet override this;
+    /// the default runs [`execute`](Self::execute) on that sheet. Returns
+    /// affected cells on the edited sheet.
+    fn execute_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Vec<CellCoord> {
+        match workbook.get_sheet_mut(sheet_index) {
+            Some(sheet) => self.execute(sheet),
+            None => Vec::new(),
+        }
+    }
+
+    /// Undo the command on sheet `sheet_index` of a workbook
+    fn undo_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Vec<CellCoord> {
+        match workbook.get_sheet_mut(sheet_index) {
+            Some(sheet) => self.undo(sheet),
+            None => Vec::new(),
+        }
+    }
+
     /// Get a description of this command (for UI display)
     fn description(&self) -> &str;
 
+    /// Whether the command moves cells or rewrites formulas outside the
+    /// cells it reports, so dependencies need re-reading in full
+    fn is_structural(&self) -> bool {
+        false
+    }
+
     /// Try to merge with another command (for typing sequences)
     /// Returns true if merge was successful
     fn merge(&mut self, _other: &dyn Command) -> bool {
@@ -35,6 +70,11 @@ pub struct SetCellValueCommand {
     coord: CellCoord,
     new_content: CellContent,
     old_content: Option<CellContent>,
+    /// Number format for typed date or time input
+    number_format: Option<String>,
+    old_number_format: Option<Option<String>>,
+    rich_text: Option<RichText>,
+    old_rich_text: Option<RichText>,
 }
 
 impl SetCellValueCommand {
@@ -43,6 +83,10 @@ impl SetCellValueCommand {
             coord,
             new_content,
             old_content: None,
+            number_format: None,
+            old_number_format: None,
+            rich_text: None,
+            old_rich_text: None,
         }
     }
 
@@ -54,22 +98,66 @@ impl SetCellValueCommand {
     }
 
     pub fn from_input(coord: CellCoord, input: &str) -> Self {
-        let content = rusheet_core::sheet::parse_cell_input(input);
-        Self::new(coord, content)
+        Self::from_input_with(coord, input, DateOrder::default(), DateSystem::default())
     }
-}
 
-impl Command for SetCellValueCommand {
-    fn execute(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+    /// Parse input, reading dates and times in the given order and date system
+    ///
+    /// A date or time also gives the cell a matching number format.
+    pub fn from_input_with(
+        coord: CellCoord,
+        input: &str,
+        order: DateOrder,
+        system: DateSystem,
+    ) -> Self {
+        let (content, number_format) = parse_cell_input_with(input, order, system);
+        Self {
+            number_format,
+            ..Self::new(coord, content)
+        }
+    }
+
+    /// Set text with formatting runs
+    pub fn from_rich_text(coord: CellCoord, text: RichText) -> Self {
+        let mut cell = Cell::default();
+        cell.set_rich_text(text);
+        Self {
+            rich_text: cell.rich_text,
+            ..Self::new(coord, cell.content)
+        }
+    }
+
+    /// Set the value, keeping a date or time format the cell has of its own
+    /// or from its named style in `styles`
+    fn apply(&mut self, sheet: &mut Sheet, styles: &[NamedStyle]) -> Vec<CellCoord> {
         // Capture old state for undo
-        self.old_content = sheet.get_cell(self.coord).map(|c| c.content.clone());
+        let old_cell = sheet.get_cell(self.coord);
+        self.old_content = old_cell.map(|c| c.content.clone());
+        self.old_number_format = old_cell.and_then(|c| c.format.number_format.clone());
+        self.old_rich_text = old_cell.and_then(|c| c.rich_text.clone());
 
         // Apply new value
         let cell = sheet.get_cell_mut(self.coord);
         cell.content = self.new_content.clone();
+        cell.rich_text = self.rich_text.clone();
+        cell.assign_input_format(self.number_format.clone(), styles);
 
         vec![self.coord]
     }
+}
+
+impl Command for SetCellValueCommand {
+    fn execute(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        self.apply(sheet, &[])
+    }
+
+    fn execute_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Vec<CellCoord> {
+        let Workbook { sheets, styles, .. } = workbook;
+        match sheets.get_mut(sheet_index) {
+            Some(sheet) => self.apply(sheet, styles),
+            None => Vec::new(),
+        }
+    }
 
     fn undo(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
         let cell = sheet.get_cell_mut(self.coord);
@@ -80,6 +168,8 @@ impl Command for SetCellValueCommand {
                 value: CellValue::Empty,
                 original_input: None,
             });
+        cell.format.number_format = self.old_number_format.clone();
+        cell.rich_text = self.old_rich_text.clone();
 
         vec![self.coord]
     }
@@ -91,15 +181,19 @@ impl Command for SetCellValueCommand {
 }
 
 /// Set cell format
+///
+/// The cell keeps its borders, which are set with [`SetBordersCommand`].
 #[derive(Debug)]
 pub struct SetCellFormatCommand {
     coord: CellCoord,
-    new_format: CellFormat,
-    old_format: Option<CellFormat>,
+    new_format: FormatDiff,
+    old_format: Option<FormatDiff>,
 }
 
 impl SetCellFormatCommand {
-    pub fn new(coord: CellCoord, new_format: CellFormat) -> Self {
+    /// `new_format` is the named style and changes to it the cell gets, as
+    /// from [`FormatDiff::against_style`]
+    pub fn new(coord: CellCoord, new_format: FormatDiff) -> Self {
         Self {
             coord,
             new_format,
@@ -115,7 +209,10 @@ impl Command for SetCellFormatCommand {
 
         // Then apply new format (mutable borrow)
         let cell = sheet.get_cell_mut(self.coord);
-        cell.format = self.new_format.clone();
+        cell.format = FormatDiff {
+            borders: cell.format.borders,
+            ..self.new_format.clone()
+        };
 
         vec![self.coord]
     }
@@ -133,16 +230,20 @@ impl Command for SetCellFormatCommand {
 }
 
 /// Set formatting for a range of cells
+///
+/// Each cell keeps its borders, which are set with [`SetBordersCommand`].
 #[derive(Debug)]
 pub struct SetRangeFormatCommand {
     start: CellCoord,
     end: CellCoord,
-    new_format: CellFormat,
-    old_formats: Vec<(CellCoord, CellFormat)>,
+    new_format: FormatDiff,
+    old_formats: Vec<(CellCoord, FormatDiff)>,
 }
 
 impl SetRangeFormatCommand {
-    pub fn new(start: CellCoord, end: CellCoord, new_format: CellFormat) -> Self {
+    /// `new_format` is the named style and changes to it the cells get, as
+    /// from [`FormatDiff::against_style`]
+    pub fn new(start: CellCoord, end: CellCoord, new_format: FormatDiff) -> Self {
         Self {
             start,
             end,
@@ -174,7 +275,10 @@ impl Command for SetRangeFormatCommand {
 
                 // Apply new format (mutable borrow)
                 let cell = sheet.get_cell_mut(coord);
-                cell.format = self.new_format.clone();
+                cell.format = FormatDiff {
+                    borders: cell.format.borders,
+                    ..self.new_format.clone()
+                };
 
                 affected.push(coord);
             }
@@ -200,6 +304,45 @@ impl Command for SetRangeFormatCommand {
     }
 }
 
+/// Set or clear borders on a range, resolving edges shared with neighbours
+#[derive(Debug)]
+pub struct SetBordersCommand {
+    range: CellRange,
+    target: BorderTarget,
+    line: Option<BorderLine>,
+    old_borders: Vec<(CellCoord, Borders)>,
+}
+
+impl SetBordersCommand {
+    /// Set the targeted borders of `range` to `line`, or clear them if `None`
+    pub fn new(range: CellRange, target: BorderTarget, line: Option<BorderLine>) -> Self {
+        Self {
+            range,
+            target,
+            line,
+            old_borders: Vec::new(),
+        }
+    }
+}
+
+impl Command for SetBordersCommand {
+    fn execute(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        self.old_borders = sheet.set_borders(self.range, self.target, self.line);
+        self.old_borders.iter().map(|(coord, _)| *coord).collect()
+    }
+
+    fn undo(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        for (coord, borders) in &self.old_borders {
+            sheet.set_cell_borders(*coord, *borders);
+        }
+        self.old_borders.iter().map(|(coord, _)| *coord).collect()
+    }
+
+    fn description(&self) -> &str {
+        "Set borders"
+    }
+}
+
 /// Clear cell content (but keep format)
 #[derive(Debug)]
 pub struct ClearCellCommand {
@@ -339,9 +482,139 @@ impl Command for CompositeCommand {
         affected
     }
 
+    fn execute_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Vec<CellCoord> {
+        let mut affected = Vec::new();
+        for cmd in &mut self.commands {
+            affected.extend(cmd.execute_in(workbook, sheet_index));
+        }
+        affected
+    }
+
+    fn undo_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Vec<CellCoord> {
+        let mut affected = Vec::new();
+        for cmd in self.commands.iter_mut().rev() {
+            affected.extend(cmd.undo_in(workbook, sheet_index));
+        }
+        affected
+    }
+
     fn description(&self) -> &str {
         &self.description
     }
+
+    fn is_structural(&self) -> bool {
+        self.commands.iter().any(|cmd| cmd.is_structural())
+    }
+}
+
+/// A hyperlink retargeted by a row/column insert or delete:
+/// (cell after the shift, link before, link after)
+type LinkUpdate = (CellCoord, Hyperlink, Hyperlink);
+
+/// Shift the sheet's internal hyperlink locations with `shift`
+///
+/// Updates are keyed by where each link's cell ends up, which `moved` gives
+/// (`None` for deleted cells). Locations whose target was deleted become
+/// `#REF!`, like formula references.
+fn link_updates(
+    sheet: &Sheet,
+    moved: impl Fn(CellCoord) -> Option<CellCoord>,
+    shift: impl Fn(&str) -> Option<String>,
+) -> Vec<LinkUpdate> {
+    sheet
+        .hyperlinks()
+        .into_iter()
+        .filter_map(|(coord, link)| {
+            let location = link.internal_location()?;
+            let shifted = shift(location).unwrap_or_else(|| "#REF!".to_string());
+            if shifted == location {
+                return None;
+            }
+            let new_link = Hyperlink {
+                target: LinkTarget::Location(shifted),
+                ..link.clone()
+            };
+            Some((moved(coord)?, link.clone(), new_link))
+        })
+        .collect()
+}
+
+/// Apply link updates, or restore the links from before them
+fn apply_link_updates(sheet: &mut Sheet, updates: &[LinkUpdate], undo: bool) -> Vec<CellCoord> {
+    updates
+        .iter()
+        .map(|(coord, old, new)| {
+            let link = if undo { old } else { new };
+            sheet.set_hyperlink(*coord, Some(link.clone()));
+            *coord
+        })
+        .collect()
+}
+
+/// What an insert or delete changed beyond its own sheet, for undo
+#[derive(Debug, Default)]
+struct WorkbookShift {
+    pivots_before: Vec<PivotTable>,
+    /// Updates to links on other sheets, by sheet index
+    links: Vec<(usize, Vec<LinkUpdate>)>,
+}
+
+impl WorkbookShift {
+    /// Move pivot tables, and links on other sheets that name the edited
+    /// sheet, along with an insert or delete
+    fn apply(
+        workbook: &mut Workbook,
+        sheet_index: usize,
+        (axis, at, count, insert): (Axis, u32, u32, bool),
+    ) -> Self {
+        let pivots_before = workbook.pivots.clone();
+        workbook.shift_pivots(sheet_index, axis, at, count, insert);
+
+        let Some(name) = workbook.get_sheet(sheet_index).map(|s| s.name.clone()) else {
+            return Self { pivots_before, links: Vec::new() };
+        };
+        let delta = if insert { count as i32 } else { -(count as i32) };
+        let shift = |location: &str| {
+            if !location_names_sheet(location, &name) {
+                return Some(location.to_string());
+            }
+            match axis {
+                Axis::Rows => shift_location_rows(location, &name, at, delta),
+                Axis::Cols => shift_location_cols(location, &name, at, delta),
+            }
+        };
+        let mut links = Vec::new();
+        for (index, sheet) in workbook.sheets.iter_mut().enumerate() {
+            if index == sheet_index {
+                continue;
+            }
+            let updates = link_updates(sheet, Some, shift);
+            if !updates.is_empty() {
+                apply_link_updates(sheet, &updates, false);
+                links.push((index, updates));
+            }
+        }
+        Self { pivots_before, links }
+    }
+
+    fn undo(self, workbook: &mut Workbook) {
+        workbook.pivots = self.pivots_before;
+        for (index, updates) in &self.links {
+            if let Some(sheet) = workbook.get_sheet_mut(*index) {
+                apply_link_updates(sheet, updates, true);
+            }
+        }
+    }
+}
+
+/// Whether a link location is qualified with the sheet called `name`
+fn location_names_sheet(location: &str, name: &str) -> bool {
+    location.rsplit_once('!').is_some_and(|(sheet, _)| {
+        match sheet.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) {
+            Some(quoted) => quoted.replace("''", "'") == name,
+            None => sheet == name,
+        }
+    })
 }
 
 /// Insert rows at the given position
@@ -352,6 +625,8 @@ pub struct InsertRowsCommand {
     // For undo: track which cells were shifted and their old formulas
     shifted_cells: Vec<(CellCoord, CellCoord)>,  // (old_coord, new_coord)
     formula_updates: Vec<(CellCoord, String, String)>,  // (coord, old_formula, new_formula)
+    link_updates: Vec<LinkUpdate>,
+    workbook_shift: WorkbookShift,
 }
 
 impl InsertRowsCommand {
@@ -361,6 +636,8 @@ impl InsertRowsCommand {
             count,
             shifted_cells: Vec::new(),
             formula_updates: Vec::new(),
+            link_updates: Vec::new(),
+            workbook_shift: WorkbookShift::default(),
         }
     }
 }
@@ -388,6 +665,14 @@ impl Command for InsertRowsCommand {
             }
         }
 
+        // Internal hyperlinks follow the cells they point at
+        let (at, count) = (self.at_row, self.count);
+        self.link_updates = link_updates(
+            sheet,
+            |c| Some(if c.row >= at { CellCoord::new(c.row + count, c.col) } else { c }),
+            |location| shift_location_rows(location, &sheet.name, at, count as i32),
+        );
+
         // Step 2: Insert rows and shift cells
         self.shifted_cells = sheet.insert_rows(self.at_row, self.count);
 
@@ -413,6 +698,8 @@ impl Command for InsertRowsCommand {
             }
         }
 
+        affected.extend(apply_link_updates(sheet, &self.link_updates, false));
+
         // Collect all affected cells from shifted cells
         for (old_coord, new_coord) in &self.shifted_cells {
             affected.push(*old_coord);
@@ -440,6 +727,8 @@ impl Command for InsertRowsCommand {
             }
         }
 
+        affected.extend(apply_link_updates(sheet, &self.link_updates, true));
+
         // Step 2: Delete the inserted rows to shift cells back
         sheet.delete_rows(self.at_row, self.count);
 
@@ -452,9 +741,32 @@ impl Command for InsertRowsCommand {
         affected
     }
 
+    fn execute_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Vec<CellCoord> {
+        let Some(sheet) = workbook.get_sheet_mut(sheet_index) else {
+            return Vec::new();
+        };
+        let affected = self.execute(sheet);
+        let shift = (Axis::Rows, self.at_row, self.count, true);
+        self.workbook_shift = WorkbookShift::apply(workbook, sheet_index, shift);
+        affected
+    }
+
+    fn undo_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Vec<CellCoord> {
+        let Some(sheet) = workbook.get_sheet_mut(sheet_index) else {
+            return Vec::new();
+        };
+        let affected = self.undo(sheet);
+        std::mem::take(&mut self.workbook_shift).undo(workbook);
+        affected
+    }
+
     fn description(&self) -> &str {
         "Insert rows"
     }
+
+    fn is_structural(&self) -> bool {
+        true
+    }
 }
 
 /// Delete rows at the given position
@@ -466,6 +778,13 @@ pub struct DeleteRowsCommand {
     deleted_cells: Vec<(CellCoord, Cell)>,
     // Track formula updates
     formula_updates: Vec<(CellCoord, String, String)>,  // (coord, old_formula, new_formula)
+    link_updates: Vec<LinkUpdate>,
+    // Groups, user-hidden indices and freeze boundary the deletion changes
+    outline_before: Outline,
+    hidden_before: Vec<u32>,
+    frozen_before: u32,
+    tables_before: Vec<Table>,
+    workbook_shift: WorkbookShift,
 }
 
 impl DeleteRowsCommand {
@@ -475,6 +794,12 @@ impl DeleteRowsCommand {
             count,
             deleted_cells: Vec::new(),
             formula_updates: Vec::new(),
+            link_updates: Vec::new(),
+            outline_before: Outline::new(),
+            hidden_before: Vec::new(),
+            frozen_before: 0,
+            tables_before: Vec::new(),
+            workbook_shift: WorkbookShift::default(),
         }
     }
 }
@@ -508,7 +833,27 @@ impl Command for DeleteRowsCommand {
             }
         }
 
+        // Internal hyperlinks follow the cells they point at
+        let (at, count) = (self.at_row, self.count);
+        self.link_updates = link_updates(
+            sheet,
+            |c| match c.row {
+                row if row >= at + count => Some(CellCoord::new(row - count, c.col)),
+                row if row >= at => None,
+                _ => Some(c),
+            },
+            |location| shift_location_rows(location, &sheet.name, at, -(count as i32)),
+        );
+
         // Step 2: Delete rows and capture deleted cells
+        self.outline_before = sheet.outline(Axis::Rows).clone();
+        self.frozen_before = sheet.frozen_rows;
+        self.hidden_before = sheet
+            .user_hidden(Axis::Rows)
+            .range(self.at_row..self.at_row + self.count)
+            .copied()
+            .collect();
+        self.tables_before = sheet.tables().to_vec();
         self.deleted_cells = sheet.delete_rows(self.at_row, self.count);
 
         // Step 3: Update formulas
@@ -533,6 +878,8 @@ impl Command for DeleteRowsCommand {
             }
         }
 
+        affected.extend(apply_link_updates(sheet, &self.link_updates, false));
+
         // Collect all affected cells
         for (coord, _) in &self.deleted_cells {
             affected.push(*coord);
@@ -568,8 +915,16 @@ impl Command for DeleteRowsCommand {
             }
         }
 
+        affected.extend(apply_link_updates(sheet, &self.link_updates, true));
+
         // Step 2: Insert rows back to make space
         sheet.insert_rows(self.at_row, self.count);
+        sheet.restore_tables(self.tables_before.clone());
+        sheet.set_outline(Axis::Rows, self.outline_before.clone());
+        sheet.frozen_rows = self.frozen_before;
+        for &index in &self.hidden_before {
+            sheet.set_hidden(Axis::Rows, index, index, true);
+        }
 
         // Step 3: Restore deleted cells
         for (coord, cell) in &self.deleted_cells {
@@ -598,9 +953,32 @@ impl Command for DeleteRowsCommand {
         affected
     }
 
+    fn execute_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Vec<CellCoord> {
+        let Some(sheet) = workbook.get_sheet_mut(sheet_index) else {
+            return Vec::new();
+        };
+        let affected = self.execute(sheet);
+        let shift = (Axis::Rows, self.at_row, self.count, false);
+        self.workbook_shift = WorkbookShift::apply(workbook, sheet_index, shift);
+        affected
+    }
+
+    fn undo_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Vec<CellCoord> {
+        let Some(sheet) = workbook.get_sheet_mut(sheet_index) else {
+            return Vec::new();
+        };
+        let affected = self.undo(sheet);
+        std::mem::take(&mut self.workbook_shift).undo(workbook);
+        affected
+    }
+
     fn description(&self) -> &str {
         "Delete rows"
     }
+
+    fn is_structural(&self) -> bool {
+        true
+    }
 }
 
 /// Insert columns at the given position
@@ -611,6 +989,8 @@ pub struct InsertColsCommand {
     // For undo: track which cells were shifted and their old formulas
     shifted_cells: Vec<(CellCoord, CellCoord)>,  // (old_coord, new_coord)
     formula_updates: Vec<(CellCoord, String, String)>,  // (coord, old_formula, new_formula)
+    link_updates: Vec<LinkUpdate>,
+    workbook_shift: WorkbookShift,
 }
 
 impl InsertColsCommand {
@@ -620,6 +1000,8 @@ impl InsertColsCommand {
             count,
             shifted_cells: Vec::new(),
             formula_updates: Vec::new(),
+            link_updates: Vec::new(),
+            workbook_shift: WorkbookShift::default(),
         }
     }
 }
@@ -647,6 +1029,14 @@ impl Command for InsertColsCommand {
             }
         }
 
+        // Internal hyperlinks follow the cells they point at
+        let (at, count) = (self.at_col, self.count);
+        self.link_updates = link_updates(
+            sheet,
+            |c| Some(if c.col >= at { CellCoord::new(c.row, c.col + count) } else { c }),
+            |location| shift_location_cols(location, &sheet.name, at, count as i32),
+        );
+
         // Step 2: Insert columns and shift cells
         self.shifted_cells = sheet.insert_cols(self.at_col, self.count);
 
@@ -672,6 +1062,8 @@ impl Command for InsertColsCommand {
             }
         }
 
+        affected.extend(apply_link_updates(sheet, &self.link_updates, false));
+
         // Collect all affected cells from shifted cells
         for (old_coord, new_coord) in &self.shifted_cells {
             affected.push(*old_coord);
@@ -699,6 +1091,8 @@ impl Command for InsertColsCommand {
             }
         }
 
+        affected.extend(apply_link_updates(sheet, &self.link_updates, true));
+
         // Step 2: Delete the inserted columns to shift cells back
         sheet.delete_cols(self.at_col, self.count);
 
@@ -711,9 +1105,32 @@ impl Command for InsertColsCommand {
         affected
     }
 
+    fn execute_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Vec<CellCoord> {
+        let Some(sheet) = workbook.get_sheet_mut(sheet_index) else {
+            return Vec::new();
+        };
+        let affected = self.execute(sheet);
+        let shift = (Axis::Cols, self.at_col, self.count, true);
+        self.workbook_shift = WorkbookShift::apply(workbook, sheet_index, shift);
+        affected
+    }
+
+    fn undo_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Vec<CellCoord> {
+        let Some(sheet) = workbook.get_sheet_mut(sheet_index) else {
+            return Vec::new();
+        };
+        let affected = self.undo(sheet);
+        std::mem::take(&mut self.workbook_shift).undo(workbook);
+        affected
+    }
+
     fn description(&self) -> &str {
         "Insert columns"
     }
+
+    fn is_structural(&self) -> bool {
+        true
+    }
 }
 
 /// Delete columns at the given position
@@ -725,6 +1142,13 @@ pub struct DeleteColsCommand {
     deleted_cells: Vec<(CellCoord, Cell)>,
     // Track formula updates
     formula_updates: Vec<(CellCoord, String, String)>,  // (coord, old_formula, new_formula)
+    link_updates: Vec<LinkUpdate>,
+    // Groups, user-hidden indices and freeze boundary the deletion changes
+    outline_before: Outline,
+    hidden_before: Vec<u32>,
+    frozen_before: u32,
+    tables_before: Vec<Table>,
+    workbook_shift: WorkbookShift,
 }
 
 impl DeleteColsCommand {
@@ -734,6 +1158,12 @@ impl DeleteColsCommand {
             count,
             deleted_cells: Vec::new(),
             formula_updates: Vec::new(),
+            link_updates: Vec::new(),
+            outline_before: Outline::new(),
+            hidden_before: Vec::new(),
+            frozen_before: 0,
+            tables_before: Vec::new(),
+            workbook_shift: WorkbookShift::default(),
         }
     }
 }
@@ -767,7 +1197,27 @@ impl Command for DeleteColsCommand {
             }
         }
 
+        // Internal hyperlinks follow the cells they point at
+        let (at, count) = (self.at_col, self.count);
+        self.link_updates = link_updates(
+            sheet,
+            |c| match c.col {
+                col if col >= at + count => Some(CellCoord::new(c.row, col - count)),
+                col if col >= at => None,
+                _ => Some(c),
+            },
+            |location| shift_location_cols(location, &sheet.name, at, -(count as i32)),
+        );
+
         // Step 2: Delete columns and capture deleted cells
+        self.outline_before = sheet.outline(Axis::Cols).clone();
+        self.frozen_before = sheet.frozen_cols;
+        self.hidden_before = sheet
+            .user_hidden(Axis::Cols)
+            .range(self.at_col..self.at_col + self.count)
+            .copied()
+            .collect();
+        self.tables_before = sheet.tables().to_vec();
         self.deleted_cells = sheet.delete_cols(self.at_col, self.count);
 
         // Step 3: Update formulas
@@ -792,6 +1242,8 @@ impl Command for DeleteColsCommand {
             }
         }
 
+        affected.extend(apply_link_updates(sheet, &self.link_updates, false));
+
         // Collect all affected cells
         for (coord, _) in &self.deleted_cells {
             affected.push(*coord);
@@ -827,8 +1279,16 @@ impl Command for DeleteColsCommand {
             }
         }
 
+        affected.extend(apply_link_updates(sheet, &self.link_updates, true));
+
         // Step 2: Insert columns back to make space
         sheet.insert_cols(self.at_col, self.count);
+        sheet.restore_tables(self.tables_before.clone());
+        sheet.set_outline(Axis::Cols, self.outline_before.clone());
+        sheet.frozen_cols = self.frozen_before;
+        for &index in &self.hidden_before {
+            sheet.set_hidden(Axis::Cols, index, index, true);
+        }
 
         // Step 3: Restore deleted cells
         for (coord, cell) in &self.deleted_cells {
@@ -857,9 +1317,32 @@ impl Command for DeleteColsCommand {
         affected
     }
 
+    fn execute_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Vec<CellCoord> {
+        let Some(sheet) = workbook.get_sheet_mut(sheet_index) else {
+            return Vec::new();
+        };
+        let affected = self.execute(sheet);
+        let shift = (Axis::Cols, self.at_col, self.count, false);
+        self.workbook_shift = WorkbookShift::apply(workbook, sheet_index, shift);
+        affected
+    }
+
+    fn undo_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Vec<CellCoord> {
+        let Some(sheet) = workbook.get_sheet_mut(sheet_index) else {
+            return Vec::new();
+        };
+        let affected = self.undo(sheet);
+        std::mem::take(&mut self.workbook_shift).undo(workbook);
+        affected
+    }
+
     fn description(&self) -> &str {
         "Delete columns"
     }
+
+    fn is_structural(&self) -> bool {
+        true
+    }
 }
 
 /// Sort a range of rows by a specific column
@@ -945,91 +1428,425 @@ impl Command for SortRangeCommand {
             "Sort descending"
         }
     }
+
+    fn is_structural(&self) -> bool {
+        true
+    }
 }
 
-/// Merge cells in a range
+/// Paste a copied or cut range with its top-left corner at `dest`
+///
+/// Copied formulas have their relative references translated to the new
+/// position. Cutting moves the cells instead: the formulas keep pointing at
+/// the same cells, and references anywhere on the sheet that pointed into
+/// the cut range follow it.
 #[derive(Debug)]
-pub struct MergeCellsCommand {
-    range: CellRange,
-    // For undo: store the original cell contents that were cleared
-    cleared_cells: Vec<(CellCoord, CellContent)>,
-    // Store whether merge was successful
-    success: bool,
+pub struct PasteCommand {
+    source: CellRange,
+    dest: CellCoord,
+    cut: bool,
+    // For undo: every cell touched, with its state before the paste
+    old_cells: Vec<(CellCoord, Option<Cell>)>,
+    // and formulas on other sheets that pointed into a cut range
+    other_sheets: ReplacedCells,
 }
 
-impl MergeCellsCommand {
-    pub fn new(range: CellRange) -> Self {
+impl PasteCommand {
+    /// Paste a copy of `source`
+    pub fn copy(source: CellRange, dest: CellCoord) -> Self {
         Self {
-            range,
-            cleared_cells: Vec::new(),
-            success: false,
+            source,
+            dest,
+            cut: false,
+            old_cells: Vec::new(),
+            other_sheets: Vec::new(),
         }
     }
 
-    /// Create from start/end coordinates
-    pub fn from_coords(start_row: u32, start_col: u32, end_row: u32, end_col: u32) -> Self {
-        Self::new(CellRange::new(
-            CellCoord::new(start_row, start_col),
-            CellCoord::new(end_row, end_col),
-        ))
+    /// Move `source` to `dest`
+    pub fn cut(source: CellRange, dest: CellCoord) -> Self {
+        Self {
+            source,
+            dest,
+            cut: true,
+            old_cells: Vec::new(),
+            other_sheets: Vec::new(),
+        }
     }
-}
 
-impl Command for MergeCellsCommand {
-    fn execute(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
-        self.cleared_cells.clear();
+    /// Paste the clipboard contents, or `None` if the clipboard is empty
+    pub fn from_clipboard(data: &ClipboardData, dest: CellCoord) -> Option<Self> {
+        let top_left = data.range.top_left();
+        let bottom_right = data.range.bottom_right();
+        let source = CellRange::new(
+            CellCoord::new(top_left.row as u32, top_left.col as u32),
+            CellCoord::new(bottom_right.row as u32, bottom_right.col as u32),
+        );
 
-        // Don't merge single cells
-        if self.range.is_single_cell() {
-            self.success = false;
-            return Vec::new();
+        match data.mode {
+            ClipboardMode::Copy => Some(Self::copy(source, dest)),
+            ClipboardMode::Cut => Some(Self::cut(source, dest)),
+            ClipboardMode::Empty => None,
         }
+    }
 
-        // Check for overlap with existing merges
-        if sheet.would_overlap_merge(&self.range) {
-            self.success = false;
-            return Vec::new();
+    /// Destination of a source cell, or `None` if it lands off the grid
+    fn target(&self, coord: CellCoord) -> Option<CellCoord> {
+        let row = self.dest.row as u64 + (coord.row - self.source.start.row) as u64;
+        let col = self.dest.col as u64 + (coord.col - self.source.start.col) as u64;
+        if row < Sheet::MAX_ROWS as u64 && col < Sheet::MAX_COLS as u64 {
+            Some(CellCoord::new(row as u32, col as u32))
+        } else {
+            None
         }
+    }
 
-        let master = self.range.start;
+    fn paste_copy(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        let pairs: Vec<_> = self
+            .source
+            .iter()
+            .filter_map(|from| self.target(from).map(|to| (from, to)))
+            .collect();
 
-        // Collect cells to clear (non-master cells with content)
-        for coord in self.range.iter() {
-            if coord != master {
-                if let Some(cell) = sheet.get_cell(coord) {
-                    if !cell.content.is_empty() {
-                        self.cleared_cells.push((coord, cell.content.clone()));
+        let (affected, old_cells) = copy_cells(sheet, &pairs);
+        self.old_cells = old_cells;
+        affected
+    }
+
+    fn paste_cut(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        let moved: Vec<(CellCoord, Option<Cell>)> = self
+            .source
+            .iter()
+            .map(|coord| (coord, sheet.get_cell(coord).cloned()))
+            .collect();
+        let targets: HashSet<CellCoord> = self.source.iter().filter_map(|c| self.target(c)).collect();
+
+        let mut touched: Vec<(CellCoord, Option<Cell>)> = moved.clone();
+        for coord in &targets {
+            if !self.source.contains(*coord) {
+                touched.push((*coord, sheet.get_cell(*coord).cloned()));
+            }
+        }
+
+        // Formulas elsewhere on the sheet that point into the cut range
+        let sheet_name = sheet.name.clone();
+        let moved_formula = |formula: &str| {
+            move_formula_in(formula, &sheet_name, true, &self.source, self.dest)
+        };
+        let mut formula_updates = Vec::new();
+        for coord in sheet.non_empty_coords().collect::<Vec<_>>() {
+            if self.source.contains(coord) || targets.contains(&coord) {
+                continue;
+            }
+            if let Some(cell) = sheet.get_cell(coord) {
+                if let CellContent::Formula { expression, .. } = &cell.content {
+                    if let Some(new_formula) = moved_formula(expression) {
+                        if &new_formula != expression {
+                            touched.push((coord, Some(cell.clone())));
+                            formula_updates.push((coord, new_formula));
+                        }
                     }
                 }
             }
         }
 
-        // Perform the merge
-        self.success = sheet.merge_cells(self.range);
-
-        if self.success {
-            // Return all cells in the merged range as affected
-            self.range.iter().collect()
-        } else {
-            Vec::new()
+        let mut affected = Vec::new();
+        for (coord, _) in &moved {
+            sheet.remove_cell(*coord);
+            affected.push(*coord);
         }
-    }
 
-    fn undo(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
-        if !self.success {
-            return Vec::new();
+        for (from, cell) in moved {
+            let Some(to) = self.target(from) else {
+                continue;
+            };
+            match cell {
+                Some(mut cell) => {
+                    if let CellContent::Formula { expression, .. } = &mut cell.content {
+                        if let Some(new_formula) = moved_formula(expression) {
+                            *expression = new_formula;
+                        }
+                    }
+                    sheet.set_cell(to, cell);
+                }
+                None => sheet.remove_cell(to),
+            }
+            affected.push(to);
         }
 
-        // Unmerge first
-        sheet.unmerge_cells(self.range);
-
-        // Restore cleared cells
-        for (coord, content) in &self.cleared_cells {
-            let cell = sheet.get_cell_mut(*coord);
-            cell.content = content.clone();
+        for (coord, new_formula) in formula_updates {
+            if let CellContent::Formula { expression, .. } = &mut sheet.get_cell_mut(coord).content {
+                *expression = new_formula;
+            }
+            affected.push(coord);
         }
 
-        self.range.iter().collect()
+        self.old_cells = touched;
+        affected
+    }
+
+    /// Point formulas on other sheets that refer to the cut range, by the
+    /// cut sheet's name, at its new place
+    fn move_other_sheets(&mut self, workbook: &mut Workbook, sheet_index: usize) {
+        self.other_sheets.clear();
+        let Some(sheet_name) = workbook.get_sheet(sheet_index).map(|s| s.name.clone()) else {
+            return;
+        };
+        for (index, sheet) in workbook.sheets.iter_mut().enumerate() {
+            if index == sheet_index {
+                continue;
+            }
+            for coord in sheet.non_empty_coords().collect::<Vec<_>>() {
+                let cell = sheet.get_cell_mut(coord);
+                let CellContent::Formula { expression, .. } = &cell.content else {
+                    continue;
+                };
+                let moved = move_formula_in(expression, &sheet_name, false, &self.source, self.dest);
+                let Some(new_formula) = moved.filter(|f| f != expression) else {
+                    continue;
+                };
+                self.other_sheets.push((index, coord, Some(cell.clone())));
+                if let CellContent::Formula { expression, .. } = &mut cell.content {
+                    *expression = new_formula;
+                }
+            }
+        }
+    }
+}
+
+impl Command for PasteCommand {
+    fn execute(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        if self.cut {
+            self.paste_cut(sheet)
+        } else {
+            self.paste_copy(sheet)
+        }
+    }
+
+    fn undo(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        restore_cells(sheet, &self.old_cells)
+    }
+
+    fn execute_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Vec<CellCoord> {
+        let Some(sheet) = workbook.get_sheet_mut(sheet_index) else {
+            return Vec::new();
+        };
+        let affected = self.execute(sheet);
+        if self.cut {
+            self.move_other_sheets(workbook, sheet_index);
+        }
+        affected
+    }
+
+    fn undo_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Vec<CellCoord> {
+        workbook.restore_cells(&self.other_sheets);
+        match workbook.get_sheet_mut(sheet_index) {
+            Some(sheet) => self.undo(sheet),
+            None => Vec::new(),
+        }
+    }
+
+    fn description(&self) -> &str {
+        if self.cut {
+            "Move cells"
+        } else {
+            "Paste"
+        }
+    }
+
+    fn is_structural(&self) -> bool {
+        self.cut
+    }
+}
+
+/// Autofill a target range by repeating the source range across it
+///
+/// Cells of `target` outside `source` take the content and format of the
+/// source cell at the same position in the repeating pattern, with formula
+/// references translated as for a copy.
+#[derive(Debug)]
+pub struct FillCommand {
+    source: CellRange,
+    target: CellRange,
+    // For undo: every cell filled, with its state before the fill
+    old_cells: Vec<(CellCoord, Option<Cell>)>,
+}
+
+impl FillCommand {
+    pub fn new(source: CellRange, target: CellRange) -> Self {
+        Self {
+            source,
+            target,
+            old_cells: Vec::new(),
+        }
+    }
+}
+
+impl Command for FillCommand {
+    fn execute(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        let rows = self.source.row_count();
+        let cols = self.source.col_count();
+        let start = self.source.start;
+
+        // rem_euclid keeps the pattern aligned when filling up or left
+        let pairs: Vec<_> = self
+            .target
+            .iter()
+            .filter(|to| !self.source.contains(*to))
+            .map(|to| {
+                let row = (to.row as i64 - start.row as i64).rem_euclid(rows as i64) as u32;
+                let col = (to.col as i64 - start.col as i64).rem_euclid(cols as i64) as u32;
+                (CellCoord::new(start.row + row, start.col + col), to)
+            })
+            .collect();
+
+        let (affected, old_cells) = copy_cells(sheet, &pairs);
+        self.old_cells = old_cells;
+        affected
+    }
+
+    fn undo(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        restore_cells(sheet, &self.old_cells)
+    }
+
+    fn description(&self) -> &str {
+        "Fill"
+    }
+}
+
+/// Copy cells between (from, to) pairs, translating formula references.
+/// Returns the affected cells and the prior state of every destination.
+fn copy_cells(
+    sheet: &mut Sheet,
+    pairs: &[(CellCoord, CellCoord)],
+) -> (Vec<CellCoord>, Vec<(CellCoord, Option<Cell>)>) {
+    // Snapshot sources first so overlapping ranges copy the original cells
+    let sources: Vec<Option<Cell>> = pairs
+        .iter()
+        .map(|(from, _)| sheet.get_cell(*from).cloned())
+        .collect();
+
+    let mut affected = Vec::new();
+    let mut old_cells = Vec::new();
+    for ((from, to), cell) in pairs.iter().zip(sources) {
+        old_cells.push((*to, sheet.get_cell(*to).cloned()));
+
+        match cell {
+            Some(mut cell) => {
+                if let CellContent::Formula { expression, .. } = &mut cell.content {
+                    if let Some(new_formula) = translate_formula(expression, *from, *to) {
+                        *expression = new_formula;
+                    }
+                }
+                sheet.set_cell(*to, cell);
+            }
+            None => sheet.remove_cell(*to),
+        }
+        affected.push(*to);
+    }
+
+    (affected, old_cells)
+}
+
+/// Put back cells captured before a paste or fill, in reverse order so the
+/// earliest snapshot of a cell wins
+fn restore_cells(sheet: &mut Sheet, old_cells: &[(CellCoord, Option<Cell>)]) -> Vec<CellCoord> {
+    let mut affected = Vec::new();
+
+    for (coord, cell) in old_cells.iter().rev() {
+        match cell {
+            Some(cell) => sheet.set_cell(*coord, cell.clone()),
+            None => sheet.remove_cell(*coord),
+        }
+        affected.push(*coord);
+    }
+
+    affected
+}
+
+/// Merge cells in a range
+#[derive(Debug)]
+pub struct MergeCellsCommand {
+    range: CellRange,
+    // For undo: store the original cell contents that were cleared
+    cleared_cells: Vec<(CellCoord, CellContent)>,
+    // Store whether merge was successful
+    success: bool,
+}
+
+impl MergeCellsCommand {
+    pub fn new(range: CellRange) -> Self {
+        Self {
+            range,
+            cleared_cells: Vec::new(),
+            success: false,
+        }
+    }
+
+    /// Create from start/end coordinates
+    pub fn from_coords(start_row: u32, start_col: u32, end_row: u32, end_col: u32) -> Self {
+        Self::new(CellRange::new(
+            CellCoord::new(start_row, start_col),
+            CellCoord::new(end_row, end_col),
+        ))
+    }
+}
+
+impl Command for MergeCellsCommand {
+    fn execute(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        self.cleared_cells.clear();
+
+        // Don't merge single cells
+        if self.range.is_single_cell() {
+            self.success = false;
+            return Vec::new();
+        }
+
+        // Check for overlap with existing merges
+        if sheet.would_overlap_merge(&self.range) {
+            self.success = false;
+            return Vec::new();
+        }
+
+        let master = self.range.start;
+
+        // Collect cells to clear (non-master cells with content)
+        for coord in self.range.iter() {
+            if coord != master {
+                if let Some(cell) = sheet.get_cell(coord) {
+                    if !cell.content.is_empty() {
+                        self.cleared_cells.push((coord, cell.content.clone()));
+                    }
+                }
+            }
+        }
+
+        // Perform the merge
+        self.success = sheet.merge_cells(self.range);
+
+        if self.success {
+            // Return all cells in the merged range as affected
+            self.range.iter().collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn undo(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        if !self.success {
+            return Vec::new();
+        }
+
+        // Unmerge first
+        sheet.unmerge_cells(self.range);
+
+        // Restore cleared cells
+        for (coord, content) in &self.cleared_cells {
+            let cell = sheet.get_cell_mut(*coord);
+            cell.content = content.clone();
+        }
+
+        self.range.iter().collect()
     }
 
     fn description(&self) -> &str {
@@ -1126,7 +1943,7 @@ impl ApplyFilterCommand {
 impl Command for ApplyFilterCommand {
     fn execute(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
         // Store previously hidden rows for undo
-        self.previously_hidden_rows = sheet.get_hidden_rows();
+        self.previously_hidden_rows = sheet.get_filtered_rows();
 
         // Apply the filter
         self.newly_hidden_rows = sheet.apply_column_filter(self.col, &self.visible_values, self.max_rows);
@@ -1139,81 +1956,799 @@ impl Command for ApplyFilterCommand {
     }
 
     fn undo(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
-        // Show all rows that were hidden by this filter
-        sheet.show_rows(&self.newly_hidden_rows);
+        // Show all rows that were hidden by this filter
+        sheet.show_rows(&self.newly_hidden_rows);
+
+        // Remove this filter from active filters
+        sheet.active_filters.retain(|f| f.col != self.col);
+
+        // Return affected cells
+        self.newly_hidden_rows
+            .iter()
+            .map(|&row| CellCoord::new(row, self.col))
+            .collect()
+    }
+
+    fn description(&self) -> &str {
+        "Apply Column Filter"
+    }
+}
+
+/// Clear a column filter or all filters
+#[derive(Debug)]
+pub struct ClearFilterCommand {
+    col: Option<u32>,  // None means clear all filters
+    saved_filters: Vec<FilterState>,
+    hidden_rows_before: Vec<u32>,
+}
+
+impl ClearFilterCommand {
+    pub fn new(col: Option<u32>) -> Self {
+        Self {
+            col,
+            saved_filters: Vec::new(),
+            hidden_rows_before: Vec::new(),
+        }
+    }
+}
+
+impl Command for ClearFilterCommand {
+    fn execute(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        // Save current state for undo
+        self.saved_filters = sheet.get_active_filters().to_vec();
+        self.hidden_rows_before = sheet.get_filtered_rows();
+
+        // Clear filter(s)
+        let unhidden_rows = match self.col {
+            Some(col) => sheet.clear_column_filter(col),
+            None => sheet.clear_all_filters(),
+        };
+
+        // Return affected cells
+        unhidden_rows
+            .iter()
+            .map(|&row| CellCoord::new(row, 0))
+            .collect()
+    }
+
+    fn undo(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        // Restore filters - re-hide rows that were hidden before
+        sheet.hide_rows(&self.hidden_rows_before);
+
+        // Restore filter state
+        sheet.active_filters = self.saved_filters.clone();
+
+        // Return affected cells
+        self.hidden_rows_before
+            .iter()
+            .map(|&row| CellCoord::new(row, 0))
+            .collect()
+    }
+
+    fn description(&self) -> &str {
+        match self.col {
+            Some(_) => "Clear Column Filter",
+            None => "Clear All Filters",
+        }
+    }
+}
+
+/// Freeze the top rows and left columns
+#[derive(Debug)]
+pub struct SetFrozenPanesCommand {
+    rows: u32,
+    cols: u32,
+    // For undo: the previous (rows, cols)
+    old: (u32, u32),
+}
+
+impl SetFrozenPanesCommand {
+    pub fn new(rows: u32, cols: u32) -> Self {
+        Self {
+            rows,
+            cols,
+            old: (0, 0),
+        }
+    }
+}
+
+impl Command for SetFrozenPanesCommand {
+    fn execute(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        self.old = (sheet.frozen_rows, sheet.frozen_cols);
+        sheet.set_frozen_panes(self.rows, self.cols);
+        vec![CellCoord::new(sheet.frozen_rows, sheet.frozen_cols)]
+    }
+
+    fn undo(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        sheet.set_frozen_panes(self.old.0, self.old.1);
+        vec![CellCoord::new(self.old.0, self.old.1)]
+    }
+
+    fn description(&self) -> &str {
+        if self.rows == 0 && self.cols == 0 {
+            "Unfreeze panes"
+        } else {
+            "Freeze panes"
+        }
+    }
+}
+
+/// Coordinate standing for a whole row or column in affected lists
+fn axis_coord(axis: Axis, index: u32) -> CellCoord {
+    match axis {
+        Axis::Rows => CellCoord::new(index, 0),
+        Axis::Cols => CellCoord::new(0, index),
+    }
+}
+
+/// Hide or unhide rows or columns
+#[derive(Debug)]
+pub struct SetHiddenCommand {
+    axis: Axis,
+    start: u32,
+    end: u32,
+    hidden: bool,
+    // For undo: indices whose state actually changed
+    changed: Vec<u32>,
+}
+
+impl SetHiddenCommand {
+    pub fn new(axis: Axis, start: u32, end: u32, hidden: bool) -> Self {
+        Self {
+            axis,
+            start,
+            end,
+            hidden,
+            changed: Vec::new(),
+        }
+    }
+}
+
+impl Command for SetHiddenCommand {
+    fn execute(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        self.changed = sheet.set_hidden(self.axis, self.start, self.end, self.hidden);
+        self.changed.iter().map(|&i| axis_coord(self.axis, i)).collect()
+    }
+
+    fn undo(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        for &index in &self.changed {
+            sheet.set_hidden(self.axis, index, index, !self.hidden);
+        }
+        self.changed.iter().map(|&i| axis_coord(self.axis, i)).collect()
+    }
+
+    fn description(&self) -> &str {
+        match (self.axis, self.hidden) {
+            (Axis::Rows, true) => "Hide rows",
+            (Axis::Rows, false) => "Unhide rows",
+            (Axis::Cols, true) => "Hide columns",
+            (Axis::Cols, false) => "Unhide columns",
+        }
+    }
+}
+
+/// A change to a row or column outline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutlineEdit {
+    Group { start: u32, end: u32 },
+    Ungroup { start: u32, end: u32 },
+    SetCollapsed { start: u32, end: u32, collapsed: bool },
+    ShowLevel(u8),
+    SetSummary(SummaryPosition),
+}
+
+/// Group, ungroup, collapse or expand rows or columns
+#[derive(Debug)]
+pub struct EditOutlineCommand {
+    axis: Axis,
+    edit: OutlineEdit,
+    // For undo: the outline before the edit (None if the edit was rejected)
+    old_outline: Option<Outline>,
+}
+
+impl EditOutlineCommand {
+    pub fn new(axis: Axis, edit: OutlineEdit) -> Self {
+        Self {
+            axis,
+            edit,
+            old_outline: None,
+        }
+    }
+
+    /// Rows or columns whose visibility differs between two outlines, plus the edited span
+    fn affected(&self, before: &Outline, after: &Outline) -> Vec<CellCoord> {
+        let mut indices: std::collections::BTreeSet<u32> = before
+            .collapsed_indices()
+            .symmetric_difference(&after.collapsed_indices())
+            .copied()
+            .collect();
+        match self.edit {
+            OutlineEdit::Group { start, end }
+            | OutlineEdit::Ungroup { start, end }
+            | OutlineEdit::SetCollapsed { start, end, .. } => {
+                indices.extend(start.min(end)..=start.max(end));
+            }
+            OutlineEdit::ShowLevel(_) | OutlineEdit::SetSummary(_) => {}
+        }
+        indices.into_iter().map(|i| axis_coord(self.axis, i)).collect()
+    }
+}
+
+impl Command for EditOutlineCommand {
+    fn execute(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        let before = sheet.outline(self.axis).clone();
+        let mut after = before.clone();
+        let applied = match self.edit {
+            OutlineEdit::Group { start, end } => after.group(start, end).is_ok(),
+            OutlineEdit::Ungroup { start, end } => after.ungroup(start, end).is_some(),
+            OutlineEdit::SetCollapsed { start, end, collapsed } => {
+                after.set_collapsed(start, end, collapsed)
+            }
+            OutlineEdit::ShowLevel(level) => {
+                after.show_level(level);
+                true
+            }
+            OutlineEdit::SetSummary(position) => {
+                after.summary = position;
+                true
+            }
+        };
+        if !applied {
+            self.old_outline = None;
+            return Vec::new();
+        }
+
+        let affected = self.affected(&before, &after);
+        sheet.set_outline(self.axis, after);
+        self.old_outline = Some(before);
+        affected
+    }
+
+    fn undo(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        let Some(before) = self.old_outline.clone() else {
+            return Vec::new();
+        };
+        let after = sheet.outline(self.axis).clone();
+        sheet.set_outline(self.axis, before.clone());
+        self.affected(&before, &after)
+    }
+
+    fn description(&self) -> &str {
+        match self.edit {
+            OutlineEdit::Group { .. } => "Group",
+            OutlineEdit::Ungroup { .. } => "Ungroup",
+            OutlineEdit::SetCollapsed { collapsed: true, .. } => "Collapse group",
+            OutlineEdit::SetCollapsed { collapsed: false, .. } => "Expand group",
+            OutlineEdit::ShowLevel(_) => "Show outline level",
+            OutlineEdit::SetSummary(_) => "Set summary position",
+        }
+    }
+}
+
+/// A change to a cell's comment thread
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommentEdit {
+    /// Start a thread on a cell that has none
+    Add(Comment),
+    EditBody { body: String, timestamp: i64 },
+    Reply(CommentReply),
+    EditReply { id: String, body: String, timestamp: i64 },
+    DeleteReply { id: String },
+    SetResolved(bool),
+    Delete,
+}
+
+/// Add, edit, reply to, resolve or delete a cell comment
+#[derive(Debug)]
+pub struct EditCommentCommand {
+    coord: CellCoord,
+    edit: CommentEdit,
+    // For undo: the previous thread (outer None if the edit was rejected)
+    old_comment: Option<Option<Comment>>,
+}
+
+impl EditCommentCommand {
+    pub fn new(coord: CellCoord, edit: CommentEdit) -> Self {
+        Self {
+            coord,
+            edit,
+            old_comment: None,
+        }
+    }
+
+    /// Apply the edit to the current thread, or `None` if it doesn't apply
+    fn apply(&self, current: Option<&Comment>) -> Option<Option<Comment>> {
+        if let CommentEdit::Add(comment) = &self.edit {
+            return current.is_none().then(|| Some(comment.clone()));
+        }
+
+        let mut comment = current?.clone();
+        match &self.edit {
+            CommentEdit::Add(_) => unreachable!(),
+            CommentEdit::EditBody { body, timestamp } => {
+                comment.body = body.clone();
+                comment.edited = Some(*timestamp);
+            }
+            CommentEdit::Reply(reply) => comment.replies.push(reply.clone()),
+            CommentEdit::EditReply { id, body, timestamp } => {
+                let reply = comment.reply_mut(id)?;
+                reply.body = body.clone();
+                reply.edited = Some(*timestamp);
+            }
+            CommentEdit::DeleteReply { id } => {
+                comment.reply(id)?;
+                comment.replies.retain(|r| r.id != *id);
+            }
+            CommentEdit::SetResolved(resolved) => comment.resolved = *resolved,
+            CommentEdit::Delete => return Some(None),
+        }
+        Some(Some(comment))
+    }
+}
+
+impl Command for EditCommentCommand {
+    fn execute(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        let current = sheet.get_comment(self.coord);
+        let Some(next) = self.apply(current) else {
+            self.old_comment = None;
+            return Vec::new();
+        };
+        self.old_comment = Some(sheet.set_comment(self.coord, next));
+        vec![self.coord]
+    }
+
+    fn undo(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        let Some(old) = self.old_comment.clone() else {
+            return Vec::new();
+        };
+        sheet.set_comment(self.coord, old);
+        vec![self.coord]
+    }
+
+    fn description(&self) -> &str {
+        match self.edit {
+            CommentEdit::Add(_) => "Add comment",
+            CommentEdit::EditBody { .. } => "Edit comment",
+            CommentEdit::Reply(_) => "Reply to comment",
+            CommentEdit::EditReply { .. } => "Edit reply",
+            CommentEdit::DeleteReply { .. } => "Delete reply",
+            CommentEdit::SetResolved(true) => "Resolve comment",
+            CommentEdit::SetResolved(false) => "Reopen comment",
+            CommentEdit::Delete => "Delete comment",
+        }
+    }
+}
+
+/// Set or remove the hyperlink on a cell
+#[derive(Debug)]
+pub struct SetHyperlinkCommand {
+    coord: CellCoord,
+    link: Option<Hyperlink>,
+    // For undo
+    old_link: Option<Hyperlink>,
+}
+
+impl SetHyperlinkCommand {
+    pub fn new(coord: CellCoord, link: Option<Hyperlink>) -> Self {
+        Self {
+            coord,
+            link,
+            old_link: None,
+        }
+    }
+}
+
+impl Command for SetHyperlinkCommand {
+    fn execute(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        self.old_link = sheet.set_hyperlink(self.coord, self.link.clone());
+        vec![self.coord]
+    }
+
+    fn undo(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        sheet.set_hyperlink(self.coord, self.old_link.clone());
+        vec![self.coord]
+    }
+
+    fn description(&self) -> &str {
+        if self.link.is_some() {
+            "Set hyperlink"
+        } else {
+            "Remove hyperlink"
+        }
+    }
+}
+
+/// A change to a table
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableEdit {
+    /// Turn a range into a table
+    Create(Table),
+    /// Convert a table back to a plain range
+    Remove,
+    Resize(CellRange),
+    /// Rename a table; through [`Command::execute_in`] this also rewrites
+    /// formulas that name it across the workbook
+    Rename(String),
+    SetTotalsRow(bool),
+    SetTotal { column: u32, function: Option<TotalsFunction> },
+    SetOptions { style: TableStyle, auto_expand: bool },
+    Sort { column: u32, ascending: bool },
+    Filter { column: u32, visible_values: Option<HashSet<String>> },
+}
+
+/// What a table edit changed, for undo
+#[derive(Debug)]
+struct TableSnapshot {
+    tables: Vec<Table>,
+    filtered_rows: Vec<u32>,
+    area: Option<CellRange>,
+    cells: Vec<(CellCoord, Cell)>,
+}
+
+/// A table's name before a rename, and each formula rewritten as
+/// (after, before)
+type RenameSnapshot = (String, Vec<(String, String)>);
+
+/// Create, remove, resize, rename, sort, filter or restyle a table
+///
+/// Edits the sheet can't make (a missing table, an overlapping range, ...)
+/// leave it unchanged and record the error, which [`error`](Self::error)
+/// returns after execution.
+#[derive(Debug)]
+pub struct EditTableCommand {
+    name: String,
+    edit: TableEdit,
+    error: Option<RusheetError>,
+    // For undo: the state before the edit (None if the edit was rejected)
+    before: Option<TableSnapshot>,
+    renamed: Option<RenameSnapshot>,
+}
+
+impl EditTableCommand {
+    /// Edit the table called `name` (for `Create`, the new table's name)
+    pub fn new(name: impl Into<String>, edit: TableEdit) -> Self {
+        let mut name = name.into();
+        if let TableEdit::Create(table) = &edit {
+            name = table.name.clone();
+        }
+        Self {
+            name,
+            edit,
+            error: None,
+            before: None,
+            renamed: None,
+        }
+    }
+
+    /// Why the last execution was rejected, if it was
+    pub fn error(&self) -> Option<&RusheetError> {
+        self.error.as_ref()
+    }
+
+    /// Rename the table anywhere in the workbook, rewriting the formulas
+    /// that name it
+    fn rename_in(&self, workbook: &mut Workbook, new_name: &str) -> Result<RenameSnapshot, RusheetError> {
+        let old_name = workbook
+            .table(&self.name)
+            .map(|(_, table)| table.name.clone())
+            .ok_or_else(|| RusheetError::TableNotFound(self.name.clone()))?;
+        workbook.rename_table(&old_name, new_name)?;
+        let mut rewritten = Vec::new();
+        workbook.rewrite_formulas(|formula| {
+            let renamed = rename_table_in_formula(formula, &old_name, new_name).filter(|f| f != formula)?;
+            rewritten.push((renamed.clone(), formula.to_string()));
+            Some(renamed)
+        });
+        Ok((old_name, rewritten))
+    }
+
+    /// Cells the edit may rewrite
+    fn area(&self, sheet: &Sheet) -> Option<CellRange> {
+        if let TableEdit::Create(table) = &self.edit {
+            // Room for the data row (and totals row) a new table may gain
+            let mut range = table.range;
+            range.end.row = range.end.row.max(range.start.row + 1 + u32::from(table.totals_row));
+            return Some(range);
+        }
+        let table = sheet.table(&self.name)?;
+        match &self.edit {
+            TableEdit::Resize(range) => Some(CellRange::new(
+                CellCoord::new(
+                    table.range.start.row.min(range.start.row),
+                    table.range.start.col.min(range.start.col),
+                ),
+                CellCoord::new(
+                    table.range.end.row.max(range.end.row),
+                    table.range.end.col.max(range.end.col),
+                ),
+            )),
+            TableEdit::SetTotalsRow(_) => {
+                let mut range = table.range;
+                range.end.row += 1;
+                Some(range)
+            }
+            TableEdit::SetTotal { .. } | TableEdit::Sort { .. } => Some(table.range),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, sheet: &mut Sheet) -> Result<(), RusheetError> {
+        let name = self.name.as_str();
+        let missing = || RusheetError::TableNotFound(name.to_string());
+        match &self.edit {
+            TableEdit::Create(table) => sheet.add_table(table.clone()),
+            TableEdit::Remove => sheet.remove_table(name).map(|_| ()).ok_or_else(missing),
+            TableEdit::Resize(range) => sheet.resize_table(name, *range),
+            TableEdit::Rename(new_name) => {
+                if !Table::is_valid_name(new_name) {
+                    return Err(RusheetError::InvalidTableName(new_name.clone()));
+                }
+                if sheet.table(new_name).is_some_and(|other| !other.name.eq_ignore_ascii_case(name)) {
+                    return Err(RusheetError::TableNameExists(new_name.clone()));
+                }
+                sheet.table_mut(name).ok_or_else(missing)?.name = new_name.clone();
+                Ok(())
+            }
+            TableEdit::SetTotalsRow(show) => sheet.set_table_totals_row(name, *show),
+            TableEdit::SetTotal { column, function } => sheet.set_table_total(name, *column, *function),
+            TableEdit::SetOptions { style, auto_expand } => {
+                let table = sheet.table_mut(name).ok_or_else(missing)?;
+                table.style = style.clone();
+                table.auto_expand = *auto_expand;
+                Ok(())
+            }
+            TableEdit::Sort { column, ascending } => {
+                sheet.sort_table(name, *column, *ascending).map(|_| ())
+            }
+            TableEdit::Filter { column, visible_values } => {
+                sheet.filter_table(name, *column, visible_values.clone()).map(|_| ())
+            }
+        }
+    }
+}
+
+impl Command for EditTableCommand {
+    fn execute(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        let area = self.area(sheet);
+        let cells = area
+            .iter()
+            .flat_map(|range| range.iter())
+            .filter_map(|coord| sheet.get_cell(coord).map(|cell| (coord, cell.clone())))
+            .collect();
+        let before = TableSnapshot {
+            tables: sheet.tables().to_vec(),
+            filtered_rows: sheet.get_filtered_rows(),
+            area,
+            cells,
+        };
+
+        self.error = self.apply(sheet).err();
+        if self.error.is_some() {
+            self.before = None;
+            return Vec::new();
+        }
+        let affected = table_edit_affected(&before, sheet);
+        self.before = Some(before);
+        affected
+    }
+
+    fn undo(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
+        let Some(before) = &self.before else {
+            return Vec::new();
+        };
+        let affected = table_edit_affected(before, sheet);
 
-        // Remove this filter from active filters
-        sheet.active_filters.retain(|f| f.col != self.col);
+        if let Some(area) = before.area {
+            for coord in area.iter() {
+                sheet.remove_cell(coord);
+            }
+            for (coord, cell) in &before.cells {
+                sheet.set_cell(*coord, cell.clone());
+            }
+        }
+        let filtered = sheet.get_filtered_rows();
+        sheet.show_rows(&filtered);
+        sheet.hide_rows(&before.filtered_rows);
+        sheet.restore_tables(before.tables.clone());
+        affected
+    }
 
-        // Return affected cells
-        self.newly_hidden_rows
-            .iter()
-            .map(|&row| CellCoord::new(row, self.col))
-            .collect()
+    fn execute_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Vec<CellCoord> {
+        let TableEdit::Rename(new_name) = &self.edit else {
+            return match workbook.get_sheet_mut(sheet_index) {
+                Some(sheet) => self.execute(sheet),
+                None => Vec::new(),
+            };
+        };
+        match self.rename_in(workbook, &new_name.clone()) {
+            Ok(renamed) => {
+                self.error = None;
+                self.renamed = Some(renamed);
+            }
+            Err(error) => {
+                self.error = Some(error);
+                self.renamed = None;
+            }
+        }
+        Vec::new()
+    }
+
+    fn undo_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Vec<CellCoord> {
+        let TableEdit::Rename(new_name) = &self.edit else {
+            return match workbook.get_sheet_mut(sheet_index) {
+                Some(sheet) => self.undo(sheet),
+                None => Vec::new(),
+            };
+        };
+        let Some((old_name, rewritten)) = &self.renamed else {
+            return Vec::new();
+        };
+        if workbook.rename_table(new_name, old_name).is_ok() {
+            workbook.rewrite_formulas(|formula| {
+                rewritten
+                    .iter()
+                    .find(|(after, _)| after == formula)
+                    .map(|(_, before)| before.clone())
+            });
+        }
+        Vec::new()
     }
 
     fn description(&self) -> &str {
-        "Apply Column Filter"
+        match &self.edit {
+            TableEdit::Create(_) => "Create table",
+            TableEdit::Remove => "Convert table to range",
+            TableEdit::Resize(_) => "Resize table",
+            TableEdit::Rename(_) => "Rename table",
+            TableEdit::SetTotalsRow(true) => "Show totals row",
+            TableEdit::SetTotalsRow(false) => "Hide totals row",
+            TableEdit::SetTotal { .. } => "Set table total",
+            TableEdit::SetOptions { .. } => "Set table style",
+            TableEdit::Sort { .. } => "Sort table",
+            TableEdit::Filter { .. } => "Filter table",
+        }
+    }
+
+    fn is_structural(&self) -> bool {
+        matches!(self.edit, TableEdit::Rename(_))
     }
 }
 
-/// Clear a column filter or all filters
+/// Cells in the edited area plus the first cell of each row shown or hidden
+fn table_edit_affected(before: &TableSnapshot, sheet: &Sheet) -> Vec<CellCoord> {
+    let mut affected: Vec<CellCoord> = before.area.iter().flat_map(|range| range.iter()).collect();
+    let filtered: HashSet<u32> = sheet.get_filtered_rows().into_iter().collect();
+    let previously: HashSet<u32> = before.filtered_rows.iter().copied().collect();
+    affected.extend(
+        filtered
+            .symmetric_difference(&previously)
+            .map(|&row| CellCoord::new(row, 0)),
+    );
+    affected
+}
+
+/// A change to a pivot table
+#[derive(Debug, Clone, PartialEq)]
+pub enum PivotEdit {
+    Create(PivotTable),
+    Update(PivotTable),
+    Refresh,
+    /// Refresh every pivot table; the command's name is ignored
+    RefreshAll,
+    Remove,
+}
+
+/// Create, change, refresh or remove a pivot table
+///
+/// Pivot tables belong to the workbook, so this only does anything through
+/// [`Command::execute_in`]. Output that would replace existing data is
+/// refused unless the command was made with `replace`. Rejected edits
+/// leave the workbook unchanged and record the error, which
+/// [`error`](Self::error) returns after execution.
 #[derive(Debug)]
-pub struct ClearFilterCommand {
-    col: Option<u32>,  // None means clear all filters
-    saved_filters: Vec<FilterState>,
-    hidden_rows_before: Vec<u32>,
+pub struct EditPivotCommand {
+    name: String,
+    edit: PivotEdit,
+    replace: bool,
+    error: Option<RusheetError>,
+    // For undo: the definitions before the edit and the cells it overwrote
+    pivots_before: Vec<PivotTable>,
+    replaced: ReplacedCells,
 }
 
-impl ClearFilterCommand {
-    pub fn new(col: Option<u32>) -> Self {
+impl EditPivotCommand {
+    /// Edit the pivot table called `name` (for `Create`, the new table's name)
+    pub fn new(name: impl Into<String>, edit: PivotEdit, replace: bool) -> Self {
+        let mut name = name.into();
+        if let PivotEdit::Create(pivot) = &edit {
+            name = pivot.name.clone();
+        }
         Self {
-            col,
-            saved_filters: Vec::new(),
-            hidden_rows_before: Vec::new(),
+            name,
+            edit,
+            replace,
+            error: None,
+            pivots_before: Vec::new(),
+            replaced: Vec::new(),
         }
     }
-}
 
-impl Command for ClearFilterCommand {
-    fn execute(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
-        // Save current state for undo
-        self.saved_filters = sheet.get_active_filters().to_vec();
-        self.hidden_rows_before = sheet.get_hidden_rows();
+    /// Why the last execution was rejected, if it was
+    pub fn error(&self) -> Option<&RusheetError> {
+        self.error.as_ref()
+    }
 
-        // Clear filter(s)
-        let unhidden_rows = match self.col {
-            Some(col) => sheet.clear_column_filter(col),
-            None => sheet.clear_all_filters(),
-        };
+    fn apply(&self, workbook: &mut Workbook) -> Result<ReplacedCells, RusheetError> {
+        let name = self.name.as_str();
+        match &self.edit {
+            PivotEdit::Create(pivot) => workbook.add_pivot(pivot.clone(), self.replace),
+            PivotEdit::Update(pivot) => workbook.update_pivot(name, pivot.clone(), self.replace),
+            PivotEdit::Refresh => workbook.refresh_pivot(name, self.replace),
+            PivotEdit::RefreshAll => workbook.refresh_pivots(self.replace),
+            PivotEdit::Remove => workbook.remove_pivot(name).map(|(_, replaced)| replaced),
+        }
+    }
 
-        // Return affected cells
-        unhidden_rows
+    /// Replaced cells on the edited sheet
+    fn affected(&self, sheet_index: usize) -> Vec<CellCoord> {
+        let mut affected: Vec<CellCoord> = self
+            .replaced
             .iter()
-            .map(|&row| CellCoord::new(row, 0))
-            .collect()
+            .filter(|(sheet, ..)| *sheet == sheet_index)
+            .map(|(_, coord, _)| *coord)
+            .collect();
+        affected.sort_unstable_by_key(|c| (c.row, c.col));
+        affected.dedup();
+        affected
     }
+}
 
-    fn undo(&mut self, sheet: &mut Sheet) -> Vec<CellCoord> {
-        // Restore filters - re-hide rows that were hidden before
-        sheet.hide_rows(&self.hidden_rows_before);
+impl Command for EditPivotCommand {
+    fn execute(&mut self, _sheet: &mut Sheet) -> Vec<CellCoord> {
+        Vec::new()
+    }
 
-        // Restore filter state
-        sheet.active_filters = self.saved_filters.clone();
+    fn undo(&mut self, _sheet: &mut Sheet) -> Vec<CellCoord> {
+        Vec::new()
+    }
 
-        // Return affected cells
-        self.hidden_rows_before
-            .iter()
-            .map(|&row| CellCoord::new(row, 0))
-            .collect()
+    fn execute_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Vec<CellCoord> {
+        self.pivots_before = workbook.pivots.clone();
+        match self.apply(workbook) {
+            Ok(replaced) => {
+                self.error = None;
+                self.replaced = replaced;
+                self.affected(sheet_index)
+            }
+            Err(error) => {
+                self.error = Some(error);
+                self.replaced.clear();
+                Vec::new()
+            }
+        }
+    }
+
+    fn undo_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Vec<CellCoord> {
+        if self.error.is_some() {
+            return Vec::new();
+        }
+        workbook.restore_cells(&self.replaced);
+        workbook.pivots = self.pivots_before.clone();
+        self.affected(sheet_index)
     }
 
     fn description(&self) -> &str {
-        match self.col {
-            Some(_) => "Clear Column Filter",
-            None => "Clear All Filters",
+        match &self.edit {
+            PivotEdit::Create(_) => "Create pivot table",
+            PivotEdit::Update(_) => "Change pivot table",
+            PivotEdit::Refresh | PivotEdit::RefreshAll => "Refresh pivot table",
+            PivotEdit::Remove => "Delete pivot table",
         }
     }
+
+    fn is_structural(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
@@ -1241,6 +2776,99 @@ mod tests {
         assert!(sheet.get_cell_value(coord).is_empty());
     }
 
+    #[test]
+    fn test_set_cell_value_command_formats_dates() {
+        let mut sheet = Sheet::new("Test");
+        let coord = CellCoord::new(0, 0);
+
+        let mut cmd = SetCellValueCommand::from_input_with(
+            coord,
+            "16.10.2026",
+            DateOrder::DayMonthYear,
+            DateSystem::Excel1900,
+        );
+        cmd.execute(&mut sheet);
+        let cell = sheet.get_cell(coord).unwrap();
+        assert_eq!(cell.computed_value().as_number(), Some(46311.0));
+        assert_eq!(cell.format.number_format(&[]), Some("d/m/yyyy"));
+
+        // Undo removes the number format along with the date
+        cmd.undo(&mut sheet);
+        let cell = sheet.get_cell(coord).unwrap();
+        assert!(cell.computed_value().is_empty());
+        assert_eq!(cell.format.number_format, None);
+
+        // In a workbook, a date format from the cell's named style is kept
+        let mut wb = Workbook::new("Test");
+        let day = rusheet_core::CellFormat {
+            number_format: Some("d mmm".to_string()),
+            ..Default::default()
+        };
+        wb.set_style(NamedStyle::new("Day", day));
+        wb.sheets[0].get_cell_mut(coord).format.style = Some("Day".to_string());
+        cmd.execute_in(&mut wb, 0);
+        let cell = wb.sheets[0].get_cell(coord).unwrap();
+        assert_eq!(cell.format.number_format, None);
+        assert_eq!(cell.display_value(&wb.styles, DateSystem::Excel1900), "16 Oct");
+    }
+
+    #[test]
+    fn test_set_cell_value_command_rich_text() {
+        use rusheet_core::RunFormat;
+
+        let mut sheet = Sheet::new("Test");
+        let coord = CellCoord::new(0, 0);
+        let text = RichText::new()
+            .with_run("Total:", RunFormat::new().with_bold(true))
+            .with_run(" 42", RunFormat::new());
+
+        let mut rich = SetCellValueCommand::from_rich_text(coord, text.clone());
+        rich.execute(&mut sheet);
+        assert_eq!(sheet.get_cell(coord).unwrap().text_runs(), Some(&text));
+
+        // Typing over the cell drops the runs, undo brings them back
+        let mut typed = SetCellValueCommand::from_input(coord, "Total: 42");
+        typed.execute(&mut sheet);
+        assert_eq!(sheet.get_cell(coord).unwrap().rich_text, None);
+        typed.undo(&mut sheet);
+        assert_eq!(sheet.get_cell(coord).unwrap().text_runs(), Some(&text));
+
+        // Pasting copies the runs
+        let dest = CellCoord::new(1, 0);
+        PasteCommand::copy(CellRange::new(coord, coord), dest).execute(&mut sheet);
+        assert_eq!(sheet.get_cell(dest).unwrap().text_runs(), Some(&text));
+    }
+
+    #[test]
+    fn test_set_borders_command() {
+        use rusheet_core::{BorderStyle, Color};
+
+        let mut sheet = Sheet::new("Test");
+        let range = CellRange::new(CellCoord::new(0, 0), CellCoord::new(1, 1));
+        let thin = BorderLine::new(BorderStyle::Thin, Color::BLACK);
+        let thick = BorderLine::new(BorderStyle::Thick, Color::BLACK);
+
+        SetBordersCommand::new(range, BorderTarget::All, Some(thin)).execute(&mut sheet);
+        sheet.set_cell_value(CellCoord::new(0, 0), "kept");
+
+        let mut cmd = SetBordersCommand::new(range, BorderTarget::Outline, Some(thick));
+        cmd.execute(&mut sheet);
+        assert_eq!(sheet.resolved_borders(CellCoord::new(0, 0)).top, Some(thick));
+
+        // Format commands leave borders alone
+        let bold = FormatDiff {
+            bold: Some(true),
+            ..Default::default()
+        };
+        SetCellFormatCommand::new(CellCoord::new(0, 0), bold).execute(&mut sheet);
+        assert_eq!(sheet.resolved_borders(CellCoord::new(0, 0)).top, Some(thick));
+
+        cmd.undo(&mut sheet);
+        let borders = sheet.resolved_borders(CellCoord::new(0, 0));
+        assert_eq!([borders.top, borders.right, borders.bottom, borders.left], [Some(thin); 4]);
+        assert_eq!(sheet.get_cell(CellCoord::new(0, 0)).unwrap().format.bold, Some(true));
+    }
+
     #[test]
     fn test_clear_range_command() {
         let mut sheet = Sheet::new("Test");
@@ -1323,6 +2951,9 @@ mod tests {
                 cached_value: CellValue::Empty,
             },
             format: Default::default(),
+            rich_text: None,
+            comment: None,
+            hyperlink: None,
         };
         sheet.set_cell(CellCoord::new(0, 0), cell);
 
@@ -1409,6 +3040,9 @@ mod tests {
                 cached_value: CellValue::Empty,
             },
             format: Default::default(),
+            rich_text: None,
+            comment: None,
+            hyperlink: None,
         };
         sheet.set_cell(CellCoord::new(0, 0), cell);
 
@@ -1492,6 +3126,9 @@ mod tests {
                 cached_value: CellValue::Empty,
             },
             format: Default::default(),
+            rich_text: None,
+            comment: None,
+            hyperlink: None,
         };
         sheet.set_cell(CellCoord::new(0, 0), cell);
 
@@ -1578,6 +3215,9 @@ mod tests {
                 cached_value: CellValue::Empty,
             },
             format: Default::default(),
+            rich_text: None,
+            comment: None,
+            hyperlink: None,
         };
         sheet.set_cell(CellCoord::new(0, 0), cell);
 
@@ -1611,4 +3251,430 @@ mod tests {
             panic!("Cell not found");
         }
     }
+
+    fn formula_at(sheet: &Sheet, coord: CellCoord) -> Option<String> {
+        sheet.get_cell(coord)?.content.formula_expression().map(String::from)
+    }
+
+    #[test]
+    fn test_paste_copy_translates_formulas() {
+        let mut sheet = Sheet::new("Test");
+        sheet.set_cell_value(CellCoord::new(1, 2), "=A2*$B$1");
+        sheet.set_cell_value(CellCoord::new(1, 3), "5");
+
+        let source = CellRange::new(CellCoord::new(1, 2), CellCoord::new(1, 3));
+        let mut cmd = PasteCommand::copy(source, CellCoord::new(2, 2));
+        cmd.execute(&mut sheet);
+
+        assert_eq!(formula_at(&sheet, CellCoord::new(2, 2)), Some("=A3*$B$1".to_string()));
+        assert_eq!(sheet.get_cell_value(CellCoord::new(2, 3)).as_number(), Some(5.0));
+        // Source is untouched
+        assert_eq!(formula_at(&sheet, CellCoord::new(1, 2)), Some("=A2*$B$1".to_string()));
+
+        cmd.undo(&mut sheet);
+        assert!(sheet.get_cell(CellCoord::new(2, 2)).is_none());
+        assert!(sheet.get_cell(CellCoord::new(2, 3)).is_none());
+    }
+
+    #[test]
+    fn test_paste_cut_moves_cells_and_references() {
+        let mut sheet = Sheet::new("Test");
+        sheet.set_cell_value(CellCoord::new(0, 0), "10");
+        sheet.set_cell_value(CellCoord::new(1, 0), "=A1*2");
+        sheet.set_cell_value(CellCoord::new(0, 3), "=A1+A2+B1");
+
+        // Cut A1:A2 and paste at C5
+        let source = CellRange::new(CellCoord::new(0, 0), CellCoord::new(1, 0));
+        let mut cmd = PasteCommand::cut(source, CellCoord::new(4, 2));
+        cmd.execute(&mut sheet);
+
+        assert!(sheet.get_cell(CellCoord::new(0, 0)).is_none());
+        assert!(sheet.get_cell(CellCoord::new(1, 0)).is_none());
+        assert_eq!(sheet.get_cell_value(CellCoord::new(4, 2)).as_number(), Some(10.0));
+        // The moved formula still points at the moved value
+        assert_eq!(formula_at(&sheet, CellCoord::new(5, 2)), Some("=C5*2".to_string()));
+        // Other formulas follow the moved cells
+        assert_eq!(formula_at(&sheet, CellCoord::new(0, 3)), Some("=C5+C6+B1".to_string()));
+
+        cmd.undo(&mut sheet);
+        assert_eq!(sheet.get_cell_value(CellCoord::new(0, 0)).as_number(), Some(10.0));
+        assert_eq!(formula_at(&sheet, CellCoord::new(1, 0)), Some("=A1*2".to_string()));
+        assert_eq!(formula_at(&sheet, CellCoord::new(0, 3)), Some("=A1+A2+B1".to_string()));
+        assert!(sheet.get_cell(CellCoord::new(4, 2)).is_none());
+        assert!(sheet.get_cell(CellCoord::new(5, 2)).is_none());
+    }
+
+    #[test]
+    fn test_paste_cut_moves_sheet_qualified_references() {
+        let mut workbook = Workbook::new("Test");
+        workbook.add_sheet("Other").unwrap();
+        let sheet = workbook.get_sheet_mut(0).unwrap();
+        sheet.set_cell_value(CellCoord::new(0, 0), "10");
+        sheet.set_cell_value(CellCoord::new(0, 3), "=Sheet1!A1*2");
+        let other = workbook.get_sheet_mut(1).unwrap();
+        other.set_cell_value(CellCoord::new(0, 0), "=Sheet1!A1+A1");
+
+        let source = CellRange::new(CellCoord::new(0, 0), CellCoord::new(0, 0));
+        let mut cmd = PasteCommand::cut(source, CellCoord::new(4, 2));
+        cmd.execute_in(&mut workbook, 0);
+        let sheet = workbook.get_sheet(0).unwrap();
+        assert_eq!(formula_at(sheet, CellCoord::new(0, 3)), Some("=Sheet1!C5*2".to_string()));
+        let other = workbook.get_sheet(1).unwrap();
+        assert_eq!(formula_at(other, CellCoord::new(0, 0)), Some("=Sheet1!C5+A1".to_string()));
+
+        cmd.undo_in(&mut workbook, 0);
+        let other = workbook.get_sheet(1).unwrap();
+        assert_eq!(formula_at(other, CellCoord::new(0, 0)), Some("=Sheet1!A1+A1".to_string()));
+        let sheet = workbook.get_sheet(0).unwrap();
+        assert_eq!(formula_at(sheet, CellCoord::new(0, 3)), Some("=Sheet1!A1*2".to_string()));
+    }
+
+    #[test]
+    fn test_paste_cut_overlapping_ranges() {
+        let mut sheet = Sheet::new("Test");
+        sheet.set_cell_value(CellCoord::new(0, 0), "1");
+        sheet.set_cell_value(CellCoord::new(1, 0), "2");
+
+        // Move A1:A2 down one row
+        let source = CellRange::new(CellCoord::new(0, 0), CellCoord::new(1, 0));
+        let mut cmd = PasteCommand::cut(source, CellCoord::new(1, 0));
+        cmd.execute(&mut sheet);
+
+        assert!(sheet.get_cell(CellCoord::new(0, 0)).is_none());
+        assert_eq!(sheet.get_cell_value(CellCoord::new(1, 0)).as_number(), Some(1.0));
+        assert_eq!(sheet.get_cell_value(CellCoord::new(2, 0)).as_number(), Some(2.0));
+
+        cmd.undo(&mut sheet);
+        assert_eq!(sheet.get_cell_value(CellCoord::new(0, 0)).as_number(), Some(1.0));
+        assert_eq!(sheet.get_cell_value(CellCoord::new(1, 0)).as_number(), Some(2.0));
+        assert!(sheet.get_cell(CellCoord::new(2, 0)).is_none());
+    }
+
+    #[test]
+    fn test_fill_repeats_pattern() {
+        let mut sheet = Sheet::new("Test");
+        sheet.set_cell_value(CellCoord::new(0, 1), "=A1*2");
+
+        // Fill B1 down to B4
+        let source = CellRange::new(CellCoord::new(0, 1), CellCoord::new(0, 1));
+        let target = CellRange::new(CellCoord::new(0, 1), CellCoord::new(3, 1));
+        let mut cmd = FillCommand::new(source, target);
+        let affected = cmd.execute(&mut sheet);
+
+        assert_eq!(affected.len(), 3);
+        assert_eq!(formula_at(&sheet, CellCoord::new(1, 1)), Some("=A2*2".to_string()));
+        assert_eq!(formula_at(&sheet, CellCoord::new(3, 1)), Some("=A4*2".to_string()));
+
+        cmd.undo(&mut sheet);
+        assert!(sheet.get_cell(CellCoord::new(1, 1)).is_none());
+        assert!(sheet.get_cell(CellCoord::new(3, 1)).is_none());
+    }
+
+    #[test]
+    fn test_fill_upwards() {
+        let mut sheet = Sheet::new("Test");
+        sheet.set_cell_value(CellCoord::new(4, 1), "=A5");
+
+        let source = CellRange::new(CellCoord::new(4, 1), CellCoord::new(4, 1));
+        let target = CellRange::new(CellCoord::new(0, 1), CellCoord::new(4, 1));
+        let mut cmd = FillCommand::new(source, target);
+        cmd.execute(&mut sheet);
+
+        assert_eq!(formula_at(&sheet, CellCoord::new(0, 1)), Some("=A1".to_string()));
+        assert_eq!(formula_at(&sheet, CellCoord::new(3, 1)), Some("=A4".to_string()));
+    }
+
+    #[test]
+    fn test_set_hidden_command() {
+        let mut sheet = Sheet::new("Test");
+        sheet.set_hidden(Axis::Cols, 2, 2, true);
+
+        let mut cmd = SetHiddenCommand::new(Axis::Cols, 1, 3, true);
+        let affected = cmd.execute(&mut sheet);
+        assert_eq!(affected, vec![CellCoord::new(0, 1), CellCoord::new(0, 3)]);
+        assert_eq!(sheet.get_hidden_cols(), vec![1, 2, 3]);
+
+        // Undo only unhides what the command hid
+        cmd.undo(&mut sheet);
+        assert_eq!(sheet.get_hidden_cols(), vec![2]);
+    }
+
+    #[test]
+    fn test_edit_outline_command() {
+        let mut sheet = Sheet::new("Test");
+        EditOutlineCommand::new(Axis::Rows, OutlineEdit::Group { start: 1, end: 4 })
+            .execute(&mut sheet);
+
+        let mut collapse = EditOutlineCommand::new(
+            Axis::Rows,
+            OutlineEdit::SetCollapsed { start: 1, end: 4, collapsed: true },
+        );
+        assert_eq!(collapse.execute(&mut sheet).len(), 4);
+        assert_eq!(sheet.get_hidden_rows(), vec![1, 2, 3, 4]);
+
+        // Rejected edits change nothing and undo nothing
+        let mut overlap = EditOutlineCommand::new(Axis::Rows, OutlineEdit::Group { start: 3, end: 6 });
+        assert!(overlap.execute(&mut sheet).is_empty());
+        assert!(overlap.undo(&mut sheet).is_empty());
+
+        collapse.undo(&mut sheet);
+        assert!(sheet.get_hidden_rows().is_empty());
+        assert_eq!(sheet.outline(Axis::Rows).groups().len(), 1);
+    }
+
+    #[test]
+    fn test_delete_rows_restores_outline_on_undo() {
+        let mut sheet = Sheet::new("Test");
+        sheet.group(Axis::Rows, 2, 3).unwrap();
+        sheet.set_hidden(Axis::Rows, 3, 3, true);
+
+        let mut cmd = DeleteRowsCommand::new(1, 4);
+        cmd.execute(&mut sheet);
+        assert!(sheet.outline(Axis::Rows).groups().is_empty());
+        assert!(sheet.get_hidden_rows().is_empty());
+
+        cmd.undo(&mut sheet);
+        assert!(sheet.outline(Axis::Rows).find(2, 3).is_some());
+        assert_eq!(sheet.get_hidden_rows(), vec![3]);
+    }
+
+
+    #[test]
+    fn test_set_frozen_panes_command() {
+        let mut sheet = Sheet::new("Test");
+        let mut freeze = SetFrozenPanesCommand::new(3, 1);
+        freeze.execute(&mut sheet);
+        assert_eq!((sheet.frozen_rows, sheet.frozen_cols), (3, 1));
+
+        // Deleting into the frozen rows shrinks them, and undo restores them
+        let mut delete = DeleteRowsCommand::new(1, 4);
+        delete.execute(&mut sheet);
+        assert_eq!(sheet.frozen_rows, 1);
+        delete.undo(&mut sheet);
+        assert_eq!(sheet.frozen_rows, 3);
+
+        freeze.undo(&mut sheet);
+        assert_eq!((sheet.frozen_rows, sheet.frozen_cols), (0, 0));
+    }
+
+
+    #[test]
+    fn test_edit_comment_command() {
+        let mut sheet = Sheet::new("Test");
+        let coord = CellCoord::new(1, 1);
+
+        // Replies need a thread to reply to
+        let reply = CommentReply::new("r1", "bo", 2, "done");
+        let mut orphan = EditCommentCommand::new(coord, CommentEdit::Reply(reply.clone()));
+        assert!(orphan.execute(&mut sheet).is_empty());
+
+        let comment = Comment::new("c1", "ana", 1, "@bo fix this");
+        EditCommentCommand::new(coord, CommentEdit::Add(comment.clone())).execute(&mut sheet);
+        let mut add_again = EditCommentCommand::new(coord, CommentEdit::Add(comment.clone()));
+        assert!(add_again.execute(&mut sheet).is_empty());
+
+        let mut reply_cmd = EditCommentCommand::new(coord, CommentEdit::Reply(reply));
+        reply_cmd.execute(&mut sheet);
+        let mut resolve = EditCommentCommand::new(coord, CommentEdit::SetResolved(true));
+        resolve.execute(&mut sheet);
+        let thread = sheet.get_comment(coord).unwrap();
+        assert!(thread.resolved);
+        assert_eq!(thread.replies.len(), 1);
+
+        let mut delete = EditCommentCommand::new(coord, CommentEdit::Delete);
+        delete.execute(&mut sheet);
+        assert!(sheet.get_comment(coord).is_none());
+        assert!(sheet.get_cell(coord).is_none());
+
+        delete.undo(&mut sheet);
+        resolve.undo(&mut sheet);
+        reply_cmd.undo(&mut sheet);
+        assert_eq!(sheet.get_comment(coord), Some(&comment));
+    }
+
+    #[test]
+    fn test_hyperlinks_follow_row_and_column_shifts() {
+        let mut sheet = Sheet::new("Sheet1");
+        let link = |location: &str| Some(Hyperlink::location(location).with_tooltip("go"));
+        let mut set = SetHyperlinkCommand::new(CellCoord::new(0, 0), link("B5"));
+        set.execute(&mut sheet);
+        sheet.set_hyperlink(CellCoord::new(0, 1), link("Sheet1!C2:D3"));
+        sheet.set_hyperlink(CellCoord::new(6, 0), link("Other!B5"));
+
+        let mut insert = InsertRowsCommand::new(2, 2);
+        insert.execute(&mut sheet);
+        assert_eq!(sheet.get_hyperlink(CellCoord::new(0, 0)), link("B7").as_ref());
+        assert_eq!(sheet.get_hyperlink(CellCoord::new(0, 1)), link("Sheet1!C2:D5").as_ref());
+        assert_eq!(sheet.get_hyperlink(CellCoord::new(8, 0)), link("Other!B5").as_ref());
+
+        let mut delete = DeleteColsCommand::new(1, 1);
+        delete.execute(&mut sheet);
+        assert_eq!(sheet.get_hyperlink(CellCoord::new(0, 0)), link("#REF!").as_ref());
+        assert_eq!(sheet.get_hyperlink(CellCoord::new(0, 1)), None);
+
+        delete.undo(&mut sheet);
+        insert.undo(&mut sheet);
+        assert_eq!(sheet.get_hyperlink(CellCoord::new(0, 0)), link("B5").as_ref());
+        assert_eq!(sheet.get_hyperlink(CellCoord::new(0, 1)), link("Sheet1!C2:D3").as_ref());
+
+        set.undo(&mut sheet);
+        assert!(sheet.get_cell(CellCoord::new(0, 0)).is_none());
+    }
+
+    #[test]
+    fn test_hyperlinks_on_other_sheets_follow_shifts() {
+        let mut workbook = Workbook::new("Test");
+        workbook.add_sheet("My Data").unwrap();
+        let link = |location: &str| Some(Hyperlink::location(location));
+        let other = workbook.get_sheet_mut(1).unwrap();
+        other.set_hyperlink(CellCoord::new(0, 0), link("Sheet1!A5"));
+        other.set_hyperlink(CellCoord::new(1, 0), link("A5"));
+        let data = workbook.get_sheet_mut(0).unwrap();
+        data.set_hyperlink(CellCoord::new(0, 0), link("'My Data'!B2"));
+
+        let mut insert = InsertRowsCommand::new(1, 2);
+        insert.execute_in(&mut workbook, 0);
+        let other = workbook.get_sheet(1).unwrap();
+        assert_eq!(other.get_hyperlink(CellCoord::new(0, 0)), link("Sheet1!A7").as_ref());
+        assert_eq!(other.get_hyperlink(CellCoord::new(1, 0)), link("A5").as_ref());
+
+        let mut delete = DeleteColsCommand::new(1, 1);
+        delete.execute_in(&mut workbook, 1);
+        let data = workbook.get_sheet(0).unwrap();
+        assert_eq!(data.get_hyperlink(CellCoord::new(0, 0)), link("#REF!").as_ref());
+
+        delete.undo_in(&mut workbook, 1);
+        insert.undo_in(&mut workbook, 0);
+        let other = workbook.get_sheet(1).unwrap();
+        assert_eq!(other.get_hyperlink(CellCoord::new(0, 0)), link("Sheet1!A5").as_ref());
+        let data = workbook.get_sheet(0).unwrap();
+        assert_eq!(data.get_hyperlink(CellCoord::new(0, 0)), link("'My Data'!B2").as_ref());
+    }
+
+    #[test]
+    fn test_edit_table_command() {
+        let mut sheet = Sheet::new("Test");
+        for (r, row) in [["Region", "Amount"], ["West", "3"], ["East", "1"]].iter().enumerate() {
+            for (c, value) in row.iter().enumerate() {
+                sheet.set_cell_value(CellCoord::new(r as u32, c as u32), value);
+            }
+        }
+        let range = CellRange::from_a1("A1:B3").unwrap();
+
+        let mut create = EditTableCommand::new("", TableEdit::Create(Table::new("Sales", range)));
+        create.execute(&mut sheet);
+        assert!(create.error().is_none());
+        let mut again = EditTableCommand::new("", TableEdit::Create(Table::new("Sales", range)));
+        assert!(again.execute(&mut sheet).is_empty());
+        assert_eq!(again.error(), Some(&RusheetError::TableNameExists("Sales".to_string())));
+
+        let mut totals = EditTableCommand::new("sales", TableEdit::SetTotalsRow(true));
+        totals.execute(&mut sheet);
+        let mut sort = EditTableCommand::new("Sales", TableEdit::Sort { column: 1, ascending: true });
+        sort.execute(&mut sheet);
+        let hidden = HashSet::from(["West".to_string()]);
+        let mut filter = EditTableCommand::new(
+            "Sales",
+            TableEdit::Filter { column: 0, visible_values: Some(hidden) },
+        );
+        filter.execute(&mut sheet);
+        assert_eq!(sheet.get_cell_value(CellCoord::new(1, 0)).as_text(), "East");
+        assert_eq!(sheet.get_cell_value(CellCoord::new(3, 0)).as_text(), "Total");
+        assert!(sheet.is_row_hidden(1));
+
+        filter.undo(&mut sheet);
+        assert!(!sheet.is_row_hidden(1));
+        sort.undo(&mut sheet);
+        assert_eq!(sheet.get_cell_value(CellCoord::new(1, 0)).as_text(), "West");
+        totals.undo(&mut sheet);
+        assert!(sheet.get_cell(CellCoord::new(3, 0)).is_none());
+        assert!(!sheet.table("Sales").unwrap().totals_row);
+
+        // Deleting the header row drops the table until undone
+        let mut delete = DeleteRowsCommand::new(0, 1);
+        delete.execute(&mut sheet);
+        assert!(sheet.tables().is_empty());
+        delete.undo(&mut sheet);
+        assert_eq!(sheet.table("Sales").unwrap().range, range);
+
+        create.undo(&mut sheet);
+        assert!(sheet.tables().is_empty());
+        assert_eq!(sheet.get_cell_value(CellCoord::new(0, 1)).as_text(), "Amount");
+    }
+
+    #[test]
+    fn test_rename_table_command() {
+        let mut workbook = Workbook::new("Test");
+        workbook.add_sheet("Other").unwrap();
+        let range = CellRange::from_a1("A1:B3").unwrap();
+        workbook.add_table(0, Table::new("Sales", range)).unwrap();
+        let other = workbook.get_sheet_mut(1).unwrap();
+        other.set_cell_value(CellCoord::new(0, 0), "=SUM(sales[Amount])");
+
+        let mut rename = EditTableCommand::new("Sales", TableEdit::Rename("Orders".to_string()));
+        rename.execute_in(&mut workbook, 0);
+        assert!(rename.error().is_none());
+        assert!(workbook.table("Orders").is_some());
+        let formula = |workbook: &Workbook| {
+            let cell = workbook.get_sheet(1).unwrap().get_cell(CellCoord::new(0, 0)).unwrap();
+            cell.content.formula_expression().unwrap().to_string()
+        };
+        assert_eq!(formula(&workbook), "=SUM(Orders[Amount])");
+
+        rename.undo_in(&mut workbook, 0);
+        assert!(workbook.table("Sales").is_some());
+        assert_eq!(formula(&workbook), "=SUM(sales[Amount])");
+
+        let mut invalid = EditTableCommand::new("Sales", TableEdit::Rename("1st".to_string()));
+        invalid.execute_in(&mut workbook, 0);
+        assert_eq!(invalid.error(), Some(&RusheetError::InvalidTableName("1st".to_string())));
+    }
+
+    #[test]
+    fn test_edit_pivot_command() {
+        use rusheet_core::{PivotAggregation, PivotField, PivotValueField};
+
+        let mut wb = Workbook::new("Test");
+        for (r, row) in [["Region", "Amount"], ["West", "3"], ["East", "1"]].iter().enumerate() {
+            for (c, value) in row.iter().enumerate() {
+                wb.sheets[0].set_cell_value(CellCoord::new(r as u32, c as u32), value);
+            }
+        }
+        wb.sheets[0].set_cell_value(CellCoord::new(1, 4), "keep");
+        let pivot = PivotTable::new(
+            "Pivot",
+            "Sheet1",
+            CellRange::from_a1("A1:B3").unwrap(),
+            "Sheet1",
+            CellCoord::new(0, 3),
+        )
+        .with_rows(vec![PivotField::new(0)])
+        .with_values(vec![PivotValueField::new(1, PivotAggregation::Sum)]);
+
+        // Existing data is only replaced when asked to
+        let mut refused = EditPivotCommand::new("", PivotEdit::Create(pivot.clone()), false);
+        assert!(refused.execute_in(&mut wb, 0).is_empty());
+        assert_eq!(refused.error(), Some(&RusheetError::PivotTargetNotEmpty));
+        assert!(wb.pivots.is_empty());
+
+        let mut create = EditPivotCommand::new("", PivotEdit::Create(pivot), true);
+        assert!(!create.execute_in(&mut wb, 0).is_empty());
+        assert!(create.error().is_none());
+        assert_eq!(wb.sheets[0].get_cell_value(CellCoord::new(1, 4)).as_text(), "1");
+
+        let mut remove = EditPivotCommand::new("Pivot", PivotEdit::Remove, false);
+        let mut steps = CompositeCommand::new(vec![Box::new(remove)], "Delete");
+        steps.execute_in(&mut wb, 0);
+        assert!(wb.pivots.is_empty());
+        steps.undo_in(&mut wb, 0);
+        assert_eq!(wb.pivots.len(), 1);
+
+        create.undo_in(&mut wb, 0);
+        assert!(wb.pivots.is_empty());
+        assert_eq!(wb.sheets[0].get_cell_value(CellCoord::new(1, 4)).as_text(), "keep");
+        assert!(wb.sheets[0].get_cell(CellCoord::new(0, 3)).is_none());
+        remove = EditPivotCommand::new("Pivot", PivotEdit::Remove, false);
+        remove.execute_in(&mut wb, 0);
+        assert_eq!(remove.error(), Some(&RusheetError::PivotNotFound("Pivot".to_string())));
+    }
 }
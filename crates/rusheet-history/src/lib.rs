@@ -3,8 +3,10 @@ pub mod stack;
 
 pub use command::{
     ApplyFilterCommand, ClearCellCommand, ClearFilterCommand, ClearRangeCommand, Command,
-    CommandBox, CompositeCommand, DeleteColsCommand, DeleteRowsCommand, InsertColsCommand,
-    InsertRowsCommand, MergeCellsCommand, SetCellFormatCommand, SetCellValueCommand,
-    SetRangeFormatCommand, SortRangeCommand, UnmergeCellsCommand,
+    CommandBox, CommentEdit, CompositeCommand, DeleteColsCommand, DeleteRowsCommand,
+    EditCommentCommand, EditOutlineCommand, EditPivotCommand, EditTableCommand, FillCommand, InsertColsCommand, InsertRowsCommand,
+    MergeCellsCommand, OutlineEdit, PasteCommand, PivotEdit, SetBordersCommand, SetCellFormatCommand,
+    SetCellValueCommand, SetFrozenPanesCommand, SetHiddenCommand, SetHyperlinkCommand,
+    SetRangeFormatCommand, SortRangeCommand, TableEdit, UnmergeCellsCommand,
 };
 pub use stack::HistoryManager;
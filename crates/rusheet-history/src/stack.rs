@@ -1,5 +1,5 @@
 use crate::command::CommandBox;
-use rusheet_core::{CellCoord, Sheet};
+use rusheet_core::{CellCoord, Sheet, Workbook};
 
 /// Manages undo/redo history for spreadsheet operations
 #[derive(Default)]
@@ -41,15 +41,50 @@ impl HistoryManager {
             }
         }
 
-        // Add to undo stack
+        self.push(command);
+        affected
+    }
+
+    /// Execute a command on sheet `sheet_index` of a workbook and add it to
+    /// the undo stack
+    ///
+    /// Unlike [`execute`](Self::execute), commands can reach other sheets and
+    /// workbook-level state such as pivot tables.
+    pub fn execute_in(
+        &mut self,
+        mut command: CommandBox,
+        workbook: &mut Workbook,
+        sheet_index: usize,
+    ) -> Vec<CellCoord> {
+        let affected = command.execute_in(workbook, sheet_index);
+        self.redo_stack.clear();
+        if self.enable_merging {
+            if let Some(last) = self.undo_stack.last_mut() {
+                if last.can_merge(&*command) && last.merge(&*command) {
+                    return affected;
+                }
+            }
+        }
+        self.push(command);
+        affected
+    }
+
+    /// Add a command that has already been executed to the undo stack
+    ///
+    /// Lets callers inspect a command's outcome before committing it to
+    /// history. The command is not merged with the previous one.
+    pub fn record(&mut self, command: CommandBox) {
+        self.redo_stack.clear();
+        self.push(command);
+    }
+
+    fn push(&mut self, command: CommandBox) {
         self.undo_stack.push(command);
 
         // Limit stack size
         while self.undo_stack.len() > self.max_size {
             self.undo_stack.remove(0);
         }
-
-        affected
     }
 
     /// Undo the last command
@@ -68,6 +103,22 @@ impl HistoryManager {
         Some(affected)
     }
 
+    /// Undo the last command on sheet `sheet_index` of a workbook
+    pub fn undo_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Option<Vec<CellCoord>> {
+        let mut command = self.undo_stack.pop()?;
+        let affected = command.undo_in(workbook, sheet_index);
+        self.redo_stack.push(command);
+        Some(affected)
+    }
+
+    /// Redo the last undone command on sheet `sheet_index` of a workbook
+    pub fn redo_in(&mut self, workbook: &mut Workbook, sheet_index: usize) -> Option<Vec<CellCoord>> {
+        let mut command = self.redo_stack.pop()?;
+        let affected = command.execute_in(workbook, sheet_index);
+        self.undo_stack.push(command);
+        Some(affected)
+    }
+
     /// Check if undo is available
     pub fn can_undo(&self) -> bool {
         !self.undo_stack.is_empty()
@@ -88,6 +139,16 @@ impl HistoryManager {
         self.redo_stack.last().map(|c| c.description())
     }
 
+    /// Check if the command that would be undone is structural
+    pub fn undo_is_structural(&self) -> bool {
+        self.undo_stack.last().is_some_and(|c| c.is_structural())
+    }
+
+    /// Check if the command that would be redone is structural
+    pub fn redo_is_structural(&self) -> bool {
+        self.redo_stack.last().is_some_and(|c| c.is_structural())
+    }
+
     /// Get the number of commands in the undo stack
     pub fn undo_count(&self) -> usize {
         self.undo_stack.len()
@@ -136,7 +197,9 @@ impl std::fmt::Debug for HistoryManager {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::command::SetCellValueCommand;
+    use crate::command::{
+        CompositeCommand, InsertRowsCommand, SetCellValueCommand, SortRangeCommand,
+    };
     use rusheet_core::CellValue;
 
     #[test]
@@ -204,6 +267,24 @@ mod tests {
         assert_eq!(history.undo_count(), 3);
     }
 
+    #[test]
+    fn test_structural_commands() {
+        let mut sheet = Sheet::new("Test");
+        let mut history = HistoryManager::new(100);
+        let set = SetCellValueCommand::from_value(CellCoord::new(0, 0), CellValue::Number(1.0));
+        history.execute(Box::new(set), &mut sheet);
+        assert!(!history.undo_is_structural());
+
+        let sort = Box::new(SortRangeCommand::new(0, 2, 0, 0, 0, true));
+        let insert = Box::new(InsertRowsCommand::new(0, 1));
+        let both = CompositeCommand::new(vec![sort, insert], "Sort and insert");
+        history.execute(Box::new(both), &mut sheet);
+        assert!(history.undo_is_structural());
+        history.undo(&mut sheet);
+        assert!(history.redo_is_structural());
+        assert!(!history.undo_is_structural());
+    }
+
     #[test]
     fn test_multiple_undo_redo() {
         let mut sheet = Sheet::new("Test");
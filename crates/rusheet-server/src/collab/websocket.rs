@@ -31,7 +31,7 @@ async fn handle_socket(socket: WebSocket, state: AppState, workbook_id: Uuid) {
     {
         let doc_guard = doc.read().await;
         let initial_state = doc_guard.encode_state();
-        if let Err(e) = sender.send(Message::Binary(initial_state.into())).await {
+        if let Err(e) = sender.send(Message::Binary(initial_state)).await {
             tracing::error!("Failed to send initial state: {}", e);
             return;
         }
@@ -47,7 +47,7 @@ async fn handle_socket(socket: WebSocket, state: AppState, workbook_id: Uuid) {
     let doc_clone = doc.clone();
     let mut send_task = tokio::spawn(async move {
         while let Ok(update) = update_rx.recv().await {
-            if sender.send(Message::Binary(update.into())).await.is_err() {
+            if sender.send(Message::Binary(update)).await.is_err() {
                 break;
             }
         }
@@ -1,27 +1,52 @@
 use rusheet_core::{
-    CellContent, CellCoord, CellError, CellFormat, CellRange, CellValue,
-    ConditionalFormattingRule, ConditionalRule, HorizontalAlign, RusheetError,
-    VerticalAlign, Workbook, DataValidationRule, ValidationCriteria, ValidationResult,
-    ValidationAlert, ValidationMessage, AlertStyle,
+    CellContent, CellCoord, CellFormat, CellRange, CellValue, FormatDiff,
+    parse_date_time_input, Color, ConditionalFormattingRule, DateOrder, DateSystem, ConditionalRule,
+    HorizontalAlign, Hyperlink, LinkTarget, RichText, RunFormat, RusheetError, TextRun,
+    VerticalAlign, Workbook,
+    DataValidationRule, ValidationCriteria, ValidationResult, ValidationAlert, ValidationMessage,
+    AlertStyle, Axis, BorderLine, Comment, CommentReply, PaneKind, BorderStyle, BorderTarget,
+    SummaryPosition, NamedStyle, PivotField, PivotFilter, PivotTable, PivotValueField, Table, TableSort, TableStyle, ThemeColor, ThemeFont,
+    TotalsFunction,
 };
-use rusheet_formula::{extract_references, DependencyGraph};
+use rusheet_formula::{CalcEngine, GoalSeekOptions, TraceNode};
 use rusheet_history::{
-    ApplyFilterCommand, ClearFilterCommand, ClearRangeCommand, DeleteColsCommand,
-    DeleteRowsCommand, HistoryManager, InsertColsCommand, InsertRowsCommand, MergeCellsCommand,
-    SetCellFormatCommand, SetCellValueCommand, SetRangeFormatCommand, SortRangeCommand,
-    UnmergeCellsCommand,
+    ApplyFilterCommand, ClearFilterCommand, ClearRangeCommand, Command, CommandBox,
+    CompositeCommand, DeleteColsCommand, DeleteRowsCommand, CommentEdit, EditCommentCommand,
+    EditOutlineCommand, EditPivotCommand, EditTableCommand, FillCommand, HistoryManager,
+    InsertColsCommand, InsertRowsCommand,
+    MergeCellsCommand, OutlineEdit, PasteCommand, PivotEdit, SetBordersCommand, SetCellFormatCommand,
+    SetCellValueCommand, SetFrozenPanesCommand, SetHiddenCommand, SetHyperlinkCommand,
+    SetRangeFormatCommand, SortRangeCommand, TableEdit, UnmergeCellsCommand,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use wasm_bindgen::prelude::*;
 
 use crate::viewport::{pack_format, ViewportBuffer};
 
+/// Current time for TODAY/NOW
+///
+/// std's system clock is unavailable in the browser, so read it from JS there.
+#[cfg(target_arch = "wasm32")]
+fn current_unix_ms() -> Option<f64> {
+    Some(js_sys::Date::now())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn current_unix_ms() -> Option<f64> {
+    None
+}
+
+/// Timestamp for comment edits, in Unix milliseconds
+fn comment_timestamp() -> i64 {
+    current_unix_ms().unwrap_or(0.0) as i64
+}
+
 /// Main spreadsheet engine exposed to JavaScript
 #[wasm_bindgen]
 pub struct SpreadsheetEngine {
-    workbook: Workbook,
-    dep_graph: DependencyGraph,
+    /// Workbook with its dependency graph and recalculation state
+    calc: CalcEngine,
     history: HistoryManager,
     /// Reusable buffer for viewport data (zero-copy optimization)
     viewport_buffer: ViewportBuffer,
@@ -66,6 +91,15 @@ pub struct CellData {
     pub display_value: String,
     pub formula: Option<String>,
     pub format: CellFormatData,
+    /// Formatting runs when the text has mixed formatting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rich_text: Option<Vec<TextRunData>>,
+    /// Comment thread attached to the cell
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<Comment>,
+    /// Link the cell opens, from its hyperlink or a HYPERLINK() formula
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hyperlink: Option<Hyperlink>,
     pub row: u32,
     pub col: u32,
 }
@@ -96,12 +130,325 @@ pub struct CellFormatData {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "verticalAlign")]
     pub vertical_align: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_family: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number_format: Option<String>,
+    /// Named style the format is based on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
+    /// Theme slot the text color follows
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_theme_color: Option<ThemeColor>,
+    /// Theme slot the background color follows
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_theme_color: Option<ThemeColor>,
+    /// Theme font the font family follows
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme_font: Option<ThemeFont>,
+}
+
+/// Named cell style for JavaScript
+#[derive(Serialize, Deserialize)]
+pub struct CellStyleData {
+    pub name: String,
+    pub format: CellFormatData,
+}
+
+/// Workbook theme for JavaScript
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThemeData {
+    pub name: String,
+    /// Hex colors by palette slot
+    pub colors: BTreeMap<ThemeColor, String>,
+    pub major_font: String,
+    pub minor_font: String,
 }
 
 fn is_false(b: &bool) -> bool {
     !*b
 }
 
+/// Rich text run for JavaScript
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextRunData {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub bold: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub italic: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub underline: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub strikethrough: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_size: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_family: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_color: Option<String>,
+}
+
+impl From<&TextRun> for TextRunData {
+    fn from(run: &TextRun) -> Self {
+        let format = &run.format;
+        TextRunData {
+            text: run.text.clone(),
+            bold: format.bold,
+            italic: format.italic,
+            underline: format.underline,
+            strikethrough: format.strikethrough,
+            font_size: format.font_size,
+            font_family: format.font_family.clone(),
+            text_color: format.text_color.map(|c| c.to_hex()),
+        }
+    }
+}
+
+fn text_runs_data(text: &RichText) -> Vec<TextRunData> {
+    text.runs().iter().map(TextRunData::from).collect()
+}
+
+fn rich_text_from_data(runs: Vec<TextRunData>) -> RichText {
+    let mut text = RichText::new();
+    for run in runs {
+        let format = RunFormat {
+            bold: run.bold,
+            italic: run.italic,
+            underline: run.underline,
+            strikethrough: run.strikethrough,
+            font_size: run.font_size,
+            font_family: run.font_family,
+            text_color: run.text_color.as_deref().and_then(Color::from_hex),
+        };
+        text.push(run.text, format);
+    }
+    text
+}
+
+/// Border line for JavaScript
+#[derive(Serialize, Deserialize)]
+pub struct BorderLineData {
+    pub style: BorderStyle,
+    pub color: String,
+}
+
+/// Outline group for JavaScript
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutlineGroupData {
+    pub start: u32,
+    pub end: u32,
+    pub level: u8,
+    pub collapsed: bool,
+    /// Row or column that carries the expand/collapse toggle
+    pub summary_index: Option<u32>,
+}
+
+/// Row or column outline for JavaScript
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutlineData {
+    pub summary: SummaryPosition,
+    pub max_level: u8,
+    pub groups: Vec<OutlineGroupData>,
+}
+
+/// Comment thread and the cell it sits on, for JavaScript
+#[derive(Serialize)]
+pub struct CommentData {
+    pub row: u32,
+    pub col: u32,
+    pub comment: Comment,
+}
+
+/// A cell's link, for JavaScript
+#[derive(Serialize)]
+pub struct HyperlinkData {
+    pub row: u32,
+    pub col: u32,
+    pub hyperlink: Hyperlink,
+}
+
+/// Where following a link goes, for JavaScript
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum LinkDestinationData {
+    Url {
+        url: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Location {
+        sheet_index: usize,
+        start_row: u32,
+        start_col: u32,
+        end_row: u32,
+        end_col: u32,
+    },
+}
+
+/// A cell on a given sheet, for JavaScript
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SheetCellData {
+    pub sheet_index: usize,
+    pub row: u32,
+    pub col: u32,
+}
+
+/// A table's banded styling, for JavaScript
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableStyleData {
+    pub header_color: String,
+    pub header_text_color: String,
+    pub band_color: String,
+    pub banded_rows: bool,
+    pub banded_columns: bool,
+    pub first_column: bool,
+    pub last_column: bool,
+}
+
+impl From<&TableStyle> for TableStyleData {
+    fn from(style: &TableStyle) -> Self {
+        Self {
+            header_color: style.header_color.to_hex(),
+            header_text_color: style.header_text_color.to_hex(),
+            band_color: style.band_color.to_hex(),
+            banded_rows: style.banded_rows,
+            banded_columns: style.banded_columns,
+            first_column: style.first_column,
+            last_column: style.last_column,
+        }
+    }
+}
+
+/// A column filter within a table, for JavaScript
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableFilterData {
+    pub column: u32,
+    pub visible_values: Vec<String>,
+}
+
+/// A table on the active sheet, for JavaScript
+///
+/// Column positions (`columns`, `sort`, `filters`) count from the table's
+/// first column.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableData {
+    pub name: String,
+    pub start_row: u32,
+    pub start_col: u32,
+    pub end_row: u32,
+    pub end_col: u32,
+    pub columns: Vec<String>,
+    pub totals_row: bool,
+    pub auto_expand: bool,
+    pub style: TableStyleData,
+    pub sort: Option<TableSort>,
+    pub filters: Vec<TableFilterData>,
+}
+
+/// A pivot table definition, for JavaScript
+///
+/// Blank sheet names mean the active sheet. Field columns count from the
+/// source range's first column. The output starts at the target cell and
+/// ends at `outputEndRow`/`outputEndCol` once written.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PivotTableData {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub source_sheet: String,
+    pub source_start_row: u32,
+    pub source_start_col: u32,
+    pub source_end_row: u32,
+    pub source_end_col: u32,
+    #[serde(default)]
+    pub target_sheet: String,
+    pub target_row: u32,
+    pub target_col: u32,
+    #[serde(default)]
+    pub rows: Vec<PivotField>,
+    #[serde(default)]
+    pub columns: Vec<PivotField>,
+    pub values: Vec<PivotValueField>,
+    #[serde(default)]
+    pub filters: Vec<PivotFilter>,
+    #[serde(default = "default_true")]
+    pub grand_totals: bool,
+    #[serde(default, skip_deserializing)]
+    pub output_end_row: Option<u32>,
+    #[serde(default, skip_deserializing)]
+    pub output_end_col: Option<u32>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl From<&PivotTable> for PivotTableData {
+    fn from(pivot: &PivotTable) -> Self {
+        Self {
+            name: pivot.name.clone(),
+            source_sheet: pivot.source_sheet.clone(),
+            source_start_row: pivot.source.start.row,
+            source_start_col: pivot.source.start.col,
+            source_end_row: pivot.source.end.row,
+            source_end_col: pivot.source.end.col,
+            target_sheet: pivot.target_sheet.clone(),
+            target_row: pivot.target.row,
+            target_col: pivot.target.col,
+            rows: pivot.rows.clone(),
+            columns: pivot.columns.clone(),
+            values: pivot.values.clone(),
+            filters: pivot.filters.clone(),
+            grand_totals: pivot.grand_totals,
+            output_end_row: pivot.output.map(|o| o.end.row),
+            output_end_col: pivot.output.map(|o| o.end.col),
+        }
+    }
+}
+
+impl From<PivotTableData> for PivotTable {
+    fn from(data: PivotTableData) -> Self {
+        let source = CellRange::new(
+            CellCoord::new(data.source_start_row, data.source_start_col),
+            CellCoord::new(data.source_end_row, data.source_end_col),
+        );
+        let target = CellCoord::new(data.target_row, data.target_col);
+        let mut pivot = PivotTable::new(data.name, data.source_sheet, source, data.target_sheet, target)
+            .with_rows(data.rows)
+            .with_columns(data.columns)
+            .with_values(data.values);
+        pivot.filters = data.filters;
+        pivot.grand_totals = data.grand_totals;
+        pivot
+    }
+}
+
+/// Viewport pane for JavaScript
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaneData {
+    pub kind: PaneKind,
+    pub start_row: u32,
+    pub end_row: u32,
+    pub start_col: u32,
+    pub end_col: u32,
+    /// Screen position of the pane
+    pub x: f64,
+    pub y: f64,
+    /// Added to sheet positions to draw them in the pane
+    pub offset_x: f64,
+    pub offset_y: f64,
+}
+
 /// Merge range data for JavaScript
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -122,6 +469,40 @@ pub struct MergeInfo {
     pub col_span: u32,
 }
 
+/// One step of a traced formula evaluation
+#[derive(Serialize)]
+pub struct TraceData {
+    /// Sub-expression text
+    pub text: String,
+    /// UTF-16 offsets of `text` within the root's text, for highlighting in JS
+    pub start: usize,
+    pub end: usize,
+    /// Display value, absent for multi-cell references
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub is_error: bool,
+    pub children: Vec<TraceData>,
+}
+
+impl TraceData {
+    fn from_node(node: &TraceNode, root_text: &str) -> Self {
+        let utf16_offset = |byte: usize| root_text[..byte].encode_utf16().count();
+        Self {
+            text: node.text.clone(),
+            start: utf16_offset(node.start),
+            end: utf16_offset(node.end),
+            value: node.value.as_ref().map(CellValue::as_text),
+            is_error: matches!(node.value, Some(CellValue::Error(_))),
+            children: node
+                .children
+                .iter()
+                .map(|child| Self::from_node(child, root_text))
+                .collect(),
+        }
+    }
+}
+
 impl From<&CellFormat> for CellFormatData {
     fn from(format: &CellFormat) -> Self {
         use rusheet_core::{HorizontalAlign, VerticalAlign};
@@ -143,6 +524,12 @@ impl From<&CellFormat> for CellFormatData {
                 VerticalAlign::Top => Some("top".to_string()),
                 VerticalAlign::Bottom => Some("bottom".to_string()),
             },
+            font_family: format.font_family.clone(),
+            number_format: format.number_format.clone(),
+            style: format.style.clone(),
+            text_theme_color: format.text_theme_color,
+            background_theme_color: format.background_theme_color,
+            theme_font: format.theme_font,
         }
     }
 }
@@ -152,9 +539,17 @@ impl SpreadsheetEngine {
     /// Create a new spreadsheet engine
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
+        // std has no entropy source in the browser, so seed RAND from JS
+        #[cfg(target_arch = "wasm32")]
+        rusheet_formula::functions::math::seed_random(
+            (js_sys::Math::random() * u64::MAX as f64) as u64,
+        );
+
+        let mut calc = CalcEngine::new(Workbook::new("Untitled"));
+        calc.set_clock(current_unix_ms);
+
         Self {
-            workbook: Workbook::new("Untitled"),
-            dep_graph: DependencyGraph::new(),
+            calc,
             history: HistoryManager::new(100),
             viewport_buffer: ViewportBuffer::with_capacity(1000),
         }
@@ -169,7 +564,7 @@ impl SpreadsheetEngine {
         let cell_value = self.parse_input_to_cell_value(value);
 
         // Check validation
-        let validation_result = self.workbook.active_sheet().validate_cell_value(row, col, &cell_value);
+        let validation_result = self.calc.workbook().active_sheet().validate_cell_value(row, col, &cell_value);
 
         if let ValidationResult::Invalid(alert) = validation_result {
             if alert.style == AlertStyle::Stop {
@@ -184,124 +579,83 @@ impl SpreadsheetEngine {
 
         // Proceed with original logic
         let coord = CellCoord::new(row, col);
-        let cmd = Box::new(SetCellValueCommand::from_input(coord, value));
+        let workbook = self.calc.workbook();
+        let mut cmd: CommandBox = Box::new(SetCellValueCommand::from_input_with(
+            coord,
+            value,
+            workbook.date_order,
+            workbook.date_system,
+        ));
 
-        // Execute command
-        let mut affected = self.history.execute(cmd, self.workbook.active_sheet_mut());
-
-        // Update dependency graph and recalculate if this is a formula
-        if value.starts_with('=') {
-            let refs = extract_references(value);
-            let deps: HashSet<_> = refs.into_iter().map(|(r, c)| (r, c)).collect();
-            self.dep_graph.set_dependencies((row, col), deps);
-
-            // Immediately evaluate the formula we just set
-            self.recalculate_cell(row, col);
-
-            // Then recalculate dependents
-            if let Ok(order) = self.dep_graph.get_recalc_order((row, col)) {
-                for (r, c) in order {
-                    if (r, c) != (row, col) {
-                        // Don't recalculate the formula cell twice
-                        self.recalculate_cell(r, c);
-                        affected.push(CellCoord::new(r, c));
-                    }
-                }
-            }
-        } else {
-            // Clear dependencies for non-formula cells
-            self.dep_graph.remove_cell((row, col));
-
-            // Recalculate cells that depend on this one
-            if let Ok(order) = self.dep_graph.get_recalc_order((row, col)) {
-                for (r, c) in order {
-                    if (r, c) != (row, col) {
-                        self.recalculate_cell(r, c);
-                        affected.push(CellCoord::new(r, c));
-                    }
-                }
-            }
+        // Typing just below or beside a table grows it
+        let expansion = match value.is_empty() {
+            true => None,
+            false => workbook.active_sheet().table_expansion(coord),
+        };
+        if let Some((name, range)) = expansion {
+            let resize = Box::new(EditTableCommand::new(name, TableEdit::Resize(range)));
+            cmd = Box::new(CompositeCommand::new(vec![cmd, resize], "Set cell value"));
         }
 
+        // Execute command
+        let mut affected = self.execute_command(cmd);
+
+        // Recalculate the edited cells and everything that depends on them
+        self.calc.cells_changed(affected.iter().copied());
+        affected.extend(self.recalculate_active());
+        affected.sort_unstable_by_key(|c| (c.row, c.col));
+        affected.dedup();
+
         // Return affected cells as JSON
         let coords: Vec<[u32; 2]> = affected.iter().map(|c| [c.row, c.col]).collect();
         serde_json::to_string(&coords).unwrap_or_else(|_| "[]".to_string())
     }
 
-    /// Recalculate a single cell's formula
-    fn recalculate_cell(&mut self, row: u32, col: u32) {
+    /// Set text with mixed formatting, given as a JSON array of runs
+    ///
+    /// Each run is `{ text, bold?, italic?, underline?, strikethrough?,
+    /// fontSize?, fontFamily?, textColor? }`. Returns JSON array of affected
+    /// cell coordinates, or an empty array if the runs don't parse.
+    #[wasm_bindgen(js_name = setCellRichText)]
+    pub fn set_cell_rich_text(&mut self, row: u32, col: u32, runs_json: &str) -> String {
+        match serde_json::from_str::<Vec<TextRunData>>(runs_json) {
+            Ok(runs) => self.apply_rich_text(row, col, rich_text_from_data(runs)),
+            Err(_) => "[]".to_string(),
+        }
+    }
+
+    /// Set text with mixed formatting from the runs of an XLSX shared string
+    ///
+    /// Takes the `<r>` elements of a `<si>`, as SheetJS exposes them in a
+    /// cell's `r` field. Returns JSON array of affected cell coordinates.
+    #[wasm_bindgen(js_name = setCellRichTextXml)]
+    pub fn set_cell_rich_text_xml(&mut self, row: u32, col: u32, xml: &str) -> String {
+        match RichText::from_ooxml(xml) {
+            Some(text) => self.apply_rich_text(row, col, text),
+            None => "[]".to_string(),
+        }
+    }
+
+    /// Get the runs of a rich text cell as the body of an XLSX shared string
+    #[wasm_bindgen(js_name = getCellRichTextXml)]
+    pub fn get_cell_rich_text_xml(&self, row: u32, col: u32) -> Option<String> {
+        let sheet = self.calc.workbook().active_sheet();
+        let cell = sheet.get_cell(CellCoord::new(row, col))?;
+        cell.text_runs().map(RichText::to_ooxml)
+    }
+
+    fn apply_rich_text(&mut self, row: u32, col: u32, text: RichText) -> String {
         let coord = CellCoord::new(row, col);
-        let current_sheet_name = self.workbook.active_sheet().name.to_string();
-
-        let expression = {
-            let sheet = self.workbook.active_sheet();
-            if let Some(cell) = sheet.get_cell(coord) {
-                if let CellContent::Formula { expression, .. } = &cell.content {
-                    Some(expression.clone())
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        };
+        let cmd = Box::new(SetCellValueCommand::from_rich_text(coord, text));
+        let mut affected = self.execute_command(cmd);
 
-        if let Some(expression) = expression {
-            // Log formula evaluation for debugging (only in WASM target)
-            #[cfg(all(debug_assertions, target_arch = "wasm32"))]
-            web_sys::console::log_1(&format!(
-                "[Formula] Evaluating cell ({}, {}): {}",
-                row, col, expression
-            ).into());
-
-            // Create closure to get cell values from any sheet
-            let result = rusheet_formula::evaluate_formula_cross_sheet(
-                &expression,
-                Some(&current_sheet_name),
-                |sheet_name, r, c| {
-                    let sheet = if let Some(name) = sheet_name {
-                        match self.workbook.get_sheet_by_name(name) {
-                            Some(s) => s,
-                            None => return CellValue::Error(CellError::InvalidReference),
-                        }
-                    } else {
-                        self.workbook.active_sheet()
-                    };
-                    sheet
-                        .get_cell(CellCoord::new(r, c))
-                        .map(|c| c.computed_value().clone())
-                        .unwrap_or(CellValue::Empty)
-                },
-            );
-
-            // Log result (only in WASM target)
-            #[cfg(all(debug_assertions, target_arch = "wasm32"))]
-            web_sys::console::log_1(&format!(
-                "[Formula] Result for ({}, {}): {:?}",
-                row, col, result
-            ).into());
-
-            // Check for errors and log them (only in WASM target)
-            #[cfg(target_arch = "wasm32")]
-            if matches!(result, CellValue::Error(_)) {
-                web_sys::console::error_1(&format!(
-                    "[Formula Error] Cell ({}, {}) formula '{}' failed: {:?}",
-                    row, col, expression, result
-                ).into());
-            }
+        self.calc.cell_changed(coord);
+        affected.extend(self.recalculate_active());
+        affected.sort_unstable_by_key(|c| (c.row, c.col));
+        affected.dedup();
 
-            // Update cached value
-            let sheet = self.workbook.active_sheet_mut();
-            if let Some(cell) = sheet.get_cell(coord) {
-                let new_content = CellContent::Formula {
-                    expression,
-                    cached_value: result,
-                };
-                let mut new_cell = cell.clone();
-                new_cell.content = new_content;
-                sheet.set_cell(coord, new_cell);
-            }
-        }
+        let coords: Vec<[u32; 2]> = affected.iter().map(|c| [c.row, c.col]).collect();
+        serde_json::to_string(&coords).unwrap_or_else(|_| "[]".to_string())
     }
 
     /// Helper to parse input to CellValue without setting it
@@ -328,6 +682,12 @@ impl SpreadsheetEngine {
             _ => {}
         }
 
+        // Dates and times validate as their serial number
+        let workbook = self.calc.workbook();
+        if let Some(date) = parse_date_time_input(input, workbook.date_order, workbook.date_system) {
+            return CellValue::Number(date.serial);
+        }
+
         // Default to text
         CellValue::Text(input.to_string())
     }
@@ -336,28 +696,37 @@ impl SpreadsheetEngine {
     #[wasm_bindgen(js_name = getCellData)]
     pub fn get_cell_data(&self, row: u32, col: u32) -> JsValue {
         let coord = CellCoord::new(row, col);
-        let sheet = self.workbook.active_sheet();
+        let workbook = self.calc.workbook();
+        let sheet = workbook.active_sheet();
 
         let data = if let Some(cell) = sheet.get_cell(coord) {
             // Apply conditional formatting to get effective format
-            let base_format = &cell.format;
+            let base_format = workbook.cell_format(cell);
             let value = cell.content.computed_value();
-            let effective_format = sheet.get_effective_format(row, col, base_format, value);
+            let effective_format = sheet.get_effective_format(row, col, &base_format, value);
 
             CellData {
                 value: Some(cell.content.original_input()),
-                display_value: cell.content.display_value(),
+                display_value: cell.display_value(&workbook.styles, workbook.date_system),
                 formula: cell.content.formula_expression().map(String::from),
                 format: CellFormatData::from(&effective_format),
+                rich_text: cell.text_runs().map(text_runs_data),
+                comment: cell.comment.clone(),
+                hyperlink: self.calc.hyperlink_at(coord),
                 row,
                 col,
             }
         } else {
+            // Empty cells in a table still show its banding
+            let format = sheet.table_at(coord).and_then(|table| table.format_at(coord));
             CellData {
                 value: None,
                 display_value: String::new(),
                 formula: None,
-                format: CellFormatData::default(),
+                format: format.as_ref().map(CellFormatData::from).unwrap_or_default(),
+                rich_text: None,
+                comment: None,
+                hyperlink: None,
                 row,
                 col,
             }
@@ -375,7 +744,8 @@ impl SpreadsheetEngine {
         start_col: u32,
         end_col: u32,
     ) -> String {
-        let sheet = self.workbook.active_sheet();
+        let workbook = self.calc.workbook();
+        let sheet = workbook.active_sheet();
         let mut cells: Vec<CellData> = Vec::new();
 
         for row in start_row..=end_row {
@@ -384,18 +754,39 @@ impl SpreadsheetEngine {
                 continue;
             }
             for col in start_col..=end_col {
+                if sheet.is_col_hidden(col) {
+                    continue;
+                }
                 let coord = CellCoord::new(row, col);
                 if let Some(cell) = sheet.get_cell(coord) {
                     // Apply conditional formatting to get effective format
-                    let base_format = &cell.format;
+                    let base_format = workbook.cell_format(cell);
                     let value = cell.content.computed_value();
-                    let effective_format = sheet.get_effective_format(row, col, base_format, value);
+                    let effective_format = sheet.get_effective_format(row, col, &base_format, value);
 
                     cells.push(CellData {
                         value: Some(cell.content.original_input()),
-                        display_value: cell.content.display_value(),
+                        display_value: cell.display_value(&workbook.styles, workbook.date_system),
                         formula: cell.content.formula_expression().map(String::from),
                         format: CellFormatData::from(&effective_format),
+                        rich_text: cell.text_runs().map(text_runs_data),
+                        comment: cell.comment.clone(),
+                        hyperlink: self.calc.hyperlink_at(coord),
+                        row,
+                        col,
+                    });
+                } else if let Some(format) =
+                    sheet.table_at(coord).and_then(|table| table.format_at(coord))
+                {
+                    // Empty cells in a table still show its banding
+                    cells.push(CellData {
+                        value: None,
+                        display_value: String::new(),
+                        formula: None,
+                        format: CellFormatData::from(&format),
+                        rich_text: None,
+                        comment: None,
+                        hyperlink: None,
                         row,
                         col,
                     });
@@ -421,10 +812,10 @@ impl SpreadsheetEngine {
         };
 
         let coord = CellCoord::new(row, col);
-        let format = cell_format_from_data(&format_data);
+        let format = format_diff_from_data(&format_data, self.calc.workbook());
 
         let cmd = Box::new(SetCellFormatCommand::new(coord, format));
-        self.history.execute(cmd, self.workbook.active_sheet_mut());
+        self.execute_command(cmd);
 
         true
     }
@@ -452,14 +843,52 @@ impl SpreadsheetEngine {
 
         let start = CellCoord::new(start_row, start_col);
         let end = CellCoord::new(end_row, end_col);
-        let format = cell_format_from_data(&format_data);
+        let format = format_diff_from_data(&format_data, self.calc.workbook());
 
         let cmd = Box::new(SetRangeFormatCommand::new(start, end, format));
-        self.history.execute(cmd, self.workbook.active_sheet_mut());
+        self.execute_command(cmd);
 
         true
     }
 
+    /// Apply (or with `null`, remove) a border line on part of a range
+    ///
+    /// `target` is one of "all", "outline", "inside", "insideHorizontal",
+    /// "insideVertical", "top", "right", "bottom", "left", "diagonalDown" or
+    /// "diagonalUp"; the line is `{"style": "thin", "color": "#000000"}`.
+    #[wasm_bindgen(js_name = setRangeBorders)]
+    pub fn set_range_borders(
+        &mut self,
+        start_row: u32,
+        start_col: u32,
+        end_row: u32,
+        end_col: u32,
+        target: &str,
+        line_json: &str,
+    ) -> Result<String, JsValue> {
+        let target: BorderTarget = serde_json::from_value(serde_json::Value::from(target))
+            .map_err(|e| JsValue::from_str(&format!("Invalid border target: {}", e)))?;
+        let line: Option<BorderLineData> = serde_json::from_str(line_json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid border line: {}", e)))?;
+        let line = line
+            .map(|data| {
+                Color::from_hex(&data.color)
+                    .map(|color| BorderLine::new(data.style, color))
+                    .ok_or_else(|| JsValue::from_str(&format!("Invalid border color: {}", data.color)))
+            })
+            .transpose()?;
+
+        let range = CellRange::new(
+            CellCoord::new(start_row, start_col),
+            CellCoord::new(end_row, end_col),
+        );
+        let cmd = Box::new(SetBordersCommand::new(range, target, line));
+        let affected = self.execute_command(cmd);
+
+        let coords: Vec<[u32; 2]> = affected.iter().map(|c| [c.row, c.col]).collect();
+        serde_json::to_string(&coords).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
     /// Clear a range of cells
     #[wasm_bindgen(js_name = clearRange)]
     pub fn clear_range(
@@ -473,38 +902,45 @@ impl SpreadsheetEngine {
         let end = CellCoord::new(end_row, end_col);
 
         let cmd = Box::new(ClearRangeCommand::new(start, end));
-        let affected = self.history.execute(cmd, self.workbook.active_sheet_mut());
+        let affected = self.execute_command(cmd);
 
         // Clear dependencies and recalculate dependents
-        let mut all_affected: Vec<CellCoord> = affected.clone();
-        for coord in &affected {
-            self.dep_graph.remove_cell((coord.row, coord.col));
-
-            if let Ok(order) = self.dep_graph.get_recalc_order((coord.row, coord.col)) {
-                for (r, c) in order {
-                    self.recalculate_cell(r, c);
-                    all_affected.push(CellCoord::new(r, c));
-                }
-            }
-        }
+        self.calc.cells_changed(affected);
+        let all_affected = self.recalculate_active();
 
         let coords: Vec<[u32; 2]> = all_affected.iter().map(|c| [c.row, c.col]).collect();
         serde_json::to_string(&coords).unwrap_or_else(|_| "[]".to_string())
     }
 
+    /// Recalculate dirty cells, returning the active sheet's cells that changed
+    fn recalculate_active(&mut self) -> Vec<CellCoord> {
+        let report = self.calc.recalculate();
+        report.on_sheet(self.calc.workbook().active_sheet_index)
+    }
+
+    /// Run a command on the active sheet and add it to the undo history
+    fn execute_command(&mut self, cmd: CommandBox) -> Vec<CellCoord> {
+        let sheet_index = self.calc.workbook().active_sheet_index;
+        self.history.execute_in(cmd, self.calc.workbook_mut(), sheet_index)
+    }
+
     // --- Undo/Redo ---
 
     /// Undo the last command
     #[wasm_bindgen]
     pub fn undo(&mut self) -> String {
-        if let Some(affected) = self.history.undo(self.workbook.active_sheet_mut()) {
-            // Recalculate all affected cells
-            for coord in &affected {
-                if let Ok(order) = self.dep_graph.get_recalc_order((coord.row, coord.col)) {
-                    for (r, c) in order {
-                        self.recalculate_cell(r, c);
-                    }
-                }
+        let sheet_index = self.calc.workbook().active_sheet_index;
+        let structural = self.history.undo_is_structural();
+        if let Some(affected) = self.history.undo_in(self.calc.workbook_mut(), sheet_index) {
+            if structural {
+                // Cells moved, so every formula's references are re-read
+                self.calc.rebuild_dependencies();
+                self.recalculate_all();
+            } else {
+                // Recalculate all affected cells
+                self.calc.cells_changed(affected.iter().copied());
+                self.calc.rows_visibility_changed();
+                self.calc.recalculate();
             }
 
             let coords: Vec<[u32; 2]> = affected.iter().map(|c| [c.row, c.col]).collect();
@@ -517,14 +953,18 @@ impl SpreadsheetEngine {
     /// Redo the last undone command
     #[wasm_bindgen]
     pub fn redo(&mut self) -> String {
-        if let Some(affected) = self.history.redo(self.workbook.active_sheet_mut()) {
-            // Recalculate all affected cells
-            for coord in &affected {
-                if let Ok(order) = self.dep_graph.get_recalc_order((coord.row, coord.col)) {
-                    for (r, c) in order {
-                        self.recalculate_cell(r, c);
-                    }
-                }
+        let sheet_index = self.calc.workbook().active_sheet_index;
+        let structural = self.history.redo_is_structural();
+        if let Some(affected) = self.history.redo_in(self.calc.workbook_mut(), sheet_index) {
+            if structural {
+                // Cells moved, so every formula's references are re-read
+                self.calc.rebuild_dependencies();
+                self.recalculate_all();
+            } else {
+                // Recalculate all affected cells
+                self.calc.cells_changed(affected.iter().copied());
+                self.calc.rows_visibility_changed();
+                self.calc.recalculate();
             }
 
             let coords: Vec<[u32; 2]> = affected.iter().map(|c| [c.row, c.col]).collect();
@@ -555,66 +995,284 @@ impl SpreadsheetEngine {
     /// Add a new sheet
     #[wasm_bindgen(js_name = addSheet)]
     pub fn add_sheet(&mut self, name: &str) -> Result<usize, JsValue> {
-        self.workbook.add_sheet(name).map_err(to_js_error)
+        let index = self.calc.workbook_mut().add_sheet(name).map_err(to_js_error)?;
+        // Formulas naming the new sheet now resolve
+        self.calc.rebuild_dependencies();
+        self.recalculate_all();
+        Ok(index)
     }
 
     /// Set active sheet by index
     #[wasm_bindgen(js_name = setActiveSheet)]
     pub fn set_active_sheet(&mut self, index: usize) -> bool {
-        self.workbook.set_active_sheet(index)
+        self.calc.set_active_sheet(index)
     }
 
     /// Get all sheet names as JSON array
     #[wasm_bindgen(js_name = getSheetNames)]
     pub fn get_sheet_names(&self) -> String {
-        let names = self.workbook.sheet_names();
+        let names = self.calc.workbook().sheet_names();
         serde_json::to_string(&names).unwrap_or_else(|_| "[]".to_string())
     }
 
     /// Get active sheet index
     #[wasm_bindgen(js_name = getActiveSheetIndex)]
     pub fn get_active_sheet_index(&self) -> usize {
-        self.workbook.active_sheet_index
+        self.calc.workbook().active_sheet_index
     }
 
-    /// Rename a sheet
+    /// Rename a sheet, updating formulas that reference it
     #[wasm_bindgen(js_name = renameSheet)]
     pub fn rename_sheet(&mut self, index: usize, name: &str) -> Result<bool, JsValue> {
-        self.workbook
-            .rename_sheet(index, name)
-            .map(|_| true)
-            .map_err(to_js_error)
+        rusheet_formula::rename_sheet(self.calc.workbook_mut(), index, name).map_err(to_js_error)?;
+        self.calc.rebuild_dependencies();
+        self.recalculate_all();
+        Ok(true)
     }
 
-    /// Delete a sheet
+    /// Delete a sheet, turning formulas that reference it into #REF!
     #[wasm_bindgen(js_name = deleteSheet)]
     pub fn delete_sheet(&mut self, index: usize) -> Result<bool, JsValue> {
-        self.workbook
-            .remove_sheet(index)
-            .map(|_| true)
-            .map_err(to_js_error)
+        rusheet_formula::delete_sheet(self.calc.workbook_mut(), index).map_err(to_js_error)?;
+        self.calc.rebuild_dependencies();
+        self.recalculate_all();
+        Ok(true)
+    }
+
+    // --- Date settings ---
+
+    /// Get the user's offset from UTC in minutes, as saved with the workbook
+    #[wasm_bindgen(js_name = getUtcOffset)]
+    pub fn get_utc_offset(&self) -> i32 {
+        self.calc.workbook().utc_offset_minutes
+    }
+
+    /// Set the user's offset from UTC in minutes (e.g. 480 for Asia/Taipei)
+    /// and recalculate so TODAY/NOW reflect the local date
+    ///
+    /// The offset is saved with the workbook. Daylight saving isn't
+    /// followed, so call this again when the local offset changes.
+    #[wasm_bindgen(js_name = setUtcOffset)]
+    pub fn set_utc_offset(&mut self, minutes: i32) {
+        self.calc.set_utc_offset(minutes);
+        self.recalculate_all();
+    }
+
+    /// Enable legacy array mode, where a range used where one value is
+    /// expected (e.g. `=A1:A10*2`) takes the value in the formula's own row
+    /// or column instead of returning #VALUE!
+    #[wasm_bindgen(js_name = setImplicitIntersection)]
+    pub fn set_implicit_intersection(&mut self, enabled: bool) {
+        self.calc.set_implicit_intersection(enabled);
+        self.recalculate_all();
+    }
+
+    /// Get the workbook's date system ("1900" or "1904")
+    #[wasm_bindgen(js_name = getDateSystem)]
+    pub fn get_date_system(&self) -> String {
+        match self.calc.workbook().date_system {
+            DateSystem::Excel1900 => "1900".to_string(),
+            DateSystem::Excel1904 => "1904".to_string(),
+        }
+    }
+
+    /// Switch the workbook's date system ("1900" or "1904")
+    ///
+    /// Date-formatted values are converted so they keep their calendar date,
+    /// then formulas are recalculated. Returns the number of converted cells.
+    #[wasm_bindgen(js_name = setDateSystem)]
+    pub fn set_date_system(&mut self, system: &str) -> Result<usize, JsValue> {
+        let system = match system {
+            "1900" => DateSystem::Excel1900,
+            "1904" => DateSystem::Excel1904,
+            other => {
+                return Err(to_js_error(RusheetError::Generic(format!(
+                    "Unknown date system: {}",
+                    other
+                ))))
+            }
+        };
+        let converted = self.calc.workbook_mut().set_date_system(system);
+        self.recalculate_all();
+        Ok(converted)
+    }
+
+    /// Get the order of day, month and year in typed dates ("mdy", "dmy" or "ymd")
+    #[wasm_bindgen(js_name = getDateOrder)]
+    pub fn get_date_order(&self) -> String {
+        match self.calc.workbook().date_order {
+            DateOrder::MonthDayYear => "mdy".to_string(),
+            DateOrder::DayMonthYear => "dmy".to_string(),
+            DateOrder::YearMonthDay => "ymd".to_string(),
+        }
+    }
+
+    /// Set the order of day, month and year in typed dates ("mdy", "dmy" or "ymd")
+    ///
+    /// Applies to input typed from now on and to DATEVALUE, which is
+    /// recalculated. Existing date values are left unchanged.
+    #[wasm_bindgen(js_name = setDateOrder)]
+    pub fn set_date_order(&mut self, order: &str) -> Result<(), JsValue> {
+        let order = match order {
+            "mdy" => DateOrder::MonthDayYear,
+            "dmy" => DateOrder::DayMonthYear,
+            "ymd" => DateOrder::YearMonthDay,
+            other => {
+                return Err(to_js_error(RusheetError::Generic(format!(
+                    "Unknown date order: {}",
+                    other
+                ))))
+            }
+        };
+        self.calc.workbook_mut().date_order = order;
+        self.recalculate_all();
+        Ok(())
+    }
+
+    // --- Named styles and theme ---
+
+    /// Get the workbook theme
+    #[wasm_bindgen(js_name = getTheme)]
+    pub fn get_theme(&self) -> String {
+        let theme = &self.calc.workbook().theme;
+        let data = ThemeData {
+            name: theme.name.clone(),
+            colors: ThemeColor::ALL
+                .iter()
+                .map(|&slot| (slot, theme.color(slot).to_hex()))
+                .collect(),
+            major_font: theme.major_font.clone(),
+            minor_font: theme.minor_font.clone(),
+        };
+        serde_json::to_string(&data).unwrap_or_else(|_| "null".to_string())
+    }
+
+    /// Change the workbook theme
+    ///
+    /// Takes `{"name", "colors": {"accent1": "#4472c4", ...}, "majorFont",
+    /// "minorFont"}`; missing fields keep their current value. Styles and
+    /// cells that follow the theme are restyled. Returns the number of
+    /// restyled cells.
+    #[wasm_bindgen(js_name = setTheme)]
+    pub fn set_theme(&mut self, theme_json: &str) -> Result<usize, JsValue> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ThemeUpdate {
+            name: Option<String>,
+            #[serde(default)]
+            colors: BTreeMap<ThemeColor, String>,
+            major_font: Option<String>,
+            minor_font: Option<String>,
+        }
+
+        let update: ThemeUpdate = serde_json::from_str(theme_json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid theme: {}", e)))?;
+        let mut theme = self.calc.workbook().theme.clone();
+        for (slot, hex) in update.colors {
+            let color = Color::from_hex(&hex)
+                .ok_or_else(|| JsValue::from_str(&format!("Invalid color: {}", hex)))?;
+            theme.set_color(slot, color);
+        }
+        if let Some(name) = update.name {
+            theme.name = name;
+        }
+        if let Some(font) = update.major_font {
+            theme.major_font = font;
+        }
+        if let Some(font) = update.minor_font {
+            theme.minor_font = font;
+        }
+        Ok(self.calc.workbook_mut().set_theme(theme))
+    }
+
+    /// Get the workbook's named cell styles
+    #[wasm_bindgen(js_name = getCellStyles)]
+    pub fn get_cell_styles(&self) -> String {
+        let styles: Vec<CellStyleData> = self
+            .calc
+            .workbook()
+            .styles
+            .iter()
+            .map(|style| CellStyleData {
+                name: style.name.clone(),
+                format: CellFormatData::from(&style.format),
+            })
+            .collect();
+        serde_json::to_string(&styles).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Add or redefine a named cell style
+    ///
+    /// Cells using the style are restyled and keep their own overrides.
+    /// Returns the number of restyled cells.
+    #[wasm_bindgen(js_name = setCellStyle)]
+    pub fn set_cell_style(&mut self, style_json: &str) -> Result<usize, JsValue> {
+        let mut data: CellStyleData = serde_json::from_str(style_json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid cell style: {}", e)))?;
+        let name = data.name.trim();
+        if name.is_empty() {
+            return Err(to_js_error(RusheetError::Generic(
+                "Style name cannot be empty".to_string(),
+            )));
+        }
+        // Styles aren't based on other styles
+        data.format.style = None;
+        let format = cell_format_from_data(&data.format, self.calc.workbook());
+        let style = NamedStyle::new(name, format);
+        Ok(self.calc.workbook_mut().set_style(style))
+    }
+
+    /// Delete a named cell style; cells using it keep their formatting
+    #[wasm_bindgen(js_name = deleteCellStyle)]
+    pub fn delete_cell_style(&mut self, name: &str) -> bool {
+        self.calc.workbook_mut().remove_style(name).is_some()
+    }
+
+    /// Apply a named cell style to a range, replacing its formatting
+    /// (borders are kept)
+    #[wasm_bindgen(js_name = applyCellStyle)]
+    pub fn apply_cell_style(
+        &mut self,
+        start_row: u32,
+        start_col: u32,
+        end_row: u32,
+        end_col: u32,
+        name: &str,
+    ) -> Result<bool, JsValue> {
+        let style = self.calc.workbook().style(name).ok_or_else(|| {
+            to_js_error(RusheetError::Generic(format!("Unknown cell style: {}", name)))
+        })?;
+        let format = FormatDiff {
+            style: Some(style.name.clone()),
+            ..FormatDiff::default()
+        };
+        let start = CellCoord::new(start_row, start_col);
+        let end = CellCoord::new(end_row, end_col);
+        let cmd = Box::new(SetRangeFormatCommand::new(start, end, format));
+        self.execute_command(cmd);
+        Ok(true)
     }
 
     // --- Row/Column sizing ---
 
     #[wasm_bindgen(js_name = setRowHeight)]
     pub fn set_row_height(&mut self, row: u32, height: f64) {
-        self.workbook.active_sheet_mut().set_row_height(row, height);
+        self.calc.workbook_mut().active_sheet_mut().set_row_height(row, height);
     }
 
     #[wasm_bindgen(js_name = setColWidth)]
     pub fn set_col_width(&mut self, col: u32, width: f64) {
-        self.workbook.active_sheet_mut().set_col_width(col, width);
+        self.calc.workbook_mut().active_sheet_mut().set_col_width(col, width);
     }
 
     #[wasm_bindgen(js_name = getRowHeight)]
     pub fn get_row_height(&self, row: u32) -> f64 {
-        self.workbook.active_sheet().get_row_height(row)
+        self.calc.workbook().active_sheet().get_row_height(row)
     }
 
     #[wasm_bindgen(js_name = getColWidth)]
     pub fn get_col_width(&self, col: u32) -> f64 {
-        self.workbook.active_sheet().get_col_width(col)
+        self.calc.workbook().active_sheet().get_col_width(col)
     }
 
     // --- Row/Column Insert/Delete ---
@@ -624,10 +1282,10 @@ impl SpreadsheetEngine {
     #[wasm_bindgen(js_name = insertRows)]
     pub fn insert_rows(&mut self, at_row: u32, count: u32) -> String {
         let cmd = Box::new(InsertRowsCommand::new(at_row, count));
-        let affected = self.history.execute(cmd, self.workbook.active_sheet_mut());
+        let affected = self.execute_command(cmd);
 
         // Rebuild dependency graph since cell references changed
-        self.rebuild_dependency_graph();
+        self.calc.rebuild_dependencies();
 
         // Recalculate all formulas
         self.recalculate_all();
@@ -641,10 +1299,10 @@ impl SpreadsheetEngine {
     #[wasm_bindgen(js_name = deleteRows)]
     pub fn delete_rows(&mut self, at_row: u32, count: u32) -> String {
         let cmd = Box::new(DeleteRowsCommand::new(at_row, count));
-        let affected = self.history.execute(cmd, self.workbook.active_sheet_mut());
+        let affected = self.execute_command(cmd);
 
         // Rebuild dependency graph since cell references changed
-        self.rebuild_dependency_graph();
+        self.calc.rebuild_dependencies();
 
         // Recalculate all formulas
         self.recalculate_all();
@@ -658,10 +1316,10 @@ impl SpreadsheetEngine {
     #[wasm_bindgen(js_name = insertCols)]
     pub fn insert_cols(&mut self, at_col: u32, count: u32) -> String {
         let cmd = Box::new(InsertColsCommand::new(at_col, count));
-        let affected = self.history.execute(cmd, self.workbook.active_sheet_mut());
+        let affected = self.execute_command(cmd);
 
         // Rebuild dependency graph since cell references changed
-        self.rebuild_dependency_graph();
+        self.calc.rebuild_dependencies();
 
         // Recalculate all formulas
         self.recalculate_all();
@@ -675,10 +1333,10 @@ impl SpreadsheetEngine {
     #[wasm_bindgen(js_name = deleteCols)]
     pub fn delete_cols(&mut self, at_col: u32, count: u32) -> String {
         let cmd = Box::new(DeleteColsCommand::new(at_col, count));
-        let affected = self.history.execute(cmd, self.workbook.active_sheet_mut());
+        let affected = self.execute_command(cmd);
 
         // Rebuild dependency graph since cell references changed
-        self.rebuild_dependency_graph();
+        self.calc.rebuild_dependencies();
 
         // Recalculate all formulas
         self.recalculate_all();
@@ -702,9 +1360,84 @@ impl SpreadsheetEngine {
         let cmd = Box::new(SortRangeCommand::new(
             start_row, end_row, start_col, end_col, sort_col, ascending,
         ));
-        let affected = self.history.execute(cmd, self.workbook.active_sheet_mut());
+        let affected = self.execute_command(cmd);
+
+        // Rebuild dependency graph since formulas moved with their rows
+        self.calc.rebuild_dependencies();
+        self.recalculate_all();
+
+        let coords: Vec<[u32; 2]> = affected.iter().map(|c| [c.row, c.col]).collect();
+        serde_json::to_string(&coords).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Paste a copied or cut range with its top-left corner at (dest_row, dest_col)
+    /// Copied formulas have their relative references adjusted; a cut moves
+    /// the cells and updates references to them across the workbook.
+    /// Returns JSON array of affected cell coordinates
+    #[wasm_bindgen(js_name = pasteRange)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn paste_range(
+        &mut self,
+        src_start_row: u32,
+        src_start_col: u32,
+        src_end_row: u32,
+        src_end_col: u32,
+        dest_row: u32,
+        dest_col: u32,
+        cut: bool,
+    ) -> String {
+        let source = CellRange::new(
+            CellCoord::new(src_start_row, src_start_col),
+            CellCoord::new(src_end_row, src_end_col),
+        );
+        let dest = CellCoord::new(dest_row, dest_col);
+        let cmd = if cut {
+            Box::new(PasteCommand::cut(source, dest))
+        } else {
+            Box::new(PasteCommand::copy(source, dest))
+        };
+        let affected = self.execute_command(cmd);
+
+        // Rebuild dependency graph since cell references changed
+        self.calc.rebuild_dependencies();
 
-        // Recalculate formulas in the sorted range
+        // Recalculate all formulas
+        self.recalculate_all();
+
+        let coords: Vec<[u32; 2]> = affected.iter().map(|c| [c.row, c.col]).collect();
+        serde_json::to_string(&coords).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Autofill the target range by repeating the source range across it
+    /// Returns JSON array of affected cell coordinates
+    #[wasm_bindgen(js_name = fillRange)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn fill_range(
+        &mut self,
+        src_start_row: u32,
+        src_start_col: u32,
+        src_end_row: u32,
+        src_end_col: u32,
+        target_start_row: u32,
+        target_start_col: u32,
+        target_end_row: u32,
+        target_end_col: u32,
+    ) -> String {
+        let source = CellRange::new(
+            CellCoord::new(src_start_row, src_start_col),
+            CellCoord::new(src_end_row, src_end_col),
+        );
+        let target = CellRange::new(
+            CellCoord::new(target_start_row, target_start_col),
+            CellCoord::new(target_end_row, target_end_col),
+        );
+        let cmd = Box::new(FillCommand::new(source, target));
+        let affected = self.execute_command(cmd);
+
+        // Rebuild dependency graph since cell references changed
+        self.calc.rebuild_dependencies();
+
+        // Recalculate all formulas
         self.recalculate_all();
 
         let coords: Vec<[u32; 2]> = affected.iter().map(|c| [c.row, c.col]).collect();
@@ -723,7 +1456,7 @@ impl SpreadsheetEngine {
         end_col: u32,
     ) -> String {
         let cmd = Box::new(MergeCellsCommand::from_coords(start_row, start_col, end_row, end_col));
-        let affected = self.history.execute(cmd, self.workbook.active_sheet_mut());
+        let affected = self.execute_command(cmd);
 
         let coords: Vec<[u32; 2]> = affected.iter().map(|c| [c.row, c.col]).collect();
         serde_json::to_string(&coords).unwrap_or_else(|_| "[]".to_string())
@@ -734,7 +1467,7 @@ impl SpreadsheetEngine {
     pub fn unmerge_cells(&mut self, row: u32, col: u32) -> String {
         let coord = CellCoord::new(row, col);
         let cmd = Box::new(UnmergeCellsCommand::from_coord(coord));
-        let affected = self.history.execute(cmd, self.workbook.active_sheet_mut());
+        let affected = self.execute_command(cmd);
 
         let coords: Vec<[u32; 2]> = affected.iter().map(|c| [c.row, c.col]).collect();
         serde_json::to_string(&coords).unwrap_or_else(|_| "[]".to_string())
@@ -743,7 +1476,7 @@ impl SpreadsheetEngine {
     /// Get merged ranges as JSON array of objects with start/end coordinates.
     #[wasm_bindgen(js_name = getMergedRanges)]
     pub fn get_merged_ranges(&self) -> String {
-        let sheet = self.workbook.active_sheet();
+        let sheet = self.calc.workbook().active_sheet();
         let ranges: Vec<MergeRangeData> = sheet
             .get_merged_ranges()
             .iter()
@@ -762,14 +1495,14 @@ impl SpreadsheetEngine {
     #[wasm_bindgen(js_name = isMergedSlave)]
     pub fn is_merged_slave(&self, row: u32, col: u32) -> bool {
         let coord = CellCoord::new(row, col);
-        self.workbook.active_sheet().is_merged_slave(coord)
+        self.calc.workbook().active_sheet().is_merged_slave(coord)
     }
 
     /// Get the merge info for a cell (returns null if not merged).
     #[wasm_bindgen(js_name = getMergeInfo)]
     pub fn get_merge_info(&self, row: u32, col: u32) -> JsValue {
         let coord = CellCoord::new(row, col);
-        let sheet = self.workbook.active_sheet();
+        let sheet = self.calc.workbook().active_sheet();
 
         if let Some(range) = sheet.get_merge_at(coord) {
             let info = MergeInfo {
@@ -784,87 +1517,737 @@ impl SpreadsheetEngine {
         }
     }
 
-    // --- Filtering ---
+    // --- Filtering ---
+
+    /// Get unique values in a column for filter dropdown
+    #[wasm_bindgen(js_name = getUniqueValuesInColumn)]
+    pub fn get_unique_values_in_column(&self, col: u32, max_rows: u32) -> String {
+        let sheet = self.calc.workbook().active_sheet();
+        let values = sheet.get_unique_values_in_column(col, max_rows);
+        serde_json::to_string(&values).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Apply a column filter
+    /// values_json is a JSON array of string values to show
+    /// Returns JSON array of affected row indices
+    #[wasm_bindgen(js_name = applyColumnFilter)]
+    pub fn apply_column_filter(&mut self, col: u32, values_json: &str, max_rows: u32) -> String {
+        // Parse the values
+        let visible_values: HashSet<String> = match serde_json::from_str(values_json) {
+            Ok(v) => v,
+            Err(_) => return "[]".to_string(),
+        };
+
+        let cmd = Box::new(ApplyFilterCommand::new(col, visible_values, max_rows));
+        let mut affected = self.execute_command(cmd);
+        self.calc.rows_visibility_changed();
+        affected.extend(self.recalculate_active());
+
+        let coords: Vec<[u32; 2]> = affected.iter().map(|c| [c.row, c.col]).collect();
+        serde_json::to_string(&coords).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Clear filter on a specific column
+    /// Returns JSON array of affected row indices
+    #[wasm_bindgen(js_name = clearColumnFilter)]
+    pub fn clear_column_filter(&mut self, col: u32) -> String {
+        let cmd = Box::new(ClearFilterCommand::new(Some(col)));
+        let mut affected = self.execute_command(cmd);
+        self.calc.rows_visibility_changed();
+        affected.extend(self.recalculate_active());
+
+        let coords: Vec<[u32; 2]> = affected.iter().map(|c| [c.row, c.col]).collect();
+        serde_json::to_string(&coords).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Clear all filters
+    /// Returns JSON array of affected row indices
+    #[wasm_bindgen(js_name = clearAllFilters)]
+    pub fn clear_all_filters(&mut self) -> String {
+        let cmd = Box::new(ClearFilterCommand::new(None));
+        let mut affected = self.execute_command(cmd);
+        self.calc.rows_visibility_changed();
+        affected.extend(self.recalculate_active());
+
+        let coords: Vec<[u32; 2]> = affected.iter().map(|c| [c.row, c.col]).collect();
+        serde_json::to_string(&coords).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Get active filters as JSON
+    #[wasm_bindgen(js_name = getActiveFilters)]
+    pub fn get_active_filters(&self) -> String {
+        let sheet = self.calc.workbook().active_sheet();
+        let filters = sheet.get_active_filters();
+
+        // Convert to a simpler format for JS
+        let js_filters: Vec<serde_json::Value> = filters.iter().map(|f| {
+            serde_json::json!({
+                "col": f.col,
+                "visibleValues": f.visible_values.iter().collect::<Vec<_>>()
+            })
+        }).collect();
+
+        serde_json::to_string(&js_filters).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Check if a row is hidden
+    #[wasm_bindgen(js_name = isRowHidden)]
+    pub fn is_row_hidden(&self, row: u32) -> bool {
+        self.calc.workbook().active_sheet().is_row_hidden(row)
+    }
+
+    /// Get all hidden rows
+    #[wasm_bindgen(js_name = getHiddenRows)]
+    pub fn get_hidden_rows(&self) -> String {
+        let hidden = self.calc.workbook().active_sheet().get_hidden_rows();
+        serde_json::to_string(&hidden).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    // --- Hiding and Outlines ---
+
+    /// Hide or unhide rows. Returns JSON array of affected coordinates.
+    #[wasm_bindgen(js_name = setRowsHidden)]
+    pub fn set_rows_hidden(&mut self, start_row: u32, end_row: u32, hidden: bool) -> String {
+        self.execute_layout(Box::new(SetHiddenCommand::new(Axis::Rows, start_row, end_row, hidden)))
+    }
+
+    /// Hide or unhide columns. Returns JSON array of affected coordinates.
+    #[wasm_bindgen(js_name = setColsHidden)]
+    pub fn set_cols_hidden(&mut self, start_col: u32, end_col: u32, hidden: bool) -> String {
+        self.execute_layout(Box::new(SetHiddenCommand::new(Axis::Cols, start_col, end_col, hidden)))
+    }
+
+    /// Check if a column is hidden
+    #[wasm_bindgen(js_name = isColHidden)]
+    pub fn is_col_hidden(&self, col: u32) -> bool {
+        self.calc.workbook().active_sheet().is_col_hidden(col)
+    }
+
+    /// Get all hidden columns
+    #[wasm_bindgen(js_name = getHiddenCols)]
+    pub fn get_hidden_cols(&self) -> String {
+        let hidden = self.calc.workbook().active_sheet().get_hidden_cols();
+        serde_json::to_string(&hidden).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Group rows. Returns "[]" if the group would partially overlap another
+    /// or nest too deep.
+    #[wasm_bindgen(js_name = groupRows)]
+    pub fn group_rows(&mut self, start_row: u32, end_row: u32) -> String {
+        self.edit_outline(Axis::Rows, OutlineEdit::Group { start: start_row, end: end_row })
+    }
+
+    /// Group columns. Returns "[]" if the group would partially overlap
+    /// another or nest too deep.
+    #[wasm_bindgen(js_name = groupCols)]
+    pub fn group_cols(&mut self, start_col: u32, end_col: u32) -> String {
+        self.edit_outline(Axis::Cols, OutlineEdit::Group { start: start_col, end: end_col })
+    }
+
+    /// Remove the row group spanning exactly these rows
+    #[wasm_bindgen(js_name = ungroupRows)]
+    pub fn ungroup_rows(&mut self, start_row: u32, end_row: u32) -> String {
+        self.edit_outline(Axis::Rows, OutlineEdit::Ungroup { start: start_row, end: end_row })
+    }
+
+    /// Remove the column group spanning exactly these columns
+    #[wasm_bindgen(js_name = ungroupCols)]
+    pub fn ungroup_cols(&mut self, start_col: u32, end_col: u32) -> String {
+        self.edit_outline(Axis::Cols, OutlineEdit::Ungroup { start: start_col, end: end_col })
+    }
+
+    /// Collapse or expand a row group
+    #[wasm_bindgen(js_name = setRowGroupCollapsed)]
+    pub fn set_row_group_collapsed(&mut self, start_row: u32, end_row: u32, collapsed: bool) -> String {
+        self.edit_outline(
+            Axis::Rows,
+            OutlineEdit::SetCollapsed { start: start_row, end: end_row, collapsed },
+        )
+    }
+
+    /// Collapse or expand a column group
+    #[wasm_bindgen(js_name = setColGroupCollapsed)]
+    pub fn set_col_group_collapsed(&mut self, start_col: u32, end_col: u32, collapsed: bool) -> String {
+        self.edit_outline(
+            Axis::Cols,
+            OutlineEdit::SetCollapsed { start: start_col, end: end_col, collapsed },
+        )
+    }
+
+    /// Show row outline levels up to `level` (0 collapses everything)
+    #[wasm_bindgen(js_name = showRowLevel)]
+    pub fn show_row_level(&mut self, level: u8) -> String {
+        self.edit_outline(Axis::Rows, OutlineEdit::ShowLevel(level))
+    }
+
+    /// Show column outline levels up to `level` (0 collapses everything)
+    #[wasm_bindgen(js_name = showColLevel)]
+    pub fn show_col_level(&mut self, level: u8) -> String {
+        self.edit_outline(Axis::Cols, OutlineEdit::ShowLevel(level))
+    }
+
+    /// Put row group summaries "after" (below) or "before" (above) their groups
+    #[wasm_bindgen(js_name = setRowSummaryPosition)]
+    pub fn set_row_summary_position(&mut self, position: &str) -> Result<String, JsValue> {
+        let position = parse_summary_position(position)?;
+        Ok(self.edit_outline(Axis::Rows, OutlineEdit::SetSummary(position)))
+    }
+
+    /// Put column group summaries "after" (right) or "before" (left) their groups
+    #[wasm_bindgen(js_name = setColSummaryPosition)]
+    pub fn set_col_summary_position(&mut self, position: &str) -> Result<String, JsValue> {
+        let position = parse_summary_position(position)?;
+        Ok(self.edit_outline(Axis::Cols, OutlineEdit::SetSummary(position)))
+    }
+
+    /// Get the row outline as JSON
+    #[wasm_bindgen(js_name = getRowOutline)]
+    pub fn get_row_outline(&self) -> String {
+        self.outline_json(Axis::Rows)
+    }
+
+    /// Get the column outline as JSON
+    #[wasm_bindgen(js_name = getColOutline)]
+    pub fn get_col_outline(&self) -> String {
+        self.outline_json(Axis::Cols)
+    }
+
+    fn edit_outline(&mut self, axis: Axis, edit: OutlineEdit) -> String {
+        self.execute_layout(Box::new(EditOutlineCommand::new(axis, edit)))
+    }
+
+    /// Run a command that only changes layout
+    ///
+    /// Only SUBTOTAL formulas are recalculated, since they skip hidden rows.
+    fn execute_layout(&mut self, cmd: Box<dyn rusheet_history::Command>) -> String {
+        let mut affected = self.execute_command(cmd);
+        self.calc.rows_visibility_changed();
+        affected.extend(self.recalculate_active());
+        let coords: Vec<[u32; 2]> = affected.iter().map(|c| [c.row, c.col]).collect();
+        serde_json::to_string(&coords).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    fn outline_json(&self, axis: Axis) -> String {
+        let outline = self.calc.workbook().active_sheet().outline(axis);
+        let data = OutlineData {
+            summary: outline.summary,
+            max_level: outline.max_level(),
+            groups: outline
+                .groups()
+                .iter()
+                .map(|group| OutlineGroupData {
+                    start: group.start,
+                    end: group.end,
+                    level: outline.group_level(group),
+                    collapsed: group.collapsed,
+                    summary_index: outline.summary_index(group),
+                })
+                .collect(),
+        };
+        serde_json::to_string(&data).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    // --- Comments ---
+
+    /// Start a comment thread on a cell. Returns "[]" if the cell already
+    /// has one.
+    #[wasm_bindgen(js_name = addComment)]
+    pub fn add_comment(&mut self, row: u32, col: u32, author: &str, body: &str) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        let comment = Comment::new(id, author, comment_timestamp(), body);
+        self.edit_comment(row, col, CommentEdit::Add(comment))
+    }
+
+    /// Reply to a cell's comment thread
+    #[wasm_bindgen(js_name = replyToComment)]
+    pub fn reply_to_comment(&mut self, row: u32, col: u32, author: &str, body: &str) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        let reply = CommentReply::new(id, author, comment_timestamp(), body);
+        self.edit_comment(row, col, CommentEdit::Reply(reply))
+    }
+
+    /// Replace the body of a cell's comment
+    #[wasm_bindgen(js_name = editComment)]
+    pub fn edit_comment_body(&mut self, row: u32, col: u32, body: &str) -> String {
+        let edit = CommentEdit::EditBody { body: body.to_string(), timestamp: comment_timestamp() };
+        self.edit_comment(row, col, edit)
+    }
+
+    /// Replace the body of a reply
+    #[wasm_bindgen(js_name = editCommentReply)]
+    pub fn edit_comment_reply(&mut self, row: u32, col: u32, reply_id: &str, body: &str) -> String {
+        let edit = CommentEdit::EditReply {
+            id: reply_id.to_string(),
+            body: body.to_string(),
+            timestamp: comment_timestamp(),
+        };
+        self.edit_comment(row, col, edit)
+    }
+
+    /// Remove a reply from a thread
+    #[wasm_bindgen(js_name = deleteCommentReply)]
+    pub fn delete_comment_reply(&mut self, row: u32, col: u32, reply_id: &str) -> String {
+        self.edit_comment(row, col, CommentEdit::DeleteReply { id: reply_id.to_string() })
+    }
+
+    /// Resolve or reopen a cell's comment thread
+    #[wasm_bindgen(js_name = setCommentResolved)]
+    pub fn set_comment_resolved(&mut self, row: u32, col: u32, resolved: bool) -> String {
+        self.edit_comment(row, col, CommentEdit::SetResolved(resolved))
+    }
+
+    /// Remove a cell's whole comment thread
+    #[wasm_bindgen(js_name = deleteComment)]
+    pub fn delete_comment(&mut self, row: u32, col: u32) -> String {
+        self.edit_comment(row, col, CommentEdit::Delete)
+    }
+
+    /// Get a cell's comment thread as JSON, or "null"
+    #[wasm_bindgen(js_name = getComment)]
+    pub fn get_comment(&self, row: u32, col: u32) -> String {
+        let sheet = self.calc.workbook().active_sheet();
+        let comment = sheet.get_comment(CellCoord::new(row, col));
+        serde_json::to_string(&comment).unwrap_or_else(|_| "null".to_string())
+    }
+
+    /// Get every comment thread on the active sheet, in row-major order
+    #[wasm_bindgen(js_name = getComments)]
+    pub fn get_comments(&self) -> String {
+        let sheet = self.calc.workbook().active_sheet();
+        let comments: Vec<CommentData> = sheet
+            .comments()
+            .into_iter()
+            .map(|(coord, comment)| CommentData {
+                row: coord.row,
+                col: coord.col,
+                comment: comment.clone(),
+            })
+            .collect();
+        serde_json::to_string(&comments).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Get the users mentioned in a cell's comment thread
+    #[wasm_bindgen(js_name = getCommentMentions)]
+    pub fn get_comment_mentions(&self, row: u32, col: u32) -> String {
+        let sheet = self.calc.workbook().active_sheet();
+        let mentions = sheet
+            .get_comment(CellCoord::new(row, col))
+            .map(Comment::mentions)
+            .unwrap_or_default();
+        serde_json::to_string(&mentions).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    fn edit_comment(&mut self, row: u32, col: u32, edit: CommentEdit) -> String {
+        let cmd = Box::new(EditCommentCommand::new(CellCoord::new(row, col), edit));
+        self.execute_layout(cmd)
+    }
+
+    // --- Hyperlinks ---
+
+    /// Link a cell to a URL, or to a location in the workbook with a leading
+    /// `#` (e.g. `#Sheet2!A1`). A blank address removes the link.
+    #[wasm_bindgen(js_name = setCellHyperlink)]
+    pub fn set_cell_hyperlink(
+        &mut self,
+        row: u32,
+        col: u32,
+        address: &str,
+        tooltip: Option<String>,
+    ) -> String {
+        let link = (!address.trim().is_empty()).then(|| Hyperlink {
+            tooltip: tooltip.filter(|t| !t.is_empty()),
+            ..Hyperlink::parse(address)
+        });
+        let cmd = Box::new(SetHyperlinkCommand::new(CellCoord::new(row, col), link));
+        self.execute_layout(cmd)
+    }
+
+    /// Remove a cell's hyperlink (a HYPERLINK() formula keeps its link)
+    #[wasm_bindgen(js_name = removeCellHyperlink)]
+    pub fn remove_cell_hyperlink(&mut self, row: u32, col: u32) -> String {
+        let cmd = Box::new(SetHyperlinkCommand::new(CellCoord::new(row, col), None));
+        self.execute_layout(cmd)
+    }
+
+    /// Get every link on the active sheet, including HYPERLINK() formulas,
+    /// in row-major order
+    #[wasm_bindgen(js_name = getHyperlinks)]
+    pub fn get_hyperlinks(&self) -> String {
+        let sheet = self.calc.workbook().active_sheet();
+        let mut links: Vec<HyperlinkData> = sheet
+            .non_empty_coords()
+            .filter_map(|coord| {
+                Some(HyperlinkData {
+                    row: coord.row,
+                    col: coord.col,
+                    hyperlink: self.calc.hyperlink_at(coord)?,
+                })
+            })
+            .collect();
+        links.sort_by_key(|link| (link.row, link.col));
+        serde_json::to_string(&links).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Work out where following a cell's link goes: `{ kind: "url", url }`
+    /// or `{ kind: "location", sheetIndex, startRow, startCol, endRow,
+    /// endCol }`. Returns "null" if the cell has no link or its location
+    /// doesn't resolve.
+    #[wasm_bindgen(js_name = resolveHyperlink)]
+    pub fn resolve_hyperlink(&self, row: u32, col: u32) -> String {
+        let destination = self
+            .calc
+            .hyperlink_at(CellCoord::new(row, col))
+            .and_then(|link| match link.target {
+                LinkTarget::Url(url) => Some(LinkDestinationData::Url { url }),
+                LinkTarget::Location(location) => {
+                    let (sheet_index, range) = self.calc.resolve_location(&location)?;
+                    Some(LinkDestinationData::Location {
+                        sheet_index,
+                        start_row: range.start.row,
+                        start_col: range.start.col,
+                        end_row: range.end.row,
+                        end_col: range.end.col,
+                    })
+                }
+            });
+        serde_json::to_string(&destination).unwrap_or_else(|_| "null".to_string())
+    }
+
+    // --- Tables ---
+
+    /// Turn a range into a table whose first row holds the column names
+    ///
+    /// A blank `name` picks the next free `TableN`. Returns the table's name.
+    #[wasm_bindgen(js_name = createTable)]
+    pub fn create_table(
+        &mut self,
+        start_row: u32,
+        start_col: u32,
+        end_row: u32,
+        end_col: u32,
+        name: Option<String>,
+        totals_row: bool,
+    ) -> Result<String, JsValue> {
+        let workbook = self.calc.workbook();
+        let name = match name.as_deref().map(str::trim) {
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ => workbook.next_table_name(),
+        };
+        if workbook.table(&name).is_some() {
+            return Err(to_js_error(RusheetError::TableNameExists(name)));
+        }
+        let range = CellRange::new(
+            CellCoord::new(start_row, start_col),
+            CellCoord::new(end_row, end_col),
+        );
+        let mut edits = vec![EditTableCommand::new(&name, TableEdit::Create(Table::new(&name, range)))];
+        if totals_row {
+            edits.push(EditTableCommand::new(&name, TableEdit::SetTotalsRow(true)));
+        }
+        self.edit_tables(edits)?;
+        Ok(name)
+    }
+
+    /// Get the active sheet's tables as a JSON array of TableData
+    #[wasm_bindgen(js_name = getTables)]
+    pub fn get_tables(&self) -> String {
+        let sheet = self.calc.workbook().active_sheet();
+        let tables: Vec<TableData> = sheet
+            .tables()
+            .iter()
+            .map(|table| TableData {
+                name: table.name.clone(),
+                start_row: table.range.start.row,
+                start_col: table.range.start.col,
+                end_row: table.range.end.row,
+                end_col: table.range.end.col,
+                columns: table.column_names(sheet),
+                totals_row: table.totals_row,
+                auto_expand: table.auto_expand,
+                style: TableStyleData::from(&table.style),
+                sort: table.sort,
+                filters: table
+                    .filters
+                    .iter()
+                    .map(|f| {
+                        let mut visible_values: Vec<_> = f.visible_values.iter().cloned().collect();
+                        visible_values.sort();
+                        TableFilterData { column: f.col, visible_values }
+                    })
+                    .collect(),
+            })
+            .collect();
+        serde_json::to_string(&tables).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Convert a table back to a plain range, keeping its cells
+    #[wasm_bindgen(js_name = convertTableToRange)]
+    pub fn convert_table_to_range(&mut self, name: &str) -> Result<String, JsValue> {
+        self.edit_table(name, TableEdit::Remove)
+    }
+
+    /// Move or resize a table; the header row must stay where it is
+    #[wasm_bindgen(js_name = resizeTable)]
+    pub fn resize_table(
+        &mut self,
+        name: &str,
+        start_row: u32,
+        start_col: u32,
+        end_row: u32,
+        end_col: u32,
+    ) -> Result<String, JsValue> {
+        let range = CellRange::new(
+            CellCoord::new(start_row, start_col),
+            CellCoord::new(end_row, end_col),
+        );
+        self.edit_table(name, TableEdit::Resize(range))
+    }
+
+    /// Rename a table, updating formulas that reference it
+    #[wasm_bindgen(js_name = renameTable)]
+    pub fn rename_table(&mut self, name: &str, new_name: &str) -> Result<bool, JsValue> {
+        self.edit_table(name, TableEdit::Rename(new_name.trim().to_string()))?;
+        Ok(true)
+    }
 
-    /// Get unique values in a column for filter dropdown
-    #[wasm_bindgen(js_name = getUniqueValuesInColumn)]
-    pub fn get_unique_values_in_column(&self, col: u32, max_rows: u32) -> String {
-        let sheet = self.workbook.active_sheet();
-        let values = sheet.get_unique_values_in_column(col, max_rows);
-        serde_json::to_string(&values).unwrap_or_else(|_| "[]".to_string())
+    /// Show or hide a table's totals row
+    #[wasm_bindgen(js_name = setTableTotalsRow)]
+    pub fn set_table_totals_row(&mut self, name: &str, show: bool) -> Result<String, JsValue> {
+        self.edit_table(name, TableEdit::SetTotalsRow(show))
     }
 
-    /// Apply a column filter
-    /// values_json is a JSON array of string values to show
-    /// Returns JSON array of affected row indices
-    #[wasm_bindgen(js_name = applyColumnFilter)]
-    pub fn apply_column_filter(&mut self, col: u32, values_json: &str, max_rows: u32) -> String {
-        // Parse the values
-        let visible_values: HashSet<String> = match serde_json::from_str(values_json) {
-            Ok(v) => v,
-            Err(_) => return "[]".to_string(),
-        };
+    /// Set the totals row function of a table column: "sum", "average",
+    /// "count", "countnums", "max", "min", "stddev" or "var". No function
+    /// clears the cell.
+    #[wasm_bindgen(js_name = setTableTotal)]
+    pub fn set_table_total(
+        &mut self,
+        name: &str,
+        column: u32,
+        function: Option<String>,
+    ) -> Result<String, JsValue> {
+        let function = function
+            .map(|f| serde_json::from_value::<TotalsFunction>(f.to_lowercase().into()))
+            .transpose()
+            .map_err(|e| JsValue::from_str(&format!("Invalid totals function: {}", e)))?;
+        self.edit_table(name, TableEdit::SetTotal { column, function })
+    }
+
+    /// Change a table's styling and auto-expansion
+    ///
+    /// Takes a partial TableStyleData plus `autoExpand`; missing fields keep
+    /// their current value.
+    #[wasm_bindgen(js_name = setTableStyle)]
+    pub fn set_table_style(&mut self, name: &str, style_json: &str) -> Result<String, JsValue> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct TableStyleUpdate {
+            header_color: Option<String>,
+            header_text_color: Option<String>,
+            band_color: Option<String>,
+            banded_rows: Option<bool>,
+            banded_columns: Option<bool>,
+            first_column: Option<bool>,
+            last_column: Option<bool>,
+            auto_expand: Option<bool>,
+        }
 
-        let cmd = Box::new(ApplyFilterCommand::new(col, visible_values, max_rows));
-        let affected = self.history.execute(cmd, self.workbook.active_sheet_mut());
+        let update: TableStyleUpdate = serde_json::from_str(style_json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid table style: {}", e)))?;
+        let table = self
+            .calc
+            .workbook()
+            .active_sheet()
+            .table(name)
+            .ok_or_else(|| to_js_error(RusheetError::TableNotFound(name.to_string())))?;
+        let mut style = table.style.clone();
+        let colors = [
+            (update.header_color, &mut style.header_color),
+            (update.header_text_color, &mut style.header_text_color),
+            (update.band_color, &mut style.band_color),
+        ];
+        for (hex, color) in colors {
+            if let Some(hex) = hex {
+                *color = Color::from_hex(&hex)
+                    .ok_or_else(|| JsValue::from_str(&format!("Invalid color: {}", hex)))?;
+            }
+        }
+        let flags = [
+            (update.banded_rows, &mut style.banded_rows),
+            (update.banded_columns, &mut style.banded_columns),
+            (update.first_column, &mut style.first_column),
+            (update.last_column, &mut style.last_column),
+        ];
+        for (value, flag) in flags {
+            if let Some(value) = value {
+                *flag = value;
+            }
+        }
+        let auto_expand = update.auto_expand.unwrap_or(table.auto_expand);
+        self.edit_table(name, TableEdit::SetOptions { style, auto_expand })
+    }
 
-        let coords: Vec<[u32; 2]> = affected.iter().map(|c| [c.row, c.col]).collect();
-        serde_json::to_string(&coords).unwrap_or_else(|_| "[]".to_string())
+    /// Sort a table's data rows by one of its columns (counted from the
+    /// table's first column)
+    #[wasm_bindgen(js_name = sortTable)]
+    pub fn sort_table(&mut self, name: &str, column: u32, ascending: bool) -> Result<String, JsValue> {
+        self.edit_table(name, TableEdit::Sort { column, ascending })
     }
 
-    /// Clear filter on a specific column
-    /// Returns JSON array of affected row indices
-    #[wasm_bindgen(js_name = clearColumnFilter)]
-    pub fn clear_column_filter(&mut self, col: u32) -> String {
-        let cmd = Box::new(ClearFilterCommand::new(Some(col)));
-        let affected = self.history.execute(cmd, self.workbook.active_sheet_mut());
+    /// Filter a table column to a JSON array of values to show, or clear
+    /// its filter when no values are given
+    #[wasm_bindgen(js_name = filterTable)]
+    pub fn filter_table(
+        &mut self,
+        name: &str,
+        column: u32,
+        values_json: Option<String>,
+    ) -> Result<String, JsValue> {
+        let visible_values: Option<HashSet<String>> = values_json
+            .map(|json| serde_json::from_str(&json))
+            .transpose()
+            .map_err(|e| JsValue::from_str(&format!("Invalid filter values: {}", e)))?;
+        self.edit_table(name, TableEdit::Filter { column, visible_values })
+    }
+
+    /// Apply a table edit to the active sheet and recalculate
+    ///
+    /// Returns a JSON array of affected cells.
+    fn edit_table(&mut self, name: &str, edit: TableEdit) -> Result<String, JsValue> {
+        self.edit_tables(vec![EditTableCommand::new(name, edit)])
+    }
 
+    /// Apply table edits in order as one undo step
+    ///
+    /// If any edit is rejected, those before it are undone.
+    fn edit_tables(&mut self, edits: Vec<EditTableCommand>) -> Result<String, JsValue> {
+        let sheet_index = self.calc.workbook().active_sheet_index;
+        let mut affected = Vec::new();
+        let mut done: Vec<CommandBox> = Vec::new();
+        for mut cmd in edits {
+            affected.extend(cmd.execute_in(self.calc.workbook_mut(), sheet_index));
+            if let Some(err) = cmd.error() {
+                let err = to_js_error(err.clone());
+                for cmd in done.iter_mut().rev() {
+                    cmd.undo_in(self.calc.workbook_mut(), sheet_index);
+                }
+                return Err(err);
+            }
+            done.push(Box::new(cmd));
+        }
+        if done.len() == 1 {
+            self.history.record(done.remove(0));
+        } else {
+            let description = done[0].description().to_string();
+            self.history.record(Box::new(CompositeCommand::new(done, description)));
+        }
+
+        self.calc.cells_changed(affected.iter().copied());
+        self.calc.rows_visibility_changed();
+        affected.extend(self.recalculate_active());
+        affected.sort_unstable_by_key(|c| (c.row, c.col));
+        affected.dedup();
         let coords: Vec<[u32; 2]> = affected.iter().map(|c| [c.row, c.col]).collect();
-        serde_json::to_string(&coords).unwrap_or_else(|_| "[]".to_string())
+        serde_json::to_string(&coords).map_err(|e| JsValue::from_str(&e.to_string()))
     }
 
-    /// Clear all filters
-    /// Returns JSON array of affected row indices
-    #[wasm_bindgen(js_name = clearAllFilters)]
-    pub fn clear_all_filters(&mut self) -> String {
-        let cmd = Box::new(ClearFilterCommand::new(None));
-        let affected = self.history.execute(cmd, self.workbook.active_sheet_mut());
+    // --- Pivot Tables ---
 
-        let coords: Vec<[u32; 2]> = affected.iter().map(|c| [c.row, c.col]).collect();
-        serde_json::to_string(&coords).unwrap_or_else(|_| "[]".to_string())
+    /// Add a pivot table from a JSON definition and write its output
+    ///
+    /// A blank `name` picks the next free `PivotTableN`, and blank sheet
+    /// names mean the active sheet. Output that would replace existing data
+    /// is refused unless `replace` is set. Returns the pivot table's name.
+    #[wasm_bindgen(js_name = createPivotTable)]
+    pub fn create_pivot_table(&mut self, definition_json: &str, replace: bool) -> Result<String, JsValue> {
+        let mut pivot = self.parse_pivot(definition_json)?;
+        if pivot.name.trim().is_empty() {
+            pivot.name = self.calc.workbook().next_pivot_name();
+        }
+        let name = pivot.name.clone();
+        self.edit_pivot(&name, PivotEdit::Create(pivot), replace)?;
+        Ok(name)
     }
 
-    /// Get active filters as JSON
-    #[wasm_bindgen(js_name = getActiveFilters)]
-    pub fn get_active_filters(&self) -> String {
-        let sheet = self.workbook.active_sheet();
-        let filters = sheet.get_active_filters();
+    /// Get every pivot table definition as a JSON array of PivotTableData
+    #[wasm_bindgen(js_name = getPivotTables)]
+    pub fn get_pivot_tables(&self) -> String {
+        let pivots: Vec<PivotTableData> = self.calc.workbook().pivots.iter().map(PivotTableData::from).collect();
+        serde_json::to_string(&pivots).unwrap_or_else(|_| "[]".to_string())
+    }
 
-        // Convert to a simpler format for JS
-        let js_filters: Vec<serde_json::Value> = filters.iter().map(|f| {
-            serde_json::json!({
-                "col": f.col,
-                "visibleValues": f.visible_values.iter().collect::<Vec<_>>()
-            })
-        }).collect();
+    /// Replace a pivot table's definition and rewrite its output
+    #[wasm_bindgen(js_name = updatePivotTable)]
+    pub fn update_pivot_table(
+        &mut self,
+        name: &str,
+        definition_json: &str,
+        replace: bool,
+    ) -> Result<bool, JsValue> {
+        let mut pivot = self.parse_pivot(definition_json)?;
+        if pivot.name.trim().is_empty() {
+            pivot.name = name.to_string();
+        }
+        self.edit_pivot(name, PivotEdit::Update(pivot), replace)?;
+        Ok(true)
+    }
 
-        serde_json::to_string(&js_filters).unwrap_or_else(|_| "[]".to_string())
+    /// Recompute a pivot table from its source's current values
+    #[wasm_bindgen(js_name = refreshPivotTable)]
+    pub fn refresh_pivot_table(&mut self, name: &str, replace: bool) -> Result<bool, JsValue> {
+        self.edit_pivot(name, PivotEdit::Refresh, replace)?;
+        Ok(true)
     }
 
-    /// Check if a row is hidden
-    #[wasm_bindgen(js_name = isRowHidden)]
-    pub fn is_row_hidden(&self, row: u32) -> bool {
-        self.workbook.active_sheet().is_row_hidden(row)
+    /// Recompute every pivot table
+    ///
+    /// If any one fails, none are refreshed and its error is returned.
+    #[wasm_bindgen(js_name = refreshAllPivotTables)]
+    pub fn refresh_all_pivot_tables(&mut self, replace: bool) -> Result<bool, JsValue> {
+        self.edit_pivot("", PivotEdit::RefreshAll, replace)?;
+        Ok(true)
+    }
+
+    /// Remove a pivot table and clear its output
+    #[wasm_bindgen(js_name = deletePivotTable)]
+    pub fn delete_pivot_table(&mut self, name: &str) -> Result<bool, JsValue> {
+        self.edit_pivot(name, PivotEdit::Remove, false)?;
+        Ok(true)
+    }
+
+    /// Apply a pivot table edit as one undoable step
+    fn edit_pivot(&mut self, name: &str, edit: PivotEdit, replace: bool) -> Result<(), JsValue> {
+        let mut cmd = EditPivotCommand::new(name, edit, replace);
+        let sheet_index = self.calc.workbook().active_sheet_index;
+        cmd.execute_in(self.calc.workbook_mut(), sheet_index);
+        if let Some(err) = cmd.error() {
+            return Err(to_js_error(err.clone()));
+        }
+        self.history.record(Box::new(cmd));
+        self.pivots_written();
+        Ok(())
+    }
+
+    fn parse_pivot(&self, definition_json: &str) -> Result<PivotTable, JsValue> {
+        let data: PivotTableData = serde_json::from_str(definition_json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid pivot table: {}", e)))?;
+        let mut pivot = PivotTable::from(data);
+        let active = &self.calc.workbook().active_sheet().name;
+        for sheet in [&mut pivot.source_sheet, &mut pivot.target_sheet] {
+            if sheet.trim().is_empty() {
+                *sheet = active.clone();
+            }
+        }
+        Ok(pivot)
     }
 
-    /// Get all hidden rows
-    #[wasm_bindgen(js_name = getHiddenRows)]
-    pub fn get_hidden_rows(&self) -> String {
-        let hidden = self.workbook.active_sheet().get_hidden_rows();
-        serde_json::to_string(&hidden).unwrap_or_else(|_| "[]".to_string())
+    /// Pivot output replaces cells outright, so formulas it overwrote no
+    /// longer have dependencies
+    fn pivots_written(&mut self) {
+        self.calc.rebuild_dependencies();
+        self.recalculate_all();
     }
 
-    // --- Search and Replace ---
 
     /// Search for cells matching the query
     ///
@@ -884,7 +2267,7 @@ impl SpreadsheetEngine {
         let options: rusheet_core::SearchOptions = serde_json::from_str(options_json)
             .map_err(JsRuSheetError::from_error)?;
 
-        let results = rusheet_core::SearchEngine::search(&self.workbook, &options)
+        let results = rusheet_core::SearchEngine::search(self.calc.workbook(), &options)
             .map_err(JsRuSheetError::from_error)?;
 
         serde_json::to_string(&results)
@@ -910,9 +2293,13 @@ impl SpreadsheetEngine {
         let options: rusheet_core::ReplaceOptions = serde_json::from_str(options_json)
             .map_err(JsRuSheetError::from_error)?;
 
-        let results = rusheet_core::SearchEngine::replace(&mut self.workbook, &options)
+        let results = rusheet_core::SearchEngine::replace(self.calc.workbook_mut(), &options)
             .map_err(JsRuSheetError::from_error)?;
 
+        // Replaced text may have edited formulas
+        self.calc.rebuild_dependencies();
+        self.calc.recalculate_all();
+
         serde_json::to_string(&results)
             .map_err(JsRuSheetError::from_error)
     }
@@ -926,10 +2313,10 @@ impl SpreadsheetEngine {
             match_entire_cell: false,
             use_regex: false,
             search_formulas: false,
-            sheet_indices: Some(vec![self.workbook.active_sheet_index]),
+            sheet_indices: Some(vec![self.calc.workbook().active_sheet_index]),
         };
 
-        let results = rusheet_core::SearchEngine::search(&self.workbook, &options)
+        let results = rusheet_core::SearchEngine::search(self.calc.workbook(), &options)
             .map_err(JsRuSheetError::from_error)?;
 
         serde_json::to_string(&results)
@@ -979,7 +2366,7 @@ impl SpreadsheetEngine {
             enabled: input.enabled,
         };
 
-        self.workbook.active_sheet_mut().add_conditional_formatting(rule);
+        self.calc.workbook_mut().active_sheet_mut().add_conditional_formatting(rule);
 
         Ok(rule_id)
     }
@@ -987,13 +2374,13 @@ impl SpreadsheetEngine {
     /// Remove a conditional formatting rule by ID
     #[wasm_bindgen(js_name = removeConditionalFormatting)]
     pub fn remove_conditional_formatting(&mut self, rule_id: &str) -> bool {
-        self.workbook.active_sheet_mut().remove_conditional_formatting(rule_id)
+        self.calc.workbook_mut().active_sheet_mut().remove_conditional_formatting(rule_id)
     }
 
     /// Get all conditional formatting rules for the active sheet
     #[wasm_bindgen(js_name = getConditionalFormattingRules)]
     pub fn get_conditional_formatting_rules(&self) -> Result<String, JsValue> {
-        let rules = &self.workbook.active_sheet().conditional_formatting;
+        let rules = &self.calc.workbook().active_sheet().conditional_formatting;
         serde_json::to_string(rules)
             .map_err(JsRuSheetError::from_error)
     }
@@ -1012,7 +2399,7 @@ impl SpreadsheetEngine {
         let input: UpdateInput = serde_json::from_str(rule_json)
             .map_err(JsRuSheetError::from_error)?;
 
-        let sheet = self.workbook.active_sheet_mut();
+        let sheet = self.calc.workbook_mut().active_sheet_mut();
         if let Some(existing) = sheet.conditional_formatting.iter_mut().find(|r| r.id == rule_id) {
             if let Some(range) = input.range { existing.range = range; }
             if let Some(rule) = input.rule { existing.rule = rule; }
@@ -1027,7 +2414,7 @@ impl SpreadsheetEngine {
     /// Clear all conditional formatting rules from active sheet
     #[wasm_bindgen(js_name = clearConditionalFormatting)]
     pub fn clear_conditional_formatting(&mut self) {
-        self.workbook.active_sheet_mut().conditional_formatting.clear();
+        self.calc.workbook_mut().active_sheet_mut().conditional_formatting.clear();
     }
 
     // --- Data Validation ---
@@ -1082,7 +2469,7 @@ impl SpreadsheetEngine {
             enabled: input.enabled,
         };
 
-        self.workbook.active_sheet_mut().add_data_validation(rule);
+        self.calc.workbook_mut().active_sheet_mut().add_data_validation(rule);
 
         Ok(rule_id)
     }
@@ -1090,13 +2477,13 @@ impl SpreadsheetEngine {
     /// Remove a data validation rule by ID
     #[wasm_bindgen(js_name = removeDataValidation)]
     pub fn remove_data_validation(&mut self, rule_id: &str) -> bool {
-        self.workbook.active_sheet_mut().remove_data_validation(rule_id)
+        self.calc.workbook_mut().active_sheet_mut().remove_data_validation(rule_id)
     }
 
     /// Get all data validation rules for the active sheet
     #[wasm_bindgen(js_name = getDataValidationRules)]
     pub fn get_data_validation_rules(&self) -> Result<String, JsValue> {
-        let rules = self.workbook.active_sheet().get_data_validation_rules();
+        let rules = self.calc.workbook().active_sheet().get_data_validation_rules();
         serde_json::to_string(rules)
             .map_err(JsRuSheetError::from_error)
     }
@@ -1104,7 +2491,7 @@ impl SpreadsheetEngine {
     /// Get dropdown items for a cell (if it has list validation)
     #[wasm_bindgen(js_name = getCellDropdownItems)]
     pub fn get_cell_dropdown_items(&self, row: u32, col: u32) -> Result<JsValue, JsValue> {
-        let items = self.workbook.active_sheet().get_cell_dropdown_items(row, col);
+        let items = self.calc.workbook().active_sheet().get_cell_dropdown_items(row, col);
         match items {
             Some(items) => {
                 let js_array = js_sys::Array::new();
@@ -1122,7 +2509,7 @@ impl SpreadsheetEngine {
     #[wasm_bindgen(js_name = validateCellValue)]
     pub fn validate_cell_value(&self, row: u32, col: u32, value: &str) -> Result<String, JsValue> {
         let cell_value = self.parse_input_to_cell_value(value);
-        let result = self.workbook.active_sheet().validate_cell_value(row, col, &cell_value);
+        let result = self.calc.workbook().active_sheet().validate_cell_value(row, col, &cell_value);
 
         #[derive(Serialize)]
         struct ValidationResponse {
@@ -1158,7 +2545,7 @@ impl SpreadsheetEngine {
     /// Clear all data validation rules from active sheet
     #[wasm_bindgen(js_name = clearDataValidation)]
     pub fn clear_data_validation(&mut self) {
-        self.workbook.active_sheet_mut().data_validation.clear();
+        self.calc.workbook_mut().active_sheet_mut().data_validation.clear();
     }
 
     // --- Serialization ---
@@ -1166,7 +2553,8 @@ impl SpreadsheetEngine {
     /// Serialize workbook to JSON
     #[wasm_bindgen]
     pub fn serialize(&self) -> String {
-        self.workbook
+        self.calc
+            .workbook()
             .to_json()
             .unwrap_or_else(|_| "{}".to_string())
     }
@@ -1176,8 +2564,7 @@ impl SpreadsheetEngine {
     pub fn deserialize(&mut self, json: &str) -> bool {
         match Workbook::from_json(json) {
             Ok(wb) => {
-                self.workbook = wb;
-                self.rebuild_dependency_graph();
+                self.calc.set_workbook(wb);
                 self.history.clear();
                 true
             }
@@ -1185,38 +2572,120 @@ impl SpreadsheetEngine {
         }
     }
 
-    /// Rebuild dependency graph from current workbook state
-    fn rebuild_dependency_graph(&mut self) {
-        self.dep_graph.clear();
-
-        for sheet in &self.workbook.sheets {
-            for coord in sheet.non_empty_coords() {
-                if let Some(cell) = sheet.get_cell(coord) {
-                    if let Some(expression) = cell.content.formula_expression() {
-                        let refs = extract_references(expression);
-                        let deps: HashSet<_> = refs.into_iter().collect();
-                        self.dep_graph.set_dependencies((coord.row, coord.col), deps);
-                    }
-                }
-            }
-        }
-    }
-
     /// Recalculate all formulas in the workbook
     #[wasm_bindgen(js_name = recalculateAll)]
     pub fn recalculate_all(&mut self) {
-        let sheet = self.workbook.active_sheet();
-        let coords: Vec<CellCoord> = sheet.non_empty_coords().collect();
+        self.calc.recalculate_all();
+    }
+
+    // --- Formula Auditing ---
+
+    /// Evaluate a cell's formula step by step
+    /// Returns a JSON tree of sub-expressions with their values, or "null"
+    /// if the cell holds no formula
+    #[wasm_bindgen(js_name = traceFormula)]
+    pub fn trace_formula(&self, row: u32, col: u32) -> String {
+        let trace = self
+            .calc
+            .trace_cell(CellCoord::new(row, col))
+            .map(|root| TraceData::from_node(&root, &root.text));
+        serde_json::to_string(&trace).unwrap_or_else(|_| "null".to_string())
+    }
+
+    /// Get the cells a formula reads directly, on any sheet, as a JSON array
+    /// of `{ sheetIndex, row, col }`
+    #[wasm_bindgen(js_name = getPrecedents)]
+    pub fn get_precedents(&self, row: u32, col: u32) -> String {
+        let precedents = self.calc.precedents(CellCoord::new(row, col));
+        sheet_cells_json(&precedents)
+    }
+
+    /// Get the formulas, on any sheet, that read a cell directly, as a JSON
+    /// array of `{ sheetIndex, row, col }`
+    #[wasm_bindgen(js_name = getDependents)]
+    pub fn get_dependents(&self, row: u32, col: u32) -> String {
+        let dependents = self.calc.dependents(CellCoord::new(row, col));
+        sheet_cells_json(&dependents)
+    }
+
+    // --- What-If Analysis ---
+
+    /// Find the value of the input cell that makes the target formula equal `goal`
+    /// When a solution is found it is entered into the input cell as an
+    /// undoable edit. Returns JSON: { input, value, iterations, converged }
+    #[wasm_bindgen(js_name = goalSeek)]
+    pub fn goal_seek(
+        &mut self,
+        target_row: u32,
+        target_col: u32,
+        goal: f64,
+        input_row: u32,
+        input_col: u32,
+    ) -> Result<String, JsValue> {
+        let result = self
+            .calc
+            .goal_seek(
+                CellCoord::new(target_row, target_col),
+                goal,
+                CellCoord::new(input_row, input_col),
+                GoalSeekOptions::default(),
+            )
+            .map_err(to_js_error)?;
+
+        if result.converged {
+            self.set_cell_value(input_row, input_col, &result.input.to_string());
+        }
+
+        serde_json::to_string(&result).map_err(JsRuSheetError::from_error)
+    }
 
-        for coord in coords {
-            self.recalculate_cell(coord.row, coord.col);
+    /// Evaluate a data table laid out on the active sheet, without changing any cells
+    ///
+    /// options_json format:
+    /// {
+    ///   "range": [start_row, start_col, end_row, end_col],
+    ///   "rowInput": [row, col] or null,  // fed from the table's first row
+    ///   "colInput": [row, col] or null   // fed from the table's first column
+    /// }
+    ///
+    /// Returns a JSON 2D array of display values for the table body
+    #[wasm_bindgen(js_name = dataTable)]
+    pub fn data_table(&mut self, options_json: &str) -> Result<String, JsValue> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct DataTableOptions {
+            range: [u32; 4],
+            row_input: Option<[u32; 2]>,
+            col_input: Option<[u32; 2]>,
         }
+
+        let options: DataTableOptions =
+            serde_json::from_str(options_json).map_err(JsRuSheetError::from_error)?;
+        let [start_row, start_col, end_row, end_col] = options.range;
+        let range = CellRange::new(
+            CellCoord::new(start_row, start_col),
+            CellCoord::new(end_row, end_col),
+        );
+        let table = self
+            .calc
+            .data_table_range(
+                range,
+                options.row_input.map(|[r, c]| CellCoord::new(r, c)),
+                options.col_input.map(|[r, c]| CellCoord::new(r, c)),
+            )
+            .map_err(to_js_error)?;
+
+        let display: Vec<Vec<String>> = table
+            .iter()
+            .map(|row| row.iter().map(CellValue::as_text).collect())
+            .collect();
+        serde_json::to_string(&display).map_err(JsRuSheetError::from_error)
     }
 
     /// Get total dimensions of the spreadsheet
     #[wasm_bindgen(js_name = getDimensions)]
     pub fn get_dimensions(&self) -> String {
-        let sheet = self.workbook.active_sheet();
+        let sheet = self.calc.workbook().active_sheet();
         
         // Use a reasonable limit for scrollable area for now
         let max_rows = 1000;
@@ -1234,12 +2703,73 @@ impl SpreadsheetEngine {
     }
 
     /// Get cell coordinates from pixel coordinates
+    ///
+    /// Without scroll offsets the point is in sheet space. With them it is in
+    /// viewport space: points over frozen rows or columns ignore that scroll.
     #[wasm_bindgen(js_name = getCellFromPixel)]
-    pub fn get_cell_from_pixel(&self, x: f64, y: f64) -> Vec<u32> {
-        let sheet = self.workbook.active_sheet();
-        let row = sheet.row_at_y(y);
-        let col = sheet.col_at_x(x);
-        vec![row, col]
+    pub fn get_cell_from_pixel(
+        &self,
+        x: f64,
+        y: f64,
+        scroll_x: Option<f64>,
+        scroll_y: Option<f64>,
+    ) -> Vec<u32> {
+        let sheet = self.calc.workbook().active_sheet();
+        let coord = match (scroll_x, scroll_y) {
+            (None, None) => CellCoord::new(sheet.row_at_y(y), sheet.col_at_x(x)),
+            _ => sheet.cell_at_point(x, y, scroll_x.unwrap_or(0.0), scroll_y.unwrap_or(0.0)),
+        };
+        vec![coord.row, coord.col]
+    }
+
+    // --- Freeze Panes ---
+
+    /// Freeze the top `rows` rows and left `cols` columns (0, 0 unfreezes)
+    #[wasm_bindgen(js_name = setFrozenPanes)]
+    pub fn set_frozen_panes(&mut self, rows: u32, cols: u32) {
+        let cmd = Box::new(SetFrozenPanesCommand::new(rows, cols));
+        self.execute_command(cmd);
+    }
+
+    /// Get frozen panes as JSON: { rows, cols, width, height }
+    /// `width`/`height` are the pixel size of the frozen columns/rows
+    #[wasm_bindgen(js_name = getFrozenPanes)]
+    pub fn get_frozen_panes(&self) -> String {
+        let sheet = self.calc.workbook().active_sheet();
+        let (width, height) = sheet.frozen_size();
+        serde_json::to_string(&serde_json::json!({
+            "rows": sheet.frozen_rows,
+            "cols": sheet.frozen_cols,
+            "width": width,
+            "height": height
+        }))
+        .unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Split the viewport into frozen and scrolling panes
+    ///
+    /// Returns a JSON array of PaneData, frozen panes first
+    #[wasm_bindgen(js_name = getViewportPanes)]
+    pub fn get_viewport_panes(&self, scroll_x: f64, scroll_y: f64, width: f64, height: f64) -> String {
+        let panes: Vec<PaneData> = self
+            .calc
+            .workbook()
+            .active_sheet()
+            .panes(scroll_x, scroll_y, width, height)
+            .iter()
+            .map(|pane| PaneData {
+                kind: pane.kind,
+                start_row: pane.range.first_row as u32,
+                end_row: pane.range.last_row as u32,
+                start_col: pane.range.first_col as u32,
+                end_col: pane.range.last_col as u32,
+                x: pane.x,
+                y: pane.y,
+                offset_x: pane.offset_x,
+                offset_y: pane.offset_y,
+            })
+            .collect();
+        serde_json::to_string(&panes).unwrap_or_else(|_| "[]".to_string())
     }
 
     // =========================================================================
@@ -1251,7 +2781,8 @@ impl SpreadsheetEngine {
     #[wasm_bindgen(js_name = populateViewport)]
     pub fn populate_viewport(&mut self, start_row: u32, end_row: u32, start_col: u32, end_col: u32) {
         self.viewport_buffer.clear();
-        let sheet = self.workbook.active_sheet();
+        let workbook = self.calc.workbook();
+        let sheet = workbook.active_sheet();
 
         for row in start_row..=end_row {
             // Skip hidden rows (filtered out)
@@ -1259,6 +2790,9 @@ impl SpreadsheetEngine {
                 continue;
             }
             for col in start_col..=end_col {
+                if sheet.is_col_hidden(col) {
+                    continue;
+                }
                 let coord = CellCoord::new(row, col);
                 if let Some(cell) = sheet.get_cell(coord) {
                     // Extract numeric value (NaN for non-numeric)
@@ -1269,9 +2803,9 @@ impl SpreadsheetEngine {
                     };
 
                     // Apply conditional formatting to get effective format
-                    let base_format = &cell.format;
+                    let base_format = workbook.cell_format(cell);
                     let value = cell.content.computed_value();
-                    let effective_format = sheet.get_effective_format(row, col, base_format, value);
+                    let effective_format = sheet.get_effective_format(row, col, &base_format, value);
 
                     // Pack format flags using effective format
                     let h_align = match effective_format.horizontal_align {
@@ -1298,7 +2832,8 @@ impl SpreadsheetEngine {
                         col,
                         numeric_value,
                         format_flags,
-                        cell.content.display_value(),
+                        &sheet.resolved_borders(coord),
+                        cell.display_value(&workbook.styles, workbook.date_system),
                     );
                 }
             }
@@ -1335,6 +2870,18 @@ impl SpreadsheetEngine {
         self.viewport_buffer.formats.as_ptr()
     }
 
+    /// Get pointer to viewport packed border styles (Uint32Array)
+    #[wasm_bindgen(js_name = getViewportBorderStylesPtr)]
+    pub fn get_viewport_border_styles_ptr(&self) -> *const u32 {
+        self.viewport_buffer.border_styles.as_ptr()
+    }
+
+    /// Get pointer to viewport border colors (Uint32Array, 6 per cell)
+    #[wasm_bindgen(js_name = getViewportBorderColorsPtr)]
+    pub fn get_viewport_border_colors_ptr(&self) -> *const u32 {
+        self.viewport_buffer.border_colors.as_ptr()
+    }
+
     /// Get viewport display values as JSON (strings still need serialization)
     #[wasm_bindgen(js_name = getViewportDisplayValues)]
     pub fn get_viewport_display_values(&self) -> String {
@@ -1350,21 +2897,81 @@ impl Default for SpreadsheetEngine {
 }
 
 /// Convert CellFormatData to CellFormat
-fn cell_format_from_data(data: &CellFormatData) -> CellFormat {
+fn parse_summary_position(position: &str) -> Result<SummaryPosition, JsValue> {
+    match position {
+        "after" => Ok(SummaryPosition::After),
+        "before" => Ok(SummaryPosition::Before),
+        other => Err(JsValue::from_str(&format!("Invalid summary position: {}", other))),
+    }
+}
+
+/// Cells with their sheet index as JSON, for JavaScript
+fn sheet_cells_json(cells: &[(usize, CellCoord)]) -> String {
+    let cells: Vec<SheetCellData> = cells
+        .iter()
+        .map(|&(sheet_index, coord)| SheetCellData {
+            sheet_index,
+            row: coord.row,
+            col: coord.col,
+        })
+        .collect();
+    serde_json::to_string(&cells).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Convert frontend format data to the named style and changes a cell stores
+fn format_diff_from_data(data: &CellFormatData, workbook: &Workbook) -> FormatDiff {
+    FormatDiff::against_style(&workbook.styles, &cell_format_from_data(data, workbook))
+}
+
+/// Build a cell format from frontend data
+///
+/// The format starts from the named style given in the data, if any, and the
+/// data's fields override it. A color or font that differs from the base
+/// stops following the theme.
+fn cell_format_from_data(data: &CellFormatData, workbook: &Workbook) -> CellFormat {
     use rusheet_core::{Color, HorizontalAlign, VerticalAlign};
 
-    let mut format = CellFormat::default();
+    let mut format = data
+        .style
+        .as_deref()
+        .and_then(|name| workbook.style_format(name))
+        .unwrap_or_default();
     format.bold = data.bold;
     format.italic = data.italic;
     format.underline = data.underline;
-    format.font_size = data.font_size;
+    if data.font_size.is_some() {
+        format.font_size = data.font_size;
+    }
+    if data.number_format.is_some() {
+        format.number_format = data.number_format.clone();
+    }
 
-    if let Some(ref color) = data.text_color {
-        format.text_color = Color::from_hex(color);
+    if let Some(color) = data.text_color.as_deref().and_then(Color::from_hex) {
+        if format.text_color != Some(color) {
+            format.text_color = Some(color);
+            format.text_theme_color = None;
+        }
+    }
+    if let Some(color) = data.background_color.as_deref().and_then(Color::from_hex) {
+        if format.background_color != Some(color) {
+            format.background_color = Some(color);
+            format.background_theme_color = None;
+        }
+    }
+    if data.font_family.is_some() && format.font_family != data.font_family {
+        format.font_family = data.font_family.clone();
+        format.theme_font = None;
+    }
+    if data.text_theme_color.is_some() {
+        format.text_theme_color = data.text_theme_color;
+    }
+    if data.background_theme_color.is_some() {
+        format.background_theme_color = data.background_theme_color;
     }
-    if let Some(ref color) = data.background_color {
-        format.background_color = Color::from_hex(color);
+    if data.theme_font.is_some() {
+        format.theme_font = data.theme_font;
     }
+    format.apply_theme(&workbook.theme);
 
     if let Some(ref align) = data.horizontal_align {
         format.horizontal_align = match align.as_str() {
@@ -1406,7 +3013,7 @@ mod tests {
         assert!(result.is_ok(), "Failed to deserialize camelCase format data");
 
         let format = result.unwrap();
-        assert_eq!(format.bold, true);
+        assert!(format.bold);
         assert_eq!(format.font_size, Some(12));
         assert_eq!(format.text_color, Some("#ff0000".to_string()));
         assert_eq!(format.background_color, Some("#00ff00".to_string()));
@@ -1425,6 +3032,7 @@ mod tests {
             background_color: Some("#00ff00".to_string()),
             horizontal_align: Some("right".to_string()),
             vertical_align: Some("bottom".to_string()),
+            ..Default::default()
         };
 
         let json = serde_json::to_string(&format).unwrap();
@@ -1447,8 +3055,8 @@ mod tests {
         }"##;
 
         let format: CellFormatData = serde_json::from_str(json).unwrap();
-        assert_eq!(format.bold, true);
-        assert_eq!(format.italic, false); // default
+        assert!(format.bold);
+        assert!(!format.italic); // default
         assert_eq!(format.text_color, Some("#ff0000".to_string()));
         assert_eq!(format.font_size, None); // not provided
     }
@@ -1456,7 +3064,7 @@ mod tests {
 
 #[cfg(test)]
 mod bug_fixes {
-    use rusheet_core::{CellCoord, Sheet};
+    use rusheet_core::{CellCoord, CellError, CellValue, Sheet};
 
     #[test]
     fn test_bug_1_3_number_preservation() {
@@ -1529,19 +3137,23 @@ mod bug_fixes {
     // Helper function to get cell data with conditional formatting applied
     fn get_cell_as_data(engine: &super::SpreadsheetEngine, row: u32, col: u32) -> super::CellData {
         let coord = CellCoord::new(row, col);
-        let sheet = engine.workbook.active_sheet();
+        let workbook = engine.calc.workbook();
+        let sheet = workbook.active_sheet();
 
         if let Some(cell) = sheet.get_cell(coord) {
             // Apply conditional formatting to get effective format
-            let base_format = &cell.format;
+            let base_format = workbook.cell_format(cell);
             let value = cell.content.computed_value();
-            let effective_format = sheet.get_effective_format(row, col, base_format, value);
+            let effective_format = sheet.get_effective_format(row, col, &base_format, value);
 
             super::CellData {
                 value: Some(cell.content.original_input()),
-                display_value: cell.content.display_value(),
+                display_value: cell.display_value(&workbook.styles, workbook.date_system),
                 formula: cell.content.formula_expression().map(String::from),
                 format: super::CellFormatData::from(&effective_format),
+                rich_text: cell.text_runs().map(super::text_runs_data),
+                comment: cell.comment.clone(),
+                hyperlink: engine.calc.hyperlink_at(coord),
                 row,
                 col,
             }
@@ -1551,6 +3163,9 @@ mod bug_fixes {
                 display_value: String::new(),
                 formula: None,
                 format: super::CellFormatData::default(),
+                rich_text: None,
+                comment: None,
+                hyperlink: None,
                 row,
                 col,
             }
@@ -1626,6 +3241,32 @@ mod bug_fixes {
         assert_eq!(data.display_value, "20", "After change: A1*2 = 10*2 = 20");
     }
 
+    #[test]
+    fn test_sorted_formulas_keep_recalculating() {
+        let mut engine = super::SpreadsheetEngine::new();
+        for (row, value) in ["3", "1", "2"].iter().enumerate() {
+            engine.set_cell_value(row as u32, 0, value);
+            engine.set_cell_value(row as u32, 1, &format!("=A{}*10", row + 1));
+        }
+        let shown = |engine: &super::SpreadsheetEngine, row| get_cell_as_data(engine, row, 1).display_value;
+
+        // The formula reading A1 moved to the last row with its row
+        engine.sort_range(0, 2, 0, 1, 0, true);
+        engine.set_cell_value(0, 0, "100");
+        assert_eq!(shown(&engine, 2), "1000");
+
+        // Undoing and redoing the sort moves it back and forth
+        engine.undo();
+        engine.undo();
+        engine.redo();
+        engine.set_cell_value(0, 0, "5");
+        assert_eq!(shown(&engine, 2), "50");
+        engine.undo();
+        engine.undo();
+        engine.set_cell_value(0, 0, "7");
+        assert_eq!(shown(&engine, 0), "70");
+    }
+
     #[test]
     fn test_bug_7_persistence() {
         let mut engine = super::SpreadsheetEngine::new();
@@ -1683,7 +3324,7 @@ mod bug_fixes {
 
         // Verify formatting persists
         let data = get_cell_as_data(&new_engine, 0, 0);
-        assert_eq!(data.format.bold, true);
+        assert!(data.format.bold);
         assert_eq!(data.format.text_color, Some("#ff0000".to_string()));
         assert_eq!(data.format.background_color, Some("#ffff00".to_string()));
     }
@@ -1714,7 +3355,7 @@ mod bug_fixes {
             enabled: true,
         };
 
-        engine.workbook.active_sheet_mut().add_data_validation(rule);
+        engine.calc.workbook_mut().active_sheet_mut().add_data_validation(rule);
 
         // Test valid value - should succeed
         let result = engine.set_cell_value(0, 0, "Yes");
@@ -1736,11 +3377,11 @@ mod bug_fixes {
         assert_eq!(cell_data.value, None, "Invalid value should not be set");
 
         // Test that the rule exists
-        let rules = engine.workbook.active_sheet().get_data_validation_rules();
+        let rules = engine.calc.workbook().active_sheet().get_data_validation_rules();
         assert_eq!(rules.len(), 1, "Should have one validation rule");
 
         // Test removeDataValidation
-        let removed = engine.workbook.active_sheet_mut().remove_data_validation("test-validation");
+        let removed = engine.calc.workbook_mut().active_sheet_mut().remove_data_validation("test-validation");
         assert!(removed, "Rule should be removed");
 
         // After removal, invalid value should be accepted
@@ -1777,7 +3418,7 @@ mod bug_fixes {
             enabled: true,
         };
 
-        engine.workbook.active_sheet_mut().add_data_validation(rule);
+        engine.calc.workbook_mut().active_sheet_mut().add_data_validation(rule);
 
         // Test valid values
         let result = engine.set_cell_value(0, 0, "50");
@@ -1834,7 +3475,7 @@ mod bug_fixes {
             enabled: true,
         };
 
-        engine.workbook.active_sheet_mut().add_conditional_formatting(rule);
+        engine.calc.workbook_mut().active_sheet_mut().add_conditional_formatting(rule);
 
         // Verify conditional formatting is applied
         let data_100 = get_cell_as_data(&engine, 0, 0);
@@ -1853,7 +3494,7 @@ mod bug_fixes {
                    "Cell with value 25 should not have conditional formatting");
 
         // Remove the rule
-        let removed = engine.workbook.active_sheet_mut().remove_conditional_formatting("test-rule");
+        let removed = engine.calc.workbook_mut().active_sheet_mut().remove_conditional_formatting("test-rule");
         assert!(removed, "Rule should be successfully removed");
 
         // Verify conditional formatting is no longer applied
@@ -1861,4 +3502,536 @@ mod bug_fixes {
         assert_eq!(data_after_remove.format.background_color, None,
                    "After rule removal, cell should not have conditional formatting");
     }
+
+    #[test]
+    fn test_switch_date_system() {
+        let mut engine = super::SpreadsheetEngine::new();
+        assert_eq!(engine.get_date_system(), "1900");
+
+        // A1 holds 2024-06-15 formatted as a date, B1 extracts the year
+        engine.set_cell_value(0, 0, "45458");
+        engine
+            .calc
+            .workbook_mut()
+            .active_sheet_mut()
+            .get_cell_mut(CellCoord::new(0, 0))
+            .format
+            .number_format = Some(Some("yyyy-mm-dd".to_string()));
+        engine.set_cell_value(0, 1, "=YEAR(A1)");
+        engine.set_cell_value(0, 2, "=DAY(A1)");
+
+        assert_eq!(engine.set_date_system("1904").unwrap(), 1);
+        assert_eq!(engine.get_date_system(), "1904");
+        assert_eq!(get_cell_as_data(&engine, 0, 0).display_value, "2024-06-15");
+        assert_eq!(
+            engine.calc.workbook().active_sheet().get_cell_value(CellCoord::new(0, 0)),
+            &CellValue::Number(43996.0)
+        );
+        assert_eq!(get_cell_as_data(&engine, 0, 1).display_value, "2024");
+        assert_eq!(get_cell_as_data(&engine, 0, 2).display_value, "15");
+
+        // Survives a save/load round trip
+        let mut reloaded = super::SpreadsheetEngine::new();
+        assert!(reloaded.deserialize(&engine.serialize()));
+        assert_eq!(reloaded.get_date_system(), "1904");
+    }
+
+    #[test]
+    fn test_rich_text_cells() {
+        let mut engine = super::SpreadsheetEngine::new();
+        let affected = engine.set_cell_rich_text(
+            0,
+            0,
+            r##"[{"text":"Total:","bold":true,"textColor":"#ff0000"},{"text":" 42"}]"##,
+        );
+        assert_eq!(affected, "[[0,0]]");
+        engine.set_cell_value(0, 1, "=LEN(A1)");
+
+        let data = get_cell_as_data(&engine, 0, 0);
+        assert_eq!(data.display_value, "Total: 42");
+        let runs = data.rich_text.unwrap();
+        assert_eq!(runs.len(), 2);
+        assert!(runs[0].bold);
+        assert_eq!(runs[0].text_color.as_deref(), Some("#ff0000"));
+        assert_eq!(get_cell_as_data(&engine, 0, 1).display_value, "9");
+
+        // XLSX runs round-trip through the engine
+        let xml = engine.get_cell_rich_text_xml(0, 0).unwrap();
+        engine.set_cell_rich_text_xml(1, 0, &xml);
+        assert_eq!(get_cell_as_data(&engine, 1, 0).rich_text.unwrap().len(), 2);
+        assert_eq!(engine.get_cell_rich_text_xml(0, 1), None);
+
+        // Retyping the text clears the runs
+        engine.set_cell_value(0, 0, "Total: 42");
+        assert!(get_cell_as_data(&engine, 0, 0).rich_text.is_none());
+    }
+
+    #[test]
+    fn test_typed_dates_show_as_dates() {
+        let mut engine = super::SpreadsheetEngine::new();
+        engine.set_cell_value(0, 0, "2026-10-16");
+        engine.set_cell_value(0, 1, "9:30 AM");
+        engine.set_cell_value(0, 2, "=A1+1");
+
+        let data = get_cell_as_data(&engine, 0, 0);
+        assert_eq!(data.value.as_deref(), Some("2026-10-16"));
+        assert_eq!(data.display_value, "2026-10-16");
+        assert_eq!(get_cell_as_data(&engine, 0, 1).display_value, "9:30 AM");
+        assert_eq!(get_cell_as_data(&engine, 0, 2).display_value, "46312");
+
+        // Day-first input once the order is switched
+        engine.set_date_order("dmy").unwrap();
+        assert_eq!(engine.get_date_order(), "dmy");
+        engine.set_cell_value(1, 0, "16/10/2026");
+        assert_eq!(get_cell_as_data(&engine, 1, 0).display_value, "16/10/2026");
+
+        // Undo takes the date format away with the value
+        engine.undo();
+        let cell = engine.calc.workbook().active_sheet().get_cell(CellCoord::new(1, 0));
+        assert_eq!(cell.and_then(|c| c.format.number_format(&[])), None);
+    }
+
+    #[test]
+    fn test_utc_offset_applies_to_today() {
+        let mut engine = super::SpreadsheetEngine::new();
+        engine.set_cell_value(0, 0, "=NOW()-TODAY()");
+        engine.set_utc_offset(480);
+        assert_eq!(engine.calc.date_context().utc_offset_minutes, 480);
+
+        // NOW() - TODAY() is the local time of day, always within one day
+        let value = engine
+            .calc
+            .workbook()
+            .active_sheet()
+            .get_cell(CellCoord::new(0, 0))
+            .and_then(|c| c.computed_value().as_number())
+            .unwrap();
+        assert!((0.0..1.0).contains(&value));
+
+        // The offset is saved with the workbook
+        let mut reloaded = super::SpreadsheetEngine::new();
+        assert!(reloaded.deserialize(&engine.serialize()));
+        assert_eq!(reloaded.get_utc_offset(), 480);
+        assert_eq!(reloaded.calc.date_context().utc_offset_minutes, 480);
+    }
+
+    #[test]
+    fn test_implicit_intersection_mode() {
+        let mut engine = super::SpreadsheetEngine::new();
+        engine.set_cell_value(0, 0, "1");
+        engine.set_cell_value(1, 0, "2");
+        engine.set_cell_value(2, 0, "3");
+        engine.set_cell_value(1, 1, "=A1:A3*10");
+        engine.set_cell_value(2, 1, "=@A1:A3");
+
+        let value_at = |engine: &super::SpreadsheetEngine, row, col| {
+            engine
+                .calc
+                .workbook()
+                .active_sheet()
+                .get_cell(CellCoord::new(row, col))
+                .map(|c| c.computed_value().clone())
+        };
+        assert_eq!(value_at(&engine, 1, 1), Some(CellValue::Error(CellError::InvalidValue)));
+        assert_eq!(value_at(&engine, 2, 1), Some(CellValue::Number(3.0)));
+
+        engine.set_implicit_intersection(true);
+        assert_eq!(value_at(&engine, 1, 1), Some(CellValue::Number(20.0)));
+    }
+
+    #[test]
+    fn test_trace_formula() {
+        let mut engine = super::SpreadsheetEngine::new();
+        engine.set_cell_value(0, 0, "2");
+        engine.set_cell_value(0, 1, "=\"é\"&A1*3");
+
+        let trace: serde_json::Value =
+            serde_json::from_str(&engine.trace_formula(0, 1)).unwrap();
+        assert_eq!(trace["value"], "é6");
+        let product = &trace["children"][1];
+        assert_eq!(product["text"], "A1*3");
+        assert_eq!(product["value"], "6");
+        // "é" is one UTF-16 unit but two bytes
+        assert_eq!(product["start"], 4);
+        assert_eq!(engine.trace_formula(0, 0), "null");
+
+        assert_eq!(engine.get_precedents(0, 1), r#"[{"sheetIndex":0,"row":0,"col":0}]"#);
+        assert_eq!(engine.get_dependents(0, 0), r#"[{"sheetIndex":0,"row":0,"col":1}]"#);
+
+        // Links to other sheets keep their sheet
+        engine.add_sheet("Sheet2").unwrap();
+        engine.set_cell_value(1, 0, "=Sheet2!A1");
+        assert_eq!(engine.get_precedents(1, 0), r#"[{"sheetIndex":1,"row":0,"col":0}]"#);
+    }
+
+    #[test]
+    fn test_goal_seek_and_data_table() {
+        let mut engine = super::SpreadsheetEngine::new();
+        engine.set_cell_value(0, 0, "1");
+        engine.set_cell_value(0, 1, "=A1*4");
+
+        let result: serde_json::Value =
+            serde_json::from_str(&engine.goal_seek(0, 1, 10.0, 0, 0).unwrap()).unwrap();
+        assert_eq!(result["converged"], true);
+        assert_eq!(get_cell_as_data(&engine, 0, 1).display_value, "10");
+
+        // Accepting the solution can be undone
+        engine.undo();
+        assert_eq!(get_cell_as_data(&engine, 0, 1).display_value, "4");
+
+        // Column-oriented table: inputs in D2:D3, formula in E1
+        engine.set_cell_value(1, 3, "2");
+        engine.set_cell_value(2, 3, "3");
+        engine.set_cell_value(0, 4, "=B1+1");
+        let table = engine
+            .data_table(r#"{"range": [0, 3, 2, 4], "colInput": [0, 0]}"#)
+            .unwrap();
+        assert_eq!(table, r#"[["9"],["13"]]"#);
+        assert_eq!(get_cell_as_data(&engine, 0, 0).display_value, "1");
+    }
+
+    #[test]
+    fn test_set_range_borders() {
+        let mut engine = super::SpreadsheetEngine::new();
+        let line = r##"{"style":"thick","color":"#ff0000"}"##;
+        let affected = engine.set_range_borders(0, 0, 1, 1, "outline", line).unwrap();
+        let coords: Vec<[u32; 2]> = serde_json::from_str(&affected).unwrap();
+        assert_eq!(coords.len(), 4);
+
+        engine.populate_viewport(0, 2, 0, 2);
+        let buf = &engine.viewport_buffer;
+        let top_left = (0..buf.rows.len()).find(|&i| buf.rows[i] == 0 && buf.cols[i] == 0).unwrap();
+        // Thick (3) on top and left
+        assert_eq!(buf.border_styles[top_left], 3 | 3 << 9);
+
+        engine.undo();
+        engine.populate_viewport(0, 2, 0, 2);
+        assert!(engine.viewport_buffer.border_styles.iter().all(|&b| b == 0));
+    }
+
+
+    #[test]
+    fn test_hide_columns_and_group_rows() {
+        let mut engine = super::SpreadsheetEngine::new();
+        engine.set_cell_value(0, 0, "a");
+        engine.set_cell_value(0, 1, "b");
+        engine.set_cell_value(2, 0, "c");
+
+        engine.set_cols_hidden(1, 1, true);
+        assert!(engine.is_col_hidden(1));
+        let cells: Vec<serde_json::Value> =
+            serde_json::from_str(&engine.get_viewport_data(0, 5, 0, 5)).unwrap();
+        assert_eq!(cells.len(), 2);
+
+        assert_ne!(engine.group_rows(1, 3), "[]");
+        assert_eq!(engine.group_rows(2, 5), "[]");
+        engine.set_row_group_collapsed(1, 3, true);
+        assert_eq!(engine.get_hidden_rows(), "[1,2,3]");
+
+        let outline: serde_json::Value = serde_json::from_str(&engine.get_row_outline()).unwrap();
+        assert_eq!(outline["maxLevel"], 1);
+        assert_eq!(outline["groups"][0]["summaryIndex"], 4);
+        assert_eq!(outline["groups"][0]["collapsed"], true);
+
+        engine.undo();
+        assert_eq!(engine.get_hidden_rows(), "[]");
+        assert!(engine.is_col_hidden(1));
+    }
+
+
+    #[test]
+    fn test_frozen_panes() {
+        let mut engine = super::SpreadsheetEngine::new();
+        engine.set_frozen_panes(2, 1);
+
+        let frozen: serde_json::Value = serde_json::from_str(&engine.get_frozen_panes()).unwrap();
+        assert_eq!(frozen["rows"], 2);
+        assert_eq!(frozen["height"], 48.0);
+
+        let panes: Vec<serde_json::Value> =
+            serde_json::from_str(&engine.get_viewport_panes(0.0, 240.0, 400.0, 480.0)).unwrap();
+        assert_eq!(panes.len(), 4);
+        assert_eq!(panes[3]["kind"], "main");
+        assert_eq!(panes[3]["startRow"], 12);
+
+        // Frozen rows stay put while the rest scrolls
+        assert_eq!(engine.get_cell_from_pixel(150.0, 10.0, Some(0.0), Some(240.0)), vec![0, 1]);
+        assert_eq!(engine.get_cell_from_pixel(150.0, 60.0, Some(0.0), Some(240.0)), vec![12, 1]);
+        assert_eq!(engine.get_cell_from_pixel(150.0, 60.0, None, None), vec![2, 1]);
+
+        // Inserting above the boundary grows the frozen region
+        engine.insert_rows(0, 1);
+        let frozen: serde_json::Value = serde_json::from_str(&engine.get_frozen_panes()).unwrap();
+        assert_eq!(frozen["rows"], 3);
+
+        engine.undo();
+        engine.undo();
+        let frozen: serde_json::Value = serde_json::from_str(&engine.get_frozen_panes()).unwrap();
+        assert_eq!(frozen["rows"], 0);
+    }
+
+    #[test]
+    fn test_comment_threads() {
+        let mut engine = super::SpreadsheetEngine::new();
+        engine.set_cell_value(1, 1, "42");
+        assert_eq!(engine.add_comment(1, 1, "ana", "@bo is this right?"), "[[1,1]]");
+        assert_eq!(engine.add_comment(1, 1, "bo", "second thread"), "[]");
+        engine.reply_to_comment(1, 1, "bo", "Yes, cc @cy");
+
+        let comment: serde_json::Value = serde_json::from_str(&engine.get_comment(1, 1)).unwrap();
+        assert_eq!(comment["author"], "ana");
+        assert_eq!(comment["replies"][0]["body"], "Yes, cc @cy");
+        assert_eq!(engine.get_comment_mentions(1, 1), r#"["bo","cy"]"#);
+
+        // The thread moves with its cell
+        engine.insert_rows(0, 2);
+        assert_eq!(engine.get_comment(1, 1), "null");
+        let comments: Vec<serde_json::Value> =
+            serde_json::from_str(&engine.get_comments()).unwrap();
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0]["row"], 3);
+
+        engine.undo();
+        engine.delete_comment(1, 1);
+        assert_eq!(engine.get_comment(1, 1), "null");
+        engine.undo();
+        assert!(engine.get_comment(1, 1).contains("@bo is this right?"));
+    }
+
+    #[test]
+    fn test_hyperlinks() {
+        use rusheet_core::Hyperlink;
+
+        let mut engine = super::SpreadsheetEngine::new();
+        engine.add_sheet("Q1 Data").unwrap();
+        engine.set_cell_value(0, 0, "Docs");
+        assert_eq!(
+            engine.set_cell_hyperlink(0, 0, "https://example.com", Some("Open docs".into())),
+            "[[0,0]]"
+        );
+        engine.set_cell_value(1, 0, "=HYPERLINK(\"#'Q1 Data'!B3\", \"Jump\")");
+        engine.set_cell_hyperlink(2, 0, "#A5:B6", None);
+        engine.set_cell_hyperlink(4, 0, "#'Q1 Data'!C1", None);
+
+        let data = get_cell_as_data(&engine, 1, 0);
+        assert_eq!(data.display_value, "Jump");
+        assert_eq!(data.hyperlink, Some(Hyperlink::location("'Q1 Data'!B3")));
+
+        let links: Vec<serde_json::Value> =
+            serde_json::from_str(&engine.get_hyperlinks()).unwrap();
+        assert_eq!(links.len(), 4);
+        assert_eq!(links[0]["hyperlink"]["url"], "https://example.com");
+        assert_eq!(links[0]["hyperlink"]["tooltip"], "Open docs");
+
+        assert_eq!(
+            engine.resolve_hyperlink(0, 0),
+            r#"{"kind":"url","url":"https://example.com"}"#
+        );
+        let target: serde_json::Value =
+            serde_json::from_str(&engine.resolve_hyperlink(1, 0)).unwrap();
+        assert_eq!(target["sheetIndex"], 1);
+        assert_eq!(target["startRow"], 2);
+
+        // Internal links follow row inserts and sheet renames
+        engine.insert_rows(0, 1);
+        let target: serde_json::Value =
+            serde_json::from_str(&engine.resolve_hyperlink(3, 0)).unwrap();
+        assert_eq!(target["startRow"], 5);
+        engine.rename_sheet(1, "Targets").unwrap();
+        let data = get_cell_as_data(&engine, 5, 0);
+        assert_eq!(data.hyperlink, Some(Hyperlink::location("Targets!C1")));
+
+        engine.undo();
+        engine.remove_cell_hyperlink(2, 0);
+        assert_eq!(engine.resolve_hyperlink(2, 0), "null");
+        engine.undo();
+        assert!(engine.resolve_hyperlink(2, 0).contains(r#""startRow":4"#));
+    }
+
+    #[test]
+    fn test_tables() {
+        let mut engine = super::SpreadsheetEngine::new();
+        for (r, row) in [["Region", "Amount"], ["West", "3"], ["East", "1"]].iter().enumerate() {
+            for (c, value) in row.iter().enumerate() {
+                engine.set_cell_value(r as u32, c as u32, value);
+            }
+        }
+        assert_eq!(engine.create_table(0, 0, 2, 1, None, false).unwrap(), "Table1");
+        engine.set_cell_value(0, 3, "=SUM(Table1[Amount])");
+        assert_eq!(get_cell_as_data(&engine, 0, 3).display_value, "4");
+
+        // Typing below the table grows it, and the formula follows
+        engine.set_cell_value(3, 1, "5");
+        let tables: serde_json::Value = serde_json::from_str(&engine.get_tables()).unwrap();
+        assert_eq!(tables[0]["endRow"], 3);
+        assert_eq!(tables[0]["columns"], serde_json::json!(["Region", "Amount"]));
+        assert_eq!(get_cell_as_data(&engine, 0, 3).display_value, "9");
+        engine.undo();
+        assert_eq!(get_cell_as_data(&engine, 0, 3).display_value, "4");
+        engine.redo();
+
+        // Header and banded rows are styled, even when empty
+        assert!(get_cell_as_data(&engine, 0, 0).format.bold);
+        let band = get_cell_as_data(&engine, 1, 0).format.background_color;
+        assert_eq!(band.as_deref(), Some("#d9e1f2"));
+
+        engine.set_table_totals_row("Table1", true).unwrap();
+        assert_eq!(get_cell_as_data(&engine, 4, 1).display_value, "9");
+        engine.set_table_total("Table1", 1, Some("Max".to_string())).unwrap();
+        assert_eq!(get_cell_as_data(&engine, 4, 1).display_value, "5");
+        engine.set_cell_value(5, 3, "=Table1[[#Totals],[Amount]]*2");
+        assert_eq!(get_cell_as_data(&engine, 5, 3).display_value, "10");
+
+        engine.sort_table("Table1", 1, false).unwrap();
+        assert_eq!(get_cell_as_data(&engine, 1, 0).display_value, "");
+        assert_eq!(get_cell_as_data(&engine, 1, 1).display_value, "5");
+        engine.filter_table("Table1", 1, Some(r#"["1","3"]"#.to_string())).unwrap();
+        assert!(engine.is_row_hidden(1));
+        assert_eq!(get_cell_as_data(&engine, 4, 1).display_value, "3");
+        engine.filter_table("Table1", 1, None).unwrap();
+        assert!(!engine.is_row_hidden(1));
+        assert_eq!(get_cell_as_data(&engine, 4, 1).display_value, "5");
+
+        engine.rename_table("Table1", "Sales").unwrap();
+        let formula = get_cell_as_data(&engine, 0, 3).formula;
+        assert_eq!(formula.as_deref(), Some("=SUM(Sales[Amount])"));
+        engine.undo();
+        let formula = get_cell_as_data(&engine, 0, 3).formula;
+        assert_eq!(formula.as_deref(), Some("=SUM(Table1[Amount])"));
+        assert_eq!(get_cell_as_data(&engine, 0, 3).display_value, "9");
+        engine.redo();
+        assert_eq!(get_cell_as_data(&engine, 0, 3).display_value, "9");
+        assert!(engine.set_table_style("Sales", r##"{"bandedRows":false}"##).is_ok());
+        assert_eq!(get_cell_as_data(&engine, 1, 0).format.background_color, None);
+
+        assert!(engine.convert_table_to_range("Sales").is_ok());
+        assert_eq!(engine.get_tables(), "[]");
+        assert_eq!(get_cell_as_data(&engine, 0, 3).display_value, "#REF!");
+
+        // A table created with a totals row is undone in one step
+        for (c, value) in ["Region", "Amount"].iter().enumerate() {
+            engine.set_cell_value(10, c as u32, value);
+        }
+        engine.set_cell_value(11, 1, "2");
+        assert_eq!(engine.create_table(10, 0, 11, 1, None, true).unwrap(), "Table1");
+        assert_eq!(get_cell_as_data(&engine, 12, 1).display_value, "2");
+        engine.undo();
+        assert_eq!(engine.get_tables(), "[]");
+        assert_eq!(get_cell_as_data(&engine, 12, 1).display_value, "");
+    }
+
+    #[test]
+    fn test_pivot_tables() {
+        let mut engine = super::SpreadsheetEngine::new();
+        for (r, row) in [["Region", "Amount"], ["West", "3"], ["East", "1"], ["West", "4"]].iter().enumerate() {
+            for (c, value) in row.iter().enumerate() {
+                engine.set_cell_value(r as u32, c as u32, value);
+            }
+        }
+        let definition = r#"{
+            "sourceStartRow": 0, "sourceStartCol": 0, "sourceEndRow": 3, "sourceEndCol": 1,
+            "targetRow": 0, "targetCol": 3,
+            "rows": [{"column": 0}],
+            "values": [{"column": 1, "aggregation": "sum"}]
+        }"#;
+        assert_eq!(engine.create_pivot_table(definition, false).unwrap(), "PivotTable1");
+        assert_eq!(get_cell_as_data(&engine, 0, 4).display_value, "Sum of Amount");
+        assert_eq!(get_cell_as_data(&engine, 2, 3).display_value, "West");
+        assert_eq!(get_cell_as_data(&engine, 2, 4).display_value, "7");
+        engine.set_cell_value(0, 6, "=E4*2");
+        assert_eq!(get_cell_as_data(&engine, 0, 6).display_value, "16");
+
+        engine.set_cell_value(2, 1, "10");
+        assert!(engine.refresh_all_pivot_tables(false).unwrap());
+        assert_eq!(get_cell_as_data(&engine, 3, 4).display_value, "17");
+        assert_eq!(get_cell_as_data(&engine, 0, 6).display_value, "34");
+
+        let pivots: serde_json::Value = serde_json::from_str(&engine.get_pivot_tables()).unwrap();
+        assert_eq!(pivots[0]["sourceSheet"], "Sheet1");
+        assert_eq!((pivots[0]["outputEndRow"].as_u64(), pivots[0]["outputEndCol"].as_u64()), (Some(3), Some(4)));
+
+        // Inserted rows move the source and output along, and refreshing writes there
+        engine.insert_rows(0, 2);
+        engine.set_cell_value(4, 1, "20");
+        assert!(engine.refresh_all_pivot_tables(false).unwrap());
+        assert_eq!(get_cell_as_data(&engine, 0, 4).display_value, "");
+        assert_eq!(get_cell_as_data(&engine, 5, 4).display_value, "27");
+        engine.undo();
+        engine.undo();
+        engine.undo();
+        assert_eq!(get_cell_as_data(&engine, 3, 4).display_value, "17");
+        let pivots: serde_json::Value = serde_json::from_str(&engine.get_pivot_tables()).unwrap();
+        assert_eq!((pivots[0]["targetRow"].as_u64(), pivots[0]["targetCol"].as_u64()), (Some(0), Some(3)));
+
+        let counted = definition.replace("sum", "count");
+        assert!(engine.update_pivot_table("PivotTable1", &counted, false).unwrap());
+        assert_eq!(get_cell_as_data(&engine, 0, 4).display_value, "Count of Amount");
+        engine.undo();
+        assert_eq!(get_cell_as_data(&engine, 0, 4).display_value, "Sum of Amount");
+        engine.redo();
+        assert_eq!(get_cell_as_data(&engine, 0, 4).display_value, "Count of Amount");
+        assert!(engine.delete_pivot_table("PivotTable1").unwrap());
+        assert_eq!(engine.get_pivot_tables(), "[]");
+        assert_eq!(get_cell_as_data(&engine, 0, 4).display_value, "");
+        engine.undo();
+        assert_eq!(get_cell_as_data(&engine, 2, 3).display_value, "West");
+        assert_eq!(engine.get_pivot_tables().matches("PivotTable1").count(), 1);
+    }
+
+    #[test]
+    fn test_cell_styles_and_theme() {
+        let mut engine = super::SpreadsheetEngine::new();
+        engine.set_cell_value(0, 0, "12.5");
+        engine.set_cell_value(1, 0, "Totals");
+        assert!(engine.apply_cell_style(0, 0, 0, 1, "Currency").unwrap());
+        assert!(engine.apply_cell_style(1, 0, 1, 0, "Heading 1").unwrap());
+
+        let format = get_cell_as_data(&engine, 0, 0).format;
+        assert_eq!(format.style.as_deref(), Some("Currency"));
+        assert_eq!(format.number_format.as_deref(), Some("$#,##0.00"));
+
+        // Toolbar edits send the cell's format back and become overrides
+        let mut format = serde_json::to_value(&format).unwrap();
+        format["bold"] = true.into();
+        assert!(engine.set_cell_format(0, 0, &format.to_string()));
+        let format = get_cell_as_data(&engine, 0, 0).format;
+        assert!(format.bold);
+        assert_eq!(format.style.as_deref(), Some("Currency"));
+        let workbook = engine.calc.workbook();
+        let stored = &workbook.active_sheet().get_cell(CellCoord::new(0, 0)).unwrap().format;
+        assert_eq!(stored.bold, Some(true));
+        assert_eq!(stored.number_format, None);
+
+        // Redefining the style restyles both cells, keeping A1's bold
+        let restyled = engine
+            .set_cell_style(r#"{"name":"Currency","format":{"numberFormat":"0.00"}}"#)
+            .unwrap();
+        assert_eq!(restyled, 2);
+        let format = get_cell_as_data(&engine, 0, 0).format;
+        assert!(format.bold);
+        assert_eq!(format.number_format.as_deref(), Some("0.00"));
+        assert_eq!(get_cell_as_data(&engine, 0, 1).format.number_format.as_deref(), Some("0.00"));
+
+        // The heading follows theme color changes
+        assert_eq!(get_cell_as_data(&engine, 1, 0).format.text_color.as_deref(), Some("#44546a"));
+        assert_eq!(engine.set_theme(r##"{"colors":{"dark2":"#1f3864"}}"##).unwrap(), 1);
+        assert_eq!(get_cell_as_data(&engine, 1, 0).format.text_color.as_deref(), Some("#1f3864"));
+        let theme: serde_json::Value = serde_json::from_str(&engine.get_theme()).unwrap();
+        assert_eq!(theme["colors"]["dark2"], "#1f3864");
+        assert_eq!(theme["minorFont"], "Calibri");
+
+        // Styles and theme survive a save/load round trip
+        let mut reloaded = super::SpreadsheetEngine::new();
+        assert!(reloaded.deserialize(&engine.serialize()));
+        assert_eq!(reloaded.get_theme(), engine.get_theme());
+        assert_eq!(reloaded.get_cell_styles(), engine.get_cell_styles());
+        let format = get_cell_as_data(&reloaded, 0, 0).format;
+        assert!(format.bold);
+        assert_eq!(format.number_format.as_deref(), Some("0.00"));
+
+        assert!(engine.delete_cell_style("Heading 1"));
+        assert!(!engine.delete_cell_style("Heading 1"));
+        assert_eq!(get_cell_as_data(&engine, 1, 0).format.style, None);
+        assert_eq!(get_cell_as_data(&engine, 1, 0).format.font_size, Some(15));
+    }
 }
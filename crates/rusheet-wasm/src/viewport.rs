@@ -4,6 +4,8 @@
 //! from Rust to JavaScript without JSON serialization overhead. JavaScript
 //! can directly read Rust memory through typed arrays.
 
+use rusheet_core::{BorderLine, BorderStyle, Borders};
+
 /// Packed cell format flags (4 bytes / u32)
 ///
 /// Bit layout:
@@ -41,7 +43,9 @@ pub fn pack_format(
 }
 
 /// Unpack format flags back to components
-#[inline]
+///
+/// The JS side does its own unpacking; this mirror is used to test `pack_format`.
+#[cfg(test)]
 pub fn unpack_format(flags: u32) -> (bool, bool, bool, u8, u8, u8) {
     let bold = (flags & (1 << 0)) != 0;
     let italic = (flags & (1 << 1)) != 0;
@@ -52,6 +56,53 @@ pub fn unpack_format(flags: u32) -> (bool, bool, bool, u8, u8, u8) {
     (bold, italic, underline, font_size, h_align, v_align)
 }
 
+/// Number of border colors stored per cell
+pub const BORDER_EDGES: usize = 6;
+
+/// Packed border styles (4 bytes / u32)
+///
+/// Three bits per edge, in the order top (bits 0-2), right (3-5),
+/// bottom (6-8), left (9-11), diagonal down (12-14) and diagonal up
+/// (15-17). Style codes: 0=none, 1=thin, 2=medium, 3=thick, 4=dashed,
+/// 5=dotted, 6=double.
+#[inline]
+pub fn pack_border_styles(borders: &Borders) -> u32 {
+    border_edges(borders)
+        .iter()
+        .enumerate()
+        .fold(0, |flags, (i, edge)| {
+            let code = match edge.map(|line| line.style) {
+                None => 0,
+                Some(BorderStyle::Thin) => 1,
+                Some(BorderStyle::Medium) => 2,
+                Some(BorderStyle::Thick) => 3,
+                Some(BorderStyle::Dashed) => 4,
+                Some(BorderStyle::Dotted) => 5,
+                Some(BorderStyle::Double) => 6,
+            };
+            flags | code << (i * 3)
+        })
+}
+
+/// Border colors as 0xRRGGBBAA, in the same edge order as the styles
+#[inline]
+pub fn pack_border_colors(borders: &Borders) -> [u32; BORDER_EDGES] {
+    border_edges(borders).map(|edge| {
+        edge.map_or(0, |line| u32::from_be_bytes([line.color.r, line.color.g, line.color.b, line.color.a]))
+    })
+}
+
+fn border_edges(borders: &Borders) -> [Option<BorderLine>; BORDER_EDGES] {
+    [
+        borders.top,
+        borders.right,
+        borders.bottom,
+        borders.left,
+        borders.diagonal_down,
+        borders.diagonal_up,
+    ]
+}
+
 /// Viewport buffer for zero-copy data transfer.
 ///
 /// Stores cell data in flat arrays that can be directly accessed
@@ -66,11 +117,16 @@ pub struct ViewportBuffer {
     pub values: Vec<f64>,
     /// Packed format flags
     pub formats: Vec<u32>,
+    /// Packed border styles
+    pub border_styles: Vec<u32>,
+    /// Border colors, `BORDER_EDGES` per cell
+    pub border_colors: Vec<u32>,
     /// Display strings (still need JSON for strings)
     pub display_values: Vec<String>,
 }
 
 impl ViewportBuffer {
+    #[cfg(test)]
     pub fn new() -> Self {
         Self::default()
     }
@@ -81,6 +137,8 @@ impl ViewportBuffer {
             cols: Vec::with_capacity(capacity),
             values: Vec::with_capacity(capacity),
             formats: Vec::with_capacity(capacity),
+            border_styles: Vec::with_capacity(capacity),
+            border_colors: Vec::with_capacity(capacity * BORDER_EDGES),
             display_values: Vec::with_capacity(capacity),
         }
     }
@@ -90,6 +148,8 @@ impl ViewportBuffer {
         self.cols.clear();
         self.values.clear();
         self.formats.clear();
+        self.border_styles.clear();
+        self.border_colors.clear();
         self.display_values.clear();
     }
 
@@ -97,6 +157,7 @@ impl ViewportBuffer {
         self.rows.len()
     }
 
+    #[cfg(test)]
     pub fn is_empty(&self) -> bool {
         self.rows.is_empty()
     }
@@ -108,12 +169,15 @@ impl ViewportBuffer {
         col: u32,
         numeric_value: f64,
         format_flags: u32,
+        borders: &Borders,
         display: String,
     ) {
         self.rows.push(row);
         self.cols.push(col);
         self.values.push(numeric_value);
         self.formats.push(format_flags);
+        self.border_styles.push(pack_border_styles(borders));
+        self.border_colors.extend(pack_border_colors(borders));
         self.display_values.push(display);
     }
 }
@@ -121,6 +185,7 @@ impl ViewportBuffer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rusheet_core::Color;
 
     #[test]
     fn test_pack_unpack_format() {
@@ -140,13 +205,20 @@ mod tests {
         let mut buf = ViewportBuffer::new();
         assert!(buf.is_empty());
 
-        buf.push(0, 0, 42.0, 0, "42".to_string());
-        buf.push(1, 1, f64::NAN, 1, "text".to_string());
+        let borders = Borders {
+            bottom: Some(BorderLine::new(BorderStyle::Double, Color::RED)),
+            ..Borders::default()
+        };
+        buf.push(0, 0, 42.0, 0, &Borders::default(), "42".to_string());
+        buf.push(1, 1, f64::NAN, 1, &borders, "text".to_string());
 
         assert_eq!(buf.len(), 2);
         assert_eq!(buf.rows, vec![0, 1]);
         assert_eq!(buf.cols, vec![0, 1]);
         assert_eq!(buf.values[0], 42.0);
         assert!(buf.values[1].is_nan());
+        assert_eq!(buf.border_styles, vec![0, 6 << 6]);
+        assert_eq!(buf.border_colors.len(), 2 * BORDER_EDGES);
+        assert_eq!(buf.border_colors[BORDER_EDGES + 2], 0xff0000ff);
     }
 }
@@ -2,7 +2,27 @@ import Papa from 'papaparse';
 import * as XLSX from 'xlsx';
 import { emitter } from './EventEmitter';
 import * as WasmBridge from './WasmBridge';
-import type { CellData, CellFormat } from '../types';
+import type {
+  BorderLine,
+  BorderTarget,
+  CellComment,
+  CellData,
+  CellFormat,
+  CellHyperlink,
+  CellStyle,
+  Comment,
+  FrozenPanes,
+  LinkDestination,
+  Outline,
+  PivotTable,
+  SheetCell,
+  Table,
+  TableStyle,
+  TextRun,
+  Theme,
+  TotalsFunction,
+  ViewportPane,
+} from '../types';
 import type {
   FormatChangeEvent,
   SheetAddEvent,
@@ -153,6 +173,22 @@ export class RusheetAPI {
     return affected;
   }
 
+  /**
+   * Set text with mixed formatting, e.g. a bold label followed by plain text
+   * @param runs - Text runs in order, each with its own formatting
+   */
+  setCellRichText(row: number, col: number, runs: TextRun[], source: 'user' | 'api' = 'api'): [number, number][] {
+    const oldData = WasmBridge.getCellData(row, col);
+    const oldValue = oldData?.value ?? null;
+    const affected = WasmBridge.setCellRichText(row, col, runs);
+
+    emitter.emit<CellChangeEvent>('change', {
+      row, col, oldValue, newValue: runs.map(run => run.text).join(''), source
+    });
+
+    return affected;
+  }
+
   getCellData(row: number, col: number): CellData | null {
     return WasmBridge.getCellData(row, col);
   }
@@ -182,6 +218,10 @@ export class RusheetAPI {
     return success;
   }
 
+  setRangeBorders(startRow: number, startCol: number, endRow: number, endCol: number, target: BorderTarget, line: BorderLine | null): [number, number][] {
+    return WasmBridge.setRangeBorders(startRow, startCol, endRow, endCol, target, line);
+  }
+
   clearRange(startRow: number, startCol: number, endRow: number, endCol: number): [number, number][] {
     return WasmBridge.clearRange(startRow, startCol, endRow, endCol);
   }
@@ -475,14 +515,53 @@ export class RusheetAPI {
 
     // Create workbook
     const ws = XLSX.utils.aoa_to_sheet(trimmedData);
+
+    // Hyperlinks, with internal ones in the `#Sheet2!A1` form
+    for (const { row, col, hyperlink } of WasmBridge.getHyperlinks()) {
+      const r = row - startRow;
+      const c = col - startCol;
+      if (r < 0 || c < 0 || r > lastNonEmptyRow || c > maxCol) continue;
+      const cell = ws[XLSX.utils.encode_cell({ r, c })] as XLSX.CellObject | undefined;
+      if (!cell) continue;
+      const target = 'url' in hyperlink ? hyperlink.url : `#${hyperlink.location}`;
+      cell.l = { Target: target, Tooltip: hyperlink.tooltip };
+    }
+
+    // Rich text keeps its runs as shared string XML in `r`, with an HTML
+    // rendering in `h`; runs are only written to the shared string table
+    for (let r = 0; r <= lastNonEmptyRow; r++) {
+      for (let c = 0; c <= maxCol; c++) {
+        const xml = WasmBridge.getCellRichTextXml(startRow + r, startCol + c);
+        const cell = ws[XLSX.utils.encode_cell({ r, c })] as XLSX.CellObject | undefined;
+        if (!xml || !cell) continue;
+        cell.r = xml;
+        cell.h = this.richTextHtml(xml);
+      }
+    }
     const wb = XLSX.utils.book_new();
     XLSX.utils.book_append_sheet(wb, ws, sheetName);
 
     // Write to buffer
-    const buffer = XLSX.write(wb, { type: 'array', bookType: 'xlsx' });
+    const buffer = XLSX.write(wb, { type: 'array', bookType: 'xlsx', bookSST: true });
     return buffer;
   }
 
+  /** Render shared string runs as HTML spans, the way SheetJS reads them */
+  private richTextHtml(xml: string): string {
+    return Array.from(xml.matchAll(/<r>([\s\S]*?)<\/r>/g), ([, run]) => {
+      // Text is already escaped for XML, which HTML reads the same way
+      const text = /<t[^>]*>([\s\S]*?)<\/t>/.exec(run)?.[1] ?? '';
+      const style: string[] = [];
+      if (/<b\/>/.test(run)) style.push('font-weight: bold;');
+      if (/<i\/>/.test(run)) style.push('font-style: italic;');
+      if (/<u\/>/.test(run)) style.push('text-decoration: underline;');
+      if (/<strike\/>/.test(run)) style.push('text-decoration: line-through;');
+      const color = /<color rgb="[0-9A-F]{2}([0-9A-F]{6})"/.exec(run)?.[1];
+      if (color) style.push(`color: #${color};`);
+      return style.length ? `<span style="${style.join(' ')}">${text}</span>` : text;
+    }).join('');
+  }
+
   /**
    * Import XLSX data from ArrayBuffer
    * @param buffer - ArrayBuffer containing XLSX file data
@@ -542,6 +621,25 @@ export class RusheetAPI {
       }
     }
 
+    // Rich text keeps its runs, which SheetJS exposes as raw XML in `r`;
+    // hyperlinks are in `l`
+    if (ws['!ref']) {
+      const range = XLSX.utils.decode_range(ws['!ref']);
+      for (const address of Object.keys(ws)) {
+        const cell = ws[address] as XLSX.CellObject | undefined;
+        if (address.startsWith('!') || !cell) continue;
+        const { r, c } = XLSX.utils.decode_cell(address);
+        const row = startRow + r - range.s.r;
+        const col = startCol + c - range.s.c;
+        if (cell.t === 's' && cell.r?.includes('<r>')) {
+          WasmBridge.setCellRichTextXml(row, col, cell.r);
+        }
+        if (cell.l?.Target) {
+          WasmBridge.setCellHyperlink(row, col, cell.l.Target, cell.l.Tooltip);
+        }
+      }
+    }
+
     emitter.emit('dataLoaded', { rows: data.length, cols: maxColCount, source: 'xlsx', sheetName: targetSheetName });
 
     return { rows: data.length, cols: maxColCount, sheetName: targetSheetName };
@@ -709,6 +807,43 @@ export class RusheetAPI {
     return affected;
   }
 
+  // Paste and autofill
+  /**
+   * Paste a copied or cut range with its top-left corner at (destRow, destCol).
+   * Copied formulas have their relative references adjusted; a cut moves the
+   * cells and updates references to them across the workbook.
+   */
+  pasteRange(
+    srcStartRow: number,
+    srcStartCol: number,
+    srcEndRow: number,
+    srcEndCol: number,
+    destRow: number,
+    destCol: number,
+    cut = false
+  ): [number, number][] {
+    return WasmBridge.pasteRange(srcStartRow, srcStartCol, srcEndRow, srcEndCol, destRow, destCol, cut);
+  }
+
+  /**
+   * Autofill the target range by repeating the source range across it
+   */
+  fillRange(
+    srcStartRow: number,
+    srcStartCol: number,
+    srcEndRow: number,
+    srcEndCol: number,
+    targetStartRow: number,
+    targetStartCol: number,
+    targetEndRow: number,
+    targetEndCol: number
+  ): [number, number][] {
+    return WasmBridge.fillRange(
+      srcStartRow, srcStartCol, srcEndRow, srcEndCol,
+      targetStartRow, targetStartCol, targetEndRow, targetEndCol
+    );
+  }
+
   // Cell Merging
   mergeCells(
     startRow: number,
@@ -813,6 +948,393 @@ export class RusheetAPI {
     return WasmBridge.getHiddenRows();
   }
 
+  // Freeze panes
+
+  /**
+   * Freeze the top rows and left columns; (0, 0) unfreezes
+   */
+  setFrozenPanes(rows: number, cols: number): void {
+    WasmBridge.setFrozenPanes(rows, cols);
+    emitter.emit('change', { type: 'layout', frozenRows: rows, frozenCols: cols });
+  }
+
+  getFrozenPanes(): FrozenPanes {
+    return WasmBridge.getFrozenPanes();
+  }
+
+  getViewportPanes(scrollX: number, scrollY: number, width: number, height: number): ViewportPane[] {
+    return WasmBridge.getViewportPanes(scrollX, scrollY, width, height);
+  }
+
+  getCellFromPixel(x: number, y: number, scrollX?: number, scrollY?: number): [number, number] {
+    return WasmBridge.getCellFromPixel(x, y, scrollX, scrollY);
+  }
+
+  // Hiding and outlines
+
+  /**
+   * Hide or unhide rows or columns (independent of filters)
+   */
+  setHidden(axis: WasmBridge.OutlineAxis, start: number, end: number, hidden: boolean): void {
+    WasmBridge.setHidden(axis, start, end, hidden);
+    emitter.emit('change', { type: 'layout', axis });
+  }
+
+  isColHidden(col: number): boolean {
+    return WasmBridge.isColHidden(col);
+  }
+
+  getHiddenCols(): number[] {
+    return WasmBridge.getHiddenCols();
+  }
+
+  /**
+   * Group rows or columns. Returns false if the group would partially
+   * overlap another group or nest more than 7 levels deep.
+   */
+  group(axis: WasmBridge.OutlineAxis, start: number, end: number): boolean {
+    const affected = WasmBridge.group(axis, start, end);
+    if (affected.length > 0) {
+      emitter.emit('change', { type: 'layout', axis });
+    }
+    return affected.length > 0;
+  }
+
+  ungroup(axis: WasmBridge.OutlineAxis, start: number, end: number): boolean {
+    const affected = WasmBridge.ungroup(axis, start, end);
+    if (affected.length > 0) {
+      emitter.emit('change', { type: 'layout', axis });
+    }
+    return affected.length > 0;
+  }
+
+  setGroupCollapsed(axis: WasmBridge.OutlineAxis, start: number, end: number, collapsed: boolean): void {
+    WasmBridge.setGroupCollapsed(axis, start, end, collapsed);
+    emitter.emit('change', { type: 'layout', axis });
+  }
+
+  /**
+   * Show outline levels up to `level`; deeper groups collapse
+   */
+  showOutlineLevel(axis: WasmBridge.OutlineAxis, level: number): void {
+    WasmBridge.showOutlineLevel(axis, level);
+    emitter.emit('change', { type: 'layout', axis });
+  }
+
+  setSummaryPosition(axis: WasmBridge.OutlineAxis, position: 'after' | 'before'): void {
+    WasmBridge.setSummaryPosition(axis, position);
+    emitter.emit('change', { type: 'layout', axis });
+  }
+
+  getOutline(axis: WasmBridge.OutlineAxis): Outline {
+    return WasmBridge.getOutline(axis);
+  }
+
+  // Comments
+
+  /**
+   * Start a comment thread on a cell. Returns false if the cell already has one.
+   */
+  addComment(row: number, col: number, author: string, body: string): boolean {
+    return this.commentEdited(row, col, WasmBridge.addComment(row, col, author, body));
+  }
+
+  replyToComment(row: number, col: number, author: string, body: string): boolean {
+    return this.commentEdited(row, col, WasmBridge.replyToComment(row, col, author, body));
+  }
+
+  editCommentBody(row: number, col: number, body: string): boolean {
+    return this.commentEdited(row, col, WasmBridge.editComment(row, col, body));
+  }
+
+  editCommentReply(row: number, col: number, replyId: string, body: string): boolean {
+    return this.commentEdited(row, col, WasmBridge.editCommentReply(row, col, replyId, body));
+  }
+
+  deleteCommentReply(row: number, col: number, replyId: string): boolean {
+    return this.commentEdited(row, col, WasmBridge.deleteCommentReply(row, col, replyId));
+  }
+
+  setCommentResolved(row: number, col: number, resolved: boolean): boolean {
+    return this.commentEdited(row, col, WasmBridge.setCommentResolved(row, col, resolved));
+  }
+
+  deleteComment(row: number, col: number): boolean {
+    return this.commentEdited(row, col, WasmBridge.deleteComment(row, col));
+  }
+
+  getComment(row: number, col: number): Comment | null {
+    return WasmBridge.getComment(row, col);
+  }
+
+  getComments(): CellComment[] {
+    return WasmBridge.getComments();
+  }
+
+  getCommentMentions(row: number, col: number): string[] {
+    return WasmBridge.getCommentMentions(row, col);
+  }
+
+  private commentEdited(row: number, col: number, affected: [number, number][]): boolean {
+    if (affected.length > 0) {
+      emitter.emit('change', { type: 'comment', row, col });
+    }
+    return affected.length > 0;
+  }
+
+  // Hyperlinks
+
+  /**
+   * Link a cell to a URL, or to a workbook location with a leading `#`
+   * (e.g. `#Sheet2!A1`). A blank address removes the link.
+   */
+  setCellHyperlink(row: number, col: number, address: string, tooltip?: string): void {
+    WasmBridge.setCellHyperlink(row, col, address, tooltip);
+    emitter.emit('change', { type: 'hyperlink', row, col });
+  }
+
+  removeCellHyperlink(row: number, col: number): void {
+    WasmBridge.removeCellHyperlink(row, col);
+    emitter.emit('change', { type: 'hyperlink', row, col });
+  }
+
+  getHyperlinks(): CellHyperlink[] {
+    return WasmBridge.getHyperlinks();
+  }
+
+  resolveHyperlink(row: number, col: number): LinkDestination | null {
+    return WasmBridge.resolveHyperlink(row, col);
+  }
+
+  /**
+   * Follow a cell's link: open URLs in a new tab, or switch sheet and
+   * select the target of an internal link
+   */
+  followHyperlink(row: number, col: number): LinkDestination | null {
+    const destination = WasmBridge.resolveHyperlink(row, col);
+    if (destination?.kind === 'url') {
+      if (typeof window !== 'undefined') {
+        window.open(destination.url, '_blank', 'noopener,noreferrer');
+      }
+    } else if (destination?.kind === 'location') {
+      if (destination.sheetIndex !== this.currentSheetIndex) {
+        this.setActiveSheet(destination.sheetIndex, 'user');
+      }
+      this.setSelection(destination.startRow, destination.startCol);
+    }
+    return destination;
+  }
+
+  // Formula auditing
+
+  /**
+   * Cells a formula reads directly. Entries whose `sheetIndex` isn't the
+   * current sheet are links to other sheets.
+   */
+  getPrecedents(row: number, col: number): SheetCell[] {
+    return WasmBridge.getPrecedents(row, col);
+  }
+
+  /**
+   * Formulas that read a cell directly. Entries whose `sheetIndex` isn't the
+   * current sheet are links from other sheets.
+   */
+  getDependents(row: number, col: number): SheetCell[] {
+    return WasmBridge.getDependents(row, col);
+  }
+
+  // Named styles and theme
+
+  getTheme(): Theme {
+    return WasmBridge.getTheme();
+  }
+
+  /**
+   * Change the theme; missing fields and colors keep their current value.
+   * Styles and cells that follow the theme are restyled.
+   */
+  setTheme(theme: Partial<Theme> & { colors?: Partial<Theme['colors']> }): number {
+    const restyled = WasmBridge.setTheme(theme);
+    emitter.emit('change', { type: 'style' });
+    return restyled;
+  }
+
+  getCellStyles(): CellStyle[] {
+    return WasmBridge.getCellStyles();
+  }
+
+  /**
+   * Add or redefine a named style. Cells using it are restyled and keep
+   * their own overrides.
+   */
+  setCellStyle(style: CellStyle): number {
+    const restyled = WasmBridge.setCellStyle(style);
+    emitter.emit('change', { type: 'style' });
+    return restyled;
+  }
+
+  /** Delete a named style; cells using it keep their formatting */
+  deleteCellStyle(name: string): boolean {
+    const deleted = WasmBridge.deleteCellStyle(name);
+    if (deleted) {
+      emitter.emit('change', { type: 'style' });
+    }
+    return deleted;
+  }
+
+  applyCellStyle(startRow: number, startCol: number, endRow: number, endCol: number, name: string, source: EventSource = 'api'): boolean {
+    const success = WasmBridge.applyCellStyle(startRow, startCol, endRow, endCol, name);
+    if (success) {
+      emitter.emit<FormatChangeEvent>('formatChange', {
+        type: 'range',
+        startRow, startCol, endRow, endCol,
+        format: { style: name }, source
+      });
+    }
+    return success;
+  }
+
+  // Tables
+
+  /**
+   * Turn a range into a table whose first row holds the column names.
+   * Returns the table's name, or null if the range can't hold a table.
+   */
+  createTable(startRow: number, startCol: number, endRow: number, endCol: number, name?: string, totalsRow: boolean = false): string | null {
+    try {
+      const created = WasmBridge.createTable(startRow, startCol, endRow, endCol, name, totalsRow);
+      emitter.emit('change', { type: 'table', name: created });
+      return created;
+    } catch (e: any) {
+      console.warn(`Create table failed: ${e.message || e}`);
+      return null;
+    }
+  }
+
+  getTables(): Table[] {
+    return WasmBridge.getTables();
+  }
+
+  /** The table containing a cell, if any */
+  getTableAt(row: number, col: number): Table | null {
+    return WasmBridge.getTables().find((t) =>
+      t.startRow <= row && row <= t.endRow && t.startCol <= col && col <= t.endCol
+    ) ?? null;
+  }
+
+  /** Convert a table back to a plain range, keeping its cells */
+  convertTableToRange(name: string): boolean {
+    return this.tableEdited(name, 'Convert table', () => WasmBridge.convertTableToRange(name));
+  }
+
+  resizeTable(name: string, startRow: number, startCol: number, endRow: number, endCol: number): boolean {
+    return this.tableEdited(name, 'Resize table', () =>
+      WasmBridge.resizeTable(name, startRow, startCol, endRow, endCol)
+    );
+  }
+
+  /** Rename a table, updating formulas that reference it */
+  renameTable(name: string, newName: string): boolean {
+    try {
+      const success = WasmBridge.renameTable(name, newName);
+      emitter.emit('change', { type: 'table', name: newName, oldName: name });
+      return success;
+    } catch (e: any) {
+      console.warn(`Rename table failed: ${e.message || e}`);
+      return false;
+    }
+  }
+
+  setTableTotalsRow(name: string, show: boolean): boolean {
+    return this.tableEdited(name, 'Set totals row', () => WasmBridge.setTableTotalsRow(name, show));
+  }
+
+  /** Set the totals row function of a table column, or clear it with null */
+  setTableTotal(name: string, column: number, fn: TotalsFunction | null): boolean {
+    return this.tableEdited(name, 'Set table total', () => WasmBridge.setTableTotal(name, column, fn));
+  }
+
+  /** Change a table's styling; missing fields keep their current value */
+  setTableStyle(name: string, style: Partial<TableStyle> & { autoExpand?: boolean }): boolean {
+    return this.tableEdited(name, 'Set table style', () => WasmBridge.setTableStyle(name, style));
+  }
+
+  sortTable(name: string, column: number, ascending: boolean = true): boolean {
+    return this.tableEdited(name, 'Sort table', () => WasmBridge.sortTable(name, column, ascending));
+  }
+
+  /** Filter a table column to the given values, or clear its filter with null */
+  filterTable(name: string, column: number, visibleValues: string[] | null): boolean {
+    return this.tableEdited(name, 'Filter table', () => WasmBridge.filterTable(name, column, visibleValues));
+  }
+
+  private tableEdited(name: string, action: string, edit: () => [number, number][]): boolean {
+    try {
+      const affected = edit();
+      emitter.emit('change', { type: 'table', name, affected });
+      return true;
+    } catch (e: any) {
+      console.warn(`${action} failed: ${e.message || e}`);
+      return false;
+    }
+  }
+
+  // Pivot Tables
+
+  /**
+   * Add a pivot table and write its output to the target cells.
+   * Returns its name, or null if the definition is invalid, the output
+   * would overlap its source, a table or another pivot table, or it would
+   * replace existing data and `replace` isn't set. Undoable.
+   */
+  createPivotTable(definition: PivotTable, replace = false): string | null {
+    try {
+      const name = WasmBridge.createPivotTable(definition, replace);
+      emitter.emit('change', { type: 'pivot', name });
+      return name;
+    } catch (e: any) {
+      console.warn(`Create pivot table failed: ${e.message || e}`);
+      return null;
+    }
+  }
+
+  getPivotTables(): PivotTable[] {
+    return WasmBridge.getPivotTables();
+  }
+
+  /** Replace a pivot table's definition and rewrite its output */
+  updatePivotTable(name: string, definition: PivotTable, replace = false): boolean {
+    return this.pivotEdited(name, 'Update pivot table', () =>
+      WasmBridge.updatePivotTable(name, definition, replace)
+    );
+  }
+
+  /** Recompute a pivot table from its source's current values */
+  refreshPivotTable(name: string, replace = false): boolean {
+    return this.pivotEdited(name, 'Refresh pivot table', () => WasmBridge.refreshPivotTable(name, replace));
+  }
+
+  /** Recompute every pivot table; none are refreshed if any one fails */
+  refreshAllPivotTables(replace = false): boolean {
+    return this.pivotEdited(null, 'Refresh pivot tables', () => WasmBridge.refreshAllPivotTables(replace));
+  }
+
+  /** Remove a pivot table and clear its output */
+  deletePivotTable(name: string): boolean {
+    return this.pivotEdited(name, 'Delete pivot table', () => WasmBridge.deletePivotTable(name));
+  }
+
+  private pivotEdited(name: string | null, action: string, edit: () => boolean): boolean {
+    try {
+      const success = edit();
+      emitter.emit('change', { type: 'pivot', name });
+      return success;
+    } catch (e: any) {
+      console.warn(`${action} failed: ${e.message || e}`);
+      return false;
+    }
+  }
+
   // Cleanup
   destroy(): void {
     emitter.removeAllListeners();
@@ -1,4 +1,24 @@
-import type { CellData, CellFormat } from '../types';
+import type {
+  BorderLine,
+  BorderTarget,
+  CellComment,
+  CellData,
+  CellFormat,
+  CellHyperlink,
+  CellStyle,
+  Comment,
+  FrozenPanes,
+  LinkDestination,
+  Outline,
+  PivotTable,
+  SheetCell,
+  Table,
+  TableStyle,
+  TextRun,
+  Theme,
+  TotalsFunction,
+  ViewportPane,
+} from '../types';
 
 // Dynamic import for WASM module
 let wasmModule: typeof import('../../pkg/rusheet_wasm') | null = null;
@@ -33,6 +53,20 @@ export function setCellValue(row: number, col: number, value: string): [number,
   return JSON.parse(json);
 }
 
+export function setCellRichText(row: number, col: number, runs: TextRun[]): [number, number][] {
+  const json = getEngine().setCellRichText(row, col, JSON.stringify(runs));
+  return JSON.parse(json);
+}
+
+export function setCellRichTextXml(row: number, col: number, xml: string): [number, number][] {
+  const json = getEngine().setCellRichTextXml(row, col, xml);
+  return JSON.parse(json);
+}
+
+export function getCellRichTextXml(row: number, col: number): string | undefined {
+  return getEngine().getCellRichTextXml(row, col);
+}
+
 export function getCellData(row: number, col: number): CellData | null {
   const data = getEngine().getCellData(row, col);
   return data as CellData | null;
@@ -68,6 +102,25 @@ export function setRangeFormat(
   );
 }
 
+export function setRangeBorders(
+  startRow: number,
+  startCol: number,
+  endRow: number,
+  endCol: number,
+  target: BorderTarget,
+  line: BorderLine | null
+): [number, number][] {
+  const json = getEngine().setRangeBorders(
+    startRow,
+    startCol,
+    endRow,
+    endCol,
+    target,
+    JSON.stringify(line)
+  );
+  return JSON.parse(json);
+}
+
 export function clearRange(
   startRow: number,
   startCol: number,
@@ -171,6 +224,37 @@ export function sortRange(
   return JSON.parse(json);
 }
 
+// Paste and autofill
+export function pasteRange(
+  srcStartRow: number,
+  srcStartCol: number,
+  srcEndRow: number,
+  srcEndCol: number,
+  destRow: number,
+  destCol: number,
+  cut: boolean
+): [number, number][] {
+  const json = getEngine().pasteRange(srcStartRow, srcStartCol, srcEndRow, srcEndCol, destRow, destCol, cut);
+  return JSON.parse(json);
+}
+
+export function fillRange(
+  srcStartRow: number,
+  srcStartCol: number,
+  srcEndRow: number,
+  srcEndCol: number,
+  targetStartRow: number,
+  targetStartCol: number,
+  targetEndRow: number,
+  targetEndCol: number
+): [number, number][] {
+  const json = getEngine().fillRange(
+    srcStartRow, srcStartCol, srcEndRow, srcEndCol,
+    targetStartRow, targetStartCol, targetEndRow, targetEndCol
+  );
+  return JSON.parse(json);
+}
+
 export function serialize(): string {
   return getEngine().serialize();
 }
@@ -272,6 +356,304 @@ export function getHiddenRows(): number[] {
   return JSON.parse(json);
 }
 
+// =============================================================================
+// Freeze Panes
+// =============================================================================
+
+export function setFrozenPanes(rows: number, cols: number): void {
+  getEngine().setFrozenPanes(rows, cols);
+}
+
+export function getFrozenPanes(): FrozenPanes {
+  return JSON.parse(getEngine().getFrozenPanes());
+}
+
+/**
+ * Split the viewport into frozen and scrolling panes (frozen panes first).
+ * Scroll offsets apply to the scrolling pane only.
+ */
+export function getViewportPanes(
+  scrollX: number,
+  scrollY: number,
+  width: number,
+  height: number
+): ViewportPane[] {
+  return JSON.parse(getEngine().getViewportPanes(scrollX, scrollY, width, height));
+}
+
+/**
+ * Find the cell under a point. With scroll offsets the point is in viewport
+ * space and frozen rows/columns are accounted for; without, it is in sheet space.
+ */
+export function getCellFromPixel(
+  x: number,
+  y: number,
+  scrollX?: number,
+  scrollY?: number
+): [number, number] {
+  const [row, col] = getEngine().getCellFromPixel(x, y, scrollX, scrollY);
+  return [row, col];
+}
+
+// =============================================================================
+// Hiding and Outlines
+// =============================================================================
+
+export type OutlineAxis = 'rows' | 'cols';
+
+export function setHidden(axis: OutlineAxis, start: number, end: number, hidden: boolean): [number, number][] {
+  const eng = getEngine();
+  const json = axis === 'rows' ? eng.setRowsHidden(start, end, hidden) : eng.setColsHidden(start, end, hidden);
+  return JSON.parse(json);
+}
+
+export function isColHidden(col: number): boolean {
+  return getEngine().isColHidden(col);
+}
+
+export function getHiddenCols(): number[] {
+  const json = getEngine().getHiddenCols();
+  return JSON.parse(json);
+}
+
+export function group(axis: OutlineAxis, start: number, end: number): [number, number][] {
+  const eng = getEngine();
+  const json = axis === 'rows' ? eng.groupRows(start, end) : eng.groupCols(start, end);
+  return JSON.parse(json);
+}
+
+export function ungroup(axis: OutlineAxis, start: number, end: number): [number, number][] {
+  const eng = getEngine();
+  const json = axis === 'rows' ? eng.ungroupRows(start, end) : eng.ungroupCols(start, end);
+  return JSON.parse(json);
+}
+
+export function setGroupCollapsed(
+  axis: OutlineAxis,
+  start: number,
+  end: number,
+  collapsed: boolean
+): [number, number][] {
+  const eng = getEngine();
+  const json =
+    axis === 'rows'
+      ? eng.setRowGroupCollapsed(start, end, collapsed)
+      : eng.setColGroupCollapsed(start, end, collapsed);
+  return JSON.parse(json);
+}
+
+export function showOutlineLevel(axis: OutlineAxis, level: number): [number, number][] {
+  const eng = getEngine();
+  const json = axis === 'rows' ? eng.showRowLevel(level) : eng.showColLevel(level);
+  return JSON.parse(json);
+}
+
+export function setSummaryPosition(axis: OutlineAxis, position: 'after' | 'before'): [number, number][] {
+  const eng = getEngine();
+  const json =
+    axis === 'rows' ? eng.setRowSummaryPosition(position) : eng.setColSummaryPosition(position);
+  return JSON.parse(json);
+}
+
+export function getOutline(axis: OutlineAxis): Outline {
+  const eng = getEngine();
+  const json = axis === 'rows' ? eng.getRowOutline() : eng.getColOutline();
+  return JSON.parse(json);
+}
+
+// =============================================================================
+// Comments
+// =============================================================================
+
+export function addComment(row: number, col: number, author: string, body: string): [number, number][] {
+  return JSON.parse(getEngine().addComment(row, col, author, body));
+}
+
+export function replyToComment(row: number, col: number, author: string, body: string): [number, number][] {
+  return JSON.parse(getEngine().replyToComment(row, col, author, body));
+}
+
+export function editComment(row: number, col: number, body: string): [number, number][] {
+  return JSON.parse(getEngine().editComment(row, col, body));
+}
+
+export function editCommentReply(row: number, col: number, replyId: string, body: string): [number, number][] {
+  return JSON.parse(getEngine().editCommentReply(row, col, replyId, body));
+}
+
+export function deleteCommentReply(row: number, col: number, replyId: string): [number, number][] {
+  return JSON.parse(getEngine().deleteCommentReply(row, col, replyId));
+}
+
+export function setCommentResolved(row: number, col: number, resolved: boolean): [number, number][] {
+  return JSON.parse(getEngine().setCommentResolved(row, col, resolved));
+}
+
+export function deleteComment(row: number, col: number): [number, number][] {
+  return JSON.parse(getEngine().deleteComment(row, col));
+}
+
+export function getComment(row: number, col: number): Comment | null {
+  return JSON.parse(getEngine().getComment(row, col));
+}
+
+export function getComments(): CellComment[] {
+  return JSON.parse(getEngine().getComments());
+}
+
+/** Users mentioned with `@name` anywhere in a cell's thread */
+export function getCommentMentions(row: number, col: number): string[] {
+  return JSON.parse(getEngine().getCommentMentions(row, col));
+}
+
+// =============================================================================
+// Hyperlinks
+// =============================================================================
+
+/**
+ * Link a cell to a URL, or to a workbook location with a leading `#`
+ * (e.g. `#Sheet2!A1`). A blank address removes the link.
+ */
+export function setCellHyperlink(row: number, col: number, address: string, tooltip?: string): [number, number][] {
+  return JSON.parse(getEngine().setCellHyperlink(row, col, address, tooltip));
+}
+
+export function removeCellHyperlink(row: number, col: number): [number, number][] {
+  return JSON.parse(getEngine().removeCellHyperlink(row, col));
+}
+
+/** Every link on the active sheet, including HYPERLINK() formulas */
+export function getHyperlinks(): CellHyperlink[] {
+  return JSON.parse(getEngine().getHyperlinks());
+}
+
+export function resolveHyperlink(row: number, col: number): LinkDestination | null {
+  return JSON.parse(getEngine().resolveHyperlink(row, col));
+}
+
+// =============================================================================
+// Formula Auditing
+// =============================================================================
+
+/** Cells a formula reads directly, including cells on other sheets */
+export function getPrecedents(row: number, col: number): SheetCell[] {
+  return JSON.parse(getEngine().getPrecedents(row, col));
+}
+
+/** Formulas that read a cell directly, including formulas on other sheets */
+export function getDependents(row: number, col: number): SheetCell[] {
+  return JSON.parse(getEngine().getDependents(row, col));
+}
+
+// =============================================================================
+// Named Styles and Theme
+// =============================================================================
+
+export function getTheme(): Theme {
+  return JSON.parse(getEngine().getTheme());
+}
+
+/** Change theme fields; returns the number of restyled cells */
+export function setTheme(theme: Partial<Theme> & { colors?: Partial<Theme['colors']> }): number {
+  return getEngine().setTheme(JSON.stringify(theme));
+}
+
+export function getCellStyles(): CellStyle[] {
+  return JSON.parse(getEngine().getCellStyles());
+}
+
+/** Add or redefine a style; returns the number of restyled cells */
+export function setCellStyle(style: CellStyle): number {
+  return getEngine().setCellStyle(JSON.stringify(style));
+}
+
+export function deleteCellStyle(name: string): boolean {
+  return getEngine().deleteCellStyle(name);
+}
+
+export function applyCellStyle(startRow: number, startCol: number, endRow: number, endCol: number, name: string): boolean {
+  return getEngine().applyCellStyle(startRow, startCol, endRow, endCol, name);
+}
+
+// =============================================================================
+// Tables
+// =============================================================================
+
+/** Turn a range into a table; returns its name (picked when blank) */
+export function createTable(startRow: number, startCol: number, endRow: number, endCol: number, name?: string, totalsRow: boolean = false): string {
+  return getEngine().createTable(startRow, startCol, endRow, endCol, name, totalsRow);
+}
+
+export function getTables(): Table[] {
+  return JSON.parse(getEngine().getTables());
+}
+
+export function convertTableToRange(name: string): [number, number][] {
+  return JSON.parse(getEngine().convertTableToRange(name));
+}
+
+export function resizeTable(name: string, startRow: number, startCol: number, endRow: number, endCol: number): [number, number][] {
+  return JSON.parse(getEngine().resizeTable(name, startRow, startCol, endRow, endCol));
+}
+
+export function renameTable(name: string, newName: string): boolean {
+  return getEngine().renameTable(name, newName);
+}
+
+export function setTableTotalsRow(name: string, show: boolean): [number, number][] {
+  return JSON.parse(getEngine().setTableTotalsRow(name, show));
+}
+
+export function setTableTotal(name: string, column: number, fn: TotalsFunction | null): [number, number][] {
+  return JSON.parse(getEngine().setTableTotal(name, column, fn ?? undefined));
+}
+
+export function setTableStyle(name: string, style: Partial<TableStyle> & { autoExpand?: boolean }): [number, number][] {
+  return JSON.parse(getEngine().setTableStyle(name, JSON.stringify(style)));
+}
+
+export function sortTable(name: string, column: number, ascending: boolean): [number, number][] {
+  return JSON.parse(getEngine().sortTable(name, column, ascending));
+}
+
+/** Filter a table column to the given values, or clear its filter with null */
+export function filterTable(name: string, column: number, visibleValues: string[] | null): [number, number][] {
+  const json = visibleValues ? JSON.stringify(visibleValues) : undefined;
+  return JSON.parse(getEngine().filterTable(name, column, json));
+}
+
+// =============================================================================
+// Pivot Tables
+// =============================================================================
+
+/**
+ * Add a pivot table and write its output; returns its name (picked when blank).
+ * Throws if the output would replace existing data, unless `replace` is set.
+ */
+export function createPivotTable(definition: PivotTable, replace = false): string {
+  return getEngine().createPivotTable(JSON.stringify(definition), replace);
+}
+
+export function getPivotTables(): PivotTable[] {
+  return JSON.parse(getEngine().getPivotTables());
+}
+
+export function updatePivotTable(name: string, definition: PivotTable, replace = false): boolean {
+  return getEngine().updatePivotTable(name, JSON.stringify(definition), replace);
+}
+
+export function refreshPivotTable(name: string, replace = false): boolean {
+  return getEngine().refreshPivotTable(name, replace);
+}
+
+export function refreshAllPivotTables(replace = false): boolean {
+  return getEngine().refreshAllPivotTables(replace);
+}
+
+export function deletePivotTable(name: string): boolean {
+  return getEngine().deletePivotTable(name);
+}
+
 // =============================================================================
 // Zero-Copy Viewport API
 // =============================================================================
@@ -289,6 +671,10 @@ export interface ViewportArrays {
   values: Float64Array;
   /** Packed format flags */
   formats: Uint32Array;
+  /** Packed border styles (see unpackBorders) */
+  borderStyles: Uint32Array;
+  /** Border colors as 0xRRGGBBAA, 6 per cell */
+  borderColors: Uint32Array;
   /** Display strings (still JSON) */
   displayValues: string[];
   /** Number of cells */
@@ -324,6 +710,8 @@ export function getViewportArrays(
       cols: new Uint32Array(0),
       values: new Float64Array(0),
       formats: new Uint32Array(0),
+      borderStyles: new Uint32Array(0),
+      borderColors: new Uint32Array(0),
       displayValues: [],
       length: 0,
     };
@@ -334,12 +722,16 @@ export function getViewportArrays(
   const colsPtr = eng.getViewportColsPtr();
   const valuesPtr = eng.getViewportValuesPtr();
   const formatsPtr = eng.getViewportFormatsPtr();
+  const borderStylesPtr = eng.getViewportBorderStylesPtr();
+  const borderColorsPtr = eng.getViewportBorderColorsPtr();
 
   // Create views into WASM memory (zero-copy!)
   const rows = new Uint32Array(wasmMemory.buffer, rowsPtr, len);
   const cols = new Uint32Array(wasmMemory.buffer, colsPtr, len);
   const values = new Float64Array(wasmMemory.buffer, valuesPtr, len);
   const formats = new Uint32Array(wasmMemory.buffer, formatsPtr, len);
+  const borderStyles = new Uint32Array(wasmMemory.buffer, borderStylesPtr, len);
+  const borderColors = new Uint32Array(wasmMemory.buffer, borderColorsPtr, len * 6);
 
   // Display values still need JSON parsing
   const displayValues: string[] = JSON.parse(eng.getViewportDisplayValues());
@@ -349,6 +741,8 @@ export function getViewportArrays(
     cols,
     values,
     formats,
+    borderStyles,
+    borderColors,
     displayValues,
     length: len,
   };
@@ -387,3 +781,37 @@ export function unpackFormatFlags(flags: number): {
     verticalAlign: verticalAlignMap[vAlign] || 'middle',
   };
 }
+
+/** Resolved borders of one viewport cell */
+export interface CellBorders {
+  top?: BorderLine;
+  right?: BorderLine;
+  bottom?: BorderLine;
+  left?: BorderLine;
+  diagonalDown?: BorderLine;
+  diagonalUp?: BorderLine;
+}
+
+/**
+ * Unpack the borders of the cell at `index` in a viewport.
+ *
+ * @param viewport - Arrays from getViewportArrays
+ * @param index - Cell index within the viewport
+ * @returns Border lines present on the cell
+ */
+export function unpackBorders(viewport: ViewportArrays, index: number): CellBorders {
+  const edges = ['top', 'right', 'bottom', 'left', 'diagonalDown', 'diagonalUp'] as const;
+  const styleMap = [null, 'thin', 'medium', 'thick', 'dashed', 'dotted', 'double'] as const;
+  const flags = viewport.borderStyles[index];
+  const borders: CellBorders = {};
+
+  edges.forEach((edge, i) => {
+    const style = styleMap[(flags >> (i * 3)) & 0x7];
+    if (!style) return;
+    const rgba = viewport.borderColors[index * 6 + i];
+    const color = '#' + (rgba >>> 8).toString(16).padStart(6, '0');
+    borders[edge] = { style, color };
+  });
+
+  return borders;
+}
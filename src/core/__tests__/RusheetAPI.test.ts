@@ -30,7 +30,7 @@ vi.mock('../WasmBridge', () => ({
   serialize: vi.fn().mockReturnValue('{}'),
   deserialize: vi.fn().mockReturnValue(true),
   getViewportData: vi.fn().mockReturnValue([]),
-  getViewportArrays: vi.fn().mockReturnValue({ rows: new Uint32Array(0), cols: new Uint32Array(0), values: new Float64Array(0), formats: new Uint32Array(0), displayValues: [], length: 0 }),
+  getViewportArrays: vi.fn().mockReturnValue({ rows: new Uint32Array(0), cols: new Uint32Array(0), values: new Float64Array(0), formats: new Uint32Array(0), borderStyles: new Uint32Array(0), borderColors: new Uint32Array(0), displayValues: [], length: 0 }),
   // Mock filter functions
   getUniqueValuesInColumn: vi.fn().mockReturnValue([]),
   applyColumnFilter: vi.fn().mockReturnValue([]),
@@ -4,6 +4,8 @@ import * as XLSX from 'xlsx';
 // Mock WasmBridge for unit tests
 vi.mock('../WasmBridge', () => {
   const mockCells: Map<string, { value: string; displayValue: string }> = new Map();
+  const mockLinks: Map<string, { address: string; tooltip?: string }> = new Map();
+  const mockRichText: Map<string, string> = new Map();
 
   return {
     initWasm: vi.fn().mockResolvedValue(undefined),
@@ -33,10 +35,30 @@ vi.mock('../WasmBridge', () => {
       }
       return [];
     }),
+    setCellHyperlink: vi.fn((row: number, col: number, address: string, tooltip?: string) => {
+      mockLinks.set(`${row},${col}`, { address, tooltip });
+      return [[row, col]];
+    }),
+    getHyperlinks: vi.fn(() =>
+      Array.from(mockLinks, ([key, { address, tooltip }]) => {
+        const [row, col] = key.split(',').map(Number);
+        const hyperlink = address.startsWith('#') ? { location: address.slice(1) } : { url: address };
+        return { row, col, hyperlink: { ...hyperlink, tooltip } };
+      })
+    ),
+    setCellRichTextXml: vi.fn((row: number, col: number, xml: string) => {
+      mockRichText.set(`${row},${col}`, xml);
+      return [[row, col]];
+    }),
+    getCellRichTextXml: vi.fn((row: number, col: number) => mockRichText.get(`${row},${col}`)),
     serialize: vi.fn(() => '{}'),
     deserialize: vi.fn(() => true),
     // Clear mock cells for test isolation
-    __clearMockCells: () => mockCells.clear(),
+    __clearMockCells: () => {
+      mockCells.clear();
+      mockLinks.clear();
+      mockRichText.clear();
+    },
     __getMockCells: () => mockCells,
   };
 });
@@ -206,6 +228,42 @@ describe('XLSX Import/Export', () => {
       expect(api.getCellData(1, 0)?.value).toBe('Item1');
       expect(api.getCellData(1, 1)?.value).toBe('100');
     });
+
+    it('keeps hyperlinks', () => {
+      api.setCellValue(0, 0, 'Docs');
+      api.setCellValue(1, 0, 'Totals');
+      api.setCellHyperlink(0, 0, 'https://example.com', 'Open docs');
+      api.setCellHyperlink(1, 0, "#'Q1 Data'!B3");
+
+      const buffer = api.exportXLSX();
+      const ws = XLSX.read(buffer, { type: 'array' }).Sheets.Sheet1;
+      expect(ws.A1.l?.Target).toBe('https://example.com');
+      expect(ws.A2.l?.Target).toBe("#'Q1 Data'!B3");
+
+      api.importXLSX(buffer);
+      expect(api.getHyperlinks()).toEqual([
+        { row: 0, col: 0, hyperlink: { url: 'https://example.com', tooltip: 'Open docs' } },
+        { row: 1, col: 0, hyperlink: { location: "'Q1 Data'!B3", tooltip: undefined } },
+      ]);
+    });
+
+    it('keeps rich text runs', async () => {
+      const WasmBridge = await import('../WasmBridge');
+      const runs = '<r><rPr><b/></rPr><t>Bold</t></r><r><t xml:space="preserve"> plain</t></r>';
+      api.setCellValue(0, 0, 'Bold plain');
+      WasmBridge.setCellRichTextXml(0, 0, runs);
+
+      const buffer = api.exportXLSX();
+      const ws = XLSX.read(buffer, { type: 'array' }).Sheets.Sheet1;
+      expect(ws.A1.v).toBe('Bold plain');
+      expect(ws.A1.r).toContain('<b/>');
+      expect(ws.A1.h).toContain('Bold');
+
+      (WasmBridge as unknown as { __clearMockCells: () => void }).__clearMockCells();
+      api.importXLSX(buffer);
+      expect(api.getCellData(0, 0)?.value).toBe('Bold plain');
+      expect(WasmBridge.getCellRichTextXml(0, 0)).toContain('<b/>');
+    });
   });
 });
 
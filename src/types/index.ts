@@ -8,10 +8,26 @@ export interface CellData {
   displayValue: string;
   formula?: string;
   format: CellFormat;
+  richText?: TextRun[];
+  comment?: Comment;
+  /** Link the cell opens, from its hyperlink or a HYPERLINK() formula */
+  hyperlink?: Hyperlink;
   row: number;
   col: number;
 }
 
+/** A stretch of text with its own formatting inside a rich text cell */
+export interface TextRun {
+  text: string;
+  bold?: boolean;
+  italic?: boolean;
+  underline?: boolean;
+  strikethrough?: boolean;
+  fontSize?: number;
+  fontFamily?: string;
+  textColor?: string;
+}
+
 export interface CellFormat {
   bold?: boolean;
   italic?: boolean;
@@ -21,6 +37,246 @@ export interface CellFormat {
   backgroundColor?: string;
   horizontalAlign?: 'left' | 'center' | 'right';
   verticalAlign?: 'top' | 'middle' | 'bottom';
+  fontFamily?: string;
+  numberFormat?: string;
+  /** Named style the format is based on */
+  style?: string;
+  /** Theme slot the text color follows */
+  textThemeColor?: ThemeColorSlot;
+  /** Theme slot the background color follows */
+  backgroundThemeColor?: ThemeColorSlot;
+  /** Theme font the font family follows */
+  themeFont?: 'major' | 'minor';
+}
+
+export type ThemeColorSlot =
+  | 'dark1'
+  | 'light1'
+  | 'dark2'
+  | 'light2'
+  | 'accent1'
+  | 'accent2'
+  | 'accent3'
+  | 'accent4'
+  | 'accent5'
+  | 'accent6'
+  | 'hyperlink'
+  | 'followedHyperlink';
+
+/** Workbook theme: a color palette plus heading (major) and body (minor) fonts */
+export interface Theme {
+  name: string;
+  colors: Record<ThemeColorSlot, string>;
+  majorFont: string;
+  minorFont: string;
+}
+
+/** A named, workbook-level cell style such as "Heading 1" or "Currency" */
+export interface CellStyle {
+  name: string;
+  format: CellFormat;
+}
+
+export type BorderStyle = 'thin' | 'medium' | 'thick' | 'dashed' | 'dotted' | 'double';
+
+export interface BorderLine {
+  style: BorderStyle;
+  color: string;
+}
+
+export type BorderTarget =
+  | 'all'
+  | 'outline'
+  | 'inside'
+  | 'insideHorizontal'
+  | 'insideVertical'
+  | 'top'
+  | 'right'
+  | 'bottom'
+  | 'left'
+  | 'diagonalDown'
+  | 'diagonalUp';
+
+export interface OutlineGroup {
+  start: number;
+  end: number;
+  level: number;
+  collapsed: boolean;
+  /** Row or column that carries the expand/collapse toggle */
+  summaryIndex: number | null;
+}
+
+export interface Outline {
+  summary: 'after' | 'before';
+  maxLevel: number;
+  groups: OutlineGroup[];
+}
+
+export interface CommentReply {
+  id: string;
+  author: string;
+  /** Unix time in milliseconds */
+  timestamp: number;
+  body: string;
+  /** When the body was last edited (Unix milliseconds) */
+  edited?: number;
+}
+
+/** A comment thread attached to a cell */
+export interface Comment extends CommentReply {
+  replies?: CommentReply[];
+  resolved?: boolean;
+}
+
+export interface CellComment {
+  row: number;
+  col: number;
+  comment: Comment;
+}
+
+/**
+ * A cell hyperlink: an external URL, or a location in the workbook
+ * (a reference such as `Sheet2!A1` or a defined name)
+ */
+export type Hyperlink = ({ url: string } | { location: string }) & { tooltip?: string };
+
+export interface CellHyperlink {
+  row: number;
+  col: number;
+  hyperlink: Hyperlink;
+}
+
+/** Where following a link goes */
+export type LinkDestination =
+  | { kind: 'url'; url: string }
+  | {
+      kind: 'location';
+      sheetIndex: number;
+      startRow: number;
+      startCol: number;
+      endRow: number;
+      endCol: number;
+    };
+
+/** A cell and the sheet it's on; `sheetIndex` can differ from the active sheet */
+export interface SheetCell {
+  sheetIndex: number;
+  row: number;
+  col: number;
+}
+
+/** Totals row function of a table column */
+export type TotalsFunction =
+  | 'sum'
+  | 'average'
+  | 'count'
+  | 'countnums'
+  | 'max'
+  | 'min'
+  | 'stddev'
+  | 'var';
+
+export interface TableStyle {
+  headerColor: string;
+  headerTextColor: string;
+  bandColor: string;
+  bandedRows: boolean;
+  bandedColumns: boolean;
+  /** Bold the first column */
+  firstColumn: boolean;
+  /** Bold the last column */
+  lastColumn: boolean;
+}
+
+/**
+ * A structured table. Column positions in `sort` and `filters` count from
+ * the table's first column.
+ */
+export interface Table {
+  name: string;
+  startRow: number;
+  startCol: number;
+  endRow: number;
+  endCol: number;
+  /** Header cell texts, used by structured references like `Table1[Amount]` */
+  columns: string[];
+  totalsRow: boolean;
+  /** Grow when typing directly below or to the right */
+  autoExpand: boolean;
+  style: TableStyle;
+  sort: { column: number; ascending: boolean } | null;
+  filters: { column: number; visibleValues: string[] }[];
+}
+
+export type PivotAggregation = 'sum' | 'count' | 'average' | 'min' | 'max' | 'distinct_count';
+
+/** How a pivot field's values become row or column items */
+export type PivotGrouping =
+  | { type: 'none' }
+  | { type: 'date'; bucket: 'year' | 'quarter' | 'month' | 'day' }
+  | { type: 'bins'; start: number; size: number };
+
+export interface PivotField {
+  /** Column offset from the source range's first column */
+  column: number;
+  grouping?: PivotGrouping;
+}
+
+export interface PivotValueField {
+  column: number;
+  aggregation: PivotAggregation;
+  /** Header label; defaults to e.g. "Sum of Amount" */
+  name?: string;
+}
+
+/**
+ * A pivot table definition. Blank sheet names mean the active sheet; field
+ * columns count from the source range's first column.
+ */
+export interface PivotTable {
+  name?: string;
+  sourceSheet?: string;
+  /** Source records, with field names in the first row */
+  sourceStartRow: number;
+  sourceStartCol: number;
+  sourceEndRow: number;
+  sourceEndCol: number;
+  targetSheet?: string;
+  /** Top-left cell of the output */
+  targetRow: number;
+  targetCol: number;
+  rows?: PivotField[];
+  columns?: PivotField[];
+  values: PivotValueField[];
+  /** Keep only records whose item label is listed, e.g. "(blank)" */
+  filters?: { field: PivotField; items: string[] }[];
+  grandTotals?: boolean;
+  /** Last cell written by the last refresh (read-only) */
+  outputEndRow?: number | null;
+  outputEndCol?: number | null;
+}
+
+export interface FrozenPanes {
+  rows: number;
+  cols: number;
+  /** Pixel width of the frozen columns */
+  width: number;
+  /** Pixel height of the frozen rows */
+  height: number;
+}
+
+export interface ViewportPane {
+  kind: 'corner' | 'top' | 'left' | 'main';
+  startRow: number;
+  endRow: number;
+  startCol: number;
+  endCol: number;
+  /** Screen position of the pane */
+  x: number;
+  y: number;
+  /** Added to sheet positions to draw them in the pane */
+  offsetX: number;
+  offsetY: number;
 }
 
 export interface Selection {